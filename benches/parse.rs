@@ -0,0 +1,120 @@
+//! Benchmarks for parsing a large document into a tree.
+//!
+//! A generated JSON array of 200k objects with four members each makes about
+//! 1M nodes. The parse is measured as a whole, and the tree is built from the
+//! same nodes once growing the node vector and once with
+//! [`Tree::with_capacity`], to show what reserving up front saves.
+//!
+//! A generated LDIF file of 100k entries is parsed into memory and, as for
+//! files above the streaming threshold, indexed from disk.
+//!
+//! The same 200k objects, one per line, make a JSON Lines file. JSON Lines and
+//! LDIF files above 8 MB parse on all cores; `RAYON_NUM_THREADS=1 cargo bench`
+//! measures them on one thread for comparison.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::fmt::Write;
+use xtv::parser::jsonlines::JsonLinesParser;
+use xtv::parser::ldif::decode::ValueDecoders;
+use xtv::parser::ldif::{LdifParser, build_ldif_index_quiet};
+use xtv::parser::{Parser, json::JsonParser};
+use xtv::tree::{Tree, TreeNode};
+
+const OBJECT_COUNT: usize = 200_000;
+
+const ENTRY_COUNT: usize = 100_000;
+
+/// About five nodes per object: the object and its four members
+const NODE_COUNT: usize = 1 + 5 * OBJECT_COUNT;
+
+fn large_json() -> String {
+    format!("[{}]", objects().join(","))
+}
+
+fn large_jsonl() -> String {
+    objects().join("\n")
+}
+
+fn objects() -> Vec<String> {
+    (0..OBJECT_COUNT)
+        .map(|i| {
+            format!(
+                r#"{{"id": {i}, "name": "user{i}", "active": true, "score": {}.5}}"#,
+                i % 100
+            )
+        })
+        .collect()
+}
+
+fn large_ldif() -> String {
+    let mut ldif = String::from("version: 1\n\ndn: dc=example,dc=com\ndc: example\n\n");
+    for i in 0..ENTRY_COUNT {
+        write!(
+            ldif,
+            "dn: uid=user{i},dc=example,dc=com\nobjectClass: person\nuid: user{i}\ncn: User {i}\nmail: user{i}@example.com\n\n"
+        )
+        .unwrap();
+    }
+    ldif
+}
+
+fn build(mut tree: Tree) -> Tree {
+    for i in 0..OBJECT_COUNT {
+        let object = tree.add_child_node(0, TreeNode::new(format!("[{i}]"), "object"));
+        for key in ["id", "name", "active", "score"] {
+            tree.add_child_node(object, TreeNode::new(key, "attribute"));
+        }
+    }
+    tree
+}
+
+fn parse_large_document(c: &mut Criterion) {
+    let json = large_json();
+    let mut group = c.benchmark_group("parse_1m_nodes");
+    group.sample_size(10);
+
+    group.bench_function("json", |b| {
+        b.iter(|| std::hint::black_box(JsonParser.parse(&json).unwrap()))
+    });
+    let jsonl = large_jsonl();
+    group.bench_function("jsonl", |b| {
+        b.iter(|| std::hint::black_box(JsonLinesParser::default().parse(&jsonl).unwrap()))
+    });
+    group.bench_function("build_growing", |b| {
+        b.iter(|| build(Tree::new(TreeNode::new("root", "root"))))
+    });
+    group.bench_function("build_with_capacity", |b| {
+        b.iter(|| {
+            build(Tree::with_capacity(
+                TreeNode::new("root", "root"),
+                NODE_COUNT,
+            ))
+        })
+    });
+
+    group.finish();
+}
+
+fn parse_ldif(c: &mut Criterion) {
+    let ldif = large_ldif();
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(&mut file, ldif.as_bytes()).unwrap();
+    let mut group = c.benchmark_group("ldif_100k_entries");
+    group.sample_size(10);
+
+    group.bench_function("parse", |b| {
+        b.iter(|| std::hint::black_box(LdifParser::default().parse(&ldif).unwrap()))
+    });
+    group.bench_function("index", |b| {
+        b.iter(|| {
+            std::hint::black_box(
+                build_ldif_index_quiet(file.path(), ValueDecoders::default()).unwrap(),
+            )
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse_large_document, parse_ldif);
+criterion_main!(benches);
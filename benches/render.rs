@@ -0,0 +1,42 @@
+//! Benchmarks for drawing and navigating a large tree view.
+//!
+//! A fully expanded 1M-node tree is drawn into an 80x40 test terminal. Each
+//! frame should only touch the rows inside the viewport.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use ratatui::{Terminal, backend::TestBackend};
+use xtv::tree::{Tree, TreeNode, TreeVariant};
+use xtv::ui::theme::Theme;
+use xtv::ui::tree_view::TreeView;
+
+const NODE_COUNT: usize = 1_000_000;
+
+fn large_tree() -> TreeVariant {
+    let mut tree = Tree::new(TreeNode::new("root", "array"));
+    for i in 0..NODE_COUNT {
+        tree.add_child_node(0, TreeNode::new(format!("[{i}]"), "number"));
+    }
+    TreeVariant::InMemory(tree)
+}
+
+fn navigate_large_tree(c: &mut Criterion) {
+    let tree = large_tree();
+    let mut view = TreeView::new(tree.root_id());
+    let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+    let theme = Theme::dark();
+
+    c.bench_function("navigate_and_draw_1m_nodes", |b| {
+        b.iter(|| {
+            view.navigate_down(&tree);
+            terminal
+                .draw(|f| view.render(f, f.size(), &tree, &theme, &[], None))
+                .unwrap();
+        });
+    });
+
+    // Navigation must reuse the visible node list built for the first frame
+    assert_eq!(view.rebuild_count(), 1);
+}
+
+criterion_group!(benches, navigate_large_tree);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+//! Benchmarks for streaming-mode node access.
+//!
+//! Walks 10k consecutive LDIF entries through a `StreamingTree`, once with the
+//! default prefetch window and once with prefetching disabled.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::io::Write;
+use xtv::parser::ldif::build_ldif_index;
+use xtv::tree::streaming::DEFAULT_PREFETCH_WINDOW;
+
+const ENTRY_COUNT: usize = 10_000;
+
+fn write_ldif() -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(file, "version: 1\n\ndn: dc=example,dc=com\ndc: example\n").unwrap();
+    for i in 0..ENTRY_COUNT {
+        writeln!(
+            file,
+            "dn: uid=user{i},dc=example,dc=com\nuid: user{i}\ncn: User {i}\nmail: user{i}@example.com\n"
+        )
+        .unwrap();
+    }
+    file
+}
+
+fn walk_entries(c: &mut Criterion) {
+    let file = write_ldif();
+    let mut group = c.benchmark_group("walk_10k_entries");
+    group.sample_size(10);
+
+    for (name, window) in [("prefetch", DEFAULT_PREFETCH_WINDOW), ("no_prefetch", 0)] {
+        group.bench_function(name, |b| {
+            b.iter_with_setup(
+                || {
+                    build_ldif_index(file.path())
+                        .unwrap()
+                        .with_prefetch_window(window)
+                },
+                |tree| {
+                    let base = tree.get_children(tree.root_id())[0];
+                    for id in tree.get_children(base) {
+                        std::hint::black_box(tree.get_node(id));
+                    }
+                },
+            );
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, walk_entries);
+criterion_main!(benches);
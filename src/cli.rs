@@ -1,4 +1,5 @@
-use clap::Parser;
+use crate::config::{ColorMode, Overrides};
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -6,23 +7,147 @@ use std::path::PathBuf;
 #[clap(version)]
 #[clap(about = "X Tree Viewer - View tree structures from serialized data files", long_about = None)]
 pub struct Cli {
-    /// Path to the file to view (reads from stdin if not provided)
+    /// Paths to the files to view, each in its own tab (reads from stdin if not provided)
     #[clap(value_name = "FILE")]
-    pub file: Option<PathBuf>,
+    pub files: Vec<PathBuf>,
 
-    /// Format to use when reading from stdin (xml, json, jsonl, html, ldif, toml, yaml)
+    /// Format to use when reading from stdin (xml, json, jsonl, html, ldif, toml, yaml; overrides XTV_FORMAT)
     #[clap(short, long, value_name = "FORMAT")]
     pub format: Option<String>,
 
-    /// Path to custom configuration file
+    /// Path to custom configuration file (overrides XTV_CONFIG)
     #[clap(short, long, value_name = "PATH")]
     pub config: Option<PathBuf>,
 
-    /// Threshold in bytes for switching to streaming mode (overrides config)
+    /// Threshold in bytes for switching to streaming mode (overrides config and XTV_STREAMING_THRESHOLD)
     #[clap(long, value_name = "BYTES")]
     pub streaming_threshold: Option<u64>,
 
-    /// Disable streaming mode (always load entire file into memory)
+    /// Disable streaming mode (always load entire file into memory; also set by XTV_NO_STREAMING)
     #[clap(long)]
     pub no_streaming: bool,
+
+    /// Expand the tree to this depth at startup, -1 for fully expanded (overrides config)
+    #[clap(long, value_name = "DEPTH", allow_hyphen_values = true, value_parser = clap::value_parser!(i32).range(-1..))]
+    pub expand_depth: Option<i32>,
+
+    /// Don't capture the mouse, keeping the terminal's own text selection (overrides config)
+    #[clap(long)]
+    pub no_mouse: bool,
+
+    /// Append a debug log of streaming, parsing and key handling to PATH (overrides config)
+    #[clap(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Read the file again automatically when it changes on disk
+    #[clap(long)]
+    pub watch: bool,
+
+    /// Start with the file's default view instead of the expanded nodes, selection and search saved when it was last closed
+    #[clap(long)]
+    pub no_restore: bool,
+
+    /// Print the tree as text instead of starting the viewer
+    #[clap(long)]
+    pub print: bool,
+
+    /// Levels of the tree to print expanded (prints all levels if not provided)
+    #[clap(long, value_name = "DEPTH", requires = "print")]
+    pub depth: Option<usize>,
+
+    /// Start at the node at a jq path such as .users[0], a JSON Pointer such as /users/0, an XPath such as /catalog/item for XML and HTML, or an LDIF DN (with --print, print only its subtree)
+    #[clap(long, value_name = "PATH")]
+    pub select: Option<String>,
+
+    /// Print statistics of the whole tree, such as node counts by type and memory usage, instead of the tree itself
+    #[clap(long, requires = "print")]
+    pub stats: bool,
+
+    /// When to use colors: auto leaves them out if NO_COLOR is set, and with --print if the output isn't a terminal (overrides config)
+    #[clap(long, value_name = "WHEN", possible_values = &["auto", "always", "never"])]
+    pub color: Option<String>,
+
+    /// Print the tree without icons, with ├── and └── guides showing its structure
+    #[clap(long, requires = "print")]
+    pub ascii_tree: bool,
+
+    /// Print long values whole instead of cutting them like the viewer does
+    #[clap(long, requires = "print")]
+    pub full_values: bool,
+
+    /// Convert the tree (or the subtree at --select) to a document and write it to PATH instead of starting the viewer ("-" writes to stdout)
+    #[clap(long, value_name = "PATH", conflicts_with = "print")]
+    pub output: Option<PathBuf>,
+
+    /// Format of the --output document (json, yaml); defaults to the one of its extension, or JSON
+    #[clap(long, value_name = "FORMAT", possible_values = &["json", "yaml"], requires = "output")]
+    pub output_format: Option<String>,
+
+    /// Write the tree to PATH as a standalone HTML page of collapsible nodes, expanded as the viewer would start (see --expand-depth) with the node at --select highlighted, instead of starting the viewer
+    #[clap(long, value_name = "PATH", conflicts_with_all = &["print", "output"])]
+    pub export_html: Option<PathBuf>,
+
+    /// Compare two files of the same format and show their differences
+    #[clap(long, number_of_values = 2, value_names = &["OLD", "NEW"], conflicts_with = "files")]
+    pub diff: Option<Vec<PathBuf>>,
+
+    /// Load the files, print how long detecting their format, reading and parsing took, with node counts, as one line of JSON per file, and exit
+    #[clap(long, conflicts_with_all = &["print", "output", "diff", "export-html"])]
+    pub bench: bool,
+
+    /// How to write errors to stderr: as text, or as one line of JSON with their kind, message, file and line
+    #[clap(long, value_name = "FORMAT", default_value = "text", possible_values = &["text", "json"])]
+    pub error_format: String,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// The settings the flags override, the top layer over the environment
+    /// and the config file
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            config: self.config.clone(),
+            format: self.format.clone(),
+            theme: None,
+            color: self.color.as_deref().map(|when| match when {
+                "always" => ColorMode::Always,
+                "never" => ColorMode::Never,
+                _ => ColorMode::Auto,
+            }),
+            no_color: false,
+            streaming_threshold: self.streaming_threshold,
+            no_streaming: self.no_streaming,
+            expand_depth: self.expand_depth,
+            no_mouse: self.no_mouse,
+            log_file: self.log_file.clone(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create, locate or check the configuration file
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Write a sample configuration file to the configuration path
+    Init {
+        /// Overwrite an existing configuration file
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Print the path the configuration is read from
+    Path,
+
+    /// Check a configuration file (the one at the configuration path if not provided) for syntax errors, unknown keys and invalid values
+    Validate {
+        #[clap(value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
 }
@@ -1,5 +1,8 @@
 use crate::error::{Result, XtvError};
-use serde::{Deserialize, Serialize};
+use crate::parser::ParserOptions;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::path::{Path, PathBuf};
 
 /// Application configuration
@@ -16,6 +19,18 @@ pub struct Config {
     /// Navigation settings
     #[serde(default)]
     pub navigation: NavigationConfig,
+
+    /// Search settings
+    #[serde(default)]
+    pub search: SearchConfig,
+
+    /// Per-format parser options, in `[parser.<format>]` sections
+    #[serde(default)]
+    pub parser: ParserOptions,
+
+    /// Debugging settings
+    #[serde(default)]
+    pub debug: DebugConfig,
 }
 
 /// UI configuration
@@ -28,6 +43,228 @@ pub struct UiConfig {
     /// Default expanded depth (0 = collapsed, -1 = fully expanded)
     #[serde(default = "default_expanded_depth")]
     pub default_expanded_depth: i32,
+
+    /// Per-slot color overrides on top of the theme (e.g. `label = "blue"`)
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+
+    /// When to use colors at all
+    #[serde(default)]
+    pub color: ColorMode,
+
+    /// Capture the mouse for clicking and scrolling (disables native text selection)
+    #[serde(default = "default_mouse")]
+    pub mouse: bool,
+
+    /// Show the number of hidden children after collapsed nodes, e.g. `(1,234)`
+    #[serde(default = "default_show_child_counts")]
+    pub show_child_counts: bool,
+
+    /// Show a one-line preview after collapsed objects and arrays
+    #[serde(default = "default_show_previews")]
+    pub show_previews: bool,
+
+    /// Show attributes on their element's row instead of under `@attributes`
+    #[serde(default)]
+    pub inline_attributes: bool,
+
+    /// Line numbers in a gutter left of the tree: off, absolute or relative
+    #[serde(default)]
+    pub line_numbers: LineNumbers,
+
+    /// Icons in front of labels: unicode, ascii or nerd (needs a Nerd Font)
+    #[serde(default)]
+    pub icons: IconSet,
+
+    /// How copied text reaches the clipboard: auto, system or osc52
+    #[serde(default)]
+    pub clipboard: ClipboardMode,
+
+    /// Largest text copied through OSC 52, longer text is cut (e.g. `"100KB"`)
+    #[serde(default = "default_osc52_max_bytes")]
+    pub osc52_max_bytes: ByteSize,
+
+    /// Values at least this large show their size after their label, e.g.
+    /// `(2.1 MB)`; 0 turns the hints off
+    #[serde(default = "default_size_hint_threshold")]
+    pub size_hint_threshold: ByteSize,
+
+    /// Arrays with more elements show them in collapsible pages of this
+    /// many, such as `[0..999]`; 0 shows all elements at once
+    #[serde(default = "default_array_page_size")]
+    pub array_page_size: usize,
+
+    /// Show the selected node's details next to the tree at startup
+    #[serde(default)]
+    pub show_detail_pane: bool,
+
+    /// Where the detail pane goes: right or bottom
+    #[serde(default)]
+    pub detail_pane_position: DetailPanePosition,
+
+    /// Separator between labels in the path bar and in copied paths, e.g.
+    /// "/" for paths that paste into jq or XPath
+    #[serde(default = "default_path_separator")]
+    pub path_separator: String,
+
+    /// Remember expanded nodes, the selection and the search of each file in
+    /// ~/.local/state/xtv/views and restore them when it is opened again
+    #[serde(default = "default_persist_state")]
+    pub persist_state: bool,
+
+    /// Draw ├── and └── guides instead of indenting when copying the visible
+    /// tree as text (yt, pt)
+    #[serde(default)]
+    pub copy_ascii_tree: bool,
+
+    /// Copy long values whole instead of cut like in the tree when copying
+    /// the visible tree as text
+    #[serde(default)]
+    pub copy_full_values: bool,
+
+    /// Start with the values of `redact_keys` masked, as toggled with `R`
+    #[serde(default)]
+    pub redact: bool,
+
+    /// Glob patterns of labels and attribute keys whose values redaction
+    /// masks, matched without regard to case (e.g. `"*_key"`)
+    #[serde(default = "default_redact_keys")]
+    pub redact_keys: Vec<String>,
+}
+
+/// Line numbers shown in the tree view's gutter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    /// Row index within the visible tree, starting at 1
+    Absolute,
+    /// Distance from the selected row, which shows its absolute number (like vim)
+    Relative,
+}
+
+impl LineNumbers {
+    /// The next mode in the order off, absolute, relative
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Off,
+        }
+    }
+}
+
+/// Glyphs drawn in front of labels, see [`crate::ui::icons`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconSet {
+    /// Triangles such as `▶` and `▼`
+    #[default]
+    Unicode,
+    /// `+`, `-` and `*`, for fonts without the triangles
+    Ascii,
+    /// Nerd Font glyphs, with an icon for each node type
+    Nerd,
+}
+
+/// When to use colors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Unless `NO_COLOR` is set; with `--print` also only on a terminal
+    #[default]
+    Auto,
+    Always,
+    /// Default terminal colors only, the selection in reverse video
+    Never,
+}
+
+/// How copied text reaches the clipboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardMode {
+    /// The system clipboard, or OSC 52 where there is none (e.g. over SSH)
+    #[default]
+    Auto,
+    /// Only the system clipboard
+    System,
+    /// Only the OSC 52 escape sequence, which the terminal turns into a copy
+    Osc52,
+}
+
+/// Whether searches tell upper and lower case apart
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "CaseSetting", into = "CaseSetting")]
+pub enum CaseSensitivity {
+    /// Ignore case unless the query contains an uppercase letter
+    #[default]
+    Smart,
+    Sensitive,
+    Insensitive,
+}
+
+impl CaseSensitivity {
+    /// The next mode in the order smart, sensitive, insensitive
+    pub fn next(self) -> Self {
+        match self {
+            Self::Smart => Self::Sensitive,
+            Self::Sensitive => Self::Insensitive,
+            Self::Insensitive => Self::Smart,
+        }
+    }
+
+    /// Whether `query` is matched case-sensitively in this mode
+    pub fn is_sensitive(self, query: &str) -> bool {
+        match self {
+            Self::Smart => query.chars().any(char::is_uppercase),
+            Self::Sensitive => true,
+            Self::Insensitive => false,
+        }
+    }
+}
+
+/// `case_sensitive` as written in the config: `true`, `false` or `"smart"`
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum CaseSetting {
+    Bool(bool),
+    Name(String),
+}
+
+impl TryFrom<CaseSetting> for CaseSensitivity {
+    type Error = String;
+
+    fn try_from(setting: CaseSetting) -> std::result::Result<Self, Self::Error> {
+        match setting {
+            CaseSetting::Bool(true) => Ok(Self::Sensitive),
+            CaseSetting::Bool(false) => Ok(Self::Insensitive),
+            CaseSetting::Name(name) if name == "smart" => Ok(Self::Smart),
+            CaseSetting::Name(name) => Err(format!(
+                "invalid case_sensitive '{}', expected true, false or \"smart\"",
+                name
+            )),
+        }
+    }
+}
+
+impl From<CaseSensitivity> for CaseSetting {
+    fn from(mode: CaseSensitivity) -> Self {
+        match mode {
+            CaseSensitivity::Smart => Self::Name("smart".to_string()),
+            CaseSensitivity::Sensitive => Self::Bool(true),
+            CaseSensitivity::Insensitive => Self::Bool(false),
+        }
+    }
+}
+
+/// Where the detail pane is placed relative to the tree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetailPanePosition {
+    #[default]
+    Right,
+    Bottom,
 }
 
 /// Streaming configuration
@@ -40,6 +277,77 @@ pub struct StreamingConfig {
     /// Enable streaming mode
     #[serde(default = "default_streaming_enabled")]
     pub enabled: bool,
+
+    /// Number of sibling/child nodes to prefetch on a cache miss (0 disables prefetching)
+    #[serde(default = "default_prefetch_window")]
+    pub prefetch_window: usize,
+
+    /// Per-format thresholds overriding `threshold_bytes` (e.g. `ldif = "100MB"`)
+    #[serde(default)]
+    pub thresholds: BTreeMap<String, ByteSize>,
+}
+
+impl StreamingConfig {
+    /// Get the streaming threshold for a format, falling back to `threshold_bytes`
+    pub fn threshold_for(&self, format: &str) -> u64 {
+        self.thresholds
+            .get(&format.to_lowercase())
+            .map(|size| size.0)
+            .unwrap_or(self.threshold_bytes)
+    }
+}
+
+/// A size in bytes, written either as an integer or as a string with a unit
+/// such as `"512KB"`, `"100MB"`, `"1.5GiB"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// Parse a size with an optional unit (B, KB, MB, GB, TB or KiB, MiB, GiB, TiB)
+    ///
+    /// Decimal and binary units are both treated as powers of 1024, matching
+    /// how file sizes are usually reported by `ls -h` and `du -h`.
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("Invalid size '{}'", text))?;
+        let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+            "" | "B" => 1,
+            "K" | "KB" | "KIB" => 1 << 10,
+            "M" | "MB" | "MIB" => 1 << 20,
+            "G" | "GB" | "GIB" => 1 << 30,
+            "T" | "TB" | "TIB" => 1 << 40,
+            other => return Err(format!("Unknown size unit '{}' in '{}'", other, text)),
+        };
+        Ok(Self((number * multiplier as f64) as u64))
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bytes(u64),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Bytes(bytes) => Ok(Self(bytes)),
+            Raw::Text(text) => Self::parse(&text).map_err(serde::de::Error::custom),
+        }
+    }
 }
 
 /// Navigation configuration
@@ -48,6 +356,43 @@ pub struct NavigationConfig {
     /// Number of lines to scroll for page up/down
     #[serde(default = "default_page_scroll_lines")]
     pub page_scroll_lines: usize,
+
+    /// Rows of context kept above and below the selection when scrolling
+    #[serde(default = "default_scrolloff")]
+    pub scrolloff: usize,
+
+    /// Ask for confirmation before expanding the whole tree (`zR`) when it has
+    /// more nodes than this, or before deep expanding siblings (`E`) when that
+    /// would show more nodes than this. Streaming trees always ask on `zR`.
+    #[serde(default = "default_expand_all_threshold")]
+    pub expand_all_threshold: usize,
+}
+
+/// Debugging configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DebugConfig {
+    /// File the debug log is appended to, e.g. `"/tmp/xtv.log"` (empty
+    /// disables the log)
+    #[serde(default)]
+    pub log_file: PathBuf,
+}
+
+/// Search configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchConfig {
+    /// Number of earlier queries recalled with Up/Down in the search prompt
+    /// (0 disables the history)
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+
+    /// Keep the history in ~/.local/state/xtv/history across sessions
+    #[serde(default = "default_persist_history")]
+    pub persist_history: bool,
+
+    /// Match case: true, false or "smart" (only if the query has an uppercase
+    /// letter). Ctrl-S in the search prompt switches it for the current query.
+    #[serde(default)]
+    pub case_sensitive: CaseSensitivity,
 }
 
 // Default value functions
@@ -59,6 +404,47 @@ fn default_expanded_depth() -> i32 {
     0
 }
 
+fn default_mouse() -> bool {
+    true
+}
+
+fn default_show_child_counts() -> bool {
+    true
+}
+
+fn default_show_previews() -> bool {
+    true
+}
+
+fn default_path_separator() -> String {
+    " > ".to_string()
+}
+
+fn default_redact_keys() -> Vec<String> {
+    [
+        "password",
+        "passwd",
+        "secret",
+        "token",
+        "*_key",
+        "userPassword",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_size_hint_threshold() -> ByteSize {
+    ByteSize(10 * 1024)
+}
+
+fn default_array_page_size() -> usize {
+    1000
+}
+
+fn default_osc52_max_bytes() -> ByteSize {
+    ByteSize(100 * 1024)
+}
+
 fn default_streaming_threshold() -> u64 {
     100 * 1024 * 1024 // 100MB
 }
@@ -67,15 +453,59 @@ fn default_streaming_enabled() -> bool {
     true
 }
 
+fn default_prefetch_window() -> usize {
+    crate::tree::streaming::DEFAULT_PREFETCH_WINDOW
+}
+
 fn default_page_scroll_lines() -> usize {
     10
 }
 
+fn default_scrolloff() -> usize {
+    3
+}
+
+fn default_expand_all_threshold() -> usize {
+    100_000
+}
+
+fn default_history_size() -> usize {
+    100
+}
+
+fn default_persist_history() -> bool {
+    true
+}
+
+fn default_persist_state() -> bool {
+    true
+}
+
 impl Default for UiConfig {
     fn default() -> Self {
         Self {
             theme: default_theme(),
             default_expanded_depth: default_expanded_depth(),
+            colors: BTreeMap::new(),
+            color: ColorMode::Auto,
+            mouse: default_mouse(),
+            show_child_counts: default_show_child_counts(),
+            show_previews: default_show_previews(),
+            inline_attributes: false,
+            line_numbers: LineNumbers::Off,
+            icons: IconSet::Unicode,
+            clipboard: ClipboardMode::Auto,
+            osc52_max_bytes: default_osc52_max_bytes(),
+            size_hint_threshold: default_size_hint_threshold(),
+            array_page_size: default_array_page_size(),
+            show_detail_pane: false,
+            detail_pane_position: DetailPanePosition::Right,
+            path_separator: default_path_separator(),
+            persist_state: default_persist_state(),
+            copy_ascii_tree: false,
+            copy_full_values: false,
+            redact: false,
+            redact_keys: default_redact_keys(),
         }
     }
 }
@@ -85,6 +515,8 @@ impl Default for StreamingConfig {
         Self {
             threshold_bytes: default_streaming_threshold(),
             enabled: default_streaming_enabled(),
+            prefetch_window: default_prefetch_window(),
+            thresholds: BTreeMap::new(),
         }
     }
 }
@@ -93,6 +525,8 @@ impl Default for NavigationConfig {
     fn default() -> Self {
         Self {
             page_scroll_lines: default_page_scroll_lines(),
+            scrolloff: default_scrolloff(),
+            expand_all_threshold: default_expand_all_threshold(),
         }
     }
 }
@@ -103,6 +537,19 @@ impl Default for Config {
             ui: UiConfig::default(),
             streaming: StreamingConfig::default(),
             navigation: NavigationConfig::default(),
+            search: SearchConfig::default(),
+            parser: ParserOptions::default(),
+            debug: DebugConfig::default(),
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            history_size: default_history_size(),
+            persist_history: default_persist_history(),
+            case_sensitive: CaseSensitivity::default(),
         }
     }
 }
@@ -162,15 +609,71 @@ impl Config {
         }
     }
 
+    /// Get the search history path (~/.local/state/xtv/history), or `None`
+    /// if the history isn't persisted
+    pub fn search_history_path(&self) -> Option<PathBuf> {
+        if !self.search.persist_history || self.search.history_size == 0 {
+            return None;
+        }
+        Self::xdg_state_dir().map(|dir| dir.join("history"))
+    }
+
+    /// Get the directory of the saved view states (~/.local/state/xtv/views),
+    /// or `None` if they aren't persisted
+    pub fn view_state_dir(&self) -> Option<PathBuf> {
+        if !self.ui.persist_state {
+            return None;
+        }
+        Self::xdg_state_dir().map(|dir| dir.join("views"))
+    }
+
+    /// Get the XDG state directory of xtv (~/.local/state/xtv)
+    fn xdg_state_dir() -> Option<PathBuf> {
+        if let Ok(state_dir) = std::env::var("XDG_STATE_HOME") {
+            Some(PathBuf::from(state_dir).join("xtv"))
+        } else if let Ok(home) = std::env::var("HOME") {
+            Some(PathBuf::from(home).join(".local").join("state").join("xtv"))
+        } else {
+            None
+        }
+    }
+
+    /// Apply a layer of settings on top of the config file's, then validate
+    /// the result
+    pub fn apply(&mut self, overrides: &Overrides) -> Result<()> {
+        if let Some(theme) = &overrides.theme {
+            self.ui.theme = theme.clone();
+        }
+        if let Some(color) = overrides.color {
+            self.ui.color = color;
+        }
+        if overrides.no_color && self.ui.color == ColorMode::Auto {
+            self.ui.color = ColorMode::Never;
+        }
+        if let Some(depth) = overrides.expand_depth {
+            self.ui.default_expanded_depth = depth;
+        }
+        if overrides.no_mouse {
+            self.ui.mouse = false;
+        }
+        if let Some(threshold) = overrides.streaming_threshold {
+            // Overrides the per-format thresholds too
+            self.streaming.threshold_bytes = threshold;
+            self.streaming.thresholds.clear();
+        }
+        if overrides.no_streaming {
+            self.streaming.enabled = false;
+        }
+        if let Some(log_file) = &overrides.log_file {
+            self.debug.log_file = log_file.clone();
+        }
+        self.validate()
+    }
+
     /// Validate configuration values
     fn validate(&self) -> Result<()> {
-        // Validate theme
-        if self.ui.theme != "dark" && self.ui.theme != "light" {
-            return Err(XtvError::Config(format!(
-                "Invalid theme '{}'. Must be 'dark' or 'light'",
-                self.ui.theme
-            )));
-        }
+        // Validate theme and color overrides
+        crate::ui::theme::Theme::from_config(&self.ui)?;
 
         // Validate expanded depth
         if self.ui.default_expanded_depth < -1 {
@@ -180,6 +683,18 @@ impl Config {
             )));
         }
 
+        if self.ui.path_separator.is_empty() {
+            return Err(XtvError::Config(
+                "Invalid path_separator: must not be empty".to_string(),
+            ));
+        }
+
+        if self.ui.osc52_max_bytes.0 == 0 {
+            return Err(XtvError::Config(
+                "Invalid osc52_max_bytes: must be > 0".to_string(),
+            ));
+        }
+
         // Validate streaming threshold
         if self.streaming.threshold_bytes == 0 {
             return Err(XtvError::Config(
@@ -187,6 +702,19 @@ impl Config {
             ));
         }
 
+        // Validate per-format streaming thresholds
+        if let Some((format, _)) = self
+            .streaming
+            .thresholds
+            .iter()
+            .find(|(_, size)| size.0 == 0)
+        {
+            return Err(XtvError::Config(format!(
+                "Invalid streaming threshold for '{}': must be > 0",
+                format
+            )));
+        }
+
         // Validate page scroll lines
         if self.navigation.page_scroll_lines == 0 {
             return Err(XtvError::Config(
@@ -201,6 +729,213 @@ impl Config {
     pub fn sample_config() -> String {
         toml::to_string_pretty(&Self::default()).unwrap_or_else(|_| String::new())
     }
+
+    /// Check the content of a configuration file, returning every problem:
+    /// TOML syntax errors, unknown keys, values of the wrong type and values
+    /// that fail validation. Loading ignores unknown keys, so this is the
+    /// only place they are reported.
+    pub fn check(content: &str) -> Vec<ConfigProblem> {
+        let table = match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => return vec![ConfigProblem::from_toml(content, &e)],
+        };
+
+        let mut problems = Vec::new();
+        if let Ok(toml::Value::Table(known)) = toml::Value::try_from(Self::default()) {
+            let lines = key_lines(content);
+            find_unknown_keys(&table, &known, "", &lines, &mut problems);
+        }
+
+        match toml::from_str::<Config>(content) {
+            Ok(config) => {
+                if let Err(XtvError::Config(message)) = config.validate() {
+                    problems.push(ConfigProblem {
+                        line: None,
+                        message,
+                    });
+                }
+            }
+            Err(e) => problems.push(ConfigProblem::from_toml(content, &e)),
+        }
+        problems.sort_by_key(|problem| problem.line.unwrap_or(usize::MAX));
+        problems
+    }
+}
+
+/// Settings that override the config file, from the `XTV_*` environment
+/// variables or from command-line flags.
+///
+/// The layers are resolved as defaults, config file, environment, command
+/// line: `Overrides::from_env(..).then(cli.overrides())` gives the settings
+/// of the top two, which [`Config::apply`] puts over the file's.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    /// Path of the config file (`XTV_CONFIG`, `--config`)
+    pub config: Option<PathBuf>,
+    /// Format of the input (`XTV_FORMAT`, `--format`)
+    pub format: Option<String>,
+    /// Color theme (`XTV_THEME`)
+    pub theme: Option<String>,
+    /// When to use colors (`--color`)
+    pub color: Option<ColorMode>,
+    /// Leave out colors unless asked for them (`NO_COLOR`)
+    pub no_color: bool,
+    /// Streaming threshold for every format (`XTV_STREAMING_THRESHOLD`,
+    /// `--streaming-threshold`)
+    pub streaming_threshold: Option<u64>,
+    /// Never stream (`XTV_NO_STREAMING`, `--no-streaming`)
+    pub no_streaming: bool,
+    /// Depth expanded at startup (`--expand-depth`)
+    pub expand_depth: Option<i32>,
+    /// Don't capture the mouse (`--no-mouse`)
+    pub no_mouse: bool,
+    /// File to write the debug log to (`--log-file`)
+    pub log_file: Option<PathBuf>,
+}
+
+impl Overrides {
+    /// Read the `XTV_*` environment variables through `var`, such as
+    /// `|name| std::env::var(name).ok()`. Empty variables count as unset.
+    ///
+    /// `XTV_STREAMING_THRESHOLD` takes a size like `"100MB"`, and
+    /// `XTV_NO_STREAMING` is on unless it is `0` or `false`.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let streaming_threshold = match var("XTV_STREAMING_THRESHOLD") {
+            Some(value) => Some(
+                ByteSize::parse(&value)
+                    .map_err(|e| {
+                        XtvError::Config(format!("Invalid XTV_STREAMING_THRESHOLD: {}", e))
+                    })?
+                    .0,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            config: var("XTV_CONFIG").map(PathBuf::from),
+            format: var("XTV_FORMAT"),
+            theme: var("XTV_THEME"),
+            color: None,
+            no_color: var("NO_COLOR").is_some(),
+            streaming_threshold,
+            no_streaming: var("XTV_NO_STREAMING")
+                .is_some_and(|value| !matches!(value.to_lowercase().as_str(), "0" | "false")),
+            expand_depth: None,
+            no_mouse: false,
+            log_file: None,
+        })
+    }
+
+    /// These settings with the ones of `top` over them
+    pub fn then(self, top: Overrides) -> Self {
+        Self {
+            config: top.config.or(self.config),
+            format: top.format.or(self.format),
+            theme: top.theme.or(self.theme),
+            color: top.color.or(self.color),
+            no_color: top.no_color || self.no_color,
+            streaming_threshold: top.streaming_threshold.or(self.streaming_threshold),
+            no_streaming: top.no_streaming || self.no_streaming,
+            expand_depth: top.expand_depth.or(self.expand_depth),
+            no_mouse: top.no_mouse || self.no_mouse,
+            log_file: top.log_file.or(self.log_file),
+        }
+    }
+}
+
+/// A problem in a configuration file found by [`Config::check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Line of the file the problem is on, starting at 1, if known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn from_toml(content: &str, error: &toml::de::Error) -> Self {
+        Self {
+            line: error
+                .span()
+                .map(|span| content[..span.start].matches('\n').count() + 1),
+            message: error.message().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Report the keys of `table` that `known`, the serialized default
+/// configuration, lacks. Tables that are empty by default, such as
+/// `ui.colors`, take any key.
+fn find_unknown_keys(
+    table: &toml::Table,
+    known: &toml::Table,
+    prefix: &str,
+    lines: &HashMap<String, usize>,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match (known.get(key), value) {
+            (Some(toml::Value::Table(known)), toml::Value::Table(table)) if !known.is_empty() => {
+                find_unknown_keys(table, known, &path, lines, problems);
+            }
+            (Some(_), _) => {}
+            (None, _) => problems.push(ConfigProblem {
+                line: lines.get(&path).copied(),
+                message: format!("unknown key `{}`", path),
+            }),
+        }
+    }
+}
+
+/// The line each table header and key is on, by dotted path such as
+/// `ui.theme`. Keys inside inline tables and multi-line values are missed.
+fn key_lines(content: &str) -> HashMap<String, usize> {
+    let dotted = |key: &str| {
+        key.split('.')
+            .map(|part| part.trim().trim_matches(|c| c == '"' || c == '\''))
+            .collect::<Vec<_>>()
+            .join(".")
+    };
+
+    let mut lines = HashMap::new();
+    let mut table = String::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        let path = if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_start_matches('[');
+            let Some(end) = header.find(']') else {
+                continue;
+            };
+            table = dotted(&header[..end]);
+            table.clone()
+        } else if let Some((key, _)) = line.split_once('=')
+            && !key.trim().is_empty()
+            && !key.starts_with('#')
+        {
+            if table.is_empty() {
+                dotted(key)
+            } else {
+                format!("{}.{}", table, dotted(key))
+            }
+        } else {
+            continue;
+        };
+        lines.entry(path).or_insert(index + 1);
+    }
+    lines
 }
 
 #[cfg(test)]
@@ -212,9 +947,22 @@ mod tests {
         let config = Config::default();
         assert_eq!(config.ui.theme, "dark");
         assert_eq!(config.ui.default_expanded_depth, 0);
+        assert!(config.ui.mouse);
+        assert!(config.ui.show_child_counts);
+        assert!(config.ui.show_previews);
+        assert!(!config.ui.inline_attributes);
+        assert_eq!(config.ui.line_numbers, LineNumbers::Off);
+        assert_eq!(config.ui.clipboard, ClipboardMode::Auto);
+        assert_eq!(config.ui.osc52_max_bytes, ByteSize(100 * 1024));
+        assert!(!config.ui.show_detail_pane);
+        assert_eq!(config.ui.detail_pane_position, DetailPanePosition::Right);
         assert_eq!(config.streaming.threshold_bytes, 100 * 1024 * 1024);
         assert!(config.streaming.enabled);
+        assert_eq!(config.streaming.prefetch_window, 50);
         assert_eq!(config.navigation.page_scroll_lines, 10);
+        assert_eq!(config.navigation.scrolloff, 3);
+        assert_eq!(config.search.history_size, 100);
+        assert!(config.search.persist_history);
     }
 
     #[test]
@@ -244,11 +992,228 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_per_format_thresholds() {
+        let config: Config = toml::from_str(
+            "[streaming]\nthreshold_bytes = 1000\n\n[streaming.thresholds]\nldif = \"100MB\"\njsonl = 2048\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.streaming.threshold_for("ldif"), 100 * 1024 * 1024);
+        assert_eq!(config.streaming.threshold_for("LDIF"), 100 * 1024 * 1024);
+        assert_eq!(config.streaming.threshold_for("jsonl"), 2048);
+        assert_eq!(config.streaming.threshold_for("xml"), 1000);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_zero_per_format_threshold_is_invalid() {
+        let mut config = Config::default();
+        config
+            .streaming
+            .thresholds
+            .insert("ldif".to_string(), ByteSize(0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_byte_size_parsing() {
+        assert_eq!(ByteSize::parse("512"), Ok(ByteSize(512)));
+        assert_eq!(ByteSize::parse("4KB"), Ok(ByteSize(4096)));
+        assert_eq!(ByteSize::parse("100 MB"), Ok(ByteSize(100 * 1024 * 1024)));
+        assert_eq!(
+            ByteSize::parse("1.5GiB"),
+            Ok(ByteSize(3 * 512 * 1024 * 1024))
+        );
+        assert!(ByteSize::parse("MB").is_err());
+        assert!(ByteSize::parse("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_invalid_color_override_is_rejected() {
+        let config: Config = toml::from_str("[ui.colors]\nlabel = \"not-a-color\"\n").unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("Invalid color 'not-a-color'"));
+    }
+
+    #[test]
+    fn test_line_numbers_modes() {
+        let config: Config = toml::from_str("[ui]\nline_numbers = \"relative\"\n").unwrap();
+        assert_eq!(config.ui.line_numbers, LineNumbers::Relative);
+        assert!(toml::from_str::<Config>("[ui]\nline_numbers = \"hybrid\"\n").is_err());
+        assert_eq!(LineNumbers::Relative.next(), LineNumbers::Off);
+    }
+
+    #[test]
+    fn test_icon_sets() {
+        assert_eq!(Config::default().ui.icons, IconSet::Unicode);
+        let config: Config = toml::from_str("[ui]\nicons = \"nerd\"\n").unwrap();
+        assert_eq!(config.ui.icons, IconSet::Nerd);
+        assert!(toml::from_str::<Config>("[ui]\nicons = \"emoji\"\n").is_err());
+    }
+
+    #[test]
+    fn test_clipboard_modes() {
+        let config: Config =
+            toml::from_str("[ui]\nclipboard = \"osc52\"\nosc52_max_bytes = \"1MB\"\n").unwrap();
+        assert_eq!(config.ui.clipboard, ClipboardMode::Osc52);
+        assert_eq!(config.ui.osc52_max_bytes, ByteSize(1024 * 1024));
+        assert!(toml::from_str::<Config>("[ui]\nclipboard = \"x11\"\n").is_err());
+    }
+
+    #[test]
+    fn test_case_sensitivity_setting() {
+        assert_eq!(
+            Config::default().search.case_sensitive,
+            CaseSensitivity::Smart
+        );
+        let config: Config = toml::from_str("[search]\ncase_sensitive = true\n").unwrap();
+        assert_eq!(config.search.case_sensitive, CaseSensitivity::Sensitive);
+        let config: Config = toml::from_str("[search]\ncase_sensitive = \"smart\"\n").unwrap();
+        assert_eq!(config.search.case_sensitive, CaseSensitivity::Smart);
+        assert!(toml::from_str::<Config>("[search]\ncase_sensitive = \"yes\"\n").is_err());
+
+        assert!(CaseSensitivity::Smart.is_sensitive("Alice"));
+        assert!(!CaseSensitivity::Smart.is_sensitive("alice"));
+        assert!(!CaseSensitivity::Insensitive.is_sensitive("Alice"));
+    }
+
     #[test]
     fn test_sample_config() {
         let sample = Config::sample_config();
         assert!(sample.contains("theme"));
         assert!(sample.contains("threshold_bytes"));
         assert!(sample.contains("page_scroll_lines"));
+        assert_eq!(Config::check(&sample), []);
+    }
+
+    #[test]
+    fn test_check_reports_unknown_keys_with_lines() {
+        let content = "[ui]\ntheme = \"light\"\nthme = \"dark\"\n\n[ui.colors]\nlabel = \"blue\"\n\n[serch]\nhistory_size = 10\n";
+        let problems: Vec<String> = Config::check(content)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                "line 3: unknown key `ui.thme`",
+                "line 8: unknown key `serch`"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overrides_are_layered() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            Overrides::from_env(|name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        let file = || -> Config {
+            toml::from_str(
+                "[ui]\ntheme = \"light\"\n\n[streaming]\nthreshold_bytes = 1000\n\n[streaming.thresholds]\nldif = 500\n",
+            )
+            .unwrap()
+        };
+
+        // The file's values stand without overrides
+        let mut config = file();
+        config.apply(&Overrides::default()).unwrap();
+        assert_eq!(config.ui.theme, "light");
+        assert_eq!(config.streaming.threshold_for("ldif"), 500);
+
+        // The environment overrides the file
+        let from_env = env(&[
+            ("XTV_THEME", "dark"),
+            ("XTV_FORMAT", "json"),
+            ("XTV_STREAMING_THRESHOLD", "2KB"),
+            ("XTV_NO_STREAMING", "1"),
+            ("XTV_CONFIG", ""),
+        ])
+        .unwrap();
+        assert_eq!(from_env.format.as_deref(), Some("json"));
+        assert_eq!(from_env.config, None);
+        let mut config = file();
+        config.apply(&from_env).unwrap();
+        assert_eq!(config.ui.theme, "dark");
+        assert_eq!(config.streaming.threshold_for("ldif"), 2048);
+        assert!(!config.streaming.enabled);
+
+        // The command line overrides the environment
+        let cli = Overrides {
+            format: Some("yaml".to_string()),
+            streaming_threshold: Some(10),
+            ..Overrides::default()
+        };
+        let layered = from_env.then(cli);
+        assert_eq!(layered.format.as_deref(), Some("yaml"));
+        assert_eq!(layered.theme.as_deref(), Some("dark"));
+        let mut config = file();
+        config.apply(&layered).unwrap();
+        assert_eq!(config.streaming.threshold_for("ldif"), 10);
+
+        assert!(!env(&[("XTV_NO_STREAMING", "false")]).unwrap().no_streaming);
+        assert!(env(&[("XTV_STREAMING_THRESHOLD", "lots")]).is_err());
+        let mut config = Config::default();
+        let bad_theme = env(&[("XTV_THEME", "neon")]).unwrap();
+        assert!(config.apply(&bad_theme).is_err());
+
+        // NO_COLOR turns colors off unless they are asked for
+        let no_color = env(&[("NO_COLOR", "1")]).unwrap();
+        let mut config = Config::default();
+        config.apply(&no_color).unwrap();
+        assert_eq!(config.ui.color, ColorMode::Never);
+        let always = Overrides {
+            color: Some(ColorMode::Always),
+            ..Overrides::default()
+        };
+        let mut config = Config::default();
+        config.apply(&no_color.then(always)).unwrap();
+        assert_eq!(config.ui.color, ColorMode::Always);
+        assert!(!env(&[("NO_COLOR", "")]).unwrap().no_color);
+    }
+
+    #[test]
+    fn test_parser_options() {
+        let content = "[parser.html]\nskip_scripts = true\n\n[parser.jsonl]\nskip_invalid = true\n\n[parser.csv]\ndelimiter = \";\"\n\n[parser.ldif.attribute_labels]\nsponsor = { label = \"Sponsor\", dn = true }\n\n[parser.ldif.decoders]\nlastLogon = \"none\"\n";
+        let config: Config = toml::from_str(content).unwrap();
+        assert!(config.parser.html.skip_scripts);
+        assert!(!config.parser.jsonl.skip_invalid_lines);
+        assert!(config.parser.ldif.friendly_names);
+        assert_eq!(config.parser.ldif.attribute_labels.len(), 1);
+        assert!(config.parser.ldif.decode_values);
+        let decoders = config.parser.ldif.value_decoders();
+        assert_eq!(decoders.decode("lastLogon", "0"), None);
+        assert_eq!(decoders.decode("pwdLastSet", "0").as_deref(), Some("never"));
+
+        let problems: Vec<String> = Config::check(content)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                "line 5: unknown key `parser.jsonl.skip_invalid`",
+                "line 7: unknown key `parser.csv`"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_reports_syntax_and_value_errors() {
+        let syntax = Config::check("[ui]\ntheme = \"dark\"\nmouse = \n");
+        assert_eq!(syntax.len(), 1);
+        assert_eq!(syntax[0].line, Some(3));
+
+        let wrong_type = Config::check("[navigation]\nscrolloff = \"three\"\n");
+        assert_eq!(wrong_type.len(), 1);
+        assert_eq!(wrong_type[0].line, Some(2));
+
+        let invalid = Config::check("[navigation]\npage_scroll_lines = 0\n");
+        assert_eq!(invalid[0].line, None);
+        assert!(invalid[0].message.contains("page_scroll_lines"));
     }
 }
@@ -0,0 +1,173 @@
+//! CSV, for arrays of flat objects such as the records of a JSON Lines file.
+
+use crate::error::{Result, XtvError};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// An array of flat objects as CSV, one row per object under a header row.
+///
+/// The columns are the keys of all objects, in the order they first occur,
+/// and objects without a key get an empty cell for it, as do nulls. Cells
+/// with commas, quotes or line breaks are quoted, doubling the quotes in
+/// them; rows end in `\n`.
+///
+/// Anything else fails, saying why: values other than arrays, items other
+/// than objects, and objects holding arrays or objects, which a cell can't
+/// hold.
+pub fn to_csv(value: &Value) -> Result<String> {
+    let Value::Array(items) = value else {
+        return Err(XtvError::Serialize(format!(
+            "CSV needs an array of objects, not {}",
+            kind(value)
+        )));
+    };
+
+    let mut rows: Vec<&Map<String, Value>> = Vec::with_capacity(items.len());
+    let mut columns: Vec<&str> = Vec::new();
+    let mut seen = HashSet::new();
+    for (index, item) in items.iter().enumerate() {
+        let Value::Object(row) = item else {
+            return Err(XtvError::Serialize(format!(
+                "CSV rows must be objects, but [{}] is {}",
+                index,
+                kind(item)
+            )));
+        };
+        for (key, cell) in row {
+            if cell.is_array() || cell.is_object() {
+                return Err(XtvError::Serialize(format!(
+                    "CSV cells can't hold {}, as in [{}].{}",
+                    kind(cell),
+                    index,
+                    key
+                )));
+            }
+            if seen.insert(key.as_str()) {
+                columns.push(key);
+            }
+        }
+        rows.push(row);
+    }
+    if columns.is_empty() {
+        return Err(XtvError::Serialize(
+            "CSV needs objects with keys to make columns of".to_string(),
+        ));
+    }
+
+    let mut csv = String::new();
+    push_row(
+        &mut csv,
+        columns.iter().map(|&column| Cow::Borrowed(column)),
+    );
+    for row in rows {
+        push_row(
+            &mut csv,
+            columns.iter().map(|&column| match row.get(column) {
+                None | Some(Value::Null) => Cow::Borrowed(""),
+                Some(Value::String(s)) => Cow::Borrowed(s.as_str()),
+                Some(other) => Cow::Owned(other.to_string()),
+            }),
+        );
+    }
+    Ok(csv)
+}
+
+fn push_row<'a>(csv: &mut String, cells: impl Iterator<Item = Cow<'a, str>>) {
+    for (index, cell) in cells.enumerate() {
+        if index > 0 {
+            csv.push(',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&cell.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(&cell);
+        }
+    }
+    csv.push('\n');
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error(value: Value) -> String {
+        to_csv(&value).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_header_and_rows() {
+        let value = json!([
+            {"id": 1, "name": "Ada", "admin": true},
+            {"id": 2, "name": "Bob", "admin": false},
+        ]);
+        assert_eq!(
+            to_csv(&value).unwrap(),
+            "admin,id,name\ntrue,1,Ada\nfalse,2,Bob\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_keys_and_nulls_are_empty() {
+        let value = json!([{"a": 1}, {"b": 2}, {"a": null, "c": 3.5}]);
+        assert_eq!(to_csv(&value).unwrap(), "a,b,c\n1,,\n,2,\n,,3.5\n");
+    }
+
+    #[test]
+    fn test_quoting() {
+        let value = json!([{
+            "plain": "x y",
+            "comma": "a,b",
+            "quote": "say \"hi\"",
+            "lines": "one\ntwo\r\n",
+        }]);
+        assert_eq!(
+            to_csv(&value).unwrap(),
+            "comma,lines,plain,quote\n\"a,b\",\"one\ntwo\r\n\",x y,\"say \"\"hi\"\"\"\n"
+        );
+        // Keys are quoted the same way
+        assert_eq!(to_csv(&json!([{"a,b": 1}])).unwrap(), "\"a,b\"\n1\n");
+    }
+
+    #[test]
+    fn test_values_that_are_not_tables_fail() {
+        assert_eq!(
+            error(json!({"a": 1})),
+            "Cannot serialize: CSV needs an array of objects, not an object"
+        );
+        assert_eq!(
+            error(json!([{"a": 1}, 2])),
+            "Cannot serialize: CSV rows must be objects, but [1] is a number"
+        );
+        assert_eq!(
+            error(json!([{"a": 1}, {"a": {"b": 2}}])),
+            "Cannot serialize: CSV cells can't hold an object, as in [1].a"
+        );
+        assert_eq!(
+            error(json!([{"tags": []}])),
+            "Cannot serialize: CSV cells can't hold an array, as in [0].tags"
+        );
+        assert_eq!(
+            error(json!([])),
+            "Cannot serialize: CSV needs objects with keys to make columns of"
+        );
+        assert_eq!(
+            error(json!([{}])),
+            "Cannot serialize: CSV needs objects with keys to make columns of"
+        );
+    }
+}
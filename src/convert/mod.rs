@@ -0,0 +1,100 @@
+//! Subtrees written in another format than the one they were read from.
+//!
+//! The converters take a subtree as exported to JSON (see
+//! [`crate::tree::export`]) and write it as YAML, TOML or CSV, whatever the
+//! input was, for `yY`, `yT` and `yC`. They fail with
+//! [`XtvError::Serialize`] where the target format can't hold the data:
+//! TOML has no null, and CSV only holds arrays of flat objects.
+
+mod csv;
+mod toml;
+
+pub use self::csv::to_csv;
+pub use self::toml::to_toml;
+
+use crate::error::{Result, XtvError};
+use serde_json::Value;
+
+/// A format subtrees can be converted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Csv,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Yaml => "YAML",
+            Self::Toml => "TOML",
+            Self::Csv => "CSV",
+        }
+    }
+
+    /// Write `value` in this format. `key` is the label TOML puts a value
+    /// that isn't a table under, since a TOML document is always a table.
+    pub fn convert(self, value: Value, key: &str) -> Result<String> {
+        match self {
+            Self::Yaml => to_yaml(&value),
+            Self::Toml => to_toml(value, key),
+            Self::Csv => to_csv(&value),
+        }
+    }
+}
+
+/// `value` as a YAML document
+pub fn to_yaml(value: &Value) -> Result<String> {
+    serde_yaml::to_string(value).map_err(|e| XtvError::Serialize(e.to_string()))
+}
+
+/// Where in a value something went wrong, as `servers[2].port`
+pub(crate) fn display_path(segments: &[String]) -> String {
+    let mut path = String::new();
+    for segment in segments.iter().rev() {
+        if !segment.starts_with('[') && !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(segment);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_yaml() {
+        let value = json!({"name": "xtv", "tags": ["a", 1, null], "nested": {"on": true}});
+        let yaml = to_yaml(&value).unwrap();
+        assert_eq!(
+            yaml,
+            "name: xtv\nnested:\n  on: true\ntags:\n- a\n- 1\n- null\n"
+        );
+        assert_eq!(serde_yaml::from_str::<Value>(&yaml).unwrap(), value);
+    }
+
+    #[test]
+    fn test_formats() {
+        let value = json!([{"a": 1}]);
+        assert_eq!(
+            Format::Yaml.convert(value.clone(), "x").unwrap(),
+            "- a: 1\n"
+        );
+        assert_eq!(
+            Format::Toml.convert(value.clone(), "x").unwrap(),
+            "[[x]]\na = 1\n"
+        );
+        assert_eq!(Format::Csv.convert(value, "x").unwrap(), "a\n1\n");
+    }
+
+    #[test]
+    fn test_display_path() {
+        let segments = ["port", "[2]", "servers"].map(String::from);
+        assert_eq!(display_path(&segments), "servers[2].port");
+        assert_eq!(display_path(&["[0]".to_string()]), "[0]");
+        assert_eq!(display_path(&[]), "");
+    }
+}
@@ -0,0 +1,162 @@
+//! TOML, which has datetimes but no null.
+
+use super::display_path;
+use crate::error::{Result, XtvError};
+use serde_json::Value as Json;
+
+/// Why a value can't be written, and the keys and indices leading to it,
+/// innermost first
+struct Unrepresentable {
+    reason: String,
+    path: Vec<String>,
+}
+
+/// `value` as a TOML document.
+///
+/// Objects are written as their contents and other values as `key = value`.
+/// Strings that are TOML datetimes are written as datetimes. Arrays mixing
+/// tables with other values are written inline, as TOML allows. Nulls and
+/// integers beyond 64 bits can't be written, and the error says where the
+/// first one is.
+pub fn to_toml(value: Json, key: &str) -> Result<String> {
+    let table = if value.is_object() {
+        value
+    } else {
+        Json::Object(serde_json::Map::from_iter([(key.to_string(), value)]))
+    };
+    let table = to_toml_value(table).map_err(|e| {
+        let path = display_path(&e.path);
+        if path.is_empty() {
+            XtvError::Serialize(e.reason)
+        } else {
+            XtvError::Serialize(format!("{} at {}", e.reason, path))
+        }
+    })?;
+    toml::to_string(&table).map_err(|e| XtvError::Serialize(e.to_string()))
+}
+
+fn to_toml_value(value: Json) -> std::result::Result<toml::Value, Unrepresentable> {
+    let fail = |reason: String| Unrepresentable {
+        reason,
+        path: Vec::new(),
+    };
+    Ok(match value {
+        Json::Null => return Err(fail("TOML has no null value".to_string())),
+        Json::Bool(b) => toml::Value::Boolean(b),
+        Json::Number(n) => match (n.as_i64(), n.is_u64()) {
+            (Some(n), _) => toml::Value::Integer(n),
+            (None, true) => return Err(fail(format!("{} is too large for a TOML integer", n))),
+            (None, false) => toml::Value::Float(
+                n.as_f64()
+                    .ok_or_else(|| fail(format!("{} is out of range", n)))?,
+            ),
+        },
+        Json::String(s) => match s.parse() {
+            Ok(datetime) => toml::Value::Datetime(datetime),
+            Err(_) => toml::Value::String(s),
+        },
+        Json::Array(items) => toml::Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    to_toml_value(item).map_err(|mut e| {
+                        e.path.push(format!("[{}]", index));
+                        e
+                    })
+                })
+                .collect::<std::result::Result<_, _>>()?,
+        ),
+        Json::Object(map) => toml::Value::Table(
+            map.into_iter()
+                .map(|(key, value)| match to_toml_value(value) {
+                    Ok(value) => Ok((key, value)),
+                    Err(mut e) => {
+                        e.path.push(key);
+                        Err(e)
+                    }
+                })
+                .collect::<std::result::Result<_, _>>()?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error(value: Json) -> String {
+        to_toml(value, "value").unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_tables_and_arrays_of_tables() {
+        let value = json!({
+            "name": "xtv",
+            "package": {"version": "1.0", "edition": 2024},
+            "bin": [{"name": "a"}, {"name": "b"}],
+        });
+        assert_eq!(
+            to_toml(value, "ignored").unwrap(),
+            "name = \"xtv\"\n\n[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n\n\
+             [package]\nedition = 2024\nversion = \"1.0\"\n"
+        );
+    }
+
+    #[test]
+    fn test_values_other_than_tables_get_the_key() {
+        assert_eq!(to_toml(json!(42), "port").unwrap(), "port = 42\n");
+        assert_eq!(
+            to_toml(json!([1, 2]), "ports [0]").unwrap(),
+            "\"ports [0]\" = [1, 2]\n"
+        );
+        assert_eq!(
+            to_toml(json!([{"a": 1}]), "items").unwrap(),
+            "[[items]]\na = 1\n"
+        );
+    }
+
+    #[test]
+    fn test_datetimes_and_floats() {
+        let value = json!({"when": "1979-05-27T07:32:00Z", "day": "1979-05-27", "pi": 3.5});
+        assert_eq!(
+            to_toml(value, "x").unwrap(),
+            "day = 1979-05-27\npi = 3.5\nwhen = 1979-05-27T07:32:00Z\n"
+        );
+        // Strings that merely look like dates stay strings
+        assert_eq!(
+            to_toml(json!({"v": "2024-13-99"}), "x").unwrap(),
+            "v = \"2024-13-99\"\n"
+        );
+    }
+
+    #[test]
+    fn test_mixed_arrays_are_written_inline() {
+        let value = json!({"mixed": [{"a": 1}, 2, [3]]});
+        let text = to_toml(value.clone(), "x").unwrap();
+        assert_eq!(text, "mixed = [{ a = 1 }, 2, [3]]\n");
+        let reparsed: toml::Value = toml::from_str(&text).unwrap();
+        assert_eq!(serde_json::to_value(reparsed).unwrap(), value);
+    }
+
+    #[test]
+    fn test_unrepresentable_values_say_where_they_are() {
+        assert_eq!(
+            error(json!(null)),
+            "Cannot serialize: TOML has no null value at value"
+        );
+        assert_eq!(
+            error(json!({"servers": [{"port": 1}, {"port": null}]})),
+            "Cannot serialize: TOML has no null value at servers[1].port"
+        );
+        assert_eq!(
+            error(json!({"big": u64::MAX})),
+            "Cannot serialize: 18446744073709551615 is too large for a TOML integer at big"
+        );
+        assert_eq!(
+            error(json!({"matrix": [[1, null]]})),
+            "Cannot serialize: TOML has no null value at matrix[0][1]"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::io;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -13,7 +14,7 @@ pub enum XtvError {
     YamlParse(#[from] serde_yaml::Error),
 
     #[error("TOML parsing error: {0}")]
-    TomlParse(#[from] toml::de::Error),
+    TomlParse(#[from] toml_edit::TomlError),
 
     #[error("XML parsing error: {0}")]
     XmlParse(String),
@@ -30,11 +31,78 @@ pub enum XtvError {
     #[error("Invalid node ID: {0}")]
     InvalidNodeId(usize),
 
+    #[error("Cannot load node {id}: {source}")]
+    NodeLoad {
+        id: usize,
+        #[source]
+        source: io::Error,
+    },
+
     #[error("TUI error: {0}")]
     Tui(String),
 
     #[error("Configuration error: {0}")]
     Config(String),
+
+    #[error("Invalid XPath: {0}")]
+    XPath(String),
+
+    #[error("No node at {0}")]
+    NoSuchPath(String),
+
+    #[error("Cannot serialize: {0}")]
+    Serialize(String),
+
+    #[error("Interrupted")]
+    Interrupted,
+
+    #[error("{}: {source}", path.display())]
+    InFile {
+        path: PathBuf,
+        #[source]
+        source: Box<XtvError>,
+    },
+}
+
+impl XtvError {
+    /// Attribute the error to the file at `path`
+    pub fn in_file(self, path: impl Into<PathBuf>) -> Self {
+        match self {
+            Self::InFile { .. } => self,
+            source => Self::InFile {
+                path: path.into(),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The error without the file it was attributed to
+    pub fn cause(&self) -> &XtvError {
+        match self {
+            Self::InFile { source, .. } => source,
+            _ => self,
+        }
+    }
+
+    /// The file the error was attributed to, if any
+    pub fn file(&self) -> Option<&PathBuf> {
+        match self {
+            Self::InFile { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Line and, if known, column of a parse error, both starting at 1
+    pub fn location(&self) -> Option<(usize, Option<usize>)> {
+        match self.cause() {
+            Self::JsonParse(e) if e.line() > 0 => Some((e.line(), Some(e.column()))),
+            Self::YamlParse(e) => e
+                .location()
+                .map(|location| (location.line(), Some(location.column()))),
+            Self::LdifParse { line, .. } => Some((*line, None)),
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, XtvError>;
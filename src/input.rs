@@ -0,0 +1,137 @@
+//! Reading input that may be too large to hold in memory.
+//!
+//! Files on disk can be streamed directly, but stdin can only be read once.
+//! Large piped input in a streamable format is therefore spooled to a
+//! temporary file, which is then indexed like any other file.
+
+use crate::error::{Result, XtvError};
+use std::io::{self, Read, Write};
+use tempfile::NamedTempFile;
+
+/// Size of the chunks read from the input
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of leading bytes used to detect the input format
+const DETECTION_PREFIX_SIZE: usize = 4096;
+
+/// Input read from a non-seekable source such as stdin
+#[derive(Debug)]
+pub enum Input {
+    /// The complete input, small enough to parse in memory
+    Buffered(String),
+    /// The input spooled to a temporary file, deleted when dropped
+    Spooled(NamedTempFile),
+}
+
+/// Read all input, spilling to a temporary file once it exceeds `threshold` bytes.
+///
+/// Spooling only happens if `can_stream` accepts the beginning of the input
+/// (e.g. it looks like LDIF); otherwise the input is buffered regardless of size.
+/// Pass `None` as threshold to always buffer.
+///
+/// # Arguments
+///
+/// * `reader` - The input source
+/// * `threshold` - Size in bytes above which streamable input is spooled
+/// * `can_stream` - Decides from the first few KB whether the input can stream
+///
+/// # Errors
+///
+/// Returns an error if reading or writing the temporary file fails, or if
+/// buffered input is not valid UTF-8.
+pub fn read_input<R: Read>(
+    mut reader: R,
+    threshold: Option<u64>,
+    can_stream: impl Fn(&str) -> bool,
+) -> Result<Input> {
+    let mut buffer = Vec::new();
+    let mut chunk = vec![0u8; READ_CHUNK_SIZE];
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let over_threshold = threshold.is_some_and(|t| buffer.len() as u64 > t);
+        if over_threshold && can_stream(&detection_prefix(&buffer)) {
+            return spool(&buffer, reader).map(Input::Spooled);
+        }
+    }
+
+    String::from_utf8(buffer)
+        .map(Input::Buffered)
+        .map_err(|e| XtvError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// The beginning of the buffer as text, tolerating a split multi-byte character
+fn detection_prefix(buffer: &[u8]) -> std::borrow::Cow<'_, str> {
+    String::from_utf8_lossy(&buffer[..buffer.len().min(DETECTION_PREFIX_SIZE)])
+}
+
+/// Write what was read so far plus the rest of the input to a temporary file
+fn spool<R: Read>(head: &[u8], mut rest: R) -> Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    {
+        let mut writer = io::BufWriter::new(file.as_file_mut());
+        writer.write_all(head)?;
+        io::copy(&mut rest, &mut writer)?;
+        writer.flush()?;
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_small_input_is_buffered() {
+        let input = read_input(Cursor::new("dn: cn=a\n"), Some(1024), |_| true).unwrap();
+        assert!(matches!(input, Input::Buffered(s) if s == "dn: cn=a\n"));
+    }
+
+    #[test]
+    fn test_large_streamable_input_is_spooled() {
+        let content = "dn: cn=a\ncn: a\n\n".repeat(10_000);
+        let input = read_input(Cursor::new(content.clone()), Some(1024), |prefix| {
+            prefix.starts_with("dn:")
+        })
+        .unwrap();
+
+        let Input::Spooled(file) = input else {
+            panic!("expected spooled input");
+        };
+        assert_eq!(std::fs::read_to_string(file.path()).unwrap(), content);
+    }
+
+    #[test]
+    fn test_large_non_streamable_input_is_buffered() {
+        let content = "{\"a\": 1}".repeat(10_000);
+        let input = read_input(Cursor::new(content.clone()), Some(1024), |_| false).unwrap();
+        assert!(matches!(input, Input::Buffered(s) if s == content));
+    }
+
+    #[test]
+    fn test_no_threshold_always_buffers() {
+        let content = "dn: cn=a\n".repeat(10_000);
+        let input = read_input(Cursor::new(content), None, |_| true).unwrap();
+        assert!(matches!(input, Input::Buffered(_)));
+    }
+
+    #[test]
+    fn test_spooled_file_is_removed_on_drop() {
+        let content = "dn: cn=a\n".repeat(1_000);
+        let Input::Spooled(file) = read_input(Cursor::new(content), Some(10), |_| true).unwrap()
+        else {
+            panic!("expected spooled input");
+        };
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists());
+    }
+}
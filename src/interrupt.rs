@@ -0,0 +1,62 @@
+//! Ctrl-C and termination signals as a flag that long operations check.
+//!
+//! The default action of SIGINT, SIGTERM and SIGHUP ends the process on the
+//! spot, which leaves the terminal in raw mode and the alternate screen. With
+//! [`install`] they set a process-wide flag instead: the viewer quits at its
+//! next event and restores the terminal, and index building, searches and
+//! statistics stop early. A second signal while the flag is set still ends
+//! the process right away, for when something doesn't check the flag.
+//!
+//! In raw mode Ctrl-C arrives as a key rather than as SIGINT, and the viewer
+//! quits on that key itself.
+
+use crate::error::{Result, XtvError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once, OnceLock};
+
+/// Exit code after an interruption, as shells report for SIGINT
+pub const EXIT_CODE: i32 = 130;
+
+fn flag() -> &'static Arc<AtomicBool> {
+    static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    FLAG.get_or_init(|| Arc::new(AtomicBool::new(false)))
+}
+
+/// Catch SIGINT, SIGTERM and SIGHUP from now on. Safe to call more than once.
+///
+/// Does nothing on platforms without Unix signals.
+pub fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        #[cfg(unix)]
+        {
+            use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+            use signal_hook::flag;
+            for signal in [SIGINT, SIGTERM, SIGHUP] {
+                // The shutdown is registered first, so it only sees the flag
+                // set by an earlier signal
+                let result =
+                    flag::register_conditional_shutdown(signal, EXIT_CODE, Arc::clone(flag()))
+                        .and_then(|_| flag::register(signal, Arc::clone(flag())));
+                if let Err(e) = result {
+                    log::warn!("Cannot handle signal {}: {}", signal, e);
+                }
+            }
+        }
+    });
+}
+
+/// Whether a signal arrived
+pub fn is_interrupted() -> bool {
+    flag().load(Ordering::Relaxed)
+}
+
+/// Fails with `XtvError::Interrupted` once interrupted, for use with `?` in
+/// loops
+pub fn check() -> Result<()> {
+    if is_interrupted() {
+        Err(XtvError::Interrupted)
+    } else {
+        Ok(())
+    }
+}
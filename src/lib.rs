@@ -4,9 +4,16 @@
 
 pub mod cli;
 pub mod config;
+pub mod convert;
 pub mod error;
+pub mod input;
+pub mod interrupt;
+pub mod logging;
 pub mod parser;
+pub mod query;
+pub mod timing;
 pub mod tree;
 pub mod ui;
+pub mod util;
 
 pub use error::{Result, XtvError};
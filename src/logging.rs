@@ -0,0 +1,78 @@
+//! The debug log written with `--log-file` or `[debug] log_file`.
+//!
+//! stderr is unusable while the TUI owns the terminal, so diagnostics such as
+//! streaming cache misses, seeks and read durations go through the `log`
+//! macros to a file instead. Without a log file no logger is installed and
+//! the macros reduce to a check of the maximum level, which stays off.
+
+use crate::error::{Result, XtvError};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+
+/// Writes every record as one line, appended to the log file
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let line = format!(
+            "{} {:<5} [{}] {}: {}\n",
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"),
+            record.level(),
+            thread::current().name().unwrap_or("worker"),
+            record.target(),
+            record.args()
+        );
+        // Written unbuffered, so the log is complete up to a hang or crash
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Append debug records to the file at `path` from now on
+pub fn init(path: &Path) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| XtvError::Config(format!("Failed to open log file {:?}: {}", path, e)))?;
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }))
+    .map_err(|e| XtvError::Config(e.to_string()))?;
+    log::set_max_level(LevelFilter::Debug);
+    log::info!("xtv {} started", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_appended_to_the_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        init(file.path()).unwrap();
+        log::debug!("Cache miss for node {}", 7);
+
+        let log = std::fs::read_to_string(file.path()).unwrap();
+        assert!(log.contains("INFO "));
+        assert!(log.contains("xtv::logging: xtv"));
+        assert!(log.contains("DEBUG"));
+        assert!(log.contains("xtv::logging::tests: Cache miss for node 7"));
+        // Only one logger can be installed
+        assert!(init(file.path()).is_err());
+    }
+}
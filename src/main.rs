@@ -1,81 +1,480 @@
-use clap::Parser;
-use std::fs;
-use std::io::{self, Read};
-use xtv::{cli::Cli, config::Config, parser, tree::TreeVariant, ui::App};
+use clap::{CommandFactory, ErrorKind, Parser};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{env, fs};
+use tempfile::NamedTempFile;
+use xtv::cli::{Cli, Command, ConfigCommand};
+use xtv::config::{ColorMode, Config, Overrides};
+use xtv::input::{Input, read_input};
+use xtv::interrupt;
+use xtv::parser::ParserRegistry;
+use xtv::query::path::resolve_path;
+use xtv::timing::LoadTimings;
+use xtv::tree::export::{self, DocumentFormat};
+use xtv::tree::{Tree, diff};
+use xtv::ui::printer::{print_stats, print_tree};
+use xtv::ui::render_html;
+use xtv::ui::render_text::{TextLayout, TextOptions};
+use xtv::{XtvError, tree::TreeVariant, ui::App};
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    let cli = Cli::parse();
+    let json_errors = cli.error_format == "json";
+    if let Err(e) = run(cli) {
+        if json_errors {
+            eprintln!("{}", error_json(&e));
+        } else {
+            eprintln!("Error: {}", e);
+        }
+        std::process::exit(exit_code(&e));
     }
 }
 
-fn run() -> xtv::Result<()> {
-    let cli = Cli::parse();
+/// The exit code for an error, documented in the README. Usage errors exit
+/// with clap's 2.
+fn exit_code(error: &XtvError) -> i32 {
+    match error.cause() {
+        XtvError::Io(_) => 3,
+        XtvError::JsonParse(_)
+        | XtvError::YamlParse(_)
+        | XtvError::TomlParse(_)
+        | XtvError::XmlParse(_)
+        | XtvError::HtmlParse(_)
+        | XtvError::LdifParse { .. } => 4,
+        XtvError::UnsupportedFormat(_) => 5,
+        XtvError::Config(_) => 6,
+        XtvError::Interrupted => interrupt::EXIT_CODE,
+        _ => 1,
+    }
+}
 
-    // Load configuration
-    let config = Config::load_with_custom_path(cli.config.as_deref())?;
-
-    // CLI flags override config values
-    let streaming_threshold = cli
-        .streaming_threshold
-        .unwrap_or(config.streaming.threshold_bytes);
-    let streaming_enabled = config.streaming.enabled && !cli.no_streaming;
-
-    let tree_variant = if let Some(file_path) = &cli.file {
-        // Check file size to determine if we should use streaming
-        let metadata = fs::metadata(file_path)?;
-        let file_size = metadata.len();
-
-        // Determine if format is LDIF (from --format flag or file extension)
-        let is_ldif = cli
-            .format
-            .as_ref()
-            .map(|f| f.eq_ignore_ascii_case("ldif"))
-            .unwrap_or_else(|| {
-                file_path
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|s| s.eq_ignore_ascii_case("ldif"))
-                    .unwrap_or(false)
-            });
-
-        let should_stream = streaming_enabled && file_size > streaming_threshold && is_ldif;
+/// The error as a one-line JSON object for `--error-format json`
+fn error_json(error: &XtvError) -> String {
+    let cause = error.cause();
+    let kind = match exit_code(error) {
+        3 => "io",
+        4 => "parse",
+        5 => "unsupported_format",
+        6 => "config",
+        interrupt::EXIT_CODE => "interrupted",
+        _ => "other",
+    };
+    let mut object = serde_json::Map::new();
+    object.insert("kind".into(), kind.into());
+    object.insert("message".into(), cause.to_string().into());
+    if let Some(file) = error.file() {
+        object.insert("file".into(), file.display().to_string().into());
+    }
+    if let Some((line, column)) = error.location() {
+        object.insert("line".into(), line.into());
+        if let Some(column) = column {
+            object.insert("column".into(), column.into());
+        }
+    }
+    serde_json::Value::Object(object).to_string()
+}
 
-        if should_stream {
-            // Use streaming mode for large LDIF files
-            let streaming_tree = parser::ldif::build_ldif_index(file_path)?;
-            TreeVariant::Streaming(streaming_tree)
-        } else {
-            // Use in-memory parsing
-            let content = fs::read_to_string(file_path)?;
-            // Use --format if provided, otherwise detect from file extension
-            let parser = if let Some(format) = &cli.format {
-                parser::get_parser_from_format(format)?
+fn run(cli: Cli) -> xtv::Result<()> {
+    // Before indexing starts, so Ctrl-C can stop it
+    interrupt::install();
+
+    // Settings from the environment, overridden by the flags
+    let overrides = Overrides::from_env(|name| env::var(name).ok())?.then(cli.overrides());
+    let format = overrides.format.as_deref();
+
+    // Handled before loading the configuration, which may be what's broken
+    if let Some(Command::Config(command)) = &cli.command {
+        return run_config_command(command, overrides.config.clone());
+    }
+
+    // Load configuration, then put the overrides on top
+    let mut config = Config::load_with_custom_path(overrides.config.as_deref())?;
+    config.apply(&overrides)?;
+    if !config.debug.log_file.as_os_str().is_empty() {
+        xtv::logging::init(&config.debug.log_file)?;
+    }
+    // Unknown formats are rejected with the list of known ones before any
+    // file is read
+    let parsers = ParserRegistry::default();
+    if let Some(format) = format {
+        parsers.by_name(format, &config.parser)?;
+    }
+    // Printing and converting read every node, so they gain nothing from streaming
+    let converting = cli.print || cli.output.is_some() || cli.export_html.is_some();
+    let streaming_enabled = config.streaming.enabled && !converting;
+    if cli.output.is_some() && cli.files.len() > 1 {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--output converts a single FILE",
+            )
+            .exit();
+    }
+    if cli.export_html.is_some() && (cli.files.len() > 1 || cli.diff.is_some()) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--export-html exports a single FILE",
+            )
+            .exit();
+    }
+
+    if let Some([old, new]) = cli.diff.as_deref() {
+        let (old, old_format) =
+            parse_file(old, format, &parsers, &config).map_err(|e| e.in_file(old))?;
+        let (new, new_format) =
+            parse_file(new, format, &parsers, &config).map_err(|e| e.in_file(new))?;
+        if old_format != new_format {
+            return Err(XtvError::UnsupportedFormat(format!(
+                "--diff compares files of the same format, not {} and {}",
+                old_format, new_format
+            )));
+        }
+        let diff = diff::diff(&old, &new);
+        let mut app = App::new(TreeVariant::from(diff.tree), config)
+            .with_diff(diff.changes, diff.differences);
+        return app.run();
+    }
+
+    // Keeps spooled stdin on disk until the viewer exits
+    let mut _spooled_stdin = None;
+
+    // Every file opens in its own tab
+    let mut tabs = Vec::new();
+    for file_path in &cli.files {
+        let (tree_variant, timings) =
+            load_file(file_path, format, &parsers, &config, streaming_enabled)
+                .map_err(|e| e.in_file(file_path))?;
+        log::debug!("{:?}: {:?}", file_path, timings);
+        tabs.push((file_path, tree_variant, timings));
+    }
+
+    let (file_path, tree_variant, timings) = if !tabs.is_empty() {
+        let (file_path, tree_variant, timings) = tabs.remove(0);
+        (Some(file_path), tree_variant, timings)
+    } else {
+        let (tree_variant, timings, spooled) =
+            load_stdin(format, &parsers, &config, streaming_enabled)?;
+        _spooled_stdin = spooled;
+        (None, tree_variant, timings)
+    };
+
+    if cli.bench {
+        let first = std::iter::once((file_path.map(PathBuf::as_path), &tree_variant, &timings));
+        let rest = tabs
+            .iter()
+            .map(|(path, tree, timings)| (Some(path.as_path()), tree, timings));
+        return print_bench(first.chain(rest));
+    }
+
+    if let Some(output) = &cli.output {
+        return write_document(&cli, &tree_variant, output);
+    }
+
+    if let Some(html_path) = &cli.export_html {
+        let file = file_path.map(|path| (path.as_path(), overrides.format.clone()));
+        return export_html(tree_variant, config, file, cli.select.as_deref(), html_path);
+    }
+
+    if cli.print {
+        let mut out = io::stdout().lock();
+        for tree in std::iter::once(tree_variant).chain(tabs.into_iter().map(|(_, tree, _)| tree)) {
+            let root = selected_root(&tree, cli.select.as_deref())?;
+            let options = TextOptions {
+                layout: if cli.ascii_tree {
+                    TextLayout::Guides
+                } else {
+                    TextLayout::Viewer
+                },
+                full_values: cli.full_values,
+                color: match config.ui.color {
+                    ColorMode::Always => true,
+                    ColorMode::Never => false,
+                    ColorMode::Auto => io::stdout().is_terminal(),
+                },
+            };
+            let printed = if cli.stats {
+                print_stats(&mut out, &tree.tree_stats())
             } else {
-                parser::detect_parser(file_path)?
+                print_tree(&mut out, &tree, root, cli.depth, &config, &options)
             };
-            let tree = parser.parse(&content)?;
-            TreeVariant::InMemory(tree)
+            match printed {
+                // The reader, such as `head`, has seen enough
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                result => result?,
+            }
+        }
+        return Ok(());
+    }
+
+    // Run TUI
+    let history_path = config.search_history_path();
+    let view_state_dir = config.view_state_dir();
+    let mut app = App::new(tree_variant, config)
+        .with_load_timings(&timings)
+        .with_parsers(parsers);
+    if let Some(file_path) = file_path {
+        app = app.with_file(file_path, overrides.format.clone());
+    }
+    for (file_path, tree_variant, _) in tabs {
+        app = app
+            .with_tab(tree_variant)
+            .with_file(file_path, overrides.format.clone());
+    }
+    let mut app = app
+        .with_watch(cli.watch)
+        .with_search_history_file(history_path)
+        .with_view_states(view_state_dir, !cli.no_restore);
+    if let Some(path) = &cli.select {
+        app = app.with_selection(path);
+    }
+    app.run()?;
+
+    Ok(())
+}
+
+/// The node given with `--select`, or the root
+fn selected_root(tree: &TreeVariant, select: Option<&str>) -> xtv::Result<usize> {
+    match select {
+        Some(path) => {
+            resolve_path(tree, path).ok_or_else(|| XtvError::NoSuchPath(path.to_string()))
         }
+        None => Ok(tree.root_id()),
+    }
+}
+
+/// Convert the selected subtree to a document for `--output`, written to
+/// stdout for `-`
+fn write_document(cli: &Cli, tree: &TreeVariant, output: &Path) -> xtv::Result<()> {
+    let root = selected_root(tree, cli.select.as_deref())?;
+    let format = match &cli.output_format {
+        Some(name) => DocumentFormat::from_name(name)
+            .ok_or_else(|| XtvError::UnsupportedFormat(name.clone()))?,
+        None => DocumentFormat::for_path(output),
+    };
+    let document = export::to_document(tree, root, format)?;
+    if output.as_os_str() == "-" {
+        io::stdout().lock().write_all(document.as_bytes())?;
     } else {
-        // Reading from stdin - always use in-memory mode
-        let mut content = String::new();
-        io::stdin().read_to_string(&mut content)?;
+        fs::write(output, document).map_err(|e| XtvError::from(e).in_file(output))?;
+    }
+    Ok(())
+}
 
-        let parser = if let Some(format) = &cli.format {
-            parser::get_parser_from_format(format)?
-        } else {
-            parser::detect_parser_from_content(&content)?
+/// Write the view as an HTML page for `--export-html`, with `select`
+/// selected. `file` is the file the tree was read from and its `--format`.
+fn export_html(
+    tree: TreeVariant,
+    config: Config,
+    file: Option<(&Path, Option<String>)>,
+    select: Option<&str>,
+    html_path: &Path,
+) -> xtv::Result<()> {
+    // Fail on a path that selects nothing, which the viewer would ignore
+    selected_root(&tree, select)?;
+    let mut app = App::new(tree, config);
+    if let Some((file_path, format)) = file {
+        app = app.with_file(file_path, format);
+    }
+    if let Some(path) = select {
+        app = app.with_selection(path);
+    }
+    let (html, truncated) = app.view_as_html();
+    if truncated {
+        eprintln!("Warning: {}", render_html::truncation_warning());
+    }
+    fs::write(html_path, html).map_err(|e| XtvError::from(e).in_file(html_path))?;
+    Ok(())
+}
+
+/// Print the load timings of each file as a line of JSON for `--bench`
+fn print_bench<'a>(
+    loaded: impl Iterator<Item = (Option<&'a Path>, &'a TreeVariant, &'a LoadTimings)>,
+) -> xtv::Result<()> {
+    let mut out = io::stdout().lock();
+    for (file_path, tree, timings) in loaded {
+        let mut json = timings.to_json(tree);
+        json["file"] = match file_path {
+            Some(path) => path.display().to_string().into(),
+            None => "-".into(),
         };
+        writeln!(out, "{}", json)?;
+    }
+    Ok(())
+}
+
+/// Run `xtv config init`, `path` or `validate` on the configuration at
+/// `custom_path` (from --config), or the XDG path
+fn run_config_command(command: &ConfigCommand, custom_path: Option<PathBuf>) -> xtv::Result<()> {
+    let config_path = custom_path.or_else(Config::xdg_config_path).ok_or_else(|| {
+        XtvError::Config("Cannot find the config directory: set XDG_CONFIG_HOME or HOME".into())
+    });
+
+    match command {
+        ConfigCommand::Init { force } => {
+            let path = config_path?;
+            if path.exists() && !force {
+                return Err(XtvError::Config(format!(
+                    "{} already exists (use --force to overwrite it)",
+                    path.display()
+                )));
+            }
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&path, Config::sample_config())?;
+            println!("Wrote {}", path.display());
+        }
+        ConfigCommand::Path => println!("{}", config_path?.display()),
+        ConfigCommand::Validate { file } => {
+            let path = match file {
+                Some(file) => file.clone(),
+                None => config_path?,
+            };
+            let content = fs::read_to_string(&path).map_err(|e| {
+                XtvError::Config(format!("Failed to read config file {:?}: {}", path, e))
+            })?;
+            let problems = Config::check(&content);
+            if !problems.is_empty() {
+                for problem in &problems {
+                    println!("{}: {}", path.display(), problem);
+                }
+                return Err(XtvError::Config(format!(
+                    "{} problem(s) in {}",
+                    problems.len(),
+                    path.display()
+                )));
+            }
+            println!("{}: OK", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Load a file, streaming it if it is large and its format supports that,
+/// and time each step
+fn load_file(
+    file_path: &Path,
+    format: Option<&str>,
+    parsers: &ParserRegistry,
+    config: &Config,
+    streaming_enabled: bool,
+) -> xtv::Result<(TreeVariant, LoadTimings)> {
+    // Pick the parser from --format, the extension, or the file's content
+    let start = Instant::now();
+    let parser = parsers.for_file(file_path, format, &config.parser)?;
+    let detect = start.elapsed();
+
+    // Stream large files in formats that support it
+    let file_size = fs::metadata(file_path)?.len();
+    let threshold = config.streaming.threshold_for(parser.format_name());
+    let should_stream = streaming_enabled && parser.supports_streaming() && file_size > threshold;
+    log::debug!(
+        "{:?} has {} bytes, streaming threshold {}: {}",
+        file_path,
+        file_size,
+        threshold,
+        if should_stream {
+            "streaming"
+        } else {
+            "in memory"
+        }
+    );
 
+    let mut read = Duration::ZERO;
+    let start = Instant::now();
+    let tree_variant = if should_stream {
+        let streaming_tree = parser
+            .build_index(file_path)?
+            .with_prefetch_window(config.streaming.prefetch_window)
+            .with_background_loading()?;
+        TreeVariant::Streaming(Box::new(streaming_tree))
+    } else {
+        // Use in-memory parsing
+        let content = fs::read_to_string(file_path)?;
+        read = start.elapsed();
         let tree = parser.parse(&content)?;
-        TreeVariant::InMemory(tree)
+        TreeVariant::from(tree)
     };
+    let timings = LoadTimings {
+        format: parser.format_name(),
+        bytes: file_size,
+        streaming: should_stream,
+        detect,
+        read,
+        parse: start.elapsed() - read,
+        nodes: tree_variant.node_count(),
+    };
+    Ok((tree_variant, timings))
+}
 
-    // Run TUI
-    let mut app = App::new(tree_variant);
-    app.run()?;
+/// Load standard input. Large input in a streamable format is spooled to a
+/// temporary file and loaded like a file, which is returned to keep it on
+/// disk while the tree is in use.
+fn load_stdin(
+    format: Option<&str>,
+    parsers: &ParserRegistry,
+    config: &Config,
+    streaming_enabled: bool,
+) -> xtv::Result<(TreeVariant, LoadTimings, Option<NamedTempFile>)> {
+    let can_stream = |prefix: &str| {
+        let parser = match format {
+            Some(format) => parsers.by_name(format, &config.parser),
+            None => parsers.by_content(prefix, &config.parser),
+        };
+        parser.is_ok_and(|p| p.supports_streaming())
+    };
+    // Without --format, LDIF is the only streamable format detectable from content
+    let threshold = config.streaming.threshold_for(format.unwrap_or("ldif"));
+    let threshold = streaming_enabled.then_some(threshold);
 
-    Ok(())
+    let start = Instant::now();
+    let input = read_input(io::stdin().lock(), threshold, can_stream)?;
+    let read = start.elapsed();
+    let (tree_variant, timings, spooled) = match input {
+        Input::Spooled(file) => {
+            let (tree_variant, mut timings) =
+                load_file(file.path(), format, parsers, config, streaming_enabled)?;
+            // Reading stdin is what spooled it
+            timings.read += read;
+            (tree_variant, timings, Some(file))
+        }
+        Input::Buffered(content) => {
+            let start = Instant::now();
+            let parser = match format {
+                Some(format) => parsers.by_name(format, &config.parser)?,
+                None => parsers.by_content(&content, &config.parser)?,
+            };
+            let detect = start.elapsed();
+            log::debug!("Reading stdin as {}", parser.format_name());
+
+            let start = Instant::now();
+            let tree_variant = TreeVariant::from(parser.parse(&content)?);
+            let timings = LoadTimings {
+                format: parser.format_name(),
+                bytes: content.len() as u64,
+                streaming: false,
+                detect,
+                read,
+                parse: start.elapsed(),
+                nodes: tree_variant.node_count(),
+            };
+            (tree_variant, timings, None)
+        }
+    };
+    log::debug!("stdin: {:?}", timings);
+    Ok((tree_variant, timings, spooled))
+}
+
+/// Parse a whole file into memory, returning the tree and its format
+fn parse_file(
+    path: &Path,
+    format: Option<&str>,
+    parsers: &ParserRegistry,
+    config: &Config,
+) -> xtv::Result<(Tree, &'static str)> {
+    let parser = parsers.for_file(path, format, &config.parser)?;
+    let content = fs::read_to_string(path)?;
+    Ok((parser.parse(&content)?, parser.format_name()))
 }
@@ -0,0 +1,136 @@
+//! Splitting large files of independent records into chunks that parse on
+//! several threads.
+//!
+//! JSON Lines records are lines and LDIF entries end at blank lines, so these
+//! files split at record boundaries into chunks that parse on their own. The
+//! parsers parse the chunks on rayon's thread pool into trees of their own and
+//! stitch them together in order with
+//! [`Tree::append_subtrees`](crate::tree::Tree::append_subtrees), which gives
+//! the same tree as parsing the whole file on one thread.
+
+use rayon::prelude::*;
+
+/// Smaller files parse on one thread faster than they split
+pub(crate) const MIN_PARALLEL_BYTES: usize = 8 * 1024 * 1024;
+
+/// Smallest chunk worth a task of its own
+const MIN_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// A part of a file that starts at a record boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Chunk<'a> {
+    pub text: &'a str,
+    /// Lines of the file before the chunk
+    pub line_offset: usize,
+}
+
+impl<'a> Chunk<'a> {
+    /// All of `content`
+    pub(crate) fn whole(content: &'a str) -> Self {
+        Self {
+            text: content,
+            line_offset: 0,
+        }
+    }
+}
+
+/// `content` in a few chunks per thread of rayon's pool, or in one chunk if
+/// it is smaller than [`MIN_PARALLEL_BYTES`] or there is one thread.
+///
+/// `boundary(content, offset)` is the first record boundary at or after
+/// `offset`, see [`line_boundary`] and [`blank_line_boundary`].
+pub(crate) fn split_for_threads(
+    content: &str,
+    boundary: fn(&str, usize) -> usize,
+) -> Vec<Chunk<'_>> {
+    let threads = rayon::current_num_threads();
+    if content.len() < MIN_PARALLEL_BYTES || threads == 1 {
+        return vec![Chunk::whole(content)];
+    }
+    split(
+        content,
+        (threads * 4).min(content.len() / MIN_CHUNK_BYTES),
+        boundary,
+    )
+}
+
+/// `content` in at most `count` chunks of about equal size, each ending at a
+/// record boundary
+pub(crate) fn split(
+    content: &str,
+    count: usize,
+    boundary: fn(&str, usize) -> usize,
+) -> Vec<Chunk<'_>> {
+    let size = content.len().div_ceil(count.max(1)).max(1);
+    let mut texts = Vec::new();
+    let mut start = 0;
+    while start < content.len() {
+        let end = boundary(content, (start + size).min(content.len()));
+        texts.push(&content[start..end]);
+        start = end;
+    }
+
+    // Lines before each chunk, counted in parallel
+    let lines: Vec<usize> = texts
+        .par_iter()
+        .map(|text| text.bytes().filter(|&byte| byte == b'\n').count())
+        .collect();
+    let mut line_offset = 0;
+    texts
+        .into_iter()
+        .zip(lines)
+        .map(|(text, lines)| {
+            let chunk = Chunk { text, line_offset };
+            line_offset += lines;
+            chunk
+        })
+        .collect()
+}
+
+/// The start of the first line at or after `offset`, for JSON Lines
+pub(crate) fn line_boundary(content: &str, offset: usize) -> usize {
+    content.as_bytes()[offset..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(content.len(), |i| offset + i + 1)
+}
+
+/// The line after the first blank line at or after `offset`, for LDIF
+pub(crate) fn blank_line_boundary(content: &str, offset: usize) -> usize {
+    let bytes = content.as_bytes();
+    let mut offset = offset;
+    while offset < bytes.len() {
+        offset = line_boundary(content, offset);
+        match &bytes[offset..] {
+            [b'\n', ..] => return offset + 1,
+            [b'\r', b'\n', ..] => return offset + 2,
+            _ => {}
+        }
+    }
+    content.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(chunks: Vec<Chunk<'_>>) -> Vec<(&str, usize)> {
+        chunks
+            .into_iter()
+            .map(|chunk| (chunk.text, chunk.line_offset))
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks_end_at_record_boundaries() {
+        assert_eq!(
+            texts(split("a\nbb\nccc\n\nd\n\r\ne", 4, line_boundary)),
+            [("a\nbb\n", 0), ("ccc\n\n", 2), ("d\n\r\ne", 4)]
+        );
+        assert_eq!(
+            texts(split("a\nb\n\r\nc\nd\n\ne\n", 8, blank_line_boundary)),
+            [("a\nb\n\r\n", 0), ("c\nd\n\n", 3), ("e\n", 6)]
+        );
+        assert_eq!(texts(split("", 4, line_boundary)), []);
+    }
+}
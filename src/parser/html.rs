@@ -1,11 +1,43 @@
-use super::Parser;
+use super::{Parser, ParserFormat, add_virtual_attributes_node};
 use crate::error::Result;
 use crate::tree::{Tree, TreeNode};
 use ego_tree::NodeRef;
-use scraper::{Html, node::Node};
+use scraper::{
+    Html,
+    node::{Doctype, Node},
+};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
-pub struct HtmlParser;
+/// Options of the HTML parser, from `[parser.html]` in the config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HtmlOptions {
+    /// Leave out `<script>` and `<style>` elements along with their code
+    #[serde(default)]
+    pub skip_scripts: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HtmlParser {
+    options: HtmlOptions,
+}
+
+impl HtmlParser {
+    pub fn new(options: HtmlOptions) -> Self {
+        Self { options }
+    }
+}
+
+/// HTML files, and markup with an HTML doctype or `<html>` tag, which is
+/// tried before XML
+pub(super) const FORMAT: ParserFormat = ParserFormat::new("html", |options| {
+    Box::new(HtmlParser::new(options.html.clone()))
+})
+.with_aliases(&["htm"])
+.with_extensions(&["html", "htm"])
+.with_probe(|content| {
+    content.starts_with('<') && (content.contains("<!DOCTYPE html") || content.contains("<html"))
+});
 
 impl Parser for HtmlParser {
     fn parse(&self, content: &str) -> Result<Tree> {
@@ -13,17 +45,22 @@ impl Parser for HtmlParser {
         let document = Html::parse_document(content);
 
         // Create tree with root node
-        let mut tree = Tree::new(TreeNode::new("root", "root"));
+        let mut tree = Tree::new(TreeNode::new("root", "root")).with_format(self.format_name());
         let root_id = tree.root_id();
 
-        // Traverse DOM tree recursively from root element
-        for child in document.root_element().children() {
-            traverse_node(&mut tree, root_id, child);
+        // Traverse from the document, so the doctype, comments around the
+        // `<html>` element and the element itself with its attributes are kept
+        for child in document.tree.root().children() {
+            traverse_node(&mut tree, root_id, child, &self.options);
         }
 
         Ok(tree)
     }
 
+    fn format_name(&self) -> &'static str {
+        "html"
+    }
+
     fn can_parse(&self, file_path: &Path) -> bool {
         file_path
             .extension()
@@ -33,81 +70,77 @@ impl Parser for HtmlParser {
     }
 }
 
-fn traverse_node(tree: &mut Tree, parent_id: usize, node: NodeRef<Node>) {
-    match node.value() {
-        Node::Element(element) => {
-            let tag_name = element.name();
-            let mut elem_node = TreeNode::new(tag_name, "element");
-
-            // Collect attributes
-            for (key, value) in element.attrs() {
-                elem_node.add_attribute(key, value);
+/// Add `node` and its descendants below `parent_id`.
+///
+/// Uses an explicit stack instead of recursion so that deeply nested markup
+/// cannot overflow the call stack.
+fn traverse_node(tree: &mut Tree, parent_id: usize, node: NodeRef<Node>, options: &HtmlOptions) {
+    let mut stack = vec![(parent_id, node)];
+
+    while let Some((parent_id, node)) = stack.pop() {
+        match node.value() {
+            Node::Element(element) => {
+                let tag_name = element.name();
+                if options.skip_scripts && matches!(tag_name, "script" | "style") {
+                    continue;
+                }
+                let mut elem_node = TreeNode::new(tag_name, "element");
+
+                // Collect attributes
+                for (key, value) in element.attrs() {
+                    elem_node.add_attribute(key, value);
+                }
+
+                // Clone attributes and add node to tree
+                let attributes = elem_node.attributes.clone();
+                let elem_id = tree.add_child_node(parent_id, elem_node);
+
+                // The children are added later from the stack, so the virtual
+                // attributes node comes first
+                add_virtual_attributes_node(tree, elem_id, &attributes);
+
+                // Push children in reverse so they are added in document order
+                stack.extend(node.children().rev().map(|child| (elem_id, child)));
             }
 
-            // Clone attributes and add node to tree
-            let attributes = elem_node.attributes.clone();
-            let elem_id = tree.add_child_node(parent_id, elem_node);
-
-            // Create virtual attributes node if there are attributes
-            if let Some(virtual_id) = create_virtual_attributes_node(tree, &attributes) {
-                tree.get_node_mut(elem_id)
-                    .unwrap()
-                    .children
-                    .insert(0, virtual_id);
+            Node::Text(text) => {
+                let trimmed = text.text.trim();
+                if !trimmed.is_empty() {
+                    let mut text_node = TreeNode::new("text", "text");
+                    text_node.add_attribute("content", trimmed);
+                    tree.add_child_node(parent_id, text_node);
+                }
             }
 
-            // Add element as child to parent
-
-            // Recursively process children
-            for child in node.children() {
-                traverse_node(tree, elem_id, child);
+            Node::Comment(comment) => {
+                let mut comment_node = TreeNode::new("comment", "comment");
+                comment_node.add_attribute("content", &comment.comment);
+                tree.add_child_node(parent_id, comment_node);
             }
-        }
 
-        Node::Text(text) => {
-            let trimmed = text.text.trim();
-            if !trimmed.is_empty() {
-                let mut text_node = TreeNode::new("text", "text");
-                text_node.add_attribute("content", trimmed);
-                tree.add_child_node(parent_id, text_node);
+            Node::Doctype(doctype) => {
+                let mut doctype_node = TreeNode::new("doctype", "doctype");
+                doctype_node.add_attribute("content", doctype_declaration(doctype));
+                tree.add_child_node(parent_id, doctype_node);
             }
-        }
 
-        Node::Comment(comment) => {
-            let mut comment_node = TreeNode::new("comment", "comment");
-            comment_node.add_attribute("content", &comment.comment);
-            tree.add_child_node(parent_id, comment_node);
+            // Skip other node types (Document, ProcessingInstruction, etc.)
+            _ => {}
         }
-
-        // Skip other node types (Document, Doctype, ProcessingInstruction, etc.)
-        _ => {}
     }
 }
 
-fn create_virtual_attributes_node(
-    tree: &mut Tree,
-    attributes: &[crate::tree::node::Attribute],
-) -> Option<usize> {
-    if attributes.is_empty() {
-        return None;
-    }
-
-    let virtual_node = TreeNode::new("@attributes", TreeNode::VIRTUAL_ATTRIBUTES_TYPE);
-
-    // Sort attributes alphanumerically by key
-    let mut sorted_attrs = attributes.to_vec();
-    sorted_attrs.sort_by(|a, b| a.key.cmp(&b.key));
-
-    // Add the virtual node first to get its ID
-    let virtual_id = tree.add_node(virtual_node);
-
-    for attr in sorted_attrs {
-        let mut attr_node = TreeNode::new(&attr.key, TreeNode::ATTRIBUTE_TYPE);
-        attr_node.add_attribute("value", &attr.value);
-        tree.add_child_node(virtual_id, attr_node);
+/// The declaration of a doctype after `<!DOCTYPE`, e.g. `html` or
+/// `html PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd"`
+fn doctype_declaration(doctype: &Doctype) -> String {
+    let mut declaration = doctype.name().to_string();
+    match (doctype.public_id(), doctype.system_id()) {
+        ("", "") => {}
+        ("", system) => declaration.push_str(&format!(" SYSTEM \"{}\"", system)),
+        (public, "") => declaration.push_str(&format!(" PUBLIC \"{}\"", public)),
+        (public, system) => declaration.push_str(&format!(" PUBLIC \"{}\" \"{}\"", public, system)),
     }
-
-    Some(virtual_id)
+    declaration
 }
 
 #[cfg(test)]
@@ -117,7 +150,7 @@ mod tests {
     #[test]
     fn test_parse_simple_html() {
         let html = r#"<html><body><p>Hello World</p></body></html>"#;
-        let parser = HtmlParser;
+        let parser = HtmlParser::default();
         let tree = parser.parse(html).unwrap();
         assert!(tree.node_count() > 0);
     }
@@ -125,41 +158,98 @@ mod tests {
     #[test]
     fn test_parse_html_with_attributes() {
         let html = r#"<div id="container" class="wrapper"><p>Content</p></div>"#;
-        let parser = HtmlParser;
+        let parser = HtmlParser::default();
         let tree = parser.parse(html).unwrap();
         assert!(tree.node_count() > 2);
     }
 
+    /// The ID of the first node with `label`, in document order
+    fn find(tree: &Tree, label: &str) -> usize {
+        (0..tree.node_count())
+            .find(|&id| tree.get_node(id).unwrap().label == label)
+            .unwrap()
+    }
+
     #[test]
     fn test_virtual_attributes_node_created() {
         let html = r#"<div id="test" class="example"></div>"#;
-        let parser = HtmlParser;
+        let parser = HtmlParser::default();
         let tree = parser.parse(html).unwrap();
 
-        // Find a div element - it should have attributes
-        let root = tree.get_node(0).unwrap();
-        if !root.children.is_empty() {
-            let div = tree.get_node(root.children[0]).unwrap();
+        let div_id = find(&tree, "div");
+        let div = tree.get_node(div_id).unwrap();
+        let virtual_id = div.children[0];
+        let virtual_node = tree.get_node(virtual_id).unwrap();
+        assert_eq!(virtual_node.node_type, "@attributes");
+
+        // Both levels know their parents, which the path bar walks up
+        assert_eq!(virtual_node.parent_id, Some(div_id));
+        let labels: Vec<&str> = virtual_node
+            .children
+            .iter()
+            .map(|&id| {
+                let attribute = tree.get_node(id).unwrap();
+                assert_eq!(attribute.parent_id, Some(virtual_id));
+                attribute.label.as_str()
+            })
+            .collect();
+        assert_eq!(labels, ["class", "id"]);
+    }
 
-            // First child should be @attributes if div has attributes
-            if !div.children.is_empty() {
-                let first_child = tree.get_node(div.children[0]).unwrap();
-                assert_eq!(first_child.node_type, "@attributes");
-            }
+    #[test]
+    fn test_full_page_keeps_doctype_html_attributes_and_head() {
+        let html = r#"<!DOCTYPE html>
+<html lang="en" data-theme="dark">
+<head><meta charset="utf-8"><title>Hello</title><link rel="icon" href="x.png"></head>
+<body><p>Hi</p></body>
+</html>"#;
+        let tree = HtmlParser::default().parse(html).unwrap();
+        let root = tree.get_node(tree.root_id()).unwrap();
+        let top: Vec<&str> = root
+            .children
+            .iter()
+            .map(|&id| tree.get_node(id).unwrap().label.as_str())
+            .collect();
+        assert_eq!(top, ["doctype", "html"]);
+        let doctype = tree.get_node(root.children[0]).unwrap();
+        assert_eq!(doctype.attributes[0].value, "html");
+
+        assert_eq!(
+            tree.find_by_path(&["html", "@attributes", "lang"]),
+            Some(find(&tree, "lang"))
+        );
+        let title = tree
+            .find_by_path(&["html", "head", "title", "text"])
+            .unwrap();
+        assert_eq!(tree.get_node(title).unwrap().attributes[0].value, "Hello");
+        for label in ["meta", "link"] {
+            let head = tree.find_by_path(&["html", "head"]).unwrap();
+            assert_eq!(tree.get_parent(find(&tree, label)), Some(head));
         }
     }
 
+    #[test]
+    fn test_doctype_declaration_with_identifiers() {
+        let html = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd"><p>x</p>"#;
+        let tree = HtmlParser::default().parse(html).unwrap();
+        let doctype = tree.get_node(find(&tree, "doctype")).unwrap();
+        assert_eq!(
+            doctype.attributes[0].value,
+            r#"html PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd""#
+        );
+    }
+
     #[test]
     fn test_void_elements() {
         let html = r#"<div><img src="test.jpg" alt="Test"><br></div>"#;
-        let parser = HtmlParser;
+        let parser = HtmlParser::default();
         let tree = parser.parse(html).unwrap();
         assert!(tree.node_count() > 0);
     }
 
     #[test]
     fn test_can_parse_html_extension() {
-        let parser = HtmlParser;
+        let parser = HtmlParser::default();
         assert!(parser.can_parse(Path::new("test.html")));
         assert!(parser.can_parse(Path::new("test.HTML")));
         assert!(parser.can_parse(Path::new("test.htm")));
@@ -175,11 +265,29 @@ mod tests {
 
             </div>
         "#;
-        let parser = HtmlParser;
+        let parser = HtmlParser::default();
         let tree = parser.parse(html).unwrap();
 
         // Should not have whitespace-only text nodes
         // Just verify it parses successfully
         assert!(tree.node_count() > 0);
     }
+
+    #[test]
+    fn test_skip_scripts() {
+        let html = "<html><head><style>p {}</style><script>run()</script></head><body><p>Hi</p></body></html>";
+        let labels = |parser: HtmlParser| -> Vec<String> {
+            let tree = parser.parse(html).unwrap();
+            (0..tree.node_count())
+                .filter_map(|id| tree.get_node(id))
+                .map(|node| node.label.to_string())
+                .collect()
+        };
+
+        assert!(labels(HtmlParser::default()).contains(&"script".to_string()));
+        let skipped = labels(HtmlParser::new(HtmlOptions { skip_scripts: true }));
+        assert!(!skipped.contains(&"script".to_string()));
+        assert!(!skipped.contains(&"style".to_string()));
+        assert!(skipped.contains(&"p".to_string()));
+    }
 }
@@ -1,19 +1,30 @@
-use super::Parser;
+use super::span::LineIndex;
+use super::{Parser, ParserFormat, Serializer, export_value, value_node};
 use crate::error::Result;
-use crate::tree::{Tree, TreeNode};
+use crate::tree::{Tree, TreeNode, TreeVariant, ValueKind};
+use serde::Deserialize;
 use serde_json::Value;
 use std::path::Path;
 
 pub struct JsonParser;
 
+/// JSON files, and content starting with an object or array
+pub(super) const FORMAT: ParserFormat = ParserFormat::new("json", |_| Box::new(JsonParser))
+    .with_extensions(&["json"])
+    .with_probe(|content| content.starts_with('{') || content.starts_with('['));
+
 impl Parser for JsonParser {
     fn parse(&self, content: &str) -> Result<Tree> {
-        let value: Value = serde_json::from_str(content)?;
-        let mut tree = Tree::new(TreeNode::new("root", "root"));
+        let value = parse_value(content)?;
+        // The root stands for a top-level array or object
+        let root = TreeNode::new("root", if value.is_array() { "array" } else { "root" });
+        let mut tree =
+            Tree::with_capacity(root, estimate_nodes(content)).with_format(self.format_name());
         let root_id = tree.root_id();
+        let scalar = !value.is_object() && !value.is_array();
 
         // Build tree from JSON value - handle top level specially
-        match &value {
+        match value {
             Value::Object(map) => {
                 // Add object fields directly to root
                 for (key, child_value) in map {
@@ -22,19 +33,28 @@ impl Parser for JsonParser {
             }
             Value::Array(arr) => {
                 // Add array items directly to root
-                for (index, item) in arr.iter().enumerate() {
-                    convert_value(&mut tree, root_id, item, &format!("[{}]", index));
+                for (index, item) in arr.into_iter().enumerate() {
+                    convert_value(&mut tree, root_id, item, format!("[{}]", index));
                 }
             }
             _ => {
                 // For scalar values at top level, add them as a child
-                convert_value(&mut tree, root_id, &value, "value");
+                convert_value(&mut tree, root_id, value, "value".to_string());
             }
         }
 
+        // The root stands for a top-level object or array
+        let top = if scalar { root_id + 1 } else { root_id };
+        let start = content.len() - content.trim_start().len();
+        record_spans(&mut tree, top, content, start, &mut LineIndex::new(content));
+
         Ok(tree)
     }
 
+    fn format_name(&self) -> &'static str {
+        "json"
+    }
+
     fn can_parse(&self, file_path: &Path) -> bool {
         file_path
             .extension()
@@ -44,55 +64,245 @@ impl Parser for JsonParser {
     }
 }
 
-fn convert_value(tree: &mut Tree, parent_id: usize, value: &Value, key: &str) {
-    match value {
-        Value::Object(map) => {
-            // Create a node for this object
-            let mut node = TreeNode::new(key, "object");
+impl Serializer for JsonParser {
+    fn serialize(&self, tree: &TreeVariant, node_id: usize) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&export_value(tree, node_id)?)?)
+    }
+}
 
-            // Add attribute for object size
-            node.add_attribute("size", format!("{} fields", map.len()));
+/// Roughly how many nodes the JSON values in `content` make: one for every
+/// value after a comma and every first value in an object or array.
+///
+/// Commas and brackets inside strings are counted as well, so this may
+/// overestimate, but never by more than the length of the text.
+pub(super) fn estimate_nodes(content: &str) -> usize {
+    1 + content
+        .bytes()
+        .filter(|byte| matches!(byte, b'{' | b'[' | b','))
+        .count()
+}
 
-            let node_id = tree.add_child_node(parent_id, node);
+/// Parse JSON text without serde_json's nesting limit.
+///
+/// The deserializer grows its stack on the heap as needed, so deeply nested
+/// input is parsed instead of rejected or overflowing the stack.
+pub(super) fn parse_value(content: &str) -> serde_json::Result<Value> {
+    let mut deserializer = serde_json::Deserializer::from_str(content);
+    deserializer.disable_recursion_limit();
+    let value = Value::deserialize(serde_stacker::Deserializer::new(&mut deserializer))?;
+    deserializer.end()?;
+    Ok(value)
+}
 
-            // Recursively add children
-            for (child_key, child_value) in map {
-                convert_value(tree, node_id, child_value, child_key);
+/// Add `value` and its descendants below `parent_id`.
+///
+/// Uses an explicit stack instead of recursion so that deeply nested input
+/// cannot overflow the call stack. The value is consumed as it is converted,
+/// which also keeps nested containers from being dropped recursively.
+pub(super) fn convert_value(tree: &mut Tree, parent_id: usize, value: Value, key: String) {
+    let mut stack = vec![(parent_id, key, value)];
+
+    while let Some((parent_id, key, value)) = stack.pop() {
+        match value {
+            Value::Object(map) => {
+                // Create a node for this object, with room for its members
+                let mut node = TreeNode::new(key, "object");
+                node.children.reserve_exact(map.len());
+
+                // Add attribute for object size
+                node.add_attribute("size", format!("{} fields", map.len()));
+
+                let node_id = tree.add_child_node(parent_id, node);
+
+                // Push children in reverse so they are added in document order
+                stack.extend(map.into_iter().rev().map(|(k, v)| (node_id, k, v)));
             }
-        }
-        Value::Array(arr) => {
-            // Create a node for this array
-            let mut node = TreeNode::new(key, "array");
-            node.add_attribute("size", format!("{} items", arr.len()));
+            Value::Array(arr) => {
+                // Create a node for this array
+                let mut node = TreeNode::new(key, "array");
+                node.children.reserve_exact(arr.len());
+                node.add_attribute("size", format!("{} items", arr.len()));
 
-            let node_id = tree.add_child_node(parent_id, node);
+                let node_id = tree.add_child_node(parent_id, node);
 
-            // Recursively add children with indices
-            for (index, item) in arr.iter().enumerate() {
-                convert_value(tree, node_id, item, &format!("[{}]", index));
+                // Push children with indices, in reverse for document order
+                stack.extend(
+                    arr.into_iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(index, item)| (node_id, format!("[{}]", index), item)),
+                );
             }
+            Value::String(s) => {
+                tree.add_child_node(parent_id, value_node(key, s, ValueKind::String));
+            }
+            Value::Number(n) => {
+                let kind = if n.is_f64() {
+                    ValueKind::Float
+                } else {
+                    ValueKind::Integer
+                };
+                tree.add_child_node(parent_id, value_node(key, n.to_string(), kind));
+            }
+            Value::Bool(b) => {
+                tree.add_child_node(
+                    parent_id,
+                    value_node(key, b.to_string(), ValueKind::Boolean),
+                );
+            }
+            Value::Null => {
+                tree.add_child_node(
+                    parent_id,
+                    value_node(key, "null".to_string(), ValueKind::Null),
+                );
+            }
+        }
+    }
+}
+
+/// Record where the JSON value starting at byte `start` of `content` and
+/// every value in it begin, with `top` the node made from that value.
+///
+/// The value must be valid JSON, as it was parsed before. Object members are
+/// matched to the children by key, so keys that occur twice point to the
+/// last occurrence, whose value the tree holds.
+pub(super) fn record_spans(
+    tree: &mut Tree,
+    top: usize,
+    content: &str,
+    start: usize,
+    lines: &mut LineIndex,
+) {
+    let bytes = content.as_bytes();
+    // Open objects and arrays: their node, whether they're objects, and the
+    // position of the next array item
+    let mut open: Vec<(Option<usize>, bool, usize)> = Vec::new();
+    let mut pos = start;
+    let mut node = Some(top);
+
+    loop {
+        // A value starts at `pos`
+        if let Some(node) = node.and_then(|id| tree.get_node_mut(id)) {
+            node.source_span = Some(lines.position(pos));
         }
-        Value::String(s) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", s.clone());
-            tree.add_child_node(parent_id, node);
+        match bytes.get(pos) {
+            Some(b'{') => open.push((node, true, 0)),
+            Some(b'[') => open.push((node, false, 0)),
+            Some(b'"') => pos = string_end(bytes, pos) - 1,
+            Some(_) => {
+                while bytes
+                    .get(pos + 1)
+                    .is_some_and(|b| !b",]}".contains(b) && !b.is_ascii_whitespace())
+                {
+                    pos += 1;
+                }
+            }
+            None => return,
         }
-        Value::Number(n) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", n.to_string());
-            tree.add_child_node(parent_id, node);
+        pos += 1;
+
+        // Find the next value, closing the containers that end before it
+        loop {
+            pos = skip_whitespace(bytes, pos);
+            let Some((container, is_object, index)) = open.last_mut() else {
+                return;
+            };
+            match bytes.get(pos) {
+                Some(b',') => pos += 1,
+                Some(b'}' | b']') => {
+                    open.pop();
+                    pos += 1;
+                }
+                Some(_) if *is_object => {
+                    let end = string_end(bytes, pos);
+                    let raw = &content[pos..end];
+                    let key = if raw.contains('\\') {
+                        serde_json::from_str(raw).unwrap_or_default()
+                    } else {
+                        raw[1..raw.len() - 1].to_string()
+                    };
+                    node = container.and_then(|id| child_with_label(tree, id, &key));
+                    // Skip the colon
+                    pos = skip_whitespace(bytes, end) + 1;
+                    pos = skip_whitespace(bytes, pos);
+                    break;
+                }
+                Some(_) => {
+                    node =
+                        container.and_then(|id| tree.get_node(id)?.children.get(*index).copied());
+                    *index += 1;
+                    break;
+                }
+                None => return,
+            }
         }
-        Value::Bool(b) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", b.to_string());
-            tree.add_child_node(parent_id, node);
+    }
+}
+
+/// The first number of a JSON document that the serializer would write
+/// differently, such as `1e2`, written as `100.0`, or an integer too big for
+/// 64 bits
+pub(crate) fn lost_on_save(content: &str) -> Option<String> {
+    let bytes = content.as_bytes();
+    let mut pos = 0;
+    while let Some(&b) = bytes.get(pos) {
+        match b {
+            b'"' => pos = string_end(bytes, pos),
+            b'-' | b'0'..=b'9' => {
+                let start = pos;
+                while bytes
+                    .get(pos)
+                    .is_some_and(|b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                {
+                    pos += 1;
+                }
+                let number = &content[start..pos];
+                let written = number
+                    .parse::<serde_json::Number>()
+                    .ok()
+                    .map(|n| n.to_string());
+                if written.as_deref() != Some(number) {
+                    return Some(format!("the number {}", number));
+                }
+            }
+            _ => pos += 1,
         }
-        Value::Null => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", "null");
-            tree.add_child_node(parent_id, node);
+    }
+    None
+}
+
+/// The offset after the string starting with the quote at `start`
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut pos = start + 1;
+    while let Some(&b) = bytes.get(pos) {
+        match b {
+            b'\\' => pos += 2,
+            b'"' => return pos + 1,
+            _ => pos += 1,
         }
     }
+    bytes.len()
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// The child of an object with the key `label`. Keys are sorted, as
+/// serde_json keeps objects in sorted maps.
+fn child_with_label(tree: &Tree, id: usize, label: &str) -> Option<usize> {
+    let children = &tree.get_node(id)?.children;
+    let label_of = |child: usize| tree.get_node(child).map_or("", |node| node.label.as_str());
+    match children.binary_search_by(|&child| label_of(child).cmp(label)) {
+        Ok(index) => Some(children[index]),
+        Err(_) => children
+            .iter()
+            .copied()
+            .find(|&child| label_of(child) == label),
+    }
 }
 
 #[cfg(test)]
@@ -118,4 +328,52 @@ mod tests {
 
         assert!(tree.node_count() > 3);
     }
+
+    #[test]
+    fn test_node_estimate() {
+        // Exact without empty containers and strings holding commas
+        let json = r#"{"a": [1, {"b": 2, "c": 3}], "d": null}"#;
+        let tree = JsonParser.parse(json).unwrap();
+        assert_eq!(estimate_nodes(json), tree.node_count());
+        assert_eq!(estimate_nodes(r#"{"a": "x, y"}"#), 3);
+    }
+
+    #[test]
+    fn test_children_keep_document_order() {
+        let json = r#"{"a": [1, {"b": 2, "c": 3}], "d": null}"#;
+        let tree = JsonParser.parse(json).unwrap();
+
+        let labels = |id| -> Vec<String> {
+            tree.get_children(id)
+                .iter()
+                .map(|&child| tree.get_node(child).unwrap().label.to_string())
+                .collect()
+        };
+        let root = tree.root_id();
+        assert_eq!(labels(root), ["a", "d"]);
+        let a = tree.get_children(root)[0];
+        assert_eq!(labels(a), ["[0]", "[1]"]);
+        assert_eq!(labels(tree.get_children(a)[1]), ["b", "c"]);
+    }
+
+    #[test]
+    fn test_nodes_know_where_they_start() {
+        let json = "{\n  \"b\": [1, {\"x\": \"a\\\"b\"}],\n  \"a\": \"ü\", \"a\": true\n}";
+        let tree = JsonParser.parse(json).unwrap();
+        let span = |path: &str| {
+            tree.get_node(tree.find_by_path_str(path).unwrap())
+                .unwrap()
+                .source_span
+        };
+
+        assert_eq!(span("."), Some((1, 1)));
+        assert_eq!(span(".b"), Some((2, 8)));
+        assert_eq!(span(".b[0]"), Some((2, 9)));
+        assert_eq!(span(".b[1].x"), Some((2, 18)));
+        // The later of two equal keys holds the value
+        assert_eq!(span(".a"), Some((3, 18)));
+
+        let tree = JsonParser.parse("  42").unwrap();
+        assert_eq!(tree.get_node(1).unwrap().source_span, Some((1, 3)));
+    }
 }
@@ -1,15 +1,63 @@
-use super::Parser;
+use super::chunks::{self, Chunk};
+use super::json::{convert_value, estimate_nodes, parse_value, record_spans};
+use super::span::LineIndex;
+use super::{Parser, ParserFormat, Serializer, export_value};
 use crate::error::Result;
-use crate::tree::{Tree, TreeNode};
-use serde_json::Value;
+use crate::tree::{Tree, TreeNode, TreeVariant};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
-pub struct JsonLinesParser;
+/// Options of the JSON Lines parser, from `[parser.jsonl]` in the config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JsonLinesOptions {
+    /// Leave out lines that aren't valid JSON instead of failing, as in
+    /// logs with the odd truncated line
+    #[serde(default)]
+    pub skip_invalid_lines: bool,
+}
 
-impl Parser for JsonLinesParser {
-    fn parse(&self, content: &str) -> Result<Tree> {
-        let mut tree = Tree::new(TreeNode::new("root", "root"));
+#[derive(Debug, Clone, Default)]
+pub struct JsonLinesParser {
+    options: JsonLinesOptions,
+}
+
+impl JsonLinesParser {
+    pub fn new(options: JsonLinesOptions) -> Self {
+        Self { options }
+    }
+
+    /// Parses the chunks on rayon's thread pool and joins their records in
+    /// order. The first invalid line fails the parse, as on one thread.
+    fn parse_chunks(&self, chunks: &[Chunk]) -> Result<Tree> {
+        let parts: Vec<Result<Tree>> = chunks
+            .par_iter()
+            .map(|chunk| self.parse_chunk(chunk))
+            .collect();
+        let mut parts = parts.into_iter();
+        let mut tree = match parts.next() {
+            Some(part) => part?,
+            None => Tree::new(TreeNode::new("root", "array")),
+        };
+        let root_id = tree.root_id();
+        for part in parts {
+            let part = part?;
+            tree.reserve(part.node_count() - 1);
+            for record in tree.append_subtrees(part) {
+                tree.attach(root_id, record);
+            }
+        }
+        Ok(tree.with_format(self.format_name()))
+    }
+
+    /// The records of one chunk under a root of their own
+    fn parse_chunk(&self, chunk: &Chunk) -> Result<Tree> {
+        let content = chunk.text;
+        // One node per line, plus the nodes of its value
+        let capacity = estimate_nodes(content) + content.lines().count();
+        let mut tree = Tree::with_capacity(TreeNode::new("root", "array"), capacity);
         let root_id = tree.root_id();
+        let mut lines = LineIndex::new(content);
 
         // Parse each line as a separate JSON value
         for (line_num, line) in content.lines().enumerate() {
@@ -21,15 +69,51 @@ impl Parser for JsonLinesParser {
             }
 
             // Parse the JSON value on this line
-            let value: Value = serde_json::from_str(trimmed)?;
+            let value = match parse_value(trimmed) {
+                Ok(value) => value,
+                Err(_) if self.options.skip_invalid_lines => continue,
+                Err(e) => return Err(e.into()),
+            };
 
             // Create a node for this line, numbered starting from 1
-            let label = format!("[{}]", line_num + 1);
-            convert_value(&mut tree, root_id, &value, &label);
+            let label = format!("[{}]", chunk.line_offset + line_num + 1);
+            let node_id = tree.node_count();
+            convert_value(&mut tree, root_id, value, label);
+            let start = trimmed.as_ptr() as usize - content.as_ptr() as usize;
+            record_spans(&mut tree, node_id, content, start, &mut lines);
         }
 
+        // Lines were counted from the start of the chunk
+        if chunk.line_offset > 0 {
+            for id in 1..tree.node_count() {
+                if let Some((line, _)) = tree
+                    .get_node_mut(id)
+                    .and_then(|node| node.source_span.as_mut())
+                {
+                    *line += chunk.line_offset;
+                }
+            }
+        }
         Ok(tree)
     }
+}
+
+/// JSON Lines files; their content is detected as JSON
+pub(super) const FORMAT: ParserFormat = ParserFormat::new("jsonl", |options| {
+    Box::new(JsonLinesParser::new(options.jsonl.clone()))
+})
+.with_aliases(&["jsonlines"])
+.with_extensions(&["jsonl"]);
+
+impl Parser for JsonLinesParser {
+    fn parse(&self, content: &str) -> Result<Tree> {
+        // Large files parse in chunks of whole lines on all cores
+        self.parse_chunks(&chunks::split_for_threads(content, chunks::line_boundary))
+    }
+
+    fn format_name(&self) -> &'static str {
+        "jsonl"
+    }
 
     fn can_parse(&self, file_path: &Path) -> bool {
         file_path
@@ -40,54 +124,18 @@ impl Parser for JsonLinesParser {
     }
 }
 
-fn convert_value(tree: &mut Tree, parent_id: usize, value: &Value, key: &str) {
-    match value {
-        Value::Object(map) => {
-            // Create a node for this object
-            let mut node = TreeNode::new(key, "object");
-
-            // Add attribute for object size
-            node.add_attribute("size", format!("{} fields", map.len()));
-
-            let node_id = tree.add_child_node(parent_id, node);
-
-            // Recursively add children
-            for (child_key, child_value) in map {
-                convert_value(tree, node_id, child_value, child_key);
-            }
-        }
-        Value::Array(arr) => {
-            // Create a node for this array
-            let mut node = TreeNode::new(key, "array");
-            node.add_attribute("size", format!("{} items", arr.len()));
-
-            let node_id = tree.add_child_node(parent_id, node);
-
-            // Recursively add children with indices
-            for (index, item) in arr.iter().enumerate() {
-                convert_value(tree, node_id, item, &format!("[{}]", index));
-            }
+impl Serializer for JsonLinesParser {
+    /// Writes the root as one line per record, anything else as a single line
+    fn serialize(&self, tree: &TreeVariant, node_id: usize) -> Result<String> {
+        if node_id != tree.root_id() {
+            return Ok(serde_json::to_string(&export_value(tree, node_id)?)?);
         }
-        Value::String(s) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", s.clone());
-            tree.add_child_node(parent_id, node);
-        }
-        Value::Number(n) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", n.to_string());
-            tree.add_child_node(parent_id, node);
-        }
-        Value::Bool(b) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", b.to_string());
-            tree.add_child_node(parent_id, node);
-        }
-        Value::Null => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", "null");
-            tree.add_child_node(parent_id, node);
+        let mut lines = String::new();
+        for record in tree.get_children(node_id) {
+            lines.push_str(&serde_json::to_string(&export_value(tree, record)?)?);
+            lines.push('\n');
         }
+        Ok(lines)
     }
 }
 
@@ -100,7 +148,7 @@ mod tests {
         let jsonl = r#"{"name": "Alice", "age": 30}
 {"name": "Bob", "age": 25}
 {"name": "Charlie", "age": 35}"#;
-        let parser = JsonLinesParser;
+        let parser = JsonLinesParser::default();
         let tree = parser.parse(jsonl).unwrap();
 
         assert!(tree.node_count() > 0);
@@ -116,7 +164,7 @@ mod tests {
 
 {"name": "Bob"}
 "#;
-        let parser = JsonLinesParser;
+        let parser = JsonLinesParser::default();
         let tree = parser.parse(jsonl).unwrap();
 
         let root = tree.get_node(tree.root_id()).unwrap();
@@ -124,6 +172,21 @@ mod tests {
         assert_eq!(root.children.len(), 2);
     }
 
+    #[test]
+    fn test_nodes_know_their_line() {
+        let jsonl = "{\"a\": 1}\n\n  {\"b\": [2]}\n";
+        let tree = JsonLinesParser::default().parse(jsonl).unwrap();
+        let span = |path: &str| {
+            tree.get_node(tree.find_by_path_str(path).unwrap())
+                .unwrap()
+                .source_span
+        };
+
+        assert_eq!(span(".[0].a"), Some((1, 7)));
+        assert_eq!(span(".[1]"), Some((3, 3)));
+        assert_eq!(span(".[1].b[0]"), Some((3, 10)));
+    }
+
     #[test]
     fn test_parse_jsonlines_different_types() {
         let jsonl = r#"{"type": "object"}
@@ -132,7 +195,7 @@ mod tests {
 42
 true
 null"#;
-        let parser = JsonLinesParser;
+        let parser = JsonLinesParser::default();
         let tree = parser.parse(jsonl).unwrap();
 
         let root = tree.get_node(tree.root_id()).unwrap();
@@ -145,7 +208,7 @@ null"#;
         let jsonl = r#"{"id": 1}
 {"id": 2}
 {"id": 3}"#;
-        let parser = JsonLinesParser;
+        let parser = JsonLinesParser::default();
         let tree = parser.parse(jsonl).unwrap();
 
         let root = tree.get_node(tree.root_id()).unwrap();
@@ -165,7 +228,7 @@ null"#;
 
     #[test]
     fn test_can_parse_jsonl_extension() {
-        let parser = JsonLinesParser;
+        let parser = JsonLinesParser::default();
         assert!(parser.can_parse(Path::new("test.jsonl")));
         assert!(parser.can_parse(Path::new("test.JSONL")));
         assert!(!parser.can_parse(Path::new("test.json")));
@@ -176,10 +239,47 @@ null"#;
     fn test_parse_nested_jsonlines() {
         let jsonl = r#"{"user": {"name": "Alice", "age": 30}, "active": true}
 {"user": {"name": "Bob", "age": 25}, "active": false}"#;
-        let parser = JsonLinesParser;
+        let parser = JsonLinesParser::default();
         let tree = parser.parse(jsonl).unwrap();
 
         // Should create a complex tree structure
         assert!(tree.node_count() > 10);
     }
+
+    #[test]
+    fn test_chunks_parse_like_the_whole_file() {
+        let jsonl = "{\"a\": [1, {\"b\": 2}]}\n\n\"x\"\n[3]\n  {\"c\": null}\n{}\n7";
+        let parser = JsonLinesParser::default();
+        let whole = parser.parse_chunks(&[Chunk::whole(jsonl)]).unwrap();
+        for count in 2..6 {
+            let chunks = chunks::split(jsonl, count, chunks::line_boundary);
+            let parts = parser.parse_chunks(&chunks).unwrap();
+            assert_eq!(format!("{:?}", parts), format!("{:?}", whole));
+        }
+
+        // The first invalid line is the error, whatever chunk it is in
+        let jsonl = "1\n{\n2\n[\n";
+        let whole = parser.parse_chunks(&[Chunk::whole(jsonl)]).unwrap_err();
+        let chunks = chunks::split(jsonl, 4, chunks::line_boundary);
+        let parts = parser.parse_chunks(&chunks).unwrap_err();
+        assert_eq!(parts.to_string(), whole.to_string());
+    }
+
+    #[test]
+    fn test_skip_invalid_lines() {
+        let jsonl = "{\"a\": 1}\n{\"a\": \n{\"a\": 3}\n";
+        assert!(JsonLinesParser::default().parse(jsonl).is_err());
+
+        let parser = JsonLinesParser::new(JsonLinesOptions {
+            skip_invalid_lines: true,
+        });
+        let tree = parser.parse(jsonl).unwrap();
+        let labels: Vec<String> = tree
+            .get_children(tree.root_id())
+            .into_iter()
+            .map(|id| tree.get_node(id).unwrap().label.to_string())
+            .collect();
+        // Records keep the number of their line
+        assert_eq!(labels, ["[1]", "[3]"]);
+    }
 }
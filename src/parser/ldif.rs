@@ -1,11 +1,24 @@
-use super::Parser;
+pub mod decode;
+pub mod schema;
+
+use super::chunks::{self, Chunk};
+use super::{Parser, ParserFormat, Serializer};
 use crate::error::{Result, XtvError};
-use crate::tree::{NodeType, Tree, TreeNode, streaming::*};
+use crate::interrupt;
+use crate::query::path::is_index_label;
+use crate::tree::export::MAX_EXPORT_NODES;
+use crate::tree::{NodeType, Tree, TreeNode, TreeVariant, streaming::*};
+use crate::util::natural_cmp;
+use decode::{Decoder, ValueDecoders};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::HashMap;
+use rayon::prelude::*;
+use schema::AttributeLabel;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 /// Maximum size for a single attribute value (1MB)
 /// Prevents memory exhaustion from extremely large attribute values
@@ -19,16 +32,171 @@ const MAX_ATTRIBUTES_PER_ENTRY: usize = 100;
 /// Prevents memory exhaustion from attributes with excessive values
 const MAX_VALUES_PER_ATTRIBUTE: usize = 1000;
 
+/// Number of entries between progress records in the debug log
+const INDEX_LOG_INTERVAL: usize = 100_000;
+
 /// Maximum total index size (100MB)
 /// Prevents unbounded memory growth during index building
 const MAX_INDEX_SIZE_BYTES: usize = 100 * 1024 * 1024; // 100MB
 
-pub struct LdifParser;
+#[derive(Debug, Clone, Default)]
+pub struct LdifParser {
+    options: LdifOptions,
+}
+
+impl LdifParser {
+    pub fn new(options: LdifOptions) -> Self {
+        Self { options }
+    }
+
+    /// Parses the entries of the chunks and builds their nodes on rayon's
+    /// thread pool, then places the entries in the DN hierarchy in order.
+    /// The first error fails the parse, as on one thread.
+    fn parse_chunks(&self, chunks: &[Chunk]) -> Result<Tree> {
+        let decoders = self.options.value_decoders();
+        let parts: Vec<Result<(Tree, Vec<String>)>> = chunks
+            .par_iter()
+            .map(|&chunk| {
+                let mut parser = LdifFileParser::new(chunk, decoders.clone());
+                let entries = parser.parse_entries()?;
+                Ok(parser.build_entries(entries))
+            })
+            .collect();
+
+        let mut tree: Option<Tree> = None;
+        // Map from DN to node ID for building hierarchy
+        let mut dn_to_node: HashMap<String, usize> = HashMap::new();
+        for part in parts {
+            let (part, dns) = part?;
+            let (tree, entries) = match &mut tree {
+                Some(tree) => {
+                    tree.reserve(part.node_count() - 1);
+                    let entries = tree.append_subtrees(part);
+                    (tree, entries)
+                }
+                None => {
+                    // The first part's entries are placed again below
+                    let tree = tree.insert(part);
+                    let root_id = tree.root_id();
+                    let entries = tree
+                        .get_node_mut(root_id)
+                        .map(|root| std::mem::take(&mut root.children))
+                        .unwrap_or_default();
+                    (tree, entries)
+                }
+            };
+            let root_id = tree.root_id();
+
+            for (entry_id, dn) in entries.into_iter().zip(dns) {
+                // Get parent DN
+                let parent_dn = get_parent_dn(&dn);
+
+                // Find parent node (only if it exists, don't create placeholders)
+                let parent_id = if let Some(ref parent) = parent_dn {
+                    // Check if parent exists in the entries we've already processed
+                    dn_to_node.get(parent).copied().unwrap_or(root_id)
+                } else {
+                    // No parent DN, attach to root
+                    root_id
+                };
+
+                // Compute RDN (relative to parent if parent exists in tree)
+                let parent_dn_for_label = if parent_id == root_id {
+                    None
+                } else {
+                    parent_dn.as_deref()
+                };
+                if let Some(entry) = tree.get_node_mut(entry_id) {
+                    entry.label = compute_rdn(&dn, parent_dn_for_label).into();
+                }
+                tree.attach(parent_id, entry_id);
+
+                // Store DN to node mapping
+                dn_to_node.insert(dn, entry_id);
+            }
+        }
+        Ok(tree.unwrap_or_else(|| Tree::new(TreeNode::new("root", "root"))))
+    }
+}
+
+/// Options for LDIF files, from `[parser.ldif]` in the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LdifOptions {
+    /// Show friendly names of well-known attributes after their key, e.g.
+    /// `Member of · membership` after `memberOf`
+    #[serde(default = "default_friendly_names")]
+    pub friendly_names: bool,
+    /// Friendly names of further attributes, or other names for built-in
+    /// ones (see [`schema`])
+    #[serde(default)]
+    pub attribute_labels: BTreeMap<String, AttributeLabel>,
+    /// Add a readable form of timestamps, SIDs, GUIDs and account flags
+    /// as a `decoded` attribute (see [`decode`])
+    #[serde(default = "default_decode_values")]
+    pub decode_values: bool,
+    /// Decoders of further attributes, or `"none"` for built-in ones
+    #[serde(default)]
+    pub decoders: BTreeMap<String, Decoder>,
+}
+
+fn default_friendly_names() -> bool {
+    true
+}
+
+fn default_decode_values() -> bool {
+    true
+}
+
+impl Default for LdifOptions {
+    fn default() -> Self {
+        Self {
+            friendly_names: default_friendly_names(),
+            attribute_labels: BTreeMap::new(),
+            decode_values: default_decode_values(),
+            decoders: BTreeMap::new(),
+        }
+    }
+}
+
+impl LdifOptions {
+    /// The decoders these options ask for
+    pub fn value_decoders(&self) -> ValueDecoders {
+        if self.decode_values {
+            ValueDecoders::new(&self.decoders)
+        } else {
+            ValueDecoders::none()
+        }
+    }
+}
+
+/// LDIF files, and content starting with `version:` or `dn:`
+pub(super) const FORMAT: ParserFormat = ParserFormat::new("ldif", |options| {
+    Box::new(LdifParser::new(options.ldif.clone()))
+})
+.with_extensions(&["ldif"])
+.with_probe(looks_like_ldif);
 
 impl Parser for LdifParser {
     fn parse(&self, content: &str) -> Result<Tree> {
-        let mut parser = LdifFileParser::new(content);
-        parser.parse()
+        // Large files parse in chunks of whole entries on all cores
+        let chunks = chunks::split_for_threads(content, chunks::blank_line_boundary);
+        Ok(self.parse_chunks(&chunks)?.with_format(self.format_name()))
+    }
+
+    fn format_name(&self) -> &'static str {
+        "ldif"
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    fn build_index(&self, file_path: &Path) -> Result<StreamingTree> {
+        build_index(
+            file_path,
+            progress_bar(file_path)?,
+            self.options.value_decoders(),
+        )
     }
 
     fn can_parse(&self, file_path: &Path) -> bool {
@@ -40,25 +208,181 @@ impl Parser for LdifParser {
     }
 }
 
+impl Serializer for LdifParser {
+    /// Writes entries followed by the entries below them, so every parent
+    /// precedes its children, and attribute nodes as attribute lines.
+    ///
+    /// Streaming trees write at most [`MAX_EXPORT_NODES`] entries.
+    fn serialize(&self, tree: &TreeVariant, node_id: usize) -> Result<String> {
+        let node = tree
+            .get_node_blocking(node_id)
+            .ok_or(XtvError::InvalidNodeId(node_id))?;
+        if node.is_attribute() {
+            return Ok(attribute_line(&node));
+        }
+        if node.is_virtual_attributes() {
+            return Ok(attribute_lines(tree, node_id).concat());
+        }
+
+        let max_entries = match tree {
+            TreeVariant::InMemory(_) => usize::MAX,
+            TreeVariant::Streaming(_) => MAX_EXPORT_NODES,
+        };
+        let mut blocks = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(id) = stack.pop() {
+            let Some(node) = tree.get_node_blocking(id) else {
+                continue;
+            };
+            let mut children = tree.get_children(id);
+            if node.node_type == "entry" {
+                if blocks.len() == max_entries {
+                    return Err(XtvError::Serialize(format!(
+                        "subtree has more than {} entries",
+                        max_entries
+                    )));
+                }
+                // The `@attributes` node comes first and holds the DN, which
+                // leads the entry
+                let has_attributes = children
+                    .first()
+                    .is_some_and(|&first| tree.get_label(first).as_deref() == Some("@attributes"));
+                let mut lines = if has_attributes {
+                    attribute_lines(tree, children.remove(0))
+                } else {
+                    Vec::new()
+                };
+                let dn = lines.iter().position(|line| line.starts_with("dn:"));
+                if let Some(dn) = dn {
+                    let dn = lines.remove(dn);
+                    lines.insert(0, dn);
+                }
+                blocks.push(lines.concat());
+            }
+            stack.extend(children.into_iter().rev());
+        }
+        Ok(blocks.join("\n"))
+    }
+}
+
+/// The lines of the attributes under an `@attributes` node
+fn attribute_lines(tree: &TreeVariant, attributes_id: usize) -> Vec<String> {
+    tree.get_children(attributes_id)
+        .into_iter()
+        .filter_map(|id| tree.get_node_blocking(id))
+        .map(|attr| attribute_line(&attr))
+        .collect()
+}
+
+/// An attribute as `key: value`, or base64 encoded as `key:: …` if the value
+/// isn't a safe string
+fn attribute_line(attr: &TreeNode) -> String {
+    use base64::{Engine as _, engine::general_purpose};
+
+    // Multi-valued attributes are labelled `mail [0]`, `mail [1]`, …
+    let key = match attr.label.rsplit_once(' ') {
+        Some((key, index)) if is_index_label(index) => key,
+        _ => attr.label.as_str(),
+    };
+    let value = attr
+        .attributes
+        .iter()
+        .find(|a| a.key == "value")
+        .map_or("", |a| a.value.as_str());
+    if attr.is_url_ref() {
+        return format!("{}:< {}\n", key, value);
+    }
+
+    let safe = value
+        .bytes()
+        .all(|b| b.is_ascii() && !matches!(b, b'\0' | b'\n' | b'\r'))
+        && !value.starts_with([' ', ':', '<'])
+        && !value.ends_with(' ');
+    if safe {
+        format!("{}: {}\n", key, value)
+    } else {
+        format!("{}:: {}\n", key, general_purpose::STANDARD.encode(value))
+    }
+}
+
+/// Check whether content looks like LDIF from its first line
+///
+/// Used for content-based format detection, e.g. when reading from stdin.
+pub fn looks_like_ldif(content: &str) -> bool {
+    let trimmed = content.trim_start();
+    trimmed.starts_with("version:") || trimmed.starts_with("dn:")
+}
+
+/// The path of a `file:` URL as used by URL references (`key:< URL`), with
+/// `%XX` escapes decoded
+///
+/// `file:///tmp/photo.jpg` and `file://localhost/tmp/photo.jpg` give
+/// `/tmp/photo.jpg`; `file:photo.jpg` gives the relative path `photo.jpg`.
+/// Other schemes and hosts give `None`.
+pub fn file_url_path(url: &str) -> Option<PathBuf> {
+    let scheme = url.get(..5)?;
+    if !scheme.eq_ignore_ascii_case("file:") {
+        return None;
+    }
+    let rest = &url[5..];
+    let path = match rest.strip_prefix("//") {
+        Some(authority_and_path) => {
+            let slash = authority_and_path.find('/')?;
+            let (host, path) = authority_and_path.split_at(slash);
+            if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+                return None;
+            }
+            path
+        }
+        None => rest,
+    };
+    if path.is_empty() {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut input = path.bytes();
+    while let Some(byte) = input.next() {
+        if byte == b'%' {
+            let hex = [input.next()?, input.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
 struct LdifFileParser<'a> {
     lines: Vec<&'a str>,
     line_num: usize,
+    /// Lines of the file before the chunk being parsed
+    line_offset: usize,
+    decoders: ValueDecoders,
 }
 
 impl<'a> LdifFileParser<'a> {
-    fn new(content: &'a str) -> Self {
+    fn new(chunk: Chunk<'a>, decoders: ValueDecoders) -> Self {
         LdifFileParser {
-            lines: content.lines().collect(),
+            lines: chunk.text.lines().collect(),
             line_num: 0,
+            line_offset: chunk.line_offset,
+            decoders,
         }
     }
 
-    fn parse(&mut self) -> Result<Tree> {
+    /// Lines of the file up to the current one
+    fn line(&self) -> usize {
+        self.line_offset + self.line_num
+    }
+
+    fn parse_entries(&mut self) -> Result<Vec<LdifEntry>> {
         let mut entries = Vec::new();
 
-        // Parse optional version line
+        // Parse optional version line, which only the file's first chunk has
         if let Some(line) = self.peek_line() {
-            if line.starts_with("version:") {
+            if self.line_offset == 0 && line.starts_with("version:") {
                 self.line_num += 1;
             }
         }
@@ -70,7 +394,7 @@ impl<'a> LdifFileParser<'a> {
             }
         }
 
-        Ok(self.build_tree(entries))
+        Ok(entries)
     }
 
     fn parse_entry(&mut self) -> Result<Option<LdifEntry>> {
@@ -89,6 +413,7 @@ impl<'a> LdifFileParser<'a> {
         }
 
         // Read logical line (handling folding)
+        let line = self.line() + 1;
         let logical_line = self.read_logical_line();
         if logical_line.is_empty() {
             return Ok(None);
@@ -97,14 +422,15 @@ impl<'a> LdifFileParser<'a> {
         // First line should be DN
         if !logical_line.starts_with("dn:") {
             return Err(XtvError::LdifParse {
-                line: self.line_num,
+                line: self.line(),
                 message: format!("Expected DN, got: {}", logical_line),
             });
         }
 
         // Parse DN value (may be base64-encoded with dn::)
-        let dn = parse_dn_value(&logical_line[3..], self.line_num)?;
+        let dn = parse_dn_value(&logical_line[3..], self.line())?;
         let mut attributes = Vec::new();
+        let mut attribute_lines = Vec::new();
 
         // Read attributes until blank line or EOF
         loop {
@@ -128,6 +454,7 @@ impl<'a> LdifFileParser<'a> {
                 }
             }
 
+            let attribute_line = self.line() + 1;
             let logical_line = self.read_logical_line();
             if logical_line.is_empty() {
                 break;
@@ -135,12 +462,18 @@ impl<'a> LdifFileParser<'a> {
 
             // Parse attribute line
             if logical_line.contains(':') {
-                let (key, value) = parse_attribute_line(&logical_line, self.line_num)?;
+                let (key, value) = parse_attribute_line(&logical_line, self.line())?;
                 attributes.push((key, value));
+                attribute_lines.push(attribute_line);
             }
         }
 
-        Ok(Some(LdifEntry { dn, attributes }))
+        Ok(Some(LdifEntry {
+            dn,
+            attributes,
+            line,
+            attribute_lines,
+        }))
     }
 
     fn peek_line(&self) -> Option<&str> {
@@ -169,83 +502,287 @@ impl<'a> LdifFileParser<'a> {
         result
     }
 
-    fn build_tree(&mut self, entries: Vec<LdifEntry>) -> Tree {
-        let mut tree = Tree::new(TreeNode::new("root", "root"));
+    /// The nodes of the entries, every entry a child of the root, with
+    /// their DNs. [`LdifParser::parse_chunks`] places the entries in the DN
+    /// hierarchy and labels them.
+    fn build_entries(&mut self, entries: Vec<LdifEntry>) -> (Tree, Vec<String>) {
+        // Every entry makes its own node, @attributes, the dn and one node
+        // per attribute value
+        let capacity = 1 + entries
+            .iter()
+            .map(|entry| 3 + entry.attributes.len())
+            .sum::<usize>();
+        let mut tree = Tree::with_capacity(TreeNode::new("root", "root"), capacity);
         let root_id = tree.root_id();
-
-        // Map from DN to node ID for building hierarchy
-        let mut dn_to_node: HashMap<String, usize> = HashMap::new();
+        let mut dns = Vec::with_capacity(entries.len());
 
         for entry in entries {
-            // Get parent DN
-            let parent_dn = get_parent_dn(&entry.dn);
-
-            // Find parent node (only if it exists, don't create placeholders)
-            let parent_id = if let Some(ref parent) = parent_dn {
-                // Check if parent exists in the entries we've already processed
-                dn_to_node.get(parent).copied().unwrap_or(root_id)
-            } else {
-                // No parent DN, attach to root
-                root_id
-            };
-
-            // Compute RDN (relative to parent if parent exists in tree)
-            let parent_dn_for_label = if parent_id == root_id {
-                None
-            } else {
-                parent_dn.as_deref()
-            };
-            let rdn = compute_rdn(&entry.dn, parent_dn_for_label);
-
-            // Create entry node with RDN as label
-            let entry_node = TreeNode::new(&rdn, "entry");
-            let entry_id = tree.add_child_node(parent_id, entry_node);
-
-            // Store DN to node mapping
-            dn_to_node.insert(entry.dn.clone(), entry_id);
-
-            // Create @attributes virtual node
-            let mut attr_map: HashMap<String, Vec<String>> = HashMap::new();
-            attr_map.insert("dn".to_string(), vec![entry.dn.clone()]);
-
-            // Group multi-valued attributes
-            for (key, value) in entry.attributes {
-                attr_map.entry(key).or_insert_with(Vec::new).push(value);
-            }
+            let mut entry_node = TreeNode::new("", "entry");
+            entry_node.source_span = Some((entry.line, 1));
+            let entry_id = tree.add_child_node(root_id, entry_node);
+            dns.push(entry.dn.clone());
 
             // Create virtual attributes node
             let virtual_node = TreeNode::new("@attributes", TreeNode::VIRTUAL_ATTRIBUTES_TYPE);
             let virtual_id = tree.add_child_node(entry_id, virtual_node);
 
-            // Sort attribute keys alphanumerically
-            let mut sorted_keys: Vec<_> = attr_map.keys().collect();
-            sorted_keys.sort();
+            // Lines of the values of every key, in the order of the values
+            let mut lines: HashMap<String, Vec<usize>> = HashMap::new();
+            lines.insert("dn".to_string(), vec![entry.line]);
+            for ((key, _), line) in entry.attributes.iter().zip(&entry.attribute_lines) {
+                lines.entry(key.clone()).or_default().push(*line);
+            }
 
             // Add individual attribute nodes in sorted order
-            for key in sorted_keys {
-                let values = &attr_map[key];
-                if values.len() == 1 {
-                    let mut attr_node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-                    attr_node.add_attribute("value", &values[0]);
-                    tree.add_child_node(virtual_id, attr_node);
-                } else {
-                    for (idx, value) in values.iter().enumerate() {
-                        let label = format!("{} [{}]", key, idx);
-                        let mut attr_node = TreeNode::new(&label, TreeNode::ATTRIBUTE_TYPE);
-                        attr_node.add_attribute("value", value);
-                        tree.add_child_node(virtual_id, attr_node);
+            for (key, values) in group_attributes(&entry.dn, entry.attributes) {
+                let key_lines = lines.get(&key).map_or(&[][..], Vec::as_slice);
+                for (index, (label, value)) in
+                    attribute_labels(&key, &values).into_iter().enumerate()
+                {
+                    let mut attr_node = TreeNode::new(&label, value.node_type());
+                    attr_node.add_attribute("value", &value.text);
+                    if let Some(decoded) = self.decoders.decode(&key, &value.text) {
+                        attr_node.add_attribute("decoded", &decoded);
                     }
+                    attr_node.source_span = key_lines.get(index).map(|&line| (line, 1));
+                    tree.add_child_node(virtual_id, attr_node);
                 }
             }
         }
 
-        tree
+        (tree, dns)
     }
 }
 
 struct LdifEntry {
     dn: String,
-    attributes: Vec<(String, String)>,
+    attributes: Vec<(String, AttributeValue)>,
+    /// Line of the DN, counting from 1
+    line: usize,
+    /// Line of every attribute
+    attribute_lines: Vec<usize>,
+}
+
+/// A single LDIF entry parsed from its lines, with attributes grouped for display
+///
+/// Shared by the streaming index builder and the streaming node loader so that
+/// both see exactly the same attributes, in the same order, after limits are applied.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EntryBlock {
+    /// The entry's distinguished name
+    pub dn: String,
+    /// Attributes grouped by key, sorted by key, including `dn`
+    pub attributes: Vec<(String, Vec<AttributeValue>)>,
+    /// Limit violations and skipped lines encountered while parsing
+    pub warnings: Vec<String>,
+}
+
+impl EntryBlock {
+    /// Looks up the `index`-th value of attribute `key`.
+    pub fn value(&self, key: &str, index: usize) -> Option<&str> {
+        self.attribute(key, index).map(|value| value.text.as_str())
+    }
+
+    /// Looks up the `index`-th value of attribute `key` as written.
+    pub fn attribute(&self, key: &str, index: usize) -> Option<&AttributeValue> {
+        let pos = self
+            .attributes
+            .binary_search_by(|(k, _)| natural_cmp(k, key))
+            .ok()?;
+        self.attributes[pos].1.get(index)
+    }
+}
+
+/// A value of an attribute, or with `url` the URL it is read from, as in
+/// `jpegPhoto:< file:///tmp/photo.jpg`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AttributeValue {
+    pub text: String,
+    pub url: bool,
+}
+
+impl AttributeValue {
+    fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            url: false,
+        }
+    }
+
+    fn url(url: impl Into<String>) -> Self {
+        Self {
+            text: url.into(),
+            url: true,
+        }
+    }
+
+    /// Type of the node showing the value
+    pub fn node_type(&self) -> &'static str {
+        attribute_type(self.url)
+    }
+}
+
+/// Type of the nodes of attribute values, [`TreeNode::URL_REF_TYPE`] for
+/// values read from a URL
+pub(crate) fn attribute_type(url: bool) -> &'static str {
+    if url {
+        TreeNode::URL_REF_TYPE
+    } else {
+        TreeNode::ATTRIBUTE_TYPE
+    }
+}
+
+/// Group attribute values by key, with `dn` included, sorted by key in
+/// natural order
+fn group_attributes(
+    dn: &str,
+    attributes: Vec<(String, AttributeValue)>,
+) -> Vec<(String, Vec<AttributeValue>)> {
+    let mut grouped: BTreeMap<String, Vec<AttributeValue>> = BTreeMap::new();
+    grouped.insert("dn".to_string(), vec![AttributeValue::new(dn)]);
+    for (key, value) in attributes {
+        grouped.entry(key).or_default().push(value);
+    }
+    let mut grouped: Vec<_> = grouped.into_iter().collect();
+    grouped.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+    grouped
+}
+
+/// Labels for the values of one attribute: `key` if single-valued, `key [i]` otherwise
+fn attribute_labels<'v>(
+    key: &str,
+    values: &'v [AttributeValue],
+) -> Vec<(String, &'v AttributeValue)> {
+    if values.len() == 1 {
+        return vec![(key.to_string(), &values[0])];
+    }
+    values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| (format!("{} [{}]", key, idx), value))
+        .collect()
+}
+
+/// Truncate a string to at most `max` bytes without splitting a character
+fn truncate_at_char_boundary(value: &mut String, max: usize) {
+    if value.len() > max {
+        let mut end = max;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+    }
+}
+
+/// Join folded lines (continuations start with a single space) and drop comments
+fn unfold_lines(lines: &[String]) -> Vec<String> {
+    let mut logical: Vec<String> = Vec::new();
+    for line in lines {
+        if let Some(rest) = line.strip_prefix(' ') {
+            if let Some(last) = logical.last_mut() {
+                last.push_str(rest);
+            }
+        } else if !line.starts_with('#') {
+            logical.push(line.clone());
+        }
+    }
+    logical
+}
+
+/// Parse attribute lines of an entry, applying the per-entry size limits
+fn collect_attributes(
+    lines: impl Iterator<Item = String>,
+    line_num: usize,
+    warnings: &mut Vec<String>,
+) -> Vec<(String, AttributeValue)> {
+    let mut pairs = Vec::new();
+    let mut value_counts: HashMap<String, usize> = HashMap::new();
+    value_counts.insert("dn".to_string(), 1);
+    for line in lines {
+        let (key, mut value) = match parse_attribute_line(&line, line_num) {
+            Ok(pair) => pair,
+            Err(e) => {
+                warnings.push(format!("Skipping attribute line: {}", e));
+                continue;
+            }
+        };
+        let is_new_key = !value_counts.contains_key(&key);
+        if is_new_key && value_counts.len() >= MAX_ATTRIBUTES_PER_ENTRY {
+            warnings.push(format!(
+                "Entry has too many attributes (>{} limit), skipping '{}'",
+                MAX_ATTRIBUTES_PER_ENTRY, key
+            ));
+            continue;
+        }
+        let count = value_counts.entry(key.clone()).or_default();
+        if *count >= MAX_VALUES_PER_ATTRIBUTE {
+            warnings.push(format!(
+                "Attribute '{}' has too many values (>{} limit), skipping",
+                key, MAX_VALUES_PER_ATTRIBUTE
+            ));
+            continue;
+        }
+        *count += 1;
+        if value.text.len() > MAX_ATTRIBUTE_VALUE_SIZE {
+            warnings.push(format!(
+                "Attribute '{}' value exceeds {} bytes, truncating",
+                key, MAX_ATTRIBUTE_VALUE_SIZE
+            ));
+            truncate_at_char_boundary(&mut value.text, MAX_ATTRIBUTE_VALUE_SIZE);
+        }
+        pairs.push((key, value));
+    }
+
+    pairs
+}
+
+/// Parse the lines of one LDIF entry (no blank lines) into an [`EntryBlock`].
+///
+/// Comments and a leading `version:` line are skipped. Malformed attribute
+/// lines are skipped with a warning, and the per-entry size limits are
+/// applied, so large or damaged files can still be browsed.
+///
+/// # Returns
+///
+/// * `Ok(Some(block))` - The parsed entry
+/// * `Ok(None)` - The lines contained no entry (only comments or a version line)
+/// * `Err` - The first line is not a DN or the DN cannot be decoded
+pub(crate) fn parse_entry_block(
+    lines: &[String],
+    first_line_num: usize,
+) -> Result<Option<EntryBlock>> {
+    let mut logical = unfold_lines(lines).into_iter();
+    let mut first = logical.next();
+    if first.as_deref().is_some_and(|l| l.starts_with("version:")) {
+        first = logical.next();
+    }
+    let Some(first) = first else {
+        return Ok(None);
+    };
+    let Some(dn_raw) = first.strip_prefix("dn:") else {
+        return Err(XtvError::LdifParse {
+            line: first_line_num,
+            message: format!("Expected DN, got: {}", first),
+        });
+    };
+    let dn = parse_dn_value(dn_raw, first_line_num)?;
+
+    let mut warnings = Vec::new();
+    let mut dn_value = dn.clone();
+    if dn_value.len() > MAX_ATTRIBUTE_VALUE_SIZE {
+        warnings.push(format!(
+            "DN exceeds {} bytes, truncating",
+            MAX_ATTRIBUTE_VALUE_SIZE
+        ));
+        truncate_at_char_boundary(&mut dn_value, MAX_ATTRIBUTE_VALUE_SIZE);
+    }
+
+    let pairs = collect_attributes(logical, first_line_num, &mut warnings);
+    Ok(Some(EntryBlock {
+        dn,
+        attributes: group_attributes(&dn_value, pairs),
+        warnings,
+    }))
 }
 
 /// Extract the parent DN from a DN
@@ -301,12 +838,27 @@ fn compute_rdn(dn: &str, parent: Option<&str>) -> String {
 }
 
 /// Build an index for streaming LDIF parsing
+///
+/// Only the tree structure, entry RDNs, and interned attribute keys are kept
+/// in memory; attribute values are re-read from the file when nodes are loaded.
 pub fn build_ldif_index(file_path: &Path) -> Result<StreamingTree> {
-    let file = File::open(file_path)?;
-    let file_size = file.metadata()?.len();
-    let reader = BufReader::new(file);
+    build_index(
+        file_path,
+        progress_bar(file_path)?,
+        ValueDecoders::default(),
+    )
+}
 
-    // Setup progress bar
+/// Build a streaming index without drawing a progress bar
+///
+/// Used when re-indexing while the TUI owns the terminal.
+pub fn build_ldif_index_quiet(file_path: &Path, decoders: ValueDecoders) -> Result<StreamingTree> {
+    build_index(file_path, ProgressBar::hidden(), decoders)
+}
+
+/// A progress bar over the bytes of the file being indexed
+fn progress_bar(file_path: &Path) -> Result<ProgressBar> {
+    let file_size = std::fs::metadata(file_path)?.len();
     let pb = ProgressBar::new(file_size);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -315,285 +867,249 @@ pub fn build_ldif_index(file_path: &Path) -> Result<StreamingTree> {
             .progress_chars("##-"),
     );
     pb.set_message("Building index...");
+    Ok(pb)
+}
 
-    let mut index = LdifIndex::new(0);
-    let mut dn_to_entry_id: HashMap<String, usize> = HashMap::new();
-
-    // Track estimated index size to prevent memory exhaustion
-    let mut estimated_index_size: usize = 0;
-
-    // Add root node
-    let root_entry = IndexEntry::new(0, None, NodeType::Root);
-    let root_id = index.add_entry(root_entry);
-    estimated_index_size += std::mem::size_of::<IndexEntry>() + "root".len();
-
-    let mut current_offset = 0u64;
-    let mut current_line_num = 0usize;
-    let mut lines_iter = reader.lines();
-
-    // Skip version line if present
-    if let Some(Ok(first_line)) = lines_iter.next() {
-        current_line_num += 1;
-        current_offset += first_line.len() as u64 + 1; // +1 for newline
-
-        if !first_line.starts_with("version:") {
-            // Not a version line, we need to process it
-            // Reset to start
-            let file = File::open(file_path)?;
-            let reader = BufReader::new(file);
-            lines_iter = reader.lines();
-            current_offset = 0;
-            current_line_num = 0;
+fn build_index(
+    file_path: &Path,
+    pb: ProgressBar,
+    decoders: ValueDecoders,
+) -> Result<StreamingTree> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+
+    let mut builder = IndexBuilder::new(decoders);
+    let mut cursor = BlockCursor::default();
+    let mut warnings = WarningLog::default();
+    let start = Instant::now();
+    let mut entries = 0;
+
+    while let Some(raw) = cursor.next_block(&mut reader)? {
+        if interrupt::is_interrupted() {
+            pb.abandon_with_message("Interrupted");
+            return Err(XtvError::Interrupted);
+        }
+        let block = match parse_entry_block(&raw.lines, raw.line_num) {
+            Ok(Some(block)) => block,
+            Ok(None) => continue,
+            Err(e) => {
+                warnings.push(format!("Skipping block at offset {}: {}", raw.offset, e));
+                continue;
+            }
+        };
+        for warning in &block.warnings {
+            warnings.push(format!("{} (entry at offset {})", warning, raw.offset));
         }
-    }
-
-    // Parse entries and build index
-    while let Some(Ok(line)) = lines_iter.next() {
-        current_line_num += 1;
-        let line_len = line.len() as u64 + 1; // +1 for newline
 
-        // Skip empty lines and comments
-        if line.trim().is_empty() || line.starts_with('#') {
-            current_offset += line_len;
-            pb.set_position(current_offset);
-            continue;
+        builder.add_entry(&block, raw.offset);
+        entries += 1;
+        if entries % INDEX_LOG_INTERVAL == 0 {
+            log::debug!(
+                "Indexed {} entries up to offset {} ({:.0} entries/s)",
+                entries,
+                cursor.offset,
+                entries as f64 / start.elapsed().as_secs_f64()
+            );
         }
 
-        // Check if this is a DN line (start of entry)
-        if line.starts_with("dn:") {
-            let entry_offset = current_offset;
-            let dn_line_num = current_line_num;
-            let mut dn_raw = line[3..].to_string();
-
-            // Handle line folding for DN
-            current_offset += line_len;
-            let mut next_line_buf = String::new();
-            while let Some(Ok(next_line)) = lines_iter.next() {
-                current_line_num += 1;
-                let next_len = next_line.len() as u64 + 1;
-                if next_line.starts_with(' ') {
-                    // Continuation line
-                    dn_raw.push_str(&next_line[1..]);
-                    current_offset += next_len;
-                } else {
-                    next_line_buf = next_line;
-                    current_offset += next_len;
-                    break;
-                }
-            }
+        // Check if we're approaching the index size limit
+        if builder.estimated_size > MAX_INDEX_SIZE_BYTES {
+            pb.finish_with_message("Index size limit exceeded");
+            return Err(XtvError::LdifParse {
+                line: cursor.line_num,
+                message: format!(
+                    "Index size exceeded {} bytes limit at offset {} - file may be malicious or corrupted",
+                    MAX_INDEX_SIZE_BYTES, cursor.offset
+                ),
+            });
+        }
+        pb.set_position(cursor.offset);
+    }
 
-            // Parse DN value (may be base64-encoded)
-            let dn = parse_dn_value(&dn_raw, dn_line_num)?;
+    pb.finish_with_message("Index complete");
+    log::info!(
+        "Indexed {} entries of {:?} in {:?} ({:.0} entries/s)",
+        entries,
+        file_path,
+        start.elapsed(),
+        entries as f64 / start.elapsed().as_secs_f64()
+    );
 
-            // Determine parent DN and compute RDN
-            let parent_dn = get_parent_dn(&dn);
-            let parent_entry_id = if let Some(ref pdn) = parent_dn {
-                dn_to_entry_id.get(pdn).copied().unwrap_or(root_id)
-            } else {
-                root_id
-            };
+    let tree = StreamingTree::new(file_path.to_path_buf(), builder.index).map_err(XtvError::Io)?;
+    tree.report(warnings.into_messages());
+    Ok(tree)
+}
 
-            let rdn = compute_rdn(&dn, parent_dn.as_deref());
+/// Maximum number of individual index warnings kept for display
+const MAX_REPORTED_WARNINGS: usize = 20;
 
-            // Create index entry for this LDIF entry
-            let entry_node = IndexEntry::new(
-                entry_offset,
-                Some(parent_entry_id),
-                NodeType::Entry {
-                    dn: dn.clone(),
-                    rdn: rdn.clone(),
-                },
-            );
-            let entry_id = index.add_entry(entry_node);
+/// Collects index warnings, keeping the first few and counting the rest
+#[derive(Default)]
+struct WarningLog {
+    messages: Vec<String>,
+    suppressed: usize,
+}
 
-            // Track index size
-            estimated_index_size += std::mem::size_of::<IndexEntry>() + dn.len() + rdn.len();
+impl WarningLog {
+    fn push(&mut self, message: String) {
+        if self.messages.len() < MAX_REPORTED_WARNINGS {
+            self.messages.push(message);
+        } else {
+            self.suppressed += 1;
+        }
+    }
 
-            // Store DN to entry ID mapping
-            dn_to_entry_id.insert(dn.clone(), entry_id);
+    fn into_messages(mut self) -> Vec<String> {
+        if self.suppressed > 0 {
+            self.messages
+                .push(format!("{} more index warnings not shown", self.suppressed));
+        }
+        self.messages
+    }
+}
 
-            // Add child to parent
-            index.add_child(parent_entry_id, entry_id);
+/// The raw lines of one LDIF entry and where it starts in the file
+struct RawBlock {
+    offset: u64,
+    line_num: usize,
+    lines: Vec<String>,
+}
 
-            // Parse attributes from this entry
-            let mut attr_map: HashMap<String, Vec<String>> = HashMap::new();
+/// Splits an LDIF file into blank-line separated blocks while tracking exact byte offsets
+#[derive(Default)]
+struct BlockCursor {
+    /// Byte offset of the next unread line
+    offset: u64,
+    /// Number of lines consumed so far
+    line_num: usize,
+}
 
-            // Truncate DN if too large
-            let dn_truncated = if dn.len() > MAX_ATTRIBUTE_VALUE_SIZE {
-                eprintln!(
-                    "Warning: DN exceeds {} bytes, truncating (entry at offset {})",
-                    MAX_ATTRIBUTE_VALUE_SIZE, entry_offset
-                );
-                dn[..MAX_ATTRIBUTE_VALUE_SIZE].to_string()
-            } else {
-                dn.clone()
-            };
-            attr_map.insert("dn".to_string(), vec![dn_truncated]);
-
-            let mut attribute_count = 1; // Already have 'dn'
-
-            // Read and parse attributes until empty line
-            let mut attr_line_num = current_line_num;
-            if !next_line_buf.is_empty() && !next_line_buf.trim().is_empty() {
-                // Process the line we already read
-                if let Ok((key, mut value)) = parse_attribute_line(&next_line_buf, attr_line_num) {
-                    if attribute_count < MAX_ATTRIBUTES_PER_ENTRY {
-                        // Truncate value if too large
-                        if value.len() > MAX_ATTRIBUTE_VALUE_SIZE {
-                            eprintln!(
-                                "Warning: Attribute '{}' value exceeds {} bytes, truncating (entry at offset {})",
-                                key, MAX_ATTRIBUTE_VALUE_SIZE, entry_offset
-                            );
-                            value.truncate(MAX_ATTRIBUTE_VALUE_SIZE);
-                        }
-
-                        let values = attr_map.entry(key.clone()).or_default();
-                        if values.len() < MAX_VALUES_PER_ATTRIBUTE {
-                            values.push(value);
-                            if values.len() == 1 {
-                                attribute_count += 1;
-                            }
-                        } else {
-                            eprintln!(
-                                "Warning: Attribute '{}' has too many values (>{} limit), skipping (entry at offset {})",
-                                key, MAX_VALUES_PER_ATTRIBUTE, entry_offset
-                            );
-                        }
-                    } else {
-                        eprintln!(
-                            "Warning: Entry has too many attributes (>{} limit), skipping remaining (entry at offset {})",
-                            MAX_ATTRIBUTES_PER_ENTRY, entry_offset
-                        );
-                    }
-                }
+impl BlockCursor {
+    /// Read the next non-empty block, or `None` at end of file
+    fn next_block(&mut self, reader: &mut impl BufRead) -> Result<Option<RawBlock>> {
+        let mut block: Option<RawBlock> = None;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                return Ok(block);
             }
+            let line_offset = self.offset;
+            self.offset += read as u64;
+            self.line_num += 1;
 
-            while let Some(Ok(line)) = lines_iter.next() {
-                current_line_num += 1;
-                attr_line_num = current_line_num;
-                let len = line.len() as u64 + 1;
-                current_offset += len;
-
-                if line.trim().is_empty() {
-                    break;
-                }
-
-                // Skip comments
-                if line.starts_with('#') {
-                    continue;
+            let content = line.trim_end_matches(['\r', '\n']);
+            if content.trim().is_empty() {
+                if block.is_some() {
+                    return Ok(block);
                 }
+                continue;
+            }
+            block
+                .get_or_insert_with(|| RawBlock {
+                    offset: line_offset,
+                    line_num: self.line_num,
+                    lines: Vec::new(),
+                })
+                .lines
+                .push(content.to_string());
+        }
+    }
+}
 
-                // Handle line folding
-                let mut logical_line = line.clone();
-                while let Some(Ok(next_line)) = lines_iter.next() {
-                    current_line_num += 1;
-                    let next_len = next_line.len() as u64 + 1;
-                    if next_line.starts_with(' ') {
-                        logical_line.push_str(&next_line[1..]);
-                        current_offset += next_len;
-                    } else {
-                        // Put it back somehow - actually we can't, so we need a different approach
-                        // For now, just break and accept we might miss one line
-                        break;
-                    }
-                }
+/// Accumulates LDIF entries into a streaming index
+struct IndexBuilder {
+    index: LdifIndex,
+    root_id: usize,
+    /// Full DN to entry node ID, only needed while building
+    dn_to_entry_id: HashMap<String, usize>,
+    /// Estimated index size, to prevent memory exhaustion
+    estimated_size: usize,
+}
 
-                if let Ok((key, mut value)) = parse_attribute_line(&logical_line, attr_line_num) {
-                    if attribute_count < MAX_ATTRIBUTES_PER_ENTRY {
-                        // Truncate value if too large
-                        if value.len() > MAX_ATTRIBUTE_VALUE_SIZE {
-                            eprintln!(
-                                "Warning: Attribute '{}' value exceeds {} bytes, truncating (entry at offset {})",
-                                key, MAX_ATTRIBUTE_VALUE_SIZE, entry_offset
-                            );
-                            value.truncate(MAX_ATTRIBUTE_VALUE_SIZE);
-                        }
-
-                        let values = attr_map.entry(key.clone()).or_default();
-                        if values.len() < MAX_VALUES_PER_ATTRIBUTE {
-                            values.push(value);
-                            if values.len() == 1 {
-                                attribute_count += 1;
-                            }
-                        } else {
-                            eprintln!(
-                                "Warning: Attribute '{}' has too many values (>{} limit), skipping (entry at offset {})",
-                                key, MAX_VALUES_PER_ATTRIBUTE, entry_offset
-                            );
-                        }
-                    }
-                    // Note: If we've hit MAX_ATTRIBUTES_PER_ENTRY, we silently skip
-                    // to avoid spamming warnings for every subsequent attribute
-                }
-            }
+impl IndexBuilder {
+    fn new(decoders: ValueDecoders) -> Self {
+        let mut index = LdifIndex::new(0).with_decoders(decoders);
+        let root_id = index.add_entry(IndexEntry::new(0, None, NodeType::Root));
+        Self {
+            index,
+            root_id,
+            dn_to_entry_id: HashMap::new(),
+            estimated_size: std::mem::size_of::<IndexEntry>(),
+        }
+    }
 
-            // Create @attributes virtual node
-            let virtual_node = IndexEntry::new(0, Some(entry_id), NodeType::VirtualAttributes);
-            let virtual_id = index.add_entry(virtual_node);
-            index.add_child(entry_id, virtual_id);
-            estimated_index_size += std::mem::size_of::<IndexEntry>() + "@attributes".len();
-
-            // Check if we're approaching the index size limit
-            if estimated_index_size > MAX_INDEX_SIZE_BYTES {
-                pb.finish_with_message("Index size limit exceeded");
-                return Err(XtvError::LdifParse {
-                    line: current_line_num,
-                    message: format!(
-                        "Index size exceeded {} bytes limit at offset {} - file may be malicious or corrupted",
-                        MAX_INDEX_SIZE_BYTES, current_offset
-                    ),
-                });
+    /// Add an entry, its `@attributes` node, and one node per attribute value
+    fn add_entry(&mut self, block: &EntryBlock, offset: u64) {
+        let entry_size = std::mem::size_of::<IndexEntry>();
+        let parent_dn = get_parent_dn(&block.dn);
+        let parent_id = parent_dn
+            .as_ref()
+            .and_then(|pdn| self.dn_to_entry_id.get(pdn).copied())
+            .unwrap_or(self.root_id);
+
+        let node_type = self.entry_node_type(&block.dn, parent_dn.as_deref(), parent_id);
+        if let NodeType::Entry { rdn, .. } = &node_type {
+            self.estimated_size += entry_size + rdn.len();
+        }
+        let entry_id = self
+            .index
+            .add_entry(IndexEntry::new(offset, Some(parent_id), node_type));
+        self.index.add_child(parent_id, entry_id);
+        self.dn_to_entry_id.insert(block.dn.clone(), entry_id);
+
+        let virtual_node = IndexEntry::new(offset, Some(entry_id), NodeType::VirtualAttributes);
+        let virtual_id = self.index.add_entry(virtual_node);
+        self.index.add_child(entry_id, virtual_id);
+        self.estimated_size += entry_size;
+
+        for (key, values) in &block.attributes {
+            let key_symbol = self.index.strings_mut().intern(key);
+            for (idx, value) in values.iter().enumerate() {
+                let index = (values.len() > 1).then_some(idx as u32);
+                let attr_type = NodeType::Attribute {
+                    key: key_symbol,
+                    index,
+                    url: value.url,
+                };
+                let attr_id =
+                    self.index
+                        .add_entry(IndexEntry::new(offset, Some(virtual_id), attr_type));
+                self.index.add_child(virtual_id, attr_id);
+                self.estimated_size += entry_size + 2 * std::mem::size_of::<usize>();
             }
+        }
+    }
 
-            // Sort attribute keys alphanumerically
-            let mut sorted_keys: Vec<_> = attr_map.keys().collect();
-            sorted_keys.sort();
-
-            // Create attribute nodes
-            for key in sorted_keys {
-                let values = &attr_map[key];
-                if values.len() == 1 {
-                    let attr_node = IndexEntry::new(
-                        0,
-                        Some(virtual_id),
-                        NodeType::Attribute {
-                            key: (*key).clone(),
-                            value: values[0].clone(),
-                        },
-                    );
-                    let attr_id = index.add_entry(attr_node);
-                    index.add_child(virtual_id, attr_id);
-                    estimated_index_size +=
-                        std::mem::size_of::<IndexEntry>() + key.len() + values[0].len();
-                } else {
-                    for (idx, value) in values.iter().enumerate() {
-                        let label = format!("{} [{}]", key, idx);
-                        let attr_node = IndexEntry::new(
-                            0,
-                            Some(virtual_id),
-                            NodeType::Attribute {
-                                key: label.clone(),
-                                value: value.clone(),
-                            },
-                        );
-                        let attr_id = index.add_entry(attr_node);
-                        index.add_child(virtual_id, attr_id);
-                        estimated_index_size +=
-                            std::mem::size_of::<IndexEntry>() + label.len() + value.len();
-                    }
-                }
+    /// Entry label parts matching the in-memory parser: the RDN below a parent
+    /// entry in the file, otherwise the full DN (stored as RDN + interned suffix)
+    fn entry_node_type(&mut self, dn: &str, parent_dn: Option<&str>, parent_id: usize) -> NodeType {
+        if parent_id != self.root_id {
+            let rdn = compute_rdn(dn, parent_dn);
+            return NodeType::Entry {
+                rdn: rdn.into_boxed_str(),
+                suffix: None,
+            };
+        }
+        let full = dn.trim();
+        if let Some(parent) = parent_dn {
+            let rdn = compute_rdn(dn, Some(parent));
+            if full.len() == rdn.len() + 1 + parent.len()
+                && full.starts_with(rdn.as_str())
+                && full.ends_with(parent)
+            {
+                let suffix = self.index.strings_mut().intern(parent);
+                return NodeType::Entry {
+                    rdn: rdn.into_boxed_str(),
+                    suffix: Some(suffix),
+                };
             }
-
-            pb.set_position(current_offset);
-        } else {
-            current_offset += line_len;
-            pb.set_position(current_offset);
+        }
+        NodeType::Entry {
+            rdn: full.into(),
+            suffix: None,
         }
     }
-
-    pb.finish_with_message("Index complete");
-
-    StreamingTree::new(file_path.to_path_buf(), index).map_err(|e| XtvError::Io(e))
 }
 
 /// Parse a DN value which may be base64-encoded (dn:: prefix means base64)
@@ -620,7 +1136,7 @@ fn parse_dn_value(value: &str, line_num: usize) -> Result<String> {
 }
 
 /// Parse an attribute line (extracted from LdifFileParser for reuse)
-fn parse_attribute_line(line: &str, line_num: usize) -> Result<(String, String)> {
+fn parse_attribute_line(line: &str, line_num: usize) -> Result<(String, AttributeValue)> {
     use base64::{Engine as _, engine::general_purpose};
 
     // Find the first colon - this is the separator between key and value
@@ -641,9 +1157,9 @@ fn parse_attribute_line(line: &str, line_num: usize) -> Result<(String, String)>
         match general_purpose::STANDARD.decode(encoded) {
             Ok(bytes) => match String::from_utf8(bytes) {
                 // Valid UTF-8: use the decoded string
-                Ok(s) => Ok((key.to_string(), s)),
+                Ok(s) => Ok((key.to_string(), AttributeValue::new(s))),
                 // Binary data: keep original base64 so user can decode with 'd' menu
-                Err(_) => Ok((key.to_string(), encoded.to_string())),
+                Err(_) => Ok((key.to_string(), AttributeValue::new(encoded))),
             },
             Err(e) => Err(XtvError::LdifParse {
                 line: line_num,
@@ -652,12 +1168,10 @@ fn parse_attribute_line(line: &str, line_num: usize) -> Result<(String, String)>
         }
     } else if let Some(rest) = after_colon.strip_prefix('<') {
         // URL reference (:<)
-        let url = rest.trim();
-        Ok((key.to_string(), format!("<URL reference: {}>", url)))
+        Ok((key.to_string(), AttributeValue::url(rest.trim())))
     } else {
         // Plain value (:)
-        let value = after_colon.trim();
-        Ok((key.to_string(), value.to_string()))
+        Ok((key.to_string(), AttributeValue::new(after_colon.trim())))
     }
 }
 
@@ -665,10 +1179,67 @@ fn parse_attribute_line(line: &str, line_num: usize) -> Result<(String, String)>
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_nodes_know_their_line() {
+        let ldif =
+            "version: 1\n\ndn: dc=example\nmail: a@x\ndescription: long\n  folded\nmail: b@x\n";
+        let tree = LdifParser::default().parse(ldif).unwrap();
+        let span = |path: &[&str]| {
+            tree.get_node(tree.find_by_path(path).unwrap())
+                .unwrap()
+                .source_span
+        };
+
+        assert_eq!(span(&["dc=example"]), Some((3, 1)));
+        assert_eq!(span(&["dc=example", "@attributes", "dn"]), Some((3, 1)));
+        assert_eq!(
+            span(&["dc=example", "@attributes", "mail [0]"]),
+            Some((4, 1))
+        );
+        assert_eq!(
+            span(&["dc=example", "@attributes", "mail [1]"]),
+            Some((7, 1))
+        );
+        assert_eq!(
+            span(&["dc=example", "@attributes", "description"]),
+            Some((5, 1))
+        );
+    }
+
+    #[test]
+    fn test_chunks_parse_like_the_whole_file() {
+        let ldif = "version: 1\n\n# top\ndn: dc=example\ndc: example\n\n\
+            dn: ou=people,dc=example\nou: people\n\n\
+            dn: cn=a,ou=people,dc=example\ncn: a\ndescription: long\n  folded\n\n\
+            dn: cn=b,ou=gone,dc=example\ncn: b\n\n\
+            dn: cn=c,ou=people,dc=example\ncn: c\nmail: c@x\nmail: d@x\n";
+        let parser = LdifParser::default();
+        let whole = parser.parse_chunks(&[Chunk::whole(ldif)]).unwrap();
+        for count in 2..8 {
+            let chunks = chunks::split(ldif, count, chunks::blank_line_boundary);
+            let parts = parser.parse_chunks(&chunks).unwrap();
+            assert_eq!(format!("{:?}", parts), format!("{:?}", whole));
+        }
+        // Parents in earlier chunks are found
+        assert!(
+            whole
+                .find_by_path(&["dc=example", "ou=people", "cn=c"])
+                .is_some()
+        );
+
+        // The first error is reported, with its line in the file
+        let ldif = "dn: dc=a\n\nnot a dn\n\ndn: dc=b\n\nnot a dn either\n";
+        let whole = parser.parse_chunks(&[Chunk::whole(ldif)]).unwrap_err();
+        let chunks = chunks::split(ldif, 4, chunks::blank_line_boundary);
+        let parts = parser.parse_chunks(&chunks).unwrap_err();
+        assert_eq!(parts.to_string(), whole.to_string());
+        assert!(whole.to_string().contains("line 3"), "{}", whole);
+    }
+
     #[test]
     fn test_parse_simple_ldif() {
         let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\ncn: Test\nsn: User\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
         assert!(tree.node_count() > 0);
 
@@ -686,7 +1257,7 @@ cn: First
 dn: cn=Second,dc=example,dc=com
 cn: Second
 "#;
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         let root = tree.get_node(0).unwrap();
@@ -704,7 +1275,7 @@ cn: Second
     #[test]
     fn test_line_folding() {
         let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\ndescription: This is a long\n description that continues\n  on multiple lines\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         assert!(tree.node_count() > 0);
@@ -719,7 +1290,7 @@ objectClass: top
 objectClass: person
 objectClass: organizationalPerson
 "#;
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         let root = tree.get_node(0).unwrap();
@@ -750,7 +1321,7 @@ objectClass: organizationalPerson
     fn test_base64_decoding() {
         // "Test" in base64 is "VGVzdA=="
         let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\ndescription:: VGVzdA==\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         assert!(tree.node_count() > 0);
@@ -764,7 +1335,7 @@ objectClass: organizationalPerson
         let encoded_dn = general_purpose::STANDARD.encode(dn);
         let ldif = format!("version: 1\n\ndn:: {}\ncn: Test\n", encoded_dn);
 
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(&ldif).unwrap();
 
         let root = tree.get_node(0).unwrap();
@@ -780,7 +1351,7 @@ objectClass: organizationalPerson
         // Test that :: in a plain value doesn't confuse the parser
         let ldif =
             "version: 1\n\ndn: cn=Test,dc=example,dc=com\ndescription: value with :: inside\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         assert!(tree.node_count() > 0);
@@ -805,10 +1376,72 @@ objectClass: organizationalPerson
     #[test]
     fn test_url_reference() {
         let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\nphoto:< file:///tmp/photo.jpg\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
-        assert!(tree.node_count() > 0);
+        let photo = (0..tree.node_count())
+            .filter_map(|id| tree.get_node(id))
+            .find(|node| node.label == "photo")
+            .unwrap();
+        assert_eq!(photo.node_type, TreeNode::URL_REF_TYPE);
+        assert!(photo.is_attribute());
+        assert_eq!(photo.attributes[0].value, "file:///tmp/photo.jpg");
+
+        // The reference is written back as a reference
+        assert_eq!(attribute_line(photo), "photo:< file:///tmp/photo.jpg\n");
+    }
+
+    #[test]
+    fn test_decoded_values() {
+        let ldif = "dn: cn=Alice,dc=example\nwhenCreated: 20240101120000.0Z\n\
+                    objectSid:: AQIAAAAAAAUgAAAAIAIAAA==\nuserAccountControl: 66048\n\
+                    description: 20240101120000Z\n";
+        let decoded = |tree: &Tree, label: &str| {
+            let node = (0..tree.node_count())
+                .filter_map(|id| tree.get_node(id))
+                .find(|node| node.label == label)
+                .unwrap();
+            node.attributes
+                .iter()
+                .find(|a| a.key == "decoded")
+                .map(|a| a.value.clone())
+        };
+
+        let tree = LdifParser::default().parse(ldif).unwrap();
+        assert_eq!(
+            decoded(&tree, "whenCreated").as_deref(),
+            Some("2024-01-01T12:00:00.0Z")
+        );
+        // Binary values that happen to be valid UTF-8 decode as well
+        assert_eq!(decoded(&tree, "objectSid").as_deref(), Some("S-1-5-32-544"));
+        assert_eq!(
+            decoded(&tree, "userAccountControl").as_deref(),
+            Some("NORMAL_ACCOUNT | DONT_EXPIRE_PASSWORD")
+        );
+        assert_eq!(decoded(&tree, "description"), None);
+
+        let options = LdifOptions {
+            decode_values: false,
+            ..LdifOptions::default()
+        };
+        let tree = LdifParser::new(options).parse(ldif).unwrap();
+        assert_eq!(decoded(&tree, "whenCreated"), None);
+    }
+
+    #[test]
+    fn test_file_url_path() {
+        for (url, path) in [
+            ("file:///tmp/photo.jpg", Some("/tmp/photo.jpg")),
+            ("FILE://localhost/tmp/photo.jpg", Some("/tmp/photo.jpg")),
+            ("file:///tmp/my%20photo.jpg", Some("/tmp/my photo.jpg")),
+            ("file:photo.jpg", Some("photo.jpg")),
+            ("file://server/share/photo.jpg", None),
+            ("file:///tmp/bad%2", None),
+            ("http://example.com/photo.jpg", None),
+            ("file:", None),
+        ] {
+            assert_eq!(file_url_path(url), path.map(PathBuf::from), "{}", url);
+        }
     }
 
     #[test]
@@ -820,7 +1453,7 @@ dn: cn=Test,dc=example,dc=com
 # Another comment
 cn: Test
 "#;
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         let root = tree.get_node(0).unwrap();
@@ -830,7 +1463,7 @@ cn: Test
     #[test]
     fn test_version_line() {
         let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\ncn: Test\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let result = parser.parse(ldif);
         assert!(result.is_ok());
     }
@@ -838,7 +1471,7 @@ cn: Test
     #[test]
     fn test_virtual_attributes_node() {
         let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\ncn: Test\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         let root = tree.get_node(0).unwrap();
@@ -854,7 +1487,7 @@ cn: Test
 
     #[test]
     fn test_can_parse_ldif_extension() {
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         assert!(parser.can_parse(Path::new("test.ldif")));
         assert!(parser.can_parse(Path::new("test.LDIF")));
         assert!(!parser.can_parse(Path::new("test.xml")));
@@ -863,7 +1496,7 @@ cn: Test
     #[test]
     fn test_empty_ldif() {
         let ldif = "";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         let root = tree.get_node(0).unwrap();
@@ -873,7 +1506,7 @@ cn: Test
     #[test]
     fn test_malformed_entry() {
         let ldif = "version: 1\n\nnotadn: invalid\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let result = parser.parse(ldif);
         assert!(result.is_err());
     }
@@ -881,7 +1514,7 @@ cn: Test
     #[test]
     fn test_whitespace_in_values() {
         let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\ncn:  Test  \n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
         assert!(tree.node_count() > 0);
     }
@@ -889,7 +1522,7 @@ cn: Test
     #[test]
     fn test_no_version_line() {
         let ldif = "dn: cn=Test,dc=example,dc=com\ncn: Test\n";
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let result = parser.parse(ldif);
         assert!(result.is_ok());
     }
@@ -910,7 +1543,7 @@ dn: cn=John Doe,ou=People,dc=example,dc=com
 objectClass: person
 cn: John Doe
 "#;
-        let parser = LdifParser;
+        let parser = LdifParser::default();
         let tree = parser.parse(ldif).unwrap();
 
         let root = tree.get_node(0).unwrap();
@@ -967,4 +1600,133 @@ cn: John Doe
         );
         assert_eq!(compute_rdn("dc=example,dc=com", None), "dc=example,dc=com");
     }
+
+    fn assert_streaming_matches_in_memory(ldif: &str) {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, ldif.as_bytes()).unwrap();
+
+        let tree = LdifParser::default().parse(ldif).unwrap();
+        let streaming = build_ldif_index(file.path()).unwrap();
+
+        assert_eq!(tree.node_count(), streaming.node_count());
+        for id in 0..tree.node_count() {
+            let expected = tree.get_node(id).unwrap();
+            let actual = streaming.get_node(id).unwrap();
+            assert_eq!(expected.label, actual.label, "label of node {}", id);
+            assert_eq!(expected.node_type, actual.node_type, "type of node {}", id);
+            let values = |node: &TreeNode| -> Vec<(String, String)> {
+                node.attributes
+                    .iter()
+                    .map(|a| (a.key.clone(), a.value.clone()))
+                    .collect()
+            };
+            assert_eq!(values(&expected), values(&actual), "value of node {}", id);
+            assert_eq!(
+                expected.children, actual.children,
+                "children of node {}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_streaming_index_matches_in_memory() {
+        assert_streaming_matches_in_memory(
+            "version: 1\n\n# comment\ndn: dc=example,dc=com\nobjectClass: top\ndc: example\n\n\
+             dn: ou=People,dc=example,dc=com\nou: People\n\n\
+             dn: cn=John Doe,ou=People,dc=example,dc=com\nobjectClass: top\nobjectClass: person\n\
+             description: This is a long\n  description\nsn: Doe\n\n\
+             dn: cn=Orphan,ou=Missing,dc=other\ndescription:: VGVzdA==\nmail: a@example.com\n\
+             jpegPhoto:< file:///tmp/photo.jpg\nwhenCreated: 20240101120000Z\n\
+             userAccountControl: 514\n",
+        );
+    }
+
+    #[test]
+    fn test_streaming_index_crlf_and_no_version() {
+        assert_streaming_matches_in_memory(
+            "dn: cn=A,dc=example\r\ncn: A\r\n\r\ndn: cn=B,cn=A,dc=example\r\ncn: B\r\n",
+        );
+    }
+
+    #[test]
+    fn test_streaming_index_does_not_hold_values() {
+        let value = "x".repeat(10_000);
+        let mut ldif = String::new();
+        for i in 0..50 {
+            ldif.push_str(&format!(
+                "dn: cn=user{},dc=example\ndescription: {}\n\n",
+                i, value
+            ));
+        }
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, ldif.as_bytes()).unwrap();
+
+        let streaming = build_ldif_index(file.path()).unwrap();
+
+        assert!(streaming.index_memory_bytes() < 50 * value.len() / 10);
+    }
+
+    #[test]
+    fn test_entry_block_limits() {
+        let long = "é".repeat(MAX_ATTRIBUTE_VALUE_SIZE);
+        let lines = vec!["dn: cn=Test".to_string(), format!("cn: {}", long)];
+        let block = parse_entry_block(&lines, 1).unwrap().unwrap();
+
+        let cn = block.value("cn", 0).unwrap();
+        assert!(cn.len() <= MAX_ATTRIBUTE_VALUE_SIZE);
+        assert_eq!(block.value("dn", 0), Some("cn=Test"));
+        assert_eq!(block.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_attributes_in_natural_order() {
+        let lines: Vec<String> = ["dn: cn=Test", "tel10: c", "Mail: m", "tel2: b", "cn: Test"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let block = parse_entry_block(&lines, 1).unwrap().unwrap();
+        let keys: Vec<&str> = block.attributes.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["cn", "dn", "Mail", "tel2", "tel10"]);
+        assert_eq!(block.value("tel10", 0), Some("c"));
+        assert_eq!(block.value("Mail", 0), Some("m"));
+        assert_eq!(block.value("mail", 0), None);
+    }
+
+    #[test]
+    fn test_entry_block_skips_version_and_comments() {
+        let lines: Vec<String> = ["version: 1", "# note", "dn: cn=Test", "cn: Test"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let block = parse_entry_block(&lines, 1).unwrap().unwrap();
+        assert_eq!(block.dn, "cn=Test");
+        assert_eq!(block.value("cn", 0), Some("Test"));
+
+        let only_version = vec!["version: 1".to_string()];
+        assert!(parse_entry_block(&only_version, 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_index_warnings_are_reported_not_printed() {
+        let mut ldif = String::new();
+        for i in 0..MAX_REPORTED_WARNINGS + 5 {
+            ldif.push_str(&format!(
+                "dn: cn=user{},dc=example
+broken line
+
+",
+                i
+            ));
+        }
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, ldif.as_bytes()).unwrap();
+
+        let streaming = build_ldif_index_quiet(file.path(), ValueDecoders::default()).unwrap();
+        let messages = streaming.take_messages();
+
+        assert_eq!(messages.len(), MAX_REPORTED_WARNINGS + 1);
+        assert_eq!(messages.last().unwrap(), "5 more index warnings not shown");
+        assert!(streaming.take_messages().is_empty());
+    }
 }
@@ -0,0 +1,373 @@
+//! Readable forms of well-known LDAP values.
+//!
+//! Directory dumps store timestamps as GeneralizedTime (`20240101120000Z`) or
+//! as Windows FILETIME counts of 100 ns since 1601, security identifiers and
+//! GUIDs as binary, and account flags as bitmasks. For the attributes listed
+//! here the parser adds a `decoded` attribute next to the node's `value`,
+//! such as `2024-01-01T12:00:00Z` or `S-1-5-21-…`; the raw value stays the
+//! one that is copied and exported.
+//!
+//! `parser.ldif.decode_values` turns this off, and `[parser.ldif.decoders]`
+//! decodes further attributes or, with `"none"`, stops decoding built-in ones.
+
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// How the values of an attribute are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decoder {
+    /// `20240101120000Z`, also with fractions of seconds or an offset
+    GeneralizedTime,
+    /// 100 ns intervals since 1601-01-01 UTC, as in `lastLogon`
+    Filetime,
+    /// A binary security identifier, written as `S-1-5-21-…`
+    Sid,
+    /// A binary GUID as in `objectGUID`, written as a UUID string
+    Guid,
+    /// The flags of `userAccountControl`
+    AccountControl,
+    /// Not decoded, to turn off a built-in decoder
+    None,
+}
+
+/// Built-in attributes and their decoders
+const BUILTIN: &[(&str, Decoder)] = &[
+    ("whenCreated", Decoder::GeneralizedTime),
+    ("whenChanged", Decoder::GeneralizedTime),
+    ("createTimestamp", Decoder::GeneralizedTime),
+    ("modifyTimestamp", Decoder::GeneralizedTime),
+    ("pwdChangedTime", Decoder::GeneralizedTime),
+    ("pwdAccountLockedTime", Decoder::GeneralizedTime),
+    ("dSCorePropagationData", Decoder::GeneralizedTime),
+    ("lastLogon", Decoder::Filetime),
+    ("lastLogonTimestamp", Decoder::Filetime),
+    ("lastLogoff", Decoder::Filetime),
+    ("pwdLastSet", Decoder::Filetime),
+    ("accountExpires", Decoder::Filetime),
+    ("badPasswordTime", Decoder::Filetime),
+    ("lockoutTime", Decoder::Filetime),
+    ("objectSid", Decoder::Sid),
+    ("sIDHistory", Decoder::Sid),
+    ("objectGUID", Decoder::Guid),
+    ("msExchMailboxGuid", Decoder::Guid),
+    ("userAccountControl", Decoder::AccountControl),
+    (
+        "msDS-User-Account-Control-Computed",
+        Decoder::AccountControl,
+    ),
+];
+
+/// Decoders by attribute name: the built-in ones extended by the config file
+#[derive(Debug, Clone)]
+pub struct ValueDecoders {
+    /// By lowercase attribute name
+    by_name: HashMap<String, Decoder>,
+}
+
+/// The built-in decoders alone
+impl Default for ValueDecoders {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl ValueDecoders {
+    /// The built-in decoders, with `overrides` added or replacing them
+    pub fn new(overrides: &BTreeMap<String, Decoder>) -> Self {
+        let mut by_name: HashMap<String, Decoder> = BUILTIN
+            .iter()
+            .map(|&(name, decoder)| (name.to_lowercase(), decoder))
+            .collect();
+        for (name, &decoder) in overrides {
+            by_name.insert(name.to_lowercase(), decoder);
+        }
+        Self { by_name }
+    }
+
+    /// No decoders at all
+    pub fn none() -> Self {
+        Self {
+            by_name: HashMap::new(),
+        }
+    }
+
+    /// The readable form of a value of the attribute `key`, if it has a
+    /// decoder and the value is of the expected form
+    pub fn decode(&self, key: &str, value: &str) -> Option<String> {
+        if self.by_name.is_empty() {
+            return None;
+        }
+        match self.by_name.get(&key.to_lowercase())? {
+            Decoder::GeneralizedTime => generalized_time(value),
+            Decoder::Filetime => filetime(value),
+            Decoder::Sid => sid(&binary(value)?),
+            Decoder::Guid => guid(&binary(value)?),
+            Decoder::AccountControl => account_control(value),
+            Decoder::None => None,
+        }
+    }
+}
+
+/// `YYYYMMDDHHMMSS` with optional minutes and seconds, fraction and a `Z` or
+/// `±HHMM` offset, as an ISO 8601 timestamp
+fn generalized_time(value: &str) -> Option<String> {
+    let value = value.trim();
+    let zone_start = value.find(['Z', 'z', '+', '-']).unwrap_or(value.len());
+    let (local, zone) = value.split_at(zone_start);
+    let (digits, fraction) = match local.split_once(['.', ',']) {
+        Some((digits, fraction)) => (digits, Some(fraction)),
+        None => (local, None),
+    };
+    if !matches!(digits.len(), 10 | 12 | 14) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let number = |range: std::ops::Range<usize>| digits.get(range)?.parse::<u32>().ok();
+    let date = NaiveDate::from_ymd_opt(number(0..4)? as i32, number(4..6)?, number(6..8)?)?;
+    let time = NaiveTime::from_hms_opt(
+        number(8..10)?,
+        number(10..12).unwrap_or(0),
+        number(12..14).unwrap_or(0),
+    )?;
+    let mut timestamp = NaiveDateTime::new(date, time)
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string();
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        timestamp.push('.');
+        timestamp.push_str(fraction);
+    }
+    match zone {
+        "" => {}
+        "Z" | "z" => timestamp.push('Z'),
+        _ if matches!(zone.len(), 3 | 5) && zone[1..].bytes().all(|b| b.is_ascii_digit()) => {
+            timestamp.push_str(&zone[..3]);
+            timestamp.push(':');
+            timestamp.push_str(zone.get(3..5).unwrap_or("00"));
+        }
+        _ => return None,
+    }
+    Some(timestamp)
+}
+
+/// Seconds from 1601-01-01 to the Unix epoch
+const FILETIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+/// A FILETIME as an ISO 8601 timestamp in UTC; 0 and the largest value
+/// mean that there is no such time, as in an account that never expires
+fn filetime(value: &str) -> Option<String> {
+    let ticks: i64 = value.trim().parse().ok()?;
+    if ticks == 0 || ticks == i64::MAX {
+        return Some("never".to_string());
+    }
+    if ticks < 0 {
+        return None;
+    }
+    let (seconds, ticks) = (ticks / 10_000_000, ticks % 10_000_000);
+    let time = DateTime::from_timestamp(seconds - FILETIME_EPOCH_OFFSET, 0)?;
+    let time = time.format("%Y-%m-%dT%H:%M:%S");
+    Some(if ticks == 0 {
+        format!("{}Z", time)
+    } else {
+        format!("{}.{:07}Z", time, ticks)
+    })
+}
+
+/// The bytes of a binary value. The parser keeps binary values base64
+/// encoded as they were in the file, unless they happened to be valid UTF-8.
+fn binary(value: &str) -> Option<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(value.trim())
+        .ok()
+        .or_else(|| Some(value.as_bytes().to_vec()))
+}
+
+/// A security identifier: revision, count of sub-authorities, a 48-bit
+/// big-endian authority and 32-bit little-endian sub-authorities
+fn sid(bytes: &[u8]) -> Option<String> {
+    let (&revision, rest) = bytes.split_first()?;
+    let (&count, rest) = rest.split_first()?;
+    if revision != 1 || rest.len() != 6 + 4 * count as usize {
+        return None;
+    }
+    let (authority, subs) = rest.split_at(6);
+    let authority = authority
+        .iter()
+        .fold(0u64, |value, &byte| value << 8 | byte as u64);
+    let mut text = format!("S-{}-{}", revision, authority);
+    for sub in subs.chunks_exact(4) {
+        let sub = u32::from_le_bytes(sub.try_into().ok()?);
+        text.push_str(&format!("-{}", sub));
+    }
+    Some(text)
+}
+
+/// A GUID, whose first three fields are stored little-endian
+fn guid(bytes: &[u8]) -> Option<String> {
+    let bytes: &[u8; 16] = bytes.try_into().ok()?;
+    let data1 = u32::from_le_bytes(bytes[0..4].try_into().ok()?);
+    let data2 = u16::from_le_bytes(bytes[4..6].try_into().ok()?);
+    let data3 = u16::from_le_bytes(bytes[6..8].try_into().ok()?);
+    let hex = |bytes: &[u8]| -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() };
+    Some(format!(
+        "{:08x}-{:04x}-{:04x}-{}-{}",
+        data1,
+        data2,
+        data3,
+        hex(&bytes[8..10]),
+        hex(&bytes[10..16])
+    ))
+}
+
+/// Flags of `userAccountControl`
+const ACCOUNT_CONTROL_FLAGS: &[(u32, &str)] = &[
+    (0x0000_0001, "SCRIPT"),
+    (0x0000_0002, "ACCOUNTDISABLE"),
+    (0x0000_0008, "HOMEDIR_REQUIRED"),
+    (0x0000_0010, "LOCKOUT"),
+    (0x0000_0020, "PASSWD_NOTREQD"),
+    (0x0000_0040, "PASSWD_CANT_CHANGE"),
+    (0x0000_0080, "ENCRYPTED_TEXT_PWD_ALLOWED"),
+    (0x0000_0100, "TEMP_DUPLICATE_ACCOUNT"),
+    (0x0000_0200, "NORMAL_ACCOUNT"),
+    (0x0000_0800, "INTERDOMAIN_TRUST_ACCOUNT"),
+    (0x0000_1000, "WORKSTATION_TRUST_ACCOUNT"),
+    (0x0000_2000, "SERVER_TRUST_ACCOUNT"),
+    (0x0001_0000, "DONT_EXPIRE_PASSWORD"),
+    (0x0002_0000, "MNS_LOGON_ACCOUNT"),
+    (0x0004_0000, "SMARTCARD_REQUIRED"),
+    (0x0008_0000, "TRUSTED_FOR_DELEGATION"),
+    (0x0010_0000, "NOT_DELEGATED"),
+    (0x0020_0000, "USE_DES_KEY_ONLY"),
+    (0x0040_0000, "DONT_REQ_PREAUTH"),
+    (0x0080_0000, "PASSWORD_EXPIRED"),
+    (0x0100_0000, "TRUSTED_TO_AUTH_FOR_DELEGATION"),
+    (0x0400_0000, "PARTIAL_SECRETS_ACCOUNT"),
+];
+
+/// The names of the flags set in an account control value, with unknown
+/// bits in hex
+fn account_control(value: &str) -> Option<String> {
+    // Written as a signed 32-bit number by some tools
+    let value = value.trim();
+    let flags = value
+        .parse::<u32>()
+        .or_else(|_| value.parse::<i32>().map(|v| v as u32))
+        .ok()?;
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = flags;
+    for &(bit, name) in ACCOUNT_CONTROL_FLAGS {
+        if flags & bit != 0 {
+            names.push(name.to_string());
+            rest &= !bit;
+        }
+    }
+    if rest != 0 {
+        names.push(format!("{:#x}", rest));
+    }
+    if names.is_empty() {
+        return Some("none".to_string());
+    }
+    Some(names.join(" | "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(key: &str, value: &str) -> Option<String> {
+        ValueDecoders::default().decode(key, value)
+    }
+
+    #[test]
+    fn test_generalized_time() {
+        let decode = |value| decode("whenCreated", value);
+        assert_eq!(
+            decode("20240101120000Z").as_deref(),
+            Some("2024-01-01T12:00:00Z")
+        );
+        assert_eq!(
+            decode("20231231235959.5Z").as_deref(),
+            Some("2023-12-31T23:59:59.5Z")
+        );
+        assert_eq!(
+            decode("202403150830+0130").as_deref(),
+            Some("2024-03-15T08:30:00+01:30")
+        );
+        assert_eq!(
+            decode("2024011512-05").as_deref(),
+            Some("2024-01-15T12:00:00-05:00")
+        );
+        assert_eq!(decode("20241301120000Z"), None);
+        assert_eq!(decode("yesterday"), None);
+    }
+
+    #[test]
+    fn test_filetime() {
+        let decode = |value| decode("lastLogon", value);
+        assert_eq!(
+            decode("133496640000000000").as_deref(),
+            Some("2024-01-14T00:00:00Z")
+        );
+        assert_eq!(
+            decode("116444736000000001").as_deref(),
+            Some("1970-01-01T00:00:00.0000001Z")
+        );
+        assert_eq!(decode("0").as_deref(), Some("never"));
+        assert_eq!(decode("9223372036854775807").as_deref(), Some("never"));
+        assert_eq!(decode("-5"), None);
+    }
+
+    #[test]
+    fn test_sid_and_guid() {
+        assert_eq!(
+            decode("objectSid", "AQUAAAAAAAUVAAAAx/f+13x3VciUWs4B9QMAAA==").as_deref(),
+            Some("S-1-5-21-3623811015-3361044348-30300820-1013")
+        );
+        // The local administrators group
+        assert_eq!(
+            decode("objectSid", "AQIAAAAAAAUgAAAAIAIAAA==").as_deref(),
+            Some("S-1-5-32-544")
+        );
+        assert_eq!(
+            decode("objectGUID", "UGLxyNafNUKuoNUWmDSRIA==").as_deref(),
+            Some("c8f16250-9fd6-4235-aea0-d51698349120")
+        );
+        assert_eq!(decode("objectGUID", "c2hvcnQ="), None);
+        assert_eq!(decode("objectSid", "S-1-5-32-544"), None);
+    }
+
+    #[test]
+    fn test_account_control() {
+        assert_eq!(
+            decode("userAccountControl", "66048").as_deref(),
+            Some("NORMAL_ACCOUNT | DONT_EXPIRE_PASSWORD")
+        );
+        assert_eq!(
+            decode("userAccountControl", "514").as_deref(),
+            Some("ACCOUNTDISABLE | NORMAL_ACCOUNT")
+        );
+        assert_eq!(decode("userAccountControl", "4").as_deref(), Some("0x4"));
+    }
+
+    #[test]
+    fn test_configured_decoders() {
+        let overrides: BTreeMap<String, Decoder> =
+            toml::from_str("lastLogon = \"none\"\nexpires = \"generalized_time\"\n").unwrap();
+        let decoders = ValueDecoders::new(&overrides);
+        assert_eq!(decoders.decode("LASTLOGON", "133496640000000000"), None);
+        assert_eq!(
+            decoders.decode("expires", "20240101000000Z").as_deref(),
+            Some("2024-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            ValueDecoders::none().decode("whenCreated", "20240101000000Z"),
+            None
+        );
+        assert_eq!(decode("cn", "20240101000000Z"), None);
+    }
+}
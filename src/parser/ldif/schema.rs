@@ -0,0 +1,322 @@
+//! Friendly names of LDAP attributes.
+//!
+//! Directory dumps, from Active Directory in particular, are full of
+//! attribute names such as `msDS-parentdistname` or `pwdLastSet` that say
+//! little to anyone but a directory administrator. This table gives the
+//! well-known ones a label and a category, which the viewer shows after the
+//! attribute's key, and tells which attributes hold DNs of other entries,
+//! such as `memberOf`, so that `gd` can jump to them.
+//!
+//! `[parser.ldif.attribute_labels]` in the config file adds attributes or
+//! renames built-in ones. Attribute names compare without regard to case,
+//! as in LDAP.
+
+use crate::query::path::is_index_label;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// What an attribute is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    /// Names and identifiers of the entry
+    Identity,
+    /// Groups, members and other entries the entry refers to
+    Membership,
+    /// When the entry was created, changed or last used
+    Timestamps,
+    /// Passwords, account flags and access control
+    Security,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Identity => "identity",
+            Category::Membership => "membership",
+            Category::Timestamps => "timestamps",
+            Category::Security => "security",
+        })
+    }
+}
+
+/// An attribute's entry in `[parser.ldif.attribute_labels]`: just a label,
+/// as in `employeeNumber = "Staff number"`, or a table such as
+/// `sponsor = { label = "Sponsor", category = "membership", dn = true }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeLabel {
+    Label(String),
+    Info {
+        label: String,
+        #[serde(default)]
+        category: Option<Category>,
+        /// Whether the values are DNs of other entries
+        #[serde(default)]
+        dn: bool,
+    },
+}
+
+/// What is known about an attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeInfo {
+    pub label: String,
+    pub category: Option<Category>,
+    /// Whether the values are DNs of other entries
+    pub dn: bool,
+}
+
+impl AttributeInfo {
+    /// The label and category as shown after the key, e.g.
+    /// `Member of · membership`
+    pub fn describe(&self) -> String {
+        match self.category {
+            Some(category) => format!("{} · {}", self.label, category),
+            None => self.label.to_string(),
+        }
+    }
+}
+
+/// Built-in attributes: name, label, category and whether values are DNs
+const BUILTIN: &[(&str, &str, Option<Category>, bool)] = {
+    use Category::*;
+    &[
+        // Identity
+        ("cn", "Common name", Some(Identity), false),
+        ("sn", "Surname", Some(Identity), false),
+        ("givenName", "Given name", Some(Identity), false),
+        ("displayName", "Display name", Some(Identity), false),
+        ("name", "Name", Some(Identity), false),
+        ("uid", "User ID", Some(Identity), false),
+        ("uidNumber", "Numeric user ID", Some(Identity), false),
+        ("gidNumber", "Numeric group ID", Some(Identity), false),
+        ("sAMAccountName", "Logon name", Some(Identity), false),
+        (
+            "userPrincipalName",
+            "User principal name",
+            Some(Identity),
+            false,
+        ),
+        ("mail", "Email address", Some(Identity), false),
+        ("employeeID", "Employee ID", Some(Identity), false),
+        ("employeeNumber", "Employee number", Some(Identity), false),
+        ("objectGUID", "Object GUID", Some(Identity), false),
+        (
+            "distinguishedName",
+            "Distinguished name",
+            Some(Identity),
+            true,
+        ),
+        ("msDS-parentdistname", "Parent DN", Some(Identity), true),
+        ("o", "Organization", Some(Identity), false),
+        ("ou", "Organizational unit", Some(Identity), false),
+        ("dc", "Domain component", Some(Identity), false),
+        ("c", "Country", None, false),
+        ("l", "Locality", None, false),
+        ("st", "State or province", None, false),
+        ("telephoneNumber", "Phone number", None, false),
+        ("title", "Job title", None, false),
+        ("objectClass", "Object class", None, false),
+        ("homeDirectory", "Home directory", None, false),
+        ("loginShell", "Login shell", None, false),
+        // Membership
+        ("member", "Member", Some(Membership), true),
+        ("memberOf", "Member of", Some(Membership), true),
+        ("uniqueMember", "Member", Some(Membership), true),
+        ("memberUid", "Member user ID", Some(Membership), false),
+        (
+            "primaryGroupID",
+            "Primary group RID",
+            Some(Membership),
+            false,
+        ),
+        ("manager", "Manager", Some(Membership), true),
+        ("directReports", "Direct report", Some(Membership), true),
+        ("managedBy", "Managed by", Some(Membership), true),
+        ("owner", "Owner", Some(Membership), true),
+        ("secretary", "Secretary", Some(Membership), true),
+        ("seeAlso", "See also", Some(Membership), true),
+        // Timestamps
+        ("whenCreated", "Created", Some(Timestamps), false),
+        ("whenChanged", "Changed", Some(Timestamps), false),
+        ("createTimestamp", "Created", Some(Timestamps), false),
+        ("modifyTimestamp", "Modified", Some(Timestamps), false),
+        ("creatorsName", "Created by", Some(Timestamps), true),
+        ("modifiersName", "Modified by", Some(Timestamps), true),
+        ("lastLogon", "Last logon", Some(Timestamps), false),
+        (
+            "lastLogonTimestamp",
+            "Last logon (replicated)",
+            Some(Timestamps),
+            false,
+        ),
+        ("lastLogoff", "Last logoff", Some(Timestamps), false),
+        ("pwdLastSet", "Password last set", Some(Timestamps), false),
+        ("accountExpires", "Account expires", Some(Timestamps), false),
+        (
+            "badPasswordTime",
+            "Last bad password",
+            Some(Timestamps),
+            false,
+        ),
+        ("lockoutTime", "Locked out at", Some(Timestamps), false),
+        (
+            "shadowLastChange",
+            "Password changed (days)",
+            Some(Timestamps),
+            false,
+        ),
+        // Security
+        ("userPassword", "Password", Some(Security), false),
+        ("unicodePwd", "Password", Some(Security), false),
+        ("userAccountControl", "Account flags", Some(Security), false),
+        (
+            "msDS-User-Account-Control-Computed",
+            "Computed account flags",
+            Some(Security),
+            false,
+        ),
+        (
+            "nTSecurityDescriptor",
+            "Security descriptor",
+            Some(Security),
+            false,
+        ),
+        ("objectSid", "Security identifier", Some(Security), false),
+        (
+            "sIDHistory",
+            "Former security identifiers",
+            Some(Security),
+            false,
+        ),
+        ("badPwdCount", "Bad password count", Some(Security), false),
+        (
+            "adminCount",
+            "Protected by AdminSDHolder",
+            Some(Security),
+            false,
+        ),
+        (
+            "pwdAccountLockedTime",
+            "Locked out at",
+            Some(Security),
+            false,
+        ),
+        (
+            "servicePrincipalName",
+            "Service principal name",
+            Some(Security),
+            false,
+        ),
+    ]
+};
+
+/// The built-in table extended by the config file
+#[derive(Debug, Clone)]
+pub struct AttributeLabels {
+    /// By lowercase attribute name
+    by_name: HashMap<String, AttributeInfo>,
+}
+
+/// The built-in table alone
+impl Default for AttributeLabels {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl AttributeLabels {
+    /// The built-in attributes, with `overrides` added or replacing them
+    pub fn new(overrides: &BTreeMap<String, AttributeLabel>) -> Self {
+        let mut by_name: HashMap<String, AttributeInfo> = BUILTIN
+            .iter()
+            .map(|&(name, label, category, dn)| {
+                let info = AttributeInfo {
+                    label: label.to_string(),
+                    category,
+                    dn,
+                };
+                (name.to_lowercase(), info)
+            })
+            .collect();
+        for (name, label) in overrides {
+            let name = name.to_lowercase();
+            let info = match label {
+                // A label alone keeps what the table knows otherwise
+                AttributeLabel::Label(label) => AttributeInfo {
+                    label: label.clone(),
+                    ..by_name.get(&name).cloned().unwrap_or(AttributeInfo {
+                        label: String::new(),
+                        category: None,
+                        dn: false,
+                    })
+                },
+                AttributeLabel::Info {
+                    label,
+                    category,
+                    dn,
+                } => AttributeInfo {
+                    label: label.clone(),
+                    category: *category,
+                    dn: *dn,
+                },
+            };
+            by_name.insert(name, info);
+        }
+        Self { by_name }
+    }
+
+    /// What is known about the attribute `name`, which may carry the index
+    /// of a multi-valued attribute's node, as in `memberOf [2]`
+    pub fn get(&self, name: &str) -> Option<&AttributeInfo> {
+        let name = match name.rsplit_once(' ') {
+            Some((name, index)) if is_index_label(index) => name,
+            _ => name,
+        };
+        self.by_name.get(&name.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_and_configured_labels() {
+        let overrides: BTreeMap<String, AttributeLabel> = toml::from_str(
+            "employeeNumber = \"Staff number\"\n\
+             sponsor = { label = \"Sponsor\", category = \"membership\", dn = true }\n",
+        )
+        .unwrap();
+        let labels = AttributeLabels::new(&overrides);
+
+        let member_of = labels.get("MEMBEROF [3]").unwrap();
+        assert_eq!(member_of.describe(), "Member of · membership");
+        assert!(member_of.dn);
+        assert_eq!(
+            labels.get("objectClass").unwrap().describe(),
+            "Object class"
+        );
+        assert!(labels.get("mystery").is_none());
+
+        // A label alone renames, and keeps the category
+        let staff = labels.get("employeenumber").unwrap();
+        assert_eq!(staff.describe(), "Staff number · identity");
+        let sponsor = labels.get("sponsor").unwrap();
+        assert_eq!(sponsor.category, Some(Category::Membership));
+        assert!(sponsor.dn);
+    }
+
+    #[test]
+    fn test_builtin_names_are_unique() {
+        let mut names: Vec<String> = BUILTIN
+            .iter()
+            .map(|(name, ..)| name.to_lowercase())
+            .collect();
+        names.sort();
+        let count = names.len();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+}
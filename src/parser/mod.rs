@@ -1,15 +1,35 @@
+pub(crate) mod chunks;
 pub mod html;
 pub mod json;
 pub mod jsonlines;
 pub mod ldif;
+pub mod registry;
+pub(crate) mod span;
 pub mod toml;
 pub mod xml;
 pub mod yaml;
 
 use crate::error::{Result, XtvError};
-use crate::tree::Tree;
+use crate::tree::export::{self, MAX_EXPORT_DEPTH};
+use crate::tree::{StreamingTree, Tree, TreeNode, TreeVariant, ValueKind};
+use crate::util::natural_cmp;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+pub use registry::{ParserFormat, ParserRegistry};
+
+/// Options of the parsers that have any, from the `[parser.<format>]`
+/// sections of the config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParserOptions {
+    #[serde(default)]
+    pub html: html::HtmlOptions,
+    #[serde(default)]
+    pub jsonl: jsonlines::JsonLinesOptions,
+    #[serde(default)]
+    pub ldif: ldif::LdifOptions,
+}
+
 /// Trait for parsing different file formats into a Tree.
 ///
 /// Each file format (JSON, XML, YAML, LDIF, etc.) has its own parser implementation
@@ -17,7 +37,8 @@ use std::path::Path;
 ///
 /// # Implementing a Parser
 ///
-/// To add support for a new format, implement this trait:
+/// To add support for a new format, implement this trait and register it in
+/// a [`ParserRegistry`]:
 ///
 /// ```ignore
 /// use xtv::parser::Parser;
@@ -33,6 +54,10 @@ use std::path::Path;
 ///         todo!()
 ///     }
 ///
+///     fn format_name(&self) -> &'static str {
+///         "myformat"
+///     }
+///
 ///     fn can_parse(&self, file_path: &Path) -> bool {
 ///         file_path.extension()
 ///             .and_then(|ext| ext.to_str())
@@ -58,6 +83,37 @@ pub trait Parser {
     /// Returns an error if the content is malformed or cannot be parsed.
     fn parse(&self, content: &str) -> Result<Tree>;
 
+    /// Gets the canonical name of the format handled by this parser.
+    ///
+    /// This is the name accepted by `--format` and used as key for
+    /// per-format settings such as `[streaming.thresholds]`.
+    fn format_name(&self) -> &'static str;
+
+    /// Checks if this format can be viewed without loading it into memory.
+    ///
+    /// Parsers returning `true` must implement [`build_index`](Parser::build_index).
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
+    /// Builds a streaming index for a file without loading it into memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - Path to the file to index
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::UnsupportedFormat` by default; parsers that support
+    /// streaming return I/O or parse errors from indexing.
+    fn build_index(&self, file_path: &Path) -> Result<StreamingTree> {
+        let _ = file_path;
+        Err(XtvError::UnsupportedFormat(format!(
+            "Streaming is not supported for {}",
+            self.format_name()
+        )))
+    }
+
     /// Checks if this parser can handle the given file path.
     ///
     /// Typically checks the file extension to determine compatibility.
@@ -72,11 +128,107 @@ pub trait Parser {
     fn can_parse(&self, file_path: &Path) -> bool;
 }
 
-/// Detects and returns the appropriate parser for a file based on extension.
+/// Companion to [`Parser`] for writing subtrees back in the parser's format.
+///
+/// Values are written as the [`ValueKind`] the parser recorded, so a quoted
+/// `"42"` in the input stays a string, and typed by how they read where it
+/// recorded none. Parsing the output yields the same tree again.
+pub trait Serializer {
+    /// Writes the subtree under `node_id`.
+    ///
+    /// The root stands for the whole document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node doesn't exist, the subtree is too large or
+    /// too deeply nested, or it can't be expressed in the format (such as a
+    /// null value in TOML).
+    fn serialize(&self, tree: &TreeVariant, node_id: usize) -> Result<String>;
+}
+
+/// Gets the serializer for a format name as returned by [`Parser::format_name`].
+///
+/// HTML is written as XML markup, which HTML parsers accept as well.
+///
+/// # Returns
+///
+/// `None` if the format can't be written.
+pub fn get_serializer(format: &str) -> Option<Box<dyn Serializer>> {
+    match format {
+        "json" => Some(Box::new(json::JsonParser)),
+        "jsonl" => Some(Box::new(jsonlines::JsonLinesParser::default())),
+        "xml" | "html" => Some(Box::new(xml::XmlParser)),
+        "ldif" => Some(Box::new(ldif::LdifParser::default())),
+        "toml" => Some(Box::new(toml::TomlParser)),
+        "yaml" => Some(Box::new(yaml::YamlParser)),
+        _ => None,
+    }
+}
+
+/// The subtree under `node_id` as JSON, for serializers building on it
+fn export_value(tree: &TreeVariant, node_id: usize) -> Result<serde_json::Value> {
+    let export = export::to_json(tree, node_id).ok_or(XtvError::InvalidNodeId(node_id))?;
+    if export.truncated {
+        return Err(XtvError::Serialize(format!(
+            "subtree has more than {} nodes or {} levels",
+            export::MAX_EXPORT_NODES,
+            MAX_EXPORT_DEPTH
+        )));
+    }
+    Ok(export.value)
+}
+
+/// An attribute node holding a value of the given kind
+fn value_node(label: String, value: String, kind: ValueKind) -> TreeNode {
+    let mut node = TreeNode::new(label, TreeNode::ATTRIBUTE_TYPE).with_value_kind(kind);
+    node.add_attribute("value", value);
+    node
+}
+
+/// Fails once a serializer walking the tree recursively gets too deep
+fn check_depth(depth: usize) -> Result<()> {
+    if depth > MAX_EXPORT_DEPTH {
+        return Err(XtvError::Serialize(format!(
+            "subtree is nested deeper than {} levels",
+            MAX_EXPORT_DEPTH
+        )));
+    }
+    Ok(())
+}
+
+/// Add a virtual `@attributes` node below `element_id` holding one node per
+/// attribute, sorted by name, if there are any attributes. The markup parsers
+/// call it before adding the element's children, so it comes first.
+fn add_virtual_attributes_node(
+    tree: &mut Tree,
+    element_id: usize,
+    attributes: &[crate::tree::node::Attribute],
+) {
+    if attributes.is_empty() {
+        return;
+    }
+
+    let virtual_node = TreeNode::new("@attributes", TreeNode::VIRTUAL_ATTRIBUTES_TYPE);
+    let virtual_id = tree.add_child_node(element_id, virtual_node);
+
+    // Sort attributes by key in natural order
+    let mut sorted_attrs = attributes.to_vec();
+    sorted_attrs.sort_by(|a, b| natural_cmp(&a.key, &b.key));
+
+    for attr in sorted_attrs {
+        let mut attr_node = TreeNode::new(&attr.key, TreeNode::ATTRIBUTE_TYPE);
+        attr_node.add_attribute("value", &attr.value);
+        tree.add_child_node(virtual_id, attr_node);
+    }
+}
+
+/// Detects and returns the appropriate built-in parser for a file based on
+/// extension, see [`ParserRegistry::by_extension`].
 ///
 /// # Arguments
 ///
 /// * `file_path` - Path to the file to detect parser for
+/// * `options` - Options the parser is constructed with
 ///
 /// # Returns
 ///
@@ -96,37 +248,18 @@ pub trait Parser {
 /// # Examples
 ///
 /// ```ignore
-/// use xtv::parser::detect_parser;
+/// use xtv::parser::{ParserOptions, detect_parser};
 /// use std::path::Path;
 ///
-/// let parser = detect_parser(Path::new("data.json"))?;
+/// let parser = detect_parser(Path::new("data.json"), &ParserOptions::default())?;
 /// let tree = parser.parse(content)?;
 /// ```
-pub fn detect_parser(file_path: &Path) -> Result<Box<dyn Parser>> {
-    let extension = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|s| s.to_lowercase());
-
-    match extension.as_deref() {
-        Some("json") => Ok(Box::new(json::JsonParser)),
-        Some("jsonl") => Ok(Box::new(jsonlines::JsonLinesParser)),
-        Some("xml") => Ok(Box::new(xml::XmlParser)),
-        Some("html") | Some("htm") => Ok(Box::new(html::HtmlParser)),
-        Some("ldif") => Ok(Box::new(ldif::LdifParser)),
-        Some("toml") => Ok(Box::new(toml::TomlParser)),
-        Some("yaml") | Some("yml") => Ok(Box::new(yaml::YamlParser)),
-        Some(ext) => Err(XtvError::UnsupportedFormat(format!(
-            "File extension '.{}' is not supported",
-            ext
-        ))),
-        None => Err(XtvError::UnsupportedFormat(
-            "File has no extension".to_string(),
-        )),
-    }
+pub fn detect_parser(file_path: &Path, options: &ParserOptions) -> Result<Box<dyn Parser>> {
+    ParserRegistry::default().by_extension(file_path, options)
 }
 
-/// Gets a parser from a format string.
+/// Gets a built-in parser from a format string, see
+/// [`ParserRegistry::by_name`].
 ///
 /// This is useful when the user explicitly specifies the format via CLI flag
 /// (e.g., `--format json`) instead of relying on file extension detection.
@@ -134,6 +267,7 @@ pub fn detect_parser(file_path: &Path) -> Result<Box<dyn Parser>> {
 /// # Arguments
 ///
 /// * `format` - Format name (case-insensitive)
+/// * `options` - Options the parser is constructed with
 ///
 /// # Returns
 ///
@@ -153,28 +287,35 @@ pub fn detect_parser(file_path: &Path) -> Result<Box<dyn Parser>> {
 /// # Examples
 ///
 /// ```ignore
-/// use xtv::parser::get_parser_from_format;
+/// use xtv::parser::{ParserOptions, get_parser_from_format};
 ///
-/// let parser = get_parser_from_format("json")?;
+/// let parser = get_parser_from_format("json", &ParserOptions::default())?;
 /// let tree = parser.parse(content)?;
 /// ```
-pub fn get_parser_from_format(format: &str) -> Result<Box<dyn Parser>> {
-    match format.to_lowercase().as_str() {
-        "json" => Ok(Box::new(json::JsonParser)),
-        "jsonl" | "jsonlines" => Ok(Box::new(jsonlines::JsonLinesParser)),
-        "xml" => Ok(Box::new(xml::XmlParser)),
-        "html" | "htm" => Ok(Box::new(html::HtmlParser)),
-        "ldif" => Ok(Box::new(ldif::LdifParser)),
-        "toml" => Ok(Box::new(toml::TomlParser)),
-        "yaml" | "yml" => Ok(Box::new(yaml::YamlParser)),
-        _ => Err(XtvError::UnsupportedFormat(format!(
-            "Format '{}' is not supported",
-            format
-        ))),
-    }
+pub fn get_parser_from_format(format: &str, options: &ParserOptions) -> Result<Box<dyn Parser>> {
+    ParserRegistry::default().by_name(format, options)
+}
+
+/// Selects the built-in parser for a file from an explicit format, its
+/// extension, or its content, see [`ParserRegistry::for_file`].
+///
+/// # Errors
+///
+/// Returns an error if the format is unknown, the file cannot be read, or the
+/// format cannot be detected.
+pub fn detect_parser_for_file(
+    file_path: &Path,
+    format: Option<&str>,
+    options: &ParserOptions,
+) -> Result<Box<dyn Parser>> {
+    ParserRegistry::default().for_file(file_path, format, options)
 }
 
-/// Detects parser from content by analyzing the file format.
+/// Number of leading bytes inspected when detecting a file's format from content
+const CONTENT_DETECTION_BYTES: usize = 4096;
+
+/// Detects a built-in parser from content by analyzing the file format, see
+/// [`ParserRegistry::by_content`].
 ///
 /// This is used when reading from stdin or when the file has no extension.
 /// The function examines the content's structure to guess the format.
@@ -182,6 +323,7 @@ pub fn get_parser_from_format(format: &str) -> Result<Box<dyn Parser>> {
 /// # Arguments
 ///
 /// * `content` - The file content to analyze
+/// * `options` - Options the parser is constructed with
 ///
 /// # Returns
 ///
@@ -190,7 +332,7 @@ pub fn get_parser_from_format(format: &str) -> Result<Box<dyn Parser>> {
 ///
 /// # Detection Heuristics
 ///
-/// - Starts with `<?xml` or `<` → XML or HTML (checks for DOCTYPE/html tag)
+/// - Starts with `<` → HTML if it has a DOCTYPE or html tag, otherwise XML
 /// - Starts with `{` or `[` → JSON
 /// - Starts with `version:` or `dn:` → LDIF
 /// - Starts with `---` or `%YAML` → YAML
@@ -204,37 +346,176 @@ pub fn get_parser_from_format(format: &str) -> Result<Box<dyn Parser>> {
 /// # Examples
 ///
 /// ```ignore
-/// use xtv::parser::detect_parser_from_content;
+/// use xtv::parser::{ParserOptions, detect_parser_from_content};
 ///
 /// let content = "{\"key\": \"value\"}";
-/// let parser = detect_parser_from_content(content)?;
+/// let parser = detect_parser_from_content(content, &ParserOptions::default())?;
 /// let tree = parser.parse(content)?;
 /// ```
-pub fn detect_parser_from_content(content: &str) -> Result<Box<dyn Parser>> {
-    let trimmed = content.trim_start();
-
-    // Try to detect format from content
-    if trimmed.starts_with("<?xml") || trimmed.starts_with('<') {
-        // Could be XML or HTML
-        if trimmed.contains("<!DOCTYPE html") || trimmed.contains("<html") {
-            Ok(Box::new(html::HtmlParser))
-        } else {
-            Ok(Box::new(xml::XmlParser))
+pub fn detect_parser_from_content(
+    content: &str,
+    options: &ParserOptions,
+) -> Result<Box<dyn Parser>> {
+    ParserRegistry::default().by_content(content, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compare two subtrees by labels, types, attributes and value kinds
+    fn assert_same_subtree(expected: &Tree, expected_id: usize, actual: &Tree, actual_id: usize) {
+        let a = expected.get_node(expected_id).unwrap();
+        let b = actual.get_node(actual_id).unwrap();
+        let attributes = |node: &crate::tree::TreeNode| -> Vec<(String, String)> {
+            node.attributes
+                .iter()
+                .map(|attr| (attr.key.clone(), attr.value.clone()))
+                .collect()
+        };
+        assert_eq!(
+            (&a.label, &a.node_type, attributes(a), a.value_kind),
+            (&b.label, &b.node_type, attributes(b), b.value_kind)
+        );
+        assert_eq!(
+            a.children.len(),
+            b.children.len(),
+            "children of {}",
+            a.label
+        );
+        for (&x, &y) in a.children.iter().zip(&b.children) {
+            assert_same_subtree(expected, x, actual, y);
         }
-    } else if trimmed.starts_with('{') || trimmed.starts_with('[') {
-        Ok(Box::new(json::JsonParser))
-    } else if trimmed.starts_with("version:") || trimmed.starts_with("dn:") {
-        Ok(Box::new(ldif::LdifParser))
-    } else if trimmed.starts_with("---") || trimmed.starts_with("%YAML") {
-        // YAML document separator or directive
-        Ok(Box::new(yaml::YamlParser))
-    } else if trimmed.contains(':') && !trimmed.contains("::") {
-        // Generic YAML-like structure (key: value pairs)
-        // Try YAML as it's more flexible than LDIF
-        Ok(Box::new(yaml::YamlParser))
-    } else {
-        Err(XtvError::UnsupportedFormat(
-            "Could not detect format from content. Use --format to specify the format.".to_string(),
-        ))
+    }
+
+    /// Parse `content`, write the whole tree and parse the output again
+    fn assert_round_trip(parser: &dyn Parser, content: &str) {
+        let tree = parser.parse(content).unwrap();
+        let format = tree.format().unwrap();
+        let tree = TreeVariant::from(tree);
+        let serializer = get_serializer(format).unwrap();
+        let output = serializer.serialize(&tree, tree.root_id()).unwrap();
+
+        let TreeVariant::InMemory(tree) = &tree else {
+            unreachable!()
+        };
+        let reparsed = parser.parse(&output).unwrap();
+        assert_same_subtree(tree, tree.root_id(), &reparsed, reparsed.root_id());
+    }
+
+    #[test]
+    fn test_round_trip_sample_files() {
+        assert_round_trip(
+            &yaml::YamlParser,
+            include_str!("../../examples/sample.yaml"),
+        );
+        assert_round_trip(
+            &jsonlines::JsonLinesParser::default(),
+            include_str!("../../examples/sample.jsonl"),
+        );
+    }
+
+    #[test]
+    fn test_round_trip_json_and_toml() {
+        let json = r#"{"name": "x", "n": [1, 2.5, true, null, "42"], "deep": {"a": {"b": []}}}"#;
+        assert_round_trip(&json::JsonParser, json);
+        assert_round_trip(&json::JsonParser, r#"[{"a": 1}, "b"]"#);
+
+        let toml = "title = \"demo\"\nwhen = 1979-05-27T07:32:00Z\n\n[server]\nport = 8080\nhosts = [\"a\", \"b\"]\n\n[[users]]\nname = \"ann\"\n";
+        assert_round_trip(&toml::TomlParser, toml);
+    }
+
+    #[test]
+    fn test_round_trip_empty_and_index_keyed_roots() {
+        assert_round_trip(&json::JsonParser, "[]");
+        assert_round_trip(&json::JsonParser, r#"{"[0]": 1}"#);
+        assert_round_trip(&yaml::YamlParser, "[]\n");
+        assert_round_trip(&yaml::YamlParser, "'[0]': a\n'[1]': b\n");
+    }
+
+    #[test]
+    fn test_round_trip_markup() {
+        let xml = r#"<?xml version="1.0"?>
+<shop open="yes &amp; no">
+  <item id="1">Dune &lt;1965&gt;</item>
+  <item id="2"><title>Emma</title>text<empty/></item>
+</shop>"#;
+        assert_round_trip(&xml::XmlParser, xml);
+
+        let html = "<html><body><p class=\"a\">Hi <b>there</b></p><!-- note --><br></body></html>";
+        assert_round_trip(&html::HtmlParser::default(), html);
+    }
+
+    #[test]
+    fn test_round_trip_ldif() {
+        let ldif = "version: 1\n\ndn: dc=example\ndc: example\n\ndn: cn=a,dc=example\ncn: a\nmail: a@x\nmail: b@x\ndescription:: IGxlYWRpbmcgc3BhY2U=\n\ndn: cn=b,cn=a,dc=example\ncn: b\n";
+        assert_round_trip(&ldif::LdifParser::default(), ldif);
+    }
+
+    /// Every node but the root has a parent that lists it as a child
+    fn assert_parents_linked(parser: &dyn Parser, content: &str) {
+        let tree = parser.parse(content).unwrap();
+        assert_eq!(tree.get_parent(tree.root_id()), None);
+        for node_id in (0..tree.node_count()).filter(|&id| id != tree.root_id()) {
+            let label = &tree.get_node(node_id).unwrap().label;
+            let parent_id = tree
+                .get_parent(node_id)
+                .unwrap_or_else(|| panic!("{} ({}) has no parent", label, parser.format_name()));
+            assert!(
+                tree.get_children(parent_id).contains(&node_id),
+                "{} ({}) is not among its parent's children",
+                label,
+                parser.format_name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_parsed_node_has_a_parent() {
+        assert_parents_linked(&json::JsonParser, r#"{"a": [1, {"b": null}], "c": "d"}"#);
+        assert_parents_linked(
+            &jsonlines::JsonLinesParser::default(),
+            include_str!("../../examples/sample.jsonl"),
+        );
+        assert_parents_linked(
+            &yaml::YamlParser,
+            include_str!("../../examples/sample.yaml"),
+        );
+        assert_parents_linked(&toml::TomlParser, "a = 1\n[b]\nc = [2, 3]\n[[d]]\ne = 4\n");
+        assert_parents_linked(
+            &xml::XmlParser,
+            r#"<?xml version="1.0"?><a x="1" y="2"><b z="3">t</b><c w="4"/></a>"#,
+        );
+        assert_parents_linked(
+            &html::HtmlParser::default(),
+            "<!DOCTYPE html><html lang=\"en\"><body><p class=\"a\" id=\"b\">Hi<br></p></body></html>",
+        );
+        assert_parents_linked(
+            &ldif::LdifParser::default(),
+            "dn: dc=example\ndc: example\n\ndn: cn=a,dc=example\ncn: a\n",
+        );
+    }
+
+    #[test]
+    fn test_serializing_subtrees() {
+        let tree = TreeVariant::from(yaml::YamlParser.parse("a:\n  b: !t 1\n  c: [x]\n").unwrap());
+        let a = tree.get_children(tree.root_id())[0];
+        let yaml = yaml::YamlParser.serialize(&tree, a).unwrap();
+        assert_eq!(yaml, "b: !t 1\nc:\n- x\n");
+
+        let tree = TreeVariant::from(toml::TomlParser.parse("[server]\nport = 1\n").unwrap());
+        let server = tree.get_children(tree.root_id())[0];
+        let port = tree.get_children(server)[0];
+        assert_eq!(
+            toml::TomlParser.serialize(&tree, server).unwrap(),
+            "port = 1\n"
+        );
+        assert_eq!(
+            toml::TomlParser.serialize(&tree, port).unwrap(),
+            "port = 1\n"
+        );
+
+        let tree = TreeVariant::from(json::JsonParser.parse(r#"{"a": null}"#).unwrap());
+        assert!(toml::TomlParser.serialize(&tree, tree.root_id()).is_err());
     }
 }
@@ -0,0 +1,344 @@
+//! The formats xtv can read and how to recognize them.
+//!
+//! Every format is a [`ParserFormat`]: its name, the file extensions it is
+//! used for, an optional probe that recognizes its content, and a function
+//! building its parser. The built-in formats declare theirs next to their
+//! parsers; library users add their own with [`ParserRegistry::register`]
+//! and hand the registry to the viewer with
+//! [`App::with_parsers`](crate::ui::app::App::with_parsers).
+//!
+//! Formats registered later are consulted first, so a registration can take
+//! over the name or an extension of a built-in format, and its probe runs
+//! before the built-in ones.
+
+use super::{CONTENT_DETECTION_BYTES, Parser, ParserOptions};
+use crate::error::{Result, XtvError};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Builds a parser with the options from the config file
+pub type ParserConstructor = fn(&ParserOptions) -> Box<dyn Parser>;
+
+/// Whether content, with leading whitespace removed, is in a format
+pub type ContentProbe = fn(&str) -> bool;
+
+/// A format in a [`ParserRegistry`]
+#[derive(Debug, Clone, Copy)]
+pub struct ParserFormat {
+    /// Name accepted by `--format`, as returned by [`Parser::format_name`]
+    pub name: &'static str,
+    /// Further names accepted by `--format`
+    pub aliases: &'static [&'static str],
+    /// File extensions without the dot, in lowercase
+    pub extensions: &'static [&'static str],
+    /// Recognizes the format from the beginning of a file or stdin
+    pub probe: Option<ContentProbe>,
+    pub constructor: ParserConstructor,
+}
+
+impl ParserFormat {
+    /// A format that is only used when asked for by name
+    pub const fn new(name: &'static str, constructor: ParserConstructor) -> Self {
+        Self {
+            name,
+            aliases: &[],
+            extensions: &[],
+            probe: None,
+            constructor,
+        }
+    }
+
+    /// Sets further names for `--format` (builder pattern)
+    pub const fn with_aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Sets the file extensions the format is used for (builder pattern)
+    pub const fn with_extensions(mut self, extensions: &'static [&'static str]) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Sets the probe recognizing the format's content (builder pattern)
+    pub const fn with_probe(mut self, probe: ContentProbe) -> Self {
+        self.probe = Some(probe);
+        self
+    }
+
+    fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+}
+
+/// The formats that files and stdin can be read in.
+///
+/// The default registry holds the built-in formats.
+///
+/// # Examples
+///
+/// Reading a format of key=value lines:
+///
+/// ```
+/// use std::path::Path;
+/// use xtv::error::Result;
+/// use xtv::parser::{Parser, ParserFormat, ParserOptions, ParserRegistry};
+/// use xtv::tree::{Tree, TreeNode};
+///
+/// struct PropertiesParser;
+///
+/// impl Parser for PropertiesParser {
+///     fn parse(&self, content: &str) -> Result<Tree> {
+///         let mut tree = Tree::new(TreeNode::new("root", "root")).with_format("properties");
+///         for (key, value) in content.lines().filter_map(|line| line.split_once('=')) {
+///             let mut node = TreeNode::new(key.trim(), TreeNode::ATTRIBUTE_TYPE);
+///             node.add_attribute("value", value.trim());
+///             tree.add_child_node(0, node);
+///         }
+///         Ok(tree)
+///     }
+///
+///     fn format_name(&self) -> &'static str {
+///         "properties"
+///     }
+///
+///     fn can_parse(&self, file_path: &Path) -> bool {
+///         file_path.extension().is_some_and(|ext| ext == "properties")
+///     }
+/// }
+///
+/// let mut registry = ParserRegistry::default();
+/// registry.register(
+///     ParserFormat::new("properties", |_| Box::new(PropertiesParser))
+///         .with_extensions(&["properties"]),
+/// );
+///
+/// let options = ParserOptions::default();
+/// let parser = registry.by_extension(Path::new("app.properties"), &options).unwrap();
+/// let tree = parser.parse("name = xtv\nversion = 1").unwrap();
+/// assert_eq!(tree.get_node(1).unwrap().label, "name");
+/// assert!(registry.names().contains(&"properties"));
+/// // The built-in formats are still there
+/// assert!(registry.by_name("json", &options).is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParserRegistry {
+    formats: Vec<ParserFormat>,
+}
+
+impl Default for ParserRegistry {
+    /// The built-in formats
+    fn default() -> Self {
+        // Lowest priority first: the loose YAML probe only runs when no
+        // other format recognized the content, and HTML is tried before XML
+        let mut registry = Self::empty();
+        for format in [
+            super::yaml::FORMAT,
+            super::toml::FORMAT,
+            super::ldif::FORMAT,
+            super::json::FORMAT,
+            super::jsonlines::FORMAT,
+            super::xml::FORMAT,
+            super::html::FORMAT,
+        ] {
+            registry.register(format);
+        }
+        registry
+    }
+}
+
+impl ParserRegistry {
+    /// A registry without any formats
+    pub fn empty() -> Self {
+        Self {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Adds a format, which takes precedence over the ones registered before
+    pub fn register(&mut self, format: ParserFormat) {
+        self.formats.push(format);
+    }
+
+    /// The registered formats, the ones consulted first first
+    pub fn formats(&self) -> impl Iterator<Item = &ParserFormat> {
+        self.formats.iter().rev()
+    }
+
+    /// Names of the registered formats in alphabetical order, without
+    /// aliases
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.formats.iter().map(|format| format.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Gets the parser of a format by its name or an alias, ignoring case.
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::UnsupportedFormat`, listing the known formats, if
+    /// no format has the name.
+    pub fn by_name(&self, name: &str, options: &ParserOptions) -> Result<Box<dyn Parser>> {
+        match self.formats().find(|format| format.is_named(name)) {
+            Some(format) => Ok((format.constructor)(options)),
+            None => Err(XtvError::UnsupportedFormat(format!(
+                "Format '{}' is not supported (known formats: {})",
+                name,
+                self.names().join(", ")
+            ))),
+        }
+    }
+
+    /// Gets the parser for a file by its extension, ignoring case.
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::UnsupportedFormat` if the file has no extension or
+    /// no format uses it.
+    pub fn by_extension(
+        &self,
+        file_path: &Path,
+        options: &ParserOptions,
+    ) -> Result<Box<dyn Parser>> {
+        let Some(extension) = file_path.extension().and_then(|ext| ext.to_str()) else {
+            return Err(XtvError::UnsupportedFormat(
+                "File has no extension".to_string(),
+            ));
+        };
+        let extension = extension.to_lowercase();
+        self.formats()
+            .find(|format| format.extensions.contains(&extension.as_str()))
+            .map(|format| (format.constructor)(options))
+            .ok_or_else(|| {
+                XtvError::UnsupportedFormat(format!(
+                    "File extension '.{}' is not supported",
+                    extension
+                ))
+            })
+    }
+
+    /// Gets the parser of the first format whose probe recognizes `content`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::UnsupportedFormat` if no probe recognizes it.
+    pub fn by_content(&self, content: &str, options: &ParserOptions) -> Result<Box<dyn Parser>> {
+        let trimmed = content.trim_start();
+        self.formats()
+            .find(|format| format.probe.is_some_and(|probe| probe(trimmed)))
+            .map(|format| (format.constructor)(options))
+            .ok_or_else(|| {
+                XtvError::UnsupportedFormat(
+                    "Could not detect format from content. Use --format to specify the format."
+                        .to_string(),
+                )
+            })
+    }
+
+    /// Selects the parser for a file from an explicit format, its extension,
+    /// or its content.
+    ///
+    /// The explicit format (from `--format`) wins. Otherwise the extension is
+    /// used, and if that is missing or unknown the first few kilobytes of the
+    /// file are inspected with [`by_content`](ParserRegistry::by_content).
+    /// Only the beginning of the file is read, so this is cheap even for
+    /// multi-gigabyte files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the format is unknown, the file cannot be read, or
+    /// the format cannot be detected.
+    pub fn for_file(
+        &self,
+        file_path: &Path,
+        format: Option<&str>,
+        options: &ParserOptions,
+    ) -> Result<Box<dyn Parser>> {
+        let (parser, chosen_by) = if let Some(format) = format {
+            (self.by_name(format, options)?, "--format")
+        } else if let Ok(parser) = self.by_extension(file_path, options) {
+            (parser, "extension")
+        } else {
+            let mut prefix = Vec::with_capacity(CONTENT_DETECTION_BYTES);
+            File::open(file_path)?
+                .take(CONTENT_DETECTION_BYTES as u64)
+                .read_to_end(&mut prefix)?;
+            let content = String::from_utf8_lossy(&prefix);
+            (self.by_content(&content, options)?, "content")
+        };
+        log::debug!(
+            "Reading {:?} as {} (by {})",
+            file_path,
+            parser.format_name(),
+            chosen_by
+        );
+        Ok(parser)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::yaml::YamlParser;
+
+    #[test]
+    fn test_builtin_formats() {
+        let registry = ParserRegistry::default();
+        let options = ParserOptions::default();
+        assert_eq!(
+            registry.names(),
+            ["html", "json", "jsonl", "ldif", "toml", "xml", "yaml"]
+        );
+        let name = |parser: Result<Box<dyn Parser>>| parser.unwrap().format_name();
+        assert_eq!(name(registry.by_name("JSONLINES", &options)), "jsonl");
+        assert_eq!(name(registry.by_name("htm", &options)), "html");
+        assert_eq!(
+            name(registry.by_extension(Path::new("a.YML"), &options)),
+            "yaml"
+        );
+
+        let detected = [
+            ("<!DOCTYPE html><html></html>", "html"),
+            ("  <a/>", "xml"),
+            (r#"{"a": 1}"#, "json"),
+            ("dn: cn=a\ncn: a\n", "ldif"),
+            ("---\na: 1", "yaml"),
+            ("a: 1", "yaml"),
+        ];
+        for (content, format) in detected {
+            assert_eq!(name(registry.by_content(content, &options)), format);
+        }
+        assert!(registry.by_content("a::b", &options).is_err());
+    }
+
+    #[test]
+    fn test_registered_formats_take_precedence() {
+        let mut registry = ParserRegistry::default();
+        registry.register(
+            ParserFormat::new("mine", |_| Box::new(YamlParser))
+                .with_aliases(&["my"])
+                .with_extensions(&["json", "mine"])
+                .with_probe(|content| content.starts_with('{')),
+        );
+        let options = ParserOptions::default();
+        assert!(registry.by_name("MY", &options).is_ok());
+        let name = |parser: Result<Box<dyn Parser>>| parser.unwrap().format_name();
+        // The YAML parser stands in for the registered format
+        assert_eq!(
+            name(registry.by_extension(Path::new("x.json"), &options)),
+            "yaml"
+        );
+        assert_eq!(name(registry.by_content("{}", &options)), "yaml");
+        assert_eq!(name(registry.by_name("json", &options)), "json");
+        assert_eq!(registry.formats().next().unwrap().name, "mine");
+
+        assert!(ParserRegistry::empty().by_name("json", &options).is_err());
+    }
+}
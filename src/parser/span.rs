@@ -0,0 +1,56 @@
+//! Turning byte offsets into the lines and columns recorded as
+//! [`TreeNode::source_span`](crate::tree::TreeNode::source_span).
+
+/// Line starts of a text, for looking up the line and column of an offset
+pub(crate) struct LineIndex<'a> {
+    content: &'a str,
+    /// Byte offset of the first character of every line
+    starts: Vec<usize>,
+    /// The last offset looked up with its line and column, so that increasing
+    /// offsets on a long line don't count the line's characters again
+    last: (usize, usize, usize),
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(content: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            content,
+            starts,
+            last: (0, 1, 1),
+        }
+    }
+
+    /// The 1-based line and column, in characters, of the character at byte
+    /// `offset`
+    pub(crate) fn position(&mut self, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let (last_offset, last_line, last_column) = self.last;
+        let column = if line == last_line && offset >= last_offset {
+            last_column + self.content[last_offset..offset].chars().count()
+        } else {
+            self.content[self.starts[line - 1]..offset].chars().count() + 1
+        };
+        self.last = (offset, line, column);
+        (line, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let mut lines = LineIndex::new("ab\nüx\n\nz");
+        assert_eq!(lines.position(0), (1, 1));
+        assert_eq!(lines.position(1), (1, 2));
+        // Columns count characters, not bytes
+        assert_eq!(lines.position(5), (2, 2));
+        assert_eq!(lines.position(3), (2, 1));
+        assert_eq!(lines.position(7), (3, 1));
+        assert_eq!(lines.position(8), (4, 1));
+    }
+}
@@ -1,27 +1,32 @@
-use super::Parser;
-use crate::error::Result;
-use crate::tree::{Tree, TreeNode};
+use super::{Parser, ParserFormat, Serializer, check_depth, value_node};
+use crate::convert;
+use crate::error::{Result, XtvError};
+use crate::tree::{Tree, TreeNode, TreeVariant, ValueKind};
 use std::path::Path;
-use toml::Value;
+use toml_edit::{Datetime, DocumentMut, InlineTable, Item, Key, Table, Value};
 
 pub struct TomlParser;
 
+/// TOML files; their content isn't detected
+pub(super) const FORMAT: ParserFormat =
+    ParserFormat::new("toml", |_| Box::new(TomlParser)).with_extensions(&["toml"]);
+
 impl Parser for TomlParser {
     fn parse(&self, content: &str) -> Result<Tree> {
-        let value: Value = toml::from_str(content)?;
-        let mut tree = Tree::new(TreeNode::new("root", "root"));
+        let document: DocumentMut = content.parse()?;
+        let mut tree = Tree::new(TreeNode::new("root", "root")).with_format(self.format_name());
         let root_id = tree.root_id();
 
         // TOML documents are always tables at the top level
-        if let Value::Table(table) = &value {
-            for (key, child_value) in table {
-                convert_value(&mut tree, root_id, child_value, key);
-            }
-        }
+        convert_table(&mut tree, root_id, document.as_table());
 
         Ok(tree)
     }
 
+    fn format_name(&self) -> &'static str {
+        "toml"
+    }
+
     fn can_parse(&self, file_path: &Path) -> bool {
         file_path
             .extension()
@@ -31,64 +36,301 @@ impl Parser for TomlParser {
     }
 }
 
-fn convert_value(tree: &mut Tree, parent_id: usize, value: &Value, key: &str) {
-    match value {
-        Value::Table(table) => {
-            // Create a node for this table
-            let mut node = TreeNode::new(key, "table");
-            node.add_attribute("size", format!("{} fields", table.len()));
+impl Serializer for TomlParser {
+    /// Writes tables as their contents and other values as `key = value`,
+    /// since a TOML document is always a table. Values are written as the
+    /// kind they were read as, so a quoted date stays a string.
+    fn serialize(&self, tree: &TreeVariant, node_id: usize) -> Result<String> {
+        let mut path = Vec::new();
+        let value = to_value(tree, node_id, &mut path).map_err(|e| match e {
+            XtvError::Serialize(reason) if !path.is_empty() => {
+                path.reverse();
+                XtvError::Serialize(format!("{} at {}", reason, convert::display_path(&path)))
+            }
+            e => e,
+        })?;
+        let table = match value {
+            toml::Value::Table(table) => table,
+            value => toml::Table::from_iter([(tree.get_label(node_id).unwrap_or_default(), value)]),
+        };
+        toml::to_string(&table).map_err(|e| XtvError::Serialize(e.to_string()))
+    }
+}
 
-            let node_id = tree.add_child_node(parent_id, node);
+/// Build the TOML value of a subtree. If it fails, `path` holds the keys and
+/// indices leading to the value that can't be written.
+fn to_value(tree: &TreeVariant, node_id: usize, path: &mut Vec<String>) -> Result<toml::Value> {
+    check_depth(path.len())?;
+    let node = tree
+        .get_node_blocking(node_id)
+        .ok_or(XtvError::InvalidNodeId(node_id))?;
+    if node.is_attribute() {
+        let text = node
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "value")
+            .map_or("", |attr| attr.value.as_str());
+        let kind = node.value_kind.unwrap_or_else(|| ValueKind::guess(text));
+        return scalar_value(text, kind);
+    }
+    let children = tree.get_children(node_id);
 
-            // Recursively add children
-            for (child_key, child_value) in table {
-                convert_value(tree, node_id, child_value, child_key);
-            }
+    if node.is_array() {
+        let mut items = Vec::with_capacity(children.len());
+        for (index, id) in children.into_iter().enumerate() {
+            path.push(format!("[{}]", index));
+            items.push(to_value(tree, id, path)?);
+            path.pop();
         }
-        Value::Array(arr) => {
-            // Create a node for this array
-            let mut node = TreeNode::new(key, "array");
-            node.add_attribute("size", format!("{} items", arr.len()));
+        return Ok(toml::Value::Array(items));
+    }
+    let mut table = toml::Table::new();
+    for id in children {
+        let key = tree.get_label(id).unwrap_or_default();
+        path.push(key.clone());
+        let value = to_value(tree, id, path)?;
+        path.pop();
+        table.insert(key, value);
+    }
+    Ok(toml::Value::Table(table))
+}
+
+/// A value of the given kind as TOML
+fn scalar_value(text: &str, kind: ValueKind) -> Result<toml::Value> {
+    let fail = |reason: String| XtvError::Serialize(reason);
+    Ok(match kind {
+        ValueKind::String => toml::Value::String(text.to_string()),
+        ValueKind::Integer => toml::Value::Integer(
+            text.parse()
+                .map_err(|_| fail(format!("{} is too large for a TOML integer", text)))?,
+        ),
+        ValueKind::Float => toml::Value::Float(
+            text.parse()
+                .map_err(|_| fail(format!("{} is not a TOML float", text)))?,
+        ),
+        ValueKind::Boolean => toml::Value::Boolean(text == "true"),
+        ValueKind::Null => return Err(fail("TOML has no null value".to_string())),
+        ValueKind::Datetime => match text.parse() {
+            Ok(datetime) => toml::Value::Datetime(datetime),
+            Err(_) => toml::Value::String(text.to_string()),
+        },
+    })
+}
 
-            let node_id = tree.add_child_node(parent_id, node);
+/// A TOML item still to be added to the tree
+enum Entry<'a> {
+    Item(&'a Item),
+    Value(&'a Value),
+    Table(&'a Table),
+}
+
+/// An entry still to be added, with its key as written in the document if
+/// that differs from the label
+struct Child<'a> {
+    label: String,
+    key: Option<String>,
+    entry: Entry<'a>,
+}
+
+/// Add the entries of the document's top-level `table` below `parent_id`.
+///
+/// Tables are labeled `table`, or `inline_table` if written as `{ ... }`, and
+/// tables made up by dotted keys or by the headers of their subtables have a
+/// `form` attribute saying so. The tables of an array of tables `[[bin]]` are
+/// labeled `bin [0]`, `bin [1]` and so on. Keys written dotted or quoted are
+/// kept in a `key` attribute, and datetimes say in `kind` whether they are
+/// local or have an offset.
+///
+/// Uses an explicit stack instead of recursion so that deeply nested input
+/// cannot overflow the call stack.
+fn convert_table(tree: &mut Tree, parent_id: usize, table: &Table) {
+    let mut stack: Vec<_> = table_entries(table, None)
+        .into_iter()
+        .rev()
+        .map(|child| (parent_id, child))
+        .collect();
 
-            // Recursively add children with indices
-            for (index, item) in arr.iter().enumerate() {
-                convert_value(tree, node_id, item, &format!("[{}]", index));
+    while let Some((parent_id, Child { label, key, entry })) = stack.pop() {
+        // The written key of a table made up by dotted keys starts the keys
+        // of its entries
+        let prefix = key.clone().unwrap_or_else(|| label.clone());
+
+        let (mut node, children) = match entry {
+            Entry::Item(Item::Table(table)) | Entry::Table(table) => {
+                let mut node = TreeNode::new(label, "table");
+                node.add_attribute("size", format!("{} fields", table.len()));
+                if table.is_dotted() {
+                    node.add_attribute("form", "dotted keys");
+                } else if table.is_implicit() {
+                    node.add_attribute("form", "implicit");
+                }
+                let prefix = table.is_dotted().then_some(prefix.as_str());
+                (node, table_entries(table, prefix))
             }
+            Entry::Item(Item::ArrayOfTables(tables)) => {
+                let mut node = TreeNode::new(&label, "array");
+                node.add_attribute("size", format!("{} items", tables.len()));
+                let children = tables
+                    .iter()
+                    .enumerate()
+                    .map(|(index, table)| Child {
+                        label: format!("{} [{}]", label, index),
+                        key: None,
+                        entry: Entry::Table(table),
+                    })
+                    .collect();
+                (node, children)
+            }
+            Entry::Item(Item::Value(value)) | Entry::Value(value) => match value {
+                Value::InlineTable(table) => {
+                    let mut node = TreeNode::new(label, "inline_table");
+                    node.add_attribute("size", format!("{} fields", table.len()));
+                    if table.is_dotted() {
+                        node.add_attribute("form", "dotted keys");
+                    }
+                    let prefix = table.is_dotted().then_some(prefix.as_str());
+                    (node, inline_table_entries(table, prefix))
+                }
+                Value::Array(array) => {
+                    let mut node = TreeNode::new(label, "array");
+                    node.add_attribute("size", format!("{} items", array.len()));
+                    let children = array
+                        .iter()
+                        .enumerate()
+                        .map(|(index, item)| Child {
+                            label: format!("[{}]", index),
+                            key: None,
+                            entry: Entry::Value(item),
+                        })
+                        .collect();
+                    (node, children)
+                }
+                Value::String(s) => {
+                    let node = value_node(label, s.value().clone(), ValueKind::String);
+                    (node, Vec::new())
+                }
+                Value::Integer(n) => {
+                    let node = value_node(label, n.value().to_string(), ValueKind::Integer);
+                    (node, Vec::new())
+                }
+                Value::Float(f) => {
+                    let node = value_node(label, f.value().to_string(), ValueKind::Float);
+                    (node, Vec::new())
+                }
+                Value::Boolean(b) => {
+                    let node = value_node(label, b.value().to_string(), ValueKind::Boolean);
+                    (node, Vec::new())
+                }
+                Value::Datetime(dt) => {
+                    let mut node = value_node(label, dt.value().to_string(), ValueKind::Datetime);
+                    node.add_attribute("kind", datetime_kind(dt.value()));
+                    (node, Vec::new())
+                }
+            },
+            Entry::Item(Item::None) => continue,
+        };
+        if let Some(key) = key {
+            node.add_attribute("key", key);
         }
-        Value::String(s) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", s.clone());
-            tree.add_child_node(parent_id, node);
-        }
-        Value::Integer(n) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", n.to_string());
-            tree.add_child_node(parent_id, node);
-        }
-        Value::Float(f) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", f.to_string());
-            tree.add_child_node(parent_id, node);
-        }
-        Value::Boolean(b) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", b.to_string());
-            tree.add_child_node(parent_id, node);
+        let node_id = tree.add_child_node(parent_id, node);
+
+        // Push children in reverse so they are added in document order
+        stack.extend(children.into_iter().rev().map(|child| (node_id, child)));
+    }
+}
+
+/// The entries of a table, sorted by key. Inside a table made up by dotted
+/// keys, `prefix` is the written key of that table.
+fn table_entries<'a>(table: &'a Table, prefix: Option<&str>) -> Vec<Child<'a>> {
+    let mut children: Vec<_> = table
+        .iter()
+        .map(|(label, item)| child(label, table.key(label), prefix, Entry::Item(item)))
+        .collect();
+    children.sort_by(|a, b| a.label.cmp(&b.label));
+    children
+}
+
+/// The entries of an inline table, sorted by key, see [`table_entries`]
+fn inline_table_entries<'a>(table: &'a InlineTable, prefix: Option<&str>) -> Vec<Child<'a>> {
+    let mut children: Vec<_> = table
+        .iter()
+        .map(|(label, value)| child(label, table.key(label), prefix, Entry::Value(value)))
+        .collect();
+    children.sort_by(|a, b| a.label.cmp(&b.label));
+    children
+}
+
+fn child<'a>(label: &str, key: Option<&Key>, prefix: Option<&str>, entry: Entry<'a>) -> Child<'a> {
+    let written = key.map_or_else(|| label.to_string(), |key| key.display_repr().into_owned());
+    let written = match prefix {
+        Some(prefix) => format!("{}.{}", prefix, written),
+        None => written,
+    };
+    Child {
+        label: label.to_string(),
+        key: (written != label).then_some(written),
+        entry,
+    }
+}
+
+/// A number of a TOML document that the serializer would write differently,
+/// such as `0xff`, written as `255`, or `1e2`, written as `100.0`
+pub(crate) fn lost_on_save(content: &str) -> Option<String> {
+    let document: DocumentMut = content.parse().ok()?;
+    let mut tables = vec![document.as_table()];
+    let mut values = Vec::new();
+    while let Some(table) = tables.pop() {
+        for (_, item) in table.iter() {
+            match item {
+                Item::Table(table) => tables.push(table),
+                Item::ArrayOfTables(array) => tables.extend(array.iter()),
+                Item::Value(value) => values.push(value),
+                Item::None => {}
+            }
         }
-        Value::Datetime(dt) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", dt.to_string());
-            tree.add_child_node(parent_id, node);
+    }
+    while let Some(value) = values.pop() {
+        let (read, written) = match value {
+            Value::InlineTable(table) => {
+                values.extend(table.iter().map(|(_, value)| value));
+                continue;
+            }
+            Value::Array(array) => {
+                values.extend(array.iter());
+                continue;
+            }
+            Value::Integer(n) => (n.display_repr(), toml::Value::Integer(*n.value())),
+            Value::Float(f) => (f.display_repr(), toml::Value::Float(*f.value())),
+            _ => continue,
+        };
+        if read.trim() != written.to_string() {
+            return Some(format!("the number {}", read.trim()));
         }
     }
+    None
+}
+
+/// Whether a datetime has a date, a time and an offset
+fn datetime_kind(datetime: &Datetime) -> &'static str {
+    match (datetime.date, datetime.time, datetime.offset) {
+        (Some(_), Some(_), Some(_)) => "offset datetime",
+        (Some(_), Some(_), None) => "local datetime",
+        (Some(_), None, _) => "local date",
+        (None, _, _) => "local time",
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn attribute<'a>(node: &'a TreeNode, key: &str) -> Option<&'a str> {
+        node.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.as_str())
+    }
+
     #[test]
     fn test_parse_simple_toml() {
         let toml = r#"
@@ -142,6 +384,96 @@ datetime = 1979-05-27T07:32:00Z
         assert!(tree.node_count() > 5);
     }
 
+    #[test]
+    fn test_tables_keep_how_they_were_written() {
+        let toml = r#"
+[package]
+name = "xtv"
+metadata.docs.features = ["all"]
+"build script" = "build.rs"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+toml = "0.8"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[[bin]]
+name = "xtv"
+
+[[bin]]
+name = "xtv-bench"
+path = "src/bench.rs"
+"#;
+        let tree = TomlParser.parse(toml).unwrap();
+        let node = |path: &[&str]| {
+            let id = tree.find_by_path(path).unwrap();
+            tree.get_node(id).unwrap()
+        };
+
+        let bin = node(&["bin"]);
+        assert_eq!(bin.node_type, "array");
+        let labels: Vec<_> = bin
+            .children
+            .iter()
+            .map(|&id| tree.get_node(id).unwrap().label.as_str())
+            .collect();
+        assert_eq!(labels, ["bin [0]", "bin [1]"]);
+        assert_eq!(
+            attribute(node(&["bin", "bin [1]", "path"]), "value"),
+            Some("src/bench.rs")
+        );
+
+        assert_eq!(node(&["dependencies"]).node_type, "table");
+        assert_eq!(attribute(node(&["dependencies"]), "form"), None);
+        assert_eq!(node(&["dependencies", "serde"]).node_type, "inline_table");
+        assert!(node(&["dependencies", "serde"]).is_object());
+        assert_eq!(attribute(node(&["target"]), "form"), Some("implicit"));
+        assert_eq!(
+            attribute(node(&["target", "cfg(unix)"]), "key"),
+            Some("'cfg(unix)'")
+        );
+
+        let docs = node(&["package", "metadata", "docs"]);
+        assert_eq!(attribute(docs, "form"), Some("dotted keys"));
+        assert_eq!(attribute(docs, "key"), Some("metadata.docs"));
+        let features = node(&["package", "metadata", "docs", "features"]);
+        assert_eq!(attribute(features, "key"), Some("metadata.docs.features"));
+        assert_eq!(attribute(node(&["package", "name"]), "key"), None);
+        assert_eq!(
+            attribute(node(&["package", "build script"]), "key"),
+            Some("\"build script\"")
+        );
+    }
+
+    #[test]
+    fn test_datetime_kinds() {
+        let toml =
+            "a = 1979-05-27T07:32:00Z\nb = 1979-05-27T07:32:00\nc = 1979-05-27\nd = 07:32:00\n";
+        let tree = TomlParser.parse(toml).unwrap();
+        let kinds: Vec<_> = tree
+            .get_children(tree.root_id())
+            .into_iter()
+            .map(|id| {
+                attribute(tree.get_node(id).unwrap(), "kind")
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "offset datetime",
+                "local datetime",
+                "local date",
+                "local time"
+            ]
+        );
+        let a = tree.get_node(tree.get_children(tree.root_id())[0]).unwrap();
+        assert_eq!(attribute(a, "value"), Some("1979-05-27T07:32:00Z"));
+    }
+
     #[test]
     fn test_can_parse_toml_extension() {
         let parser = TomlParser;
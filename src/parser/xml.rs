@@ -1,7 +1,9 @@
-use super::Parser;
+use super::span::LineIndex;
+use super::{Parser, ParserFormat, Serializer, add_virtual_attributes_node, check_depth};
 use crate::error::{Result, XtvError};
-use crate::tree::{Tree, TreeNode};
+use crate::tree::{Tree, TreeNode, TreeVariant};
 use quick_xml::Reader;
+use quick_xml::escape::{escape, partial_escape};
 use quick_xml::events::Event;
 use std::path::Path;
 
@@ -25,29 +27,46 @@ use std::path::Path;
 /// ```
 pub struct XmlParser;
 
+/// XML files, and content starting with markup
+pub(super) const FORMAT: ParserFormat = ParserFormat::new("xml", |_| Box::new(XmlParser))
+    .with_extensions(&["xml"])
+    .with_probe(|content| content.starts_with('<'));
+
 impl Parser for XmlParser {
     fn parse(&self, content: &str) -> Result<Tree> {
         let mut reader = Reader::from_str(content);
         reader.trim_text(true);
 
-        let mut tree = Tree::new(TreeNode::new("root", "root"));
+        let mut tree = Tree::new(TreeNode::new("root", "root")).with_format(self.format_name());
         let root_id = tree.root_id();
 
         // Stack to track parent nodes
         let mut parent_stack: Vec<usize> = vec![root_id];
         let mut buf = Vec::new();
+        let mut lines = LineIndex::new(content);
 
         loop {
+            // The event starts after the whitespace that trimming skips
+            let end_of_last = reader.buffer_position();
+            let start = end_of_last
+                + content.as_bytes()[end_of_last..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_whitespace())
+                    .count();
             match reader.read_event_into(&mut buf) {
                 Ok(Event::Start(e)) => {
                     let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
                     let mut node = TreeNode::new(name, "element");
+                    node.source_span = Some(lines.position(start));
 
                     // Add XML attributes
                     for attr in e.attributes() {
                         let attr = attr.map_err(|e| XtvError::XmlParse(e.to_string()))?;
                         let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
-                        let value = String::from_utf8_lossy(&attr.value).to_string();
+                        let value = attr
+                            .unescape_value()
+                            .map_err(|e| XtvError::XmlParse(e.to_string()))?
+                            .to_string();
                         node.add_attribute(key, value);
                     }
 
@@ -55,14 +74,11 @@ impl Parser for XmlParser {
                     let attributes = node.attributes.clone();
 
                     // Add as child to current parent
-                    let node_id = if let Some(&parent_id) = parent_stack.last() {
-                        tree.add_child_node(parent_id, node)
-                    } else {
-                        tree.add_node(node)
-                    };
+                    let parent_id = parent_stack.last().copied().unwrap_or(root_id);
+                    let node_id = tree.add_child_node(parent_id, node);
 
                     // Create virtual attributes node if there are attributes
-                    add_virtual_attributes_if_present(&mut tree, node_id, &attributes);
+                    add_virtual_attributes_node(&mut tree, node_id, &attributes);
 
                     // Push this node as the new parent
                     parent_stack.push(node_id);
@@ -81,24 +97,26 @@ impl Parser for XmlParser {
                     // Only add non-empty text nodes
                     if !text.is_empty() {
                         let mut text_node = TreeNode::new("text", "text");
+                        text_node.source_span = Some(lines.position(start));
                         text_node.add_attribute("content", text);
-                        let text_id = tree.add_node(text_node);
-
-                        if let Some(&parent_id) = parent_stack.last() {
-                            tree.get_node_mut(parent_id).unwrap().add_child(text_id);
-                        }
+                        let parent_id = parent_stack.last().copied().unwrap_or(root_id);
+                        tree.add_child_node(parent_id, text_node);
                     }
                 }
                 Ok(Event::Empty(e)) => {
                     // Self-closing tag
                     let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
                     let mut node = TreeNode::new(name, "element");
+                    node.source_span = Some(lines.position(start));
 
                     // Add XML attributes
                     for attr in e.attributes() {
                         let attr = attr.map_err(|e| XtvError::XmlParse(e.to_string()))?;
                         let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
-                        let value = String::from_utf8_lossy(&attr.value).to_string();
+                        let value = attr
+                            .unescape_value()
+                            .map_err(|e| XtvError::XmlParse(e.to_string()))?
+                            .to_string();
                         node.add_attribute(key, value);
                     }
 
@@ -106,14 +124,11 @@ impl Parser for XmlParser {
                     let attributes = node.attributes.clone();
 
                     // Add as child to current parent
-                    let node_id = if let Some(&parent_id) = parent_stack.last() {
-                        tree.add_child_node(parent_id, node)
-                    } else {
-                        tree.add_node(node)
-                    };
+                    let parent_id = parent_stack.last().copied().unwrap_or(root_id);
+                    let node_id = tree.add_child_node(parent_id, node);
 
                     // Create virtual attributes node if there are attributes
-                    add_virtual_attributes_if_present(&mut tree, node_id, &attributes);
+                    add_virtual_attributes_node(&mut tree, node_id, &attributes);
                 }
                 Ok(Event::Eof) => break,
                 Err(e) => return Err(XtvError::XmlParse(e.to_string())),
@@ -126,6 +141,10 @@ impl Parser for XmlParser {
         Ok(tree)
     }
 
+    fn format_name(&self) -> &'static str {
+        "xml"
+    }
+
     fn can_parse(&self, file_path: &Path) -> bool {
         file_path
             .extension()
@@ -135,55 +154,118 @@ impl Parser for XmlParser {
     }
 }
 
-/// Creates a virtual "@attributes" node containing individual attribute nodes
-/// Returns None if the attributes vector is empty
-fn create_virtual_attributes_node(
-    tree: &mut Tree,
-    attributes: &[crate::tree::node::Attribute],
-) -> Option<usize> {
-    if attributes.is_empty() {
-        return None;
-    }
-
-    // Create the virtual container node
-    let virtual_node = TreeNode::new("@attributes", TreeNode::VIRTUAL_ATTRIBUTES_TYPE);
-
-    // Sort attributes alphanumerically by key
-    let mut sorted_attrs = attributes.to_vec();
-    sorted_attrs.sort_by(|a, b| a.key.cmp(&b.key));
-
-    // Add the virtual node first to get its ID
-    let virtual_id = tree.add_node(virtual_node);
-
-    // Create individual attribute nodes as children
-    for attr in sorted_attrs {
-        let mut attr_node = TreeNode::new(&attr.key, TreeNode::ATTRIBUTE_TYPE);
-        attr_node.add_attribute("value", &attr.value);
-        tree.add_child_node(virtual_id, attr_node);
+impl Serializer for XmlParser {
+    /// Writes elements indented by two spaces, and the attributes of an
+    /// `@attributes` node as `name="value"` pairs
+    fn serialize(&self, tree: &TreeVariant, node_id: usize) -> Result<String> {
+        let mut out = String::new();
+        write_markup(tree, node_id, 0, &mut out)?;
+        Ok(out)
     }
-
-    Some(virtual_id)
 }
 
-/// Add virtual attributes node to an element node if it has attributes
-/// Inserts the virtual node as the first child (index 0)
-fn add_virtual_attributes_if_present(
-    tree: &mut Tree,
-    node_id: usize,
-    attributes: &[crate::tree::node::Attribute],
-) {
-    if let Some(virtual_id) = create_virtual_attributes_node(tree, attributes) {
-        tree.get_node_mut(node_id)
-            .unwrap()
-            .children
-            .insert(0, virtual_id);
+/// Append the markup of a subtree, indented by `depth` levels
+fn write_markup(tree: &TreeVariant, node_id: usize, depth: usize, out: &mut String) -> Result<()> {
+    check_depth(depth)?;
+    let node = tree
+        .get_node_blocking(node_id)
+        .ok_or(XtvError::InvalidNodeId(node_id))?;
+    let content = |node: &TreeNode, key: &str| {
+        node.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+            .unwrap_or_default()
+    };
+    let children: Vec<(usize, TreeNode)> = tree
+        .get_children(node_id)
+        .into_iter()
+        .filter_map(|id| Some((id, tree.get_node_blocking(id)?)))
+        .collect();
+    let indent = "  ".repeat(depth);
+
+    if node.is_virtual_attributes() {
+        let pairs: Vec<String> = children
+            .iter()
+            .map(|(_, attr)| format!("{}=\"{}\"", attr.label, escape(&content(attr, "value"))))
+            .collect();
+        out.push_str(&pairs.join(" "));
+        return Ok(());
     }
+    match node.node_type.as_str() {
+        TreeNode::ATTRIBUTE_TYPE => {
+            let value = content(&node, "value");
+            out.push_str(&format!("{}=\"{}\"", node.label, escape(&value)));
+        }
+        "text" => {
+            let text = content(&node, "content");
+            out.push_str(&format!("{}{}\n", indent, partial_escape(&text)));
+        }
+        "comment" => {
+            out.push_str(&format!("{}<!--{}-->\n", indent, content(&node, "content")));
+        }
+        "doctype" => {
+            out.push_str(&format!(
+                "{}<!DOCTYPE {}>\n",
+                indent,
+                content(&node, "content")
+            ));
+        }
+        "element" => {
+            out.push_str(&format!("{}<{}", indent, node.label));
+            for attr in &node.attributes {
+                out.push_str(&format!(" {}=\"{}\"", attr.key, escape(&attr.value)));
+            }
+            let children: Vec<_> = children
+                .into_iter()
+                .filter(|(_, child)| !child.is_virtual_attributes())
+                .collect();
+            match children.as_slice() {
+                [] => out.push_str("/>\n"),
+                [(_, text)] if text.node_type == "text" => {
+                    let text = content(text, "content");
+                    out.push_str(&format!(">{}</{}>\n", partial_escape(&text), node.label));
+                }
+                _ => {
+                    out.push_str(">\n");
+                    for (child_id, _) in children {
+                        write_markup(tree, child_id, depth + 1, out)?;
+                    }
+                    out.push_str(&format!("{}</{}>\n", indent, node.label));
+                }
+            }
+        }
+        // The root stands for the document, its children are top-level
+        _ => {
+            for (child_id, _) in children {
+                write_markup(tree, child_id, depth, out)?;
+            }
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_nodes_know_where_they_start() {
+        let xml =
+            "<?xml version=\"1.0\"?>\n<shop>\n  <item id=\"1\">one</item>\n  <empty/>\n</shop>";
+        let tree = XmlParser.parse(xml).unwrap();
+        let span = |path: &str| {
+            tree.get_node(tree.find_by_path_str(path).unwrap())
+                .unwrap()
+                .source_span
+        };
+
+        assert_eq!(span(".shop"), Some((2, 1)));
+        assert_eq!(span(".shop.item"), Some((3, 3)));
+        assert_eq!(span(".shop.item.text"), Some((3, 16)));
+        assert_eq!(span(".shop.empty"), Some((4, 3)));
+    }
+
     #[test]
     fn test_parse_simple_xml() {
         let xml = r#"<root><item>test</item></root>"#;
@@ -219,6 +301,14 @@ mod tests {
 
         // Virtual node should have 2 attribute children
         assert_eq!(first_child.children.len(), 2);
+
+        // Both the virtual node and text nodes know their parents
+        assert_eq!(tree.get_parent(first_child_id), Some(root.children[0]));
+        let child = tree.get_node(root_element.children[1]).unwrap();
+        assert_eq!(
+            tree.get_parent(child.children[0]),
+            Some(root_element.children[1])
+        );
     }
 
     #[test]
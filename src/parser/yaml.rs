@@ -1,47 +1,62 @@
-use super::Parser;
-use crate::error::Result;
-use crate::tree::{Tree, TreeNode};
+use super::{Parser, ParserFormat, Serializer, check_depth, value_node};
+use crate::error::{Result, XtvError};
+use crate::tree::{Tree, TreeNode, TreeVariant, ValueKind};
 use serde_yaml::Value;
+use serde_yaml::value::{Tag, TaggedValue};
 use std::path::Path;
 
 pub struct YamlParser;
 
+/// YAML files, and content starting with a document marker or directive, or
+/// with `key: value` pairs if no other format recognizes it
+pub(super) const FORMAT: ParserFormat = ParserFormat::new("yaml", |_| Box::new(YamlParser))
+    .with_aliases(&["yml"])
+    .with_extensions(&["yaml", "yml"])
+    .with_probe(|content| {
+        content.starts_with("---")
+            || content.starts_with("%YAML")
+            || (content.contains(':') && !content.contains("::"))
+    });
+
 impl Parser for YamlParser {
     fn parse(&self, content: &str) -> Result<Tree> {
         let value: Value = serde_yaml::from_str(content)?;
-        let mut tree = Tree::new(TreeNode::new("root", "root"));
+        // The root stands for a top-level sequence or mapping
+        let root_type = if value.is_sequence() {
+            "sequence"
+        } else {
+            "root"
+        };
+        let mut tree = Tree::new(TreeNode::new("root", root_type)).with_format(self.format_name());
         let root_id = tree.root_id();
 
         // Build tree from YAML value - handle top level specially
-        match &value {
+        match value {
             Value::Mapping(map) => {
                 // Add mapping fields directly to root
                 for (key, child_value) in map {
-                    let key_str = match key {
-                        Value::String(s) => s.clone(),
-                        Value::Number(n) => n.to_string(),
-                        Value::Bool(b) => b.to_string(),
-                        Value::Null => "null".to_string(),
-                        _ => format!("{:?}", key),
-                    };
-                    convert_value(&mut tree, root_id, child_value, &key_str);
+                    convert_value(&mut tree, root_id, child_value, key_to_string(&key));
                 }
             }
             Value::Sequence(arr) => {
                 // Add sequence items directly to root
-                for (index, item) in arr.iter().enumerate() {
-                    convert_value(&mut tree, root_id, item, &format!("[{}]", index));
+                for (index, item) in arr.into_iter().enumerate() {
+                    convert_value(&mut tree, root_id, item, format!("[{}]", index));
                 }
             }
             _ => {
                 // For scalar values at top level, add them as a child
-                convert_value(&mut tree, root_id, &value, "value");
+                convert_value(&mut tree, root_id, value, "value".to_string());
             }
         }
 
         Ok(tree)
     }
 
+    fn format_name(&self) -> &'static str {
+        "yaml"
+    }
+
     fn can_parse(&self, file_path: &Path) -> bool {
         file_path
             .extension()
@@ -51,75 +66,234 @@ impl Parser for YamlParser {
     }
 }
 
-fn convert_value(tree: &mut Tree, parent_id: usize, value: &Value, key: &str) {
-    match value {
-        Value::Mapping(map) => {
-            // Create a node for this mapping (object)
-            let mut node = TreeNode::new(key, "mapping");
-
-            // Add attribute for mapping size
-            node.add_attribute("size", format!("{} fields", map.len()));
-
-            let node_id = tree.add_child_node(parent_id, node);
-
-            // Recursively add children
-            for (child_key, child_value) in map {
-                // Convert key to string
-                let key_str = match child_key {
-                    Value::String(s) => s.clone(),
-                    Value::Number(n) => n.to_string(),
-                    Value::Bool(b) => b.to_string(),
-                    Value::Null => "null".to_string(),
-                    _ => format!("{:?}", child_key),
-                };
-                convert_value(tree, node_id, child_value, &key_str);
-            }
-        }
-        Value::Sequence(arr) => {
-            // Create a node for this sequence (array)
-            let mut node = TreeNode::new(key, "sequence");
-            node.add_attribute("size", format!("{} items", arr.len()));
+impl Serializer for YamlParser {
+    fn serialize(&self, tree: &TreeVariant, node_id: usize) -> Result<String> {
+        Ok(serde_yaml::to_string(&to_value(tree, node_id, 0)?)?)
+    }
+}
+
+/// Build the YAML value of a subtree, keeping tags
+fn to_value(tree: &TreeVariant, node_id: usize, depth: usize) -> Result<Value> {
+    check_depth(depth)?;
+    let node = tree
+        .get_node_blocking(node_id)
+        .ok_or(XtvError::InvalidNodeId(node_id))?;
+    let children = tree.get_children(node_id);
+
+    if node.is_array() {
+        let items = children
+            .into_iter()
+            .map(|id| to_value(tree, id, depth + 1))
+            .collect::<Result<_>>()?;
+        return Ok(Value::Sequence(items));
+    }
+    if node.node_type == "tagged" {
+        let tag = node.attributes.iter().find(|attr| attr.key == "tag");
+        let inner = match children.first() {
+            Some(&id) => to_value(tree, id, depth + 1)?,
+            None => Value::Null,
+        };
+        return Ok(Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag.map_or("", |attr| attr.value.as_str())),
+            value: inner,
+        })));
+    }
+    if node.is_attribute() {
+        let text = node
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "value")
+            .map_or("", |attr| attr.value.as_str());
+        return Ok(scalar_value(text, node.value_kind));
+    }
 
-            let node_id = tree.add_child_node(parent_id, node);
+    let mut mapping = serde_yaml::Mapping::new();
+    for id in children {
+        let key = tree.get_label(id).unwrap_or_default();
+        mapping.insert(Value::String(key), to_value(tree, id, depth + 1)?);
+    }
+    Ok(Value::Mapping(mapping))
+}
 
-            // Recursively add children with indices
-            for (index, item) in arr.iter().enumerate() {
-                convert_value(tree, node_id, item, &format!("[{}]", index));
+/// A value of the given kind as YAML, typed by how it reads if the kind is
+/// unknown
+fn scalar_value(text: &str, kind: Option<ValueKind>) -> Value {
+    match kind.unwrap_or_else(|| ValueKind::guess(text)) {
+        ValueKind::Integer | ValueKind::Float => match serde_yaml::from_str(text) {
+            Ok(number @ Value::Number(_)) => number,
+            _ => Value::String(text.to_string()),
+        },
+        ValueKind::Boolean => Value::Bool(text == "true"),
+        ValueKind::Null => Value::Null,
+        ValueKind::String | ValueKind::Datetime => Value::String(text.to_string()),
+    }
+}
+
+/// What of a YAML document the serializer can't write back as it was read,
+/// if anything: keys that aren't strings are written as strings, tags of the
+/// core schema such as `!!binary` are gone once the document is read, and
+/// numbers are written as serde_yaml writes them, so `1e2` becomes `100.0`
+pub(crate) fn lost_on_save(content: &str) -> Option<String> {
+    // Lines more indented than the line starting a block scalar belong to it
+    let mut block_indent = None;
+    for (number, line) in content.lines().enumerate() {
+        let indent = line.len() - line.trim_start().len();
+        if let Some(block) = block_indent {
+            if line.trim().is_empty() || indent > block {
+                continue;
             }
+            block_indent = None;
         }
-        Value::String(s) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", s.clone());
-            tree.add_child_node(parent_id, node);
+        if has_core_tag(line) {
+            return Some(format!("the tag on line {}", number + 1));
         }
-        Value::Number(n) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", n.to_string());
-            tree.add_child_node(parent_id, node);
+        let value = plain_value(line);
+        if value.starts_with(['|', '>']) {
+            block_indent = Some(indent);
+            continue;
         }
-        Value::Bool(b) => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", b.to_string());
-            tree.add_child_node(parent_id, node);
+        let items = value.trim_matches(['[', ']', '{', '}']).split(',');
+        for item in items.map(|item| item.rsplit(": ").next().unwrap_or(item).trim()) {
+            if let Ok(Value::Number(n)) = serde_yaml::from_str(item)
+                && serde_yaml::to_string(&n).ok().as_deref().map(str::trim_end) != Some(item)
+            {
+                return Some(format!("the number {} on line {}", item, number + 1));
+            }
         }
-        Value::Null => {
-            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-            node.add_attribute("value", "null");
-            tree.add_child_node(parent_id, node);
+    }
+    let value: Value = serde_yaml::from_str(content).ok()?;
+    let mut stack = vec![&value];
+    while let Some(value) = stack.pop() {
+        match value {
+            Value::Mapping(map) => {
+                for (key, value) in map {
+                    if !key.is_string() {
+                        return Some(format!("the key {}", key_to_string(key)));
+                    }
+                    stack.push(value);
+                }
+            }
+            Value::Sequence(items) => stack.extend(items),
+            Value::Tagged(tagged) => stack.push(&tagged.value),
+            _ => {}
         }
-        Value::Tagged(tagged) => {
-            // Handle tagged values (e.g., !tag value)
-            // For now, just process the inner value with a note about the tag
-            let tag = &tagged.tag;
-            let inner_value = &tagged.value;
-
-            // Create a tagged node
-            let mut node = TreeNode::new(key, "tagged");
-            node.add_attribute("tag", tag.to_string());
-            let node_id = tree.add_child_node(parent_id, node);
-
-            // Add the inner value as a child
-            convert_value(tree, node_id, inner_value, "value");
+    }
+    None
+}
+
+/// Whether a line has a tag such as `!!binary` or `!<tag:yaml.org,2002:str>`
+/// where a node starts
+fn has_core_tag(line: &str) -> bool {
+    line.match_indices("!!")
+        .chain(line.match_indices("!<"))
+        .any(|(i, _)| {
+            let before = line[..i].trim_end();
+            before.is_empty() || before.ends_with([':', '-', '?', '[', '{', ','])
+        })
+}
+
+/// The value written on a line, without its comment, the dashes of sequence
+/// items and the key of a mapping entry
+fn plain_value(line: &str) -> &str {
+    let line = line.find(" #").map_or(line, |end| &line[..end]);
+    let mut value = line.trim();
+    while let Some(rest) = value
+        .strip_prefix('-')
+        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+    {
+        value = rest.trim_start();
+    }
+    match value.find(": ") {
+        Some(colon) => value[colon + 2..].trim_start(),
+        None if value.ends_with(':') => "",
+        None => value,
+    }
+}
+
+/// Convert a mapping key to a label
+fn key_to_string(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => "null".to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+/// Add `value` and its descendants below `parent_id`.
+///
+/// Uses an explicit stack instead of recursion so that deeply nested input
+/// cannot overflow the call stack.
+fn convert_value(tree: &mut Tree, parent_id: usize, value: Value, key: String) {
+    let mut stack = vec![(parent_id, key, value)];
+
+    while let Some((parent_id, key, value)) = stack.pop() {
+        match value {
+            Value::Mapping(map) => {
+                // Create a node for this mapping (object)
+                let mut node = TreeNode::new(key, "mapping");
+
+                // Add attribute for mapping size
+                node.add_attribute("size", format!("{} fields", map.len()));
+
+                let node_id = tree.add_child_node(parent_id, node);
+
+                // Push children in reverse so they are added in document order
+                let children: Vec<_> = map
+                    .into_iter()
+                    .map(|(k, v)| (node_id, key_to_string(&k), v))
+                    .collect();
+                stack.extend(children.into_iter().rev());
+            }
+            Value::Sequence(arr) => {
+                // Create a node for this sequence (array)
+                let mut node = TreeNode::new(key, "sequence");
+                node.add_attribute("size", format!("{} items", arr.len()));
+
+                let node_id = tree.add_child_node(parent_id, node);
+
+                // Push children with indices, in reverse for document order
+                stack.extend(
+                    arr.into_iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(index, item)| (node_id, format!("[{}]", index), item)),
+                );
+            }
+            Value::String(s) => {
+                tree.add_child_node(parent_id, value_node(key, s, ValueKind::String));
+            }
+            Value::Number(n) => {
+                let kind = if n.is_f64() {
+                    ValueKind::Float
+                } else {
+                    ValueKind::Integer
+                };
+                tree.add_child_node(parent_id, value_node(key, n.to_string(), kind));
+            }
+            Value::Bool(b) => {
+                tree.add_child_node(
+                    parent_id,
+                    value_node(key, b.to_string(), ValueKind::Boolean),
+                );
+            }
+            Value::Null => {
+                tree.add_child_node(
+                    parent_id,
+                    value_node(key, "null".to_string(), ValueKind::Null),
+                );
+            }
+            Value::Tagged(tagged) => {
+                // Handle tagged values (e.g., !tag value)
+                // For now, just process the inner value with a note about the tag
+                let mut node = TreeNode::new(key, "tagged");
+                node.add_attribute("tag", tagged.tag.to_string());
+                let node_id = tree.add_child_node(parent_id, node);
+
+                // Add the inner value as a child
+                stack.push((node_id, "value".to_string(), tagged.value));
+            }
         }
     }
 }
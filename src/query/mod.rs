@@ -0,0 +1,5 @@
+//! Structured queries over trees, as opposed to the text search of the UI,
+//! and the paths that select a node in their syntax.
+
+pub mod path;
+pub mod xpath;
@@ -0,0 +1,375 @@
+//! Paths that select a single node, for pasting into other tools: jq paths
+//! and JSON Pointers for JSON, YAML and TOML trees and XPaths for XML and
+//! HTML trees.
+
+use super::xpath::XPath;
+use crate::tree::{TreeNode, TreeVariant};
+
+/// The nodes from below the root down to `node_id`
+fn ancestry(tree: &TreeVariant, node_id: usize) -> Vec<(usize, TreeNode)> {
+    let mut nodes = Vec::new();
+    let mut current = node_id;
+    while let Some(parent) = tree.get_parent(current) {
+        if let Some(node) = tree.get_node_blocking(current) {
+            nodes.push((current, node));
+        }
+        current = parent;
+    }
+    nodes.reverse();
+    nodes
+}
+
+/// Position of a node among its parent's children
+fn child_index(tree: &TreeVariant, parent: usize, node_id: usize) -> usize {
+    tree.get_children(parent)
+        .iter()
+        .position(|&child| child == node_id)
+        .unwrap_or(0)
+}
+
+/// Whether a label is an array index such as `[3]`
+pub(crate) fn is_index_label(label: &str) -> bool {
+    label
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Whether a node is addressed by its position: children of arrays, which
+/// include the root of a top-level array or of JSON Lines records
+fn is_sequence_item(tree: &TreeVariant, parent: usize) -> bool {
+    tree.get_node_blocking(parent).is_some_and(|p| p.is_array())
+}
+
+/// A jq path such as `.users[3].email`.
+///
+/// Children of arrays are addressed by their position, object keys are quoted
+/// unless they are identifiers.
+/// [`find_by_path_str`](TreeVariant::find_by_path_str) resolves it again.
+pub fn jq_path(tree: &TreeVariant, node_id: usize) -> String {
+    let mut path = String::new();
+    for (id, node) in ancestry(tree, node_id) {
+        if node.is_virtual_attributes() {
+            continue;
+        }
+        let parent = tree.get_parent(id).unwrap_or(tree.root_id());
+
+        if is_sequence_item(tree, parent) {
+            path.push_str(&format!("[{}]", child_index(tree, parent, id)));
+        } else if is_identifier(&node.label) {
+            path.push('.');
+            path.push_str(&node.label);
+        } else {
+            path.push('.');
+            path.push_str(&serde_json::Value::from(node.label.as_str()).to_string());
+        }
+    }
+    // jq paths start with a dot, also before an index
+    if !path.starts_with('.') {
+        path.insert(0, '.');
+    }
+    path
+}
+
+/// A JSON Pointer (RFC 6901) such as `/users/3/email`, or `""` for the root.
+///
+/// Array items are addressed by their position and everything else by its
+/// label, with `~` and `/` escaped as `~0` and `~1`. Trees of other formats
+/// get a pointer of their labels, which
+/// [`find_by_json_pointer`](TreeVariant::find_by_json_pointer) resolves.
+pub fn json_pointer(tree: &TreeVariant, node_id: usize) -> String {
+    let mut pointer = String::new();
+    for (id, node) in ancestry(tree, node_id) {
+        let parent = tree.get_parent(id).unwrap_or(tree.root_id());
+        pointer.push('/');
+        if is_sequence_item(tree, parent) {
+            pointer.push_str(&child_index(tree, parent, id).to_string());
+        } else {
+            pointer.push_str(&node.label.replace('~', "~0").replace('/', "~1"));
+        }
+    }
+    pointer
+}
+
+/// Whether `/`-paths into a tree are XPaths rather than JSON Pointers
+pub fn uses_xpath(tree: &TreeVariant) -> bool {
+    matches!(tree.format(), Some("xml" | "html"))
+}
+
+/// The LDIF entry with the distinguished name `dn`, such as
+/// `ou=People,dc=example,dc=com`.
+///
+/// Entries are labeled with their RDN below a parent entry and with their
+/// whole DN otherwise, so the DN is matched from its end, one label at a
+/// time. Names compare without regard to case, as in LDAP.
+pub fn resolve_dn(tree: &TreeVariant, dn: &str) -> Option<usize> {
+    let mut rest = dn.trim().to_lowercase();
+    let mut node_id = tree.root_id();
+    loop {
+        let (child, remaining) = tree.get_children(node_id).into_iter().find_map(|child| {
+            let label = tree.get_label(child)?.to_lowercase();
+            if rest == label {
+                return Some((child, String::new()));
+            }
+            let parent = rest.strip_suffix(&label)?.strip_suffix(',')?;
+            Some((child, parent.to_string()))
+        })?;
+        if remaining.is_empty() {
+            return Some(child);
+        }
+        node_id = child;
+        rest = remaining;
+    }
+}
+
+/// The node selected by `path`: if it starts with `/` an XPath for XML and
+/// HTML (trees in memory only) and a JSON Pointer for other formats, a jq
+/// path (see [`find_by_path_str`](TreeVariant::find_by_path_str)) if it
+/// starts with `.` or `[`, and an LDIF DN otherwise.
+pub fn resolve_path(tree: &TreeVariant, path: &str) -> Option<usize> {
+    let path = path.trim();
+    if path.starts_with('/') && !uses_xpath(tree) {
+        tree.find_by_json_pointer(path)
+    } else if path.starts_with('/') {
+        let TreeVariant::InMemory(in_memory) = tree else {
+            return None;
+        };
+        let xpath = XPath::parse(path).ok()?;
+        xpath.evaluate(in_memory).first().copied()
+    } else if path.starts_with(['.', '[']) {
+        tree.find_by_path_str(path)
+    } else {
+        resolve_dn(tree, path)
+    }
+}
+
+/// Whether jq accepts a key without quotes, as in `.name`
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// An XPath such as `/catalog/item[2]/@id`, evaluable by
+/// [`XPath`](super::xpath::XPath).
+///
+/// Elements get a position only when siblings share their name, and attributes
+/// are addressed on their element rather than through `@attributes`.
+pub fn xpath(tree: &TreeVariant, node_id: usize) -> String {
+    let mut path = String::new();
+    for (id, node) in ancestry(tree, node_id) {
+        let parent = tree.get_parent(id).unwrap_or(tree.root_id());
+        if node.is_virtual_attributes() {
+            // Only the `@attributes` node itself stands for all attributes
+            if id == node_id {
+                path.push_str("/@*");
+            }
+            continue;
+        }
+        let in_attributes = tree
+            .get_node_blocking(parent)
+            .is_some_and(|p| p.is_virtual_attributes());
+
+        if in_attributes {
+            path.push_str("/@");
+            path.push_str(&node.label);
+            continue;
+        }
+        let step = match node.node_type.as_str() {
+            "text" => "text()".to_string(),
+            "comment" => "comment()".to_string(),
+            _ if is_xml_name(&node.label) => node.label.to_string(),
+            // Labels that aren't names are addressed among all elements
+            _ => "*".to_string(),
+        };
+        path.push('/');
+        path.push_str(&step);
+
+        // Siblings the step also selects
+        let siblings: Vec<usize> = tree
+            .get_children(parent)
+            .into_iter()
+            .filter(|&sibling| {
+                tree.get_node_blocking(sibling)
+                    .is_some_and(|s| !s.is_virtual_attributes() && same_step(&s, &node, &step))
+            })
+            .collect();
+        if siblings.len() > 1 {
+            let position = siblings.iter().position(|&s| s == id).unwrap_or(0);
+            path.push_str(&format!("[{}]", position + 1));
+        }
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+/// Whether `sibling` is selected by the step that selects `node`
+fn same_step(sibling: &TreeNode, node: &TreeNode, step: &str) -> bool {
+    let is_element = |n: &TreeNode| !matches!(n.node_type.as_str(), "text" | "comment");
+    match step {
+        "text()" | "comment()" => sibling.node_type == node.node_type,
+        "*" => is_element(sibling),
+        _ => is_element(sibling) && sibling.label == node.label,
+    }
+}
+
+/// Whether a label can be written as an XPath name test
+fn is_xml_name(label: &str) -> bool {
+    let mut chars = label.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        Parser, json::JsonParser, jsonlines::JsonLinesParser, ldif::LdifParser, xml::XmlParser,
+    };
+    use crate::tree::Tree;
+
+    /// The ID of the first node with `label`, in document order
+    fn find(tree: &Tree, label: &str) -> usize {
+        (0..tree.node_count())
+            .find(|&id| tree.get_node(id).unwrap().label == label)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_jq_paths() {
+        let json = r#"{"users": [{"id": 1}, {"id": 2, "e-mail": "b@x", "tags": ["a"]}]}"#;
+        let tree = JsonParser.parse(json).unwrap();
+        let email = find(&tree, "e-mail");
+        let tags = find(&tree, "tags");
+        let tree = TreeVariant::from(tree);
+
+        assert_eq!(jq_path(&tree, tree.root_id()), ".");
+        assert_eq!(jq_path(&tree, email), r#".users[1]."e-mail""#);
+        let tag = tree.get_children(tags)[0];
+        assert_eq!(jq_path(&tree, tag), ".users[1].tags[0]");
+
+        let tree = TreeVariant::from(JsonParser.parse("[[1], 2]").unwrap());
+        let inner = tree.get_children(tree.root_id())[0];
+        assert_eq!(jq_path(&tree, tree.get_children(inner)[0]), ".[0][0]");
+    }
+
+    #[test]
+    fn test_jq_paths_resolve_to_their_node() {
+        let json = r#"{"users": [{"id": 1}, {"id": 2, "e-\"mail": "b@x", "tags": ["a"]}]}"#;
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        for path in [".users[1].\"e-\\\"mail\"", ".users[1].tags[0]", ".users"] {
+            let node_id = tree.find_by_path_str(path).unwrap();
+            assert_eq!(jq_path(&tree, node_id), path);
+        }
+        assert_eq!(resolve_path(&tree, "."), Some(tree.root_id()));
+        assert_eq!(resolve_path(&tree, ".users[2]"), None);
+        assert_eq!(resolve_path(&tree, ".nobody"), None);
+    }
+
+    #[test]
+    fn test_jq_paths_of_json_lines_use_positions() {
+        // The records are labeled with their lines, 1 and 3
+        let parsed = JsonLinesParser::default()
+            .parse("{\"a\": 1}\n\n{\"a\": 2}\n")
+            .unwrap();
+        let tree = TreeVariant::from(parsed);
+        let second = tree.get_children(tree.root_id())[1];
+        assert_eq!(jq_path(&tree, second), ".[1]");
+        assert_eq!(resolve_path(&tree, ".[1]"), Some(second));
+        assert_eq!(resolve_path(&tree, "[1]"), Some(second));
+        assert_eq!(resolve_path(&tree, "/1"), Some(second));
+        assert_eq!(resolve_path(&tree, ".[3]"), None);
+    }
+
+    #[test]
+    fn test_json_pointers_resolve_to_their_node() {
+        let json = r#"{"users": [{"id": 1}, {"a/b~c": 2}], "0": {"x": 3}}"#;
+        let parsed = JsonParser.parse(json).unwrap();
+        let escaped = find(&parsed, "a/b~c");
+        let x = find(&parsed, "x");
+        let tree = TreeVariant::from(parsed);
+
+        assert_eq!(json_pointer(&tree, escaped), "/users/1/a~1b~0c");
+        assert_eq!(json_pointer(&tree, x), "/0/x");
+        assert_eq!(json_pointer(&tree, tree.root_id()), "");
+        assert_eq!(tree.find_by_json_pointer(""), Some(tree.root_id()));
+        for node_id in [escaped, x] {
+            let pointer = json_pointer(&tree, node_id);
+            assert_eq!(resolve_path(&tree, &pointer), Some(node_id), "{pointer}");
+        }
+
+        // Other formats get a pointer of their labels
+        let parsed = XmlParser.parse(r#"<a><b id="1"/></a>"#).unwrap();
+        let id = find(&parsed, "id");
+        let tree = TreeVariant::from(parsed);
+        let pointer = json_pointer(&tree, id);
+        assert_eq!(pointer, "/a/b/@attributes/id");
+        assert_eq!(tree.find_by_json_pointer(&pointer), Some(id));
+    }
+
+    #[test]
+    fn test_paths_of_every_kind_resolve() {
+        let ldif = "dn: dc=example,dc=com\ndc: example\n\n\
+                    dn: ou=People,dc=example,dc=com\nou: People\n\n\
+                    dn: cn=Ann,ou=People,dc=example,dc=com\ncn: Ann\n";
+        let parsed = LdifParser::default().parse(ldif).unwrap();
+        let ann = find(&parsed, "cn=Ann");
+        let tree = TreeVariant::from(parsed);
+        assert_eq!(
+            resolve_path(&tree, "CN=Ann,ou=people,dc=example,dc=com"),
+            Some(ann)
+        );
+        assert_eq!(
+            resolve_path(&tree, "cn=Bob,ou=People,dc=example,dc=com"),
+            None
+        );
+
+        let parsed = XmlParser.parse("<a><b/><b>x</b></a>").unwrap();
+        let tree = TreeVariant::from(parsed);
+        let second = tree.get_children(tree.get_children(tree.root_id())[0])[1];
+        assert_eq!(resolve_path(&tree, "/a/b[2]"), Some(second));
+        assert_eq!(resolve_path(&tree, "/a/c"), None);
+
+        let tree = TreeVariant::from(JsonParser.parse(r#"{"a": {"b": 1}}"#).unwrap());
+        let a = tree.get_children(tree.root_id())[0];
+        assert_eq!(
+            resolve_path(&tree, ".a.b"),
+            tree.get_children(a).first().copied()
+        );
+    }
+
+    #[test]
+    fn test_xpaths_select_the_node_again() {
+        let xml = r#"<shop><item id="a">one</item><note>hi</note><item id="b">two<b/>three</item></shop>"#;
+        let parsed = XmlParser.parse(xml).unwrap();
+        let tree = TreeVariant::from(XmlParser.parse(xml).unwrap());
+
+        let expected = [
+            (find(&parsed, "shop"), "/shop"),
+            (find(&parsed, "note"), "/shop/note"),
+            (find(&parsed, "b"), "/shop/item[2]/b"),
+        ];
+        for (id, path) in expected {
+            assert_eq!(xpath(&tree, id), path);
+        }
+
+        let second = tree.get_children(find(&parsed, "shop"))[2];
+        let attributes = tree.get_children(second)[0];
+        let id = tree.get_children(attributes)[0];
+        let text = tree.get_children(second)[3];
+        assert_eq!(xpath(&tree, attributes), "/shop/item[2]/@*");
+        assert_eq!(xpath(&tree, id), "/shop/item[2]/@id");
+        assert_eq!(xpath(&tree, text), "/shop/item[2]/text()[2]");
+
+        for node_id in [id, text, second] {
+            let selected = XPath::parse(&xpath(&tree, node_id))
+                .unwrap()
+                .evaluate(&parsed);
+            assert_eq!(selected, [node_id]);
+        }
+    }
+}
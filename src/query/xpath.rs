@@ -0,0 +1,992 @@
+//! A practical subset of XPath 1.0, evaluated over the trees built by the XML
+//! and HTML parsers.
+//!
+//! The tree's root is the document node, `element` nodes are elements, `text`
+//! and `comment` nodes are text and comments, and the attribute nodes under an
+//! element's `@attributes` node make up its attribute axis, so `@id` selects
+//! the same node the tree view shows.
+//!
+//! Supported are:
+//!
+//! - absolute and relative location paths with `/` and `//`
+//! - steps: names, `*`, `@name`, `@*`, `text()`, `comment()`, `node()`, `.`
+//!   and `..`
+//! - predicates with positions (`[2]`, `[last()]`), comparisons (`=`, `!=`,
+//!   `<`, `<=`, `>`, `>=`), `and`, `or` and parentheses
+//! - the functions `last()`, `position()`, `count()`, `contains()`,
+//!   `starts-with()`, `not()` and `string()`
+//!
+//! # Examples
+//!
+//! ```
+//! use xtv::parser::{Parser, xml::XmlParser};
+//! use xtv::query::xpath::XPath;
+//!
+//! let tree = XmlParser
+//!     .parse(r#"<shop><item id="a"/><item id="b"/></shop>"#)
+//!     .unwrap();
+//! let ids = XPath::parse("/shop/item[2]/@id").unwrap().evaluate(&tree);
+//! assert_eq!(tree.get_node(ids[0]).unwrap().label, "id");
+//! ```
+
+use crate::error::{Result, XtvError};
+use crate::tree::{Tree, TreeNode};
+use std::collections::HashSet;
+
+/// A parsed XPath expression
+#[derive(Debug, Clone)]
+pub struct XPath {
+    path: LocationPath,
+}
+
+impl XPath {
+    /// Parse an expression, which must be a location path
+    pub fn parse(expr: &str) -> Result<Self> {
+        let tokens = tokenize(expr)?;
+        let mut parser = ExprParser {
+            tokens,
+            index: 0,
+            len: expr.len(),
+        };
+        let path = parser.parse_path()?;
+        if let Some((offset, token)) = parser.tokens.get(parser.index) {
+            return Err(error(format!("unexpected {} at {}", token, offset + 1)));
+        }
+        Ok(Self { path })
+    }
+
+    /// The IDs of the selected nodes, in document order
+    pub fn evaluate(&self, tree: &Tree) -> Vec<usize> {
+        let evaluator = Evaluator { tree };
+        let selected: HashSet<usize> = evaluator
+            .eval_path(&self.path, tree.root_id())
+            .into_iter()
+            .collect();
+        if selected.is_empty() {
+            return Vec::new();
+        }
+
+        // Walk the tree once to sort the selection
+        let mut ordered = Vec::with_capacity(selected.len());
+        let mut stack = vec![tree.root_id()];
+        while let Some(node_id) = stack.pop() {
+            if selected.contains(&node_id) {
+                ordered.push(node_id);
+            }
+            if let Some(node) = tree.get_node(node_id) {
+                stack.extend(node.children.iter().rev());
+            }
+        }
+        ordered
+    }
+}
+
+fn error(message: String) -> XtvError {
+    XtvError::XPath(message)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    Dot,
+    DotDot,
+    At,
+    Star,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Comma,
+    Op(CmpOp),
+    Name(String),
+    Literal(String),
+    Number(f64),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Slash => write!(f, "'/'"),
+            Token::DoubleSlash => write!(f, "'//'"),
+            Token::Dot => write!(f, "'.'"),
+            Token::DotDot => write!(f, "'..'"),
+            Token::At => write!(f, "'@'"),
+            Token::Star => write!(f, "'*'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+            Token::Op(op) => write!(f, "'{}'", op.symbol()),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Literal(text) => write!(f, "'{}'", text),
+            Token::Number(n) => write!(f, "'{}'", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        }
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')
+}
+
+/// Split an expression into tokens with their byte offsets
+fn tokenize(expr: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c);
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '/' if next == Some('/') => {
+                chars.next();
+                Token::DoubleSlash
+            }
+            '/' => Token::Slash,
+            '.' if next == Some('.') => {
+                chars.next();
+                Token::DotDot
+            }
+            '.' if !next.is_some_and(|c| c.is_ascii_digit()) => Token::Dot,
+            '@' => Token::At,
+            '*' => Token::Star,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '=' => Token::Op(CmpOp::Eq),
+            '!' if next == Some('=') => {
+                chars.next();
+                Token::Op(CmpOp::Ne)
+            }
+            '<' | '>' => {
+                let or_equal = next == Some('=');
+                if or_equal {
+                    chars.next();
+                }
+                Token::Op(match (c, or_equal) {
+                    ('<', false) => CmpOp::Lt,
+                    ('<', true) => CmpOp::Le,
+                    ('>', false) => CmpOp::Gt,
+                    _ => CmpOp::Ge,
+                })
+            }
+            '\'' | '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, end)) if end == c => break,
+                        Some((_, ch)) => text.push(ch),
+                        None => {
+                            return Err(error(format!(
+                                "unterminated string starting at {}",
+                                offset + 1
+                            )));
+                        }
+                    }
+                }
+                Token::Literal(text)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if !(ch.is_ascii_digit() || ch == '.') {
+                        break;
+                    }
+                    number.push(ch);
+                    chars.next();
+                }
+                let value = number
+                    .parse()
+                    .map_err(|_| error(format!("invalid number '{}' at {}", number, offset + 1)))?;
+                Token::Number(value)
+            }
+            c if is_name_start(c) => {
+                let mut name = c.to_string();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if !is_name_char(ch) {
+                        break;
+                    }
+                    name.push(ch);
+                    chars.next();
+                }
+                Token::Name(name)
+            }
+            c => return Err(error(format!("unexpected '{}' at {}", c, offset + 1))),
+        };
+        tokens.push((offset, token));
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+struct LocationPath {
+    absolute: bool,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+impl Step {
+    /// The step `//` stands for
+    fn descendant_or_self() -> Self {
+        Self {
+            axis: Axis::DescendantOrSelf,
+            test: NodeTest::Node,
+            predicates: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Child,
+    Attribute,
+    DescendantOrSelf,
+    SelfNode,
+    Parent,
+}
+
+#[derive(Debug, Clone)]
+enum NodeTest {
+    Name(String),
+    /// `*`: any element, or any attribute on the attribute axis
+    Any,
+    Text,
+    Comment,
+    Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Last,
+    Position,
+    Count,
+    Contains,
+    StartsWith,
+    Not,
+    String,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "last" => Function::Last,
+            "position" => Function::Position,
+            "count" => Function::Count,
+            "contains" => Function::Contains,
+            "starts-with" => Function::StartsWith,
+            "not" => Function::Not,
+            "string" => Function::String,
+            _ => return None,
+        })
+    }
+
+    /// Smallest and largest number of arguments
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Function::Last | Function::Position => (0, 0),
+            Function::Count | Function::Not => (1, 1),
+            Function::String => (0, 1),
+            Function::Contains | Function::StartsWith => (2, 2),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Path(LocationPath),
+    Literal(String),
+    Number(f64),
+    Compare(Box<Expr>, CmpOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+/// Recursive descent over the tokens of an expression
+struct ExprParser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    /// Length of the expression, for errors at its end
+    len: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<&Token> {
+        self.tokens.get(self.index + ahead).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|(_, token)| token.clone());
+        self.index += 1;
+        token
+    }
+
+    /// An error about the next token, or about the end of the expression
+    fn unexpected(&self, expected: &str) -> XtvError {
+        match self.tokens.get(self.index) {
+            Some((offset, token)) => error(format!(
+                "expected {} but found {} at {}",
+                expected,
+                token,
+                offset + 1
+            )),
+            None => error(format!("expected {} at {}", expected, self.len + 1)),
+        }
+    }
+
+    fn expect(&mut self, token: Token, expected: &str) -> Result<()> {
+        if self.peek() == Some(&token) {
+            self.index += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn at_step(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Dot | Token::DotDot | Token::At | Token::Star | Token::Name(_))
+        )
+    }
+
+    fn parse_path(&mut self) -> Result<LocationPath> {
+        let mut path = LocationPath {
+            absolute: false,
+            steps: Vec::new(),
+        };
+        match self.peek() {
+            Some(Token::Slash) => {
+                self.index += 1;
+                path.absolute = true;
+                // A lone `/` selects the document
+                if !self.at_step() {
+                    return Ok(path);
+                }
+            }
+            Some(Token::DoubleSlash) => {
+                self.index += 1;
+                path.absolute = true;
+                path.steps.push(Step::descendant_or_self());
+            }
+            _ => {}
+        }
+
+        path.steps.push(self.parse_step()?);
+        loop {
+            match self.peek() {
+                Some(Token::Slash) => self.index += 1,
+                Some(Token::DoubleSlash) => {
+                    self.index += 1;
+                    path.steps.push(Step::descendant_or_self());
+                }
+                _ => return Ok(path),
+            }
+            path.steps.push(self.parse_step()?);
+        }
+    }
+
+    fn parse_step(&mut self) -> Result<Step> {
+        let (axis, test) = match self.peek() {
+            Some(Token::Dot) => {
+                self.index += 1;
+                return Ok(Step {
+                    axis: Axis::SelfNode,
+                    test: NodeTest::Node,
+                    predicates: Vec::new(),
+                });
+            }
+            Some(Token::DotDot) => {
+                self.index += 1;
+                return Ok(Step {
+                    axis: Axis::Parent,
+                    test: NodeTest::Node,
+                    predicates: Vec::new(),
+                });
+            }
+            Some(Token::At) => {
+                self.index += 1;
+                match self.next() {
+                    Some(Token::Name(name)) => (Axis::Attribute, NodeTest::Name(name)),
+                    Some(Token::Star) => (Axis::Attribute, NodeTest::Any),
+                    _ => {
+                        self.index -= 1;
+                        return Err(self.unexpected("an attribute name"));
+                    }
+                }
+            }
+            Some(Token::Star) => {
+                self.index += 1;
+                (Axis::Child, NodeTest::Any)
+            }
+            Some(Token::Name(_)) => (Axis::Child, self.parse_name_test()?),
+            _ => return Err(self.unexpected("a step")),
+        };
+
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::LBracket) {
+            self.index += 1;
+            predicates.push(self.parse_or()?);
+            self.expect(Token::RBracket, "']'")?;
+        }
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+        })
+    }
+
+    /// A name, or one of the node type tests `text()`, `comment()` and `node()`
+    fn parse_name_test(&mut self) -> Result<NodeTest> {
+        let Some(Token::Name(name)) = self.next() else {
+            unreachable!("called on a name");
+        };
+        if self.peek() != Some(&Token::LParen) {
+            return Ok(NodeTest::Name(name));
+        }
+        let test = match name.as_str() {
+            "text" => NodeTest::Text,
+            "comment" => NodeTest::Comment,
+            "node" => NodeTest::Node,
+            _ => {
+                self.index -= 1;
+                return Err(self.unexpected("a step"));
+            }
+        };
+        self.index += 1;
+        self.expect(Token::RParen, "')'")?;
+        Ok(test)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Name("or".to_string())) {
+            self.index += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_comparison()?;
+        while self.peek() == Some(&Token::Name("and".to_string())) {
+            self.index += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr> {
+        let left = self.parse_primary()?;
+        match self.peek() {
+            Some(&Token::Op(op)) => {
+                self.index += 1;
+                let right = self.parse_primary()?;
+                Ok(Expr::Compare(Box::new(left), op, Box::new(right)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Literal(_)) => {
+                let Some(Token::Literal(text)) = self.next() else {
+                    unreachable!();
+                };
+                Ok(Expr::Literal(text))
+            }
+            Some(&Token::Number(n)) => {
+                self.index += 1;
+                Ok(Expr::Number(n))
+            }
+            Some(Token::LParen) => {
+                self.index += 1;
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(expr)
+            }
+            Some(Token::Name(name))
+                if self.peek_at(1) == Some(&Token::LParen)
+                    && !matches!(name.as_str(), "text" | "comment" | "node") =>
+            {
+                self.parse_call()
+            }
+            Some(Token::Slash | Token::DoubleSlash) => Ok(Expr::Path(self.parse_path()?)),
+            _ if self.at_step() => Ok(Expr::Path(self.parse_path()?)),
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    fn parse_call(&mut self) -> Result<Expr> {
+        let (offset, name) = match self.tokens[self.index].clone() {
+            (offset, Token::Name(name)) => (offset, name),
+            _ => unreachable!("called on a name"),
+        };
+        let function = Function::from_name(&name)
+            .ok_or_else(|| error(format!("unknown function '{}' at {}", name, offset + 1)))?;
+        self.index += 2;
+
+        let mut args = Vec::new();
+        if self.peek() != Some(&Token::RParen) {
+            args.push(self.parse_or()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.index += 1;
+                args.push(self.parse_or()?);
+            }
+        }
+        self.expect(Token::RParen, "')'")?;
+
+        let (min, max) = function.arity();
+        if args.len() < min || args.len() > max {
+            return Err(error(format!(
+                "{}() takes {} argument(s), not {}",
+                name,
+                if min == max {
+                    min.to_string()
+                } else {
+                    format!("{} to {}", min, max)
+                },
+                args.len()
+            )));
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
+
+/// What a node is in XPath terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Document,
+    Element,
+    Text,
+    Comment,
+    /// The `@attributes` node holding an element's attributes
+    Attributes,
+    Attribute,
+}
+
+/// Result of an expression
+#[derive(Debug, Clone)]
+enum Value {
+    Nodes(Vec<usize>),
+    Str(String),
+    Num(f64),
+    Bool(bool),
+}
+
+/// A node being tested by a predicate
+#[derive(Debug, Clone, Copy)]
+struct Context {
+    node: usize,
+    /// 1-based position among the nodes the step selected
+    position: usize,
+    size: usize,
+}
+
+struct Evaluator<'a> {
+    tree: &'a Tree,
+}
+
+impl Evaluator<'_> {
+    fn node(&self, id: usize) -> Option<&TreeNode> {
+        self.tree.get_node(id)
+    }
+
+    fn kind(&self, id: usize) -> Kind {
+        if id == self.tree.root_id() {
+            return Kind::Document;
+        }
+        match self.node(id) {
+            Some(node) if node.is_virtual_attributes() => Kind::Attributes,
+            Some(node) if node.is_attribute() => Kind::Attribute,
+            Some(node) if node.node_type == "text" => Kind::Text,
+            Some(node) if node.node_type == "comment" => Kind::Comment,
+            _ => Kind::Element,
+        }
+    }
+
+    fn children(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.node(id)
+            .map(|node| node.children.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .copied()
+    }
+
+    fn axis(&self, axis: Axis, id: usize) -> Vec<usize> {
+        match axis {
+            Axis::Child => self
+                .children(id)
+                .filter(|&child| self.kind(child) != Kind::Attributes)
+                .collect(),
+            Axis::Attribute => self
+                .children(id)
+                .filter(|&child| self.kind(child) == Kind::Attributes)
+                .flat_map(|attributes| self.children(attributes))
+                .collect(),
+            Axis::DescendantOrSelf => {
+                let mut nodes = Vec::new();
+                let mut stack = vec![id];
+                while let Some(node) = stack.pop() {
+                    if self.kind(node) == Kind::Attributes {
+                        continue;
+                    }
+                    nodes.push(node);
+                    let start = stack.len();
+                    stack.extend(self.children(node));
+                    stack[start..].reverse();
+                }
+                nodes
+            }
+            Axis::SelfNode => vec![id],
+            Axis::Parent => {
+                let parent = self.node(id).and_then(|node| node.parent_id);
+                // Attributes belong to the element, not to its `@attributes` node
+                let parent = match parent {
+                    Some(parent) if self.kind(parent) == Kind::Attributes => {
+                        self.node(parent).and_then(|node| node.parent_id)
+                    }
+                    parent => parent,
+                };
+                parent.into_iter().collect()
+            }
+        }
+    }
+
+    fn test(&self, axis: Axis, test: &NodeTest, id: usize) -> bool {
+        let kind = self.kind(id);
+        let principal = if axis == Axis::Attribute {
+            Kind::Attribute
+        } else {
+            Kind::Element
+        };
+        match test {
+            NodeTest::Name(name) => {
+                kind == principal && self.node(id).is_some_and(|node| node.label == *name)
+            }
+            NodeTest::Any => kind == principal,
+            NodeTest::Text => kind == Kind::Text,
+            NodeTest::Comment => kind == Kind::Comment,
+            NodeTest::Node => true,
+        }
+    }
+
+    fn eval_path(&self, path: &LocationPath, context: usize) -> Vec<usize> {
+        let start = if path.absolute {
+            self.tree.root_id()
+        } else {
+            context
+        };
+        let mut nodes = vec![start];
+        for step in &path.steps {
+            nodes = self.eval_step(step, &nodes);
+        }
+        nodes
+    }
+
+    fn eval_step(&self, step: &Step, nodes: &[usize]) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        let mut selected = Vec::new();
+        for &node in nodes {
+            let mut candidates: Vec<usize> = self
+                .axis(step.axis, node)
+                .into_iter()
+                .filter(|&id| self.test(step.axis, &step.test, id))
+                .collect();
+            for predicate in &step.predicates {
+                let size = candidates.len();
+                candidates = candidates
+                    .into_iter()
+                    .enumerate()
+                    .filter(|&(i, id)| {
+                        let context = Context {
+                            node: id,
+                            position: i + 1,
+                            size,
+                        };
+                        self.predicate(predicate, context)
+                    })
+                    .map(|(_, id)| id)
+                    .collect();
+            }
+            selected.extend(candidates.into_iter().filter(|&id| seen.insert(id)));
+        }
+        selected
+    }
+
+    /// A number selects by position, anything else is converted to a boolean
+    fn predicate(&self, expr: &Expr, context: Context) -> bool {
+        match self.eval(expr, context) {
+            Value::Num(n) => n == context.position as f64,
+            value => self.boolean(&value),
+        }
+    }
+
+    fn eval(&self, expr: &Expr, context: Context) -> Value {
+        match expr {
+            Expr::Path(path) => Value::Nodes(self.eval_path(path, context.node)),
+            Expr::Literal(text) => Value::Str(text.clone()),
+            Expr::Number(n) => Value::Num(*n),
+            Expr::Compare(left, op, right) => {
+                let left = self.eval(left, context);
+                let right = self.eval(right, context);
+                Value::Bool(self.compare(&left, *op, &right))
+            }
+            Expr::And(left, right) => Value::Bool(
+                self.boolean(&self.eval(left, context)) && self.boolean(&self.eval(right, context)),
+            ),
+            Expr::Or(left, right) => Value::Bool(
+                self.boolean(&self.eval(left, context)) || self.boolean(&self.eval(right, context)),
+            ),
+            Expr::Call(function, args) => self.call(*function, args, context),
+        }
+    }
+
+    fn call(&self, function: Function, args: &[Expr], context: Context) -> Value {
+        let arg = |i: usize| self.eval(&args[i], context);
+        match function {
+            Function::Last => Value::Num(context.size as f64),
+            Function::Position => Value::Num(context.position as f64),
+            Function::Count => match arg(0) {
+                Value::Nodes(nodes) => Value::Num(nodes.len() as f64),
+                _ => Value::Num(f64::NAN),
+            },
+            Function::Contains => Value::Bool(self.string(&arg(0)).contains(&self.string(&arg(1)))),
+            Function::StartsWith => {
+                Value::Bool(self.string(&arg(0)).starts_with(&self.string(&arg(1))))
+            }
+            Function::Not => Value::Bool(!self.boolean(&arg(0))),
+            Function::String if args.is_empty() => Value::Str(self.string_value(context.node)),
+            Function::String => Value::Str(self.string(&arg(0))),
+        }
+    }
+
+    /// The text of a node: an attribute's value, a text node's content, or
+    /// the text inside an element
+    fn string_value(&self, id: usize) -> String {
+        let content = |id: usize, key: &str| {
+            self.node(id)
+                .and_then(|node| node.attributes.iter().find(|attr| attr.key == key))
+                .map(|attr| attr.value.clone())
+                .unwrap_or_default()
+        };
+        match self.kind(id) {
+            Kind::Attribute => content(id, "value"),
+            Kind::Text | Kind::Comment => content(id, "content"),
+            Kind::Attributes => String::new(),
+            Kind::Document | Kind::Element => {
+                let mut text = String::new();
+                for node in self.axis(Axis::DescendantOrSelf, id) {
+                    if self.kind(node) == Kind::Text {
+                        text.push_str(&content(node, "content"));
+                    }
+                }
+                text
+            }
+        }
+    }
+
+    fn string(&self, value: &Value) -> String {
+        match value {
+            Value::Nodes(nodes) => nodes
+                .first()
+                .map(|&id| self.string_value(id))
+                .unwrap_or_default(),
+            Value::Str(text) => text.clone(),
+            Value::Num(n) if n.fract() == 0.0 && n.is_finite() => format!("{}", *n as i64),
+            Value::Num(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+        }
+    }
+
+    fn number(&self, value: &Value) -> f64 {
+        match value {
+            Value::Num(n) => *n,
+            Value::Bool(b) => f64::from(u8::from(*b)),
+            value => self.string(value).trim().parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    fn boolean(&self, value: &Value) -> bool {
+        match value {
+            Value::Nodes(nodes) => !nodes.is_empty(),
+            Value::Str(text) => !text.is_empty(),
+            Value::Num(n) => *n != 0.0 && !n.is_nan(),
+            Value::Bool(b) => *b,
+        }
+    }
+
+    /// Compare two values the XPath way: a node set compares true if any of
+    /// its nodes does
+    fn compare(&self, left: &Value, op: CmpOp, right: &Value) -> bool {
+        let text = |id: usize| Value::Str(self.string_value(id));
+        match (left, right) {
+            (Value::Nodes(_), Value::Bool(_)) | (Value::Bool(_), Value::Nodes(_)) => {
+                let left = Value::Bool(self.boolean(left));
+                let right = Value::Bool(self.boolean(right));
+                self.compare_atoms(&left, op, &right)
+            }
+            (Value::Nodes(left), Value::Nodes(right)) => left.iter().any(|&l| {
+                right
+                    .iter()
+                    .any(|&r| self.compare_atoms(&text(l), op, &text(r)))
+            }),
+            (Value::Nodes(left), right) => left
+                .iter()
+                .any(|&l| self.compare_atoms(&text(l), op, right)),
+            (left, Value::Nodes(right)) => right
+                .iter()
+                .any(|&r| self.compare_atoms(left, op, &text(r))),
+            (left, right) => self.compare_atoms(left, op, right),
+        }
+    }
+
+    fn compare_atoms(&self, left: &Value, op: CmpOp, right: &Value) -> bool {
+        let is_bool = |v: &Value| matches!(v, Value::Bool(_));
+        let is_num = |v: &Value| matches!(v, Value::Num(_));
+        match op {
+            CmpOp::Eq | CmpOp::Ne => {
+                let equal = if is_bool(left) || is_bool(right) {
+                    self.boolean(left) == self.boolean(right)
+                } else if is_num(left) || is_num(right) {
+                    self.number(left) == self.number(right)
+                } else {
+                    self.string(left) == self.string(right)
+                };
+                equal == (op == CmpOp::Eq)
+            }
+            _ => {
+                let (l, r) = (self.number(left), self.number(right));
+                match op {
+                    CmpOp::Lt => l < r,
+                    CmpOp::Le => l <= r,
+                    CmpOp::Gt => l > r,
+                    _ => l >= r,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, html::HtmlParser, xml::XmlParser};
+
+    const CATALOG: &str = r#"<catalog>
+        <item id="a" type="book"><name>Dune</name><price>12</price></item>
+        <item id="b" type="film"><name>Alien</name><price>8</price></item>
+        <item id="c" type="book"><name>Emma</name><price>5.5</price></item>
+    </catalog>"#;
+
+    /// The text of each node selected by `expr`
+    fn select(tree: &Tree, expr: &str) -> Vec<String> {
+        let evaluator = Evaluator { tree };
+        XPath::parse(expr)
+            .unwrap()
+            .evaluate(tree)
+            .into_iter()
+            .map(|id| evaluator.string_value(id))
+            .collect()
+    }
+
+    #[test]
+    fn test_paths_and_positions() {
+        let tree = XmlParser.parse(CATALOG).unwrap();
+        assert_eq!(select(&tree, "/catalog/item[2]/@id"), ["b"]);
+        assert_eq!(select(&tree, "/catalog/item[last()]/name"), ["Emma"]);
+        assert_eq!(select(&tree, "//name"), ["Dune", "Alien", "Emma"]);
+        assert_eq!(select(&tree, "//item/@*[1]"), ["a", "b", "c"]);
+        assert_eq!(select(&tree, "//price/text()"), ["12", "8", "5.5"]);
+        assert_eq!(select(&tree, "//@id/../name"), ["Dune", "Alien", "Emma"]);
+        assert_eq!(select(&tree, "/catalog/*[position() > 1]/@id"), ["b", "c"]);
+        assert!(select(&tree, "/item").is_empty());
+    }
+
+    #[test]
+    fn test_attribute_nodes_are_the_tree_nodes() {
+        let tree = XmlParser.parse(CATALOG).unwrap();
+        let ids = XPath::parse("//item[1]/@type").unwrap().evaluate(&tree);
+        let node = tree.get_node(ids[0]).unwrap();
+        assert!(node.is_attribute());
+        assert_eq!(node.label, "type");
+    }
+
+    #[test]
+    fn test_predicates() {
+        let tree = XmlParser.parse(CATALOG).unwrap();
+        assert_eq!(select(&tree, "//item[@type='book']/@id"), ["a", "c"]);
+        assert_eq!(select(&tree, "//item[price>6]/name"), ["Dune", "Alien"]);
+        assert_eq!(
+            select(&tree, "//item[@type='book' and price < 10]/@id"),
+            ["c"]
+        );
+        assert_eq!(
+            select(&tree, "//item[not(@type='book') or name='Emma']/@id"),
+            ["b", "c"]
+        );
+        assert_eq!(select(&tree, "//item[contains(name, 'li')]/@id"), ["b"]);
+        assert_eq!(select(&tree, "//item[starts-with(@id, 'c')]/@id"), ["c"]);
+        assert_eq!(
+            select(&tree, "/catalog[count(item) = 3]/item[1]/@id"),
+            ["a"]
+        );
+    }
+
+    #[test]
+    fn test_html_class_selector() {
+        let html = r#"<html><body><div class="x">one</div><div class="y">two<!-- n --></div></body></html>"#;
+        let tree = HtmlParser::default().parse(html).unwrap();
+        assert_eq!(select(&tree, "//div[@class='x']"), ["one"]);
+        assert_eq!(select(&tree, "//div[comment()]/@class"), ["y"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expr in ["", "//item[", "/a/@", "//item[foo(1)]", "/a]", "//a[@b='c]"] {
+            assert!(XPath::parse(expr).is_err(), "{expr} parsed");
+        }
+        let error = XPath::parse("//item[@id=]").unwrap_err().to_string();
+        assert!(error.contains("expected an expression"), "{error}");
+    }
+}
@@ -0,0 +1,138 @@
+//! How long loading a file took, step by step.
+//!
+//! The viewer shows a one-line summary in the footer once it has started, and
+//! `xtv --bench FILE` prints the whole breakdown as JSON and exits, which
+//! tells whether a slow file spends its time being read, parsed or indexed.
+
+use crate::tree::TreeVariant;
+use crate::ui::tree_view::format_size;
+use serde_json::json;
+use std::time::Duration;
+
+/// The steps of loading one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadTimings {
+    /// Name of the format the file was read as
+    pub format: &'static str,
+    /// Size of the file or input
+    pub bytes: u64,
+    /// Whether an index was built instead of a tree in memory
+    pub streaming: bool,
+    /// Picking the parser by `--format`, extension or content
+    pub detect: Duration,
+    /// Reading the content into memory; streamed files are never read whole
+    pub read: Duration,
+    /// Parsing the content, or building the index of a streamed file
+    pub parse: Duration,
+    /// Nodes in the tree or index
+    pub nodes: usize,
+}
+
+impl LoadTimings {
+    pub fn total(&self) -> Duration {
+        self.detect + self.read + self.parse
+    }
+
+    /// The footer's summary, e.g. `Parsed 412.0 MB in 28.4s, 3.1M nodes`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use xtv::timing::LoadTimings;
+    ///
+    /// let timings = LoadTimings {
+    ///     format: "xml",
+    ///     bytes: 412 * 1024 * 1024,
+    ///     streaming: false,
+    ///     detect: Duration::from_millis(1),
+    ///     read: Duration::from_millis(900),
+    ///     parse: Duration::from_millis(27_500),
+    ///     nodes: 3_140_000,
+    /// };
+    /// assert_eq!(timings.summary(), "Parsed 412.0 MB in 28.4s, 3.1M nodes");
+    /// ```
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {} in {}, {} nodes",
+            if self.streaming { "Indexed" } else { "Parsed" },
+            format_size(self.bytes as usize),
+            format_duration(self.total()),
+            format_count(self.nodes)
+        )
+    }
+
+    /// The breakdown printed by `--bench`, with statistics of `tree`
+    pub fn to_json(&self, tree: &TreeVariant) -> serde_json::Value {
+        let stats = tree.tree_stats();
+        let milliseconds = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        json!({
+            "format": self.format,
+            "bytes": self.bytes,
+            "streaming": self.streaming,
+            "nodes": self.nodes,
+            "timings_ms": {
+                "detect": milliseconds(self.detect),
+                "read": milliseconds(self.read),
+                if self.streaming { "index" } else { "parse" }: milliseconds(self.parse),
+                "total": milliseconds(self.total()),
+            },
+            "max_depth": stats.max_depth,
+            "by_type": stats.by_type,
+            "memory_bytes": stats.memory.total_bytes(),
+        })
+    }
+}
+
+/// `28.4s`, or `312 ms` below a second
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{} ms", duration.as_millis())
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
+
+/// `950`, `12.3K` or `3.1M`
+fn format_count(count: usize) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => format!("{:.1}K", count as f64 / 1e3),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser};
+
+    #[test]
+    fn test_breakdown_as_json() {
+        let tree = TreeVariant::from(JsonParser.parse(r#"{"a": [1, 2]}"#).unwrap());
+        let timings = LoadTimings {
+            format: "json",
+            bytes: 13,
+            streaming: false,
+            detect: Duration::ZERO,
+            read: Duration::from_millis(2),
+            parse: Duration::from_micros(500),
+            nodes: tree.node_count(),
+        };
+        assert_eq!(timings.summary(), "Parsed 13 B in 2 ms, 4 nodes");
+
+        let json = timings.to_json(&tree);
+        assert_eq!(json["nodes"], 4);
+        assert_eq!(json["timings_ms"]["parse"], 0.5);
+        assert_eq!(json["timings_ms"]["total"], 2.5);
+        assert_eq!(json["max_depth"], 2);
+        assert_eq!(json["by_type"]["array"], 1);
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(12_345), "12.3K");
+        assert_eq!(format_count(3_140_000), "3.1M");
+    }
+}
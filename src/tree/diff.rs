@@ -0,0 +1,290 @@
+//! Structural differences between two trees.
+//!
+//! Nodes are matched by their path: children of matched nodes pair up by
+//! label, and children sharing a label (array indices are unique, repeated
+//! XML elements are not) by their position among the siblings with that
+//! label. The result is a single merged tree holding the new tree's nodes,
+//! with removed nodes at the place they had in the old tree, and a
+//! [`Change`] for every node that differs.
+
+use crate::tree::stats::leaf_value;
+use crate::tree::{Tree, TreeNode};
+use std::collections::HashMap;
+
+/// How a node of the merged tree differs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Only in the new tree
+    Added,
+    /// Only in the old tree
+    Removed,
+    /// A value or text that differs; holds the old one
+    Value(String),
+    /// A node whose type differs, e.g. an object that became an array; holds
+    /// the old type
+    Type(String),
+}
+
+/// Two trees merged into one, with their differences
+#[derive(Debug)]
+pub struct Diff {
+    /// The merged tree. Its node IDs are in document order.
+    pub tree: Tree,
+    /// Changed nodes of `tree`, including every node of an added or removed
+    /// subtree
+    pub changes: HashMap<usize, Change>,
+    /// The changed nodes that begin a difference, in document order: nodes
+    /// inside an added or removed subtree are left out
+    pub differences: Vec<usize>,
+}
+
+/// A node of the merged tree yet to be added below `parent`
+enum Pending {
+    Both { old: usize, new: usize },
+    Added(usize),
+    Removed(usize),
+}
+
+/// Merge `old` and `new` into one tree showing their differences
+pub fn diff(old: &Tree, new: &Tree) -> Diff {
+    let root = new
+        .get_node(new.root_id())
+        .map(copy_node)
+        .unwrap_or_else(|| TreeNode::new("root", "root"));
+    let mut tree = Tree::new(root);
+    if let Some(format) = new.format() {
+        tree = tree.with_format(format);
+    }
+    let mut changes = HashMap::new();
+
+    // Children are pushed in reverse so that nodes are added in document order
+    let mut stack: Vec<(usize, Pending)> = pair_children(old, new, old.root_id(), new.root_id())
+        .into_iter()
+        .rev()
+        .map(|pending| (tree.root_id(), pending))
+        .collect();
+    while let Some((parent, pending)) = stack.pop() {
+        let (node, change, children) = match pending {
+            Pending::Both {
+                old: old_id,
+                new: new_id,
+            } => {
+                let (Some(old_node), Some(new_node)) = (old.get_node(old_id), new.get_node(new_id))
+                else {
+                    continue;
+                };
+                let change = if old_node.node_type != new_node.node_type {
+                    Some(Change::Type(old_node.node_type.to_string()))
+                } else {
+                    match (leaf_value(old_node), leaf_value(new_node)) {
+                        (Some(a), Some(b)) if a != b => Some(Change::Value(a.to_string())),
+                        _ => None,
+                    }
+                };
+                let children = pair_children(old, new, old_id, new_id);
+                (copy_node(new_node), change, children)
+            }
+            Pending::Added(id) => {
+                let Some(node) = new.get_node(id) else {
+                    continue;
+                };
+                let children = node.children.iter().map(|&c| Pending::Added(c)).collect();
+                (copy_node(node), Some(Change::Added), children)
+            }
+            Pending::Removed(id) => {
+                let Some(node) = old.get_node(id) else {
+                    continue;
+                };
+                let children = node.children.iter().map(|&c| Pending::Removed(c)).collect();
+                (copy_node(node), Some(Change::Removed), children)
+            }
+        };
+
+        let node_id = tree.add_child_node(parent, node);
+        if let Some(change) = change {
+            changes.insert(node_id, change);
+        }
+        stack.extend(children.into_iter().rev().map(|pending| (node_id, pending)));
+    }
+
+    let mut differences: Vec<usize> = changes
+        .iter()
+        .filter(|(id, change)| {
+            let parent = tree
+                .get_parent(**id)
+                .and_then(|parent| changes.get(&parent));
+            !matches!(
+                (change, parent),
+                (Change::Added, Some(Change::Added)) | (Change::Removed, Some(Change::Removed))
+            )
+        })
+        .map(|(&id, _)| id)
+        .collect();
+    differences.sort_unstable();
+
+    Diff {
+        tree,
+        changes,
+        differences,
+    }
+}
+
+/// Pair up the children of `old_id` and `new_id`, in the new order with
+/// removed children before the first new child that followed them
+fn pair_children(old: &Tree, new: &Tree, old_id: usize, new_id: usize) -> Vec<Pending> {
+    let old_children = old.get_children(old_id);
+    let new_children = new.get_children(new_id);
+
+    let mut by_key: HashMap<(String, usize), usize> = HashMap::new();
+    for (index, key) in sibling_keys(old, &old_children).into_iter().enumerate() {
+        by_key.insert(key, index);
+    }
+
+    let mut matched = vec![false; old_children.len()];
+    let mut pairs = Vec::new();
+    for (new_child, key) in new_children.iter().zip(sibling_keys(new, &new_children)) {
+        match by_key.get(&key) {
+            Some(&index) => {
+                matched[index] = true;
+                pairs.push((
+                    Some(index),
+                    Pending::Both {
+                        old: old_children[index],
+                        new: *new_child,
+                    },
+                ));
+            }
+            None => pairs.push((None, Pending::Added(*new_child))),
+        }
+    }
+
+    let mut merged = Vec::new();
+    let mut removed = (0..old_children.len()).filter(|&i| !matched[i]).peekable();
+    for (index, pending) in pairs {
+        if let Some(index) = index {
+            while let Some(gone) = removed.next_if(|&gone| gone < index) {
+                merged.push(Pending::Removed(old_children[gone]));
+            }
+        }
+        merged.push(pending);
+    }
+    merged.extend(removed.map(|gone| Pending::Removed(old_children[gone])));
+    merged
+}
+
+/// Each child's label and how many earlier siblings share it
+fn sibling_keys(tree: &Tree, children: &[usize]) -> Vec<(String, usize)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    children
+        .iter()
+        .map(|&id| {
+            let label = tree.get_node(id).map_or("", |node| node.label.as_str());
+            let count = seen.entry(label).or_insert(0);
+            *count += 1;
+            (label.to_string(), *count - 1)
+        })
+        .collect()
+}
+
+/// A node's label, type, attributes and value kind, without its links
+fn copy_node(node: &TreeNode) -> TreeNode {
+    let mut copy =
+        TreeNode::new(node.label.clone(), node.node_type).with_attributes(node.attributes.to_vec());
+    copy.value_kind = node.value_kind;
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser, xml::XmlParser};
+
+    /// Changes as `(path, change)`, in document order
+    fn changes(diff: &Diff) -> Vec<(String, Change)> {
+        let mut ids: Vec<&usize> = diff.changes.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .map(|&id| {
+                let mut labels = Vec::new();
+                let mut current = Some(id);
+                while let Some(node_id) = current.filter(|&id| id != diff.tree.root_id()) {
+                    labels.push(diff.tree.get_node(node_id).unwrap().label.to_string());
+                    current = diff.tree.get_parent(node_id);
+                }
+                labels.reverse();
+                (labels.join("/"), diff.changes[&id].clone())
+            })
+            .collect()
+    }
+
+    fn json_diff(old: &str, new: &str) -> Diff {
+        diff(
+            &JsonParser.parse(old).unwrap(),
+            &JsonParser.parse(new).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_added_removed_and_changed_values() {
+        let diff = json_diff(
+            r#"{"a": 1, "b": {"c": "x", "d": true}, "e": [1, 2]}"#,
+            r#"{"a": 2, "b": {"c": "x", "f": null}, "e": [1, 2, 3]}"#,
+        );
+        assert_eq!(
+            changes(&diff),
+            [
+                ("a".to_string(), Change::Value("1".into())),
+                // Nothing of the new tree follows `d`, so it goes last
+                ("b/f".to_string(), Change::Added),
+                ("b/d".to_string(), Change::Removed),
+                ("e/[2]".to_string(), Change::Added),
+            ]
+        );
+        assert_eq!(diff.differences.len(), 4);
+    }
+
+    #[test]
+    fn test_changed_type_and_whole_subtrees() {
+        let diff = json_diff(r#"{"a": 1, "b": {"c": 1}}"#, r#"{"a": [1]}"#);
+        assert_eq!(
+            changes(&diff),
+            [
+                ("a".to_string(), Change::Type("attribute".into())),
+                ("a/[0]".to_string(), Change::Added),
+                ("b".to_string(), Change::Removed),
+                ("b/c".to_string(), Change::Removed),
+            ]
+        );
+        // The removed `c` is part of the removed `b`
+        let labels: Vec<String> = diff
+            .differences
+            .iter()
+            .map(|&id| diff.tree.get_node(id).unwrap().label.to_string())
+            .collect();
+        assert_eq!(labels, ["a", "[0]", "b"]);
+    }
+
+    #[test]
+    fn test_removed_nodes_keep_their_place() {
+        let array = json_diff(r#"[1, 2, 3]"#, r#"[1]"#);
+        let labels: Vec<String> = array
+            .tree
+            .get_children(array.tree.root_id())
+            .into_iter()
+            .map(|id| array.tree.get_node(id).unwrap().label.to_string())
+            .collect();
+        assert_eq!(labels, ["[0]", "[1]", "[2]"]);
+
+        let old = XmlParser.parse("<r><a/><b/><c>1</c></r>").unwrap();
+        let new = XmlParser.parse("<r><a/><c>2</c><a/></r>").unwrap();
+        let xml = diff(&old, &new);
+        assert_eq!(
+            changes(&xml),
+            [
+                ("r/b".to_string(), Change::Removed),
+                ("r/c/text".to_string(), Change::Value("1".into())),
+                ("r/a".to_string(), Change::Added),
+            ]
+        );
+    }
+}
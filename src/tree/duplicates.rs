@@ -0,0 +1,252 @@
+//! Groups of identical subtrees, found by hashing the structure of every
+//! subtree under a node.
+//!
+//! A subtree's hash combines the type and attributes of its top node with the
+//! labels and hashes of its children, but not the top node's own label, so
+//! that items `[0]` and `[7]` of an array or two objects with the same
+//! contents under different keys are found alike. Equal hashes are taken as
+//! equal subtrees.
+//!
+//! Streaming trees hash what their index holds, the labels and shape of the
+//! subtrees, and never read values from the file.
+//!
+//! The walk is iterative, so deep trees cannot overflow the stack, and stops
+//! after a node limit.
+
+use crate::tree::TreeVariant;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Smaller subtrees, such as single values, are too common to report
+pub const MIN_DUPLICATE_NODES: usize = 3;
+
+/// Subtrees that hash alike
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// Top nodes of the subtrees in document order
+    pub nodes: Vec<usize>,
+    /// Nodes in each of the subtrees
+    pub size: usize,
+}
+
+impl DuplicateGroup {
+    /// Nodes in all copies but one
+    pub fn redundant_nodes(&self) -> usize {
+        self.size * (self.nodes.len() - 1)
+    }
+}
+
+/// What a search for duplicate subtrees found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Duplicates {
+    /// Groups with the most redundant nodes first
+    pub groups: Vec<DuplicateGroup>,
+    /// Whether values were compared, which streaming trees don't
+    pub values_compared: bool,
+    /// Whether the walk stopped at its node limit, leaving out the subtrees
+    /// it had not finished
+    pub limit_reached: bool,
+}
+
+/// A finished subtree
+struct Hashed {
+    /// Position of its top node in document order
+    position: usize,
+    hash: u64,
+    size: usize,
+}
+
+/// Groups the subtrees under `root` that hash alike, looking at no more than
+/// `limit` nodes.
+///
+/// Subtrees of fewer than [`MIN_DUPLICATE_NODES`] nodes are left out, and so
+/// are groups whose subtrees all lie in copies of one larger duplicate.
+///
+/// # Examples
+///
+/// ```
+/// use xtv::parser::{Parser, json::JsonParser};
+/// use xtv::tree::TreeVariant;
+/// use xtv::tree::duplicates::find_duplicates;
+///
+/// let json = r#"{"a": {"x": 1, "y": 2}, "b": {"x": 1, "y": 2}, "c": {"x": 3}}"#;
+/// let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+/// let duplicates = find_duplicates(&tree, tree.root_id(), 1000);
+///
+/// assert_eq!(duplicates.groups.len(), 1);
+/// let labels: Vec<_> = duplicates.groups[0]
+///     .nodes
+///     .iter()
+///     .map(|&id| tree.get_label(id).unwrap())
+///     .collect();
+/// assert_eq!(labels, ["a", "b"]);
+/// assert_eq!(duplicates.groups[0].size, 3);
+/// ```
+pub fn find_duplicates(tree: &TreeVariant, root: usize, limit: usize) -> Duplicates {
+    let values_compared = matches!(tree, TreeVariant::InMemory(_));
+    let mut hashed: HashMap<usize, Hashed> = HashMap::new();
+    let mut limit_reached = false;
+    let mut visited = 0;
+
+    // Post-order: a node is entered with no position and left with the
+    // position it was entered at, once its children are done
+    let mut stack: Vec<(usize, Option<usize>)> = vec![(root, None)];
+    while let Some((node_id, entered)) = stack.pop() {
+        let Some(position) = entered else {
+            if visited == limit {
+                limit_reached = true;
+                break;
+            }
+            stack.push((node_id, Some(visited)));
+            visited += 1;
+            let children = tree.get_children(node_id);
+            stack.extend(children.into_iter().rev().map(|child| (child, None)));
+            continue;
+        };
+
+        let mut hasher = DefaultHasher::new();
+        if let TreeVariant::InMemory(tree) = tree
+            && let Some(node) = tree.get_node(node_id)
+        {
+            node.node_type.hash(&mut hasher);
+            for attribute in &node.attributes {
+                attribute.key.hash(&mut hasher);
+                attribute.value.hash(&mut hasher);
+            }
+        }
+        let children = tree.get_children(node_id);
+        children.len().hash(&mut hasher);
+        let mut size = 1;
+        for child in children {
+            let Some(done) = hashed.get(&child) else {
+                continue;
+            };
+            tree.get_label(child).hash(&mut hasher);
+            done.hash.hash(&mut hasher);
+            size += done.size;
+        }
+        hashed.insert(
+            node_id,
+            Hashed {
+                position,
+                hash: hasher.finish(),
+                size,
+            },
+        );
+    }
+
+    let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (&node_id, done) in &hashed {
+        if done.size >= MIN_DUPLICATE_NODES {
+            by_hash.entry(done.hash).or_default().push(node_id);
+        }
+    }
+    let duplicated = |hash: u64| by_hash.get(&hash).is_some_and(|nodes| nodes.len() > 1);
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .iter()
+        .filter(|(_, nodes)| nodes.len() > 1)
+        .filter(|(_, nodes)| {
+            // Copies inside copies of a larger subtree go without saying
+            let parents: Option<Vec<u64>> = nodes
+                .iter()
+                .map(|&id| Some(hashed.get(&tree.get_parent(id)?)?.hash))
+                .collect();
+            !parents.is_some_and(|hashes| {
+                hashes.windows(2).all(|pair| pair[0] == pair[1]) && duplicated(hashes[0])
+            })
+        })
+        .map(|(_, nodes)| {
+            let mut nodes = nodes.clone();
+            nodes.sort_by_key(|id| hashed[id].position);
+            DuplicateGroup {
+                size: hashed[&nodes[0]].size,
+                nodes,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|group| {
+        (
+            std::cmp::Reverse(group.redundant_nodes()),
+            hashed[&group.nodes[0]].position,
+        )
+    });
+
+    Duplicates {
+        groups,
+        values_compared,
+        limit_reached,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ldif::build_ldif_index;
+    use crate::parser::{Parser, json::JsonParser};
+    use std::io::Write;
+
+    fn labels(tree: &TreeVariant, group: &DuplicateGroup) -> Vec<String> {
+        group
+            .nodes
+            .iter()
+            .map(|&id| tree.get_label(id).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_groups_identical_subtrees() {
+        let json = r#"{
+            "a": {"x": 1, "y": [1, 2]},
+            "b": {"x": 1, "y": [1, 2]},
+            "c": {"x": 2, "y": [1, 2]},
+            "d": [{"k": "v", "l": "w"}, {"k": "v", "l": "w"}],
+            "e": [1, 2, 3],
+            "f": [3, 2, 1]
+        }"#;
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        let duplicates = find_duplicates(&tree, tree.root_id(), 1000);
+        assert!(duplicates.values_compared);
+        assert!(!duplicates.limit_reached);
+
+        // Most redundant nodes first; the `x` leaves are too small, and
+        // the `y` arrays are reported although two of them lie in `a` and `b`
+        let groups: Vec<(Vec<String>, usize)> = duplicates
+            .groups
+            .iter()
+            .map(|group| (labels(&tree, group), group.size))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (vec!["y".into(), "y".into(), "y".into()], 3),
+                (vec!["a".into(), "b".into()], 5),
+                (vec!["[0]".into(), "[1]".into()], 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stops_at_the_node_limit() {
+        let json = r#"{"a": {"x": 1, "y": 2}, "b": {"x": 1, "y": 2}}"#;
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        let duplicates = find_duplicates(&tree, tree.root_id(), 4);
+        assert!(duplicates.limit_reached);
+        assert!(duplicates.groups.is_empty());
+    }
+
+    #[test]
+    fn test_streaming_trees_compare_labels_only() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "dn: dc=example\ndc: example\n").unwrap();
+        for name in ["alice", "bob"] {
+            writeln!(file, "dn: cn={name},dc=example\ncn: {name}\nsn: {name}\n").unwrap();
+        }
+        let tree = TreeVariant::Streaming(Box::new(build_ldif_index(file.path()).unwrap()));
+        let duplicates = find_duplicates(&tree, tree.root_id(), 1000);
+        assert!(!duplicates.values_compared);
+        assert_eq!(duplicates.groups.len(), 1);
+        assert_eq!(labels(&tree, &duplicates.groups[0]), ["cn=alice", "cn=bob"]);
+    }
+}
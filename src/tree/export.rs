@@ -0,0 +1,538 @@
+//! Converting subtrees back into JSON values, for copying and printing.
+//!
+//! Objects, mappings and tables become JSON objects keyed by their children's
+//! labels, arrays and sequences become JSON arrays, and leaves become numbers,
+//! booleans, null or strings as the parser read them (see [`ValueKind`]), so
+//! a string `"42"` stays a string. Leaves of parsers that don't record kinds
+//! are typed by how their value reads. Datetimes become strings, and floats
+//! JSON can't hold, such as `nan`, as well.
+//!
+//! Markup and directory trees are mapped the way common converters do:
+//! an XML or HTML element becomes an object with its attributes as `@name`
+//! keys, its text as `#text` and its child elements by name (repeated names
+//! are collected into an array); an element holding only text becomes that
+//! text. An LDIF entry becomes an object of its attributes, with
+//! multi-valued attributes as arrays, followed by its child entries.
+
+use crate::convert;
+use crate::error::{Result, XtvError};
+use crate::query::path::is_index_label;
+use crate::tree::{TreeNode, TreeVariant, ValueKind};
+use serde_json::{Map, Number, Value};
+use std::path::Path;
+
+/// Nodes converted at most from a streaming tree, which loads each from disk
+pub const MAX_EXPORT_NODES: usize = 100_000;
+
+/// Nesting converted at most, which keeps the conversion and serializing the
+/// result from overflowing the stack
+pub const MAX_EXPORT_DEPTH: usize = 1_000;
+
+/// Stands in for the parts of a subtree that were left out
+const OMITTED: &str = "…";
+
+/// A converted subtree
+#[derive(Debug)]
+pub struct JsonExport {
+    pub value: Value,
+    /// Whether parts were replaced by `"…"` because the subtree was too large
+    /// or too deep
+    pub truncated: bool,
+}
+
+/// Convert the subtree under `node_id`.
+///
+/// Streaming trees convert at most [`MAX_EXPORT_NODES`] nodes.
+pub fn to_json(tree: &TreeVariant, node_id: usize) -> Option<JsonExport> {
+    let max_nodes = match tree {
+        TreeVariant::InMemory(_) => usize::MAX,
+        TreeVariant::Streaming(_) => MAX_EXPORT_NODES,
+    };
+    Exporter::new(tree, max_nodes).export(node_id)
+}
+
+/// Formats a subtree can be written as a document in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Json,
+    Yaml,
+}
+
+impl DocumentFormat {
+    /// The format called `name`, as in `--output-format yaml`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// The format matching a file's extension, JSON unless it is YAML
+    pub fn for_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|ext| Self::from_name(&ext.to_string_lossy()))
+            .unwrap_or(Self::Json)
+    }
+}
+
+/// Serialize the whole subtree under `node_id` as a document, ending in a
+/// newline.
+///
+/// Unlike [`to_json`] nothing is left out: a subtree that would need
+/// shortening is an error, since the document would not hold the data.
+pub fn to_document(tree: &TreeVariant, node_id: usize, format: DocumentFormat) -> Result<String> {
+    let export = Exporter::new(tree, usize::MAX)
+        .export(node_id)
+        .ok_or(XtvError::InvalidNodeId(node_id))?;
+    if export.truncated {
+        return Err(XtvError::Serialize(format!(
+            "nested deeper than {} levels",
+            MAX_EXPORT_DEPTH
+        )));
+    }
+    match format {
+        DocumentFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&export.value)?;
+            json.push('\n');
+            Ok(json)
+        }
+        DocumentFormat::Yaml => convert::to_yaml(&export.value),
+    }
+}
+
+struct Exporter<'a> {
+    tree: &'a TreeVariant,
+    /// Nodes that may still be converted
+    budget: usize,
+    truncated: bool,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(tree: &'a TreeVariant, max_nodes: usize) -> Self {
+        Self {
+            tree,
+            budget: max_nodes,
+            truncated: false,
+        }
+    }
+
+    fn export(mut self, node_id: usize) -> Option<JsonExport> {
+        let node = self.tree.get_node_blocking(node_id)?;
+        // An attribute under `@attributes` is markup text, anything else typed
+        let typed = !self
+            .tree
+            .get_parent(node_id)
+            .and_then(|parent| self.tree.get_node_blocking(parent))
+            .is_some_and(|parent| parent.is_virtual_attributes());
+        let value = self.value(node_id, &node, typed, 0);
+        Some(JsonExport {
+            value,
+            truncated: self.truncated,
+        })
+    }
+
+    /// The loadable children of a node, without its `@attributes` node
+    fn children(&self, node_id: usize) -> Vec<(usize, TreeNode)> {
+        self.tree
+            .get_children(node_id)
+            .into_iter()
+            .filter_map(|id| Some((id, self.tree.get_node_blocking(id)?)))
+            .filter(|(_, node)| !node.is_virtual_attributes())
+            .collect()
+    }
+
+    /// The attribute nodes under a node's `@attributes` node
+    fn attributes(&self, node_id: usize) -> Vec<TreeNode> {
+        self.tree
+            .get_children(node_id)
+            .into_iter()
+            .filter_map(|id| Some((id, self.tree.get_node_blocking(id)?)))
+            .filter(|(_, node)| node.is_virtual_attributes())
+            .flat_map(|(id, _)| self.tree.get_children(id))
+            .filter_map(|id| self.tree.get_node_blocking(id))
+            .collect()
+    }
+
+    fn value(&mut self, node_id: usize, node: &TreeNode, typed: bool, depth: usize) -> Value {
+        if self.budget == 0 || depth > MAX_EXPORT_DEPTH {
+            self.truncated = true;
+            return Value::String(OMITTED.to_string());
+        }
+        self.budget -= 1;
+
+        if node.is_attribute() {
+            let text = content(node, "value").unwrap_or_default();
+            return if typed {
+                typed_scalar(text, node.value_kind)
+            } else {
+                Value::String(text.to_string())
+            };
+        }
+        if matches!(node.node_type.as_str(), "text" | "comment" | "doctype") {
+            return Value::String(content(node, "content").unwrap_or_default().to_string());
+        }
+        match node.node_type.as_str() {
+            "element" => self.element(node_id, depth),
+            "entry" => self.entry(node_id, depth),
+            "tagged" => {
+                let tag = content(node, "tag").unwrap_or_default().to_string();
+                let inner = self.object(node_id, depth);
+                let inner = match inner {
+                    Value::Object(mut map) => map.remove("value").unwrap_or(Value::Null),
+                    other => other,
+                };
+                Value::Object(Map::from_iter([(tag, inner)]))
+            }
+            _ if node.is_array() => self.array(node_id, depth),
+            // Parsers type the root of a top-level array as one
+            _ if node_id == self.tree.root_id() || node.is_object() || node.has_children() => {
+                self.object(node_id, depth)
+            }
+            _ => match content(node, "value") {
+                Some(text) => scalar(text),
+                None => Value::String(node.label.to_string()),
+            },
+        }
+    }
+
+    fn object(&mut self, node_id: usize, depth: usize) -> Value {
+        let mut map = Map::new();
+        for (child_id, child) in self.children(node_id) {
+            let value = self.value(child_id, &child, true, depth + 1);
+            map.insert(child.label.into(), value);
+        }
+        Value::Object(map)
+    }
+
+    fn array(&mut self, node_id: usize, depth: usize) -> Value {
+        let items = self
+            .children(node_id)
+            .into_iter()
+            .map(|(child_id, child)| self.value(child_id, &child, true, depth + 1))
+            .collect();
+        Value::Array(items)
+    }
+
+    fn element(&mut self, node_id: usize, depth: usize) -> Value {
+        let mut map = Map::new();
+        for attribute in self.attributes(node_id) {
+            let value = content(&attribute, "value").unwrap_or_default().to_string();
+            map.insert(format!("@{}", attribute.label), Value::String(value));
+        }
+
+        let mut texts = Vec::new();
+        let mut elements = Map::new();
+        for (child_id, child) in self.children(node_id) {
+            match child.node_type.as_str() {
+                "text" => texts.push(content(&child, "content").unwrap_or_default().to_string()),
+                "comment" | "doctype" => {}
+                _ => {
+                    let value = self.value(child_id, &child, false, depth + 1);
+                    insert_repeated(&mut elements, child.label.into(), value);
+                }
+            }
+        }
+
+        let text = texts.join(" ");
+        if map.is_empty() && elements.is_empty() {
+            return Value::String(text);
+        }
+        if !text.is_empty() {
+            map.insert("#text".to_string(), Value::String(text));
+        }
+        map.extend(elements);
+        Value::Object(map)
+    }
+
+    fn entry(&mut self, node_id: usize, depth: usize) -> Value {
+        let mut map = Map::new();
+        for attribute in self.attributes(node_id) {
+            let value = Value::String(content(&attribute, "value").unwrap_or_default().to_string());
+            // Multi-valued attributes are listed as `mail [0]`, `mail [1]`, …
+            match attribute.label.rsplit_once(' ') {
+                Some((key, index)) if is_index_label(index) => {
+                    insert_repeated(&mut map, key.to_string(), value)
+                }
+                _ => {
+                    map.insert(attribute.label.into(), value);
+                }
+            }
+        }
+        for (child_id, child) in self.children(node_id) {
+            let value = self.value(child_id, &child, false, depth + 1);
+            map.insert(child.label.into(), value);
+        }
+        Value::Object(map)
+    }
+}
+
+/// Insert a value, turning the entry into an array when the key repeats
+fn insert_repeated(map: &mut Map<String, Value>, key: String, value: Value) {
+    match map.get_mut(&key) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None => {
+            map.insert(key, value);
+        }
+    }
+}
+
+fn content<'n>(node: &'n TreeNode, key: &str) -> Option<&'n str> {
+    node.attributes
+        .iter()
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.as_str())
+}
+
+/// A leaf value as a number, boolean or null if it reads as one, else a string
+fn scalar(text: &str) -> Value {
+    if text.trim() == text
+        && let Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) =
+            serde_json::from_str(text)
+    {
+        return value;
+    }
+    Value::String(text.to_string())
+}
+
+/// A leaf value as the kind it was read as, or typed by how it reads if the
+/// parser recorded no kind
+fn typed_scalar(text: &str, kind: Option<ValueKind>) -> Value {
+    let number = match kind {
+        None => return scalar(text),
+        Some(ValueKind::String | ValueKind::Datetime) => None,
+        Some(ValueKind::Integer) => serde_json::from_str(text).ok(),
+        // The TOML parser keeps the float 1.0 as `1`
+        Some(ValueKind::Float) => text.parse().ok().and_then(Number::from_f64),
+        Some(ValueKind::Boolean) => {
+            return match text {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => Value::String(text.to_string()),
+            };
+        }
+        Some(ValueKind::Null) => return Value::Null,
+    };
+    number.map_or_else(|| Value::String(text.to_string()), Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        Parser, html::HtmlParser, json::JsonParser, jsonlines::JsonLinesParser, ldif::LdifParser,
+        toml::TomlParser, xml::XmlParser, yaml::YamlParser,
+    };
+    use serde_json::json;
+
+    const SAMPLE: &str = r#"{
+        "name": "sample",
+        "version": 2.5,
+        "users": [
+            {"id": 1, "email": "a@example.com", "admin": true, "manager": null},
+            {"id": 2, "email": "b@example.com", "tags": ["x", "y"], "address": {"city": "Berlin"}}
+        ]
+    }"#;
+
+    fn export(tree: &TreeVariant, node_id: usize) -> Value {
+        let export = to_json(tree, node_id).unwrap();
+        assert!(!export.truncated);
+        export.value
+    }
+
+    /// The child of `node_id` labelled `label`
+    fn child(tree: &TreeVariant, node_id: usize, label: &str) -> usize {
+        tree.get_children(node_id)
+            .into_iter()
+            .find(|&id| tree.get_label(id).as_deref() == Some(label))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_json_subtrees_match_the_source() {
+        let tree = TreeVariant::from(JsonParser.parse(SAMPLE).unwrap());
+        let original: Value = serde_json::from_str(SAMPLE).unwrap();
+        let root = tree.root_id();
+        assert_eq!(export(&tree, root), original);
+
+        let users = child(&tree, root, "users");
+        assert_eq!(export(&tree, users), original["users"]);
+        let second = child(&tree, users, "[1]");
+        assert_eq!(export(&tree, second), original["users"][1]);
+        let admin = child(&tree, child(&tree, users, "[0]"), "admin");
+        assert_eq!(export(&tree, admin), json!(true));
+
+        let tree = TreeVariant::from(JsonParser.parse("[1, [\"a\"]]").unwrap());
+        assert_eq!(export(&tree, tree.root_id()), json!([1, ["a"]]));
+    }
+
+    #[test]
+    fn test_roots_keep_their_kind() {
+        for json in ["[]", "{}", r#"{"[0]": "a", "[1]": "b"}"#, r#"[["x"]]"#] {
+            let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+            let original: Value = serde_json::from_str(json).unwrap();
+            assert_eq!(export(&tree, tree.root_id()), original, "{}", json);
+        }
+
+        for yaml in ["[]\n", "'[0]': a\n"] {
+            let tree = TreeVariant::from(YamlParser.parse(yaml).unwrap());
+            let original: Value = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(export(&tree, tree.root_id()), original, "{}", yaml);
+        }
+    }
+
+    #[test]
+    fn test_yaml_mappings_and_sequences() {
+        let yaml = "server:\n  port: 8080\n  hosts: [a, b]\n";
+        let tree = TreeVariant::from(YamlParser.parse(yaml).unwrap());
+        assert_eq!(
+            export(&tree, tree.root_id()),
+            json!({"server": {"port": 8080, "hosts": ["a", "b"]}})
+        );
+    }
+
+    #[test]
+    fn test_xml_elements() {
+        let xml = r#"<shop open="yes"><item id="1">Dune</item><item id="2">Emma</item><note>hi</note></shop>"#;
+        let tree = TreeVariant::from(XmlParser.parse(xml).unwrap());
+        let shop = tree.get_children(tree.root_id())[0];
+        assert_eq!(
+            export(&tree, shop),
+            json!({
+                "@open": "yes",
+                "item": [{"@id": "1", "#text": "Dune"}, {"@id": "2", "#text": "Emma"}],
+                "note": "hi"
+            })
+        );
+
+        // Attribute values stay text
+        let attributes = tree.get_children(shop)[0];
+        let open = tree.get_children(attributes)[0];
+        assert_eq!(export(&tree, open), json!("yes"));
+        let first = child(&tree, shop, "item");
+        let id = tree.get_children(tree.get_children(first)[0])[0];
+        assert_eq!(export(&tree, id), json!("1"));
+    }
+
+    #[test]
+    fn test_ldif_entries() {
+        let ldif =
+            "dn: dc=example\ndc: example\n\ndn: cn=a,dc=example\ncn: a\nmail: a@x\nmail: b@x\n";
+        let tree = TreeVariant::from(LdifParser::default().parse(ldif).unwrap());
+        let base = tree.get_children(tree.root_id())[0];
+        assert_eq!(
+            export(&tree, base),
+            json!({
+                "dn": "dc=example",
+                "dc": "example",
+                "cn=a": {"dn": "cn=a,dc=example", "cn": "a", "mail": ["a@x", "b@x"]}
+            })
+        );
+    }
+
+    #[test]
+    fn test_large_subtrees_are_cut() {
+        let tree = TreeVariant::from(JsonParser.parse(SAMPLE).unwrap());
+        let export = Exporter::new(&tree, 3).export(tree.root_id()).unwrap();
+        assert!(export.truncated);
+        assert_eq!(
+            export.value,
+            json!({"name": "sample", "users": ["…", "…"], "version": "…"})
+        );
+    }
+
+    #[test]
+    fn test_documents_round_trip() {
+        let tree = TreeVariant::from(JsonParser.parse(SAMPLE).unwrap());
+        let root = tree.root_id();
+        let original = export(&tree, root);
+
+        let json = to_document(&tree, root, DocumentFormat::Json).unwrap();
+        let reparsed = TreeVariant::from(JsonParser.parse(&json).unwrap());
+        assert_eq!(export(&reparsed, reparsed.root_id()), original);
+
+        let yaml = to_document(&tree, root, DocumentFormat::Yaml).unwrap();
+        let reparsed = TreeVariant::from(YamlParser.parse(&yaml).unwrap());
+        assert_eq!(export(&reparsed, reparsed.root_id()), original);
+    }
+
+    #[test]
+    fn test_values_keep_their_kind() {
+        let json = r#"{"version": "1.0", "flag": "true", "none": "null", "n": "42",
+                       "real": [1.0, 2, true, null]}"#;
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        let original: Value = serde_json::from_str(json).unwrap();
+        let root = tree.root_id();
+        assert_eq!(export(&tree, root), original);
+        let document = to_document(&tree, root, DocumentFormat::Json).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&document).unwrap(), original);
+        let document = to_document(&tree, root, DocumentFormat::Yaml).unwrap();
+        assert_eq!(serde_yaml::from_str::<Value>(&document).unwrap(), original);
+
+        let yaml = "port: '8080'\nday: 2024-01-01\nratio: 1.0\nempty: ~\n";
+        let tree = TreeVariant::from(YamlParser.parse(yaml).unwrap());
+        assert_eq!(
+            export(&tree, tree.root_id()),
+            json!({"port": "8080", "day": "2024-01-01", "ratio": 1.0, "empty": null})
+        );
+
+        let toml = "port = \"8080\"\nratio = 1.0\nwhen = 1979-05-27\n";
+        let tree = TreeVariant::from(TomlParser.parse(toml).unwrap());
+        let value = export(&tree, tree.root_id());
+        assert_eq!(
+            value,
+            json!({"port": "8080", "ratio": 1.0, "when": "1979-05-27"})
+        );
+        assert!(value["ratio"].is_f64());
+    }
+
+    #[test]
+    fn test_every_format_converts_to_valid_documents() {
+        let inputs: [(&dyn Parser, &str); 7] = [
+            (&JsonParser, SAMPLE),
+            (&JsonLinesParser::default(), "{\"a\": 1}\n{\"a\": [2]}\n"),
+            (&YamlParser, "a: 1\nb: [x, {c: null}]\n"),
+            (&TomlParser, "a = 1\n[b]\nc = \"x\"\n[[d]]\ne = true\n"),
+            (&XmlParser, r#"<a id="1">text<b>x</b><b/></a>"#),
+            (
+                &HtmlParser::default(),
+                "<html><body><p class=\"x\">hi</p></body></html>",
+            ),
+            (
+                &LdifParser::default(),
+                "dn: dc=example\ndc: example\n\ndn: cn=a,dc=example\ncn: a\n",
+            ),
+        ];
+        for (parser, input) in inputs {
+            let tree = TreeVariant::from(parser.parse(input).unwrap());
+            let root = tree.root_id();
+            let json = to_document(&tree, root, DocumentFormat::Json).unwrap();
+            let value: Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(value, export(&tree, root));
+            let yaml = to_document(&tree, root, DocumentFormat::Yaml).unwrap();
+            let value: Value = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(value, export(&tree, root));
+        }
+    }
+
+    #[test]
+    fn test_document_format_from_names_and_paths() {
+        assert_eq!(
+            DocumentFormat::from_name("YAML"),
+            Some(DocumentFormat::Yaml)
+        );
+        assert_eq!(DocumentFormat::from_name("xml"), None);
+        assert_eq!(
+            DocumentFormat::for_path(Path::new("out.yml")),
+            DocumentFormat::Yaml
+        );
+        assert_eq!(
+            DocumentFormat::for_path(Path::new("out.txt")),
+            DocumentFormat::Json
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Subtrees grafted below nodes after parsing, such as the JSON held by a
+//! string value.
+//!
+//! A graft hangs one node, usually of type [`EMBEDDED_JSON_TYPE`], below a
+//! node, after the children it has. Taking the graft off again only detaches
+//! that node: IDs stay valid, and grafting the same node again brings the
+//! earlier graft back.
+
+use super::Tree;
+use crate::parser::{Parser, json::JsonParser};
+use base64::{Engine as _, engine::general_purpose};
+
+/// Node type of the node holding the parsed JSON of a value
+pub const EMBEDDED_JSON_TYPE: &str = "embedded-json";
+
+/// JSON found in a value
+#[derive(Debug)]
+pub struct Embedded {
+    /// The parsed JSON, with a root of type [`EMBEDDED_JSON_TYPE`] standing
+    /// for the top-level object or array
+    pub tree: Tree,
+    /// Whether the value held the JSON base64-encoded
+    pub base64: bool,
+}
+
+/// Parses the JSON object or array that `value` holds, as text or as
+/// base64-encoded text.
+///
+/// Only values starting with `{` or `[` are parsed, so most values are
+/// turned down without trying.
+///
+/// # Examples
+///
+/// ```
+/// use xtv::tree::graft::embedded_json;
+///
+/// let embedded = embedded_json(r#"{"a": 1, "b": [2, 3]}"#).unwrap();
+/// assert!(!embedded.base64);
+/// assert_eq!(embedded.tree.find_by_path(&["b", "[1]"]), Some(4));
+///
+/// // `{"a": 1}`
+/// assert!(embedded_json("eyJhIjogMX0=").unwrap().base64);
+/// assert!(embedded_json("{not json").is_none());
+/// assert!(embedded_json("42").is_none());
+/// ```
+pub fn embedded_json(value: &str) -> Option<Embedded> {
+    if let Some(tree) = parse_json(value) {
+        return Some(Embedded {
+            tree,
+            base64: false,
+        });
+    }
+    let decoded = general_purpose::STANDARD.decode(value.trim()).ok()?;
+    let mut tree = parse_json(std::str::from_utf8(&decoded).ok()?)?;
+    tree.nodes[tree.root_id].label = "json (base64)".into();
+    Some(Embedded { tree, base64: true })
+}
+
+/// `text` parsed as JSON, if it is an object or array
+fn parse_json(text: &str) -> Option<Tree> {
+    let text = text.trim();
+    if !text.starts_with('{') && !text.starts_with('[') {
+        return None;
+    }
+    let mut tree = JsonParser.parse(text).ok()?;
+    let root = &mut tree.nodes[tree.root_id];
+    root.label = "json".into();
+    root.node_type = EMBEDDED_JSON_TYPE.into();
+    Some(tree)
+}
+
+impl Tree {
+    /// Hangs the nodes of `part` below `parent_id`, its root as the last
+    /// child.
+    ///
+    /// If `parent_id` had a graft before, that graft comes back and `part`
+    /// is dropped. The grafted nodes lose their source positions, which
+    /// refer to the text they were parsed from rather than the file.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the grafted root, or `None` if `parent_id` doesn't exist
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let leaf = tree.add_child_node(0, TreeNode::new("config", "attribute"));
+    ///
+    /// let mut part = Tree::new(TreeNode::new("json", "embedded-json"));
+    /// part.add_child_node(0, TreeNode::new("a", "attribute"));
+    ///
+    /// let top = tree.graft(leaf, part).unwrap();
+    /// assert_eq!(tree.find_by_path(&["config", "json", "a"]), Some(top + 1));
+    ///
+    /// assert!(tree.ungraft(leaf));
+    /// assert!(tree.get_children(leaf).is_empty());
+    /// assert_eq!(tree.graft(leaf, Tree::new(TreeNode::new("x", "x"))), Some(top));
+    /// ```
+    pub fn graft(&mut self, parent_id: usize, part: Tree) -> Option<usize> {
+        self.get_node(parent_id)?;
+        if let Some(&top) = self.grafts.get(&parent_id) {
+            if self.grafted(parent_id).is_none() {
+                self.attach_graft(parent_id, top);
+            }
+            return Some(top);
+        }
+
+        let offset = self.nodes.len();
+        let top = part.root_id + offset;
+        self.nodes.extend(part.nodes.into_iter().map(|mut node| {
+            node.parent_id = node.parent_id.map(|parent| parent + offset);
+            for child in &mut node.children {
+                *child += offset;
+            }
+            node.source_span = None;
+            node
+        }));
+        self.attach_graft(parent_id, top);
+        self.grafts.insert(parent_id, top);
+        Some(top)
+    }
+
+    /// Detaches the graft of `parent_id`, keeping its nodes for
+    /// [`graft`](Tree::graft).
+    ///
+    /// # Returns
+    ///
+    /// `true` if the node had a graft attached
+    pub fn ungraft(&mut self, parent_id: usize) -> bool {
+        let Some(top) = self.grafted(parent_id) else {
+            return false;
+        };
+        self.nodes[top].parent_id = None;
+        self.nodes[parent_id].children.retain(|&child| child != top);
+        if let Some(original) = self.original_children.get_mut(&parent_id) {
+            original.retain(|&child| child != top);
+        }
+        true
+    }
+
+    /// Gets the root of the graft attached below `parent_id`, if any.
+    pub fn grafted(&self, parent_id: usize) -> Option<usize> {
+        let top = *self.grafts.get(&parent_id)?;
+        (self.nodes[top].parent_id == Some(parent_id)).then_some(top)
+    }
+
+    /// Makes `top` the last child of `parent_id`, in the parsed order as well
+    fn attach_graft(&mut self, parent_id: usize, top: usize) {
+        self.attach(parent_id, top);
+        if let Some(original) = self.original_children.get_mut(&parent_id) {
+            original.push(top);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Walking trees depth-first or breadth-first.
+//!
+//! A [`Walk`] keeps the nodes still to visit in a queue rather than on the
+//! call stack, so deeply nested documents can't overflow it. Children are
+//! visited in the order they are shown, including after
+//! [`reorder_children`](super::Tree::reorder_children).
+
+use super::{Tree, TreeLike, TreeNode, TreeVariant};
+use std::collections::VecDeque;
+
+/// Order in which a [`Walk`] visits nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Pre-order: a node, then everything below its first child, then its
+    /// second child, and so on, like the rows of a fully expanded view
+    DepthFirst,
+    /// Level by level: all nodes at depth 1 before any at depth 2
+    BreadthFirst,
+}
+
+/// The tree a walk asks for children
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Tree(&'a Tree),
+    Like(&'a dyn TreeLike),
+}
+
+impl std::fmt::Debug for Source<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Tree(tree) => f.debug_tuple("Tree").field(tree).finish(),
+            Source::Like(_) => f.write_str("Like(..)"),
+        }
+    }
+}
+
+/// Iterator over the IDs in a subtree with their depth below its top, which
+/// has depth 0.
+///
+/// Created by [`Tree::walk`], [`TreeVariant::walk`] and `walk` on
+/// `dyn TreeLike`. Only node IDs are handled, so walking a streaming tree
+/// reads its index but not its file.
+#[derive(Debug, Clone)]
+pub struct Walk<'a> {
+    source: Source<'a>,
+    order: Order,
+    pending: VecDeque<(usize, usize)>,
+}
+
+impl<'a> Walk<'a> {
+    fn new(source: Source<'a>, top: usize, order: Order) -> Self {
+        Self {
+            source,
+            order,
+            pending: VecDeque::from([(top, 0)]),
+        }
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let (id, depth) = match self.order {
+            Order::DepthFirst => self.pending.pop_back()?,
+            Order::BreadthFirst => self.pending.pop_front()?,
+        };
+        let below = |&child: &usize| (child, depth + 1);
+        match (self.source, self.order) {
+            (Source::Tree(tree), Order::DepthFirst) => {
+                let children = tree.get_node(id).map_or(&[][..], |n| &n.children);
+                self.pending.extend(children.iter().rev().map(below));
+            }
+            (Source::Tree(tree), Order::BreadthFirst) => {
+                let children = tree.get_node(id).map_or(&[][..], |n| &n.children);
+                self.pending.extend(children.iter().map(below));
+            }
+            (Source::Like(tree), Order::DepthFirst) => {
+                self.pending
+                    .extend(tree.get_children(id).iter().rev().map(below));
+            }
+            (Source::Like(tree), Order::BreadthFirst) => {
+                self.pending.extend(tree.get_children(id).iter().map(below));
+            }
+        }
+        Some((id, depth))
+    }
+}
+
+impl Tree {
+    /// Walks the IDs of `top` and everything below it in the given order,
+    /// paired with their depth below `top`.
+    pub fn walk(&self, top: usize, order: Order) -> Walk<'_> {
+        Walk::new(Source::Tree(self), top, order)
+    }
+
+    /// Iterates over all nodes depth-first, as `(id, node, depth)` with the
+    /// root at depth 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+    /// tree.add_child_node(a, TreeNode::new("b", "string"));
+    /// tree.add_child_node(0, TreeNode::new("c", "string"));
+    ///
+    /// let rows: Vec<_> = tree
+    ///     .iter_dfs()
+    ///     .map(|(_, node, depth)| (node.label.as_str(), depth))
+    ///     .collect();
+    /// assert_eq!(rows, [("root", 0), ("a", 1), ("b", 2), ("c", 1)]);
+    /// ```
+    pub fn iter_dfs(&self) -> impl Iterator<Item = (usize, &TreeNode, usize)> + '_ {
+        self.with_nodes(self.walk(self.root_id, Order::DepthFirst))
+    }
+
+    /// Iterates over all nodes breadth-first, as `(id, node, depth)` with the
+    /// root at depth 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+    /// tree.add_child_node(a, TreeNode::new("b", "string"));
+    /// tree.add_child_node(0, TreeNode::new("c", "string"));
+    ///
+    /// let labels: Vec<_> = tree.iter_bfs().map(|(_, node, _)| node.label.as_str()).collect();
+    /// assert_eq!(labels, ["root", "a", "c", "b"]);
+    /// ```
+    pub fn iter_bfs(&self) -> impl Iterator<Item = (usize, &TreeNode, usize)> + '_ {
+        self.with_nodes(self.walk(self.root_id, Order::BreadthFirst))
+    }
+
+    /// Iterates over the IDs of everything below `id` depth-first, not
+    /// including `id` itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+    /// let b = tree.add_child_node(a, TreeNode::new("b", "string"));
+    /// let c = tree.add_child_node(0, TreeNode::new("c", "string"));
+    ///
+    /// assert_eq!(tree.descendants(0).collect::<Vec<_>>(), [a, b, c]);
+    /// assert_eq!(tree.descendants(a).collect::<Vec<_>>(), [b]);
+    /// ```
+    pub fn descendants(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.walk(id, Order::DepthFirst).skip(1).map(|(id, _)| id)
+    }
+
+    /// Iterates over the IDs of the parent of `id`, its parent, and so on up
+    /// to the root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+    /// let b = tree.add_child_node(a, TreeNode::new("b", "string"));
+    ///
+    /// assert_eq!(tree.ancestors(b).collect::<Vec<_>>(), [a, 0]);
+    /// assert_eq!(tree.ancestors(0).count(), 0);
+    /// ```
+    pub fn ancestors(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.get_parent(id), |&id| self.get_parent(id))
+    }
+
+    fn with_nodes<'a>(
+        &'a self,
+        walk: Walk<'a>,
+    ) -> impl Iterator<Item = (usize, &'a TreeNode, usize)> + 'a {
+        walk.filter_map(|(id, depth)| self.get_node(id).map(|node| (id, node, depth)))
+    }
+}
+
+impl TreeVariant {
+    /// Walks the IDs of `top` and everything below it in the given order,
+    /// paired with their depth below `top`.
+    ///
+    /// Streaming trees are walked through their index without reading nodes.
+    pub fn walk(&self, top: usize, order: Order) -> Walk<'_> {
+        Walk::new(Source::Like(self), top, order)
+    }
+
+    /// Iterates over all nodes depth-first, as `(id, node, depth)` with the
+    /// root at depth 0.
+    ///
+    /// Nodes are owned copies as from [`get_node`](TreeVariant::get_node),
+    /// so streaming trees read every node; prefer [`walk`](TreeVariant::walk)
+    /// when the IDs are enough.
+    pub fn iter_dfs(&self) -> impl Iterator<Item = (usize, TreeNode, usize)> + '_ {
+        self.with_nodes(self.walk(self.root_id(), Order::DepthFirst))
+    }
+
+    /// Iterates over all nodes breadth-first, as `(id, node, depth)` with the
+    /// root at depth 0.
+    ///
+    /// Like [`iter_dfs`](TreeVariant::iter_dfs), this reads every node of a
+    /// streaming tree.
+    pub fn iter_bfs(&self) -> impl Iterator<Item = (usize, TreeNode, usize)> + '_ {
+        self.with_nodes(self.walk(self.root_id(), Order::BreadthFirst))
+    }
+
+    /// Iterates over the IDs of everything below `id` depth-first, not
+    /// including `id` itself.
+    pub fn descendants(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.walk(id, Order::DepthFirst).skip(1).map(|(id, _)| id)
+    }
+
+    /// Iterates over the IDs of the parent of `id`, its parent, and so on up
+    /// to the root.
+    pub fn ancestors(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.get_parent(id), |&id| self.get_parent(id))
+    }
+
+    fn with_nodes<'a>(
+        &'a self,
+        walk: Walk<'a>,
+    ) -> impl Iterator<Item = (usize, TreeNode, usize)> + 'a {
+        walk.filter_map(|(id, depth)| self.get_node(id).map(|node| (id, node, depth)))
+    }
+}
+
+impl dyn TreeLike + '_ {
+    /// Walks the IDs of `top` and everything below it in the given order,
+    /// paired with their depth below `top`.
+    pub fn walk(&self, top: usize, order: Order) -> Walk<'_> {
+        Walk::new(Source::Like(self), top, order)
+    }
+
+    /// Iterates over the IDs of the parent of `id`, its parent, and so on up
+    /// to the root.
+    pub fn ancestors(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.get_parent(id), |&id| self.get_parent(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// root → a → (b → d), c
+    fn tree() -> Tree {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+        let b = tree.add_child_node(a, TreeNode::new("b", "object"));
+        tree.add_child_node(a, TreeNode::new("c", "string"));
+        tree.add_child_node(b, TreeNode::new("d", "string"));
+        tree
+    }
+
+    fn labels<'a>(rows: impl Iterator<Item = (usize, &'a TreeNode, usize)>) -> Vec<String> {
+        rows.map(|(_, node, depth)| format!("{}{}", node.label, depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_walk_orders() {
+        let tree = tree();
+        assert_eq!(labels(tree.iter_dfs()), ["root0", "a1", "b2", "d3", "c2"]);
+        assert_eq!(labels(tree.iter_bfs()), ["root0", "a1", "b2", "c2", "d3"]);
+        // Depth is relative to where the walk starts
+        assert_eq!(
+            tree.walk(2, Order::DepthFirst).collect::<Vec<_>>(),
+            [(2, 0), (4, 1)]
+        );
+    }
+
+    #[test]
+    fn test_walk_follows_reordered_children() {
+        let mut tree = tree();
+        tree.reorder_children(1, vec![3, 2]);
+        assert_eq!(labels(tree.iter_dfs()), ["root0", "a1", "c2", "b2", "d3"]);
+    }
+
+    #[test]
+    fn test_descendants_and_ancestors() {
+        let tree = tree();
+        assert_eq!(tree.descendants(1).collect::<Vec<_>>(), [2, 4, 3]);
+        assert_eq!(tree.descendants(4).count(), 0);
+        assert_eq!(tree.ancestors(4).collect::<Vec<_>>(), [2, 1, 0]);
+
+        let variant = TreeVariant::from(tree);
+        assert_eq!(variant.descendants(1).collect::<Vec<_>>(), [2, 4, 3]);
+        assert_eq!(variant.ancestors(4).collect::<Vec<_>>(), [2, 1, 0]);
+        let rows: Vec<_> = variant
+            .iter_bfs()
+            .map(|(id, node, depth)| (id, node.label.to_string(), depth))
+            .collect();
+        assert_eq!(rows[4], (4, "d".to_string(), 3));
+    }
+
+    #[test]
+    fn test_deep_trees_do_not_overflow() {
+        let mut tree = Tree::new(TreeNode::new("root", "array"));
+        let mut parent = 0;
+        for _ in 0..100_000 {
+            parent = tree.add_child_node(parent, TreeNode::new("item", "array"));
+        }
+        assert_eq!(tree.iter_dfs().count(), 100_001);
+        assert_eq!(tree.ancestors(parent).count(), 100_000);
+    }
+}
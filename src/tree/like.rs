@@ -0,0 +1,165 @@
+//! What the tree view needs from a tree, independent of where its nodes live.
+//!
+//! [`Tree`], [`StreamingTree`] and [`TreeVariant`] all implement [`TreeLike`],
+//! and so can other backends or test doubles. The trait is object safe, so
+//! views take a `&dyn TreeLike` and walk it with `walk` and `ancestors` on
+//! `dyn TreeLike`.
+
+use super::{StreamingTree, Tree, TreeNode, TreeVariant};
+use crate::error::{Result, XtvError};
+use std::borrow::Cow;
+
+/// Read access to a tree of nodes addressed by ID.
+///
+/// # Examples
+///
+/// A tree whose nodes are made up on demand, here a chain of 100 nodes:
+///
+/// ```
+/// use std::borrow::Cow;
+/// use xtv::tree::{Order, TreeLike, TreeNode};
+///
+/// struct Chain;
+///
+/// impl TreeLike for Chain {
+///     fn root_id(&self) -> usize {
+///         0
+///     }
+///
+///     fn get_node(&self, id: usize) -> Option<Cow<'_, TreeNode>> {
+///         (id < 100).then(|| Cow::Owned(TreeNode::new(id.to_string(), "object")))
+///     }
+///
+///     fn get_children(&self, id: usize) -> Vec<usize> {
+///         if id < 99 { vec![id + 1] } else { Vec::new() }
+///     }
+///
+///     fn get_parent(&self, id: usize) -> Option<usize> {
+///         id.checked_sub(1)
+///     }
+///
+///     fn node_count(&self) -> usize {
+///         100
+///     }
+/// }
+///
+/// let tree: &dyn TreeLike = &Chain;
+/// assert_eq!(tree.walk(0, Order::DepthFirst).count(), 100);
+/// assert_eq!(tree.ancestors(99).last(), Some(0));
+/// ```
+pub trait TreeLike {
+    /// The ID of the root node
+    fn root_id(&self) -> usize;
+
+    /// The node with the given ID, borrowed if the tree holds it in memory
+    fn get_node(&self, id: usize) -> Option<Cow<'_, TreeNode>>;
+
+    /// The node with the given ID, or why it is unavailable.
+    ///
+    /// Trees that load nodes lazily override this to report load failures as
+    /// `XtvError::NodeLoad`; by default a missing node is an invalid ID.
+    fn try_get_node(&self, id: usize) -> Result<Cow<'_, TreeNode>> {
+        self.get_node(id).ok_or(XtvError::InvalidNodeId(id))
+    }
+
+    /// The label of a node, available even if the node fails to load where
+    /// the tree can tell it without loading the node
+    fn get_label(&self, id: usize) -> Option<String> {
+        self.get_node(id).map(|node| node.label.to_string())
+    }
+
+    /// The IDs of a node's children in display order, empty if it has none
+    /// or doesn't exist
+    fn get_children(&self, id: usize) -> Vec<usize>;
+
+    /// The ID of a node's parent, `None` for the root
+    fn get_parent(&self, id: usize) -> Option<usize>;
+
+    /// The number of nodes
+    fn node_count(&self) -> usize;
+}
+
+impl TreeLike for Tree {
+    fn root_id(&self) -> usize {
+        Tree::root_id(self)
+    }
+
+    fn get_node(&self, id: usize) -> Option<Cow<'_, TreeNode>> {
+        Tree::get_node(self, id).map(Cow::Borrowed)
+    }
+
+    fn get_children(&self, id: usize) -> Vec<usize> {
+        Tree::get_children(self, id)
+    }
+
+    fn get_parent(&self, id: usize) -> Option<usize> {
+        Tree::get_parent(self, id)
+    }
+
+    fn node_count(&self) -> usize {
+        Tree::node_count(self)
+    }
+}
+
+impl TreeLike for StreamingTree {
+    fn root_id(&self) -> usize {
+        StreamingTree::root_id(self)
+    }
+
+    fn get_node(&self, id: usize) -> Option<Cow<'_, TreeNode>> {
+        TreeLike::try_get_node(self, id).ok()
+    }
+
+    fn try_get_node(&self, id: usize) -> Result<Cow<'_, TreeNode>> {
+        StreamingTree::get_node(self, id).map(|node| Cow::Owned((*node).clone()))
+    }
+
+    fn get_label(&self, id: usize) -> Option<String> {
+        StreamingTree::node_label(self, id)
+    }
+
+    fn get_children(&self, id: usize) -> Vec<usize> {
+        StreamingTree::get_children(self, id)
+    }
+
+    fn get_parent(&self, id: usize) -> Option<usize> {
+        StreamingTree::get_parent(self, id)
+    }
+
+    fn node_count(&self) -> usize {
+        StreamingTree::node_count(self)
+    }
+}
+
+impl TreeLike for TreeVariant {
+    fn root_id(&self) -> usize {
+        TreeVariant::root_id(self)
+    }
+
+    fn get_node(&self, id: usize) -> Option<Cow<'_, TreeNode>> {
+        TreeLike::try_get_node(self, id).ok()
+    }
+
+    fn try_get_node(&self, id: usize) -> Result<Cow<'_, TreeNode>> {
+        match self {
+            TreeVariant::InMemory(tree) => TreeLike::try_get_node(tree.as_ref(), id),
+            TreeVariant::Streaming(tree) => TreeLike::try_get_node(tree.as_ref(), id),
+        }
+    }
+
+    fn get_label(&self, id: usize) -> Option<String> {
+        TreeVariant::get_label(self, id)
+    }
+
+    fn get_children(&self, id: usize) -> Vec<usize> {
+        TreeVariant::get_children(self, id)
+    }
+
+    fn get_parent(&self, id: usize) -> Option<usize> {
+        TreeVariant::get_parent(self, id)
+    }
+
+    fn node_count(&self) -> usize {
+        TreeVariant::node_count(self)
+    }
+}
@@ -1,7 +1,27 @@
+pub mod diff;
+pub mod duplicates;
+pub mod export;
+pub mod graft;
+pub mod iter;
+pub mod like;
+pub mod mutate;
 pub mod node;
+pub mod search;
+pub mod snapshot;
+pub mod sort;
+pub mod stats;
 pub mod streaming;
+pub mod table;
 
-pub use node::{Attribute, TreeNode};
+use crate::error::{Result, XtvError};
+pub use iter::{Order, Walk};
+pub use like::TreeLike;
+pub use node::{Attribute, TreeNode, ValueKind};
+pub use search::{NodeMatcher, SearchTask, SearchUpdate};
+pub use stats::{MemoryUsage, NodeStats, NumberStats, StatsTask, TreeStats};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
 pub use streaming::{NodeType, StreamingTree};
 
 /// Tree structure that stores nodes in a Vec for efficient O(1) access by ID.
@@ -31,10 +51,18 @@ pub use streaming::{NodeType, StreamingTree};
 ///
 /// assert_eq!(tree.node_count(), 2);
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tree {
     nodes: Vec<TreeNode>,
     root_id: usize,
+    /// Name of the format the tree was parsed from, see [`Tree::with_format`]
+    format: Option<&'static str>,
+    /// Children of reordered nodes in their parsed order, see
+    /// [`Tree::reorder_children`]
+    original_children: HashMap<usize, Vec<usize>>,
+    /// Root of the graft of every node that has or had one, see
+    /// [`Tree::graft`]
+    grafts: HashMap<usize, usize>,
 }
 
 impl Tree {
@@ -59,9 +87,67 @@ impl Tree {
         Self {
             nodes: vec![root],
             root_id: 0,
+            format: None,
+            original_children: HashMap::new(),
+            grafts: HashMap::new(),
+        }
+    }
+
+    /// Creates a tree with the given root node and room for `capacity` nodes
+    /// in all, so that adding up to that many doesn't grow the node vector.
+    ///
+    /// Parsers pass an estimate of the number of nodes they will add; a
+    /// wrong estimate only costs memory or a later reallocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::with_capacity(TreeNode::new("root", "array"), 3);
+    /// tree.add_child_node(0, TreeNode::new("[0]", "attribute"));
+    /// assert_eq!(tree.node_count(), 2);
+    /// ```
+    pub fn with_capacity(root: TreeNode, capacity: usize) -> Self {
+        let mut nodes = Vec::with_capacity(capacity.max(1));
+        nodes.push(root);
+        Self {
+            nodes,
+            root_id: 0,
+            format: None,
+            original_children: HashMap::new(),
+            grafts: HashMap::new(),
+        }
+    }
+
+    /// Reserves room for at least `additional` more nodes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+    }
+
+    /// Gives back the room for nodes and children that building the tree
+    /// reserved but didn't use.
+    pub fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+        for node in &mut self.nodes {
+            node.children.shrink_to_fit();
         }
     }
 
+    /// Records the format the tree was parsed from.
+    ///
+    /// Parsers set this to their [`format_name`](crate::parser::Parser::format_name),
+    /// so that subtrees can be written back in the same format.
+    pub fn with_format(mut self, format: &'static str) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Gets the name of the format the tree was parsed from, if known.
+    pub fn format(&self) -> Option<&'static str> {
+        self.format
+    }
+
     /// Adds a node to the tree and returns its ID.
     ///
     /// The node ID is simply the index in the internal vector, so this operation
@@ -110,6 +196,62 @@ impl Tree {
         node_id
     }
 
+    /// Moves the nodes of `other` but its root to the end of this tree.
+    ///
+    /// Returns the new IDs of the root's children, whose subtrees keep their
+    /// shape but are attached to nothing until passed to [`Tree::attach`].
+    /// Parsers that parse parts of a file on several threads stitch the
+    /// parts together this way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "array"));
+    /// tree.add_child_node(0, TreeNode::new("[0]", "number"));
+    ///
+    /// let mut part = Tree::new(TreeNode::new("part", "array"));
+    /// let object = part.add_child_node(0, TreeNode::new("[1]", "object"));
+    /// part.add_child_node(object, TreeNode::new("a", "number"));
+    ///
+    /// for top in tree.append_subtrees(part) {
+    ///     tree.attach(0, top);
+    /// }
+    /// assert_eq!(tree.find_by_path(&["[1]", "a"]), Some(3));
+    /// assert_eq!(tree.get_parent(3), Some(2));
+    /// ```
+    pub fn append_subtrees(&mut self, other: Tree) -> Vec<usize> {
+        // Node 1 of `other` becomes the first new node
+        let offset = self.nodes.len() - 1;
+        let mut nodes = other.nodes.into_iter();
+        let tops = nodes.next().map_or_else(Vec::new, |root| {
+            root.children.into_iter().map(|id| id + offset).collect()
+        });
+        self.nodes.extend(nodes.map(|mut node| {
+            node.parent_id = match node.parent_id {
+                Some(0) | None => None,
+                Some(parent) => Some(parent + offset),
+            };
+            for child in &mut node.children {
+                *child += offset;
+            }
+            node
+        }));
+        tops
+    }
+
+    /// Makes the node `child_id`, which must have no parent, the last child of
+    /// `parent_id`.
+    pub fn attach(&mut self, parent_id: usize, child_id: usize) {
+        if let Some(child) = self.get_node_mut(child_id) {
+            child.parent_id = Some(parent_id);
+        }
+        if let Some(parent) = self.get_node_mut(parent_id) {
+            parent.children.push(child_id);
+        }
+    }
+
     /// Gets a reference to a node by ID.
     ///
     /// # Arguments
@@ -146,6 +288,44 @@ impl Tree {
         self.nodes.get_mut(id)
     }
 
+    /// Replaces the value of an attribute node, such as a JSON string or
+    /// number, and the kind of value it is.
+    ///
+    /// # Returns
+    ///
+    /// The previous value and kind, or `None` if the node isn't an attribute
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode, ValueKind};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let mut port = TreeNode::new("port", "attribute").with_value_kind(ValueKind::Integer);
+    /// port.add_attribute("value", "80");
+    /// let port = tree.add_child_node(0, port);
+    ///
+    /// let kind = Some(ValueKind::String);
+    /// assert_eq!(
+    ///     tree.set_value(port, "http".to_string(), kind),
+    ///     Some(("80".to_string(), Some(ValueKind::Integer)))
+    /// );
+    /// assert_eq!(tree.get_node(port).unwrap().attributes[0].value, "http");
+    /// assert_eq!(tree.get_node(port).unwrap().value_kind, kind);
+    /// assert_eq!(tree.set_value(0, "x".to_string(), None), None);
+    /// ```
+    pub fn set_value(
+        &mut self,
+        id: usize,
+        value: String,
+        kind: Option<ValueKind>,
+    ) -> Option<(String, Option<ValueKind>)> {
+        let node = self.nodes.get_mut(id).filter(|node| node.is_attribute())?;
+        let attr = node.attributes.first_mut()?;
+        let old = std::mem::replace(&mut attr.value, value);
+        Some((old, std::mem::replace(&mut node.value_kind, kind)))
+    }
+
     /// Gets the root node ID.
     ///
     /// # Returns
@@ -175,6 +355,42 @@ impl Tree {
             .unwrap_or_default()
     }
 
+    /// Replaces the children of a node with the same children in another
+    /// order, remembering the parsed order for
+    /// [`restore_children`](Tree::restore_children).
+    ///
+    /// Does nothing if `order` isn't a permutation of the current children.
+    pub fn reorder_children(&mut self, id: usize, order: Vec<usize>) {
+        let Some(node) = self.nodes.get_mut(id) else {
+            return;
+        };
+        if !is_permutation(&node.children, &order) {
+            return;
+        }
+        let original = std::mem::replace(&mut node.children, order);
+        self.original_children.entry(id).or_insert(original);
+    }
+
+    /// Puts the children of a node back into their parsed order.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the node had been reordered
+    pub fn restore_children(&mut self, id: usize) -> bool {
+        let Some(original) = self.original_children.remove(&id) else {
+            return false;
+        };
+        if let Some(node) = self.nodes.get_mut(id) {
+            node.children = original;
+        }
+        true
+    }
+
+    /// Gets the IDs of all nodes whose children were reordered.
+    pub fn reordered_nodes(&self) -> Vec<usize> {
+        self.original_children.keys().copied().collect()
+    }
+
     /// Gets the total number of nodes in the tree.
     ///
     /// # Returns
@@ -205,6 +421,91 @@ impl Tree {
     pub fn get_parent(&self, child_id: usize) -> Option<usize> {
         self.get_node(child_id).and_then(|node| node.parent_id)
     }
+
+    /// Finds a node by the labels on the way down to it from the root.
+    ///
+    /// Each segment selects the first child with that label, so array items
+    /// are found by labels like `[2]`. No segments select the root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "array"));
+    /// tree.add_child_node(a, TreeNode::new("[0]", "string"));
+    /// let second = tree.add_child_node(a, TreeNode::new("[1]", "string"));
+    ///
+    /// assert_eq!(tree.find_by_path(&["a", "[1]"]), Some(second));
+    /// assert_eq!(tree.find_by_path(&[]), Some(0));
+    /// assert_eq!(tree.find_by_path(&["b"]), None);
+    /// ```
+    pub fn find_by_path(&self, segments: &[&str]) -> Option<usize> {
+        let steps = segments
+            .iter()
+            .map(|segment| PathStep::Label(Cow::Borrowed(*segment)));
+        find_path(self.root_id, steps, |id, step| self.find_child(id, step))
+    }
+
+    /// Finds a node by a path such as `.a.b[2].c`, as written by
+    /// [`jq_path`](crate::query::path::jq_path).
+    ///
+    /// Segments are separated by dots and the leading dot is optional. As in
+    /// jq, `[2]` selects the child at that position and keys may be quoted,
+    /// as in `."e-mail"`. A backslash makes the next character of an unquoted
+    /// key part of the label, as in `.example\.com`. Ambiguous labels select
+    /// the first child with the label. Returns `None` for malformed paths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let host = tree.add_child_node(0, TreeNode::new("example.com", "object"));
+    /// let port = tree.add_child_node(host, TreeNode::new("port", "number"));
+    ///
+    /// assert_eq!(tree.find_by_path_str(r".example\.com.port"), Some(port));
+    /// assert_eq!(tree.find_by_path_str(r#"."example.com"[0]"#), Some(port));
+    /// assert_eq!(tree.find_by_path_str(".example.com"), None);
+    /// assert_eq!(tree.find_by_path_str("."), Some(0));
+    /// ```
+    pub fn find_by_path_str(&self, path: &str) -> Option<usize> {
+        let steps = parse_path(path)?;
+        find_path(self.root_id, steps, |id, step| self.find_child(id, step))
+    }
+
+    /// Finds a node by a JSON Pointer such as `/users/3/email` (RFC 6901).
+    ///
+    /// Reference tokens match labels, with `~1` standing for `/` and `~0`
+    /// for `~`, and an index such as `3` also selects the array item labeled
+    /// `[3]`. Returns `None` for malformed pointers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let users = tree.add_child_node(0, TreeNode::new("users", "array"));
+    /// let user = tree.add_child_node(users, TreeNode::new("[0]", "object"));
+    /// let mail = tree.add_child_node(user, TreeNode::new("e/mail", "string"));
+    ///
+    /// assert_eq!(tree.find_by_json_pointer("/users/0/e~1mail"), Some(mail));
+    /// assert_eq!(tree.find_by_json_pointer(""), Some(0));
+    /// assert_eq!(tree.find_by_json_pointer("/users/00"), None);
+    /// assert_eq!(tree.find_by_json_pointer("users"), None);
+    /// ```
+    pub fn find_by_json_pointer(&self, pointer: &str) -> Option<usize> {
+        let steps = parse_json_pointer(pointer)?;
+        find_path(self.root_id, steps, |id, step| self.find_child(id, step))
+    }
+
+    fn find_child(&self, id: usize, step: PathStep) -> Option<usize> {
+        let children = self.get_node(id).map_or(&[][..], |node| &node.children);
+        step.select(children, |child, label| self.nodes[child].label == label)
+    }
 }
 
 /// Enum representing either an in-memory tree or a streaming tree.
@@ -215,7 +516,8 @@ impl Tree {
 ///
 /// All nodes are loaded into memory as a `Tree`. This provides the fastest
 /// access but requires loading the entire file. Suitable for files up to
-/// ~100MB or when working with smaller datasets.
+/// ~100MB or when working with smaller datasets. The tree is shared so that
+/// searches can walk it on a worker thread.
 ///
 /// # Streaming Mode
 ///
@@ -231,9 +533,9 @@ impl Tree {
 #[derive(Debug)]
 pub enum TreeVariant {
     /// In-memory tree with all nodes loaded
-    InMemory(Tree),
+    InMemory(Arc<Tree>),
     /// Streaming tree with on-demand node loading
-    Streaming(StreamingTree),
+    Streaming(Box<StreamingTree>),
 }
 
 /// Macro to dispatch method calls to the appropriate tree variant.
@@ -267,6 +569,14 @@ macro_rules! dispatch {
     };
 }
 
+impl From<Tree> for TreeVariant {
+    fn from(mut tree: Tree) -> Self {
+        // Nothing is added to a tree once it is shown
+        tree.shrink_to_fit();
+        TreeVariant::InMemory(Arc::new(tree))
+    }
+}
+
 impl TreeVariant {
     /// Gets a node by ID, returning an owned copy.
     ///
@@ -287,20 +597,97 @@ impl TreeVariant {
     /// - **InMemory**: O(1) lookup + clone of the node
     /// - **Streaming**: O(1) cache lookup or disk I/O + clone
     ///   - Cache hit: Fast Arc clone then node clone
-    ///   - Cache miss: Blocking I/O to load from disk
+    ///   - Cache miss: Blocking I/O to load from disk, or a placeholder node
+    ///     when background loading is enabled
     ///
     /// # Notes
     ///
     /// Both variants require cloning to return an owned TreeNode:
     /// - InMemory: Clones from `&TreeNode`
     /// - Streaming: Clones from `Arc<TreeNode>` (cache access via Arc::clone is cheap)
+    ///
+    /// Use [`try_get_node`](TreeVariant::try_get_node) to tell why a node is
+    /// missing, and [`get_node_blocking`](TreeVariant::get_node_blocking) when
+    /// the node's actual value is required.
     pub fn get_node(&self, id: usize) -> Option<TreeNode> {
+        self.try_get_node(id).ok()
+    }
+
+    /// Gets a node by ID like [`get_node`](TreeVariant::get_node), reporting
+    /// why it is unavailable.
+    ///
+    /// In-memory trees only fail for unknown IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::NodeLoad` if a streaming node failed to load, and
+    /// `XtvError::InvalidNodeId` if the node doesn't exist.
+    pub fn try_get_node(&self, id: usize) -> Result<TreeNode> {
         match self {
-            TreeVariant::InMemory(tree) => tree.get_node(id).cloned(),
+            TreeVariant::InMemory(tree) => tree
+                .get_node(id)
+                .cloned()
+                .ok_or(XtvError::InvalidNodeId(id)),
             TreeVariant::Streaming(tree) => tree.get_node(id).map(|arc| (*arc).clone()),
         }
     }
 
+    /// Gets a node by ID, waiting for disk I/O instead of returning a placeholder.
+    ///
+    /// Identical to [`get_node`](TreeVariant::get_node) for in-memory trees.
+    /// For streaming trees with background loading this blocks until the node
+    /// is read, which suits operations like copying, decoding, and searching.
+    pub fn get_node_blocking(&self, id: usize) -> Option<TreeNode> {
+        match self {
+            TreeVariant::InMemory(tree) => tree.get_node(id).cloned(),
+            TreeVariant::Streaming(tree) => {
+                tree.get_node_blocking(id).ok().map(|arc| (*arc).clone())
+            }
+        }
+    }
+
+    /// Gets the label of a node without loading it.
+    ///
+    /// Streaming trees answer from their index alone, which makes this cheap
+    /// enough to call for every node.
+    pub fn get_label(&self, id: usize) -> Option<String> {
+        match self {
+            TreeVariant::InMemory(tree) => tree.get_node(id).map(|node| node.label.to_string()),
+            TreeVariant::Streaming(tree) => tree.node_label(id),
+        }
+    }
+
+    /// Gets the name of the format the tree was parsed from, if known.
+    pub fn format(&self) -> Option<&'static str> {
+        match self {
+            TreeVariant::InMemory(tree) => tree.format(),
+            TreeVariant::Streaming(tree) => Some(tree.format()),
+        }
+    }
+
+    /// Takes warnings and load errors queued by the tree since the last call.
+    ///
+    /// In-memory trees never queue messages.
+    pub fn take_messages(&self) -> Vec<String> {
+        match self {
+            TreeVariant::InMemory(_) => Vec::new(),
+            TreeVariant::Streaming(tree) => tree.take_messages(),
+        }
+    }
+
+    /// Collects nodes finished by a background loader.
+    ///
+    /// # Returns
+    ///
+    /// `true` if new data arrived and the display should be refreshed.
+    /// Always `false` for in-memory trees.
+    pub fn poll_loaded(&self) -> bool {
+        match self {
+            TreeVariant::InMemory(_) => false,
+            TreeVariant::Streaming(tree) => tree.poll_loaded(),
+        }
+    }
+
     /// Gets the root node ID.
     ///
     /// # Returns
@@ -332,6 +719,85 @@ impl TreeVariant {
         dispatch!(self, get_children, id)
     }
 
+    /// Shows the children of a node in another order, e.g. sorted.
+    ///
+    /// `order` must hold the same IDs as [`get_children`](TreeVariant::get_children);
+    /// otherwise nothing changes. The parsed order is kept for
+    /// [`restore_children`](TreeVariant::restore_children). In-memory trees
+    /// shared with a running search are copied first, so the search keeps
+    /// seeing the tree as it was.
+    pub fn reorder_children(&mut self, id: usize, order: Vec<usize>) {
+        match self {
+            TreeVariant::InMemory(tree) => Arc::make_mut(tree).reorder_children(id, order),
+            TreeVariant::Streaming(tree) => tree.reorder_children(id, order),
+        }
+    }
+
+    /// Puts the children of a node back into their parsed order.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the node had been reordered
+    pub fn restore_children(&mut self, id: usize) -> bool {
+        match self {
+            TreeVariant::InMemory(tree) => Arc::make_mut(tree).restore_children(id),
+            TreeVariant::Streaming(tree) => tree.restore_children(id),
+        }
+    }
+
+    /// Gets the IDs of all nodes whose children were reordered.
+    pub fn reordered_nodes(&self) -> Vec<usize> {
+        dispatch!(self, reordered_nodes)
+    }
+
+    /// Hangs the nodes of `part` below `parent_id`, see [`Tree::graft`].
+    ///
+    /// Streaming trees keep grafted nodes in memory next to their index.
+    /// In-memory trees shared with a running search are copied first.
+    pub fn graft(&mut self, parent_id: usize, part: Tree) -> Option<usize> {
+        match self {
+            TreeVariant::InMemory(tree) => Arc::make_mut(tree).graft(parent_id, part),
+            TreeVariant::Streaming(tree) => tree.graft(parent_id, part),
+        }
+    }
+
+    /// Detaches the graft of `parent_id`, see [`Tree::ungraft`].
+    ///
+    /// # Returns
+    ///
+    /// `true` if the node had a graft attached
+    pub fn ungraft(&mut self, parent_id: usize) -> bool {
+        match self {
+            TreeVariant::InMemory(tree) => Arc::make_mut(tree).ungraft(parent_id),
+            TreeVariant::Streaming(tree) => tree.ungraft(parent_id),
+        }
+    }
+
+    /// Gets the root of the graft attached below `parent_id`, if any.
+    pub fn grafted(&self, parent_id: usize) -> Option<usize> {
+        dispatch!(self, grafted, parent_id)
+    }
+
+    /// Replaces the value of an attribute node, see [`Tree::set_value`].
+    ///
+    /// Streaming trees read their values from the file and can't be changed.
+    /// In-memory trees shared with a running search are copied first.
+    ///
+    /// # Returns
+    ///
+    /// The previous value and kind, or `None` if the value can't be replaced
+    pub fn set_value(
+        &mut self,
+        id: usize,
+        value: String,
+        kind: Option<ValueKind>,
+    ) -> Option<(String, Option<ValueKind>)> {
+        match self {
+            TreeVariant::InMemory(tree) => Arc::make_mut(tree).set_value(id, value, kind),
+            TreeVariant::Streaming(_) => None,
+        }
+    }
+
     /// Gets the total number of nodes in the tree.
     ///
     /// # Returns
@@ -362,6 +828,224 @@ impl TreeVariant {
     pub fn get_parent(&self, child_id: usize) -> Option<usize> {
         dispatch!(self, get_parent, child_id)
     }
+
+    /// Starts searching the subtree under `root` on a worker thread.
+    ///
+    /// At most `limit` nodes are examined. Streaming trees are walked through
+    /// their index and only read the file if the matcher needs values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a streaming tree's file cannot be opened.
+    pub fn search(
+        &self,
+        root: usize,
+        limit: usize,
+        matcher: impl NodeMatcher,
+    ) -> std::io::Result<SearchTask> {
+        match self {
+            TreeVariant::InMemory(tree) => {
+                Ok(search::search_tree(Arc::clone(tree), root, limit, matcher))
+            }
+            TreeVariant::Streaming(tree) => tree.search(root, limit, matcher),
+        }
+    }
+
+    /// Starts gathering statistics about the subtree under `root` on a worker
+    /// thread.
+    ///
+    /// Streaming trees take everything but values from their index. Their
+    /// values are read from the file only if the subtree holds at most
+    /// `value_limit` of them; otherwise they are counted as unread.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a streaming tree's file cannot be opened.
+    pub fn stats(&self, root: usize, value_limit: usize) -> std::io::Result<StatsTask> {
+        match self {
+            TreeVariant::InMemory(tree) => Ok(stats::subtree_stats(Arc::clone(tree), root)),
+            TreeVariant::Streaming(tree) => tree.stats(root, value_limit),
+        }
+    }
+
+    /// Counts the nodes of the whole tree and estimates its memory, see
+    /// [`Tree::stats`].
+    ///
+    /// Streaming trees count from their index, without attribute bytes, and
+    /// split their memory into the index and the node cache.
+    pub fn tree_stats(&self) -> TreeStats {
+        match self {
+            TreeVariant::InMemory(tree) => tree.stats(),
+            TreeVariant::Streaming(tree) => tree.tree_stats(),
+        }
+    }
+
+    /// Approximate heap usage of the tree
+    pub fn memory_usage(&self) -> MemoryUsage {
+        dispatch!(self, memory_usage)
+    }
+
+    /// Finds a node by the labels on the way down to it from the root, see
+    /// [`Tree::find_by_path`].
+    ///
+    /// Streaming trees compare the labels in their index without reading
+    /// any nodes.
+    pub fn find_by_path(&self, segments: &[&str]) -> Option<usize> {
+        let steps = segments
+            .iter()
+            .map(|segment| PathStep::Label(Cow::Borrowed(*segment)));
+        find_path(self.root_id(), steps, |id, step| self.find_child(id, step))
+    }
+
+    /// Finds a node by a path such as `.a.b[2].c`, see
+    /// [`Tree::find_by_path_str`].
+    pub fn find_by_path_str(&self, path: &str) -> Option<usize> {
+        let steps = parse_path(path)?;
+        find_path(self.root_id(), steps, |id, step| self.find_child(id, step))
+    }
+
+    /// Finds a node by a JSON Pointer such as `/users/3/email`, see
+    /// [`Tree::find_by_json_pointer`].
+    pub fn find_by_json_pointer(&self, pointer: &str) -> Option<usize> {
+        let steps = parse_json_pointer(pointer)?;
+        find_path(self.root_id(), steps, |id, step| self.find_child(id, step))
+    }
+
+    fn find_child(&self, id: usize, step: PathStep) -> Option<usize> {
+        step.select(&self.get_children(id), |child, label| {
+            self.get_label(child).is_some_and(|l| l == label)
+        })
+    }
+}
+
+/// One segment of a node path
+#[derive(Debug, Clone, PartialEq)]
+enum PathStep<'a> {
+    /// The first child with this label
+    Label(Cow<'a, str>),
+    /// The child at this position
+    Index(usize),
+    /// A JSON Pointer reference token: the child with this label, or for an
+    /// array index the item at that position
+    Token(Cow<'a, str>),
+}
+
+impl<'a> PathStep<'a> {
+    /// The child this step selects among `children`, with `has_label`
+    /// telling whether a child has a label
+    fn select(&self, children: &[usize], has_label: impl Fn(usize, &str) -> bool) -> Option<usize> {
+        let find = |label: &str| {
+            children
+                .iter()
+                .copied()
+                .find(|&child| has_label(child, label))
+        };
+        match self {
+            PathStep::Label(label) => find(label),
+            PathStep::Index(index) => children.get(*index).copied(),
+            PathStep::Token(token) => find(token).or_else(|| {
+                // RFC 6901 array indices have no leading zeros or signs
+                let canonical = token.bytes().all(|b| b.is_ascii_digit())
+                    && (token.as_ref() == "0" || !token.starts_with('0'));
+                let index: usize = token.parse().ok().filter(|_| canonical)?;
+                children.get(index).copied()
+            }),
+        }
+    }
+}
+
+/// Follows `steps` down from `root`, with `child` finding the node one step
+/// selects below another
+fn find_path<'a>(
+    root: usize,
+    steps: impl IntoIterator<Item = PathStep<'a>>,
+    child: impl Fn(usize, PathStep<'a>) -> Option<usize>,
+) -> Option<usize> {
+    steps.into_iter().try_fold(root, child)
+}
+
+/// The steps of a path such as `.a.b[2].c`, or `None` if it is malformed
+fn parse_path(path: &str) -> Option<Vec<PathStep<'static>>> {
+    let mut chars = path.strip_prefix('.').unwrap_or(path).chars().peekable();
+    let mut steps = Vec::new();
+
+    while chars.peek().is_some() {
+        if chars.next_if_eq(&'[').is_some() {
+            let mut digits = String::new();
+            loop {
+                match chars.next()? {
+                    ']' => break,
+                    c => digits.push(c),
+                }
+            }
+            steps.push(PathStep::Index(digits.trim().parse().ok()?));
+        } else if chars.peek() == Some(&'"') {
+            // A JSON string, up to the first quote that isn't escaped
+            let mut quoted = String::from(chars.next()?);
+            let mut escaped = false;
+            loop {
+                let c = chars.next()?;
+                quoted.push(c);
+                if c == '"' && !escaped {
+                    break;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            steps.push(PathStep::Label(Cow::Owned(
+                serde_json::from_str(&quoted).ok()?,
+            )));
+        } else {
+            let mut label = String::new();
+            while let Some(c) = chars.next_if(|&c| c != '.' && c != '[') {
+                label.push(if c == '\\' { chars.next()? } else { c });
+            }
+            if label.is_empty() {
+                return None;
+            }
+            steps.push(PathStep::Label(Cow::Owned(label)));
+        }
+        // A dot must be followed by another segment
+        if chars.next_if_eq(&'.').is_some() && chars.peek().is_none() {
+            return None;
+        }
+    }
+    Some(steps)
+}
+
+/// The steps of a JSON Pointer such as `/users/3/e~1mail` (RFC 6901), or
+/// `None` if it is malformed. The empty pointer selects the root.
+fn parse_json_pointer(pointer: &str) -> Option<Vec<PathStep<'static>>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(|token| {
+            let mut label = String::new();
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                label.push(match c {
+                    '~' => match chars.next()? {
+                        '0' => '~',
+                        '1' => '/',
+                        _ => return None,
+                    },
+                    c => c,
+                });
+            }
+            Some(PathStep::Token(Cow::Owned(label)))
+        })
+        .collect()
+}
+
+/// Whether `order` holds exactly the IDs of `children`
+pub(crate) fn is_permutation(children: &[usize], order: &[usize]) -> bool {
+    let mut sorted = order.to_vec();
+    sorted.sort_unstable();
+    let mut expected = children.to_vec();
+    expected.sort_unstable();
+    sorted == expected
 }
 
 #[cfg(test)]
@@ -403,4 +1087,114 @@ mod tests {
         assert_eq!(root_children[0], child1_id);
         assert_eq!(root_children[1], child2_id);
     }
+
+    /// root → servers (array) → [0] → host "example.com" → port, [1]
+    fn servers() -> Tree {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let servers = tree.add_child_node(0, TreeNode::new("servers", "array"));
+        let first = tree.add_child_node(servers, TreeNode::new("[0]", "object"));
+        let host = tree.add_child_node(first, TreeNode::new("example.com", "object"));
+        tree.add_child_node(host, TreeNode::new("port", "number"));
+        tree.add_child_node(servers, TreeNode::new("[1]", "object"));
+        // A second key with the same label
+        tree.add_child_node(0, TreeNode::new("servers", "string"));
+        tree
+    }
+
+    #[test]
+    fn test_find_by_path() {
+        let tree = servers();
+        assert_eq!(
+            tree.find_by_path(&["servers", "[0]", "example.com"]),
+            Some(3)
+        );
+        assert_eq!(tree.find_by_path(&["servers", "[1]"]), Some(5));
+        // Ambiguous labels select the first child with the label
+        assert_eq!(tree.find_by_path(&["servers"]), Some(1));
+        assert_eq!(tree.find_by_path(&["servers", "[2]"]), None);
+        assert_eq!(tree.find_by_path(&["port"]), None);
+    }
+
+    #[test]
+    fn test_index_steps_select_positions_and_segments_labels() {
+        // JSON Lines records are labeled with their line
+        let mut lines = Tree::new(TreeNode::new("root", "array"));
+        let first = lines.add_child_node(0, TreeNode::new("[1]", "object"));
+        let second = lines.add_child_node(0, TreeNode::new("[3]", "object"));
+        assert_eq!(lines.find_by_path(&["[3]"]), Some(second));
+        assert_eq!(lines.find_by_path(&["[0]"]), None);
+        assert_eq!(lines.find_by_path_str("[1]"), Some(second));
+        assert_eq!(lines.find_by_path_str(".[0]"), Some(first));
+        assert_eq!(lines.find_by_path_str(r#"."[3]""#), Some(second));
+        assert_eq!(lines.find_by_path_str("[3]"), None);
+    }
+
+    #[test]
+    fn test_find_by_path_str() {
+        let tree = servers();
+        assert_eq!(
+            tree.find_by_path_str(r".servers[0].example\.com.port"),
+            Some(4)
+        );
+        assert_eq!(tree.find_by_path_str(r"servers.[1]"), Some(5));
+        assert_eq!(
+            tree.find_by_path_str(r#".servers[0]."example.com"."port""#),
+            Some(4)
+        );
+        assert_eq!(tree.find_by_path_str(r#"."serv\u0065rs""#), Some(1));
+        assert_eq!(tree.find_by_path_str(""), Some(0));
+        assert_eq!(tree.find_by_path_str(".servers[0].example.com"), None);
+
+        for malformed in [
+            ".servers.",
+            ".servers[0",
+            ".servers[x]",
+            "..servers",
+            r".servers\",
+            r#"."servers"#,
+            r#".servers."\x""#,
+        ] {
+            assert_eq!(tree.find_by_path_str(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn test_find_by_json_pointer() {
+        let tree = servers();
+        assert_eq!(
+            tree.find_by_json_pointer("/servers/0/example.com/port"),
+            Some(4)
+        );
+        assert_eq!(tree.find_by_json_pointer("/servers/1"), Some(5));
+        assert_eq!(tree.find_by_json_pointer("/servers/[1]"), Some(5));
+        assert_eq!(tree.find_by_json_pointer(""), Some(0));
+
+        for malformed in ["servers", "/servers/01", "/servers/+1", "/servers~2", "/"] {
+            assert_eq!(tree.find_by_json_pointer(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn test_find_by_path_in_streaming_trees() {
+        let ldif = "dn: dc=example,dc=com\ndc: example\n\n\
+                    dn: ou=People,dc=example,dc=com\nou: People\n\n\
+                    dn: cn=Ann,ou=People,dc=example,dc=com\ncn: Ann\n";
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, ldif.as_bytes()).unwrap();
+        let streaming =
+            crate::parser::ldif::build_ldif_index_quiet(file.path(), Default::default()).unwrap();
+        let tree = TreeVariant::Streaming(Box::new(streaming));
+
+        let ann = tree
+            .find_by_path(&["dc=example,dc=com", "ou=People", "cn=Ann"])
+            .unwrap();
+        assert_eq!(tree.get_label(ann).as_deref(), Some("cn=Ann"));
+        assert_eq!(
+            tree.find_by_path_str(".dc=example,dc=com.ou=People.cn=Ann"),
+            Some(ann)
+        );
+        // Entries come after the `@attributes` of their parent
+        let people = tree.find_by_path(&["dc=example,dc=com", "ou=People"]);
+        assert_eq!(tree.find_by_path_str(".dc=example,dc=com[1]"), people);
+    }
 }
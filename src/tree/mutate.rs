@@ -0,0 +1,294 @@
+//! Removing and moving nodes of a [`Tree`].
+//!
+//! Every mutation keeps `children` and `parent_id` pointing at each other.
+//! Removals compact the node vector, so the remaining nodes get new IDs; they
+//! return a table from old to new IDs for callers that hold on to IDs, such
+//! as a view's selection or expanded nodes.
+
+use super::{Order, Tree, TreeNode};
+use crate::error::{Result, XtvError};
+
+/// New ID of every old node ID, `None` for removed nodes
+pub type IdRemap = Vec<Option<usize>>;
+
+impl Tree {
+    /// Removes a node and everything below it.
+    ///
+    /// # Returns
+    ///
+    /// The new IDs of all nodes, indexed by their old IDs
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::InvalidNodeId` if the node doesn't exist or is the
+    /// root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+    /// tree.add_child_node(a, TreeNode::new("b", "string"));
+    /// let c = tree.add_child_node(0, TreeNode::new("c", "string"));
+    ///
+    /// let remap = tree.remove_subtree(a).unwrap();
+    /// assert_eq!(remap, [Some(0), None, None, Some(1)]);
+    /// assert_eq!(tree.get_children(0), [remap[c].unwrap()]);
+    /// ```
+    pub fn remove_subtree(&mut self, id: usize) -> Result<IdRemap> {
+        if id == self.root_id || id >= self.nodes.len() {
+            return Err(XtvError::InvalidNodeId(id));
+        }
+        let mut keep = vec![true; self.nodes.len()];
+        for (removed, _) in self.walk(id, Order::DepthFirst) {
+            keep[removed] = false;
+        }
+        Ok(self.compact(&keep))
+    }
+
+    /// Removes every node below the root for which `keep` returns `false`,
+    /// together with everything below it. The root is always kept.
+    ///
+    /// # Returns
+    ///
+    /// The new IDs of all nodes, indexed by their old IDs
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "comment"));
+    /// tree.add_child_node(a, TreeNode::new("b", "string"));
+    /// tree.add_child_node(0, TreeNode::new("c", "string"));
+    ///
+    /// tree.retain(|node| node.node_type != "comment");
+    /// assert_eq!(tree.node_count(), 2);
+    /// assert_eq!(tree.get_node(1).unwrap().label, "c");
+    /// ```
+    pub fn retain(&mut self, mut keep: impl FnMut(&TreeNode) -> bool) -> IdRemap {
+        let wanted: Vec<bool> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| id == self.root_id || keep(node))
+            .collect();
+
+        // A node stays only if all its ancestors stay
+        let mut keep_ids = wanted.clone();
+        let tops = (0..self.nodes.len()).filter(|&id| self.nodes[id].parent_id.is_none());
+        let mut stack: Vec<(usize, bool)> = tops.map(|id| (id, wanted[id])).collect();
+        while let Some((id, stays)) = stack.pop() {
+            keep_ids[id] = stays;
+            let children = &self.nodes[id].children;
+            stack.extend(
+                children
+                    .iter()
+                    .map(|&child| (child, stays && wanted[child])),
+            );
+        }
+        self.compact(&keep_ids)
+    }
+
+    /// Moves a node with everything below it to the end of the children of
+    /// `new_parent`.
+    ///
+    /// IDs don't change.
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::InvalidNodeId` with the offending ID if either node
+    /// doesn't exist, if `id` is the root, or if `new_parent` is `id` itself
+    /// or below it, which would make a cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+    /// let b = tree.add_child_node(0, TreeNode::new("b", "string"));
+    ///
+    /// tree.reparent(b, a).unwrap();
+    /// assert_eq!(tree.get_children(a), [b]);
+    /// assert_eq!(tree.get_parent(b), Some(a));
+    /// // a can't move below b, which is now below a
+    /// assert!(tree.reparent(a, b).is_err());
+    /// ```
+    pub fn reparent(&mut self, id: usize, new_parent: usize) -> Result<()> {
+        if id == self.root_id || id >= self.nodes.len() {
+            return Err(XtvError::InvalidNodeId(id));
+        }
+        if new_parent >= self.nodes.len()
+            || new_parent == id
+            || self.ancestors(new_parent).any(|ancestor| ancestor == id)
+        {
+            return Err(XtvError::InvalidNodeId(new_parent));
+        }
+
+        if let Some(old_parent) = self.nodes[id].parent_id {
+            self.nodes[old_parent].children.retain(|&child| child != id);
+            if let Some(original) = self.original_children.get_mut(&old_parent) {
+                original.retain(|&child| child != id);
+            }
+        }
+        self.nodes[new_parent].children.push(id);
+        if let Some(original) = self.original_children.get_mut(&new_parent) {
+            original.push(id);
+        }
+        self.nodes[id].parent_id = Some(new_parent);
+        Ok(())
+    }
+
+    /// Drops the nodes that aren't kept and renumbers the rest in order.
+    ///
+    /// The parent of a kept node must be kept as well.
+    fn compact(&mut self, keep: &[bool]) -> IdRemap {
+        let mut remap = Vec::with_capacity(keep.len());
+        let mut next = 0;
+        for &kept in keep {
+            remap.push(kept.then(|| {
+                next += 1;
+                next - 1
+            }));
+        }
+        let renumber =
+            |ids: &[usize]| -> Vec<usize> { ids.iter().filter_map(|&id| remap[id]).collect() };
+
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, kept)| **kept)
+            .map(|(mut node, _)| {
+                node.children = renumber(&node.children);
+                node.parent_id = node.parent_id.and_then(|parent| remap[parent]);
+                node
+            })
+            .collect();
+        self.original_children = std::mem::take(&mut self.original_children)
+            .into_iter()
+            .filter_map(|(id, original)| Some((remap[id]?, renumber(&original))))
+            .collect();
+        self.grafts = std::mem::take(&mut self.grafts)
+            .into_iter()
+            .filter_map(|(parent, top)| Some((remap[parent]?, remap[top]?)))
+            .collect();
+        self.root_id = remap[self.root_id].unwrap_or(0);
+        remap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::snapshot::check_references;
+
+    /// Fails the test if `children` and `parent_id` disagree anywhere
+    fn assert_consistent(tree: &Tree) {
+        if let Err(e) = check_references(&tree.nodes, tree.root_id, &tree.original_children) {
+            panic!("{} in {:#?}", e, tree.nodes);
+        }
+    }
+
+    /// Deterministic pseudo-random numbers, xorshift64
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    fn random_tree(rng: &mut Rng, size: usize) -> Tree {
+        let mut tree = Tree::new(TreeNode::new("0", "object"));
+        for label in 1..size {
+            let parent = rng.below(tree.node_count());
+            tree.add_child_node(parent, TreeNode::new(label.to_string(), "object"));
+        }
+        tree
+    }
+
+    #[test]
+    fn test_random_mutations_keep_the_tree_consistent() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..50 {
+            let mut tree = random_tree(&mut rng, 40);
+            for _ in 0..30 {
+                let count = tree.node_count();
+                let (id, other) = (rng.below(count), rng.below(count));
+                let labels_before: Vec<String> = tree
+                    .nodes
+                    .iter()
+                    .map(|node| node.label.to_string())
+                    .collect();
+                let remap = match rng.below(4) {
+                    0 => tree.remove_subtree(id).unwrap_or_default(),
+                    1 => {
+                        let modulus = 2 + rng.below(5);
+                        tree.retain(|node| node.label.len() % modulus != 0)
+                    }
+                    2 => {
+                        let order = tree.get_children(id).into_iter().rev().collect();
+                        tree.reorder_children(id, order);
+                        Vec::new()
+                    }
+                    _ => {
+                        let cycle = other == id || tree.ancestors(other).any(|a| a == id);
+                        let moved = tree.reparent(id, other);
+                        assert_eq!(moved.is_err(), cycle || id == tree.root_id);
+                        Vec::new()
+                    }
+                };
+                assert_consistent(&tree);
+                // Kept nodes keep their label under their new ID
+                for (old, new) in remap.iter().enumerate() {
+                    if let Some(new) = new {
+                        assert_eq!(tree.nodes[*new].label, labels_before[old]);
+                    }
+                }
+                if tree.node_count() == 1 {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_mutations_are_rejected() {
+        let mut tree = random_tree(&mut Rng(7), 5);
+        let root = tree.root_id();
+        assert!(matches!(
+            tree.remove_subtree(root),
+            Err(XtvError::InvalidNodeId(0))
+        ));
+        assert!(tree.remove_subtree(5).is_err());
+        assert!(tree.reparent(root, 1).is_err());
+        assert!(tree.reparent(1, 1).is_err());
+        assert!(tree.reparent(1, 9).is_err());
+        assert_eq!(tree.node_count(), 5);
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn test_removal_keeps_remembered_orders() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        for label in ["a", "b", "c"] {
+            tree.add_child_node(0, TreeNode::new(label, "string"));
+        }
+        tree.reorder_children(0, vec![3, 2, 1]);
+        tree.remove_subtree(2).unwrap();
+
+        assert_eq!(tree.get_children(0), [2, 1]);
+        assert!(tree.restore_children(0));
+        assert_eq!(tree.get_children(0), [1, 2]);
+        assert_eq!(tree.get_node(2).unwrap().label, "c");
+    }
+}
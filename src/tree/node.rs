@@ -1,3 +1,9 @@
+use compact_str::CompactString;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{PoisonError, RwLock};
+
 /// Represents a single attribute of a tree node.
 ///
 /// Attributes are key-value pairs attached to nodes. They're used to store:
@@ -15,7 +21,7 @@
 /// assert_eq!(attr.key, "id");
 /// assert_eq!(attr.value, "123");
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Attribute {
     /// The attribute key/name
     pub key: String,
@@ -50,6 +56,253 @@ impl Attribute {
     }
 }
 
+/// The type of a node, such as `object` or `entry`.
+///
+/// Trees have millions of nodes but only a handful of types, so every
+/// distinct name is stored once for the life of the process and nodes hold a
+/// pointer to it. It compares, hashes and prints like the name itself.
+///
+/// # Examples
+///
+/// ```
+/// use xtv::tree::node::TypeName;
+///
+/// let name = TypeName::from("object");
+/// assert_eq!(name, "object");
+/// assert_eq!(name.len(), 6);
+/// assert_eq!(TypeName::from("object".to_string()), name);
+///
+/// // Names are stored once, including those of other parsers
+/// let custom = TypeName::from("custom");
+/// assert!(std::ptr::eq(custom.as_str(), TypeName::from("custom").as_str()));
+/// ```
+#[derive(Clone, Copy)]
+pub struct TypeName(&'static &'static str);
+
+/// Types of the built-in parsers, which are found without taking a lock
+static BUILT_IN_TYPES: &[&str] = &[
+    "root",
+    "object",
+    "array",
+    "string",
+    "number",
+    "attribute",
+    TreeNode::VIRTUAL_ATTRIBUTES_TYPE,
+    TreeNode::URL_REF_TYPE,
+    "entry",
+    "element",
+    "text",
+    "comment",
+    "doctype",
+    "mapping",
+    "sequence",
+    "tagged",
+    "table",
+    "inline_table",
+    TreeNode::LOADING_TYPE,
+    TreeNode::ERROR_TYPE,
+];
+
+/// Types first seen at runtime, such as those of library users' parsers
+static OTHER_TYPES: RwLock<Vec<&'static &'static str>> = RwLock::new(Vec::new());
+
+impl TypeName {
+    pub fn new(name: &str) -> Self {
+        if let Some(built_in) = BUILT_IN_TYPES.iter().find(|&&known| known == name) {
+            return Self(built_in);
+        }
+        let find = |types: &[&'static &'static str]| {
+            types
+                .iter()
+                .find(|&&&known| known == name)
+                .map(|&known| Self(known))
+        };
+        let other = OTHER_TYPES.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(found) = find(&other) {
+            return found;
+        }
+        drop(other);
+        let mut other = OTHER_TYPES.write().unwrap_or_else(PoisonError::into_inner);
+        // Another thread may have added it in the meantime
+        if let Some(found) = find(&other) {
+            return found;
+        }
+        let name: &'static &'static str = Box::leak(Box::new(&*String::from(name).leak()));
+        other.push(name);
+        Self(name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl Deref for TypeName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl AsRef<str> for TypeName {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl From<&str> for TypeName {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<&String> for TypeName {
+    fn from(name: &String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for TypeName {
+    fn from(name: String) -> Self {
+        Self::new(&name)
+    }
+}
+
+impl From<TypeName> for String {
+    fn from(name: TypeName) -> Self {
+        name.0.to_string()
+    }
+}
+
+impl PartialEq for TypeName {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for TypeName {}
+
+impl PartialEq<str> for TypeName {
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == other
+    }
+}
+
+impl PartialEq<&str> for TypeName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<String> for TypeName {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == other
+    }
+}
+
+impl PartialOrd for TypeName {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TypeName {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(other.0)
+    }
+}
+
+impl std::hash::Hash for TypeName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl fmt::Debug for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0, f)
+    }
+}
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl Serialize for TypeName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for TypeName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&String::deserialize(deserializer)?))
+    }
+}
+
+/// The kind of value an attribute node holds, for formats that type their
+/// values.
+///
+/// The tree keeps every value as text; the kind says how to write it back,
+/// so that a quoted `"42"` stays a string and `1.0` stays a float.
+///
+/// # Examples
+///
+/// ```
+/// use xtv::tree::node::ValueKind;
+///
+/// assert_eq!(ValueKind::guess("42"), ValueKind::Integer);
+/// assert_eq!(ValueKind::guess("1.5e3"), ValueKind::Float);
+/// assert_eq!(ValueKind::guess(" true"), ValueKind::String);
+/// assert!(ValueKind::Integer.reads("-7"));
+/// assert!(!ValueKind::Boolean.reads("yes"));
+/// assert!(ValueKind::String.reads("null"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueKind {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Null,
+    /// A TOML date, time or datetime
+    Datetime,
+}
+
+impl ValueKind {
+    /// The kind `text` reads as: a JSON number, boolean or null, otherwise a
+    /// string
+    pub fn guess(text: &str) -> Self {
+        if text.trim() != text {
+            return Self::String;
+        }
+        match serde_json::from_str(text) {
+            Ok(serde_json::Value::Number(n)) if n.is_f64() => Self::Float,
+            Ok(serde_json::Value::Number(_)) => Self::Integer,
+            Ok(serde_json::Value::Bool(_)) => Self::Boolean,
+            Ok(serde_json::Value::Null) => Self::Null,
+            _ => Self::String,
+        }
+    }
+
+    /// Whether `text` is a value of this kind
+    pub fn reads(self, text: &str) -> bool {
+        match self {
+            Self::String => true,
+            Self::Integer => text.parse::<i128>().is_ok(),
+            // YAML writes infinity and NaN as `.inf` and `.nan`
+            Self::Float => text.parse::<f64>().is_ok() || matches!(text, ".inf" | "-.inf" | ".nan"),
+            Self::Boolean => matches!(text, "true" | "false"),
+            Self::Null => text == "null",
+            Self::Datetime => text.parse::<toml_edit::Datetime>().is_ok(),
+        }
+    }
+}
+
 /// Represents a node in the tree structure.
 ///
 /// TreeNode is the fundamental building block of the tree. Each node has:
@@ -64,12 +317,16 @@ impl Attribute {
 /// Common node types include:
 /// - `"object"` - JSON object, XML/HTML element
 /// - `"array"` - JSON array
+/// - `"table"` / `"inline_table"` - TOML table, written as `[header]` or `{ ... }`
 /// - `"element"` - XML/HTML element
 /// - `"text"` - Text content node
 /// - `"comment"` - Comment node
+/// - `"doctype"` - HTML document type declaration, such as `html`
 /// - `"entry"` - LDIF entry
 /// - `"attribute"` - Individual attribute value
+/// - `"url_ref"` - LDIF attribute whose value is read from a URL (see [`URL_REF_TYPE`](TreeNode::URL_REF_TYPE))
 /// - `"@attributes"` - Virtual container for attributes (see [`VIRTUAL_ATTRIBUTES_TYPE`](TreeNode::VIRTUAL_ATTRIBUTES_TYPE))
+/// - `"loading…"` / `"error"` - Streaming placeholders (see [`LOADING_TYPE`](TreeNode::LOADING_TYPE))
 ///
 /// # Examples
 ///
@@ -87,22 +344,36 @@ impl Attribute {
 /// assert!(!node.is_virtual_attributes());
 /// assert!(!node.is_attribute());
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TreeNode {
-    /// Display label for this node
-    pub label: String,
+    /// Display label for this node, kept inline if it is short
+    pub label: CompactString,
 
     /// Node type (e.g., "object", "array", "element", "text", "entry")
-    pub node_type: String,
+    pub node_type: TypeName,
 
-    /// Attributes associated with this node (key-value pairs)
-    pub attributes: Vec<Attribute>,
+    /// Attributes associated with this node (key-value pairs), usually none
+    /// or one, without room for more
+    #[serde(default)]
+    pub attributes: Box<[Attribute]>,
 
     /// Child node IDs (indices into the tree's node vector)
+    #[serde(default)]
     pub children: Vec<usize>,
 
     /// Parent node ID (None for root node)
+    #[serde(default)]
     pub parent_id: Option<usize>,
+
+    /// Where the node starts in the parsed text, as 1-based line and column
+    /// (None if the parser doesn't record it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_span: Option<(usize, usize)>,
+
+    /// Kind of the value of an attribute node (None if the parser doesn't
+    /// type its values, as for markup and LDIF)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_kind: Option<ValueKind>,
 }
 
 impl TreeNode {
@@ -124,13 +395,15 @@ impl TreeNode {
     /// assert!(node.attributes.is_empty());
     /// assert!(node.children.is_empty());
     /// ```
-    pub fn new(label: impl Into<String>, node_type: impl Into<String>) -> Self {
+    pub fn new(label: impl Into<CompactString>, node_type: impl Into<TypeName>) -> Self {
         Self {
             label: label.into(),
             node_type: node_type.into(),
-            attributes: Vec::new(),
+            attributes: Box::default(),
             children: Vec::new(),
             parent_id: None,
+            source_span: None,
+            value_kind: None,
         }
     }
 
@@ -153,7 +426,24 @@ impl TreeNode {
     /// assert_eq!(node.attributes.len(), 2);
     /// ```
     pub fn with_attributes(mut self, attributes: Vec<Attribute>) -> Self {
-        self.attributes = attributes;
+        self.attributes = attributes.into_boxed_slice();
+        self
+    }
+
+    /// Sets the kind of the node's value (builder pattern).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::TreeNode;
+    /// use xtv::tree::node::ValueKind;
+    ///
+    /// let mut node = TreeNode::new("port", "attribute").with_value_kind(ValueKind::Integer);
+    /// node.add_attribute("value", "80");
+    /// assert_eq!(node.value_kind, Some(ValueKind::Integer));
+    /// ```
+    pub fn with_value_kind(mut self, kind: ValueKind) -> Self {
+        self.value_kind = Some(kind);
         self
     }
 
@@ -175,7 +465,12 @@ impl TreeNode {
     /// assert_eq!(node.attributes.len(), 2);
     /// ```
     pub fn add_attribute(&mut self, key: impl Into<String>, value: impl Into<String>) {
-        self.attributes.push(Attribute::new(key, value));
+        // Nodes have few attributes, so growing the list by one each time
+        // costs less than the room a `Vec` would keep spare
+        let mut attributes = Vec::with_capacity(self.attributes.len() + 1);
+        attributes.extend(std::mem::take(&mut self.attributes));
+        attributes.push(Attribute::new(key, value));
+        self.attributes = attributes.into_boxed_slice();
     }
 
     /// Adds a child node ID to this node's children list.
@@ -212,6 +507,32 @@ impl TreeNode {
     /// of a virtual attributes container node.
     pub const ATTRIBUTE_TYPE: &'static str = "attribute";
 
+    /// The node type string for LDIF attributes written as `key:< URL`.
+    ///
+    /// They are attributes whose `value` is the URL the value is read from,
+    /// such as `file:///tmp/photo.jpg`.
+    pub const URL_REF_TYPE: &'static str = "url_ref";
+
+    /// The node type string for placeholders whose data is still being loaded.
+    ///
+    /// Streaming trees with background loading return these on a cache miss;
+    /// the real node replaces it once the load completes.
+    pub const LOADING_TYPE: &'static str = "loading…";
+
+    /// The node type string for nodes whose data failed to load.
+    ///
+    /// The failure message is stored in the `error` attribute.
+    pub const ERROR_TYPE: &'static str = "error";
+
+    /// Node types of key/value containers: JSON objects, YAML mappings and
+    /// TOML tables and inline tables.
+    pub const OBJECT_TYPES: &'static [&'static str] =
+        &["object", "mapping", "table", "inline_table"];
+
+    /// Node types of containers whose children are addressed by index: JSON
+    /// and TOML arrays and YAML sequences.
+    pub const ARRAY_TYPES: &'static [&'static str] = &["array", "sequence"];
+
     /// Checks if this node is a virtual attributes container.
     ///
     /// # Returns
@@ -226,7 +547,57 @@ impl TreeNode {
     /// # Returns
     ///
     /// `true` if node_type equals [`ATTRIBUTE_TYPE`](TreeNode::ATTRIBUTE_TYPE)
+    /// or [`URL_REF_TYPE`](TreeNode::URL_REF_TYPE)
     pub fn is_attribute(&self) -> bool {
-        self.node_type == Self::ATTRIBUTE_TYPE
+        self.node_type == Self::ATTRIBUTE_TYPE || self.is_url_ref()
+    }
+
+    /// Checks if this node is an attribute referring to its value by URL.
+    ///
+    /// # Returns
+    ///
+    /// `true` if node_type equals [`URL_REF_TYPE`](TreeNode::URL_REF_TYPE)
+    pub fn is_url_ref(&self) -> bool {
+        self.node_type == Self::URL_REF_TYPE
+    }
+
+    /// Checks if this node is one of the [`OBJECT_TYPES`](TreeNode::OBJECT_TYPES).
+    pub fn is_object(&self) -> bool {
+        Self::OBJECT_TYPES.contains(&self.node_type.as_str())
+    }
+
+    /// Checks if this node is one of the [`ARRAY_TYPES`](TreeNode::ARRAY_TYPES).
+    pub fn is_array(&self) -> bool {
+        Self::ARRAY_TYPES.contains(&self.node_type.as_str())
+    }
+
+    /// Approximate heap usage of the node's strings and lists in bytes, not
+    /// counting the node itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::TreeNode;
+    ///
+    /// let mut node = TreeNode::new("item", "element");
+    /// let empty = node.heap_bytes();
+    /// node.add_attribute("id", "123");
+    /// assert!(node.heap_bytes() >= empty + "id".len() + "123".len());
+    /// ```
+    pub fn heap_bytes(&self) -> usize {
+        let attributes: usize = self
+            .attributes
+            .iter()
+            .map(|attr| attr.key.capacity() + attr.value.capacity())
+            .sum();
+        let label = if self.label.is_heap_allocated() {
+            self.label.capacity()
+        } else {
+            0
+        };
+        label
+            + self.attributes.len() * std::mem::size_of::<Attribute>()
+            + attributes
+            + self.children.capacity() * std::mem::size_of::<usize>()
     }
 }
@@ -0,0 +1,393 @@
+//! Searching a tree on a worker thread.
+//!
+//! A search walks the subtree under a node depth-first and reports matching
+//! node IDs in document order, in batches as they are found. It runs on its
+//! own thread so that large trees never stall the caller, and stops as soon as
+//! its [`SearchTask`] is cancelled or dropped.
+//!
+//! Matchers that look at the path to each node get it from a [`PathTracker`],
+//! which extends and cuts back one string as the walk goes down and up instead
+//! of joining every node's ancestors anew.
+
+use crate::interrupt;
+use crate::tree::{Tree, TreeNode};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Matches are sent at least this often while a search runs
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Decides which nodes a search reports
+pub trait NodeMatcher: Send + 'static {
+    fn matches(&self, node: &TreeNode) -> bool;
+
+    /// Whether [`matches`](NodeMatcher::matches) looks at attribute values.
+    ///
+    /// Streaming trees keep values on disk, so searches that only look at
+    /// labels and keys never read the file.
+    fn needs_values(&self) -> bool;
+
+    /// The separator to join the labels above each node with, for matchers
+    /// that look at the path to a node through
+    /// [`matches_below`](NodeMatcher::matches_below)
+    fn path_separator(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether a node matches, given `path`, the labels of its ancestors
+    /// below the tree's root joined with the
+    /// [`path_separator`](NodeMatcher::path_separator)
+    fn matches_below(&self, node: &TreeNode, path: &str) -> bool {
+        let _ = path;
+        self.matches(node)
+    }
+}
+
+/// The path above the node a walk is at
+pub(crate) struct PathTracker {
+    separator: String,
+    path: String,
+    /// Length of `path` after each label it holds
+    ends: Vec<usize>,
+    /// Labels above the walk's first node
+    above: usize,
+}
+
+impl PathTracker {
+    /// A tracker for a walk starting at `root`, with `parent` and `label`
+    /// looking up the nodes above it
+    pub(crate) fn new(
+        separator: &str,
+        root: usize,
+        parent: impl Fn(usize) -> Option<usize>,
+        label: impl Fn(usize) -> String,
+    ) -> Self {
+        let mut labels = Vec::new();
+        let mut node = root;
+        while let Some(up) = parent(node) {
+            // The tree's root has no place in paths
+            if parent(up).is_some() {
+                labels.push(label(up));
+            }
+            node = up;
+        }
+        let mut tracker = Self {
+            separator: separator.to_string(),
+            path: String::new(),
+            ends: Vec::new(),
+            above: 0,
+        };
+        for label in labels.iter().rev() {
+            tracker.enter(Some(label));
+        }
+        tracker.above = tracker.ends.len();
+        tracker
+    }
+
+    /// The path above a node `depth` levels below the walk's first node,
+    /// dropping what the walk left behind
+    pub(crate) fn above(&mut self, depth: usize) -> &str {
+        self.ends.truncate(self.above + depth);
+        self.path.truncate(self.ends.last().copied().unwrap_or(0));
+        &self.path
+    }
+
+    /// Go down into the children of the node with `label`, or of the tree's
+    /// root with none
+    pub(crate) fn enter(&mut self, label: Option<&str>) {
+        if let Some(label) = label {
+            if !self.path.is_empty() {
+                self.path.push_str(&self.separator);
+            }
+            self.path.push_str(label);
+        }
+        self.ends.push(self.path.len());
+    }
+}
+
+/// What the worker reports back
+enum SearchEvent {
+    Matches(Vec<usize>),
+    /// The walk stopped after examining the node limit
+    LimitReached,
+}
+
+/// Progress of a running search, as returned by [`SearchTask::poll`]
+#[derive(Debug, Default)]
+pub struct SearchUpdate {
+    /// Matches found since the last poll, in document order
+    pub matches: Vec<usize>,
+    /// Whether the search stopped at its node limit
+    pub limit_reached: bool,
+    /// Whether the search is over; later polls return nothing
+    pub finished: bool,
+}
+
+/// Handle to a search running on a worker thread.
+///
+/// Dropping the handle cancels the search.
+#[derive(Debug)]
+pub struct SearchTask {
+    cancel: Arc<AtomicBool>,
+    searched: Arc<AtomicUsize>,
+    events: Receiver<SearchEvent>,
+}
+
+impl SearchTask {
+    /// Runs `walk` on a worker thread, which visits nodes through a [`Searcher`].
+    pub(crate) fn spawn(limit: usize, walk: impl FnOnce(&mut Searcher) + Send + 'static) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let searched = Arc::new(AtomicUsize::new(0));
+        let (sender, events) = channel();
+
+        let mut searcher = Searcher {
+            cancel: Arc::clone(&cancel),
+            searched: Arc::clone(&searched),
+            sender,
+            limit,
+            found: Vec::new(),
+            last_flush: Instant::now(),
+        };
+        thread::spawn(move || {
+            walk(&mut searcher);
+            searcher.flush();
+        });
+
+        Self {
+            cancel,
+            searched,
+            events,
+        }
+    }
+
+    /// Number of nodes examined so far
+    pub fn searched(&self) -> usize {
+        self.searched.load(Ordering::Relaxed)
+    }
+
+    /// Stops the search; matches already sent can still be polled.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Collects the matches found since the last call without blocking.
+    pub fn poll(&self) -> SearchUpdate {
+        let mut update = SearchUpdate::default();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => update.record(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    update.finished = true;
+                    break;
+                }
+            }
+        }
+        update
+    }
+
+    /// Waits for the search to finish and collects all remaining matches.
+    pub fn wait(&self) -> SearchUpdate {
+        let mut update = SearchUpdate::default();
+        for event in self.events.iter() {
+            update.record(event);
+        }
+        update.finished = true;
+        update
+    }
+}
+
+impl Drop for SearchTask {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl SearchUpdate {
+    fn record(&mut self, event: SearchEvent) {
+        match event {
+            SearchEvent::Matches(ids) => self.matches.extend(ids),
+            SearchEvent::LimitReached => self.limit_reached = true,
+        }
+    }
+}
+
+/// The worker's side of a search: counts nodes and batches matches
+pub(crate) struct Searcher {
+    cancel: Arc<AtomicBool>,
+    searched: Arc<AtomicUsize>,
+    sender: Sender<SearchEvent>,
+    limit: usize,
+    found: Vec<usize>,
+    last_flush: Instant,
+}
+
+impl Searcher {
+    /// Whether the walk may examine another node.
+    ///
+    /// Returns `false` once the search was cancelled, the process was
+    /// interrupted, or the search hit its node limit.
+    pub(crate) fn proceed(&mut self) -> bool {
+        if self.cancel.load(Ordering::Relaxed) || interrupt::is_interrupted() {
+            return false;
+        }
+        if self.searched.load(Ordering::Relaxed) >= self.limit {
+            let _ = self.sender.send(SearchEvent::LimitReached);
+            return false;
+        }
+        true
+    }
+
+    /// Records an examined node.
+    pub(crate) fn visit(&mut self, node_id: usize, matched: bool) {
+        self.searched.fetch_add(1, Ordering::Relaxed);
+        if matched {
+            self.found.push(node_id);
+        }
+        if !self.found.is_empty() && self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        if !self.found.is_empty() {
+            let _ = self
+                .sender
+                .send(SearchEvent::Matches(std::mem::take(&mut self.found)));
+        }
+    }
+}
+
+/// Searches an in-memory tree below `root`
+pub(crate) fn search_tree(
+    tree: Arc<Tree>,
+    root: usize,
+    limit: usize,
+    matcher: impl NodeMatcher,
+) -> SearchTask {
+    SearchTask::spawn(limit, move |searcher| {
+        let mut paths = matcher.path_separator().map(|separator| {
+            PathTracker::new(
+                separator,
+                root,
+                |id| tree.get_parent(id),
+                |id| {
+                    tree.get_node(id)
+                        .map(|node| node.label.to_string())
+                        .unwrap_or_default()
+                },
+            )
+        });
+        let mut stack = vec![(root, 0)];
+        while let Some((node_id, depth)) = stack.pop() {
+            let Some(node) = tree.get_node(node_id) else {
+                continue;
+            };
+            if !searcher.proceed() {
+                return;
+            }
+            let matched = match &mut paths {
+                Some(paths) => {
+                    let matched = matcher.matches_below(node, paths.above(depth));
+                    paths.enter(node.parent_id.map(|_| node.label.as_str()));
+                    matched
+                }
+                None => matcher.matches(node),
+            };
+            searcher.visit(node_id, matched);
+            // Push in reverse order so children are visited in document order
+            stack.extend(node.children.iter().rev().map(|&child| (child, depth + 1)));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Matches labels containing a string
+    struct Label(&'static str);
+
+    impl NodeMatcher for Label {
+        fn matches(&self, node: &TreeNode) -> bool {
+            node.label.contains(self.0)
+        }
+
+        fn needs_values(&self) -> bool {
+            false
+        }
+    }
+
+    /// root > (a > (a1, b1), b)
+    fn tree() -> Arc<Tree> {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+        tree.add_child_node(a, TreeNode::new("a1", "string"));
+        tree.add_child_node(a, TreeNode::new("b1", "string"));
+        tree.add_child_node(0, TreeNode::new("b", "string"));
+        Arc::new(tree)
+    }
+
+    #[test]
+    fn test_matches_in_document_order() {
+        let task = search_tree(tree(), 0, usize::MAX, Label("b"));
+        let update = task.wait();
+        assert_eq!(update.matches, [3, 4]);
+        assert!(update.finished);
+        assert!(!update.limit_reached);
+        assert_eq!(task.searched(), 5);
+    }
+
+    /// Matches labels containing a string below a path containing another
+    struct Below(&'static str, &'static str);
+
+    impl NodeMatcher for Below {
+        fn matches(&self, node: &TreeNode) -> bool {
+            node.label.contains(self.1)
+        }
+
+        fn needs_values(&self) -> bool {
+            false
+        }
+
+        fn path_separator(&self) -> Option<&str> {
+            Some("/")
+        }
+
+        fn matches_below(&self, node: &TreeNode, path: &str) -> bool {
+            self.matches(node) && path.contains(self.0)
+        }
+    }
+
+    #[test]
+    fn test_paths_above_nodes() {
+        let tree = tree();
+        let found = |root: usize, matcher: Below| {
+            search_tree(Arc::clone(&tree), root, 100, matcher)
+                .wait()
+                .matches
+        };
+        // Only the `b1` under `a`, not the `b` beside it
+        assert_eq!(found(0, Below("a", "b")), [3]);
+        // The root's own label is not part of paths
+        assert!(found(0, Below("root", "1")).is_empty());
+        // A subtree knows the path above it
+        assert_eq!(found(1, Below("a", "b")), [3]);
+    }
+
+    #[test]
+    fn test_subtree_and_limit() {
+        let update = search_tree(tree(), 1, usize::MAX, Label("1")).wait();
+        assert_eq!(update.matches, [2, 3]);
+
+        let task = search_tree(tree(), 0, 3, Label("1"));
+        let update = task.wait();
+        assert_eq!(update.matches, [2]);
+        assert!(update.limit_reached);
+        assert_eq!(task.searched(), 3);
+    }
+}
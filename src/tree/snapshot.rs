@@ -0,0 +1,270 @@
+//! Saving a parsed tree and loading it again, e.g. as a test fixture or a
+//! cache.
+//!
+//! A snapshot holds the nodes with their IDs, so child and parent references
+//! stay valid, wrapped in an envelope with a version:
+//!
+//! ```json
+//! {"version": 1, "format": "json", "root_id": 0, "nodes": [...]}
+//! ```
+//!
+//! Fields added in later versions must have defaults, so that older snapshots
+//! still load; unknown fields are ignored. Snapshots from a newer version
+//! than this build knows are rejected.
+
+use super::{Tree, TreeNode, is_permutation};
+use crate::error::{Result, XtvError};
+use crate::parser::{ParserOptions, get_parser_from_format};
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Version written into new snapshots
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A tree as it is written
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    format: Option<&'a str>,
+    root_id: usize,
+    nodes: &'a [TreeNode],
+    /// Sorted, so that equal trees give equal snapshots
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    original_children: BTreeMap<usize, &'a [usize]>,
+}
+
+/// A tree as it is read, before its references are checked
+#[derive(Deserialize)]
+struct Snapshot {
+    version: u32,
+    #[serde(default)]
+    format: Option<String>,
+    root_id: usize,
+    nodes: Vec<TreeNode>,
+    #[serde(default)]
+    original_children: HashMap<usize, Vec<usize>>,
+}
+
+impl Snapshot {
+    /// The tree, if the snapshot is of a known version and every ID in it
+    /// refers to a node
+    fn into_tree(self) -> Result<Tree> {
+        if self.version > SNAPSHOT_VERSION {
+            return Err(XtvError::UnsupportedFormat(format!(
+                "Tree snapshot version {} is newer than version {}",
+                self.version, SNAPSHOT_VERSION
+            )));
+        }
+        check_references(&self.nodes, self.root_id, &self.original_children)?;
+        Ok(Tree {
+            nodes: self.nodes,
+            root_id: self.root_id,
+            // Format names are static; unknown ones are dropped
+            format: self.format.and_then(|format| {
+                get_parser_from_format(&format, &ParserOptions::default())
+                    .ok()
+                    .map(|parser| parser.format_name())
+            }),
+            original_children: self.original_children,
+            grafts: HashMap::new(),
+        })
+    }
+}
+
+/// Checks that the root exists and has no parent, that every child lists
+/// its parent as parent and is listed only once, that every node with a
+/// parent is listed among its children, and that remembered orders hold the
+/// same children.
+pub(super) fn check_references(
+    nodes: &[TreeNode],
+    root_id: usize,
+    original_children: &HashMap<usize, Vec<usize>>,
+) -> Result<()> {
+    match nodes.get(root_id) {
+        Some(root) if root.parent_id.is_none() => {}
+        _ => return Err(XtvError::InvalidNodeId(root_id)),
+    }
+
+    let mut listed = vec![false; nodes.len()];
+    for (id, node) in nodes.iter().enumerate() {
+        for &child in &node.children {
+            let is_own_child = nodes
+                .get(child)
+                .is_some_and(|child| child.parent_id == Some(id));
+            if !is_own_child || std::mem::replace(&mut listed[child], true) {
+                return Err(XtvError::InvalidNodeId(child));
+            }
+        }
+    }
+    if let Some(orphan) = (0..nodes.len()).find(|&id| nodes[id].parent_id.is_some() && !listed[id])
+    {
+        return Err(XtvError::InvalidNodeId(orphan));
+    }
+
+    for (&id, order) in original_children {
+        if !nodes
+            .get(id)
+            .is_some_and(|node| is_permutation(&node.children, order))
+        {
+            return Err(XtvError::InvalidNodeId(id));
+        }
+    }
+    Ok(())
+}
+
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            format: self.format,
+            root_id: self.root_id,
+            nodes: &self.nodes,
+            original_children: self
+                .original_children
+                .iter()
+                .map(|(&id, order)| (id, order.as_slice()))
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Snapshot::deserialize(deserializer)?
+            .into_tree()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Tree {
+    /// Writes the tree as a JSON snapshot, see [`from_json`](Tree::from_json).
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::JsonParse` if serialization fails, which doesn't
+    /// happen for trees built through this API.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Reads a tree from a JSON snapshot written by [`to_json`](Tree::to_json),
+    /// keeping the node IDs.
+    ///
+    /// # Errors
+    ///
+    /// - `XtvError::JsonParse` if `json` isn't a snapshot
+    /// - `XtvError::UnsupportedFormat` if the snapshot is from a newer version
+    /// - `XtvError::InvalidNodeId` with the first ID whose references don't
+    ///   fit, such as a child that doesn't exist or names another parent
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let child = tree.add_child_node(0, TreeNode::new("name", "string"));
+    ///
+    /// let copy = Tree::from_json(&tree.to_json().unwrap()).unwrap();
+    /// assert_eq!(copy.get_node(child).unwrap().label, "name");
+    /// assert_eq!(copy.get_parent(child), Some(0));
+    /// ```
+    pub fn from_json(json: &str) -> Result<Tree> {
+        serde_json::from_str::<Snapshot>(json)?.into_tree()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser};
+
+    #[test]
+    fn test_round_trip_keeps_ids_format_and_order() {
+        let mut tree = JsonParser
+            .parse(r#"{"b": [1, {"x": null}], "a": "text"}"#)
+            .unwrap();
+        let root = tree.root_id();
+        let children = tree.get_children(root);
+        tree.reorder_children(root, children.into_iter().rev().collect());
+
+        let json = tree.to_json().unwrap();
+        let copy = Tree::from_json(&json).unwrap();
+        assert_eq!(copy.format(), Some("json"));
+        assert_eq!(copy.node_count(), tree.node_count());
+        for id in 0..tree.node_count() {
+            let (node, copied) = (tree.get_node(id).unwrap(), copy.get_node(id).unwrap());
+            assert_eq!(copied.label, node.label);
+            assert_eq!(copied.node_type, node.node_type);
+            assert_eq!(copied.children, node.children);
+            assert_eq!(copied.parent_id, node.parent_id);
+        }
+        assert_eq!(copy.reordered_nodes(), [root]);
+        // Snapshots of equal trees are equal
+        assert_eq!(copy.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_old_snapshots_load_and_newer_ones_are_rejected() {
+        // Optional fields may be missing, unknown ones are ignored
+        let json = r#"{"version": 1, "root_id": 0, "added_later": true, "nodes": [
+            {"label": "root", "node_type": "root", "children": [1]},
+            {"label": "a", "node_type": "string", "parent_id": 0}
+        ]}"#;
+        let tree = Tree::from_json(json).unwrap();
+        assert_eq!(tree.format(), None);
+        assert_eq!(tree.get_children(0), [1]);
+
+        let newer = json.replace(r#""version": 1"#, r#""version": 2"#);
+        assert!(matches!(
+            Tree::from_json(&newer),
+            Err(XtvError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_dangling_references_are_rejected() {
+        let snapshot =
+            |nodes: &str| format!(r#"{{"version": 1, "root_id": 0, "nodes": {}}}"#, nodes);
+        let invalid = [
+            // Child that doesn't exist
+            (
+                r#"[{"label": "r", "node_type": "root", "children": [1]}]"#,
+                1,
+            ),
+            // Child that names another parent
+            (
+                r#"[{"label": "r", "node_type": "root", "children": [1]},
+                    {"label": "a", "node_type": "string", "parent_id": 1}]"#,
+                1,
+            ),
+            // Parent that doesn't list the child
+            (
+                r#"[{"label": "r", "node_type": "root"},
+                    {"label": "a", "node_type": "string", "parent_id": 0}]"#,
+                1,
+            ),
+            // Root with a parent
+            (
+                r#"[{"label": "r", "node_type": "root", "parent_id": 0}]"#,
+                0,
+            ),
+        ];
+        for (nodes, id) in invalid {
+            match Tree::from_json(&snapshot(nodes)) {
+                Err(XtvError::InvalidNodeId(invalid)) => assert_eq!(invalid, id, "{}", nodes),
+                other => panic!("{} loaded as {:?}", nodes, other),
+            }
+        }
+        assert!(matches!(
+            Tree::from_json(r#"{"version": 1, "root_id": 0, "nodes": []}"#),
+            Err(XtvError::InvalidNodeId(0))
+        ));
+        // Through serde the error becomes a message
+        let error = serde_json::from_str::<Tree>(&snapshot(invalid[0].0)).unwrap_err();
+        assert!(error.to_string().contains("Invalid node ID: 1"));
+    }
+}
@@ -0,0 +1,200 @@
+//! Sorting the children of a node for display.
+//!
+//! Labels and values are compared in [natural order](crate::util): runs of
+//! digits compare as numbers, so `[2]` comes before `[10]` and `item9`
+//! before `item10`. Values that are both numbers compare numerically,
+//! including decimals and negative numbers.
+//!
+//! A leading `@attributes` node stays first, since it belongs to its element
+//! rather than being one of the sorted children.
+
+use crate::tree::{TreeNode, TreeVariant};
+use crate::util::natural_cmp;
+use std::cmp::Ordering;
+
+/// What children are ordered by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SortKey {
+    /// The child's label (object key, element name, array index)
+    Key,
+    /// The child's value; children without one come last, ordered by label
+    Value,
+    /// The value of the child's own child (or attribute) with this label,
+    /// as when ordering an array of objects by one of their fields
+    Field(String),
+}
+
+/// The children of `node_id` ordered by `key`, descending if `reverse`.
+///
+/// The sort is stable, so children that compare equal keep their order.
+/// Values are read with [`TreeVariant::get_node_blocking`], labels alone come
+/// from [`TreeVariant::get_label`] and need no disk reads in streaming trees.
+pub fn sorted_children(
+    tree: &TreeVariant,
+    node_id: usize,
+    key: &SortKey,
+    reverse: bool,
+) -> Vec<usize> {
+    let (pinned, children) = split_attributes(tree, node_id);
+    let mut keyed: Vec<(usize, String, Option<String>)> = children
+        .into_iter()
+        .map(|id| {
+            let label = tree.get_label(id).unwrap_or_default();
+            let value = match key {
+                SortKey::Key => None,
+                SortKey::Value => tree
+                    .get_node_blocking(id)
+                    .and_then(|node| node_value(&node)),
+                SortKey::Field(field) => field_value(tree, id, field),
+            };
+            (id, label, value)
+        })
+        .collect();
+
+    keyed.sort_by(|(_, a_label, a_value), (_, b_label, b_value)| {
+        let by_label = || natural_cmp(a_label, b_label);
+        let ordering = match key {
+            SortKey::Key => by_label(),
+            SortKey::Value | SortKey::Field(_) => match (a_value, b_value) {
+                (Some(a), Some(b)) => value_cmp(a, b).then_with(by_label),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => by_label(),
+            },
+        };
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    pinned
+        .into_iter()
+        .chain(keyed.into_iter().map(|(id, _, _)| id))
+        .collect()
+}
+
+/// The children of `node_id` in reverse order
+pub fn reversed_children(tree: &TreeVariant, node_id: usize) -> Vec<usize> {
+    let (pinned, mut children) = split_attributes(tree, node_id);
+    children.reverse();
+    pinned.into_iter().chain(children).collect()
+}
+
+/// A leading `@attributes` child, and the other children
+fn split_attributes(tree: &TreeVariant, node_id: usize) -> (Option<usize>, Vec<usize>) {
+    let mut children = tree.get_children(node_id);
+    let pinned = children.first().copied().filter(|&id| {
+        tree.get_node(id)
+            .is_some_and(|node| node.is_virtual_attributes())
+    });
+    if pinned.is_some() {
+        children.remove(0);
+    }
+    (pinned, children)
+}
+
+/// The value of a leaf: an attribute's value or a text node's content
+pub(crate) fn node_value(node: &TreeNode) -> Option<String> {
+    let key = if node.is_attribute() {
+        "value"
+    } else if matches!(node.node_type.as_str(), "text" | "comment" | "doctype") {
+        "content"
+    } else {
+        return None;
+    };
+    node.attributes
+        .iter()
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.clone())
+}
+
+/// The value of the child labelled `field`, looking through `@attributes`
+/// too, or the text of a child element such as `<name>x</name>`
+fn field_value(tree: &TreeVariant, node_id: usize, field: &str) -> Option<String> {
+    let mut candidates = tree.get_children(node_id);
+    if let Some(&first) = candidates.first()
+        && tree
+            .get_node(first)
+            .is_some_and(|node| node.is_virtual_attributes())
+    {
+        candidates.extend(tree.get_children(first));
+    }
+    let id = candidates
+        .into_iter()
+        .find(|&id| tree.get_label(id).as_deref() == Some(field))?;
+    let node = tree.get_node_blocking(id)?;
+    node_value(&node).or_else(|| {
+        // An element holding only text stands for that text
+        let children = tree.get_children(id);
+        match children.as_slice() {
+            [only] => tree
+                .get_node_blocking(*only)
+                .filter(|child| child.node_type == "text")
+                .and_then(|child| node_value(&child)),
+            _ => None,
+        }
+    })
+}
+
+/// Compare two values, numerically if both are numbers
+pub(crate) fn value_cmp(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => x.total_cmp(&y),
+        _ => natural_cmp(a, b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser, xml::XmlParser};
+
+    fn labels(tree: &TreeVariant, ids: &[usize]) -> Vec<String> {
+        ids.iter().filter_map(|&id| tree.get_label(id)).collect()
+    }
+
+    #[test]
+    fn test_sort_by_key_and_value() {
+        let json = r#"{"b": 10, "c": "x", "a": 9.5, "d": {"e": 1}, "e": -3}"#;
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        let root = tree.root_id();
+
+        let by_key = sorted_children(&tree, root, &SortKey::Key, false);
+        assert_eq!(labels(&tree, &by_key), ["a", "b", "c", "d", "e"]);
+        let by_value = sorted_children(&tree, root, &SortKey::Value, false);
+        assert_eq!(labels(&tree, &by_value), ["e", "a", "b", "c", "d"]);
+        let descending = sorted_children(&tree, root, &SortKey::Value, true);
+        assert_eq!(labels(&tree, &descending), ["d", "c", "b", "a", "e"]);
+    }
+
+    #[test]
+    fn test_sort_by_field() {
+        let json = r#"[{"name": "b", "age": 30}, {"name": "c"}, {"name": "a", "age": 4}]"#;
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        let root = tree.root_id();
+        let name = |id| field_value(&tree, id, "name").unwrap();
+
+        let by_age = sorted_children(&tree, root, &SortKey::Field("age".into()), false);
+        let names: Vec<String> = by_age.iter().map(|&id| name(id)).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_xml_attributes_stay_first() {
+        let xml =
+            r#"<list kind="x"><item id="2"><n>b</n></item><item id="10"><n>a</n></item></list>"#;
+        let tree = TreeVariant::from(XmlParser.parse(xml).unwrap());
+        let list = tree.get_children(tree.root_id())[0];
+        let children = tree.get_children(list);
+
+        let reversed = reversed_children(&tree, list);
+        assert_eq!(reversed, [children[0], children[2], children[1]]);
+
+        let by_id = sorted_children(&tree, list, &SortKey::Field("id".into()), true);
+        assert_eq!(by_id, [children[0], children[2], children[1]]);
+        let by_text = sorted_children(&tree, list, &SortKey::Field("n".into()), false);
+        assert_eq!(by_text, [children[0], children[2], children[1]]);
+    }
+}
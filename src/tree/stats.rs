@@ -0,0 +1,492 @@
+//! Statistics about the subtree under a node, gathered on a worker thread.
+//!
+//! The walk counts descendants by type, the depth they reach and the distinct
+//! keys they use, adds up the bytes of their values, and summarizes the
+//! numbers among the node's own children. Trees held in memory also measure
+//! the subtree written as JSON. Like a search it never stalls the caller and
+//! stops as soon as its [`StatsTask`] is cancelled or dropped.
+//!
+//! [`TreeStats`] instead describe a whole tree at once, including how much
+//! memory it takes, see [`Tree::stats`].
+
+use crate::interrupt;
+use crate::tree::{Tree, TreeNode, TreeVariant, export};
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, channel};
+use std::thread;
+
+/// What a walk found below a node
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeStats {
+    /// Number of nodes below the node, not counting the node itself
+    pub descendants: usize,
+    /// Descendants by node type
+    pub by_type: BTreeMap<String, usize>,
+    /// Levels below the node that the deepest descendant is at
+    pub max_depth: usize,
+    /// Distinct object keys, element names and attribute names; array
+    /// indices don't count
+    pub distinct_keys: usize,
+    /// Total bytes of the values of leaves and text nodes, unless they were
+    /// left on disk
+    pub value_bytes: Option<u64>,
+    /// Values on disk that were not read, see [`TreeVariant::stats`](crate::tree::TreeVariant::stats)
+    pub unread_values: usize,
+    /// Bytes of the subtree as compact JSON, see [`export::to_json`]; only
+    /// measured for trees held in memory
+    pub json_bytes: Option<u64>,
+    /// The numbers among the node's children, as in an array of numbers
+    pub numbers: Option<NumberStats>,
+}
+
+/// Summary of numeric values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// Size and shape of a whole tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeStats {
+    /// Number of nodes, including the root
+    pub nodes: usize,
+    /// Nodes by node type
+    pub by_type: BTreeMap<String, usize>,
+    /// Levels below the root that the deepest node is at
+    pub max_depth: usize,
+    /// Total bytes of all labels
+    pub label_bytes: usize,
+    /// Total bytes of all attribute keys and values, unless they were left
+    /// on disk
+    pub attribute_bytes: Option<usize>,
+    pub memory: MemoryUsage,
+}
+
+/// Approximate heap usage of a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryUsage {
+    /// All nodes are held in memory
+    InMemory { heap_bytes: usize },
+    /// Only an index is held, plus a cache of recently read nodes
+    Streaming {
+        index_bytes: usize,
+        cache_bytes: usize,
+        cached_nodes: usize,
+    },
+}
+
+impl MemoryUsage {
+    /// Bytes in use altogether
+    pub fn total_bytes(&self) -> usize {
+        match *self {
+            MemoryUsage::InMemory { heap_bytes } => heap_bytes,
+            MemoryUsage::Streaming {
+                index_bytes,
+                cache_bytes,
+                ..
+            } => index_bytes + cache_bytes,
+        }
+    }
+}
+
+impl Tree {
+    /// Counts the nodes of the tree by type, the depth they reach and the
+    /// bytes of their labels and attributes, and estimates the memory the
+    /// tree takes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xtv::tree::{MemoryUsage, Tree, TreeNode};
+    ///
+    /// let mut tree = Tree::new(TreeNode::new("root", "object"));
+    /// let a = tree.add_child_node(0, TreeNode::new("a", "object"));
+    /// let mut b = TreeNode::new("b", "attribute");
+    /// b.add_attribute("value", "text");
+    /// tree.add_child_node(a, b);
+    ///
+    /// let stats = tree.stats();
+    /// assert_eq!(stats.nodes, 3);
+    /// assert_eq!(stats.by_type["object"], 2);
+    /// assert_eq!(stats.max_depth, 2);
+    /// assert_eq!(stats.label_bytes, "root".len() + "a".len() + "b".len());
+    /// assert_eq!(stats.attribute_bytes, Some("value".len() + "text".len()));
+    /// assert!(matches!(stats.memory, MemoryUsage::InMemory { .. }));
+    /// ```
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            nodes: 0,
+            by_type: BTreeMap::new(),
+            max_depth: 0,
+            label_bytes: 0,
+            attribute_bytes: Some(0),
+            memory: self.memory_usage(),
+        };
+        let mut attribute_bytes = 0;
+        for (_, node, depth) in self.iter_dfs() {
+            stats.nodes += 1;
+            *stats.by_type.entry(node.node_type.to_string()).or_default() += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.label_bytes += node.label.len();
+            attribute_bytes += node
+                .attributes
+                .iter()
+                .map(|attr| attr.key.len() + attr.value.len())
+                .sum::<usize>();
+        }
+        stats.attribute_bytes = Some(attribute_bytes);
+        stats
+    }
+
+    /// Approximate heap usage of the nodes and remembered orders
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let nodes: usize = self.nodes.iter().map(TreeNode::heap_bytes).sum();
+        let orders: usize = self
+            .original_children
+            .values()
+            .map(|order| {
+                std::mem::size_of::<(usize, Vec<usize>)>()
+                    + order.capacity() * std::mem::size_of::<usize>()
+            })
+            .sum();
+        MemoryUsage::InMemory {
+            heap_bytes: self.nodes.capacity() * std::mem::size_of::<TreeNode>() + nodes + orders,
+        }
+    }
+}
+
+/// Handle to a statistics walk running on a worker thread.
+///
+/// Dropping the handle cancels the walk.
+#[derive(Debug)]
+pub struct StatsTask {
+    cancel: Arc<AtomicBool>,
+    visited: Arc<AtomicUsize>,
+    result: Receiver<NodeStats>,
+}
+
+impl StatsTask {
+    /// Runs `walk` on a worker thread, which visits nodes through a
+    /// [`StatsWalker`] and returns `false` if it was cancelled.
+    pub(crate) fn spawn(walk: impl FnOnce(&mut StatsWalker) -> bool + Send + 'static) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let visited = Arc::new(AtomicUsize::new(0));
+        let (sender, result) = channel();
+
+        let mut walker = StatsWalker {
+            cancel: Arc::clone(&cancel),
+            visited: Arc::clone(&visited),
+            stats: NodeStats::default(),
+            keys: HashSet::new(),
+            value_bytes: 0,
+            number_sum: 0.0,
+        };
+        thread::spawn(move || {
+            if walk(&mut walker) {
+                let _ = sender.send(walker.finish());
+            }
+        });
+
+        Self {
+            cancel,
+            visited,
+            result,
+        }
+    }
+
+    /// Number of nodes and values visited so far
+    pub fn visited(&self) -> usize {
+        self.visited.load(Ordering::Relaxed)
+    }
+
+    /// Stops the walk; it will not report a result.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Takes the result without blocking, if the walk is done.
+    pub fn poll(&self) -> Option<NodeStats> {
+        self.result.try_recv().ok()
+    }
+
+    /// Waits for the walk to finish, returning `None` if it was cancelled.
+    pub fn wait(&self) -> Option<NodeStats> {
+        self.result.recv().ok()
+    }
+}
+
+impl Drop for StatsTask {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// The worker's side of a statistics walk
+pub(crate) struct StatsWalker {
+    cancel: Arc<AtomicBool>,
+    visited: Arc<AtomicUsize>,
+    stats: NodeStats,
+    keys: HashSet<String>,
+    value_bytes: u64,
+    number_sum: f64,
+}
+
+impl StatsWalker {
+    /// Whether the walk may go on, i.e. was neither cancelled nor
+    /// interrupted.
+    pub(crate) fn proceed(&self) -> bool {
+        !self.cancel.load(Ordering::Relaxed) && !interrupt::is_interrupted()
+    }
+
+    /// Records a node `depth` levels below the walk's root, which is at
+    /// depth 0 and not counted.
+    pub(crate) fn visit(&mut self, depth: usize, node_type: &str, key: Option<&str>) {
+        self.visited.fetch_add(1, Ordering::Relaxed);
+        if depth == 0 {
+            return;
+        }
+        self.stats.descendants += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        match self.stats.by_type.get_mut(node_type) {
+            Some(count) => *count += 1,
+            None => {
+                self.stats.by_type.insert(node_type.to_string(), 1);
+            }
+        }
+        if let Some(key) = key
+            && !self.keys.contains(key)
+        {
+            self.keys.insert(key.to_string());
+        }
+    }
+
+    /// Records the value of a node `depth` levels below the root
+    pub(crate) fn value(&mut self, value: &str, depth: usize) {
+        self.visited.fetch_add(1, Ordering::Relaxed);
+        self.value_bytes += value.len() as u64;
+        if depth != 1 {
+            return;
+        }
+        let Ok(number) = value.trim().parse::<f64>() else {
+            return;
+        };
+        if !number.is_finite() {
+            return;
+        }
+        self.number_sum += number;
+        let numbers = self.stats.numbers.get_or_insert(NumberStats {
+            count: 0,
+            min: number,
+            max: number,
+            mean: 0.0,
+        });
+        numbers.count += 1;
+        numbers.min = numbers.min.min(number);
+        numbers.max = numbers.max.max(number);
+    }
+
+    /// Records values left on disk
+    pub(crate) fn skip_values(&mut self, count: usize) {
+        self.stats.unread_values += count;
+    }
+
+    /// Records the size of the subtree as JSON
+    pub(crate) fn json_bytes(&mut self, bytes: u64) {
+        self.stats.json_bytes = Some(bytes);
+    }
+
+    fn finish(mut self) -> NodeStats {
+        self.stats.distinct_keys = self.keys.len();
+        if let Some(numbers) = &mut self.stats.numbers {
+            numbers.mean = self.number_sum / numbers.count as f64;
+        }
+        self.stats.value_bytes = (self.stats.unread_values == 0).then_some(self.value_bytes);
+        self.stats
+    }
+}
+
+/// The key a node is addressed by, unless it is an array index or has no
+/// name of its own like text and `@attributes`
+fn node_key(node: &TreeNode) -> Option<&str> {
+    if node.is_virtual_attributes()
+        || matches!(node.node_type.as_str(), "text" | "comment" | "doctype")
+    {
+        return None;
+    }
+    let label = node.label.as_str();
+    let index = label
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()));
+    (!index).then_some(label)
+}
+
+/// The value of a leaf or the content of a text node
+pub(crate) fn leaf_value(node: &TreeNode) -> Option<&str> {
+    let key = if node.is_attribute() {
+        "value"
+    } else if matches!(node.node_type.as_str(), "text" | "comment" | "doctype") {
+        "content"
+    } else {
+        return None;
+    };
+    node.attributes
+        .iter()
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.as_str())
+}
+
+/// Gathers statistics about an in-memory tree below `root`
+pub(crate) fn subtree_stats(tree: Arc<Tree>, root: usize) -> StatsTask {
+    StatsTask::spawn(move |walker| {
+        let mut stack = vec![(root, 0)];
+        while let Some((node_id, depth)) = stack.pop() {
+            let Some(node) = tree.get_node(node_id) else {
+                continue;
+            };
+            if !walker.proceed() {
+                return false;
+            }
+            walker.visit(depth, &node.node_type, node_key(node));
+            if let Some(value) = leaf_value(node) {
+                walker.value(value, depth);
+            }
+            stack.extend(node.children.iter().map(|&child| (child, depth + 1)));
+        }
+        if let Some(bytes) = json_size(&TreeVariant::InMemory(tree), root) {
+            walker.json_bytes(bytes);
+        }
+        walker.proceed()
+    })
+}
+
+/// Bytes of the subtree under `root` as compact JSON, unless it is too deep
+/// to convert completely
+fn json_size(tree: &TreeVariant, root: usize) -> Option<u64> {
+    let export = export::to_json(tree, root).filter(|export| !export.truncated)?;
+    let mut counter = ByteCounter(0);
+    serde_json::to_writer(&mut counter, &export.value).ok()?;
+    Some(counter.0)
+}
+
+/// Counts the bytes written to it
+struct ByteCounter(u64);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser, xml::XmlParser};
+
+    fn stats(tree: Tree, root: impl Fn(&Tree) -> usize) -> NodeStats {
+        let root = root(&tree);
+        subtree_stats(Arc::new(tree), root).wait().unwrap()
+    }
+
+    #[test]
+    fn test_json_stats() {
+        let json = r#"{"a": [1, 2.5, -0.5, "x"], "b": {"a": "hello", "c": null}}"#;
+        let tree = JsonParser.parse(json).unwrap();
+
+        let all = stats(tree.clone(), |tree| tree.root_id());
+        assert_eq!(all.descendants, 8);
+        assert_eq!(all.max_depth, 2);
+        assert_eq!(all.by_type["attribute"], 6);
+        assert_eq!(all.by_type["array"], 1);
+        // a, b and c
+        assert_eq!(all.distinct_keys, 3);
+        // "1", "2.5", "-0.5", "x", "hello" and "null"
+        assert_eq!(all.value_bytes, Some(1 + 3 + 4 + 1 + 5 + 4));
+        assert_eq!(all.unread_values, 0);
+        assert_eq!(all.numbers, None);
+        assert_eq!(
+            all.json_bytes,
+            Some(r#"{"a":[1,2.5,-0.5,"x"],"b":{"a":"hello","c":null}}"#.len() as u64)
+        );
+
+        let array = stats(tree, |tree| tree.get_children(tree.root_id())[0]);
+        assert_eq!(array.descendants, 4);
+        assert_eq!(array.max_depth, 1);
+        assert_eq!(array.distinct_keys, 0);
+        assert_eq!(
+            array.numbers,
+            Some(NumberStats {
+                count: 3,
+                min: -0.5,
+                max: 2.5,
+                mean: 1.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_xml_stats() {
+        let xml = r#"<a id="1"><b>text</b><b/></a>"#;
+        let stats = stats(XmlParser.parse(xml).unwrap(), |tree| tree.root_id());
+        assert_eq!(stats.by_type["element"], 3);
+        assert_eq!(stats.by_type["text"], 1);
+        // a, id and b
+        assert_eq!(stats.distinct_keys, 3);
+        assert_eq!(stats.value_bytes, Some(5));
+    }
+
+    #[test]
+    fn test_tree_stats_of_parsed_files() {
+        let json = JsonParser
+            .parse(r#"{"name": "xtv", "tags": ["a", "bc"]}"#)
+            .unwrap()
+            .stats();
+        assert_eq!(json.nodes, 5);
+        assert_eq!(json.by_type["attribute"], 3);
+        assert_eq!(json.max_depth, 2);
+        // root, name, tags, [0] and [1]
+        assert_eq!(json.label_bytes, 4 + 4 + 4 + 3 + 3);
+        // Each leaf holds its value, the array its size
+        assert_eq!(
+            json.attribute_bytes,
+            Some(3 * "value".len() + 3 + 1 + 2 + "size".len() + "2 items".len())
+        );
+
+        let xml = XmlParser
+            .parse(r#"<a id="1"><b>text</b></a>"#)
+            .unwrap()
+            .stats();
+        assert_eq!(xml.by_type["element"], 2);
+        assert_eq!(xml.by_type["text"], 1);
+
+        let MemoryUsage::InMemory { heap_bytes } = xml.memory else {
+            panic!("{:?}", xml.memory);
+        };
+        // At least the nodes themselves and all their strings
+        let strings = xml.label_bytes + xml.attribute_bytes.unwrap();
+        assert!(heap_bytes >= xml.nodes * std::mem::size_of::<TreeNode>() + strings);
+    }
+
+    #[test]
+    fn test_cancelled_walk_reports_nothing() {
+        let task = StatsTask::spawn(|walker| {
+            while walker.proceed() {
+                std::thread::yield_now();
+            }
+            false
+        });
+        task.cancel();
+        assert_eq!(task.wait(), None);
+    }
+}
@@ -1,48 +1,196 @@
-use crate::tree::TreeNode;
+use crate::error::{Result, XtvError};
+use crate::parser::ldif::decode::ValueDecoders;
+use crate::parser::ldif::{EntryBlock, attribute_type};
+use crate::tree::search::{NodeMatcher, PathTracker, SearchTask};
+use crate::tree::stats::{self, MemoryUsage, StatsTask, TreeStats};
+use crate::tree::{Tree, TreeNode};
+use graft::Grafts;
+use loader::{BackgroundLoader, LoadResult, NodeLoader, reads_disk};
 use lru::LruCache;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime};
+
+mod graft;
+mod loader;
 
 /// LRU cache size for streaming tree nodes
 /// Tuned for typical navigation patterns - holds approximately 250KB-1MB of nodes
 const STREAMING_CACHE_SIZE: usize = 1000;
 
+/// Default number of sibling and child entries loaded speculatively on a cache miss
+/// Neighbouring entries are usually adjacent on disk, so one sequential read covers them
+pub const DEFAULT_PREFETCH_WINDOW: usize = 50;
+
+/// Maximum number of lines read for a single LDIF entry when loading from disk
+/// Matches the index builder's limits (100 attributes × 1000 values) with headroom
+const MAX_ENTRY_LINES: usize = 200_000;
+
+/// Size and modification time of the file an index was built from
+///
+/// Offsets in the index are only valid while the file is unchanged, so the
+/// stamp taken when the tree is created is compared against the file on disk
+/// before any attribute data is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    /// File size in bytes
+    pub len: u64,
+    /// Last modification time, if the platform reports one
+    pub modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    /// Takes the stamp of the file at `path`.
+    pub fn of(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// The file backing a streaming tree and whether it changed since indexing
+///
+/// Shared between the tree and its loaders, which may live on other threads.
+#[derive(Debug)]
+struct FileWatch {
+    /// Path to the LDIF file
+    path: PathBuf,
+    /// Stamp of the file when the tree was created
+    stamp: FileStamp,
+    /// Latched once the file is seen to differ from `stamp`
+    stale: AtomicBool,
+}
+
+impl FileWatch {
+    /// Checks whether the file differs from its stamp, latching the result.
+    fn is_stale(&self) -> bool {
+        if self.stale.load(Ordering::Relaxed) {
+            return true;
+        }
+        let changed = FileStamp::of(&self.path).map_or(true, |stamp| stamp != self.stamp);
+        if changed {
+            self.stale.store(true, Ordering::Relaxed);
+        }
+        changed
+    }
+}
+
+/// Handle to a string stored in a [`StringPool`]
+pub type Symbol = u32;
+
+/// Deduplicating string storage for the streaming index.
+///
+/// Attribute keys (`objectClass`, `mail`, ...) and the DN suffixes of entries
+/// whose parent is not part of the file repeat millions of times in large
+/// directory dumps. The pool stores each distinct string once and hands out
+/// compact [`Symbol`]s instead.
+///
+/// # Examples
+///
+/// ```
+/// use xtv::tree::streaming::StringPool;
+///
+/// let mut pool = StringPool::default();
+/// let a = pool.intern("objectClass");
+/// let b = pool.intern("objectClass");
+/// assert_eq!(a, b);
+/// assert_eq!(pool.resolve(a), "objectClass");
+/// assert_eq!(pool.len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct StringPool {
+    /// Interned strings, indexed by symbol
+    strings: Vec<Arc<str>>,
+    /// Reverse lookup sharing storage with `strings`
+    lookup: HashMap<Arc<str>, Symbol>,
+}
+
+impl StringPool {
+    /// Interns a string and returns its symbol.
+    ///
+    /// Returns the existing symbol if the string was interned before.
+    pub fn intern(&mut self, value: &str) -> Symbol {
+        if let Some(&symbol) = self.lookup.get(value) {
+            return symbol;
+        }
+        let symbol = self.strings.len() as Symbol;
+        let shared: Arc<str> = Arc::from(value);
+        self.strings.push(Arc::clone(&shared));
+        self.lookup.insert(shared, symbol);
+        symbol
+    }
+
+    /// Resolves a symbol back to its string.
+    ///
+    /// Returns an empty string for symbols that were not handed out by this pool.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        self.strings
+            .get(symbol as usize)
+            .map(|s| s.as_ref())
+            .unwrap_or("")
+    }
+
+    /// Gets the number of distinct strings in the pool.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Checks if the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Approximate heap usage of the pool in bytes.
+    pub fn memory_bytes(&self) -> usize {
+        let per_string = std::mem::size_of::<Arc<str>>() * 2 + std::mem::size_of::<Symbol>();
+        self.strings.iter().map(|s| s.len() + per_string).sum()
+    }
+}
+
 /// Type of node in the streaming index.
 ///
 /// The streaming mode needs to know node types at index-build time to support
-/// lazy loading without parsing the full tree structure into memory.
+/// lazy loading without parsing the full tree structure into memory. Only the
+/// data needed for labels and navigation is kept here; attribute values are
+/// re-read from the file when a node is loaded.
 ///
 /// # Variants
 ///
 /// - `Root`: The top-level root node of the tree
-/// - `Entry`: An LDIF entry with DN (Distinguished Name) and RDN (Relative DN)
+/// - `Entry`: An LDIF entry identified by its RDN (Relative DN)
 /// - `VirtualAttributes`: The `@attributes` container node
-/// - `Attribute`: An individual attribute with key-value pair
+/// - `Attribute`: A reference to one value of an entry's attribute
 #[derive(Debug, Clone, PartialEq)]
 pub enum NodeType {
     /// Root node of the tree
     Root,
-    /// LDIF entry node with distinguished name and relative distinguished name
+    /// LDIF entry node
+    ///
+    /// The full DN is the RDN joined with the parent entry's DN, or with
+    /// `suffix` when the parent entry is not part of the file.
     Entry {
-        /// Full Distinguished Name (e.g., "uid=alice,ou=people,dc=example,dc=com")
-        dn: String,
         /// Relative Distinguished Name (e.g., "uid=alice")
-        rdn: String,
+        rdn: Box<str>,
+        /// Interned parent DN for entries whose parent is not in the file
+        suffix: Option<Symbol>,
     },
     /// Virtual `@attributes` container node
     VirtualAttributes,
-    /// Individual attribute node with key and value
+    /// Individual attribute node, resolved against the entry's lines on load
     Attribute {
-        /// Attribute key/name
-        key: String,
-        /// Attribute value
-        value: String,
+        /// Interned attribute key/name
+        key: Symbol,
+        /// Position among the values of a multi-valued attribute (None if single-valued)
+        index: Option<u32>,
+        /// Whether the value is a URL reference (`key:< URL`)
+        url: bool,
     },
 }
-
 /// Entry in the LDIF streaming index.
 ///
 /// Each index entry represents a single node in the tree and contains just enough
@@ -50,18 +198,18 @@ pub enum NodeType {
 ///
 /// # Fields
 ///
-/// - `offset`: Byte position in the file where the LDIF entry begins
+/// - `offset`: Byte position in the file where the owning LDIF entry begins
 /// - `parent_id`: Parent node ID for O(1) parent lookup
 /// - `children`: List of child node IDs for tree traversal
 /// - `node_type`: Type information needed to reconstruct the node
 ///
 /// # Memory Usage
 ///
-/// Approximately 8 + 4 + (4 × child_count) bytes per entry on disk, plus the
-/// size of strings in the `NodeType` variant.
+/// A fixed-size record plus (8 × child_count) bytes per node. Entry nodes
+/// additionally own their RDN; all other strings live in the [`StringPool`].
 #[derive(Debug, Clone)]
 pub struct IndexEntry {
-    /// Byte offset in the file where this LDIF entry starts (only meaningful for Entry nodes)
+    /// Byte offset in the file where the LDIF entry owning this node starts
     pub offset: u64,
     /// Parent node ID (None for root)
     pub parent_id: Option<usize>,
@@ -100,19 +248,25 @@ impl IndexEntry {
 /// For a 20GB LDIF file with 1 million entries:
 /// - Each entry has ~5 attributes
 /// - Total nodes: 1M entries + 1M @attributes + 5M attributes = 7M nodes
-/// - Index size: ~50-100MB (compared to 20GB full data)
+/// - Index size: a fixed-size record per node plus one RDN per entry; attribute
+///   values are never held in the index
 ///
 /// # Structure
 ///
 /// - Node IDs are implicit (position in the vector)
 /// - Each entry knows its parent and children
 /// - Entries contain type info and file offset for lazy loading
+/// - Repeated strings (attribute keys, DN suffixes) are interned in a [`StringPool`]
 #[derive(Debug)]
 pub struct LdifIndex {
     /// Vector of index entries, indexed by node ID
     entries: Vec<IndexEntry>,
     /// Root node ID
     root_id: usize,
+    /// Interned attribute keys and DN suffixes
+    strings: StringPool,
+    /// Readable forms of attribute values, added when nodes are loaded
+    decoders: ValueDecoders,
 }
 
 impl LdifIndex {
@@ -125,9 +279,17 @@ impl LdifIndex {
         Self {
             entries: Vec::new(),
             root_id,
+            strings: StringPool::default(),
+            decoders: ValueDecoders::none(),
         }
     }
 
+    /// Decode attribute values with `decoders` when building nodes
+    pub fn with_decoders(mut self, decoders: ValueDecoders) -> Self {
+        self.decoders = decoders;
+        self
+    }
+
     /// Adds a new index entry and returns its ID.
     ///
     /// The entry ID is simply its position in the entries vector.
@@ -214,6 +376,107 @@ impl LdifIndex {
     pub fn root_id(&self) -> usize {
         self.root_id
     }
+
+    /// Gets the string pool holding interned keys and DN suffixes.
+    pub fn strings(&self) -> &StringPool {
+        &self.strings
+    }
+
+    /// Gets the string pool for interning while the index is built.
+    pub fn strings_mut(&mut self) -> &mut StringPool {
+        &mut self.strings
+    }
+
+    /// Builds the display label of an entry node from its RDN and optional suffix.
+    pub fn entry_label(&self, rdn: &str, suffix: Option<Symbol>) -> String {
+        match suffix {
+            Some(symbol) => format!("{},{}", rdn, self.strings.resolve(symbol)),
+            None => rdn.to_string(),
+        }
+    }
+
+    /// Builds the display label of a node from the index alone.
+    ///
+    /// Returns an empty string for IDs that are not in the index.
+    pub fn node_label(&self, id: usize) -> String {
+        let Some(entry) = self.get_entry(id) else {
+            return String::new();
+        };
+        match &entry.node_type {
+            NodeType::Root => "root".to_string(),
+            NodeType::Entry { rdn, suffix } => self.entry_label(rdn, *suffix),
+            NodeType::VirtualAttributes => "@attributes".to_string(),
+            NodeType::Attribute { key, index, .. } => {
+                let key = self.strings.resolve(*key);
+                match index {
+                    Some(i) => format!("{} [{}]", key, i),
+                    None => key.to_string(),
+                }
+            }
+        }
+    }
+
+    /// Reconstructs a TreeNode from the index and, for attributes, the parsed entry.
+    ///
+    /// Labels, types, and children come from the index. Attribute values are
+    /// looked up in the entry block that was read from disk, so they never have
+    /// to be kept in memory between loads.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The node ID being built
+    /// * `block` - The parsed LDIF entry owning the node (only used for attributes)
+    ///
+    /// # Returns
+    ///
+    /// * `Some(TreeNode)` - Successfully reconstructed node
+    /// * `None` - If the node doesn't exist in the index, or an attribute's value
+    ///   could not be found in the entry read from disk
+    pub(crate) fn build_node(&self, id: usize, block: Option<&EntryBlock>) -> Option<TreeNode> {
+        let entry = self.get_entry(id)?;
+
+        let label = self.node_label(id);
+        let mut node = match &entry.node_type {
+            NodeType::Root => TreeNode::new(label, "root"),
+            NodeType::Entry { .. } => TreeNode::new(label, "entry"),
+            NodeType::VirtualAttributes => TreeNode::new(label, TreeNode::VIRTUAL_ATTRIBUTES_TYPE),
+            NodeType::Attribute { key, index, url } => {
+                let key = self.strings().resolve(*key);
+                let value = block?.value(key, index.unwrap_or(0) as usize)?;
+                let mut node = TreeNode::new(label, attribute_type(*url));
+                node.add_attribute("value", value);
+                if let Some(decoded) = self.decoders.decode(key, value) {
+                    node.add_attribute("decoded", &decoded);
+                }
+                node
+            }
+        };
+
+        // Add children and parent references from index
+        node.children = entry.children.clone();
+        node.parent_id = entry.parent_id;
+
+        Some(node)
+    }
+
+    /// Approximate heap usage of the index in bytes.
+    ///
+    /// Counts the entry records, child lists, owned RDNs, and the string pool.
+    pub fn memory_bytes(&self) -> usize {
+        let records = self.entries.capacity() * std::mem::size_of::<IndexEntry>();
+        let owned: usize = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let rdn = match &entry.node_type {
+                    NodeType::Entry { rdn, .. } => rdn.len(),
+                    _ => 0,
+                };
+                entry.children.capacity() * std::mem::size_of::<usize>() + rdn
+            })
+            .sum();
+        records + owned + self.strings.memory_bytes()
+    }
 }
 
 /// Streaming tree that loads nodes on-demand from disk.
@@ -233,7 +496,11 @@ impl LdifIndex {
 /// 3. **LRU Caching**: Recently accessed nodes are kept in an LRU cache
 ///    ([`STREAMING_CACHE_SIZE`] = 1000 nodes) to avoid repeated disk I/O.
 ///
-/// 4. **Arc Sharing**: Cached nodes are wrapped in `Arc<TreeNode>` to enable cheap
+/// 4. **Prefetching**: On a cache miss, the following siblings and the first-level
+///    children of the requested node (up to the prefetch window) are loaded in the
+///    same sequential pass, since navigation usually visits them next.
+///
+/// 5. **Arc Sharing**: Cached nodes are wrapped in `Arc<TreeNode>` to enable cheap
 ///    reference counting instead of cloning on every access.
 ///
 /// # Performance Characteristics
@@ -245,16 +512,18 @@ impl LdifIndex {
 ///
 /// # Performance Notes
 ///
-/// This implementation uses blocking I/O operations. On network-mounted filesystems
+/// By default nodes are loaded with blocking I/O. On network-mounted filesystems
 /// (NFS, SMB, sshfs) or slow/unresponsive storage devices, operations may block
-/// indefinitely without timeout. For best performance and reliability, use files
-/// on local storage.
+/// indefinitely without timeout. Interactive callers should enable
+/// [`with_background_loading`](StreamingTree::with_background_loading), which
+/// moves disk reads to a worker thread and returns placeholders until the data
+/// arrives.
 ///
 /// # Known Limitations
 ///
-/// - No timeout support for I/O operations (would require async/tokio)
-/// - File operations may hang on unresponsive filesystems
-/// - Errors are reported to stderr but UI remains blocked during I/O
+/// - No timeout support for I/O operations; a hung read stalls the worker thread
+/// - Blocking accessors ([`get_node_blocking`](StreamingTree::get_node_blocking))
+///   still wait for the disk
 /// - Currently only supports LDIF format
 ///
 /// # Examples
@@ -270,24 +539,43 @@ impl LdifIndex {
 /// let root = tree.get_node(tree.root_id());
 /// ```
 pub struct StreamingTree {
-    /// Path to the LDIF file
-    file_path: PathBuf,
-    /// In-memory index of all nodes
-    index: LdifIndex,
+    /// The LDIF file and its change detection state
+    watch: Arc<FileWatch>,
+    /// In-memory index of all nodes, shared with the background loader
+    index: Arc<LdifIndex>,
     /// LRU cache for recently accessed nodes
     /// Uses Arc to avoid expensive clones on cache hits
     cache: std::cell::RefCell<LruCache<usize, Arc<TreeNode>>>,
-    /// Persistent file reader to avoid reopening file on every node load
-    reader: std::cell::RefCell<BufReader<File>>,
+    /// Loader with a persistent file reader for blocking loads
+    loader: std::cell::RefCell<NodeLoader>,
+    /// Worker thread for non-blocking loads, if enabled
+    background: Option<BackgroundLoader>,
+    /// Maximum number of siblings and children to prefetch on a cache miss
+    prefetch_window: usize,
+    /// Warnings and load errors not yet shown to the user
+    messages: std::cell::RefCell<Vec<String>>,
+    /// Children of reordered nodes in their new order; the index keeps the
+    /// file order
+    child_order: HashMap<usize, Vec<usize>>,
+    /// Nodes grafted below others, shared with searches
+    grafts: Arc<Grafts>,
+    /// Cache hits since the last miss, for the debug log
+    cache_hits: std::cell::Cell<usize>,
 }
 
 impl std::fmt::Debug for StreamingTree {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("StreamingTree")
-            .field("file_path", &self.file_path)
+            .field("watch", &self.watch)
             .field("index", &self.index)
             .field("cache", &self.cache)
-            .field("reader", &"<BufReader<File>>")
+            .field("loader", &"<NodeLoader>")
+            .field("background", &self.background.is_some())
+            .field("prefetch_window", &self.prefetch_window)
+            .field("messages", &self.messages)
+            .field("child_order", &self.child_order)
+            .field("grafts", &self.grafts)
+            .field("cache_hits", &self.cache_hits)
             .finish()
     }
 }
@@ -322,23 +610,124 @@ impl StreamingTree {
         // Cache size: holds recently accessed nodes for fast repeat access
         let cache_size = NonZeroUsize::new(STREAMING_CACHE_SIZE).unwrap();
 
+        let watch = Arc::new(FileWatch {
+            stamp: FileStamp::of(&file_path)?,
+            path: file_path,
+            stale: AtomicBool::new(false),
+        });
+        let index = Arc::new(index);
+        let grafts = Arc::new(Grafts::new(index.len()));
+
         // Open the file once and keep a persistent reader
         // Note: This may block on network filesystems without timeout
-        let file = File::open(&file_path).map_err(|e| {
-            eprintln!("Error: Failed to open file {:?}: {}", file_path, e);
-            eprintln!("Hint: Ensure the file exists and is on a responsive filesystem.");
-            e
-        })?;
-        let reader = BufReader::new(file);
+        let loader = NodeLoader::open(Arc::clone(&index), Arc::clone(&watch))?;
 
         Ok(Self {
-            file_path,
+            watch,
             index,
             cache: std::cell::RefCell::new(LruCache::new(cache_size)),
-            reader: std::cell::RefCell::new(reader),
+            loader: std::cell::RefCell::new(loader),
+            background: None,
+            prefetch_window: DEFAULT_PREFETCH_WINDOW,
+            messages: std::cell::RefCell::new(Vec::new()),
+            child_order: HashMap::new(),
+            grafts,
+            cache_hits: std::cell::Cell::new(0),
         })
     }
 
+    /// Queues warnings for the user, e.g. from building the index.
+    ///
+    /// The tree never prints to stderr because the TUI owns the terminal;
+    /// callers collect queued messages with [`take_messages`](StreamingTree::take_messages).
+    pub fn report(&self, messages: impl IntoIterator<Item = String>) {
+        self.messages.borrow_mut().extend(messages);
+    }
+
+    /// Takes all warnings and load errors queued since the last call.
+    pub fn take_messages(&self) -> Vec<String> {
+        std::mem::take(&mut *self.messages.borrow_mut())
+    }
+
+    /// Loads attribute values on a worker thread (builder pattern).
+    ///
+    /// With background loading, [`get_node`](StreamingTree::get_node) never
+    /// blocks on the disk: on a cache miss for an attribute it returns a
+    /// placeholder node of type [`TreeNode::LOADING_TYPE`] and queues the load.
+    /// Call [`poll_loaded`](StreamingTree::poll_loaded) regularly to move
+    /// finished loads into the cache. Once a load has failed, `get_node`
+    /// returns `XtvError::NodeLoad` for the node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the worker's file handle cannot be opened.
+    pub fn with_background_loading(mut self) -> std::io::Result<Self> {
+        let loader = NodeLoader::open(Arc::clone(&self.index), Arc::clone(&self.watch))?;
+        self.background = Some(BackgroundLoader::spawn(loader));
+        Ok(self)
+    }
+
+    /// Sets the prefetch window (builder pattern).
+    ///
+    /// The window is the maximum number of following siblings and, separately,
+    /// first-level children that are loaded alongside a node on a cache miss.
+    /// A window of 0 disables prefetching. The effective window is capped below
+    /// the cache size so prefetching can never evict the requested node.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - Number of neighbouring nodes to prefetch
+    pub fn with_prefetch_window(mut self, window: usize) -> Self {
+        self.prefetch_window = window;
+        self
+    }
+
+    /// Checks whether a node is currently held in the LRU cache.
+    ///
+    /// This does not update the node's recency.
+    pub fn is_cached(&self, id: usize) -> bool {
+        self.cache.borrow().contains(&id)
+    }
+
+    /// Gets the path of the file backing this tree.
+    pub fn file_path(&self) -> &Path {
+        &self.watch.path
+    }
+
+    /// Checks whether the file changed on disk since the index was built.
+    ///
+    /// Compares the file's current size and modification time with the stamp
+    /// taken at creation. A file that can no longer be read counts as changed.
+    /// Once a change is seen the tree stays stale; attribute values are no
+    /// longer read from disk because the index offsets may point at other data.
+    ///
+    /// # Performance
+    ///
+    /// Performs one `stat` call until the tree becomes stale, then none.
+    pub fn is_stale(&self) -> bool {
+        self.watch.is_stale()
+    }
+
+    /// Rebuilds the index from the current file contents.
+    ///
+    /// Returns a fresh tree with the same prefetch window and loading mode.
+    /// Node IDs of the new tree are unrelated to the IDs of this one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can no longer be read or indexed.
+    pub fn reload(&self) -> crate::error::Result<Self> {
+        let tree = crate::parser::ldif::build_ldif_index_quiet(
+            &self.watch.path,
+            self.index.decoders.clone(),
+        )?
+        .with_prefetch_window(self.prefetch_window);
+        if self.background.is_some() {
+            return Ok(tree.with_background_loading()?);
+        }
+        Ok(tree)
+    }
+
     /// Gets the root node ID.
     ///
     /// # Returns
@@ -350,8 +739,11 @@ impl StreamingTree {
 
     /// Gets a node by ID, loading from disk if not in cache.
     ///
-    /// This method first checks the LRU cache. On cache miss, it performs blocking
-    /// I/O to load the node from disk, then adds it to the cache.
+    /// Grafted nodes (see [`graft`](StreamingTree::graft)) are always in
+    /// memory. For other nodes this method first checks the LRU cache. On cache miss, it loads the node
+    /// together with its neighbours (see [`with_prefetch_window`](StreamingTree::with_prefetch_window)).
+    /// With [background loading](StreamingTree::with_background_loading) an
+    /// attribute that needs disk I/O is returned as a placeholder instead.
     ///
     /// # Arguments
     ///
@@ -359,38 +751,220 @@ impl StreamingTree {
     ///
     /// # Returns
     ///
-    /// * `Some(Arc<TreeNode>)` - The node wrapped in Arc for cheap cloning
-    /// * `None` - If the node doesn't exist or failed to load from disk
+    /// * `Ok(Arc<TreeNode>)` - The node wrapped in Arc for cheap cloning
+    /// * `Err(XtvError::NodeLoad)` - If the node failed to load, e.g. because
+    ///   the file changed. The failure is also queued in
+    ///   [`take_messages`](StreamingTree::take_messages) and remembered, so
+    ///   later calls fail without touching the disk
+    /// * `Err(XtvError::InvalidNodeId)` - If the node doesn't exist
     ///
     /// # Performance
     ///
     /// - **Cache hit**: O(1) - Just Arc::clone (cheap reference count increment)
-    /// - **Cache miss**: O(1) + blocking I/O (seek + read until blank line)
+    /// - **Cache miss**: O(1) + blocking I/O (seek + read until blank line),
+    ///   or O(1) without I/O when loading in the background
     ///
     /// # Note
     ///
     /// Returns `Arc<TreeNode>` to avoid expensive clones. Callers should use
     /// `Arc::clone()` to share ownership or dereference to access the node.
-    pub fn get_node(&self, id: usize) -> Option<Arc<TreeNode>> {
-        // Check cache first
-        {
-            let mut cache = self.cache.borrow_mut();
-            if let Some(node_arc) = cache.get(&id) {
-                // Arc::clone is cheap - just increments reference count
-                return Some(Arc::clone(node_arc));
+    pub fn get_node(&self, id: usize) -> Result<Arc<TreeNode>> {
+        if let Some(node) = self.grafts.get(id) {
+            return Ok(Arc::new(node.clone()));
+        }
+        // Arc::clone is cheap - just increments reference count
+        let cached = self.cache.borrow_mut().get(&id).map(Arc::clone);
+        if let Some(node) = cached {
+            self.cache_hits.set(self.cache_hits.get() + 1);
+            return loaded(id, node);
+        }
+        log::debug!(
+            "Cache miss for node {} after {} hits",
+            id,
+            self.cache_hits.replace(0)
+        );
+
+        let node = match &self.background {
+            Some(background) => self.load_in_background(background, id),
+            None => self.load_blocking(id),
+        };
+        loaded(id, node.ok_or(XtvError::InvalidNodeId(id))?)
+    }
+
+    /// Gets a node by ID, waiting for disk I/O if necessary.
+    ///
+    /// Unlike [`get_node`](StreamingTree::get_node) this never returns a
+    /// placeholder, so it suits operations that need the actual value such as
+    /// copying or searching. Failed loads are retried.
+    ///
+    /// # Errors
+    ///
+    /// Returns `XtvError::NodeLoad` if the node fails to load again, and
+    /// `XtvError::InvalidNodeId` if it doesn't exist.
+    pub fn get_node_blocking(&self, id: usize) -> Result<Arc<TreeNode>> {
+        if let Some(node) = self.grafts.get(id) {
+            return Ok(Arc::new(node.clone()));
+        }
+        let cached = self.cache.borrow_mut().get(&id).map(Arc::clone);
+        match cached {
+            Some(node) if node.node_type != TreeNode::ERROR_TYPE => Ok(node),
+            _ => loaded(
+                id,
+                self.load_blocking(id).ok_or(XtvError::InvalidNodeId(id))?,
+            ),
+        }
+    }
+
+    /// Moves nodes finished by the background loader into the cache.
+    ///
+    /// # Returns
+    ///
+    /// `true` if any nodes arrived, meaning the display should be refreshed
+    pub fn poll_loaded(&self) -> bool {
+        let Some(background) = &self.background else {
+            return false;
+        };
+        let delivered = background.take_results();
+        if delivered.is_empty() {
+            return false;
+        }
+        self.cache_loaded(delivered);
+        true
+    }
+
+    /// Checks whether background loads are still outstanding.
+    pub fn has_pending_loads(&self) -> bool {
+        self.background
+            .as_ref()
+            .is_some_and(BackgroundLoader::has_pending)
+    }
+
+    /// Loads the requested node and its neighbours synchronously.
+    fn load_blocking(&self, id: usize) -> Option<Arc<TreeNode>> {
+        let mut batch = vec![id];
+        batch.extend(self.prefetch_candidates(id));
+        let start = Instant::now();
+        let mut loaded = self.loader.borrow_mut().load_nodes(&batch);
+        log::debug!(
+            "Loaded node {} and {} more in {:?}, blocking",
+            id,
+            batch.len() - 1,
+            start.elapsed()
+        );
+
+        let requested = loaded
+            .iter()
+            .position(|(node_id, _)| *node_id == id)
+            .map(|pos| loaded.swap_remove(pos).1)?;
+        self.cache_loaded(loaded.into_iter().chain([(id, requested)]));
+
+        // The requested node was inserted last, so it is the most recently used entry
+        self.cache.borrow_mut().peek(&id).map(Arc::clone)
+    }
+
+    /// Caches load results, turning failures into error nodes.
+    ///
+    /// Each distinct failure message is reported once per batch.
+    fn cache_loaded(&self, results: impl IntoIterator<Item = (usize, LoadResult)>) {
+        let mut cache = self.cache.borrow_mut();
+        let mut failures: Vec<String> = Vec::new();
+        for (id, result) in results {
+            if let Err(message) = &result
+                && !failures.contains(message)
+            {
+                failures.push(message.clone());
             }
+            cache.put(id, Arc::new(self.node_or_error(id, result)));
         }
+        drop(cache);
+        self.report(failures);
+    }
 
-        // Load from disk
-        if let Some(node) = self.load_node(id) {
-            // Wrap in Arc before caching
-            let node_arc = Arc::new(node);
-            let mut cache = self.cache.borrow_mut();
-            cache.put(id, Arc::clone(&node_arc));
-            Some(node_arc)
-        } else {
-            None
+    /// Builds index-only nodes right away and queues disk reads on the worker.
+    fn load_in_background(
+        &self,
+        background: &BackgroundLoader,
+        id: usize,
+    ) -> Option<Arc<TreeNode>> {
+        let entry = self.index.get_entry(id)?;
+
+        let mut batch = vec![id];
+        batch.extend(self.prefetch_candidates(id));
+        let (disk, immediate): (Vec<usize>, Vec<usize>) = batch
+            .into_iter()
+            .partition(|&node_id| self.index.get_entry(node_id).is_some_and(reads_disk));
+        log::debug!(
+            "Queued {} nodes for node {} to load in the background",
+            disk.len(),
+            id
+        );
+        background.request(disk);
+
+        let mut cache = self.cache.borrow_mut();
+        for node_id in immediate.into_iter().rev() {
+            if let Some(node) = self.index.build_node(node_id, None) {
+                cache.put(node_id, Arc::new(node));
+            }
+        }
+        if reads_disk(entry) {
+            return Some(Arc::new(self.placeholder(id, TreeNode::LOADING_TYPE, None)));
+        }
+        cache.get(&id).map(Arc::clone)
+    }
+
+    /// Converts a load result into a node, rendering failures as error nodes.
+    fn node_or_error(&self, id: usize, result: LoadResult) -> TreeNode {
+        result.unwrap_or_else(|message| self.placeholder(id, TreeNode::ERROR_TYPE, Some(message)))
+    }
+
+    /// A stand-in for a node whose value is unavailable, with its real label and links.
+    fn placeholder(&self, id: usize, node_type: &str, message: Option<String>) -> TreeNode {
+        let mut node = TreeNode::new(self.index.node_label(id), node_type);
+        if let Some(message) = message {
+            node.add_attribute("error", message);
+        }
+        if let Some(entry) = self.index.get_entry(id) {
+            node.children = entry.children.clone();
+            node.parent_id = entry.parent_id;
+        }
+        node
+    }
+
+    /// Collects the IDs worth loading alongside `id` on a cache miss.
+    ///
+    /// Returns up to `prefetch_window` siblings following `id` and up to
+    /// `prefetch_window` of its first-level children, skipping nodes that are
+    /// already cached. The window is capped so the batch always fits in the cache.
+    fn prefetch_candidates(&self, id: usize) -> Vec<usize> {
+        let window = self
+            .prefetch_window
+            .min(STREAMING_CACHE_SIZE.saturating_sub(1) / 2);
+        if window == 0 {
+            return Vec::new();
         }
+
+        let cache = self.cache.borrow();
+        let uncached = |candidate: &usize| !cache.contains(candidate);
+
+        let mut candidates = Vec::new();
+        let siblings = self
+            .get_parent(id)
+            .and_then(|parent_id| self.index.get_entry(parent_id))
+            .map(|parent| parent.children.as_slice())
+            .unwrap_or_default();
+        if let Some(pos) = siblings.iter().position(|&sibling| sibling == id) {
+            candidates.extend(
+                siblings[pos + 1..]
+                    .iter()
+                    .copied()
+                    .filter(uncached)
+                    .take(window),
+            );
+        }
+        if let Some(entry) = self.index.get_entry(id) {
+            candidates.extend(entry.children.iter().copied().filter(uncached).take(window));
+        }
+        candidates
     }
 
     /// Gets the children IDs of a node.
@@ -410,10 +984,99 @@ impl StreamingTree {
     ///
     /// O(k) where k is the number of children (due to cloning the children vector)
     pub fn get_children(&self, id: usize) -> Vec<usize> {
-        self.index
-            .get_entry(id)
-            .map(|entry| entry.children.clone())
-            .unwrap_or_default()
+        if let Some(node) = self.grafts.get(id) {
+            return node.children.clone();
+        }
+        if let Some(order) = self.child_order.get(&id) {
+            return order.clone();
+        }
+        self.file_children(id)
+    }
+
+    /// The children of a node of the index in file order, followed by its
+    /// graft
+    fn file_children(&self, id: usize) -> Vec<usize> {
+        let Some(entry) = self.index.get_entry(id) else {
+            return Vec::new();
+        };
+        let mut children = entry.children.clone();
+        children.extend(self.grafts.grafted(id));
+        children
+    }
+
+    /// Shows the children of a node in another order.
+    ///
+    /// Only the order seen through [`get_children`](StreamingTree::get_children)
+    /// changes; the index, loaded nodes and searches keep the file order.
+    /// Does nothing if `order` isn't a permutation of the children, and for
+    /// grafted nodes.
+    pub fn reorder_children(&mut self, id: usize, order: Vec<usize>) {
+        let children = self.file_children(id);
+        if !super::is_permutation(&children, &order) {
+            return;
+        }
+        if children == order {
+            self.child_order.remove(&id);
+        } else {
+            self.child_order.insert(id, order);
+        }
+    }
+
+    /// Puts the children of a node back into file order.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the node had been reordered
+    pub fn restore_children(&mut self, id: usize) -> bool {
+        self.child_order.remove(&id).is_some()
+    }
+
+    /// Gets the IDs of all nodes whose children were reordered.
+    pub fn reordered_nodes(&self) -> Vec<usize> {
+        self.child_order.keys().copied().collect()
+    }
+
+    /// Hangs the nodes of `part` below `parent_id`, its root as the last
+    /// child, as [`Tree::graft`] does.
+    ///
+    /// The grafted nodes live in memory next to the index and get IDs after
+    /// those of the index. Searches find them; statistics leave them out.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the grafted root, or `None` if `parent_id` doesn't exist
+    pub fn graft(&mut self, parent_id: usize, part: Tree) -> Option<usize> {
+        if self.index.get_entry(parent_id).is_none() && self.grafts.get(parent_id).is_none() {
+            return None;
+        }
+        let top = Arc::make_mut(&mut self.grafts).add(parent_id, part);
+        if let Some(order) = self.child_order.get_mut(&parent_id)
+            && !order.contains(&top)
+        {
+            order.push(top);
+        }
+        Some(top)
+    }
+
+    /// Detaches the graft of `parent_id`, see [`Tree::ungraft`].
+    ///
+    /// # Returns
+    ///
+    /// `true` if the node had a graft attached
+    pub fn ungraft(&mut self, parent_id: usize) -> bool {
+        let Some(top) = self.grafts.grafted(parent_id) else {
+            return false;
+        };
+        Arc::make_mut(&mut self.grafts).remove(parent_id);
+        if let Some(order) = self.child_order.get_mut(&parent_id) {
+            order.retain(|&child| child != top);
+        }
+        true
+    }
+
+    /// Gets the root of the graft attached below `parent_id`, if any.
+    pub fn grafted(&self, parent_id: usize) -> Option<usize> {
+        self.grafts.grafted(parent_id)
     }
 
     /// Gets the parent of a node.
@@ -433,147 +1096,643 @@ impl StreamingTree {
     ///
     /// O(1) - Direct index lookup using parent_id field
     pub fn get_parent(&self, child_id: usize) -> Option<usize> {
+        if let Some(node) = self.grafts.get(child_id) {
+            return node.parent_id;
+        }
         self.index
             .get_entry(child_id)
             .and_then(|entry| entry.parent_id)
     }
 
+    /// Gets the label of a node from the index, without reading the file.
+    ///
+    /// This is a pure index operation - no disk I/O required.
+    pub fn node_label(&self, id: usize) -> Option<String> {
+        if let Some(node) = self.grafts.get(id) {
+            return Some(node.label.to_string());
+        }
+        self.index.get_entry(id)?;
+        Some(self.index.node_label(id))
+    }
+
+    /// Gets the name of the format the tree was built from.
+    ///
+    /// Streaming trees are only built for LDIF.
+    pub fn format(&self) -> &'static str {
+        "ldif"
+    }
+
     /// Gets the total number of nodes in the tree.
     ///
     /// # Returns
     ///
-    /// The count of all nodes in the index
+    /// The count of all nodes in the index and of all grafted nodes
     ///
     /// # Performance
     ///
-    /// O(1) - Returns the index length
+    /// O(1) - Adds up the lengths of the index and the grafts
     pub fn node_count(&self) -> usize {
-        self.index.len()
+        self.index.len() + self.grafts.len()
     }
 
-    /// Loads a node from disk by reading from its byte offset.
-    ///
-    /// This is an internal method called by [`get_node`](StreamingTree::get_node)
-    /// on cache misses. It performs blocking I/O to:
-    /// 1. Seek to the node's byte offset in the file
-    /// 2. Read lines until a blank line (end of LDIF entry)
-    /// 3. Parse the lines into a TreeNode using node type from index
-    ///
-    /// # Arguments
-    ///
-    /// * `id` - The node ID to load
-    ///
-    /// # Returns
-    ///
-    /// * `Some(TreeNode)` - Successfully loaded and parsed node
-    /// * `None` - If seek failed, read failed, or node doesn't exist in index
-    ///
-    /// # Safety Limits
+    /// Approximate heap usage of the in-memory index in bytes.
+    pub fn index_memory_bytes(&self) -> usize {
+        self.index.memory_bytes()
+    }
+
+    /// Searches the subtree under `root` on a worker thread.
     ///
-    /// A `MAX_LINES` limit (1000) prevents infinite loops from corrupt data.
-    /// If more than 1000 lines are read, loading stops with a warning.
+    /// Labels, types and attribute keys come from the index. Only when the
+    /// matcher needs values is the file read, with a file handle of the
+    /// search's own, one entry at a time in the order the walk reaches them.
+    /// The node cache is never touched. Attributes whose values cannot be read,
+    /// e.g. because the file changed, are matched without their value.
+    /// Paths for matchers that look at them are built from index labels as
+    /// well, so they cost no reads of the file. Grafted nodes are searched
+    /// after the children of the node they hang below.
     ///
-    /// # Performance
+    /// # Errors
     ///
-    /// This performs blocking I/O operations. May block indefinitely on
-    /// unresponsive network filesystems.
-    fn load_node(&self, id: usize) -> Option<TreeNode> {
-        let entry = self.index.get_entry(id)?;
-        let offset = entry.offset;
-
-        // Use the persistent reader and seek to offset
-        let mut reader = self.reader.borrow_mut();
-        if let Err(e) = reader.seek(SeekFrom::Start(offset)) {
-            eprintln!(
-                "Warning: Failed to seek to offset {} in file {:?}: {}",
-                offset, self.file_path, e
-            );
-            return None;
-        }
+    /// Returns an error if the file cannot be opened for reading values.
+    pub fn search(
+        &self,
+        root: usize,
+        limit: usize,
+        matcher: impl NodeMatcher,
+    ) -> std::io::Result<SearchTask> {
+        let index = Arc::clone(&self.index);
+        let grafts = Arc::clone(&self.grafts);
+        let mut loader = if matcher.needs_values() {
+            Some(NodeLoader::open(
+                Arc::clone(&index),
+                Arc::clone(&self.watch),
+            )?)
+        } else {
+            None
+        };
 
-        // Read lines until we hit an empty line (end of entry)
-        let mut lines = Vec::new();
-        let mut line = String::new();
-        let mut line_count = 0;
-        const MAX_LINES: usize = 1000; // Prevent infinite loops
+        Ok(SearchTask::spawn(limit, move |searcher| {
+            // Attributes of the entry being walked, read together with their `@attributes` node
+            let mut loaded: HashMap<usize, TreeNode> = HashMap::new();
+            let mut paths = matcher.path_separator().map(|separator| {
+                PathTracker::new(
+                    separator,
+                    root,
+                    |id| match grafts.get(id) {
+                        Some(node) => node.parent_id,
+                        None => index.get_entry(id).and_then(|entry| entry.parent_id),
+                    },
+                    |id| match grafts.get(id) {
+                        Some(node) => node.label.to_string(),
+                        None => index.node_label(id),
+                    },
+                )
+            });
+            let mut stack = vec![(root, 0)];
+            while let Some((node_id, depth)) = stack.pop() {
+                // Push children in reverse order so they are visited in document order
+                let (node, parent_id) = if let Some(node) = grafts.get(node_id) {
+                    if !searcher.proceed() {
+                        return;
+                    }
+                    stack.extend(node.children.iter().rev().map(|&child| (child, depth + 1)));
+                    (node.clone(), node.parent_id)
+                } else {
+                    let Some(entry) = index.get_entry(node_id) else {
+                        continue;
+                    };
+                    if !searcher.proceed() {
+                        return;
+                    }
 
-        loop {
-            line.clear();
+                    if let Some(loader) = &mut loader
+                        && entry.node_type == NodeType::VirtualAttributes
+                    {
+                        loaded = loader
+                            .load_nodes(&entry.children)
+                            .into_iter()
+                            .filter_map(|(id, result)| result.ok().map(|node| (id, node)))
+                            .collect();
+                    }
+                    let node = loaded
+                        .remove(&node_id)
+                        .or_else(|| index.build_node(node_id, None))
+                        .unwrap_or_else(|| {
+                            TreeNode::new(index.node_label(node_id), TreeNode::ATTRIBUTE_TYPE)
+                        });
+                    stack.extend(grafts.grafted(node_id).map(|top| (top, depth + 1)));
+                    stack.extend(entry.children.iter().rev().map(|&child| (child, depth + 1)));
+                    (node, entry.parent_id)
+                };
 
-            // Check for excessive lines (potential infinite loop or corruption)
-            line_count += 1;
-            if line_count > MAX_LINES {
-                eprintln!(
-                    "Warning: Read more than {} lines for node {}, stopping",
-                    MAX_LINES, id
-                );
-                break;
+                let matched = match &mut paths {
+                    Some(paths) => {
+                        let matched = matcher.matches_below(&node, paths.above(depth));
+                        paths.enter(parent_id.map(|_| node.label.as_str()));
+                        matched
+                    }
+                    None => matcher.matches(&node),
+                };
+                searcher.visit(node_id, matched);
             }
+        }))
+    }
 
-            match reader.read_line(&mut line) {
-                Ok(0) => break, // EOF
-                Ok(_) => {
-                    let trimmed = line.trim_end();
-                    if trimmed.is_empty() {
-                        break; // End of LDIF entry
+    /// Gathers statistics about the subtree under `root` on a worker thread.
+    ///
+    /// A first pass takes structure, types and attribute keys from the index.
+    /// If the subtree holds at most `value_limit` attributes, a second pass
+    /// reads their values with a file handle of its own, one entry at a time.
+    /// Otherwise, and for values that cannot be read, e.g. because the file
+    /// changed, the values are counted as unread.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened for reading values.
+    pub fn stats(&self, root: usize, value_limit: usize) -> std::io::Result<StatsTask> {
+        let index = Arc::clone(&self.index);
+        let mut loader = NodeLoader::open(Arc::clone(&index), Arc::clone(&self.watch))?;
+
+        Ok(StatsTask::spawn(move |walker| {
+            let mut attributes = 0;
+            let mut stack = vec![(root, 0)];
+            while let Some((node_id, depth)) = stack.pop() {
+                let Some(entry) = index.get_entry(node_id) else {
+                    continue;
+                };
+                if !walker.proceed() {
+                    return false;
+                }
+                let (node_type, key) = match &entry.node_type {
+                    NodeType::Root => ("root", None),
+                    NodeType::Entry { .. } => ("entry", None),
+                    NodeType::VirtualAttributes => (TreeNode::VIRTUAL_ATTRIBUTES_TYPE, None),
+                    NodeType::Attribute { key, url, .. } => {
+                        attributes += 1;
+                        (attribute_type(*url), Some(index.strings().resolve(*key)))
                     }
-                    lines.push(trimmed.to_string());
+                };
+                walker.visit(depth, node_type, key);
+                stack.extend(entry.children.iter().map(|&child| (child, depth + 1)));
+            }
+            if attributes > value_limit {
+                walker.skip_values(attributes);
+                return true;
+            }
+
+            let mut stack = vec![(root, 0)];
+            while let Some((node_id, depth)) = stack.pop() {
+                let Some(entry) = index.get_entry(node_id) else {
+                    continue;
+                };
+                if !walker.proceed() {
+                    return false;
                 }
-                Err(e) => {
-                    eprintln!(
-                        "Warning: I/O error reading from file {:?}: {}",
-                        self.file_path, e
-                    );
-                    return None;
+                // Attributes are read together with the others of their entry,
+                // so only an attribute the walk starts at is read on its own
+                let (batch, batch_depth) = match entry.node_type {
+                    NodeType::VirtualAttributes => (entry.children.as_slice(), depth + 1),
+                    NodeType::Attribute { .. } => (std::slice::from_ref(&node_id), depth),
+                    _ => {
+                        stack.extend(entry.children.iter().map(|&child| (child, depth + 1)));
+                        continue;
+                    }
+                };
+                for (_, result) in loader.load_nodes(batch) {
+                    match result.as_ref().ok().and_then(stats::leaf_value) {
+                        Some(value) => walker.value(value, batch_depth),
+                        None => walker.skip_values(1),
+                    }
                 }
             }
+            true
+        }))
+    }
+
+    /// Counts the nodes by type, the depth they reach and the bytes of their
+    /// labels from the index, and measures the index and the node cache.
+    ///
+    /// Attribute values stay on disk, so their bytes are not counted.
+    pub fn tree_stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            nodes: 0,
+            by_type: BTreeMap::new(),
+            max_depth: 0,
+            label_bytes: 0,
+            attribute_bytes: None,
+            memory: self.memory_usage(),
+        };
+        let mut stack = vec![(self.index.root_id(), 0)];
+        while let Some((node_id, depth)) = stack.pop() {
+            let Some(entry) = self.index.get_entry(node_id) else {
+                continue;
+            };
+            let node_type = match entry.node_type {
+                NodeType::Root => "root",
+                NodeType::Entry { .. } => "entry",
+                NodeType::VirtualAttributes => TreeNode::VIRTUAL_ATTRIBUTES_TYPE,
+                NodeType::Attribute { url, .. } => attribute_type(url),
+            };
+            stats.nodes += 1;
+            *stats.by_type.entry(node_type.to_string()).or_default() += 1;
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.label_bytes += self.index.node_label(node_id).len();
+            stack.extend(entry.children.iter().map(|&child| (child, depth + 1)));
         }
+        stats
+    }
 
-        // Parse the entry to create a TreeNode
-        self.parse_node_from_lines(id, lines)
+    /// Approximate heap usage of the index and of the nodes in the cache
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let cache = self.cache.borrow();
+        let per_node = std::mem::size_of::<(usize, Arc<TreeNode>)>()
+            + std::mem::size_of::<TreeNode>()
+            + 2 * std::mem::size_of::<usize>();
+        MemoryUsage::Streaming {
+            index_bytes: self.index_memory_bytes(),
+            cache_bytes: cache
+                .iter()
+                .map(|(_, node)| per_node + node.heap_bytes())
+                .sum(),
+            cached_nodes: cache.len(),
+        }
     }
+}
 
-    /// Parses a TreeNode from the lines read from disk.
-    ///
-    /// This method uses the node type information from the index to reconstruct
-    /// the TreeNode without needing to parse the full LDIF entry. The actual
-    /// line content is ignored - we use only the pre-computed metadata from
-    /// the index.
-    ///
-    /// # Arguments
-    ///
-    /// * `id` - The node ID being parsed
-    /// * `_lines` - Lines read from disk (currently unused, kept for future use)
-    ///
-    /// # Returns
-    ///
-    /// * `Some(TreeNode)` - Successfully reconstructed node
-    /// * `None` - If the node doesn't exist in the index
-    ///
-    /// # Implementation Note
-    ///
-    /// The `_lines` parameter is currently unused because all necessary data
-    /// (label, type, attributes) is stored in the index's `NodeType`. This
-    /// trades index size for parsing speed.
-    fn parse_node_from_lines(&self, id: usize, _lines: Vec<String>) -> Option<TreeNode> {
-        let entry = self.index.get_entry(id)?;
+/// Turns a cached error node back into the load failure it stands for
+fn loaded(id: usize, node: Arc<TreeNode>) -> Result<Arc<TreeNode>> {
+    if node.node_type != TreeNode::ERROR_TYPE {
+        return Ok(node);
+    }
+    let message = node
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "error")
+        .map_or("Unknown error", |attr| attr.value.as_str());
+    Err(XtvError::NodeLoad {
+        id,
+        source: std::io::Error::other(message.to_string()),
+    })
+}
 
-        let mut node = match &entry.node_type {
-            NodeType::Root => TreeNode::new("root", "root"),
-            NodeType::Entry { rdn, .. } => TreeNode::new(rdn, "entry"),
-            NodeType::VirtualAttributes => {
-                TreeNode::new("@attributes", TreeNode::VIRTUAL_ATTRIBUTES_TYPE)
-            }
-            NodeType::Attribute { key, value } => {
-                let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
-                node.add_attribute("value", value);
-                node
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ldif::build_ldif_index;
+    use std::io::Write;
+
+    fn write_ldif(entries: usize) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "version: 1\n\ndn: dc=example,dc=com\ndc: example\n").unwrap();
+        for i in 0..entries {
+            writeln!(file, "dn: cn=user{i},dc=example,dc=com\ncn: user{i}\n").unwrap();
+        }
+        file
+    }
+
+    fn entry_ids(tree: &StreamingTree) -> Vec<usize> {
+        let base = tree.get_children(tree.root_id())[0];
+        tree.get_children(base)
+            .into_iter()
+            .filter(|&id| {
+                matches!(
+                    tree.index.get_entry(id).unwrap().node_type,
+                    NodeType::Entry { .. }
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cache_miss_prefetches_following_siblings() {
+        let file = write_ldif(20);
+        let tree = build_ldif_index(file.path())
+            .unwrap()
+            .with_prefetch_window(5);
+        let entries = entry_ids(&tree);
+
+        tree.get_node(entries[0]).unwrap();
+
+        assert!(tree.is_cached(entries[0]));
+        for &sibling in &entries[1..=5] {
+            assert!(tree.is_cached(sibling));
+        }
+        assert!(!tree.is_cached(entries[6]));
+    }
+
+    #[test]
+    fn test_cache_miss_prefetches_children() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path()).unwrap();
+        let entry = entry_ids(&tree)[0];
+
+        tree.get_node(entry).unwrap();
+
+        for child in tree.get_children(entry) {
+            assert!(tree.is_cached(child));
+        }
+    }
+
+    #[test]
+    fn test_prefetch_disabled() {
+        let file = write_ldif(5);
+        let tree = build_ldif_index(file.path())
+            .unwrap()
+            .with_prefetch_window(0);
+        let entries = entry_ids(&tree);
+
+        tree.get_node(entries[0]).unwrap();
+
+        assert!(tree.is_cached(entries[0]));
+        assert!(!tree.is_cached(entries[1]));
+    }
+
+    #[test]
+    fn test_oversized_window_keeps_requested_node() {
+        let file = write_ldif(STREAMING_CACHE_SIZE + 10);
+        let tree = build_ldif_index(file.path())
+            .unwrap()
+            .with_prefetch_window(usize::MAX);
+        let entries = entry_ids(&tree);
+
+        let node = tree.get_node(entries[0]).unwrap();
+
+        assert_eq!(node.label, "cn=user0");
+        assert!(tree.is_cached(entries[0]));
+    }
+
+    #[test]
+    fn test_prefetched_nodes_match_direct_loads() {
+        let file = write_ldif(10);
+        let prefetching = build_ldif_index(file.path()).unwrap();
+        let direct = build_ldif_index(file.path())
+            .unwrap()
+            .with_prefetch_window(0);
+
+        for id in entry_ids(&prefetching) {
+            let a = prefetching.get_node(id).unwrap();
+            let b = direct.get_node(id).unwrap();
+            assert_eq!(a.label, b.label);
+            assert_eq!(a.children, b.children);
+        }
+    }
+
+    #[test]
+    fn test_index_interns_keys_and_suffixes() {
+        let file = write_ldif(20);
+        let tree = build_ldif_index(file.path()).unwrap();
+
+        // Keys "dc", "dn", "cn" and the base entry's missing-parent suffix "dc=com"
+        assert_eq!(tree.index.strings().len(), 4);
+        for (i, id) in entry_ids(&tree).into_iter().enumerate() {
+            let attrs = tree.get_children(id)[0];
+            let values: Vec<String> = tree
+                .get_children(attrs)
+                .into_iter()
+                .map(|attr| tree.get_node(attr).unwrap().attributes[0].value.clone())
+                .collect();
+            assert_eq!(
+                values,
+                [format!("user{i}"), format!("cn=user{i},dc=example,dc=com")]
+            );
+        }
+    }
+
+    #[test]
+    fn test_changed_file_is_stale_and_refuses_attribute_loads() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path()).unwrap();
+        let entry = entry_ids(&tree)[0];
+        let attrs = tree.get_children(entry)[0];
+        assert!(!tree.is_stale());
+
+        std::fs::write(file.path(), "dn: cn=other,dc=example,dc=com\ncn: other\n").unwrap();
+
+        assert!(tree.is_stale());
+        // Structure still comes from the index, values are no longer trusted
+        assert_eq!(tree.get_node(entry).unwrap().label, "cn=user0");
+        for attr in tree.get_children(attrs) {
+            assert!(matches!(
+                tree.get_node(attr),
+                Err(XtvError::NodeLoad { id, .. }) if id == attr
+            ));
+            assert!(tree.get_node_blocking(attr).is_err());
+        }
+        assert!(!tree.take_messages().is_empty());
+    }
+
+    #[test]
+    fn test_reload_indexes_current_contents() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path())
+            .unwrap()
+            .with_prefetch_window(7);
+
+        std::fs::write(file.path(), "dn: cn=other,dc=example,dc=com\ncn: other\n").unwrap();
+        let reloaded = tree.reload().unwrap();
+
+        assert!(!reloaded.is_stale());
+        assert_eq!(reloaded.prefetch_window, 7);
+        let entry = reloaded.get_children(reloaded.root_id())[0];
+        assert_eq!(
+            reloaded.get_node(entry).unwrap().label,
+            "cn=other,dc=example,dc=com"
+        );
+    }
+
+    fn wait_for_loads(tree: &StreamingTree) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while tree.has_pending_loads() && std::time::Instant::now() < deadline {
+            tree.poll_loaded();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert!(!tree.has_pending_loads(), "background loads did not finish");
+    }
+
+    #[test]
+    fn test_background_loading_returns_placeholder_then_value() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path())
+            .unwrap()
+            .with_background_loading()
+            .unwrap();
+        let entry = entry_ids(&tree)[0];
+
+        // Entries come from the index and never wait for the disk
+        assert_eq!(tree.get_node(entry).unwrap().node_type, "entry");
+
+        let attrs = tree.get_children(entry)[0];
+        let attr = tree.get_children(attrs)[0];
+        let placeholder = tree.get_node(attr).unwrap();
+        assert_eq!(placeholder.node_type, TreeNode::LOADING_TYPE);
+        assert_eq!(placeholder.label, "cn");
+        assert!(!tree.is_cached(attr));
+
+        wait_for_loads(&tree);
+
+        let loaded = tree.get_node(attr).unwrap();
+        assert_eq!(loaded.node_type, TreeNode::ATTRIBUTE_TYPE);
+        assert_eq!(loaded.attributes[0].value, "user0");
+    }
+
+    #[test]
+    fn test_background_load_failure_is_an_error() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path())
+            .unwrap()
+            .with_background_loading()
+            .unwrap();
+        let attrs = tree.get_children(entry_ids(&tree)[0])[0];
+        let attr = tree.get_children(attrs)[0];
+
+        std::fs::write(file.path(), "dn: cn=other\n").unwrap();
+        tree.get_node(attr).unwrap();
+        wait_for_loads(&tree);
+
+        let error = tree.get_node(attr).unwrap_err();
+        assert!(matches!(error, XtvError::NodeLoad { id, .. } if id == attr));
+        assert!(error.to_string().contains("File changed on disk"));
+    }
+
+    /// Matches attribute values, or labels when values are not needed
+    struct Containing(&'static str, bool);
+
+    impl NodeMatcher for Containing {
+        fn matches(&self, node: &TreeNode) -> bool {
+            node.label.contains(self.0)
+                || node
+                    .attributes
+                    .iter()
+                    .any(|attr| attr.value.contains(self.0))
+        }
+
+        fn needs_values(&self) -> bool {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_search_reads_values_only_when_needed() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path()).unwrap();
+        let entry = entry_ids(&tree)[1];
+        // The cn and dn attributes both contain "user1"
+        let attributes = tree.get_children(tree.get_children(entry)[0]);
+
+        let labels = tree
+            .search(0, usize::MAX, Containing("user1", false))
+            .unwrap();
+        assert_eq!(labels.wait().matches, [entry]);
+
+        let values = tree
+            .search(0, usize::MAX, Containing("user1", true))
+            .unwrap();
+        assert_eq!(values.wait().matches, [entry, attributes[0], attributes[1]]);
+        // The search has its own file handle and leaves the cache alone
+        assert!(!tree.is_cached(attributes[0]));
+    }
+
+    #[test]
+    fn test_grafts_are_navigable_and_searchable() {
+        let file = write_ldif(2);
+        let mut tree = build_ldif_index(file.path()).unwrap();
+        let count = tree.node_count();
+        let entry = entry_ids(&tree)[1];
+        let attribute = tree.get_children(tree.get_children(entry)[0])[0];
+
+        let part = crate::tree::graft::embedded_json(r#"{"nested": "user9"}"#).unwrap();
+        let top = tree.graft(attribute, part.tree).unwrap();
+        assert_eq!(top, count);
+        assert_eq!(tree.get_children(attribute), [top]);
+        let nested = tree.get_children(top)[0];
+        assert_eq!(tree.get_parent(nested), Some(top));
+        assert_eq!(tree.node_label(nested).as_deref(), Some("nested"));
+        assert_eq!(tree.get_node(nested).unwrap().attributes[0].value, "user9");
+
+        let search = tree
+            .search(0, usize::MAX, Containing("user9", true))
+            .unwrap();
+        assert_eq!(search.wait().matches, [nested]);
+
+        assert!(tree.ungraft(attribute));
+        assert!(tree.get_children(attribute).is_empty());
+        let search = tree
+            .search(0, usize::MAX, Containing("user9", true))
+            .unwrap();
+        assert!(search.wait().matches.is_empty());
+        // Grafting again brings back the same nodes
+        let part = crate::tree::graft::embedded_json("[1]").unwrap();
+        assert_eq!(tree.graft(attribute, part.tree), Some(top));
+        assert_eq!(tree.node_count(), count + 2);
+    }
+
+    #[test]
+    fn test_stats_read_values_up_to_the_limit() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path()).unwrap();
+        let entry = entry_ids(&tree)[1];
+
+        let stats = tree.stats(entry, 2).unwrap().wait().unwrap();
+        // @attributes with dn and cn
+        assert_eq!(stats.descendants, 3);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.distinct_keys, 2);
+        assert_eq!(stats.by_type[TreeNode::ATTRIBUTE_TYPE], 2);
+        assert_eq!(
+            stats.value_bytes,
+            Some(("cn=user1,dc=example,dc=com".len() + 5) as u64)
+        );
+
+        let stats = tree.stats(entry, 1).unwrap().wait().unwrap();
+        assert_eq!(stats.descendants, 3);
+        assert_eq!(stats.value_bytes, None);
+        assert_eq!(stats.unread_values, 2);
+    }
+
+    #[test]
+    fn test_tree_stats_match_the_parsed_tree() {
+        use crate::parser::{Parser, ldif::LdifParser};
+
+        let file = write_ldif(4);
+        let tree = build_ldif_index(file.path()).unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        let parsed = LdifParser::default().parse(&content).unwrap().stats();
+
+        let stats = tree.tree_stats();
+        assert_eq!(stats.nodes, parsed.nodes);
+        assert_eq!(stats.by_type, parsed.by_type);
+        assert_eq!(stats.max_depth, parsed.max_depth);
+        assert_eq!(stats.label_bytes, parsed.label_bytes);
+        assert_eq!(stats.attribute_bytes, None);
+
+        let MemoryUsage::Streaming { cached_nodes, .. } = stats.memory else {
+            panic!("{:?}", stats.memory);
+        };
+        assert_eq!(cached_nodes, 0);
+        tree.get_node(entry_ids(&tree)[0]).unwrap();
+        let MemoryUsage::Streaming {
+            index_bytes,
+            cache_bytes,
+            cached_nodes,
+        } = tree.memory_usage()
+        else {
+            unreachable!()
         };
+        assert_eq!(index_bytes, tree.index_memory_bytes());
+        assert!(cached_nodes > 0 && cache_bytes > 0);
+    }
 
-        // Add children references from index
-        node.children = entry.children.clone();
+    #[test]
+    fn test_blocking_get_skips_placeholder() {
+        let file = write_ldif(3);
+        let tree = build_ldif_index(file.path())
+            .unwrap()
+            .with_background_loading()
+            .unwrap();
+        let attrs = tree.get_children(entry_ids(&tree)[1])[0];
+        let attr = tree.get_children(attrs)[0];
 
-        Some(node)
+        let node = tree.get_node_blocking(attr).unwrap();
+        assert_eq!(node.attributes[0].value, "user1");
     }
 }
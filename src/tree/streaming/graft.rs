@@ -0,0 +1,98 @@
+//! Subtrees grafted below the nodes of a [`StreamingTree`](super::StreamingTree),
+//! such as the parsed JSON of a value.
+//!
+//! The index stays as it was built. Grafted nodes are kept in memory with IDs
+//! following those of the index, and are never evicted or read from the file.
+
+use crate::tree::{Tree, TreeNode};
+use std::collections::HashMap;
+
+/// The grafted nodes of a streaming tree
+#[derive(Debug, Clone, Default)]
+pub(super) struct Grafts {
+    /// ID of the first grafted node, the number of nodes in the index
+    first_id: usize,
+    /// Node `first_id + i` at `i`
+    nodes: Vec<TreeNode>,
+    /// Root of the graft of every node that has or had one
+    tops: HashMap<usize, usize>,
+}
+
+impl Grafts {
+    /// No grafts yet, for an index of `first_id` nodes
+    pub(super) fn new(first_id: usize) -> Self {
+        Self {
+            first_id,
+            ..Self::default()
+        }
+    }
+
+    /// Number of grafted nodes, attached or not
+    pub(super) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The grafted node `id`, or `None` for nodes of the index
+    pub(super) fn get(&self, id: usize) -> Option<&TreeNode> {
+        self.nodes.get(id.checked_sub(self.first_id)?)
+    }
+
+    fn get_mut(&mut self, id: usize) -> Option<&mut TreeNode> {
+        self.nodes.get_mut(id.checked_sub(self.first_id)?)
+    }
+
+    /// Root of the graft attached below `parent_id`, if any
+    pub(super) fn grafted(&self, parent_id: usize) -> Option<usize> {
+        let top = *self.tops.get(&parent_id)?;
+        (self.get(top)?.parent_id == Some(parent_id)).then_some(top)
+    }
+
+    /// Hang the nodes of `part` below `parent_id`, or the earlier graft if
+    /// it had one, and return the ID of the grafted root
+    pub(super) fn add(&mut self, parent_id: usize, part: Tree) -> usize {
+        if let Some(&top) = self.tops.get(&parent_id) {
+            self.attach(parent_id, top);
+            return top;
+        }
+
+        let offset = self.first_id + self.nodes.len();
+        let top = part.root_id + offset;
+        self.nodes.extend(part.nodes.into_iter().map(|mut node| {
+            node.parent_id = node.parent_id.map(|parent| parent + offset);
+            for child in &mut node.children {
+                *child += offset;
+            }
+            node.source_span = None;
+            node
+        }));
+        self.attach(parent_id, top);
+        self.tops.insert(parent_id, top);
+        top
+    }
+
+    /// Detach the graft of `parent_id`, returning whether it had one attached
+    pub(super) fn remove(&mut self, parent_id: usize) -> bool {
+        let Some(top) = self.grafted(parent_id) else {
+            return false;
+        };
+        if let Some(node) = self.get_mut(top) {
+            node.parent_id = None;
+        }
+        // Nodes of the index list their graft through `grafted`
+        if let Some(parent) = self.get_mut(parent_id) {
+            parent.children.retain(|&child| child != top);
+        }
+        true
+    }
+
+    fn attach(&mut self, parent_id: usize, top: usize) {
+        if let Some(node) = self.get_mut(top) {
+            node.parent_id = Some(parent_id);
+        }
+        if let Some(parent) = self.get_mut(parent_id)
+            && !parent.children.contains(&top)
+        {
+            parent.children.push(top);
+        }
+    }
+}
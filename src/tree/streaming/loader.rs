@@ -0,0 +1,265 @@
+//! Reading streaming nodes from disk, inline or on a background thread.
+//!
+//! A [`NodeLoader`] owns a file handle and turns node IDs into [`TreeNode`]s
+//! using the shared index. The [`StreamingTree`](super::StreamingTree) keeps one
+//! for blocking loads and can hand another to a [`BackgroundLoader`] so that
+//! slow storage never stalls the caller.
+
+use super::{FileWatch, IndexEntry, LdifIndex, MAX_ENTRY_LINES, NodeType};
+use crate::parser::ldif::{EntryBlock, parse_entry_block};
+use crate::tree::TreeNode;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+use std::time::Instant;
+
+/// Outcome of loading one node: the node, or a message describing the failure
+pub type LoadResult = std::result::Result<TreeNode, String>;
+
+/// Loads nodes from the LDIF file backing a streaming index
+pub(super) struct NodeLoader {
+    index: Arc<LdifIndex>,
+    watch: Arc<FileWatch>,
+    reader: BufReader<File>,
+}
+
+impl NodeLoader {
+    /// Opens a new file handle for loading nodes of `index`.
+    pub(super) fn open(index: Arc<LdifIndex>, watch: Arc<FileWatch>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(&watch.path)?);
+        Ok(Self {
+            index,
+            watch,
+            reader,
+        })
+    }
+
+    /// Loads a batch of nodes from disk in a single sequential pass.
+    ///
+    /// Nodes are visited in file-offset order so that adjacent entries are
+    /// served from the reader's buffer instead of separate seeks. For each LDIF
+    /// entry touched by the batch it performs blocking I/O to:
+    /// 1. Seek to the entry's byte offset in the file (relative when moving forward)
+    /// 2. Read lines until a blank line (end of LDIF entry)
+    /// 3. Parse the lines once and resolve every requested attribute against them
+    ///
+    /// Entry, root, and virtual attribute container nodes are reconstructed from
+    /// the index alone without any I/O. Attribute nodes fail when the file
+    /// changed since the index was built (see [`FileWatch::is_stale`]).
+    ///
+    /// # Returns
+    ///
+    /// One result per requested ID that exists in the index. IDs unknown to
+    /// the index are omitted.
+    ///
+    /// # Performance
+    ///
+    /// This performs blocking I/O operations. May block indefinitely on
+    /// unresponsive network filesystems.
+    pub(super) fn load_nodes(&mut self, ids: &[usize]) -> Vec<(usize, LoadResult)> {
+        let index = Arc::clone(&self.index);
+        let mut entries: Vec<(usize, &IndexEntry)> = ids
+            .iter()
+            .filter_map(|&id| index.get_entry(id).map(|entry| (id, entry)))
+            .collect();
+        entries.sort_by_key(|(_, entry)| entry.offset);
+
+        let needs_disk = entries.iter().any(|(_, entry)| reads_disk(entry));
+        let stale = needs_disk && self.watch.is_stale();
+        if stale {
+            log::debug!("Not reading {:?}: it changed on disk", self.watch.path);
+        }
+
+        let mut position: Option<u64> = None;
+        let mut current_block: Option<(u64, std::result::Result<EntryBlock, String>)> = None;
+        let mut nodes = Vec::with_capacity(entries.len());
+
+        for (id, entry) in entries {
+            if !reads_disk(entry) {
+                nodes.extend(index.build_node(id, None).map(|node| (id, Ok(node))));
+                continue;
+            }
+            if stale {
+                nodes.push((id, Err("File changed on disk; reload to view".to_string())));
+                continue;
+            }
+
+            let cached = matches!(current_block, Some((offset, _)) if offset == entry.offset);
+            if !cached {
+                let parsed = self.read_entry_block(&mut position, entry.offset);
+                current_block = Some((entry.offset, parsed));
+            }
+            let result = match current_block.as_ref().map(|(_, block)| block) {
+                Some(Ok(block)) => index
+                    .build_node(id, Some(block))
+                    .ok_or_else(|| "Value not found in entry; file may have changed".to_string()),
+                Some(Err(e)) => Err(e.clone()),
+                None => Err("Entry could not be read".to_string()),
+            };
+            nodes.push((id, result));
+        }
+
+        nodes
+    }
+
+    /// Reads and parses the LDIF entry starting at `offset`.
+    fn read_entry_block(
+        &mut self,
+        position: &mut Option<u64>,
+        offset: u64,
+    ) -> std::result::Result<EntryBlock, String> {
+        let lines = self.read_entry_lines(position, offset)?;
+        match parse_entry_block(&lines, 0) {
+            Ok(Some(block)) => Ok(block),
+            Ok(None) => Err(format!("No entry at offset {}", offset)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Reads the lines of the LDIF entry starting at `offset`.
+    ///
+    /// `position` tracks where the reader currently is so that forward moves
+    /// can use a relative seek, which keeps the read buffer intact. It is reset
+    /// to `None` whenever the position becomes unknown.
+    ///
+    /// # Safety Limits
+    ///
+    /// A [`MAX_ENTRY_LINES`] limit prevents runaway reads from corrupt data.
+    /// If more lines are read, the entry is reported as failed.
+    fn read_entry_lines(
+        &mut self,
+        position: &mut Option<u64>,
+        offset: u64,
+    ) -> std::result::Result<Vec<String>, String> {
+        let start = Instant::now();
+        let (seek, seek_result) = match *position {
+            Some(pos) if offset >= pos => {
+                ("relative", self.reader.seek_relative((offset - pos) as i64))
+            }
+            _ => (
+                "absolute",
+                self.reader.seek(SeekFrom::Start(offset)).map(|_| ()),
+            ),
+        };
+        *position = None;
+        seek_result.map_err(|e| format!("Failed to seek to offset {}: {}", offset, e))?;
+
+        let mut pos = offset;
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        loop {
+            if lines.len() >= MAX_ENTRY_LINES {
+                return Err(format!(
+                    "Entry at offset {} has more than {} lines",
+                    offset, MAX_ENTRY_LINES
+                ));
+            }
+
+            line.clear();
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .map_err(|e| format!("I/O error reading {:?}: {}", self.watch.path, e))?;
+            pos += read as u64;
+
+            let content = line.trim_end_matches(['\r', '\n']);
+            if read == 0 || content.trim().is_empty() {
+                break; // EOF or end of LDIF entry
+            }
+            lines.push(content.to_string());
+        }
+
+        log::debug!(
+            "Read {} lines at offset {} ({} seek) in {:?}",
+            lines.len(),
+            offset,
+            seek,
+            start.elapsed()
+        );
+        *position = Some(pos);
+        Ok(lines)
+    }
+}
+
+/// Whether loading this node needs to read the file
+pub(super) fn reads_disk(entry: &IndexEntry) -> bool {
+    matches!(entry.node_type, NodeType::Attribute { .. })
+}
+
+/// Loads nodes on a worker thread and hands back results without blocking.
+///
+/// Requests are batches of node IDs. The worker owns its own [`NodeLoader`]
+/// and exits when the loader is dropped.
+pub(super) struct BackgroundLoader {
+    requests: Sender<Vec<usize>>,
+    results: Receiver<Vec<(usize, LoadResult)>>,
+    /// Node IDs requested but not yet delivered
+    pending: RefCell<HashSet<usize>>,
+}
+
+impl BackgroundLoader {
+    /// Starts a worker thread that loads nodes with `loader`.
+    pub(super) fn spawn(mut loader: NodeLoader) -> Self {
+        let (requests, worker_requests) = channel::<Vec<usize>>();
+        let (worker_results, results) = channel();
+
+        thread::spawn(move || {
+            for batch in worker_requests {
+                let start = Instant::now();
+                let loaded = loader.load_nodes(&batch);
+                log::debug!(
+                    "Loaded {} nodes in the background in {:?}",
+                    batch.len(),
+                    start.elapsed()
+                );
+                if worker_results.send(loaded).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests,
+            results,
+            pending: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Queues nodes for loading, skipping those already queued.
+    pub(super) fn request(&self, ids: impl IntoIterator<Item = usize>) {
+        let mut pending = self.pending.borrow_mut();
+        let batch: Vec<usize> = ids.into_iter().filter(|&id| pending.insert(id)).collect();
+        if batch.is_empty() {
+            return;
+        }
+        if self.requests.send(batch.clone()).is_err() {
+            // Worker is gone; forget the IDs so they can be requested again
+            for id in batch {
+                pending.remove(&id);
+            }
+        }
+    }
+
+    /// Collects all results delivered since the last call.
+    pub(super) fn take_results(&self) -> Vec<(usize, LoadResult)> {
+        let mut delivered = Vec::new();
+        while let Ok(batch) = self.results.try_recv() {
+            delivered.extend(batch);
+        }
+
+        let mut pending = self.pending.borrow_mut();
+        for (id, _) in &delivered {
+            pending.remove(id);
+        }
+        delivered
+    }
+
+    /// Checks whether any requested nodes have not been delivered yet.
+    pub(super) fn has_pending(&self) -> bool {
+        !self.pending.borrow().is_empty()
+    }
+}
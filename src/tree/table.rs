@@ -0,0 +1,214 @@
+//! Arrays of similar objects as rows and columns.
+//!
+//! The records of a JSON array or a JSON Lines file usually share their
+//! keys, and are far easier to compare laid out as a table. [`extract_table`]
+//! turns the objects of an array into rows with one cell per key that most
+//! of them have; [`crate::ui::table_view`] shows the result.
+
+use crate::tree::sort::{node_value, value_cmp};
+use crate::tree::{TreeNode, TreeVariant};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Most rows extracted, so that huge arrays open quickly
+pub const MAX_TABLE_ROWS: usize = 10_000;
+
+/// The objects of an array, one row each
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    /// Keys found in at least half of the objects, in the order they first
+    /// appear
+    pub columns: Vec<String>,
+    pub rows: Vec<TableRow>,
+    /// Whether the array had more than [`MAX_TABLE_ROWS`] objects
+    pub truncated: bool,
+}
+
+/// One object of the array
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRow {
+    pub node_id: usize,
+    /// The object's label, usually its index such as `[3]`
+    pub label: String,
+    /// One cell per column: the value of a leaf, a summary such as
+    /// `[3 items]` of a container, or `None` if the object lacks the key
+    pub cells: Vec<Option<String>>,
+}
+
+impl Table {
+    /// Indices of the rows ordered by `column`, numbers numerically, with
+    /// empty cells last. The sort is stable.
+    pub fn sorted_rows(&self, column: usize, descending: bool) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.rows.len()).collect();
+        let cell = |row: usize| self.rows[row].cells.get(column).and_then(Option::as_deref);
+        order.sort_by(|&a, &b| match (cell(a), cell(b)) {
+            (Some(a), Some(b)) if descending => value_cmp(b, a),
+            (Some(a), Some(b)) => value_cmp(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        order
+    }
+}
+
+/// The objects under `node_id` as a table, if it is an array (which includes
+/// the root of a JSON Lines file or top-level array) whose children are all
+/// objects.
+///
+/// # Examples
+///
+/// ```
+/// use xtv::parser::{Parser, json::JsonParser};
+/// use xtv::tree::TreeVariant;
+/// use xtv::tree::table::extract_table;
+///
+/// let json = r#"[{"name": "Ada", "age": 36}, {"name": "Alan", "tags": [1, 2]}]"#;
+/// let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+/// let table = extract_table(&tree, tree.root_id()).unwrap();
+///
+/// assert_eq!(table.columns, ["age", "name", "tags"]);
+/// assert_eq!(table.rows[1].label, "[1]");
+/// assert_eq!(
+///     table.rows[1].cells,
+///     [None, Some("Alan".to_string()), Some("[2 items]".to_string())]
+/// );
+/// ```
+pub fn extract_table(tree: &TreeVariant, node_id: usize) -> Option<Table> {
+    let node = tree.get_node_blocking(node_id)?;
+    let children = tree.get_children(node_id);
+    if !node.is_array() {
+        return None;
+    }
+    let truncated = children.len() > MAX_TABLE_ROWS;
+
+    // Each object's fields by key, and how many objects have each key
+    let mut objects = Vec::new();
+    let mut keys: Vec<String> = Vec::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for &id in children.iter().take(MAX_TABLE_ROWS) {
+        let object = tree.get_node_blocking(id)?;
+        if !object.is_object() {
+            return None;
+        }
+        let mut fields = HashMap::new();
+        for field in tree.get_children(id) {
+            let key = tree.get_label(field).unwrap_or_default();
+            let count = counts.entry(key.clone()).or_default();
+            if *count == 0 {
+                keys.push(key.clone());
+            }
+            *count += 1;
+            fields.insert(key, field);
+        }
+        objects.push((id, String::from(object.label), fields));
+    }
+    if objects.is_empty() {
+        return None;
+    }
+
+    let columns: Vec<String> = keys
+        .into_iter()
+        .filter(|key| counts[key] * 2 >= objects.len())
+        .collect();
+    if columns.is_empty() {
+        return None;
+    }
+    let rows = objects
+        .into_iter()
+        .map(|(node_id, label, fields)| TableRow {
+            node_id,
+            label,
+            cells: columns
+                .iter()
+                .map(|key| {
+                    let field = *fields.get(key)?;
+                    Some(cell_text(tree, field, &tree.get_node_blocking(field)?))
+                })
+                .collect(),
+        })
+        .collect();
+
+    Some(Table {
+        columns,
+        rows,
+        truncated,
+    })
+}
+
+/// A leaf's value, or how many children a container has
+pub(crate) fn cell_text(tree: &TreeVariant, node_id: usize, node: &TreeNode) -> String {
+    if let Some(value) = node_value(node) {
+        return value;
+    }
+    let count = tree.get_children(node_id).len();
+    if node.is_array() {
+        format!("[{} items]", count)
+    } else {
+        format!("{{{} fields}}", count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser, jsonlines::JsonLinesParser};
+
+    fn json(text: &str) -> TreeVariant {
+        TreeVariant::from(JsonParser.parse(text).unwrap())
+    }
+
+    #[test]
+    fn test_columns_are_the_common_keys() {
+        let tree = json(
+            r#"{"users": [
+                {"name": "b", "age": 10, "x": 1},
+                {"name": "a", "age": 9.5, "y": {"z": 1}},
+                {"name": "c", "y": {}}
+            ]}"#,
+        );
+        let users = tree.get_children(tree.root_id())[0];
+        let table = extract_table(&tree, users).unwrap();
+        // `x` is in only one of three objects
+        assert_eq!(table.columns, ["age", "name", "y"]);
+        assert_eq!(
+            table.rows[1].cells,
+            [
+                Some("9.5".to_string()),
+                Some("a".to_string()),
+                Some("{1 fields}".to_string())
+            ]
+        );
+        assert!(!table.truncated);
+
+        // Numbers sort numerically, missing cells last
+        assert_eq!(table.sorted_rows(0, false), [1, 0, 2]);
+        assert_eq!(table.sorted_rows(0, true), [0, 1, 2]);
+        assert_eq!(table.sorted_rows(1, true), [2, 0, 1]);
+    }
+
+    #[test]
+    fn test_only_arrays_of_objects() {
+        let tree = json(r#"{"a": [1, {"b": 2}], "c": {"d": {"e": 1}}, "f": []}"#);
+        for id in tree.get_children(tree.root_id()) {
+            assert_eq!(extract_table(&tree, id), None);
+        }
+        assert_eq!(extract_table(&tree, tree.root_id()), None);
+
+        // An object keyed like an array is still an object
+        let tree = json(r#"{"[0]": {"a": 1}, "[1]": {"a": 2}}"#);
+        assert_eq!(extract_table(&tree, tree.root_id()), None);
+    }
+
+    #[test]
+    fn test_json_lines_records() {
+        let tree = JsonLinesParser::default()
+            .parse("{\"id\": 1, \"ok\": true}\n{\"id\": 2, \"ok\": false}\n")
+            .unwrap();
+        let tree = TreeVariant::from(tree);
+        let table = extract_table(&tree, tree.root_id()).unwrap();
+        assert_eq!(table.columns, ["id", "ok"]);
+        let labels: Vec<&str> = table.rows.iter().map(|row| row.label.as_str()).collect();
+        assert_eq!(labels, ["[1]", "[2]"]);
+    }
+}
@@ -1,43 +1,80 @@
+use crate::config::{CaseSensitivity, Config};
+use crate::convert;
 use crate::error::{Result, XtvError};
-use crate::tree::TreeVariant;
-use crate::ui::tree_view::TreeView;
-use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
-    execute,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
-};
+use crate::interrupt;
+use crate::parser::ldif::schema::AttributeLabels;
+use crate::parser::{self, ParserRegistry};
+use crate::query::path;
+use crate::timing::LoadTimings;
+use crate::tree::{SearchTask, TreeVariant, export};
+use crate::ui::finder::{Finder, FinderAction};
+use crate::ui::highlight::Syntax;
+use crate::ui::redact::{MASK, Redaction};
+use crate::ui::render_text::{self, TextLayout, TextOptions};
+use crate::ui::table_view::{TableAction, TableView};
+use crate::ui::terminal::TerminalGuard;
+use crate::ui::theme::Theme;
+use crate::ui::tree_view::{Align, SearchHighlight, TreeView, format_thousands};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     Terminal,
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
     widgets::Paragraph,
 };
+use std::collections::BTreeMap;
 use std::io;
-
-/// Maximum number of nodes to search through
-/// Prevents memory exhaustion when searching large streaming trees
-const MAX_SEARCH_NODES: usize = 100_000;
-
-/// Number of lines to scroll for page up/down operations
-const PAGE_SCROLL_LINES: usize = 10;
+use std::mem;
+use std::path::PathBuf;
+use std::time::Instant;
+use unicode_width::UnicodeWidthStr;
+
+mod clipboard;
+mod declutter;
+mod detail;
+mod diff;
+mod dn;
+mod duplicates;
+mod edit;
+mod embedded;
+mod history;
+mod marks;
+mod mouse;
+mod notification;
+mod print;
+mod redaction;
+mod reload;
+mod repeat;
+mod search;
+mod search_history;
+mod sort;
+mod stats;
+mod table;
+mod tabs;
+mod url_ref;
+mod view_state;
+mod write;
+mod xpath;
+
+use notification::Notification;
+
+/// Stands for the ancestors left out of a path bar too long for the screen
+const PATH_ELLIPSIS: &str = "…";
+
+/// Columns scrolled by `zh` and `zl`
+const HORIZONTAL_SCROLL_COLUMNS: usize = 4;
+
+/// Largest count prefix accepted before a motion, as in `25j`
+const MAX_COUNT: usize = 99_999;
+
+/// Keys that name a copy command after `y`
+const YANK_COMMANDS: &str = "yvskpjx6loYTCtf";
 
 /// Help popup width
 const HELP_POPUP_WIDTH: u16 = 80;
 
-/// Help popup height
-const HELP_POPUP_HEIGHT: u16 = 30;
-
-/// Print popup width as fraction of screen width (4/5)
-const PRINT_POPUP_WIDTH_FRACTION: u16 = 4;
-const PRINT_POPUP_WIDTH_DIVISOR: u16 = 5;
-const PRINT_POPUP_MAX_WIDTH: u16 = 100;
-
-/// Print popup height as fraction of screen height (3/4)
-const PRINT_POPUP_HEIGHT_FRACTION: u16 = 3;
-const PRINT_POPUP_HEIGHT_DIVISOR: u16 = 4;
-const PRINT_POPUP_MAX_HEIGHT: u16 = 30;
-
 /// Decode menu options
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum DecodeOption {
@@ -70,143 +107,544 @@ impl DecodeOption {
     }
 }
 
+/// Most nodes expanded at startup in a streaming tree, where every expanded
+/// node can mean disk reads while rendering
+const MAX_STREAMING_STARTUP_EXPANSIONS: usize = 10_000;
+
+/// Drawing or key handling slower than this is noted in the debug log
+const SLOW_STEP: std::time::Duration = std::time::Duration::from_millis(100);
+
 pub struct App {
+    config: Config,
+    theme: Theme,
     tree: TreeVariant,
     tree_view: TreeView,
     should_quit: bool,
+    /// Ctrl-Z was pressed; the main loop stops the process before the next frame
+    suspend_requested: bool,
     show_help: bool,
+    /// First line of the help shown, and lines it shows at once as last drawn
+    help_scroll: usize,
+    help_page: usize,
     last_key_was_y: bool,
     last_key_was_p: bool,
-    print_content: Option<String>,
+    last_key_was_m: bool,
+    last_key_was_quote: bool,
+    last_key_was_z: bool,
+    last_key_was_s: bool,
+    /// `g` typed before a tab command such as `gt`, with more than one tab
+    last_key_was_g: bool,
+    /// `[` or `]` typed before a diff command such as `]c`
+    last_key_was_bracket: Option<char>,
+    /// Count typed before the `z` prefix, e.g. the 2 of `2zR`
+    z_count: Option<usize>,
+    /// Count typed before the `g` prefix, e.g. the 15 of `15gn`
+    g_count: Option<usize>,
+    /// `Q` typed before the register to record a macro into
+    last_key_was_record: bool,
+    /// `@` typed before the register of a macro to replay
+    last_key_was_at: bool,
+    /// Count typed before `@`, e.g. the 3 of `3@a`
+    at_count: Option<usize>,
+    /// The action `.` repeats
+    last_action: Option<repeat::Action>,
+    recording: Option<repeat::Recording>,
+    /// Recorded macros by register
+    macros: BTreeMap<char, Vec<repeat::Action>>,
+    /// The register `@@` replays
+    last_macro: Option<char>,
+    /// Whether `zR` was pressed once and is waiting for confirmation
+    confirm_expand_all: bool,
+    /// Whether `E` was pressed once and is waiting for confirmation
+    confirm_expand_deep: bool,
+    /// Keys whose values are masked while `ui.redact` is on
+    redaction: Redaction,
+    /// The `y` command that was refused once for copying redacted values,
+    /// and copies them when repeated
+    confirm_redacted_yank: Option<char>,
+    /// Whether the selection is exported with its values masked, while
+    /// printing it with redaction on
+    mask_output: bool,
+    print_popup: Option<print::PrintPopup>,
+    /// Statistics popup of `S`, while open
+    stats_popup: Option<stats::StatsPopup>,
     search_mode: bool,
     search_query: String,
     search_matches: Vec<usize>,
     current_match_index: Option<usize>,
-    case_sensitive: bool,
-    cached_path: String,
+    /// Case mode of the current query, reset to the configured one for each search
+    search_case: CaseSensitivity,
+    /// Whether searches only look under the node selected when they started
+    search_subtree: bool,
+    /// Whether the words before the last must match the labels above a node
+    search_paths: bool,
+    search_origin: Option<usize>,
+    /// Search running on a worker thread
+    search_task: Option<SearchTask>,
+    /// When to search the query being typed
+    search_due: Option<Instant>,
+    /// Whether to select the first match once the running search finds it
+    search_jump: bool,
+    /// Whether the query filters the tree instead of only jumping between matches
+    search_filter: bool,
+    search_history: search_history::SearchHistory,
+    /// Where the view of each file is saved, if it is
+    view_state_dir: Option<PathBuf>,
+    /// Whether the matches were selected by an XPath query rather than searched
+    search_xpath: bool,
+    xpath_mode: bool,
+    /// Why the XPath query in the prompt is invalid
+    xpath_error: Option<String>,
+    /// File name prompt of `w`, while open
+    write_prompt: Option<write::WritePrompt>,
+    /// Value prompt of `A` or command prompt of `:`, while open
+    edit_prompt: Option<edit::EditPrompt>,
+    /// Values changed since the file was read or saved
+    edits: edit::Edits,
+    /// The key that was refused once for discarding unsaved changes, and
+    /// discards them when repeated
+    confirm_discard: Option<char>,
+    /// Path bar segments fitted to the path bar, see `path_bar_segments`
+    cached_path: Vec<(Option<usize>, String)>,
     last_selected_id: Option<usize>,
+    /// Path bar width the cached path was fitted to
+    cached_path_width: u16,
     show_decode_menu: bool,
+    show_marks: bool,
+    finder: Option<Finder>,
+    table_view: Option<TableView>,
+    marks_selected: usize,
+    marks: BTreeMap<char, marks::Mark>,
+    pins: Vec<declutter::Pin>,
+    /// Selected pin while `gp` has moved the focus to the pinned strip
+    pins_selected: Option<usize>,
+    jump_history: history::JumpHistory,
     decode_menu_selected: usize,
+    watch_file: bool,
+    /// The file the tree was read from, for reading it again
+    source: Option<reload::FileSource>,
+    /// Formats files are read in again with
+    parsers: ParserRegistry,
+    file_changed: bool,
+    last_file_check: Instant,
+    reload_error: Option<String>,
+    notification: Option<Notification>,
+    /// Notifications shown so far, listed by `W`
+    messages: notification::MessageHistory,
+    /// Where the tree and path bar were last drawn, for mouse hit-testing
+    tree_area: Rect,
+    path_area: Rect,
+    last_click: Option<mouse::Click>,
+    /// Count typed before a motion, e.g. the 25 of `25j`
+    pending_count: Option<usize>,
+    /// Share of the tree area given to the detail pane, in percent
+    detail_pane_percent: u16,
+    /// Nodes beginning a difference, in document order, when comparing files
+    differences: Option<Vec<usize>>,
+    /// Open files; the active one's state is in the fields above
+    tabs: Vec<tabs::Tab>,
+    active_tab: usize,
 }
 
 impl App {
-    pub fn new(tree: TreeVariant) -> Self {
+    pub fn new(tree: TreeVariant, config: Config) -> Self {
         let tree_view = TreeView::new(tree.root_id());
 
-        Self {
+        // The config was validated on load, so fall back quietly if it wasn't
+        let theme = Theme::from_config(&config.ui).unwrap_or_default();
+        let search_history = search_history::SearchHistory::new(config.search.history_size);
+        let search_case = config.search.case_sensitive;
+        let redaction = Redaction::new(&config.ui.redact_keys);
+
+        let mut app = Self {
+            config,
+            theme,
             tree,
             tree_view,
             should_quit: false,
+            suspend_requested: false,
             show_help: false,
+            help_scroll: 0,
+            help_page: 1,
             last_key_was_y: false,
             last_key_was_p: false,
-            print_content: None,
+            last_key_was_m: false,
+            last_key_was_quote: false,
+            last_key_was_z: false,
+            last_key_was_s: false,
+            last_key_was_g: false,
+            last_key_was_bracket: None,
+            z_count: None,
+            g_count: None,
+            last_key_was_record: false,
+            last_key_was_at: false,
+            at_count: None,
+            last_action: None,
+            recording: None,
+            macros: BTreeMap::new(),
+            last_macro: None,
+            confirm_expand_all: false,
+            confirm_expand_deep: false,
+            redaction,
+            confirm_redacted_yank: None,
+            mask_output: false,
+            print_popup: None,
+            stats_popup: None,
             search_mode: false,
             search_query: String::new(),
             search_matches: Vec::new(),
             current_match_index: None,
-            case_sensitive: false,
-            cached_path: String::new(),
+            search_case,
+            search_subtree: false,
+            search_paths: false,
+            search_origin: None,
+            search_task: None,
+            search_due: None,
+            search_jump: false,
+            search_filter: false,
+            search_history,
+            view_state_dir: None,
+            search_xpath: false,
+            xpath_mode: false,
+            xpath_error: None,
+            write_prompt: None,
+            edit_prompt: None,
+            edits: edit::Edits::default(),
+            confirm_discard: None,
+            cached_path: Vec::new(),
             last_selected_id: None,
+            cached_path_width: 0,
             show_decode_menu: false,
+            show_marks: false,
+            finder: None,
+            table_view: None,
+            marks_selected: 0,
+            marks: BTreeMap::new(),
+            pins: Vec::new(),
+            pins_selected: None,
+            jump_history: history::JumpHistory::default(),
             decode_menu_selected: 0,
+            watch_file: false,
+            source: None,
+            file_changed: false,
+            last_file_check: Instant::now(),
+            reload_error: None,
+            notification: None,
+            messages: notification::MessageHistory::default(),
+            tree_area: Rect::default(),
+            path_area: Rect::default(),
+            last_click: None,
+            pending_count: None,
+            detail_pane_percent: detail::DEFAULT_DETAIL_PANE_PERCENT,
+            differences: None,
+            tabs: vec![tabs::Tab::active(String::new())],
+            active_tab: 0,
+            parsers: ParserRegistry::default(),
+        };
+        app.reset_tree_view();
+        app
+    }
+
+    /// Switch between `@attributes` rows and attributes shown on their element's row
+    fn toggle_inline_attributes(&mut self) {
+        let selected = self.tree_view.get_selected_node_id();
+        self.config.ui.inline_attributes = !self.config.ui.inline_attributes;
+        self.tree_view
+            .set_inline_attributes(self.config.ui.inline_attributes);
+
+        // Matches inside attributes move between attribute and element rows
+        if !self.search_query.is_empty() {
+            self.find_matches(self.search_scope());
+            self.current_match_index = None;
+        }
+        if let Some(node_id) = selected {
+            let row = self.tree_view.row_node(&self.tree, node_id);
+            self.tree_view.navigate_to_node(&self.tree, row);
         }
     }
 
-    pub fn run(&mut self) -> Result<()> {
-        // Setup terminal
-        enable_raw_mode().map_err(|e| XtvError::Tui(e.to_string()))?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen).map_err(|e| XtvError::Tui(e.to_string()))?;
+    /// Replace the tree view with a fresh one for the current tree
+    fn reset_tree_view(&mut self) {
+        self.tree_view = TreeView::new(self.tree.root_id());
+        self.tree_view
+            .set_show_child_counts(self.config.ui.show_child_counts);
+        self.tree_view
+            .set_show_previews(self.config.ui.show_previews);
+        self.tree_view
+            .set_inline_attributes(self.config.ui.inline_attributes);
+        self.tree_view.set_line_numbers(self.config.ui.line_numbers);
+        self.tree_view.set_icons(self.config.ui.icons);
+        self.tree_view
+            .set_size_hint_threshold(self.config.ui.size_hint_threshold.0 as usize);
+        self.tree_view
+            .set_array_page_size(self.config.ui.array_page_size);
+        self.tree_view
+            .set_scrolloff(self.config.navigation.scrolloff);
+        self.tree_view.set_redaction(self.active_redaction());
+        let ldif = &self.config.parser.ldif;
+        if self.tree.format() == Some("ldif") && ldif.friendly_names {
+            self.tree_view
+                .set_attribute_labels(Some(AttributeLabels::new(&ldif.attribute_labels)));
+        }
+        self.apply_default_expansion();
+    }
+
+    /// Expand the tree to `ui.default_expanded_depth` (-1 expands everything).
+    ///
+    /// Streaming trees are capped at [`MAX_STREAMING_STARTUP_EXPANSIONS`]
+    /// expanded nodes, with a notification when the cap is hit.
+    fn apply_default_expansion(&mut self) {
+        let depth = usize::try_from(self.config.ui.default_expanded_depth).ok();
+        let max_nodes = matches!(self.tree, TreeVariant::Streaming(_))
+            .then_some(MAX_STREAMING_STARTUP_EXPANSIONS);
+
+        if !self.tree_view.expand_to_depth(&self.tree, depth, max_nodes) {
+            self.notify_warning(format!(
+                "Expansion stopped after {} nodes in streaming mode",
+                MAX_STREAMING_STARTUP_EXPANSIONS
+            ));
+        }
+    }
+
+    /// Tell in the footer how long loading the file took, such as
+    /// `Parsed 412.0 MB in 28.4s, 3.1M nodes`
+    pub fn with_load_timings(mut self, timings: &LoadTimings) -> Self {
+        self.notify(timings.summary());
+        self
+    }
 
-        let backend = CrosstermBackend::new(stdout);
+    /// Read files again in the formats of `parsers` instead of the built-in
+    /// ones, for formats registered by library users
+    pub fn with_parsers(mut self, parsers: ParserRegistry) -> Self {
+        self.parsers = parsers;
+        self
+    }
+
+    /// Read the file again automatically when it changes
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch_file = watch;
+        self
+    }
+
+    /// Keep the search history in a file, reading earlier queries from it
+    pub fn with_search_history_file(mut self, path: Option<PathBuf>) -> Self {
+        if let Some(path) = path
+            && let Err(e) = self.search_history.load(path)
+        {
+            self.notify_error(format!("Failed to read search history: {}", e));
+        }
+        self
+    }
+
+    /// Start at the node at `path` (see [`path::resolve_path`]), expanded
+    /// with its ancestors, or at the root with a warning if there is none
+    pub fn with_selection(mut self, path: &str) -> Self {
+        match path::resolve_path(&self.tree, path) {
+            Some(node_id) => {
+                self.reveal_node(node_id);
+                self.tree_view.expand_node(node_id);
+            }
+            None => self.notify_warning(format!("No node at {}, starting at the root", path)),
+        }
+        self
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        // Setup terminal; the guard restores it on every exit path, including panics
+        let guard = TerminalGuard::enter(self.config.ui.mouse)?;
+        let backend = CrosstermBackend::new(io::stdout());
         let mut terminal = Terminal::new(backend).map_err(|e| XtvError::Tui(e.to_string()))?;
 
         // Main loop
-        let result = self.main_loop(&mut terminal);
+        let result = self.main_loop(&mut terminal, &guard);
+        self.save_view_states();
 
         // Cleanup
-        disable_raw_mode().map_err(|e| XtvError::Tui(e.to_string()))?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)
-            .map_err(|e| XtvError::Tui(e.to_string()))?;
-        terminal
-            .show_cursor()
-            .map_err(|e| XtvError::Tui(e.to_string()))?;
-
-        result
+        drop(guard);
+        result.and_then(|()| interrupt::check())
     }
 
     fn main_loop<B: ratatui::backend::Backend>(
         &mut self,
         terminal: &mut Terminal<B>,
+        guard: &TerminalGuard,
     ) -> Result<()> {
-        while !self.should_quit {
+        while !self.should_quit && !interrupt::is_interrupted() {
+            let start = Instant::now();
             terminal
                 .draw(|f| self.render(f))
                 .map_err(|e| XtvError::Tui(e.to_string()))?;
+            if start.elapsed() > SLOW_STEP {
+                log::debug!("Drawing took {:?}", start.elapsed());
+            }
             self.handle_events()?;
+            if mem::take(&mut self.suspend_requested) {
+                guard.suspend(self.config.ui.mouse)?;
+                // The shell drew over the screen and the size may have changed
+                terminal.clear().map_err(|e| XtvError::Tui(e.to_string()))?;
+            }
+            self.poll_search();
+            self.poll_stats();
+            self.tree.poll_loaded();
+            self.poll_file_change();
+            self.collect_tree_messages();
+            self.expire_notification();
         }
         Ok(())
     }
 
     fn render(&mut self, frame: &mut ratatui::Frame) {
-        let main_chunks = Layout::default()
+        let tab_bar_height = if self.has_tabs() { 1 } else { 0 };
+        let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Min(0),    // Tree view
-                Constraint::Length(1), // Path bar
-                Constraint::Length(1), // Footer
+                Constraint::Length(tab_bar_height), // Tab bar
+                Constraint::Min(0),                 // Tree view
+                Constraint::Length(1),              // Path bar
+                Constraint::Length(1),              // Footer
             ])
             .split(frame.size());
+        if tab_bar_height > 0 {
+            self.render_tab_bar(frame, chunks[0]);
+        }
+        let main_chunks = &chunks[1..];
+        let (pins_area, main_area) = self.split_pins(main_chunks[0]);
+        if let Some(area) = pins_area {
+            self.render_pins(frame, area);
+        }
+        let (tree_area, detail_area) = self.split_detail_pane(main_area);
+        self.tree_area = tree_area;
+        self.path_area = main_chunks[1];
+
+        // Render tree view (no border)
+        let pattern = self.search_pattern();
+        let search = SearchHighlight {
+            matches: &self.search_matches,
+            current: self.current_match_index,
+            pattern: (!self.search_xpath).then_some(&pattern),
+        };
+        self.tree_view
+            .render(frame, tree_area, &self.tree, &self.theme, &search);
+        if let Some(area) = detail_area {
+            self.render_detail_pane(frame, area);
+        }
 
-        // Render tree view (full width, no border)
-        self.tree_view.render(
-            frame,
-            main_chunks[0],
-            &self.tree,
-            &self.search_matches,
-            self.current_match_index,
-        );
-
-        // Update path cache only if selection changed
+        // Update path cache only if selection or width changed
         let current_selected_id = self.tree_view.get_selected_node_id();
-        if current_selected_id != self.last_selected_id {
-            self.cached_path = self.compute_node_path();
+        let path_width = main_chunks[1].width;
+        if current_selected_id != self.last_selected_id || path_width != self.cached_path_width {
+            self.cached_path = self.path_bar_segments(path_width as usize);
             self.last_selected_id = current_selected_id;
+            self.cached_path_width = path_width;
         }
 
         // Render path bar using cached path
-        let path_bar =
-            Paragraph::new(self.cached_path.as_str()).style(Style::default().fg(Color::Gray));
-        frame.render_widget(path_bar, main_chunks[1]);
+        frame.render_widget(Paragraph::new(self.path_bar_line()), main_chunks[1]);
+
+        // Position indicator on the right of the footer, e.g. "1,234/56,789 (2%)"
+        let position = self.position_indicator();
+        let footer_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(position.len() as u16),
+            ])
+            .split(main_chunks[2]);
+        frame.render_widget(
+            Paragraph::new(position.as_str())
+                .alignment(Alignment::Right)
+                .style(Style::default().fg(self.theme.footer)),
+            footer_chunks[1],
+        );
+        let footer_area = footer_chunks[0];
 
         // Render footer or search bar
         if self.search_mode {
-            let search_text = format!("Search: {}", self.search_query);
-            let search_bar = Paragraph::new(search_text);
-            frame.render_widget(search_bar, main_chunks[2]);
-        } else if !self.search_matches.is_empty() {
+            let search_text = format!(
+                "{}{}: {}{}",
+                self.search_label(),
+                self.scope_tag(self.search_scope()),
+                self.search_query,
+                self.search_progress()
+            );
+            let search_bar =
+                Paragraph::new(search_text).style(Style::default().fg(self.theme.footer));
+            frame.render_widget(search_bar, footer_area);
+        } else if self.xpath_mode {
+            let style = if self.xpath_error.is_some() {
+                Style::default().fg(self.theme.error)
+            } else {
+                Style::default().fg(self.theme.footer)
+            };
+            frame.render_widget(
+                Paragraph::new(self.xpath_prompt()).style(style),
+                footer_area,
+            );
+        } else if let Some(prompt) = &self.write_prompt {
+            let style = if prompt.has_error() {
+                Style::default().fg(self.theme.error)
+            } else {
+                Style::default().fg(self.theme.footer)
+            };
+            frame.render_widget(
+                Paragraph::new(self.write_prompt_text()).style(style),
+                footer_area,
+            );
+        } else if let Some(prompt) = &self.edit_prompt {
+            let style = if prompt.has_error() {
+                Style::default().fg(self.theme.error)
+            } else {
+                Style::default().fg(self.theme.footer)
+            };
+            frame.render_widget(
+                Paragraph::new(self.edit_prompt_text()).style(style),
+                footer_area,
+            );
+        } else if self.notification.is_some() {
+            self.render_notification(frame, footer_area);
+        } else if !self.search_matches.is_empty() || self.is_searching() {
+            let scope = self.scope_tag(self.search_scope());
             let match_info = if let Some(idx) = self.current_match_index {
                 format!(
-                    " Search: {} ({}/{}) | n: Next | N: Previous | /: New search | Esc: Clear ",
+                    " {}{}: {} ({}/{}){} | n: Next | N: Previous | /: New search | Esc: Clear ",
+                    self.search_label(),
+                    scope,
                     self.search_query,
                     idx + 1,
-                    self.search_matches.len()
+                    self.search_matches.len(),
+                    self.search_progress()
                 )
             } else {
                 format!(
-                    " Search: {} (0/{}) ",
+                    " {}{}: {} (0/{}){} ",
+                    self.search_label(),
+                    scope,
                     self.search_query,
-                    self.search_matches.len()
+                    self.search_matches.len(),
+                    self.search_progress()
                 )
             };
-            let status_bar = Paragraph::new(match_info);
-            frame.render_widget(status_bar, main_chunks[2]);
+            let status_bar =
+                Paragraph::new(match_info).style(Style::default().fg(self.theme.footer));
+            frame.render_widget(status_bar, footer_area);
+        } else if let Some(error) = &self.reload_error {
+            let status_bar = Paragraph::new(format!(" Reload failed: {} | r: Retry ", error))
+                .style(Style::default().fg(self.theme.error));
+            frame.render_widget(status_bar, footer_area);
+        } else if self.file_changed {
+            let warning = if matches!(self.tree, TreeVariant::Streaming(_)) {
+                " File changed on disk - values may be stale | r: Reload | q: Quit "
+            } else {
+                " File changed on disk | r: Reload | q: Quit "
+            };
+            let status_bar = Paragraph::new(warning).style(Style::default().fg(self.theme.warning));
+            frame.render_widget(status_bar, footer_area);
         } else {
             let help_text =
                 " ↑/↓/j/k: Move | h/l: Smart nav | Space: Toggle | /: Search | ?: Help | q: Quit ";
-            let status_bar = Paragraph::new(help_text);
-            frame.render_widget(status_bar, main_chunks[2]);
+            let status_bar =
+                Paragraph::new(help_text).style(Style::default().fg(self.theme.footer));
+            frame.render_widget(status_bar, footer_area);
         }
 
         // Render help popup if shown
@@ -219,26 +657,117 @@ impl App {
             self.render_decode_menu(frame);
         }
 
-        // Render print popup if content is set
-        if self.print_content.is_some() {
-            self.render_print_popup(frame);
+        if self.show_marks {
+            self.render_marks_popup(frame);
         }
+
+        if let Some(finder) = &self.finder {
+            finder.render(frame, &self.theme);
+        }
+
+        if let Some(table) = &mut self.table_view {
+            table.render(frame, &self.theme);
+        }
+
+        self.render_stats_popup(frame);
+        self.render_print_popup(frame);
+        self.render_messages_popup(frame);
     }
 
-    fn compute_node_path(&self) -> String {
-        let selected_id = match self.tree_view.get_selected_node_id() {
-            Some(id) => id,
-            None => return String::new(),
+    /// The selected row out of all visible rows, e.g. `" 1,234/56,789 (2%) "`,
+    /// preceded by a count that is being typed, the macro being recorded and
+    /// how many nodes are hidden
+    fn position_indicator(&self) -> String {
+        let hidden = match self.tree_view.hidden_nodes().len() {
+            0 => String::new(),
+            n => format!(" {} hidden ", format_thousands(n)),
+        };
+        let hidden = match self.edit_indicator() {
+            Some(changed) => format!("{changed}{hidden}"),
+            None => hidden,
         };
+        let recording = self
+            .recording
+            .as_ref()
+            .map(|recording| format!("{hidden} recording @{} ", recording.register))
+            .unwrap_or(hidden);
+        let count = self
+            .pending_count
+            .map(|count| format!("{recording} {count} "))
+            .unwrap_or(recording);
+        let Some((row, total)) = self.tree_view.position() else {
+            return count;
+        };
+        format!(
+            "{count} {}/{} ({}%) ",
+            format_thousands(row),
+            format_thousands(total),
+            row * 100 / total
+        )
+    }
 
-        // Build path from root to selected node
-        let mut path_parts = Vec::new();
-        let mut current_id = selected_id;
+    /// Separator between labels in the path bar and in copied paths
+    fn path_separator(&self) -> &str {
+        &self.config.ui.path_separator
+    }
+
+    /// The cached path bar: ancestors in the path bar color between dimmed
+    /// separators, the selected node in bold
+    fn path_bar_line(&self) -> Line<'_> {
+        let Some(last) = self.cached_path.len().checked_sub(1) else {
+            return Line::default();
+        };
+        let separator = Style::default().fg(self.theme.icon);
+        let mut spans = vec![Span::raw(" ")];
+        for (i, (node_id, label)) in self.cached_path.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(self.path_separator(), separator));
+            }
+            let style = match node_id {
+                None => separator,
+                Some(_) if i == last => Style::default()
+                    .fg(self.theme.path_bar)
+                    .add_modifier(Modifier::BOLD),
+                Some(_) => Style::default().fg(self.theme.path_bar),
+            };
+            spans.push(Span::styled(label.as_str(), style));
+        }
+        Line::from(spans)
+    }
+
+    /// The path segments fitting into a path bar `width` columns wide.
+    ///
+    /// If the whole path is too long, ancestors in the middle are replaced by
+    /// one `…` segment without an ID, keeping the root, the last two segments
+    /// and as many more of the end of the path as fit.
+    fn path_bar_segments(&self, width: usize) -> Vec<(Option<usize>, String)> {
+        let segments = self.path_segments();
+        let widths: Vec<usize> = segments.iter().map(|(_, label)| label.width()).collect();
+        let elided = elided_path_segments(&widths, self.path_separator().width(), width);
+
+        let mut shown = Vec::new();
+        for (i, (id, label)) in segments.into_iter().enumerate() {
+            if !elided.contains(&i) {
+                shown.push((Some(id), label));
+            } else if i == elided.start {
+                shown.push((None, PATH_ELLIPSIS.to_string()));
+            }
+        }
+        shown
+    }
+
+    /// IDs and labels from the root down to the selected node
+    fn path_segments(&self) -> Vec<(usize, String)> {
+        let Some(selected_id) = self.tree_view.get_selected_node_id() else {
+            return Vec::new();
+        };
 
         // Walk up the tree to build the path
+        let mut segments = Vec::new();
+        let mut current_id = selected_id;
         loop {
             if let Some(node) = self.tree.get_node(current_id) {
-                path_parts.push(node.label.clone());
+                segments.push((current_id, node.label.to_string()));
             }
 
             // Find parent
@@ -249,27 +778,80 @@ impl App {
         }
 
         // Reverse to get root-to-leaf order
-        path_parts.reverse();
-
-        // Join with " > " separator
-        format!(" {}", path_parts.join(" > "))
+        segments.reverse();
+        segments
     }
 
     fn handle_events(&mut self) -> Result<()> {
-        if event::poll(std::time::Duration::from_millis(100))
+        // Wake up more often while search results or statistics are coming in
+        let timeout = if self.is_searching() || self.is_computing_stats() {
+            20
+        } else {
+            100
+        };
+        if event::poll(std::time::Duration::from_millis(timeout))
             .map_err(|e| XtvError::Tui(e.to_string()))?
         {
-            if let Event::Key(key) = event::read().map_err(|e| XtvError::Tui(e.to_string()))? {
-                self.handle_key(key)?;
+            match event::read().map_err(|e| XtvError::Tui(e.to_string()))? {
+                Event::Key(key) => {
+                    let start = Instant::now();
+                    if let Err(e) = self.handle_key(key) {
+                        log::error!("Handling {:?} failed: {}", key.code, e);
+                        return Err(e);
+                    }
+                    if start.elapsed() > SLOW_STEP {
+                        log::debug!("Handling {:?} took {:?}", key.code, start.elapsed());
+                    }
+                }
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                _ => {}
             }
         }
         Ok(())
     }
 
     fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        let before = self.selection();
+        // Tab toggles the search scope while typing a query
+        let history_key = !self.search_mode && !self.xpath_mode && history::is_history_key(&key);
+        let result = self.dispatch_key(key);
+        if !history_key {
+            self.record_jump(before);
+        }
+        result
+    }
+
+    fn dispatch_key(&mut self, key: KeyEvent) -> Result<()> {
+        // Any key dismisses the current notification
+        self.notification = None;
+
+        // Raw mode turns Ctrl-C and Ctrl-Z into keys, which quit and suspend
+        // from anywhere
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            if self.confirm_discard('c') {
+                self.should_quit = true;
+            }
+            return Ok(());
+        }
+        if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.suspend_requested = true;
+            return Ok(());
+        }
+
         // Handle modal states first
-        if self.print_content.is_some() {
-            return self.handle_print_popup_key();
+        if self.messages.is_open() {
+            self.handle_messages_popup_key(key);
+            return Ok(());
+        }
+
+        if self.print_popup.is_some() {
+            self.handle_print_popup_key(key);
+            return Ok(());
+        }
+
+        if self.stats_popup.is_some() {
+            self.handle_stats_popup_key(key);
+            return Ok(());
         }
 
         if self.show_help {
@@ -280,10 +862,63 @@ impl App {
             return self.handle_decode_menu_key(key);
         }
 
+        if self.show_marks {
+            self.handle_marks_popup_key(key);
+            return Ok(());
+        }
+
+        if self.pins_selected.is_some() {
+            self.handle_pins_key(key);
+            return Ok(());
+        }
+
+        if let Some(finder) = &mut self.finder {
+            match finder.handle_key(key) {
+                FinderAction::None => {}
+                FinderAction::Close => self.finder = None,
+                FinderAction::Jump(node_id) => {
+                    self.finder = None;
+                    // n and N continue from a match picked from the match list
+                    if let Some(index) = self.search_matches.iter().position(|&m| m == node_id) {
+                        self.current_match_index = Some(index);
+                    }
+                    self.reveal_node(node_id);
+                }
+            }
+            return Ok(());
+        }
+
+        if let Some(table) = &mut self.table_view {
+            match table.handle_key(key) {
+                TableAction::None => {}
+                TableAction::Close => self.table_view = None,
+                TableAction::Jump(node_id) => {
+                    self.table_view = None;
+                    let before = self.selection();
+                    self.reveal_node(node_id);
+                    self.record_jump(before);
+                }
+            }
+            return Ok(());
+        }
+
         if self.search_mode {
             return self.handle_search_input_key(key);
         }
 
+        if self.xpath_mode {
+            return self.handle_xpath_input_key(key);
+        }
+
+        if self.write_prompt.is_some() {
+            return self.handle_write_input_key(key);
+        }
+
+        if self.edit_prompt.is_some() {
+            self.handle_edit_input_key(key);
+            return Ok(());
+        }
+
         // Handle prefix keys
         if self.last_key_was_y {
             return self.handle_yank_command(key);
@@ -293,52 +928,72 @@ impl App {
             return self.handle_print_command(key);
         }
 
+        if self.last_key_was_m {
+            self.handle_mark_command(key);
+            return Ok(());
+        }
+
+        if self.last_key_was_quote {
+            self.handle_jump_command(key);
+            return Ok(());
+        }
+
+        if self.last_key_was_z {
+            self.handle_scroll_command(key);
+            return Ok(());
+        }
+
+        if self.last_key_was_s {
+            return self.handle_sort_command(key);
+        }
+
+        if self.last_key_was_g {
+            self.handle_g_command(key);
+            return Ok(());
+        }
+
+        if self.last_key_was_bracket.is_some() {
+            self.handle_diff_command(key);
+            return Ok(());
+        }
+
+        if self.last_key_was_record {
+            self.handle_record_command(key);
+            return Ok(());
+        }
+
+        if self.last_key_was_at {
+            return self.handle_replay_command(key);
+        }
+
         // Handle normal navigation/command keys
         self.handle_normal_key(key)
     }
 
-    /// Handle key press when print popup is visible
-    /// Any key closes the popup
-    fn handle_print_popup_key(&mut self) -> Result<()> {
-        self.print_content = None;
-        Ok(())
-    }
-
-    /// Handle key press when help screen is visible
+    /// Handle key press when help screen is visible: j/k and the page keys
+    /// scroll it
     fn handle_help_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('?') | KeyCode::Esc => {
+        let page = self.help_page as isize;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let max_scroll = self.help_lines().len().saturating_sub(self.help_page);
+        let rows = match key.code {
+            KeyCode::Char('?') | KeyCode::Esc | KeyCode::Char('q') => {
                 self.show_help = false;
+                return Ok(());
             }
-            _ => {}
-        }
-        Ok(())
-    }
-
-    /// Handle key press during search input mode
-    fn handle_search_input_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => {
-                // Exit search mode
-                self.search_mode = false;
-                self.search_query.clear();
-                self.search_matches.clear();
-                self.current_match_index = None;
-            }
-            KeyCode::Enter => {
-                // Exit search mode but keep search active
-                self.search_mode = false;
-            }
-            KeyCode::Backspace => {
-                self.search_query.pop();
-                self.perform_search();
-            }
-            KeyCode::Char(c) => {
-                self.search_query.push(c);
-                self.perform_search();
-            }
-            _ => {}
-        }
+            KeyCode::Char('d') if ctrl => page / 2,
+            KeyCode::Char('u') if ctrl => -page / 2,
+            KeyCode::Char('f') if ctrl => page,
+            KeyCode::Char('b') if ctrl => -page,
+            KeyCode::Down | KeyCode::Char('j') => 1,
+            KeyCode::Up | KeyCode::Char('k') => -1,
+            KeyCode::PageDown | KeyCode::Char(' ') => page,
+            KeyCode::PageUp => -page,
+            KeyCode::Home | KeyCode::Char('g') => isize::MIN,
+            KeyCode::End | KeyCode::Char('G') => isize::MAX,
+            _ => 0,
+        };
+        self.help_scroll = self.help_scroll.saturating_add_signed(rows).min(max_scroll);
         Ok(())
     }
 
@@ -373,165 +1028,412 @@ impl App {
     fn handle_yank_command(&mut self, key: KeyEvent) -> Result<()> {
         self.last_key_was_y = false;
         match key.code {
-            KeyCode::Char('y') => {
-                if let Some(text) = self.get_node_value_pretty() {
-                    let _ = self.copy_to_clipboard(&text);
-                }
-                return Ok(());
+            KeyCode::Char(command) if YANK_COMMANDS.contains(command) => {
+                self.run_action(repeat::Action::Yank(command))
             }
-            KeyCode::Char('v') => {
-                if let Some(text) = self.get_node_value_compact() {
-                    let _ = self.copy_to_clipboard(&text);
-                }
-                return Ok(());
+            // If we didn't handle it, process as normal key
+            _ => self.handle_normal_key(key),
+        }
+    }
+
+    /// Copy what the yank command named by the key after `y` selects
+    fn yank(&mut self, command: char) -> Result<()> {
+        if !self.confirm_redacted_yank(command) {
+            return Ok(());
+        }
+        let text = match command {
+            'y' => self.get_node_value_pretty(),
+            'v' => self.get_node_value_compact(),
+            's' => self.get_node_string_value(),
+            'k' => self.get_node_key(),
+            'p' => self.get_breadcrumb(),
+            'j' => self.get_jq_path(),
+            'x' => self.get_xpath(),
+            '6' => self.get_json_pointer(),
+            'l' => self.get_source_location(),
+            'o' => self.get_node_source(),
+            'Y' => self.get_node_converted(convert::Format::Yaml),
+            'T' => self.get_node_converted(convert::Format::Toml),
+            'C' => self.get_node_converted(convert::Format::Csv),
+            't' => self.get_visible_tree_text(),
+            'f' => self.get_referenced_path(),
+            _ => return Ok(()),
+        };
+        match text {
+            Some(text) => self.copy_to_clipboard(&text),
+            // Some lookups already explained why they came up empty
+            None if self.notification.is_none() => {
+                self.notify_warning("Nothing to copy for the selected node")
             }
-            KeyCode::Char('s') => {
-                if let Some(text) = self.get_node_string_value() {
-                    let _ = self.copy_to_clipboard(&text);
-                }
-                return Ok(());
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Handle 'z' prefix commands: scroll the selection to the center, top or
+    /// bottom, or collapse and expand the whole tree
+    fn handle_scroll_command(&mut self, key: KeyEvent) {
+        self.last_key_was_z = false;
+        let count = self.z_count.take();
+        if key.code != KeyCode::Char('R') {
+            self.confirm_expand_all = false;
+        }
+
+        match key.code {
+            KeyCode::Char('z') => self.tree_view.align_selection(Align::Center),
+            KeyCode::Char('t') => self.tree_view.align_selection(Align::Top),
+            KeyCode::Char('b') => self.tree_view.align_selection(Align::Bottom),
+            KeyCode::Char('M') => self.tree_view.collapse_all(&self.tree),
+            KeyCode::Char('R') => self.expand_all(count),
+            KeyCode::Char('p') => self.toggle_paging(),
+            KeyCode::Char('h') | KeyCode::Char('l') => {
+                let columns = (count.unwrap_or(1) * HORIZONTAL_SCROLL_COLUMNS) as isize;
+                let sign = if key.code == KeyCode::Char('h') {
+                    -1
+                } else {
+                    1
+                };
+                self.tree_view.scroll_horizontally(sign * columns);
             }
-            KeyCode::Char('k') => {
-                if let Some(text) = self.get_node_key() {
-                    let _ = self.copy_to_clipboard(&text);
+            KeyCode::Char('H') | KeyCode::Char('L') => {
+                let columns = (self.tree_view.row_width() / 2).max(1) as isize;
+                let sign = if key.code == KeyCode::Char('H') {
+                    -1
+                } else {
+                    1
+                };
+                self.tree_view.scroll_horizontally(sign * columns);
+            }
+            KeyCode::Char('w') => {
+                if self.tree_view.toggle_wrap_selected() {
+                    self.notify("Wrapping the selected row");
+                } else {
+                    self.notify("Cutting rows at the right edge");
                 }
-                return Ok(());
             }
-            _ => {
-                // Fall through to normal handling
+            _ => {}
+        }
+    }
+
+    /// Expand the whole tree, or `depth` levels of it.
+    ///
+    /// Expanding a large or streamed tree completely can take a long time, so
+    /// the first `zR` only asks for confirmation.
+    fn expand_all(&mut self, depth: Option<usize>) {
+        let guarded = matches!(self.tree, TreeVariant::Streaming(_))
+            || self.tree.node_count() > self.config.navigation.expand_all_threshold;
+        if depth.is_none() && guarded && !self.confirm_expand_all {
+            self.confirm_expand_all = true;
+            self.notify_warning(format!(
+                "Expand all {} nodes? Press zR again to confirm",
+                format_thousands(self.tree.node_count())
+            ));
+            return;
+        }
+        self.confirm_expand_all = false;
+        self.tree_view.expand_all(&self.tree, depth);
+    }
+
+    /// Show the elements of the selected array in pages or all at once
+    fn toggle_paging(&mut self) {
+        let size = self.config.ui.array_page_size;
+        match self.tree_view.toggle_paging(&self.tree) {
+            Some(true) => self.notify(format!(
+                "Showing the elements in pages of {}",
+                format_thousands(size)
+            )),
+            Some(false) => self.notify("Showing all elements at once"),
+            None if size == 0 => self.notify_warning("Array pages are off (ui.array_page_size)"),
+            None => self.notify_warning(format!(
+                "Only arrays of more than {} elements have pages",
+                format_thousands(size)
+            )),
+        }
+    }
+
+    /// Expand the selected node, its siblings and all their descendants.
+    ///
+    /// Near the root of a large or streamed tree that can be most of the tree,
+    /// so like `zR` the first `E` only asks for confirmation then.
+    fn expand_siblings_deep(&mut self) {
+        if !self.confirm_expand_deep {
+            let threshold = self.config.navigation.expand_all_threshold;
+            if self.tree_view.siblings_deep_count(&self.tree, threshold) > threshold {
+                self.confirm_expand_deep = true;
+                self.notify_warning(format!(
+                    "Expand more than {} nodes? Press E again to confirm",
+                    format_thousands(threshold)
+                ));
+                return;
             }
         }
-        // If we didn't handle it, process as normal key
-        self.handle_normal_key(key)
+        self.confirm_expand_deep = false;
+        self.tree_view.expand_all_siblings_deep(&self.tree);
     }
 
     /// Handle 'p' prefix commands (print to popup)
     fn handle_print_command(&mut self, key: KeyEvent) -> Result<()> {
         self.last_key_was_p = false;
-        match key.code {
-            KeyCode::Char('p') => {
-                self.print_content = self.get_node_value_pretty();
-                return Ok(());
-            }
-            KeyCode::Char('v') => {
-                self.print_content = self.get_node_value_compact();
-                return Ok(());
-            }
-            KeyCode::Char('s') => {
-                self.print_content = self.get_node_string_value();
-                return Ok(());
-            }
-            KeyCode::Char('k') => {
-                self.print_content = self.get_node_key();
-                return Ok(());
-            }
+        self.mask_output = self.config.ui.redact;
+        let (content, syntax) = match key.code {
+            KeyCode::Char('p') => (self.get_node_value_pretty(), Some(Syntax::Json)),
+            KeyCode::Char('v') => (self.get_node_value_compact(), Some(Syntax::Json)),
+            KeyCode::Char('s') => (self.get_node_string_value(), None),
+            KeyCode::Char('k') => (self.get_node_key(), None),
+            // `pp` prints the value, so the breadcrumb is `pb`
+            KeyCode::Char('b') => (self.get_breadcrumb(), None),
+            KeyCode::Char('j') => (self.get_jq_path(), None),
+            KeyCode::Char('x') => (self.get_xpath(), None),
+            KeyCode::Char('6') => (self.get_json_pointer(), None),
+            KeyCode::Char('l') => (self.get_source_location(), None),
+            KeyCode::Char('o') => (
+                self.get_node_source(),
+                self.tree.format().and_then(Syntax::for_format),
+            ),
+            KeyCode::Char('Y') => (
+                self.get_node_converted(convert::Format::Yaml),
+                Some(Syntax::Yaml),
+            ),
+            KeyCode::Char('T') => (self.get_node_converted(convert::Format::Toml), None),
+            KeyCode::Char('C') => (self.get_node_converted(convert::Format::Csv), None),
+            KeyCode::Char('t') => (self.get_visible_tree_text(), None),
+            KeyCode::Char('f') => (self.get_referenced_file(), None),
+            // If we didn't handle it, process as normal key
             _ => {
-                // Fall through to normal handling
+                self.mask_output = false;
+                return self.handle_normal_key(key);
             }
-        }
-        // If we didn't handle it, process as normal key
-        self.handle_normal_key(key)
+        };
+        self.mask_output = false;
+        self.print(content, syntax);
+        Ok(())
     }
 
     /// Handle normal navigation and command keys
     fn handle_normal_key(&mut self, key: KeyEvent) -> Result<()> {
+        // Digits start or extend a count, except a leading 0 (first sibling)
+        if let KeyCode::Char(c @ '0'..='9') = key.code
+            && (c != '0' || self.pending_count.is_some())
+        {
+            let digit = c.to_digit(10).unwrap_or(0) as usize;
+            let count = self.pending_count.unwrap_or(0) * 10 + digit;
+            self.pending_count = Some(count.min(MAX_COUNT));
+            return Ok(());
+        }
+        let explicit_count = self.pending_count.take();
+        let count = explicit_count.unwrap_or(1).max(1);
+        // Only `zR` right after `zR` confirms expanding everything
+        if key.code != KeyCode::Char('z') {
+            self.confirm_expand_all = false;
+        }
+        // `.` after `E` confirms it like a second `E`
+        let repeats_deep_expand = key.code == KeyCode::Char('.')
+            && self.last_action == Some(repeat::Action::ExpandSiblingsDeep);
+        if key.code != KeyCode::Char('E') && !repeats_deep_expand {
+            self.confirm_expand_deep = false;
+        }
+        // Only the same `y` command right after confirms copying redacted values
+        if key.code != KeyCode::Char('y') {
+            self.confirm_redacted_yank = None;
+        }
+        // Only the same key right after confirms discarding unsaved changes
+        if !matches!(key.code, KeyCode::Char('q' | 'r') | KeyCode::Esc) {
+            self.confirm_discard = None;
+        }
+
         match key.code {
             KeyCode::Char('q') => {
-                self.should_quit = true;
+                self.close_tab();
             }
             KeyCode::Char('?') => {
                 self.show_help = true;
+                self.help_scroll = 0;
+            }
+            KeyCode::Esc if explicit_count.is_some() => {
+                // Only cancel the pending count, which was already taken
             }
             KeyCode::Esc => {
                 // Clear search if active, otherwise quit
-                if !self.search_matches.is_empty() {
-                    self.search_query.clear();
-                    self.search_matches.clear();
-                    self.current_match_index = None;
+                if !self.search_matches.is_empty() || self.is_searching() {
+                    self.clear_search();
                 } else {
-                    self.should_quit = true;
+                    self.close_tab();
                 }
             }
             KeyCode::Up | KeyCode::Char('k') => {
-                self.tree_view.navigate_up();
+                self.run_action(repeat::Action::MoveBy(-(count as isize)))?;
             }
             KeyCode::Down | KeyCode::Char('j') => {
-                self.tree_view.navigate_down(&self.tree);
+                self.run_action(repeat::Action::MoveBy(count as isize))?;
             }
             KeyCode::Enter => {
-                self.tree_view.toggle_expand(&self.tree);
+                self.run_action(repeat::Action::Toggle)?;
+            }
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_match_list();
             }
             KeyCode::Right | KeyCode::Char('l') => {
-                self.tree_view.smart_right(&self.tree);
+                self.run_action(repeat::Action::SmartRight(count))?;
             }
             KeyCode::Left | KeyCode::Char('h') => {
-                self.tree_view.smart_left(&self.tree);
+                self.run_action(repeat::Action::SmartLeft(count))?;
             }
             KeyCode::Char('H') => {
-                self.tree_view.navigate_to_parent(&self.tree);
+                self.run_action(repeat::Action::Parent(count))?;
             }
             KeyCode::Char(' ') => {
-                self.tree_view.toggle_expand(&self.tree);
+                self.run_action(repeat::Action::Toggle)?;
             }
             KeyCode::Char('J') => {
-                self.tree_view.navigate_to_next_sibling(&self.tree);
+                self.run_action(repeat::Action::NextSibling(count))?;
             }
             KeyCode::Char('K') => {
-                self.tree_view.navigate_to_previous_sibling(&self.tree);
+                self.run_action(repeat::Action::PreviousSibling(count))?;
             }
             KeyCode::Char('0') => {
-                self.tree_view.navigate_to_first_sibling(&self.tree);
+                self.run_action(repeat::Action::FirstSibling)?;
             }
             KeyCode::Char('$') => {
-                self.tree_view.navigate_to_last_sibling(&self.tree);
+                self.run_action(repeat::Action::LastSibling)?;
+            }
+            KeyCode::Char('G') if explicit_count.is_some() => {
+                self.run_action(repeat::Action::GoToLine(count))?;
             }
             KeyCode::Char('g') => {
-                self.tree_view.navigate_to_first_line();
+                // 42gg goes to a line, 42gn to a match
+                self.last_key_was_g = true;
+                self.g_count = explicit_count;
             }
             KeyCode::Char('G') => {
-                self.tree_view.navigate_to_last_line(&self.tree);
+                self.run_action(repeat::Action::LastLine)?;
             }
             KeyCode::Char('e') => {
-                self.tree_view.expand_all_siblings(&self.tree);
+                self.run_action(repeat::Action::ExpandSiblings)?;
             }
             KeyCode::Char('E') => {
-                self.tree_view.expand_all_siblings_deep(&self.tree);
+                self.run_action(repeat::Action::ExpandSiblingsDeep)?;
             }
             KeyCode::Char('c') => {
-                self.tree_view.collapse_all_siblings(&self.tree);
+                self.run_action(repeat::Action::CollapseSiblings)?;
             }
             KeyCode::Char('C') => {
-                self.tree_view.collapse_all_siblings_deep(&self.tree);
+                self.run_action(repeat::Action::CollapseSiblingsDeep)?;
+            }
+            KeyCode::Char('.') => {
+                self.repeat_action(count)?;
+            }
+            KeyCode::Char('Q') => {
+                self.toggle_recording();
+            }
+            KeyCode::Char('@') => {
+                self.last_key_was_at = true;
+                self.at_count = explicit_count;
+            }
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.jump_back();
+            }
+            // Terminals send Ctrl-I as Tab
+            KeyCode::Tab => {
+                self.jump_forward();
+            }
+            KeyCode::Char('i') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.jump_forward();
+            }
+            KeyCode::Char('i') => {
+                // Stored in the config so the choice survives a reload
+                self.config.ui.show_previews = !self.config.ui.show_previews;
+                self.tree_view
+                    .set_show_previews(self.config.ui.show_previews);
+            }
+            KeyCode::Char('a') => {
+                self.toggle_inline_attributes();
+            }
+            KeyCode::Char('L') => {
+                self.config.ui.line_numbers = self.config.ui.line_numbers.next();
+                self.tree_view.set_line_numbers(self.config.ui.line_numbers);
+            }
+            KeyCode::Char('D') => self.toggle_detail_pane(),
+            KeyCode::Char('W') => self.open_messages_popup(),
+            KeyCode::Char('S') => self.show_stats(),
+            KeyCode::Char('T') => self.open_table_view(),
+            KeyCode::Char('X') => self.hide_selected(),
+            KeyCode::Char('U') => self.unhide_all(),
+            KeyCode::Char('P') => self.toggle_pin(),
+            KeyCode::Char('R') => self.toggle_redaction(),
+            KeyCode::Char('O') => self.toggle_embedded_json(),
+            KeyCode::Char('A') => self.start_edit(),
+            KeyCode::Char(':') => self.start_command(),
+            KeyCode::Char('<') => self.resize_detail_pane(1),
+            KeyCode::Char('>') => self.resize_detail_pane(-1),
+            KeyCode::Char(bracket @ ('[' | ']')) if self.is_diff() => {
+                self.last_key_was_bracket = Some(bracket);
             }
             KeyCode::PageUp | KeyCode::Char('[') => {
-                for _ in 0..PAGE_SCROLL_LINES {
-                    self.tree_view.navigate_up();
-                }
+                self.run_action(repeat::Action::ScrollPages(-(count as isize), 1))?;
             }
             KeyCode::PageDown | KeyCode::Char(']') => {
-                for _ in 0..PAGE_SCROLL_LINES {
-                    self.tree_view.navigate_down(&self.tree);
-                }
+                self.run_action(repeat::Action::ScrollPages(count as isize, 1))?;
             }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.run_action(repeat::Action::ScrollPages(-1, 2))?;
+            }
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.run_action(repeat::Action::ScrollPages(1, 2))?;
+            }
+            KeyCode::Char('u') => self.undo_edit(),
             KeyCode::Char('y') => {
                 self.last_key_was_y = true;
                 return Ok(());
             }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.finder = Some(Finder::new(&self.tree, self.path_separator()));
+            }
             KeyCode::Char('p') => {
                 self.last_key_was_p = true;
                 return Ok(());
             }
+            KeyCode::Char('m') => {
+                self.last_key_was_m = true;
+            }
+            KeyCode::Char('\'') => {
+                self.last_key_was_quote = true;
+            }
+            KeyCode::Char('M') => {
+                self.open_marks_popup();
+            }
+            KeyCode::Char('z') => {
+                self.last_key_was_z = true;
+                self.z_count = explicit_count;
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.save_edits();
+            }
+            KeyCode::Char('s') => {
+                self.last_key_was_s = true;
+            }
             KeyCode::Char('/') => {
-                self.search_mode = true;
-                self.search_query.clear();
-                self.search_matches.clear();
-                self.current_match_index = None;
+                self.start_search();
+            }
+            KeyCode::Char('x') => {
+                self.start_xpath();
+            }
+            KeyCode::Char('*') => {
+                self.search_same_value();
+            }
+            KeyCode::Char('w') => {
+                self.start_write();
             }
             KeyCode::Char('n') => {
-                self.next_match();
+                self.run_action(repeat::Action::NextMatch(count))?;
             }
             KeyCode::Char('N') => {
-                self.previous_match();
+                self.run_action(repeat::Action::PreviousMatch(count))?;
+            }
+            KeyCode::Char('F') => {
+                self.toggle_search_filter();
+            }
+            KeyCode::Char('r') if self.confirm_discard('r') => {
+                self.reload_tree();
             }
             KeyCode::Char('d') => {
                 // Only show decode menu if we're on a node with a value
@@ -550,32 +1452,90 @@ impl App {
         Ok(())
     }
 
-    // Get the node value as pretty-printed JSON
-    fn get_node_value_pretty(&self) -> Option<String> {
-        let node_id = self.tree_view.get_selected_node_id()?;
-        let node = self.tree.get_node(node_id)?;
+    /// Move the selection by `direction` times `navigation.page_scroll_lines / divisor` rows
+    fn scroll_pages(&mut self, direction: isize, divisor: usize) {
+        let lines = (self.config.navigation.page_scroll_lines / divisor).max(1);
+        self.tree_view
+            .navigate_by(direction * lines as isize, &self.tree);
+    }
 
-        // Convert node to JSON value and pretty print
-        let json_value = self.node_to_json(node_id, &node)?;
-        serde_json::to_string_pretty(&json_value).ok()
+    // Get the node value as pretty-printed JSON
+    fn get_node_value_pretty(&mut self) -> Option<String> {
+        let value = self.export_selected()?;
+        serde_json::to_string_pretty(&value).ok()
     }
 
     // Get the node value as compact one-line JSON
-    fn get_node_value_compact(&self) -> Option<String> {
+    fn get_node_value_compact(&mut self) -> Option<String> {
+        let value = self.export_selected()?;
+        serde_json::to_string(&value).ok()
+    }
+
+    /// The selected subtree as JSON, warning if parts of it were left out
+    fn export_selected(&mut self) -> Option<serde_json::Value> {
         let node_id = self.tree_view.get_selected_node_id()?;
-        let node = self.tree.get_node(node_id)?;
+        let mut export = export::to_json(&self.tree, node_id)?;
+        if self.mask_output && self.config.ui.redact {
+            let redacted = self.redaction.redacts(&self.tree, node_id);
+            self.redaction.redact_json(&mut export.value, redacted);
+        }
+        if export.truncated {
+            self.notify_warning(format!(
+                "Subtree too large: parts beyond {} nodes or {} levels were left out as \"…\"",
+                format_thousands(export::MAX_EXPORT_NODES),
+                export::MAX_EXPORT_DEPTH
+            ));
+        }
+        Some(export.value)
+    }
+
+    /// The selected subtree written in the input's format, e.g. as YAML when
+    /// viewing a YAML file
+    fn get_node_source(&mut self) -> Option<String> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        if self.mask_output && self.selection_redacted() {
+            self.notify_warning("The source can't be printed with redacted values (R shows them)");
+            return None;
+        }
+        let Some(serializer) = self.tree.format().and_then(parser::get_serializer) else {
+            self.notify_warning("Subtrees can't be written in this file's format");
+            return None;
+        };
+        match serializer.serialize(&self.tree, node_id) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                self.notify_error(e.to_string());
+                None
+            }
+        }
+    }
 
-        let json_value = self.node_to_json(node_id, &node)?;
-        serde_json::to_string(&json_value).ok()
+    /// The selected subtree converted to `format`, whatever the input's
+    /// format, e.g. the records of a JSON Lines file as CSV
+    fn get_node_converted(&mut self, format: convert::Format) -> Option<String> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        let value = self.export_selected()?;
+        let key = self.tree.get_label(node_id).unwrap_or_default();
+        match format.convert(value, &key) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                self.notify_error(e.to_string());
+                None
+            }
+        }
     }
 
     // Get the string value if the node is a string
     fn get_node_string_value(&self) -> Option<String> {
         let node_id = self.tree_view.get_selected_node_id()?;
-        let node = self.tree.get_node(node_id)?;
+        let node = self.tree.get_node_blocking(node_id)?;
+        if self.mask_output && self.redacts(node_id) {
+            return Some(MASK.to_string());
+        }
 
         // For attribute nodes, get the value
-        if node.is_attribute() || node.node_type == "text" || node.node_type == "comment" {
+        if node.is_attribute() || matches!(node.node_type.as_str(), "text" | "comment" | "doctype")
+        {
             node.attributes.first().map(|attr| attr.value.clone())
         } else {
             None
@@ -585,16 +1545,87 @@ impl App {
     // Get the key/label of the current node
     fn get_node_key(&self) -> Option<String> {
         let node_id = self.tree_view.get_selected_node_id()?;
-        let node = self.tree.get_node(node_id)?;
-        Some(node.label.clone())
+        let node = self.tree.get_node_blocking(node_id)?;
+        Some(node.label.to_string())
+    }
+
+    /// The path to the current node as shown in the path bar
+    fn get_breadcrumb(&self) -> Option<String> {
+        let labels: Vec<String> = self
+            .path_segments()
+            .into_iter()
+            .map(|(_, label)| label)
+            .collect();
+        (!labels.is_empty()).then(|| labels.join(self.path_separator()))
+    }
+
+    /// A jq path to the current node, e.g. `.users[3].email`
+    fn get_jq_path(&self) -> Option<String> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        Some(path::jq_path(&self.tree, node_id))
+    }
+
+    /// An XPath to the current node, e.g. `/catalog/item[2]/@id`
+    fn get_xpath(&self) -> Option<String> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        Some(path::xpath(&self.tree, node_id))
+    }
+
+    /// A JSON Pointer (RFC 6901) to the current node, e.g. `/users/3/email`
+    fn get_json_pointer(&mut self) -> Option<String> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        if node_id == self.tree.root_id() {
+            self.notify_warning("The JSON Pointer to the root is the empty string");
+            return None;
+        }
+        Some(path::json_pointer(&self.tree, node_id))
+    }
+
+    /// Where the current node starts in its file, as `file:line`, or only
+    /// the line for standard input
+    fn get_source_location(&mut self) -> Option<String> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        let span = self.tree.get_node_blocking(node_id)?.source_span;
+        let Some((line, _)) = span else {
+            self.notify_warning("The position of this node in the file isn't known");
+            return None;
+        };
+        Some(match &self.source {
+            Some(source) => format!("{}:{}", source.path.display(), line),
+            None => line.to_string(),
+        })
+    }
+
+    /// Every row the tree shows, as text without icons for pasting elsewhere
+    pub(super) fn get_visible_tree_text(&mut self) -> Option<String> {
+        let layout = if self.config.ui.copy_ascii_tree {
+            TextLayout::Guides
+        } else {
+            TextLayout::Plain
+        };
+        let options = TextOptions {
+            layout,
+            full_values: self.config.ui.copy_full_values,
+            color: false,
+        };
+        let rows = self
+            .tree_view
+            .visible_rows(&self.tree, &self.theme, &options);
+        Some(render_text::rows_to_string(&rows, layout))
     }
 
     /// Execute the selected decode option
     fn execute_decode_option(&mut self) {
-        let value = match self.get_node_string_value() {
-            Some(v) => v,
-            None => return,
+        let Some(value) = self.get_node_string_value() else {
+            self.notify_warning("Only string values can be decoded");
+            return;
         };
+        if let Some(node_id) = self.tree_view.get_selected_node_id()
+            && self.redacts(node_id)
+        {
+            self.notify_warning("Redacted values can't be decoded (R shows them)");
+            return;
+        }
 
         let options = DecodeOption::all();
         if self.decode_menu_selected >= options.len() {
@@ -610,8 +1641,9 @@ impl App {
             DecodeOption::RawValue => Some(value),
         };
 
-        if let Some(result) = decoded {
-            self.print_content = Some(result);
+        match decoded {
+            Some(result) => self.print(Some(result), None),
+            None => self.notify_warning(format!("Can't {}", option.label().to_lowercase())),
         }
     }
 
@@ -707,161 +1739,65 @@ impl App {
         None
     }
 
-    /// Convert a tree node to a JSON value
-    /// Recursively builds JSON objects and arrays from tree structure
-    fn node_to_json(
-        &self,
-        node_id: usize,
-        node: &crate::tree::TreeNode,
-    ) -> Option<serde_json::Value> {
-        use serde_json::{Map, Value};
-
-        // For attribute nodes, return the value directly
-        if node.is_attribute() {
-            if let Some(attr) = node.attributes.first() {
-                // Try to parse as JSON, otherwise return as string
-                return serde_json::from_str(&attr.value)
-                    .unwrap_or_else(|_| Value::String(attr.value.clone()))
-                    .into();
-            }
-        }
-
-        // For text/comment nodes, return the content
-        if node.node_type == "text" || node.node_type == "comment" {
-            if let Some(content) = node.attributes.iter().find(|a| a.key == "content") {
-                return Some(Value::String(content.value.clone()));
-            }
-        }
-
-        // For container nodes, recursively build object or array
-        if node.node_type == "object" {
-            let mut map = Map::new();
-            let children = self.tree.get_children(node_id);
-            for child_id in children {
-                if let Some(child_node) = self.tree.get_node(child_id) {
-                    // Skip virtual attributes node
-                    if child_node.node_type == crate::tree::TreeNode::VIRTUAL_ATTRIBUTES_TYPE {
-                        continue;
-                    }
-                    if let Some(value) = self.node_to_json(child_id, &child_node) {
-                        map.insert(child_node.label.clone(), value);
-                    }
-                }
-            }
-            return Some(Value::Object(map));
-        } else if node.node_type == "array" {
-            let mut arr = Vec::new();
-            let children = self.tree.get_children(node_id);
-            for child_id in children {
-                if let Some(child_node) = self.tree.get_node(child_id) {
-                    // Skip virtual attributes node
-                    if child_node.node_type == crate::tree::TreeNode::VIRTUAL_ATTRIBUTES_TYPE {
-                        continue;
-                    }
-                    if let Some(value) = self.node_to_json(child_id, &child_node) {
-                        arr.push(value);
-                    }
-                }
+    // Copy text to clipboard
+    // Jump to the current search match
+    /// Handle the key after `g`: `gg` goes to the first line or the line of
+    /// a count, `gn` to a match, `gd` to the entry of an LDIF DN, `g*` lists
+    /// identical subtrees, `g1` to `g9` go to an ancestor, `gp` to the pinned
+    /// nodes, and `gt`/`gT` switch tabs
+    fn handle_g_command(&mut self, key: KeyEvent) {
+        self.last_key_was_g = false;
+        let count = self.g_count.take();
+        match key.code {
+            KeyCode::Char('g') => self.go_to_line(count.unwrap_or(1)),
+            KeyCode::Char('n') => self.jump_to_match(count.unwrap_or(1)),
+            KeyCode::Char('d') => self.jump_to_dn(),
+            KeyCode::Char('*') => self.show_duplicate_subtrees(),
+            KeyCode::Char('p') => self.focus_pins(),
+            KeyCode::Char(c @ '1'..='9') => {
+                self.jump_to_path_segment(c as usize - '0' as usize);
             }
-            return Some(Value::Array(arr));
+            KeyCode::Char('t' | 'T') if self.has_tabs() => self.handle_tab_command(key),
+            _ => {}
         }
-
-        // Default: return label as string
-        Some(Value::String(node.label.clone()))
-    }
-
-    // Copy text to clipboard
-    fn copy_to_clipboard(&self, text: &str) -> Result<()> {
-        use arboard::Clipboard;
-        let mut clipboard =
-            Clipboard::new().map_err(|e| XtvError::Tui(format!("Clipboard error: {}", e)))?;
-        clipboard
-            .set_text(text.to_string())
-            .map_err(|e| XtvError::Tui(format!("Failed to copy to clipboard: {}", e)))?;
-        Ok(())
     }
 
-    // Perform search and update matches
-    fn perform_search(&mut self) {
-        self.search_matches.clear();
-        self.current_match_index = None;
-
-        if self.search_query.is_empty() {
-            return;
-        }
-
-        let query = if self.case_sensitive {
-            self.search_query.clone()
-        } else {
-            self.search_query.to_lowercase()
-        };
-
-        // Use iterative depth-first traversal with a stack to avoid collecting all nodes
-        // This prevents memory exhaustion on large streaming trees
-        let mut stack = vec![self.tree.root_id()];
-        let mut nodes_searched = 0;
-
-        while let Some(node_id) = stack.pop() {
-            // Enforce search limit to prevent memory exhaustion
-            nodes_searched += 1;
-            if nodes_searched > MAX_SEARCH_NODES {
-                eprintln!(
-                    "Warning: Search stopped after examining {} nodes (limit reached)",
-                    MAX_SEARCH_NODES
-                );
-                break;
-            }
-
-            if let Some(node) = self.tree.get_node(node_id) {
-                // Check if this node matches
-                let matches = if self.case_sensitive {
-                    node.label.contains(&query)
-                        || node.node_type.contains(&query)
-                        || node
-                            .attributes
-                            .iter()
-                            .any(|attr| attr.key.contains(&query) || attr.value.contains(&query))
-                } else {
-                    node.label.to_lowercase().contains(&query)
-                        || node.node_type.to_lowercase().contains(&query)
-                        || node.attributes.iter().any(|attr| {
-                            attr.key.to_lowercase().contains(&query)
-                                || attr.value.to_lowercase().contains(&query)
-                        })
-                };
-
-                if matches {
-                    self.search_matches.push(node_id);
-                }
-
-                // Add children to stack for depth-first traversal
-                // Push in reverse order so we process them in original order
-                let children = self.tree.get_children(node_id);
-                for child_id in children.iter().rev() {
-                    stack.push(*child_id);
-                }
+    /// Select segment `number` of the selected node's path, counting the root
+    /// as 1. The ancestors of the selection are expanded already.
+    fn jump_to_path_segment(&mut self, number: usize) {
+        let segments = self.path_segments();
+        match segments.get(number - 1) {
+            Some(&(node_id, _)) => {
+                let before = self.selection();
+                self.tree_view.navigate_to_node(&self.tree, node_id);
+                self.record_jump(before);
             }
+            None => self.notify_warning(format!("The path has only {} segments", segments.len())),
         }
+    }
 
-        // Set current match to first result if any
-        if !self.search_matches.is_empty() {
-            self.current_match_index = Some(0);
-            self.jump_to_current_match();
-        }
+    /// Jump to a line number, as shown in the gutter
+    fn go_to_line(&mut self, line: usize) {
+        self.tree_view.navigate_to_first_line();
+        self.tree_view
+            .navigate_by(line.max(1) as isize - 1, &self.tree);
     }
 
-    // Jump to the current search match
     fn jump_to_current_match(&mut self) {
         if let Some(index) = self.current_match_index {
             if let Some(&node_id) = self.search_matches.get(index) {
-                // Expand all parents of the matched node
-                self.expand_to_node(node_id);
-                // Navigate to the matched node
-                self.tree_view.navigate_to_node(&self.tree, node_id);
+                self.reveal_node(node_id);
             }
         }
     }
 
+    /// Expand the ancestors of a node and select its row
+    fn reveal_node(&mut self, node_id: usize) {
+        let row = self.tree_view.row_node(&self.tree, node_id);
+        self.expand_to_node(row);
+        self.tree_view.navigate_to_node(&self.tree, row);
+    }
+
     // Expand all parent nodes to make a node visible
     fn expand_to_node(&mut self, node_id: usize) {
         let mut path = Vec::new();
@@ -913,102 +1849,203 @@ impl App {
         self.jump_to_current_match();
     }
 
-    fn render_help_popup(&self, frame: &mut ratatui::Frame) {
+    /// Lines of the help popup
+    fn help_lines(&self) -> Vec<ratatui::text::Line<'static>> {
         use ratatui::{
-            layout::Alignment,
-            style::{Color, Modifier, Style},
+            style::{Modifier, Style},
             text::{Line, Span},
-            widgets::{Block, Borders, Clear, Paragraph},
         };
 
-        // Create centered popup area
-        let area = frame.size();
-        let popup_width = HELP_POPUP_WIDTH.min(area.width - 4);
-        let popup_height = HELP_POPUP_HEIGHT.min(area.height - 4);
-        let popup_x = (area.width - popup_width) / 2;
-        let popup_y = (area.height - popup_height) / 2;
-
-        let popup_area = ratatui::layout::Rect {
-            x: popup_x,
-            y: popup_y,
-            width: popup_width,
-            height: popup_height,
-        };
-
-        // Clear the area
-        frame.render_widget(Clear, popup_area);
-
-        // Create help text
-        let help_lines = vec![
+        let page = self.config.navigation.page_scroll_lines;
+        vec![
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Navigation",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(self.theme.heading)
                     .add_modifier(Modifier::BOLD),
             )]),
             Line::from("  ↑/k       Move up              ↓/j       Move down"),
-            Line::from("  PgUp/[    Move up 10 items     PgDn/]    Move down 10 items"),
-            Line::from("  g         First line           G         Last line"),
+            Line::from(format!(
+                "  PgUp/[    Move up {:<3} items    PgDn/]    Move down {} items",
+                page, page
+            )),
+            Line::from("  Ctrl-u    Half page up         Ctrl-d    Half page down"),
+            Line::from("  gg        First line           G         Last line"),
+            Line::from("  g1-g9     Go to the Nth segment of the path (g1: root)"),
+            Line::from("  gd        Go to the entry of a DN, e.g. of memberOf (LDIF)"),
             Line::from("  J         Next sibling         K         Previous sibling"),
             Line::from("  0         First sibling        $         Last sibling"),
-            Line::from("  H         Navigate to parent"),
+            Line::from("  H         Navigate to parent   NG        Go to line N"),
+            Line::from("  zz        Center selection     zt/zb     Selection to top/bottom"),
+            Line::from("  zh/zl     Scroll left/right    zw        Wrap selected row"),
+            Line::from("  N<motion> Repeat a motion N times, e.g. 25j, 3J, 5l (Esc cancels)"),
+            Line::from("  m<letter> Set a mark           '<letter> Jump to a mark"),
+            Line::from("  M         List marks           Ctrl-p    Find node by path"),
+            Line::from("  Ctrl-o    Jump back            Ctrl-i/Tab Jump forward"),
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Tree Manipulation",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(self.theme.heading)
                     .add_modifier(Modifier::BOLD),
             )]),
             Line::from("  →/l       Smart right: expand or move to first child"),
             Line::from("  ←/h       Smart left: collapse or move to parent"),
             Line::from("  Space     Toggle expand/collapse current node"),
             Line::from("  Enter     Toggle expand/collapse current node"),
+            Line::from("            (on a multi-line value, such as ⏎×12: show its lines)"),
             Line::from("  e         Expand siblings      E         Expand siblings (deep)"),
             Line::from("  c         Collapse siblings    C         Collapse siblings (deep)"),
+            Line::from("  zM        Collapse all         zR        Expand all (NzR: N levels)"),
+            Line::from("  zp        Toggle pages of elements of large arrays"),
+            Line::from("  X         Hide subtree         U         Show hidden subtrees"),
+            Line::from("  P         Pin/unpin node       gp        Pick a pinned node"),
+            Line::from("  R         Mask values of sensitive keys (ui.redact_keys)"),
+            Line::from("  O         Open the JSON in a value as a subtree, again: plain value"),
+            Line::from(
+                "  A         Edit the value (JSON, YAML, TOML)    u         Undo the last edit",
+            ),
+            Line::from(
+                "  :w        Save edits to the file (Ctrl-s)      :q        Quit (:q! drops edits)",
+            ),
+            Line::from("  sk/sv     Sort by key/value    sK/sV     Sort subtree by key/value"),
+            Line::from("  sf        Sort array by field  sr/ss     Reverse/restore order"),
+            Line::from("  .         Repeat the last toggle, e/E/c/C, sort or copy (N. N times)"),
+            Line::from("  Q<letter> Record actions until Q  @<letter> Replay them (@@: again)"),
+            Line::from("  i         Toggle inline previews of collapsed objects and arrays"),
+            Line::from("  a         Toggle showing attributes inline instead of @attributes"),
+            Line::from("  L         Cycle line numbers: off, absolute, relative"),
+            Line::from("  D         Toggle detail pane   </>       Grow/shrink detail pane"),
+            Line::from("  S         Statistics of the subtree: size, depth, types, numbers"),
+            Line::from("  T         Show the array of objects around the selection as a table"),
+            Line::from("  W         Messages shown in the footer so far"),
+            Line::from("  ]c/[c     Next/previous difference (--diff; ]]/[[ page)"),
+            Line::from("  gt/gT     Next/previous tab (several files)"),
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Copy/Print/Decode",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(self.theme.heading)
                     .add_modifier(Modifier::BOLD),
             )]),
             Line::from("  yy        Copy value (pretty)  pp        Print value (pretty)"),
             Line::from("  yv        Copy value (compact) pv        Print value (compact)"),
             Line::from("  ys        Copy string value    ps        Print string value"),
             Line::from("  yk        Copy key/label       pk        Print key/label"),
+            Line::from("  yp        Copy path (as above) pb        Print path"),
+            Line::from("  yj        Copy jq path         pj        Print jq path"),
+            Line::from("  yx        Copy XPath           px        Print XPath"),
+            Line::from("  y6        Copy JSON Pointer    p6        Print JSON Pointer"),
+            Line::from("  yl        Copy file:line       pl        Print file:line"),
+            Line::from("  yo        Copy in input format po        Print in input format"),
+            Line::from("  yY        Copy as YAML         pY        Print as YAML"),
+            Line::from("  yT        Copy as TOML         pT        Print as TOML"),
+            Line::from("  yC        Copy as CSV          pC        Print as CSV"),
+            Line::from("  yt        Copy visible tree    pt        Print visible tree"),
+            Line::from("  yf        Copy referenced path pf        Print referenced file"),
+            Line::from("  w         Write value or subtree (pretty JSON) to a file,"),
+            Line::from("            Tab in the prompt: the visible tree, again: the view as HTML"),
             Line::from("  d         Decode value (base64, hex, timestamp)"),
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Search",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(self.theme.heading)
                     .add_modifier(Modifier::BOLD),
             )]),
-            Line::from("  /         Start search (case-insensitive)"),
+            Line::from("  /         Start search (smart case)"),
+            Line::from("  k: / v:   Query prefix: match only keys / only values"),
+            Line::from("  v=        Query prefix: match values equal to the query"),
+            Line::from("  Tab       While searching: only search under the cursor"),
+            Line::from("  Ctrl-s    While searching: smart case / match case / ignore case"),
+            Line::from("  Ctrl-p    While searching: match earlier words in the path above"),
+            Line::from("  ↑/↓       While searching: recall earlier queries"),
             Line::from("  n         Jump to next match"),
             Line::from("  N         Jump to previous match"),
+            Line::from("  {n}gn     Jump to match number n, e.g. 15gn"),
+            Line::from("  Ctrl-l    List the matches with their paths and values"),
+            Line::from("  F         Toggle showing only matches and their ancestors"),
+            Line::from("  *         Search for values equal to the selected one"),
+            Line::from("  g*        List groups of identical subtrees"),
+            Line::from("  x         XPath query (XML/HTML), e.g. //item[@id='a']/name,"),
+            Line::from("            or JSON Pointer (other formats), e.g. /users/3/email"),
             Line::from("  Esc       Clear search / Quit"),
             Line::from(""),
             Line::from(vec![Span::styled(
                 "Other",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(self.theme.heading)
                     .add_modifier(Modifier::BOLD),
             )]),
-            Line::from("  ?         Toggle this help"),
-            Line::from("  q         Quit"),
-        ];
+            Line::from("  ?         Toggle this help (j/k and PgUp/PgDn scroll it)"),
+            Line::from("  r         Reload the file after it changed on disk"),
+            Line::from("  q         Quit (close the tab with several files)"),
+            Line::from("  Ctrl-z    Suspend to the shell, fg resumes"),
+        ]
+    }
 
+    fn render_help_popup(&mut self, frame: &mut ratatui::Frame) {
+        use ratatui::{
+            layout::Alignment,
+            style::Style,
+            widgets::{
+                Block, Borders, Clear, Paragraph,
+                block::{Position, Title},
+            },
+        };
+
+        let help_lines = self.help_lines();
+
+        // Create centered popup area, as high as the help if it fits
+        let area = frame.size();
+        let popup_width = HELP_POPUP_WIDTH.min(area.width - 4);
+        let popup_height = (help_lines.len() + 2).min((area.height - 4) as usize) as u16;
+        let popup_x = (area.width - popup_width) / 2;
+        let popup_y = (area.height - popup_height) / 2;
+
+        let popup_area = ratatui::layout::Rect {
+            x: popup_x,
+            y: popup_y,
+            width: popup_width,
+            height: popup_height,
+        };
+        self.help_page = (popup_height.saturating_sub(2) as usize).max(1);
+        self.help_scroll = self
+            .help_scroll
+            .min(help_lines.len().saturating_sub(self.help_page));
+
+        // Clear the area
+        frame.render_widget(Clear, popup_area);
+
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Keyboard Shortcuts ")
+            .title_alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .fg(self.theme.popup_fg)
+                    .bg(self.theme.popup_bg),
+            );
+        if help_lines.len() > self.help_page {
+            let position = format!(
+                " {}-{}/{} ",
+                self.help_scroll + 1,
+                (self.help_scroll + self.help_page).min(help_lines.len()),
+                help_lines.len()
+            );
+            block = block
+                .title(Title::from(" j/k/PgDn: Scroll | ?: Close ").position(Position::Bottom))
+                .title(
+                    Title::from(position)
+                        .position(Position::Bottom)
+                        .alignment(Alignment::Right),
+                );
+        }
         let help_paragraph = Paragraph::new(help_lines)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title(" Keyboard Shortcuts ")
-                    .title_alignment(Alignment::Center)
-                    .style(Style::default().bg(Color::Black)),
-            )
-            .alignment(Alignment::Left);
+            .block(block)
+            .alignment(Alignment::Left)
+            .scroll((self.help_scroll.min(u16::MAX as usize) as u16, 0));
 
         frame.render_widget(help_paragraph, popup_area);
     }
@@ -1016,7 +2053,7 @@ impl App {
     fn render_decode_menu(&self, frame: &mut ratatui::Frame) {
         use ratatui::{
             layout::Alignment,
-            style::{Color, Modifier, Style},
+            style::{Modifier, Style},
             text::{Line, Span},
             widgets::{Block, Borders, Clear, List, ListItem},
         };
@@ -1049,20 +2086,20 @@ impl App {
                         Span::styled(
                             " → ",
                             Style::default()
-                                .fg(Color::Green)
+                                .fg(self.theme.value)
                                 .add_modifier(Modifier::BOLD),
                         ),
                         Span::styled(
                             option.label(),
                             Style::default()
-                                .fg(Color::Green)
+                                .fg(self.theme.value)
                                 .add_modifier(Modifier::BOLD),
                         ),
                     ])
                 } else {
                     Line::from(vec![
                         Span::raw("   "),
-                        Span::styled(option.label(), Style::default().fg(Color::White)),
+                        Span::styled(option.label(), Style::default().fg(self.theme.popup_fg)),
                     ])
                 };
                 ListItem::new(content)
@@ -1074,53 +2111,450 @@ impl App {
                 .borders(Borders::ALL)
                 .title(" Decode Value ")
                 .title_alignment(Alignment::Center)
-                .style(Style::default().bg(Color::Black)),
+                .style(
+                    Style::default()
+                        .fg(self.theme.popup_fg)
+                        .bg(self.theme.popup_bg),
+                ),
         );
 
         frame.render_widget(list, popup_area);
     }
+}
 
-    fn render_print_popup(&self, frame: &mut ratatui::Frame) {
-        use ratatui::{
-            layout::Alignment,
-            style::{Color, Style},
-            widgets::{Block, Borders, Clear, Paragraph, Wrap},
-        };
+/// The range of path segments to leave out so the path fits into `width`
+/// columns, given each segment's width and the separator's; empty if the whole
+/// path fits.
+///
+/// The root and the last two segments always stay, even if the path is still
+/// too wide with only them and `…`.
+fn elided_path_segments(
+    widths: &[usize],
+    separator: usize,
+    width: usize,
+) -> std::ops::Range<usize> {
+    let ellipsis = PATH_ELLIPSIS.width();
+    // One column of padding before the path
+    let path_width = |segments: &[usize]| {
+        1 + segments.iter().sum::<usize>() + separator * segments.len().saturating_sub(1)
+    };
+    let n = widths.len();
+    // With three segments or fewer there is nothing in the middle to leave out
+    if n <= 3 || path_width(widths) <= width {
+        return 0..0;
+    }
+    for kept in (3..n - 1).rev() {
+        let shown = path_width(&widths[n - kept..]) + widths[0] + ellipsis + 2 * separator;
+        if shown <= width {
+            return 1..n - kept;
+        }
+    }
+    1..n - 2
+}
 
-        if let Some(content) = &self.print_content {
-            // Create centered popup area
-            let area = frame.size();
-            let popup_width = (area.width * PRINT_POPUP_WIDTH_FRACTION / PRINT_POPUP_WIDTH_DIVISOR)
-                .min(PRINT_POPUP_MAX_WIDTH);
-            let popup_height = (area.height * PRINT_POPUP_HEIGHT_FRACTION
-                / PRINT_POPUP_HEIGHT_DIVISOR)
-                .min(PRINT_POPUP_MAX_HEIGHT);
-            let popup_x = (area.width - popup_width) / 2;
-            let popup_y = (area.height - popup_height) / 2;
-
-            let popup_area = ratatui::layout::Rect {
-                x: popup_x,
-                y: popup_y,
-                width: popup_width,
-                height: popup_height,
+#[cfg(test)]
+pub(super) mod test_support {
+    use super::App;
+    use crate::config::Config;
+    use crate::parser::Parser;
+    use crate::tree::{Tree, TreeNode, TreeVariant};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    /// Send `keys` to the app one at a time. `\n`, `\t`, `\x1b` and `\x08`
+    /// stand for Enter, Tab, Esc and Backspace, `↑` and `↓` for the arrow
+    /// keys.
+    pub(super) fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                '\x1b' => KeyCode::Esc,
+                '\x08' => KeyCode::Backspace,
+                '↑' => KeyCode::Up,
+                '↓' => KeyCode::Down,
+                c => KeyCode::Char(c),
             };
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+                .unwrap();
+        }
+    }
 
-            // Clear the area
-            frame.render_widget(Clear, popup_area);
-
-            // Create paragraph with content
-            let paragraph = Paragraph::new(content.as_str())
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title(" Printed Content (press any key to close) ")
-                        .title_alignment(Alignment::Center)
-                        .style(Style::default().bg(Color::Black)),
-                )
-                .wrap(Wrap { trim: false })
-                .alignment(Alignment::Left);
+    /// An app showing a root with ten leaf children, `item0` to `item9`
+    pub(super) fn ten_leaves() -> App {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        for i in 0..10 {
+            tree.add_child_node(0, TreeNode::new(format!("item{i}"), "string"));
+        }
+        App::new(TreeVariant::from(tree), Config::default())
+    }
+
+    /// The label of the selected node
+    pub(super) fn selected_label(app: &App) -> String {
+        let id = app.tree_view.get_selected_node_id().unwrap();
+        app.tree.get_label(id).unwrap()
+    }
+
+    /// An app showing `text` as parsed by `parser`
+    pub(super) fn app_from(parser: &dyn Parser, text: &str) -> App {
+        app_with_config(parser, text, Config::default())
+    }
+
+    /// An app showing `text` as parsed by `parser`, set up by `config`
+    pub(super) fn app_with_config(parser: &dyn Parser, text: &str, config: Config) -> App {
+        App::new(TreeVariant::from(parser.parse(text).unwrap()), config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{app_from, app_with_config, press, ten_leaves};
+    use super::*;
+    use crate::parser::json::JsonParser;
+    use crate::tree::{Tree, TreeNode};
+    use ratatui::backend::TestBackend;
+    use std::io::Write;
+
+    #[test]
+    fn test_long_paths_are_cut_in_the_middle() {
+        // " root > aa > bb > cc > ddd" is 26 columns wide
+        let widths = [4, 2, 2, 2, 3];
+        assert_eq!(elided_path_segments(&widths, 3, 26), 0..0);
+        // " root > … > bb > cc > ddd"
+        assert_eq!(elided_path_segments(&widths, 3, 25), 1..2);
+        // " root > … > cc > ddd"; the last two segments stay even if too wide
+        assert_eq!(elided_path_segments(&widths, 3, 20), 1..3);
+        assert_eq!(elided_path_segments(&widths, 3, 10), 1..3);
+        // " root/…/bb/cc/ddd" with a one-column separator
+        assert_eq!(elided_path_segments(&widths, 1, 17), 1..2);
+        // Three segments are never cut
+        assert_eq!(elided_path_segments(&widths[..3], 3, 5), 0..0);
+    }
+
+    #[test]
+    fn test_streaming_load_errors_are_shown_instead_of_panicking() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "dn: cn=alice,dc=example,dc=com\ncn: alice\nmail: alice@example.com\n"
+        )
+        .unwrap();
+        let tree = crate::parser::ldif::build_ldif_index(file.path()).unwrap();
+        let mut config = Config::default();
+        config.ui.show_detail_pane = true;
+        let mut app = App::new(TreeVariant::Streaming(Box::new(tree)), config);
+
+        // Cut the file short after the index was built
+        let len = std::fs::metadata(file.path()).unwrap().len();
+        file.as_file().set_len(len / 2).unwrap();
+
+        let entry = app.tree.get_children(app.tree.root_id())[0];
+        let attrs = app.tree.get_children(entry)[0];
+        let mail = app.tree.get_children(attrs)[1];
+        assert!(matches!(
+            app.tree.try_get_node(mail),
+            Err(XtvError::NodeLoad { .. })
+        ));
+        app.expand_to_node(mail);
+        app.tree_view.navigate_to_node(&app.tree, mail);
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 12)).unwrap();
+        let mut draw = |app: &mut App| {
+            terminal.draw(|f| app.render(f)).unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..12)
+                .map(|y| {
+                    (0..80)
+                        .map(|x| buffer.get(x, y).symbol())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let screen = draw(&mut app);
+        assert!(
+            screen
+                .iter()
+                .any(|row| row.contains("mail: File changed on disk"))
+        );
+
+        // The failure also reaches the footer
+        app.collect_tree_messages();
+        let screen = draw(&mut app);
+        assert!(screen[11].contains("File changed on disk"));
+        press(&mut app, "jkR");
+    }
+
+    #[test]
+    fn test_help_scrolls_to_its_last_line() {
+        let mut app = ten_leaves();
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let mut draw = |app: &mut App| {
+            terminal.draw(|f| app.render(f)).unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..30)
+                .map(|y| {
+                    (0..100)
+                        .map(|x| buffer.get(x, y).symbol())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+        };
+        press(&mut app, "?");
+        let screen = draw(&mut app);
+        assert!(screen.iter().any(|row| row.contains("Navigation")));
+        assert!(
+            !screen
+                .iter()
+                .any(|row| row.contains("Suspend to the shell"))
+        );
+
+        press(&mut app, "G");
+        let screen = draw(&mut app);
+        assert!(
+            screen
+                .iter()
+                .any(|row| row.contains("Suspend to the shell"))
+        );
+        press(&mut app, "jjj");
+        assert_eq!(app.help_scroll, app.help_lines().len() - app.help_page);
+        press(&mut app, "g");
+        assert_eq!(app.help_scroll, 0);
+        press(&mut app, "?");
+        assert!(!app.show_help);
+    }
 
-            frame.render_widget(paragraph, popup_area);
+    #[test]
+    fn test_g_and_a_digit_selects_an_ancestor() {
+        // root > a > b > c
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let mut parent = 0;
+        for label in ["a", "b", "c"] {
+            parent = tree.add_child_node(parent, TreeNode::new(label, "object"));
         }
+        let mut config = Config::default();
+        config.ui.path_separator = "/".to_string();
+        let mut app = App::new(TreeVariant::from(tree), config).with_selection(".a.b.c");
+        assert_eq!(app.get_breadcrumb().as_deref(), Some("root/a/b/c"));
+
+        press(&mut app, "g2");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+        // Ancestors stay expanded, so the way back is on screen
+        press(&mut app, "jj");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(3));
+
+        press(&mut app, "g9");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(3));
+        assert!(app.notification.is_some());
+        press(&mut app, "gg");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(0));
+    }
+
+    #[test]
+    fn test_start_at_selected_path() {
+        let json = r#"{"services": {"web": {"env": {"A": "1"}}, "db": {}}}"#;
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 0;
+
+        let app =
+            app_with_config(&JsonParser, json, config.clone()).with_selection(".services.web.env");
+        let env = app.tree_view.get_selected_node_id().unwrap();
+        assert_eq!(app.tree.get_label(env).as_deref(), Some("env"));
+        assert!(app.tree_view.expanded_nodes().contains(&env));
+
+        let app = app_with_config(&JsonParser, json, config).with_selection(".services.mail");
+        assert!(app.notification.is_some());
+    }
+
+    #[test]
+    fn test_source_location_of_selection() {
+        let json = "{\n  \"a\": {\n    \"b\": 1\n  }\n}";
+        let mut app = app_from(&JsonParser, json).with_selection(".a.b");
+        assert_eq!(app.get_source_location().as_deref(), Some("3"));
+
+        app = app.with_file("data.json", None);
+        assert_eq!(app.get_source_location().as_deref(), Some("data.json:3"));
+    }
+
+    #[test]
+    fn test_copied_values_keep_their_kind() {
+        let json = r#"{"id": "42", "flags": ["true", true, "null", null]}"#;
+        let mut app = app_from(&JsonParser, json).with_selection(".id");
+        assert_eq!(app.get_node_value_compact().as_deref(), Some(r#""42""#));
+        assert_eq!(app.get_node_value_pretty().as_deref(), Some(r#""42""#));
+
+        press(&mut app, "gg");
+        let copied = app.get_node_value_compact().unwrap();
+        let copied: serde_json::Value = serde_json::from_str(&copied).unwrap();
+        assert_eq!(
+            copied,
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_convert_selection() {
+        let json = r#"{"rows": [{"id": 1, "name": "a,b"}, {"id": 2}], "none": null}"#;
+        let mut app = app_from(&JsonParser, json).with_selection(".rows");
+        assert_eq!(
+            app.get_node_converted(convert::Format::Csv).as_deref(),
+            Some("id,name\n1,\"a,b\"\n2,\n")
+        );
+        assert_eq!(
+            app.get_node_converted(convert::Format::Toml).as_deref(),
+            Some("[[rows]]\nid = 1\nname = \"a,b\"\n\n[[rows]]\nid = 2\n")
+        );
+
+        // The root holds a null, which TOML can't
+        press(&mut app, "gg");
+        assert!(app.notification.is_none());
+        assert_eq!(app.get_node_converted(convert::Format::Toml), None);
+        assert!(app.notification.is_some());
+        assert!(app.get_node_converted(convert::Format::Yaml).is_some());
+    }
+
+    #[test]
+    fn test_count_prefix_repeats_motion() {
+        let mut app = ten_leaves();
+        press(&mut app, "3j");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(3));
+        assert_eq!(app.pending_count, None);
+
+        press(&mut app, "10k");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(0));
+
+        // A count before G or gg jumps to that line
+        press(&mut app, "5G");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(4));
+        press(&mut app, "2gg");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+    }
+
+    #[test]
+    fn test_leading_zero_is_first_sibling() {
+        let mut app = ten_leaves();
+        press(&mut app, "5j0");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+
+        // After a digit, 0 extends the count
+        press(&mut app, "10");
+        assert_eq!(app.pending_count, Some(10));
+    }
+
+    #[test]
+    fn test_ctrl_c_quits_with_several_tabs_and_popups() {
+        let tree = Tree::new(TreeNode::new("other", "object"));
+        let mut app = ten_leaves().with_tab(TreeVariant::from(tree));
+        press(&mut app, "?");
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        app.handle_key(ctrl_c).unwrap();
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_ctrl_z_asks_to_suspend_without_a_z_prefix() {
+        let mut app = ten_leaves();
+        let ctrl_z = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        app.handle_key(ctrl_z).unwrap();
+        assert!(app.suspend_requested);
+        assert!(!app.last_key_was_z);
+    }
+
+    #[test]
+    fn test_escape_cancels_count_without_quitting() {
+        let mut app = ten_leaves();
+        press(&mut app, "25\x1b");
+        assert_eq!(app.pending_count, None);
+        assert!(!app.should_quit);
+
+        press(&mut app, "j");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+    }
+
+    #[test]
+    fn test_paths_and_searches_go_through_array_pages() {
+        let items: Vec<String> = (0..30).map(|i| format!("\"item{i}\"")).collect();
+        let json = format!("{{\"items\": [{}]}}", items.join(", "));
+        let mut config = Config::default();
+        config.ui.array_page_size = 10;
+        let mut app = app_with_config(&JsonParser, &json, config).with_selection(".items[23]");
+        assert_eq!(app.get_jq_path().as_deref(), Some(".items[23]"));
+
+        press(&mut app, "/item7");
+        app.wait_for_search();
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(app.get_jq_path().as_deref(), Some(".items[7]"));
+
+        press(&mut app, "zp");
+        assert_eq!(app.get_jq_path().as_deref(), Some(".items[7]"));
+        press(&mut app, "gg");
+        app.notification = None;
+        press(&mut app, "zp");
+        assert!(app.notification.is_some());
+    }
+
+    #[test]
+    fn test_expand_all_asks_for_confirmation_above_threshold() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let parent = tree.add_child_node(0, TreeNode::new("parent", "object"));
+        tree.add_child_node(parent, TreeNode::new("child", "string"));
+        let mut config = Config::default();
+        config.navigation.expand_all_threshold = 2;
+        let mut app = App::new(TreeVariant::from(tree), config);
+
+        press(&mut app, "zR");
+        assert!(app.notification.is_some());
+        press(&mut app, "G");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+
+        // Another key in between cancels the confirmation
+        press(&mut app, "zRjzR");
+        press(&mut app, "G");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+
+        press(&mut app, "zRzRG");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(2));
+
+        press(&mut app, "zM");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+    }
+
+    #[test]
+    fn test_deep_expand_asks_for_confirmation_above_threshold() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let parent = tree.add_child_node(0, TreeNode::new("parent", "object"));
+        let child = tree.add_child_node(parent, TreeNode::new("child", "object"));
+        tree.add_child_node(child, TreeNode::new("leaf", "string"));
+        let mut config = Config::default();
+        config.navigation.expand_all_threshold = 2;
+        let mut app = App::new(TreeVariant::from(tree), config);
+
+        press(&mut app, "jE");
+        assert!(app.notification.is_some());
+        press(&mut app, "G");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+
+        // Another key in between cancels the confirmation
+        press(&mut app, "EkjE");
+        press(&mut app, "G");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+
+        press(&mut app, "EEG");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(3));
+    }
+
+    #[test]
+    fn test_finder_jumps_to_picked_node() {
+        let mut app = ten_leaves();
+        app.handle_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL))
+            .unwrap();
+        assert!(app.finder.is_some());
+
+        // Typed keys go to the finder, not to navigation
+        press(&mut app, "item7");
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .unwrap();
+        assert!(app.finder.is_none());
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(8));
+        assert!(!app.last_key_was_p);
     }
 }
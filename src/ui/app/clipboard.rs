@@ -0,0 +1,103 @@
+//! Copying to the clipboard, through the system clipboard or OSC 52.
+//!
+//! The system clipboard isn't reachable on headless machines and over SSH.
+//! OSC 52 is an escape sequence asking the terminal itself to set its
+//! clipboard, which works wherever the terminal supports it, but terminals cap
+//! the size of the sequence, so large copies are cut to
+//! `ui.osc52_max_bytes`. Which way is used follows `ui.clipboard`.
+
+use super::App;
+use crate::config::ClipboardMode;
+use crate::ui::tree_view::format_size;
+use base64::{Engine as _, engine::general_purpose};
+use std::io::{self, Write};
+
+impl App {
+    /// Copy `text` and report in the footer how much was copied
+    pub(super) fn copy_to_clipboard(&mut self, text: &str) {
+        let max_bytes = self.config.ui.osc52_max_bytes.0 as usize;
+        let result = match self.config.ui.clipboard {
+            ClipboardMode::System => copy_with_system(text),
+            ClipboardMode::Osc52 => copy_with_osc52(text, max_bytes),
+            ClipboardMode::Auto => {
+                copy_with_system(text).or_else(|_| copy_with_osc52(text, max_bytes))
+            }
+        };
+
+        match result {
+            Ok(copied) if copied < text.len() => self.notify(format!(
+                "Copied only the first {} of {} (ui.osc52_max_bytes)",
+                format_size(copied),
+                format_size(text.len())
+            )),
+            Ok(_) => self.notify(format!("Copied {}", format_size(text.len()))),
+            Err(e) => self.notify_error(format!("Failed to copy to clipboard: {}", e)),
+        }
+    }
+}
+
+/// Set the system clipboard, returning the number of bytes copied
+fn copy_with_system(text: &str) -> Result<usize, String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text.to_string()))
+        .map_err(|e| e.to_string())?;
+    Ok(text.len())
+}
+
+/// Send the OSC 52 sequence to the terminal.
+///
+/// # Returns
+///
+/// The number of bytes of `text` that were copied.
+fn copy_with_osc52(text: &str, max_bytes: usize) -> Result<usize, String> {
+    let tmux = std::env::var_os("TMUX").is_some();
+    let (sequence, copied) = osc52_sequence(text, max_bytes, tmux);
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(sequence.as_bytes())
+        .and_then(|()| stdout.flush())
+        .map_err(|e| e.to_string())?;
+    Ok(copied)
+}
+
+/// The escape sequence setting the clipboard to `text`, cut to at most
+/// `max_bytes` at a character boundary, and the number of bytes it holds.
+///
+/// Inside tmux the sequence is wrapped so that tmux passes it on to the
+/// terminal.
+fn osc52_sequence(text: &str, max_bytes: usize, tmux: bool) -> (String, usize) {
+    let mut end = text.len().min(max_bytes);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let encoded = general_purpose::STANDARD.encode(&text[..end]);
+    let sequence = if tmux {
+        format!("\x1bPtmux;\x1b\x1b]52;c;{}\x07\x1b\\", encoded)
+    } else {
+        format!("\x1b]52;c;{}\x07", encoded)
+    };
+    (sequence, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        let (sequence, copied) = osc52_sequence("hello", 100, false);
+        assert_eq!(sequence, "\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(copied, 5);
+
+        let (sequence, _) = osc52_sequence("hello", 100, true);
+        assert_eq!(sequence, "\x1bPtmux;\x1b\x1b]52;c;aGVsbG8=\x07\x1b\\");
+    }
+
+    #[test]
+    fn test_osc52_payload_is_cut_at_a_character() {
+        // "é" takes two bytes, so cutting after two keeps only "h"
+        let (sequence, copied) = osc52_sequence("héllo", 2, false);
+        assert_eq!(copied, 1);
+        assert_eq!(sequence, "\x1b]52;c;aA==\x07");
+    }
+}
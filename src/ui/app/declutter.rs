@@ -0,0 +1,298 @@
+//! Hiding noisy subtrees and pinning important nodes.
+//!
+//! `X` leaves the selected subtree out of the rows, and `U` shows all hidden
+//! subtrees again; the footer counts them meanwhile. Searches still find
+//! matches inside hidden subtrees, and jumping to one, as to a mark or a
+//! path, shows its subtree again.
+//!
+//! `P` pins the selected node, or unpins it, to a strip above the tree that
+//! shows the path and current value of each pinned node. `gp` moves the focus
+//! to the strip, where j/k pick a pin, Enter jumps to it, `P` unpins it and
+//! Esc goes back to the tree.
+//!
+//! Like marks, hidden and pinned nodes are found again by their label paths
+//! after a reload, and each tab has its own.
+
+use super::App;
+use crate::tree::table::cell_text;
+use crate::ui::redact::MASK;
+use crate::ui::tree_view::{truncate_middle, truncate_to_width, unavailable_node};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Nodes pinned at most, so that the strip stays compact
+pub(super) const MAX_PINS: usize = 9;
+
+/// Rows the tree keeps at least below the strip; with fewer the strip is
+/// left out
+const MIN_TREE_ROWS: u16 = 5;
+
+/// A pinned node
+#[derive(Debug, Clone)]
+pub(super) struct Pin {
+    node_id: usize,
+    /// Labels from below the root down to the node
+    labels: Vec<String>,
+}
+
+impl App {
+    /// `X`: hide the selected subtree
+    pub(super) fn hide_selected(&mut self) {
+        match self.tree_view.hide_selected(&self.tree) {
+            Some(node_id) => {
+                let label = self.tree.get_label(node_id).unwrap_or_default();
+                self.notify(format!("Hid {} (U shows all hidden nodes)", label));
+            }
+            None => self.notify_warning("Only nodes below the root can be hidden"),
+        }
+    }
+
+    /// `U`: show all hidden subtrees again
+    pub(super) fn unhide_all(&mut self) {
+        match self.tree_view.unhide_all(&self.tree) {
+            0 => self.notify("Nothing is hidden"),
+            1 => self.notify("Showing 1 hidden node again"),
+            count => self.notify(format!("Showing {} hidden nodes again", count)),
+        }
+    }
+
+    /// `P`: pin the selected node, or unpin it if it is pinned
+    pub(super) fn toggle_pin(&mut self) {
+        let Some(node_id) = self.tree_view.get_selected_node_id() else {
+            return;
+        };
+        if let Some(index) = self.pins.iter().position(|pin| pin.node_id == node_id) {
+            self.pins.remove(index);
+            self.notify("Unpinned");
+            return;
+        }
+        if self.pins.len() >= MAX_PINS {
+            self.notify_warning(format!(
+                "At most {} nodes can be pinned; P on a pinned node unpins it",
+                MAX_PINS
+            ));
+            return;
+        }
+        let labels = self.label_path(node_id);
+        self.pins.push(Pin { node_id, labels });
+        self.notify("Pinned (gp to pick a pinned node)");
+    }
+
+    /// `gp`: move the focus to the strip of pinned nodes
+    pub(super) fn focus_pins(&mut self) {
+        if self.pins.is_empty() {
+            self.notify("No pinned nodes (use P to pin one)");
+            return;
+        }
+        self.pins_selected = Some(0);
+    }
+
+    /// Handle a key while the strip of pinned nodes has the focus
+    pub(super) fn handle_pins_key(&mut self, key: KeyEvent) {
+        let Some(selected) = self.pins_selected else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.pins_selected = None,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.pins_selected = Some(selected.saturating_sub(1));
+            }
+            KeyCode::Down | KeyCode::Char('j') if selected + 1 < self.pins.len() => {
+                self.pins_selected = Some(selected + 1);
+            }
+            KeyCode::Enter => {
+                self.pins_selected = None;
+                if let Some(pin) = self.pins.get(selected) {
+                    let before = self.selection();
+                    self.reveal_node(pin.node_id);
+                    self.record_jump(before);
+                }
+            }
+            KeyCode::Char('P') => {
+                self.pins.remove(selected);
+                self.pins_selected = match self.pins.len() {
+                    0 => None,
+                    len => Some(selected.min(len - 1)),
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Find hidden and pinned nodes again after the tree was rebuilt,
+    /// dropping pins whose node no longer exists
+    pub(super) fn restore_declutter(&mut self, hidden: &[Vec<String>]) {
+        for labels in hidden {
+            let (node_id, matched) = self.resolve_label_path(labels);
+            if matched == labels.len() && node_id != self.tree.root_id() {
+                self.tree_view.hide(node_id);
+            }
+        }
+
+        let before = self.pins.len();
+        let pins = std::mem::take(&mut self.pins);
+        self.pins = pins
+            .into_iter()
+            .filter_map(|pin| {
+                let (node_id, matched) = self.resolve_label_path(&pin.labels);
+                (matched == pin.labels.len()).then_some(Pin {
+                    node_id,
+                    labels: pin.labels,
+                })
+            })
+            .collect();
+        self.pins_selected = None;
+        if self.pins.len() < before {
+            self.notify_warning("Unpinned nodes that are gone");
+        }
+    }
+
+    /// Split `area` into the strip of pinned nodes, if there are any and
+    /// there is room, and the rest
+    pub(super) fn split_pins(&self, area: Rect) -> (Option<Rect>, Rect) {
+        // One row per pin and a border below them
+        let height = self.pins.len() as u16 + 1;
+        if self.pins.is_empty() || area.height < height + MIN_TREE_ROWS {
+            return (None, area);
+        }
+        let strip = Rect { height, ..area };
+        let rest = Rect {
+            y: area.y + height,
+            height: area.height - height,
+            ..area
+        };
+        (Some(strip), rest)
+    }
+
+    /// Draw the pinned nodes, one per row, with their path and value
+    pub(super) fn render_pins(&self, frame: &mut Frame, area: Rect) {
+        let width = area.width as usize;
+        let lines: Vec<Line> = self
+            .pins
+            .iter()
+            .enumerate()
+            .map(|(i, pin)| {
+                let path = if pin.labels.is_empty() {
+                    "(root)".to_string()
+                } else {
+                    pin.labels.join(self.path_separator())
+                };
+                // Streamed nodes that aren't loaded show their placeholder
+                let value = match self.tree.try_get_node(pin.node_id) {
+                    Ok(_) if self.redacts(pin.node_id) => MASK.to_string(),
+                    Ok(node) => cell_text(&self.tree, pin.node_id, &node),
+                    Err(error) => unavailable_node(&self.tree, pin.node_id, &error)
+                        .label
+                        .to_string(),
+                };
+                let value = value.replace(['\n', '\r'], " ");
+                let mut style = Style::default();
+                if self.pins_selected == Some(i) {
+                    style = style
+                        .bg(self.theme.selection_bg)
+                        .add_modifier(Modifier::BOLD);
+                }
+                // The path takes at most half of the row, the value the rest
+                let number = format!(" {} ", i + 1);
+                let path = truncate_middle(&path, width / 2).into_owned();
+                let rest = width.saturating_sub(number.width() + path.width() + 3);
+                Line::from(vec![
+                    Span::styled(number, style.fg(self.theme.key)),
+                    Span::styled(path, style.fg(self.theme.path_bar)),
+                    Span::styled(" = ", style.fg(self.theme.icon)),
+                    Span::styled(truncate_to_width(&value, rest), style.fg(self.theme.value)),
+                ])
+            })
+            .collect();
+        let block = Block::default()
+            .borders(Borders::BOTTOM)
+            .border_style(Style::default().fg(self.theme.icon));
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_from, press, selected_label};
+
+    fn app() -> App {
+        let json = r#"{"a": 1, "metadata": {"x": {"y": "needle"}}, "z": 2}"#;
+        app_from(&JsonParser, json)
+    }
+
+    #[test]
+    fn test_hide_and_unhide() {
+        let mut app = app();
+        press(&mut app, "X");
+        assert!(app.tree_view.hidden_nodes().is_empty());
+
+        press(&mut app, "jjX");
+        assert_eq!(selected_label(&app), "z");
+        assert_eq!(app.tree_view.hidden_nodes().len(), 1);
+        assert!(app.position_indicator().contains("1 hidden"));
+        press(&mut app, "k");
+        assert_eq!(selected_label(&app), "a");
+
+        press(&mut app, "U");
+        assert!(app.tree_view.hidden_nodes().is_empty());
+        assert!(!app.position_indicator().contains("hidden"));
+        assert_eq!(selected_label(&app), "a");
+    }
+
+    #[test]
+    fn test_search_shows_hidden_matches() {
+        let mut app = app();
+        press(&mut app, "jjX/needle\n");
+        app.wait_for_search();
+        assert!(app.tree_view.hidden_nodes().is_empty());
+        assert_eq!(selected_label(&app), "y");
+    }
+
+    #[test]
+    fn test_pins() {
+        let mut app = app();
+        press(&mut app, "gp");
+        assert_eq!(app.pins_selected, None);
+
+        press(&mut app, "jPjP");
+        assert_eq!(app.pins.len(), 2);
+        let (strip, rest) = app.split_pins(Rect::new(0, 0, 80, 20));
+        assert_eq!(strip.map(|strip| strip.height), Some(3));
+        assert_eq!(rest.height, 17);
+        assert_eq!(app.split_pins(Rect::new(0, 0, 80, 7)).0, None);
+
+        // Jump to the first pin from elsewhere
+        press(&mut app, "jgp");
+        assert_eq!(app.pins_selected, Some(0));
+        press(&mut app, "jkk\n");
+        assert_eq!(app.pins_selected, None);
+        assert_eq!(selected_label(&app), "a");
+
+        // Unpin from the strip and from the tree
+        press(&mut app, "gpjP");
+        assert_eq!(app.pins.len(), 1);
+        assert_eq!(app.pins_selected, Some(0));
+        press(&mut app, "\x1bP");
+        assert!(app.pins.is_empty());
+        assert_eq!(app.split_pins(Rect::new(0, 0, 80, 20)).0, None);
+    }
+
+    #[test]
+    fn test_pins_are_limited() {
+        let json = format!("[{}]", vec!["1"; MAX_PINS + 1].join(","));
+        let mut app = app_from(&JsonParser, &json);
+        for _ in 0..=MAX_PINS {
+            press(&mut app, "jP");
+        }
+        assert_eq!(app.pins.len(), MAX_PINS);
+    }
+}
@@ -0,0 +1,129 @@
+//! The detail pane: `D` shows the selected node's label, type and attributes
+//! in a [`DetailView`] next to the tree, or below it with
+//! `ui.detail_pane_position = "bottom"`.
+//!
+//! `<` and `>` move the border between tree and pane. The pane is left out
+//! while the terminal is too small for both, and comes back once it grows.
+
+use super::App;
+use crate::config::DetailPanePosition;
+use crate::ui::detail_view::DetailView;
+use crate::ui::tree_view::unavailable_node;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+};
+
+/// Share of the tree area taken by the detail pane, in percent
+pub(super) const DEFAULT_DETAIL_PANE_PERCENT: u16 = 40;
+const MIN_DETAIL_PANE_PERCENT: u16 = 20;
+const MAX_DETAIL_PANE_PERCENT: u16 = 80;
+
+/// Percentage points moved by `<` and `>`
+const DETAIL_PANE_STEP: u16 = 5;
+
+/// Smallest tree area that still fits the pane on the right
+const MIN_SPLIT_WIDTH: u16 = 60;
+
+/// Smallest tree area that still fits the pane at the bottom
+const MIN_SPLIT_HEIGHT: u16 = 16;
+
+impl App {
+    /// Show or hide the detail pane
+    pub(super) fn toggle_detail_pane(&mut self) {
+        self.config.ui.show_detail_pane = !self.config.ui.show_detail_pane;
+    }
+
+    /// Grow the detail pane by `steps` (shrink it if negative)
+    pub(super) fn resize_detail_pane(&mut self, steps: i16) {
+        if !self.config.ui.show_detail_pane {
+            return;
+        }
+        let percent = self
+            .detail_pane_percent
+            .saturating_add_signed(steps * DETAIL_PANE_STEP as i16);
+        self.detail_pane_percent = percent.clamp(MIN_DETAIL_PANE_PERCENT, MAX_DETAIL_PANE_PERCENT);
+    }
+
+    /// Split `area` into the tree and, if shown and there is room, the pane
+    pub(super) fn split_detail_pane(&self, area: Rect) -> (Rect, Option<Rect>) {
+        if !self.config.ui.show_detail_pane {
+            return (area, None);
+        }
+        let (direction, fits) = match self.config.ui.detail_pane_position {
+            DetailPanePosition::Right => (Direction::Horizontal, area.width >= MIN_SPLIT_WIDTH),
+            DetailPanePosition::Bottom => (Direction::Vertical, area.height >= MIN_SPLIT_HEIGHT),
+        };
+        if !fits {
+            return (area, None);
+        }
+        let chunks = Layout::default()
+            .direction(direction)
+            .constraints([
+                Constraint::Percentage(100 - self.detail_pane_percent),
+                Constraint::Percentage(self.detail_pane_percent),
+            ])
+            .split(area);
+        (chunks[0], Some(chunks[1]))
+    }
+
+    /// Draw the selected node's details into `area`
+    pub(super) fn render_detail_pane(&self, frame: &mut Frame, area: Rect) {
+        // Streaming nodes that aren't loaded yet show their placeholder until
+        // a later frame, rather than blocking on the disk while drawing
+        let node = self.tree_view.get_selected_node_id().map(|id| {
+            let node = self
+                .tree
+                .try_get_node(id)
+                .unwrap_or_else(|error| unavailable_node(&self.tree, id, &error));
+            self.redact_node(id, node)
+        });
+        DetailView::new().render(frame, area, node.as_ref(), &self.theme);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::app_with_config;
+
+    fn app(position: DetailPanePosition) -> App {
+        let mut config = Config::default();
+        config.ui.show_detail_pane = true;
+        config.ui.detail_pane_position = position;
+        app_with_config(&JsonParser, r#"{"a": 1}"#, config)
+    }
+
+    #[test]
+    fn test_pane_is_left_out_when_there_is_no_room() {
+        let app = app(DetailPanePosition::Right);
+        let (tree, pane) = app.split_detail_pane(Rect::new(0, 0, 100, 20));
+        assert_eq!((tree.width, pane.map(|p| p.width)), (60, Some(40)));
+        let narrow = Rect::new(0, 0, 50, 20);
+        assert_eq!(app.split_detail_pane(narrow), (narrow, None));
+
+        let app = self::app(DetailPanePosition::Bottom);
+        let (tree, pane) = app.split_detail_pane(Rect::new(0, 0, 50, 20));
+        assert_eq!((tree.height, pane.map(|p| p.height)), (12, Some(8)));
+        let short = Rect::new(0, 0, 100, 10);
+        assert_eq!(app.split_detail_pane(short), (short, None));
+    }
+
+    #[test]
+    fn test_resizing_stays_within_bounds() {
+        let mut app = app(DetailPanePosition::Right);
+        app.resize_detail_pane(1);
+        assert_eq!(app.detail_pane_percent, 45);
+        app.resize_detail_pane(100);
+        assert_eq!(app.detail_pane_percent, MAX_DETAIL_PANE_PERCENT);
+        app.resize_detail_pane(-100);
+        assert_eq!(app.detail_pane_percent, MIN_DETAIL_PANE_PERCENT);
+
+        // A hidden pane keeps its size
+        app.toggle_detail_pane();
+        app.resize_detail_pane(1);
+        assert_eq!(app.detail_pane_percent, MIN_DETAIL_PANE_PERCENT);
+    }
+}
@@ -0,0 +1,129 @@
+//! Diff mode of `xtv --diff OLD NEW`: the merged tree of [`crate::tree::diff`]
+//! with its differences marked.
+//!
+//! `]c` and `[c` jump to the next and previous difference, so `[` and `]`
+//! page with `[[` and `]]` in this mode. `F` without a search query shows
+//! only the changed branches.
+
+use super::App;
+use crate::tree::diff::Change;
+use crate::ui::tree_view::format_thousands;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::collections::HashMap;
+
+impl App {
+    /// Mark the `changes` and `differences` of a [`Diff`](crate::tree::diff::Diff)
+    /// whose merged tree the app shows
+    pub fn with_diff(mut self, changes: HashMap<usize, Change>, differences: Vec<usize>) -> Self {
+        self.tree_view.set_changes(changes);
+        self.notify(match differences.len() {
+            0 => "The files have no differences".to_string(),
+            n => format!(
+                "{} differences (]c/[c: next/previous, F: only changed branches)",
+                format_thousands(n)
+            ),
+        });
+        self.differences = Some(differences);
+        self
+    }
+
+    /// Whether `[` and `]` start a diff command instead of paging
+    pub(super) fn is_diff(&self) -> bool {
+        self.differences.is_some()
+    }
+
+    /// Handle the key after `[` or `]`
+    pub(super) fn handle_diff_command(&mut self, key: KeyEvent) {
+        let Some(bracket) = self.last_key_was_bracket.take() else {
+            return;
+        };
+        let forward = bracket == ']';
+        match key.code {
+            KeyCode::Char('c') => self.jump_to_difference(forward),
+            KeyCode::Char(c) if c == bracket => {
+                self.scroll_pages(if forward { 1 } else { -1 }, 1);
+            }
+            _ => {}
+        }
+    }
+
+    /// Select the next difference after the selected node, or the previous
+    /// one before it, wrapping around at the end
+    fn jump_to_difference(&mut self, forward: bool) {
+        let Some(differences) = self.differences.as_deref() else {
+            return;
+        };
+        if differences.is_empty() {
+            self.notify("The files have no differences");
+            return;
+        }
+        // Node IDs of the merged tree are in document order
+        let selected = self.tree_view.get_selected_node_id().unwrap_or(0);
+        let index = if forward {
+            let next = differences.partition_point(|&id| id <= selected);
+            if next == differences.len() { 0 } else { next }
+        } else {
+            match differences.partition_point(|&id| id < selected) {
+                0 => differences.len() - 1,
+                previous => previous - 1,
+            }
+        };
+        let (node_id, total) = (differences[index], differences.len());
+        self.reveal_node(node_id);
+        self.notify(format!(
+            "Difference {}/{}",
+            format_thousands(index + 1),
+            format_thousands(total)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::{Parser, json::JsonParser};
+    use crate::tree::TreeVariant;
+    use crate::tree::diff::diff;
+    use crate::ui::app::test_support::{press, selected_label};
+
+    fn app() -> App {
+        let old = JsonParser
+            .parse(r#"{"a": 1, "b": {"c": 1, "d": 2}, "e": 3}"#)
+            .unwrap();
+        let new = JsonParser
+            .parse(r#"{"a": 1, "b": {"c": 2, "d": 2}, "e": 4}"#)
+            .unwrap();
+        let diff = diff(&old, &new);
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 1;
+        App::new(TreeVariant::from(diff.tree), config).with_diff(diff.changes, diff.differences)
+    }
+
+    #[test]
+    fn test_jump_between_differences() {
+        let mut app = app();
+        press(&mut app, "]c");
+        assert_eq!(selected_label(&app), "c");
+        press(&mut app, "]c");
+        assert_eq!(selected_label(&app), "e");
+        press(&mut app, "]c");
+        assert_eq!(selected_label(&app), "c");
+        press(&mut app, "[c");
+        assert_eq!(selected_label(&app), "e");
+    }
+
+    #[test]
+    fn test_filter_shows_only_changed_branches() {
+        let mut app = app();
+        press(&mut app, "F");
+        assert!(app.tree_view.is_filtered());
+        let mut visible = vec![selected_label(&app)];
+        for _ in 0..3 {
+            press(&mut app, "j");
+            visible.push(selected_label(&app));
+        }
+        // `a` and `d` are the same in both files
+        assert_eq!(visible, ["root", "b", "c", "e"]);
+    }
+}
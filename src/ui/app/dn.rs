@@ -0,0 +1,89 @@
+//! Following DNs: on an LDIF attribute whose values are DNs of other entries,
+//! such as `memberOf` or `manager`, `gd` jumps to the entry it names.
+//!
+//! Which attributes hold DNs comes from the attribute table of
+//! [`schema`](crate::parser::ldif::schema), extended by
+//! `[parser.ldif.attribute_labels]`.
+
+use super::App;
+use crate::parser::ldif::schema::AttributeLabels;
+use crate::query::path::resolve_dn;
+
+impl App {
+    /// Jump to the entry named by the selected DN-valued attribute
+    pub(super) fn jump_to_dn(&mut self) {
+        let Some(node_id) = self.tree_view.get_selected_node_id() else {
+            return;
+        };
+        let Some(node) = self.tree.get_node_blocking(node_id) else {
+            return;
+        };
+        let labels = AttributeLabels::new(&self.config.parser.ldif.attribute_labels);
+        let holds_dn = self.tree.format() == Some("ldif")
+            && node.is_attribute()
+            && labels.get(&node.label).is_some_and(|info| info.dn);
+        if !holds_dn {
+            self.notify_warning("The selected node isn't a DN such as memberOf");
+            return;
+        }
+        let dn = node
+            .attributes
+            .iter()
+            .find(|a| a.key == "value")
+            .map_or("", |a| a.value.as_str());
+        match resolve_dn(&self.tree, dn) {
+            Some(entry_id) => {
+                let before = self.selection();
+                self.reveal_node(entry_id);
+                self.record_jump(before);
+            }
+            None => self.notify_warning(format!("No entry {} in this file", dn)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ldif::LdifParser;
+    use crate::ui::app::test_support::app_from;
+
+    fn select(app: &mut App, label: &str) {
+        let id = (0..app.tree.node_count())
+            .find(|&id| app.tree.get_label(id).as_deref() == Some(label))
+            .unwrap();
+        app.reveal_node(id);
+    }
+
+    fn selected(app: &App) -> Option<String> {
+        let id = app.tree_view.get_selected_node_id()?;
+        app.tree.get_label(id)
+    }
+
+    #[test]
+    fn test_jumps_to_member_entries() {
+        let ldif = "dn: dc=example\ndc: example\n\n\
+                    dn: cn=admins,dc=example\nmember: CN=Alice,DC=Example\n\
+                    member: cn=nobody,dc=example\n\n\
+                    dn: cn=alice,dc=example\nmemberOf: cn=admins,dc=example\n";
+        let mut app = app_from(&LdifParser::default(), ldif);
+
+        select(&mut app, "memberOf");
+        app.jump_to_dn();
+        assert_eq!(selected(&app).as_deref(), Some("cn=admins"));
+
+        // DNs compare without regard to case
+        select(&mut app, "member [0]");
+        app.jump_to_dn();
+        assert_eq!(selected(&app).as_deref(), Some("cn=alice"));
+
+        // Entries missing from the file and other attributes are reported
+        for label in ["member [1]", "dc"] {
+            app.notification = None;
+            select(&mut app, label);
+            app.jump_to_dn();
+            assert_eq!(selected(&app).as_deref(), Some(label));
+            assert!(app.notification.is_some(), "{}", label);
+        }
+    }
+}
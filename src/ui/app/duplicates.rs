@@ -0,0 +1,133 @@
+//! Finding repeated values and subtrees.
+//!
+//! `*` searches the whole tree for values equal to the selected one, with a
+//! `v=` query, so `n`/`N`, highlighting and the `F` filter work as after `/`.
+//! `g*` hashes every subtree (see [`crate::tree::duplicates`]) and lists the
+//! groups of identical ones, largest savings first.
+
+use super::App;
+use crate::config::CaseSensitivity;
+use crate::tree::duplicates::{MIN_DUPLICATE_NODES, find_duplicates};
+use crate::ui::finder::Finder;
+use crate::ui::tree_view::format_thousands;
+
+/// Most nodes hashed by `g*`
+const MAX_DUPLICATE_NODES: usize = 500_000;
+
+impl App {
+    /// Search for values equal to the selected node's value
+    pub(super) fn search_same_value(&mut self) {
+        let Some(value) = self.get_node_string_value() else {
+            self.notify_warning("The selected node has no value");
+            return;
+        };
+        self.clear_search();
+        self.search_query = format!("v={}", value);
+        self.search_case = CaseSensitivity::Sensitive;
+        self.search_subtree = false;
+        self.perform_search(self.search_scope());
+    }
+
+    /// List the groups of identical subtrees in the whole tree
+    pub(super) fn show_duplicate_subtrees(&mut self) {
+        let duplicates = find_duplicates(&self.tree, self.tree.root_id(), MAX_DUPLICATE_NODES);
+        if duplicates.groups.is_empty() {
+            self.notify(format!(
+                "No identical subtrees of {} or more nodes",
+                MIN_DUPLICATE_NODES
+            ));
+        } else {
+            let count = duplicates.groups.len();
+            let entries = duplicates
+                .groups
+                .iter()
+                .enumerate()
+                .flat_map(|(number, group)| {
+                    let detail = format!(
+                        "#{}/{}: {} copies of {} nodes",
+                        number + 1,
+                        count,
+                        group.nodes.len(),
+                        format_thousands(group.size)
+                    );
+                    group
+                        .nodes
+                        .iter()
+                        .map(move |&node_id| (node_id, detail.clone()))
+                })
+                .map(|(node_id, detail)| {
+                    let path = self.label_path(node_id).join(self.path_separator());
+                    (node_id, path, detail)
+                })
+                .collect();
+            self.finder = Some(Finder::list("Duplicates", entries, 0));
+        }
+
+        if duplicates.limit_reached {
+            self.notify_warning(format!(
+                "Only the first {} nodes were compared (limit reached)",
+                format_thousands(MAX_DUPLICATE_NODES)
+            ));
+        } else if !duplicates.values_compared && !duplicates.groups.is_empty() {
+            self.notify("Subtrees of streamed files are compared by their labels, not values");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_with_config, press};
+
+    fn json_app(json: &str) -> App {
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 3;
+        app_with_config(&JsonParser, json, config)
+    }
+
+    fn selected(app: &App) -> Vec<String> {
+        let id = app.tree_view.get_selected_node_id().unwrap();
+        app.label_path(id)
+    }
+
+    #[test]
+    fn test_star_finds_equal_values() {
+        let mut app = json_app(r#"{"a": "red", "b": {"c": "red", "d": "Red"}, "e": "reddish"}"#);
+        press(&mut app, "j*");
+        app.wait_for_search();
+        assert_eq!(app.search_query, "v=red");
+        assert_eq!(app.search_matches.len(), 2);
+        assert_eq!(selected(&app).last().unwrap(), "a");
+
+        press(&mut app, "n");
+        assert_eq!(selected(&app).last().unwrap(), "c");
+        press(&mut app, "n");
+        assert_eq!(selected(&app).last().unwrap(), "a");
+
+        // Objects have no value of their own
+        press(&mut app, "\x1bj*");
+        assert!(app.search_query.is_empty());
+        assert!(app.notification.is_some());
+    }
+
+    #[test]
+    fn test_lists_duplicate_subtrees() {
+        let mut app =
+            json_app(r#"{"a": {"x": 1, "y": 2}, "b": [{"x": 1, "y": 2}], "c": {"x": 1}}"#);
+        // The list holds both copies; picking one jumps to it
+        press(&mut app, "g*");
+        assert!(app.finder.is_some());
+        press(&mut app, "\n");
+        assert!(app.finder.is_none());
+        assert_eq!(selected(&app), ["a"]);
+        press(&mut app, "g*j\n");
+        assert_eq!(selected(&app), ["b", "[0]"]);
+
+        let mut app = json_app(r#"{"a": {"x": 1, "y": 2}, "b": {"x": 1, "y": 3}}"#);
+        press(&mut app, "g*");
+        assert!(app.finder.is_none());
+        assert!(app.notification.is_some());
+    }
+}
@@ -0,0 +1,680 @@
+//! Editing values: `A` asks for a new value of the selected leaf in the
+//! footer, starting with the current one, `u` undoes the last change and
+//! `:w` or Ctrl-s writes the whole document back to the file with the
+//! format's [`Serializer`](crate::parser::Serializer).
+//!
+//! Only JSON, YAML and TOML files held in memory can be edited. Changed
+//! values are marked like differences until they are saved. Saving writes
+//! keys and items in file order, however `s` sorted them in the view, except
+//! that JSON and TOML keys come out sorted, and leaves out the comments of
+//! YAML and TOML files. Values keep the
+//! kind they were read as: an edited string stays a string even if it reads
+//! as a number, and an edited number stays a number unless the new value
+//! isn't one.
+//!
+//! Everything else the serializer writes as it would write any value, so a
+//! file is only editable if that leaves the values that weren't edited as
+//! they are in the file: numbers such as `1e2`, `0xff` or integers too big
+//! for 64 bits would be rewritten, YAML keys that aren't strings would become
+//! strings and tags such as `!!binary` would be dropped. This is checked on
+//! the first edit by reading back what saving would write.
+//!
+//! Quitting, closing the tab or reloading with unsaved changes asks first;
+//! the same key again goes ahead. `:` also takes `q`, `q!`, `wq` and `x`.
+
+use super::App;
+use crate::parser::{self, json, toml, yaml};
+use crate::tree::diff::Change;
+use crate::tree::graft::EMBEDDED_JSON_TYPE;
+use crate::tree::{Tree, TreeNode, TreeVariant, ValueKind};
+use crate::ui::tree_view::format_thousands;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Formats whose files can be edited and saved
+const EDITABLE_FORMATS: [&str; 3] = ["json", "yaml", "toml"];
+
+/// The values changed since the file was read or saved
+#[derive(Debug, Default)]
+pub(super) struct Edits {
+    /// Value of every changed node as it is in the file
+    saved: HashMap<usize, String>,
+    /// The node changed last and its value and kind before, for `u`
+    last: Option<(usize, String, Option<ValueKind>)>,
+    /// Whether saving writes back the values that weren't edited as they
+    /// were read, or why not, checked at the first edit
+    round_trip: Option<Result<(), String>>,
+}
+
+impl Edits {
+    /// Whether there are changes that aren't saved
+    pub(super) fn is_dirty(&self) -> bool {
+        !self.saved.is_empty()
+    }
+
+    /// Remember that `node_id` changed from `old` of kind `kind` to `new`
+    fn record(&mut self, node_id: usize, old: String, kind: Option<ValueKind>, new: &str) {
+        let saved = self.saved.entry(node_id).or_insert_with(|| old.clone());
+        if saved == new {
+            self.saved.remove(&node_id);
+        }
+        self.last = Some((node_id, old, kind));
+    }
+
+    /// The changed nodes with their saved values, marked in the tree
+    fn changes(&self) -> HashMap<usize, Change> {
+        self.saved
+            .iter()
+            .map(|(&id, value)| (id, Change::Value(value.clone())))
+            .collect()
+    }
+}
+
+/// What the prompt asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptTarget {
+    /// A new value for the node
+    Value(usize),
+    /// A command after `:`
+    Command,
+}
+
+/// The value or command prompt in the footer
+#[derive(Debug)]
+pub(super) struct EditPrompt {
+    target: PromptTarget,
+    text: String,
+    /// Why the last command failed
+    error: Option<String>,
+}
+
+impl EditPrompt {
+    /// Whether the prompt shows an error rather than a question
+    pub(super) fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+impl App {
+    /// `A`: ask for a new value of the selected leaf
+    pub(super) fn start_edit(&mut self) {
+        let Some(node_id) = self.tree_view.get_selected_node_id() else {
+            return;
+        };
+        if let Err(reason) = self.editable(node_id) {
+            self.notify_warning(reason);
+            return;
+        }
+        let value = self
+            .tree
+            .get_node(node_id)
+            .and_then(|node| value_of(&node).map(str::to_string));
+        let Some(value) = value else {
+            return;
+        };
+        if value.contains('\n') {
+            self.notify_warning("Values of several lines can't be edited in the prompt");
+            return;
+        }
+        self.edit_prompt = Some(EditPrompt {
+            target: PromptTarget::Value(node_id),
+            text: value,
+            error: None,
+        });
+    }
+
+    /// `:`: ask for a command
+    pub(super) fn start_command(&mut self) {
+        self.edit_prompt = Some(EditPrompt {
+            target: PromptTarget::Command,
+            text: String::new(),
+            error: None,
+        });
+    }
+
+    /// Handle a key while typing a value or command
+    pub(super) fn handle_edit_input_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = &mut self.edit_prompt else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.edit_prompt = None,
+            KeyCode::Enter => {
+                let target = prompt.target;
+                let text = std::mem::take(&mut prompt.text);
+                self.edit_prompt = None;
+                match target {
+                    PromptTarget::Value(node_id) => self.set_value(node_id, text),
+                    PromptTarget::Command => self.run_command(text.trim()),
+                }
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                prompt.text.clear();
+                prompt.error = None;
+            }
+            KeyCode::Backspace => {
+                prompt.text.pop();
+                prompt.error = None;
+            }
+            KeyCode::Char(c) => {
+                prompt.text.push(c);
+                prompt.error = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether `node_id` holds a value that can be edited and saved, or why not
+    fn editable(&mut self, node_id: usize) -> Result<(), String> {
+        let format = self.tree.format().unwrap_or_default();
+        if matches!(self.tree, TreeVariant::Streaming(_))
+            || !EDITABLE_FORMATS.contains(&format)
+            || self.differences.is_some()
+        {
+            return Err("Only JSON, YAML and TOML files held in memory can be edited".into());
+        }
+        if self.redacts(node_id) {
+            return Err("Turn off redaction (R) to edit masked values".into());
+        }
+        let Some(node) = self.tree.get_node(node_id) else {
+            return Err("Select a value to edit it".into());
+        };
+        if value_of(&node).is_none() {
+            return Err("Select a value to edit it".into());
+        }
+        let in_graft = std::iter::once(node_id)
+            .chain(self.tree.ancestors(node_id))
+            .filter_map(|id| self.tree.get_node(id))
+            .any(|node| node.node_type == EMBEDDED_JSON_TYPE);
+        if in_graft || self.tree.grafted(node_id).is_some() {
+            return Err("Press O to show the plain value before editing it".into());
+        }
+        // Files read from stdin can't be saved anyway
+        let Some(path) = self.source.as_ref().map(|source| source.path.clone()) else {
+            return Ok(());
+        };
+        self.edits
+            .round_trip
+            .get_or_insert_with(|| round_trip(format, &path))
+            .clone()
+    }
+
+    /// Replace the value of `node_id` with `value`, keeping its kind if the
+    /// value reads as one
+    fn set_value(&mut self, node_id: usize, value: String) {
+        let kind = self.tree.get_node(node_id).and_then(|node| node.value_kind);
+        let kind = kind.map(|kind| {
+            if kind.reads(&value) {
+                kind
+            } else {
+                ValueKind::guess(&value)
+            }
+        });
+        let Some((old, old_kind)) = self.tree.set_value(node_id, value.clone(), kind) else {
+            return;
+        };
+        if old == value {
+            return;
+        }
+        self.edits.record(node_id, old, old_kind, &value);
+        self.show_edits();
+        let label = self.tree.get_label(node_id).unwrap_or_default();
+        self.notify(format!("Changed {} (:w saves, u undoes)", label));
+    }
+
+    /// `u`: put back the value before the last change
+    pub(super) fn undo_edit(&mut self) {
+        let Some((node_id, old, old_kind)) = self.edits.last.take() else {
+            self.notify("Nothing to undo");
+            return;
+        };
+        let Some((new, new_kind)) = self.tree.set_value(node_id, old.clone(), old_kind) else {
+            return;
+        };
+        self.edits.record(node_id, new, new_kind, &old);
+        // Only one level of undo
+        self.edits.last = None;
+        self.show_edits();
+        self.tree_view.navigate_to_node(&self.tree, node_id);
+        let label = self.tree.get_label(node_id).unwrap_or_default();
+        self.notify(format!("Undid the change to {}", label));
+    }
+
+    /// Mark the changed values in the tree and show their new values
+    fn show_edits(&mut self) {
+        self.tree_view.set_changes(self.edits.changes());
+        self.tree_view.children_reordered(&self.tree);
+    }
+
+    /// `:w` or Ctrl-s: write the document back to its file.
+    ///
+    /// # Returns
+    ///
+    /// Whether it was written
+    pub(super) fn save_edits(&mut self) -> bool {
+        if !self.edits.is_dirty() {
+            self.notify("No changes to save");
+            return true;
+        }
+        let Some(path) = self.source.as_ref().map(|source| source.path.clone()) else {
+            self.notify_warning("The tree was read from stdin; w writes the selection to a file");
+            return false;
+        };
+        let Some(serializer) = self.tree.format().and_then(parser::get_serializer) else {
+            return false;
+        };
+        let file_order = in_file_order(&self.tree);
+        let tree = file_order.as_ref().unwrap_or(&self.tree);
+        let written = serializer
+            .serialize(tree, tree.root_id())
+            .and_then(|mut text| {
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                std::fs::write(&path, &text)?;
+                Ok(text.len())
+            });
+        match written {
+            Ok(bytes) => {
+                self.edits = Edits::default();
+                self.show_edits();
+                if let Some(source) = &mut self.source {
+                    source.saved();
+                }
+                self.file_changed = false;
+                self.notify(format!(
+                    "Wrote {} bytes to {}",
+                    format_thousands(bytes),
+                    path.display()
+                ));
+                true
+            }
+            Err(e) => {
+                self.notify_error(format!("Cannot save {}: {}", path.display(), e));
+                false
+            }
+        }
+    }
+
+    /// Run a command typed after `:`
+    fn run_command(&mut self, command: &str) {
+        match command {
+            "w" => {
+                self.save_edits();
+            }
+            "q" => self.close_tab(),
+            "q!" => {
+                self.edits = Edits::default();
+                self.close_tab();
+            }
+            "wq" | "x" => {
+                if self.save_edits() {
+                    self.close_tab();
+                }
+            }
+            "" => {}
+            _ => {
+                self.edit_prompt = Some(EditPrompt {
+                    target: PromptTarget::Command,
+                    text: command.to_string(),
+                    error: Some("not a command; :w, :q, :q!, :wq".to_string()),
+                });
+            }
+        }
+    }
+
+    /// Whether `command` may throw away unsaved changes: right away if there
+    /// are none, otherwise only when repeated
+    pub(super) fn confirm_discard(&mut self, command: char) -> bool {
+        let unsaved = match command {
+            // Ctrl-c quits with every tab
+            'c' => self.unsaved_tabs(),
+            _ => usize::from(self.edits.is_dirty()),
+        };
+        if unsaved == 0 || self.confirm_discard == Some(command) {
+            self.confirm_discard = None;
+            return true;
+        }
+        self.confirm_discard = Some(command);
+        let key = match command {
+            'c' => "Ctrl-c".to_string(),
+            c => c.to_string(),
+        };
+        self.notify_warning(format!(
+            "Unsaved changes; :w saves them, {} again discards them",
+            key
+        ));
+        false
+    }
+
+    /// Text of the value or command prompt in the footer
+    pub(super) fn edit_prompt_text(&self) -> String {
+        let Some(prompt) = &self.edit_prompt else {
+            return String::new();
+        };
+        let what = match prompt.target {
+            PromptTarget::Value(node_id) => {
+                format!("{}: ", self.tree.get_label(node_id).unwrap_or_default())
+            }
+            PromptTarget::Command => ":".to_string(),
+        };
+        match &prompt.error {
+            Some(error) => format!("{}{}  ({})", what, prompt.text, error),
+            None => format!("{}{}", what, prompt.text),
+        }
+    }
+
+    /// Footer note on how many values changed, if any did
+    pub(super) fn edit_indicator(&self) -> Option<String> {
+        let changed = self.edits.saved.len();
+        (changed > 0).then(|| format!(" {} changed ", format_thousands(changed)))
+    }
+}
+
+/// Whether writing the file at `path` back with the serializer of `format`
+/// keeps every value as it was read, or why not: the document read back
+/// from what would be written has to hold the same values of the same kinds
+fn round_trip(format: &str, path: &Path) -> Result<(), String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let cannot_keep = |what: String| Err(format!("Saving would change {} of this file", what));
+    let lost = match format {
+        "json" => json::lost_on_save(&content),
+        "yaml" => yaml::lost_on_save(&content),
+        "toml" => toml::lost_on_save(&content),
+        _ => None,
+    };
+    if let Some(what) = lost {
+        return cannot_keep(what);
+    }
+    let Some(serializer) = parser::get_serializer(format) else {
+        return Err(format!("Cannot write {} files", format));
+    };
+    let parser =
+        parser::get_parser_from_format(format, &Default::default()).map_err(|e| e.to_string())?;
+    let read = TreeVariant::from(parser.parse(&content).map_err(|e| e.to_string())?);
+    let written = serializer
+        .serialize(&read, read.root_id())
+        .and_then(|text| parser.parse(&text))
+        .map_err(|e| format!("Cannot save this file: {}", e))?;
+    let TreeVariant::InMemory(read) = read else {
+        return Ok(());
+    };
+    match first_difference(&read, &written) {
+        Some(label) => cannot_keep(format!("the value of {}", label)),
+        None => Ok(()),
+    }
+}
+
+/// The label of the first node that differs between two trees in its label,
+/// type, value, the kind of its value or its number of children. Children
+/// are matched by label, since the serializers of JSON and TOML sort keys.
+fn first_difference(a: &Tree, b: &Tree) -> Option<String> {
+    let by_label = |tree: &Tree, node: &TreeNode| {
+        let mut children = node.children.clone();
+        children.sort_by_cached_key(|&id| tree.get_node(id).map(|node| node.label.clone()));
+        children
+    };
+    let mut stack = vec![(a.root_id(), b.root_id())];
+    while let Some((a_id, b_id)) = stack.pop() {
+        let (Some(a_node), Some(b_node)) = (a.get_node(a_id), b.get_node(b_id)) else {
+            continue;
+        };
+        if a_node.label != b_node.label
+            || a_node.node_type != b_node.node_type
+            || value_of(a_node) != value_of(b_node)
+            || a_node.value_kind != b_node.value_kind
+            || a_node.children.len() != b_node.children.len()
+        {
+            return Some(a_node.label.to_string());
+        }
+        let pairs = by_label(a, a_node).into_iter().zip(by_label(b, b_node));
+        stack.extend(pairs);
+    }
+    None
+}
+
+/// A copy of the tree with the children of every node sorted with `s` back in
+/// file order, or `None` if nothing was sorted
+fn in_file_order(tree: &TreeVariant) -> Option<TreeVariant> {
+    let TreeVariant::InMemory(tree) = tree else {
+        return None;
+    };
+    let reordered = tree.reordered_nodes();
+    if reordered.is_empty() {
+        return None;
+    }
+    let mut tree = Tree::clone(tree);
+    for id in reordered {
+        tree.restore_children(id);
+    }
+    Some(TreeVariant::from(tree))
+}
+
+/// The value of an attribute node
+fn value_of(node: &TreeNode) -> Option<&str> {
+    node.is_attribute()
+        .then(|| node.attributes.first())
+        .flatten()
+        .map(|attr| attr.value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{
+        Parser, json::JsonParser, toml::TomlParser, xml::XmlParser, yaml::YamlParser,
+    };
+    use crate::ui::app::test_support::{app_from, press};
+
+    fn parser(name: &str) -> &'static dyn Parser {
+        match name.rsplit('.').next() {
+            Some("yaml") => &YamlParser,
+            Some("toml") => &TomlParser,
+            _ => &JsonParser,
+        }
+    }
+
+    fn app(dir: &tempfile::TempDir, name: &str, content: &str) -> App {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        let mut app = app_from(parser(name), content).with_file(path, None);
+        app.tree_view.expand_all(&app.tree, None);
+        app
+    }
+
+    #[test]
+    fn test_edited_values_are_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, "a.json", r#"{"host": "a", "port": 80}"#);
+        press(&mut app, "jjA80\n");
+        assert!(app.edits.is_dirty());
+        assert!(app.position_indicator().contains("1 changed"));
+        let text = app.get_visible_tree_text().unwrap();
+        assert!(text.contains("8080"), "{text}");
+
+        press(&mut app, ":w\n");
+        assert!(!app.edits.is_dirty());
+        let saved = std::fs::read_to_string(dir.path().join("a.json")).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&saved).unwrap();
+        assert_eq!(saved, serde_json::json!({"host": "a", "port": 8080}));
+        assert!(!app.file_changed);
+    }
+
+    #[test]
+    fn test_saving_keeps_the_kind_of_every_value() {
+        let files = [
+            (
+                "a.json",
+                r#"{"version": "1.0", "flag": "true", "none": "null", "n": 2, "f": 2.5,
+                    "b": false, "z": null}"#,
+            ),
+            (
+                "a.yaml",
+                "version: '1.0'\nflag: 'true'\nnone: 'null'\nn: 2\nf: 2.5\nb: false\nz: null\n",
+            ),
+            (
+                "a.toml",
+                "version = \"1.0\"\nflag = \"true\"\nday = \"1979-05-27\"\n\
+                 when = 1979-05-27\nn = 2\nf = 1.0\nb = false\n",
+            ),
+        ];
+        for (name, content) in files {
+            let dir = tempfile::tempdir().unwrap();
+            let mut app = app(&dir, name, content);
+            let version = app.tree.find_by_path(&["version"]).unwrap();
+            app.tree_view.navigate_to_node(&app.tree, version);
+            press(&mut app, "A\x08\x08\x082.0\n:w\n");
+
+            let saved = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            let saved = parser(name).parse(&saved).unwrap();
+            let before = parser(name).parse(content).unwrap();
+            let values = |tree: &crate::tree::Tree| -> Vec<_> {
+                tree.get_children(tree.root_id())
+                    .into_iter()
+                    .map(|id| tree.get_node(id).unwrap())
+                    .map(|node| {
+                        (
+                            node.label.clone(),
+                            value_of(node).map(str::to_string),
+                            node.value_kind,
+                        )
+                    })
+                    .collect()
+            };
+            let mut expected = values(&before);
+            for (label, value, _) in &mut expected {
+                if *label == "version" {
+                    *value = Some("2.0".to_string());
+                }
+            }
+            assert_eq!(values(&saved), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_sorting_does_not_reorder_the_saved_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, "a.yaml", "list: [3, 1, 2]\nname: a\n");
+        let list = app.tree.find_by_path(&["list"]).unwrap();
+        app.tree_view.navigate_to_node(&app.tree, list);
+        press(&mut app, "sv");
+        assert!(!app.tree.reordered_nodes().is_empty());
+
+        let name = app.tree.find_by_path(&["name"]).unwrap();
+        app.tree_view.navigate_to_node(&app.tree, name);
+        press(&mut app, "Ab\n:w\n");
+        let saved = std::fs::read_to_string(dir.path().join("a.yaml")).unwrap();
+        assert_eq!(saved, "list:\n- 3\n- 1\n- 2\nname: ab\n");
+        // The view stays sorted
+        let values: Vec<_> = app
+            .tree
+            .get_children(list)
+            .into_iter()
+            .filter_map(|id| app.tree.get_node(id))
+            .filter_map(|node| value_of(&node).map(str::to_string))
+            .collect();
+        assert_eq!(values, ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_undo_puts_back_the_last_value() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, "a.yaml", "name: old\n");
+        press(&mut app, "jA\x08\x08\x08new\n");
+        assert!(app.edits.is_dirty());
+        press(&mut app, "u");
+        assert!(!app.edits.is_dirty());
+        assert!(app.get_visible_tree_text().unwrap().contains("name: old"));
+        // Only one level
+        press(&mut app, "u");
+        assert!(app.get_visible_tree_text().unwrap().contains("name: old"));
+    }
+
+    #[test]
+    fn test_quitting_with_unsaved_changes_asks_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app(&dir, "a.json", r#"{"a": "x"}"#);
+        press(&mut app, "jAy\nq");
+        assert!(!app.should_quit);
+        press(&mut app, "jq");
+        assert!(!app.should_quit);
+        press(&mut app, "qq");
+        assert!(app.should_quit);
+        // The file is left alone
+        let content = std::fs::read_to_string(dir.path().join("a.json")).unwrap();
+        assert_eq!(content, r#"{"a": "x"}"#);
+    }
+
+    #[test]
+    fn test_files_that_saving_would_change_cannot_be_edited() {
+        let files = [
+            (
+                "a.json",
+                "{\"big\": 123456789012345678901234, \"a\": \"x\"}",
+            ),
+            ("a.json", "{\"exp\": 1e2, \"a\": \"x\"}"),
+            ("a.yaml", "1: one\na: x\n"),
+            ("a.yaml", "true: yes\na: x\n"),
+            ("a.yaml", "~: nothing\na: x\n"),
+            ("a.yaml", "data: !!binary aGVsbG8=\na: x\n"),
+            ("a.yaml", "exp: 1e2\na: x\n"),
+            ("a.yaml", "list: [1, 0x1f]\na: x\n"),
+            ("a.toml", "mask = 0xff\na = \"x\"\n"),
+            ("a.toml", "list = [1, 1e2]\na = \"x\"\n"),
+        ];
+        for (name, content) in files {
+            let dir = tempfile::tempdir().unwrap();
+            let mut app = app(&dir, name, content);
+            let a = app.tree.find_by_path(&["a"]).unwrap();
+            app.tree_view.navigate_to_node(&app.tree, a);
+            press(&mut app, "Ay\n:w\n");
+            assert!(!app.edits.is_dirty(), "{content}");
+            let saved = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            assert_eq!(saved, content);
+        }
+    }
+
+    #[test]
+    fn test_files_that_round_trip_can_be_edited() {
+        let files = [
+            (
+                "a.json",
+                "{\"[0]\": \"first\", \"n\": -3, \"f\": 2.5, \"list\": [1, \"2\"], \"a\": \"x\"}",
+            ),
+            (
+                "a.yaml",
+                "# comment\n'1': one\nn: -3\nf: 2.5\nlist: [1, '2']\nnote: a !!b c\ntext: |\n  1e2\na: x\n",
+            ),
+            (
+                "a.toml",
+                "a = \"x\"\nn = -3\nf = 2.5\nlist = [1, \"2\"]\n[t]\nx = 1\n",
+            ),
+        ];
+        for (name, content) in files {
+            let dir = tempfile::tempdir().unwrap();
+            let mut app = app(&dir, name, content);
+            let a = app.tree.find_by_path(&["a"]).unwrap();
+            app.tree_view.navigate_to_node(&app.tree, a);
+            press(&mut app, "Ay\n:w\n");
+            let saved = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            let saved = parser(name).parse(&saved).unwrap();
+            let mut expected = parser(name).parse(content).unwrap();
+            let a = expected.find_by_path(&["a"]).unwrap();
+            expected.get_node_mut(a).unwrap().attributes[0].value = "xy".to_string();
+            assert_eq!(first_difference(&saved, &expected), None, "{content}");
+            assert!(!saved.get_node(saved.root_id()).unwrap().is_array());
+        }
+    }
+
+    #[test]
+    fn test_only_some_formats_can_be_edited() {
+        let mut app = app_from(&XmlParser, "<a>text</a>");
+        app.tree_view.expand_all(&app.tree, None);
+        press(&mut app, "GA");
+        assert!(app.edit_prompt.is_none());
+        press(&mut app, ":nope\n");
+        assert!(app.edit_prompt.as_ref().is_some_and(EditPrompt::has_error));
+    }
+}
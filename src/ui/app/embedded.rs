@@ -0,0 +1,118 @@
+//! Embedded JSON: `O` parses the JSON object or array a value holds, as text
+//! or base64-encoded, and grafts it below the value's node (see
+//! [`crate::tree::graft`]) so it can be navigated and searched. `O` again,
+//! on the node or anywhere in the graft, shows the plain value again.
+
+use super::App;
+use crate::tree::graft::{EMBEDDED_JSON_TYPE, embedded_json};
+
+impl App {
+    /// `O`: parse the JSON in the selected value, or take it off again
+    pub(super) fn toggle_embedded_json(&mut self) {
+        let Some(node_id) = self.tree_view.get_selected_node_id() else {
+            return;
+        };
+        if self.tree.grafted(node_id).is_some() {
+            self.show_plain_value(node_id);
+            return;
+        }
+
+        let embedded = self.get_node_string_value().and_then(|v| embedded_json(&v));
+        let Some(embedded) = embedded else {
+            match self.embedded_json_owner(node_id) {
+                Some(owner) => self.show_plain_value(owner),
+                None => self.notify_warning("The value holds no JSON object or array"),
+            }
+            return;
+        };
+        let Some(top) = self.tree.graft(node_id, embedded.tree) else {
+            return;
+        };
+        self.tree_view.expand_node(node_id);
+        self.tree_view.expand_node(top);
+        self.tree_view.children_reordered(&self.tree);
+        if embedded.base64 {
+            self.notify("Parsed the base64-encoded JSON (O shows the plain value)");
+        } else {
+            self.notify("Parsed the embedded JSON (O shows the plain value)");
+        }
+    }
+
+    /// Take the parsed JSON off `owner` and select it
+    fn show_plain_value(&mut self, owner: usize) {
+        self.tree.ungraft(owner);
+        self.tree_view.children_reordered(&self.tree);
+        self.tree_view.navigate_to_node(&self.tree, owner);
+        self.notify("Showing the plain value");
+    }
+
+    /// The node whose value the embedded JSON `node_id` is part of was
+    /// parsed from
+    fn embedded_json_owner(&self, node_id: usize) -> Option<usize> {
+        std::iter::once(node_id)
+            .chain(self.tree.ancestors(node_id))
+            .find(|&id| {
+                self.tree
+                    .get_node(id)
+                    .is_some_and(|node| node.node_type == EMBEDDED_JSON_TYPE)
+            })
+            .and_then(|top| self.tree.get_parent(top))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_from, press, selected_label};
+
+    fn app() -> App {
+        // `blob` is `{"id": 7}` in base64
+        let json =
+            r#"{"blob": "eyJpZCI6IDd9", "config": "{\"db\": {\"port\": 5432}}", "plain": "{x"}"#;
+        let mut app = app_from(&JsonParser, json);
+        app.tree_view.expand_all(&app.tree, None);
+        app
+    }
+
+    #[test]
+    fn test_embedded_json_becomes_a_subtree() {
+        let mut app = app();
+        press(&mut app, "jjO");
+        let text = app.get_visible_tree_text().unwrap();
+        assert!(text.contains("json"), "{text}");
+        assert!(text.contains("port: 5432"), "{text}");
+
+        // Searches find the parsed values after the value itself
+        press(&mut app, "/5432\n");
+        app.wait_for_search();
+        assert_eq!(app.search_matches.len(), 2);
+        press(&mut app, "n");
+        assert_eq!(selected_label(&app), "port");
+
+        // `O` inside the graft shows the plain value again
+        press(&mut app, "O");
+        assert_eq!(selected_label(&app), "config");
+        assert!(!app.get_visible_tree_text().unwrap().contains("port: 5432"));
+    }
+
+    #[test]
+    fn test_base64_json_is_decoded() {
+        let mut app = app();
+        press(&mut app, "jO");
+        let text = app.get_visible_tree_text().unwrap();
+        assert!(text.contains("json (base64)"), "{text}");
+        assert!(text.contains("id: 7"), "{text}");
+        press(&mut app, "O");
+        assert!(!app.get_visible_tree_text().unwrap().contains("id: 7"));
+    }
+
+    #[test]
+    fn test_values_without_json_stay_plain() {
+        let mut app = app();
+        let before = app.tree.node_count();
+        press(&mut app, "GO");
+        assert_eq!(selected_label(&app), "plain");
+        assert_eq!(app.tree.node_count(), before);
+    }
+}
@@ -0,0 +1,140 @@
+//! Jump history: Ctrl-O goes back to where the selection was before a jump,
+//! Ctrl-I (Tab) goes forward again.
+//!
+//! Any key that moves the selection by more than one row counts as a jump:
+//! search matches, `g`/`G`, marks, parent jumps and counted motions.
+
+use super::App;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Oldest positions are dropped beyond this many
+const MAX_JUMP_HISTORY: usize = 100;
+
+/// Node IDs the selection jumped away from, and those jumped back from
+#[derive(Debug, Default)]
+pub(super) struct JumpHistory {
+    back: Vec<usize>,
+    forward: Vec<usize>,
+}
+
+impl JumpHistory {
+    /// Remember a position before a new jump, which discards the forward history
+    fn push(&mut self, node_id: usize) {
+        self.forward.clear();
+        if self.back.last() == Some(&node_id) {
+            return;
+        }
+        if self.back.len() == MAX_JUMP_HISTORY {
+            self.back.remove(0);
+        }
+        self.back.push(node_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.back.clear();
+        self.forward.clear();
+    }
+}
+
+/// Whether a key moves through the history rather than making a new jump
+pub(super) fn is_history_key(key: &KeyEvent) -> bool {
+    let control = key.modifiers.contains(KeyModifiers::CONTROL);
+    matches!(key.code, KeyCode::Tab)
+        || (control && matches!(key.code, KeyCode::Char('o') | KeyCode::Char('i')))
+}
+
+impl App {
+    /// Selected row index and node, used to detect jumps around a key press
+    pub(super) fn selection(&self) -> Option<(usize, usize)> {
+        let (row, _) = self.tree_view.position()?;
+        Some((row, self.tree_view.get_selected_node_id()?))
+    }
+
+    /// Remember the position before a key if the key moved the selection by
+    /// more than one row
+    pub(super) fn record_jump(&mut self, before: Option<(usize, usize)>) {
+        let (Some((row, node_id)), Some((new_row, _))) = (before, self.selection()) else {
+            return;
+        };
+        if row.abs_diff(new_row) > 1 {
+            self.jump_history.push(node_id);
+        }
+    }
+
+    pub(super) fn jump_back(&mut self) {
+        let Some(target) = self.jump_history.back.pop() else {
+            self.notify("Already at the oldest jump");
+            return;
+        };
+        if let Some(current) = self.tree_view.get_selected_node_id() {
+            self.jump_history.forward.push(current);
+        }
+        self.reveal_node(target);
+        self.notify(format!(
+            "jumped back ({} remaining)",
+            self.jump_history.back.len()
+        ));
+    }
+
+    pub(super) fn jump_forward(&mut self) {
+        let Some(target) = self.jump_history.forward.pop() else {
+            self.notify("Already at the newest jump");
+            return;
+        };
+        if let Some(current) = self.tree_view.get_selected_node_id() {
+            self.jump_history.back.push(current);
+        }
+        self.reveal_node(target);
+        self.notify(format!(
+            "jumped forward ({} remaining)",
+            self.jump_history.forward.len()
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::app::test_support::{press, ten_leaves};
+
+    fn back(app: &mut App) {
+        app.handle_key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::CONTROL))
+            .unwrap();
+    }
+
+    fn selected(app: &App) -> Option<usize> {
+        app.tree_view.get_selected_node_id()
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut app = ten_leaves();
+        press(&mut app, "jG");
+        assert_eq!(selected(&app), Some(10));
+        // Single-row moves are not jumps
+        assert_eq!(app.jump_history.back, [1]);
+
+        back(&mut app);
+        assert_eq!(selected(&app), Some(1));
+        assert!(app.notification.is_some());
+
+        press(&mut app, "\t");
+        assert_eq!(selected(&app), Some(10));
+
+        // A new jump discards the forward history
+        back(&mut app);
+        press(&mut app, "5j");
+        assert!(app.jump_history.forward.is_empty());
+        assert_eq!(app.jump_history.back, [1]);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = JumpHistory::default();
+        for id in 0..MAX_JUMP_HISTORY + 10 {
+            history.push(id);
+        }
+        assert_eq!(history.back.len(), MAX_JUMP_HISTORY);
+        assert_eq!(history.back[0], 10);
+    }
+}
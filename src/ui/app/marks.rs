@@ -0,0 +1,254 @@
+//! Vim-like marks: `m{letter}` remembers the selected node, `'{letter}` jumps
+//! back to it, and `M` lists all marks in a popup.
+//!
+//! A mark stores the node ID along with its label path, so that marks on a
+//! streamed file can be found again after the file is re-indexed.
+
+use super::App;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem},
+};
+
+/// Marks popup width
+const MARKS_POPUP_WIDTH: u16 = 70;
+
+/// A remembered node
+#[derive(Debug, Clone)]
+pub(super) struct Mark {
+    node_id: usize,
+    /// Labels from below the root down to the node
+    labels: Vec<String>,
+}
+
+impl App {
+    /// Handle the key after `m`: set the mark named by a letter
+    pub(super) fn handle_mark_command(&mut self, key: KeyEvent) {
+        self.last_key_was_m = false;
+        if let KeyCode::Char(name) = key.code
+            && name.is_ascii_alphabetic()
+        {
+            self.set_mark(name);
+        }
+    }
+
+    /// Handle the key after `'`: jump to the mark named by a letter
+    pub(super) fn handle_jump_command(&mut self, key: KeyEvent) {
+        self.last_key_was_quote = false;
+        if let KeyCode::Char(name) = key.code
+            && name.is_ascii_alphabetic()
+        {
+            self.jump_to_mark(name);
+        }
+    }
+
+    /// Handle a key while the marks popup is open
+    pub(super) fn handle_marks_popup_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('M') => {
+                self.show_marks = false;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.marks_selected = self.marks_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.marks_selected + 1 < self.marks.len() => {
+                self.marks_selected += 1;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {}
+            KeyCode::Enter => {
+                if let Some(&name) = self.marks.keys().nth(self.marks_selected) {
+                    self.show_marks = false;
+                    self.jump_to_mark(name);
+                }
+            }
+            // Pressing a mark's letter picks it directly
+            KeyCode::Char(name) if self.marks.contains_key(&name) => {
+                self.show_marks = false;
+                self.jump_to_mark(name);
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn open_marks_popup(&mut self) {
+        if self.marks.is_empty() {
+            self.notify("No marks set (use m followed by a letter)");
+            return;
+        }
+        self.marks_selected = 0;
+        self.show_marks = true;
+    }
+
+    fn set_mark(&mut self, name: char) {
+        let Some(node_id) = self.tree_view.get_selected_node_id() else {
+            return;
+        };
+        let labels = self.label_path(node_id);
+        self.marks.insert(name, Mark { node_id, labels });
+        self.notify(format!("Mark '{}' set", name));
+    }
+
+    /// Expand the ancestors of a marked node and select it
+    fn jump_to_mark(&mut self, name: char) {
+        let Some(mark) = self.marks.get(&name) else {
+            self.notify(format!("Mark '{}' is not set", name));
+            return;
+        };
+        let node_id = mark.node_id;
+        self.reveal_node(node_id);
+    }
+
+    /// Find marked nodes again after the tree was rebuilt, dropping marks whose
+    /// node no longer exists
+    pub(super) fn remap_marks(&mut self) {
+        let mut removed = Vec::new();
+        let names: Vec<char> = self.marks.keys().copied().collect();
+        for name in names {
+            let labels = &self.marks[&name].labels;
+            let (node_id, matched) = self.resolve_label_path(labels);
+            if matched == labels.len() {
+                if let Some(mark) = self.marks.get_mut(&name) {
+                    mark.node_id = node_id;
+                }
+            } else {
+                self.marks.remove(&name);
+                removed.push(name.to_string());
+            }
+        }
+
+        if !removed.is_empty() {
+            self.notify_warning(format!(
+                "Removed marks whose nodes are gone: {}",
+                removed.join(", ")
+            ));
+        }
+    }
+
+    pub(super) fn render_marks_popup(&self, frame: &mut Frame) {
+        let area = frame.size();
+        let width = MARKS_POPUP_WIDTH.min(area.width);
+        // One row per mark plus the borders
+        let height = (self.marks.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+        frame.render_widget(Clear, popup_area);
+
+        let items: Vec<ListItem> = self
+            .marks
+            .iter()
+            .enumerate()
+            .map(|(i, (name, mark))| {
+                let path = if mark.labels.is_empty() {
+                    "(root)".to_string()
+                } else {
+                    mark.labels.join(self.path_separator())
+                };
+                let style = if i == self.marks_selected {
+                    Style::default()
+                        .fg(self.theme.value)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(self.theme.popup_fg)
+                };
+                let marker = if i == self.marks_selected {
+                    " → "
+                } else {
+                    "   "
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(marker, style),
+                    Span::styled(format!("{}  ", name), style.fg(self.theme.key)),
+                    Span::styled(path, style),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Marks ")
+                .title_alignment(Alignment::Center)
+                .style(
+                    Style::default()
+                        .fg(self.theme.popup_fg)
+                        .bg(self.theme.popup_bg),
+                ),
+        );
+        frame.render_widget(list, popup_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::tree::{Tree, TreeNode, TreeVariant};
+    use crate::ui::app::test_support::press;
+
+    /// root > (a > a1, b > b1), all collapsed
+    fn app() -> App {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        for label in ["a", "b"] {
+            let parent = tree.add_child_node(0, TreeNode::new(label, "object"));
+            tree.add_child_node(parent, TreeNode::new(format!("{label}1"), "string"));
+        }
+        App::new(TreeVariant::from(tree), Config::default())
+    }
+
+    #[test]
+    fn test_set_and_jump_to_mark() {
+        let mut app = app();
+        // Select b1 and mark it, then collapse everything and go back to the top
+        press(&mut app, "jjlj");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(4));
+        press(&mut app, "mxhhgg");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(0));
+
+        press(&mut app, "'x");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(4));
+        assert_eq!(app.marks[&'x'].labels, ["b", "b1"]);
+    }
+
+    #[test]
+    fn test_unknown_mark_is_reported() {
+        let mut app = app();
+        press(&mut app, "j'q");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+        assert!(app.notification.is_some());
+    }
+
+    #[test]
+    fn test_marks_popup_picks_a_mark() {
+        let mut app = app();
+        press(&mut app, "jmajmbM");
+        assert!(app.show_marks);
+
+        press(&mut app, "j\n");
+        assert!(!app.show_marks);
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(3));
+
+        press(&mut app, "Ma");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+    }
+
+    #[test]
+    fn test_remap_drops_marks_that_are_gone() {
+        let mut app = app();
+        press(&mut app, "jmajmb");
+        // Pretend b's subtree was renamed by a reload
+        app.marks.get_mut(&'b').unwrap().labels = vec!["c".to_string()];
+
+        app.remap_marks();
+        assert!(app.marks.contains_key(&'a'));
+        assert!(!app.marks.contains_key(&'b'));
+        assert!(app.notification.is_some());
+    }
+}
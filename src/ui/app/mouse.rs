@@ -0,0 +1,163 @@
+//! Mouse input: selecting and toggling rows, scrolling, and path bar clicks.
+//!
+//! Mouse capture is enabled unless `ui.mouse` is off (or `--no-mouse` is
+//! given), since capturing disables the terminal's native text selection.
+
+use super::App;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::text::Span;
+use std::time::{Duration, Instant};
+
+/// Rows moved per scroll wheel step
+const SCROLL_ROWS: isize = 3;
+
+/// Two clicks on the same row within this interval count as a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// A left click on a tree row, remembered to detect double-clicks
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Click {
+    index: usize,
+    at: Instant,
+}
+
+impl App {
+    pub(super) fn handle_mouse(&mut self, mouse: MouseEvent) {
+        // Popups and the search prompt are keyboard-only
+        if self.print_popup.is_some()
+            || self.show_help
+            || self.show_decode_menu
+            || self.show_marks
+            || self.finder.is_some()
+            || self.search_mode
+            || self.xpath_mode
+            || self.write_prompt.is_some()
+        {
+            return;
+        }
+
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.tree_view.navigate_by(SCROLL_ROWS, &self.tree),
+            MouseEventKind::ScrollUp => self.tree_view.navigate_by(-SCROLL_ROWS, &self.tree),
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.notification = None;
+                if self.path_area.height > 0 && mouse.row == self.path_area.y {
+                    self.click_path_bar(mouse.column);
+                } else {
+                    self.click_tree(mouse.column, mouse.row);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Select the clicked row; toggle it when clicking the icon or double-clicking
+    fn click_tree(&mut self, column: u16, row: u16) {
+        let Some(hit) = self.tree_view.hit_test(self.tree_area, column, row) else {
+            return;
+        };
+
+        let double_click = self.last_click.is_some_and(|click| {
+            click.index == hit.index && click.at.elapsed() < DOUBLE_CLICK_INTERVAL
+        });
+        self.last_click = Some(Click {
+            index: hit.index,
+            at: Instant::now(),
+        });
+
+        self.tree_view.select_index(hit.index);
+        if hit.on_icon || double_click {
+            self.tree_view.toggle_expand(&self.tree);
+            // A third click shouldn't toggle again
+            self.last_click = None;
+        }
+    }
+
+    /// Jump to the ancestor whose label was clicked in the path bar
+    fn click_path_bar(&mut self, column: u16) {
+        let column = column.saturating_sub(self.path_area.x) as usize;
+        let separator_width = Span::raw(self.path_separator()).width();
+
+        // The path starts after one space of padding
+        let mut start = 1;
+        for (node_id, label) in self.path_bar_segments(self.path_area.width as usize) {
+            let end = start + Span::raw(label.as_str()).width();
+            if let Some(node_id) = node_id
+                && (start..end).contains(&column)
+            {
+                self.tree_view.navigate_to_node(&self.tree, node_id);
+                return;
+            }
+            start = end + separator_width;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::tree::{Tree, TreeNode, TreeVariant};
+    use crossterm::event::KeyModifiers;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    /// root > parent > child, drawn with the tree in rows 0-9 and the path bar on row 10
+    fn app() -> App {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let parent = tree.add_child_node(0, TreeNode::new("parent", "object"));
+        tree.add_child_node(parent, TreeNode::new("child", "string"));
+
+        let mut app = App::new(TreeVariant::from(tree), Config::default());
+        draw(&mut app);
+        app
+    }
+
+    fn draw(app: &mut App) {
+        let mut terminal = Terminal::new(TestBackend::new(40, 12)).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+    }
+
+    fn click(app: &mut App, column: u16, row: u16) {
+        app.handle_mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+        draw(app);
+    }
+
+    #[test]
+    fn test_click_selects_and_icon_click_toggles() {
+        let mut app = app();
+        click(&mut app, 10, 1);
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+
+        // ">> " plus one level of indent puts the icon in column 5
+        click(&mut app, 5, 1);
+        app.tree_view.navigate_to_last_line(&app.tree);
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(2));
+    }
+
+    #[test]
+    fn test_double_click_toggles() {
+        let mut app = app();
+        click(&mut app, 10, 1);
+        click(&mut app, 10, 1);
+        app.tree_view.navigate_to_last_line(&app.tree);
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(2));
+    }
+
+    #[test]
+    fn test_path_bar_click_jumps_to_ancestor() {
+        let mut app = app();
+        app.tree_view.expand_node(1);
+        app.tree_view.navigate_to_node(&app.tree, 2);
+
+        // Path bar reads " root > parent > child"
+        click(&mut app, 9, 10);
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(1));
+        click(&mut app, 1, 10);
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(0));
+    }
+}
@@ -0,0 +1,316 @@
+//! Transient messages shown in the footer, and their history.
+//!
+//! Warnings and errors from the tree layer (index warnings, failed loads) and
+//! from the app itself are collected here instead of being printed to stderr,
+//! which would corrupt the alternate screen. Every notification is also kept
+//! in a short history, which `W` shows in a popup after the footer moved on.
+
+use super::App;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long a notification stays in the footer
+const NOTIFICATION_TTL: Duration = Duration::from_secs(5);
+
+/// How many notifications the message history keeps
+const HISTORY_SIZE: usize = 100;
+
+/// Message history popup width
+const MESSAGES_POPUP_WIDTH: u16 = 90;
+
+/// How important a notification is, which decides its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Severity {
+    /// Confirms that something happened, e.g. a copy
+    Info,
+    /// Something didn't go as asked but nothing failed
+    Warning,
+    Error,
+}
+
+/// A message shown in the footer until it expires or a key is pressed
+#[derive(Debug, Clone)]
+pub(super) struct Notification {
+    message: String,
+    severity: Severity,
+    /// Messages that arrived together with this one but are not shown
+    more: usize,
+    shown_at: Instant,
+}
+
+impl Notification {
+    fn is_expired(&self) -> bool {
+        self.shown_at.elapsed() >= NOTIFICATION_TTL
+    }
+}
+
+/// The notifications shown so far, oldest first
+#[derive(Debug, Default)]
+pub(super) struct MessageHistory {
+    messages: VecDeque<Notification>,
+    /// Lines scrolled up from the newest message while the popup is open
+    scroll: Option<usize>,
+}
+
+impl MessageHistory {
+    fn push(&mut self, notification: Notification) {
+        if self.messages.len() == HISTORY_SIZE {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(notification);
+    }
+
+    /// Whether the history popup is open
+    pub(super) fn is_open(&self) -> bool {
+        self.scroll.is_some()
+    }
+}
+
+impl App {
+    /// Show a message in the footer
+    pub(super) fn notify(&mut self, message: impl Into<String>) {
+        self.push_notification(Severity::Info, message.into(), 0);
+    }
+
+    /// Show a warning in the footer
+    pub(super) fn notify_warning(&mut self, message: impl Into<String>) {
+        self.push_notification(Severity::Warning, message.into(), 0);
+    }
+
+    /// Show an error in the footer
+    pub(super) fn notify_error(&mut self, message: impl Into<String>) {
+        self.push_notification(Severity::Error, message.into(), 0);
+    }
+
+    /// Show a notification and add it to the history
+    fn push_notification(&mut self, severity: Severity, message: String, more: usize) {
+        match severity {
+            Severity::Info => log::info!("{}", message),
+            Severity::Warning | Severity::Error => log::warn!("{}", message),
+        }
+        let notification = Notification {
+            message,
+            severity,
+            more,
+            shown_at: Instant::now(),
+        };
+        self.messages.push(notification.clone());
+        self.notification = Some(notification);
+    }
+
+    /// Show the latest messages reported by the tree, if any.
+    ///
+    /// Tree messages describe failed loads, so they are shown as errors. All
+    /// of them go into the history, the footer shows the latest.
+    pub(super) fn collect_tree_messages(&mut self) {
+        let mut messages = self.tree.take_messages();
+        let Some(latest) = messages.pop() else {
+            return;
+        };
+        let more = messages.len();
+        for message in messages {
+            self.messages.push(Notification {
+                message,
+                severity: Severity::Error,
+                more: 0,
+                shown_at: Instant::now(),
+            });
+        }
+        self.push_notification(Severity::Error, latest, more);
+    }
+
+    /// Drop the notification once it has expired
+    pub(super) fn expire_notification(&mut self) {
+        if self
+            .notification
+            .as_ref()
+            .is_some_and(Notification::is_expired)
+        {
+            self.notification = None;
+        }
+    }
+
+    fn severity_color(&self, severity: Severity) -> Color {
+        match severity {
+            Severity::Info => self.theme.footer,
+            Severity::Warning => self.theme.warning,
+            Severity::Error => self.theme.error,
+        }
+    }
+
+    /// Render the current notification, if any, into the footer area
+    pub(super) fn render_notification(&self, frame: &mut Frame, area: Rect) {
+        let Some(notification) = &self.notification else {
+            return;
+        };
+
+        let mut text = format!(" {}", notification.message);
+        if notification.more > 0 {
+            text.push_str(&format!(" (+{} more)", notification.more));
+        }
+        let color = self.severity_color(notification.severity);
+        frame.render_widget(Paragraph::new(text).style(Style::default().fg(color)), area);
+    }
+
+    /// Open the message history, scrolled to the newest message
+    pub(super) fn open_messages_popup(&mut self) {
+        if self.messages.messages.is_empty() {
+            self.notify("No messages yet");
+            return;
+        }
+        self.messages.scroll = Some(0);
+    }
+
+    pub(super) fn handle_messages_popup_key(&mut self, key: KeyEvent) {
+        let Some(scroll) = self.messages.scroll else {
+            return;
+        };
+        let oldest = self.messages.messages.len().saturating_sub(1);
+        self.messages.scroll = match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some((scroll + 1).min(oldest)),
+            KeyCode::Down | KeyCode::Char('j') => Some(scroll.saturating_sub(1)),
+            KeyCode::Char('g') | KeyCode::Home => Some(oldest),
+            KeyCode::Char('G') | KeyCode::End => Some(0),
+            _ => None,
+        };
+    }
+
+    /// Draw the message history, newest at the bottom like `:messages` in Vim
+    pub(super) fn render_messages_popup(&self, frame: &mut Frame) {
+        let Some(scroll) = self.messages.scroll else {
+            return;
+        };
+        let area = frame.size();
+        let width = MESSAGES_POPUP_WIDTH.min(area.width);
+        let shown = self.messages.messages.len() - scroll;
+        // One row per message plus the borders
+        let height = (shown as u16 + 2).min(area.height.saturating_sub(2));
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+        frame.render_widget(Clear, popup_area);
+
+        let rows = usize::from(height.saturating_sub(2));
+        let lines: Vec<Line> = self
+            .messages
+            .messages
+            .iter()
+            .take(shown)
+            .skip(shown.saturating_sub(rows))
+            .map(|notification| {
+                let style = Style::default().fg(self.severity_color(notification.severity));
+                let age = format_age(notification.shown_at.elapsed());
+                Line::from(vec![
+                    Span::styled(
+                        format!(" {:>4} ", age),
+                        Style::default().fg(self.theme.popup_fg),
+                    ),
+                    Span::styled(notification.message.clone(), style),
+                ])
+            })
+            .collect();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Messages (j/k: Scroll, any other key: Close) ")
+            .style(
+                Style::default()
+                    .fg(self.theme.popup_fg)
+                    .bg(self.theme.popup_bg),
+            );
+        frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+    }
+}
+
+/// How long ago something happened, e.g. `"12s"` or `"3m"`
+fn format_age(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::tree::{Tree, TreeNode, TreeVariant};
+    use crossterm::event::KeyModifiers;
+
+    fn app() -> App {
+        let tree = Tree::new(TreeNode::new("root", "object"));
+        App::new(TreeVariant::from(tree), Config::default())
+    }
+
+    #[test]
+    fn test_history_keeps_every_notification() {
+        let mut app = app();
+        app.notify("first");
+        app.notify_warning("second");
+        app.notify_error("third");
+        let severities: Vec<_> = app
+            .messages
+            .messages
+            .iter()
+            .map(|n| (n.message.as_str(), n.severity))
+            .collect();
+        assert_eq!(
+            severities,
+            [
+                ("first", Severity::Info),
+                ("second", Severity::Warning),
+                ("third", Severity::Error)
+            ]
+        );
+        assert_eq!(app.notification.as_ref().unwrap().message, "third");
+
+        for i in 0..HISTORY_SIZE {
+            app.notify(format!("message {i}"));
+        }
+        assert_eq!(app.messages.messages.len(), HISTORY_SIZE);
+        assert_eq!(app.messages.messages[0].message, "message 0");
+    }
+
+    #[test]
+    fn test_messages_popup_scrolls_and_closes() {
+        let mut app = app();
+        app.open_messages_popup();
+        assert!(!app.messages.is_open());
+
+        app.notify("a");
+        app.notify("b");
+        app.open_messages_popup();
+        let press = |app: &mut App, code| {
+            app.handle_messages_popup_key(KeyEvent::new(code, KeyModifiers::NONE))
+        };
+        // "No messages yet" is a message as well
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Char('k'));
+        }
+        assert_eq!(app.messages.scroll, Some(2));
+        press(&mut app, KeyCode::Char('j'));
+        assert_eq!(app.messages.scroll, Some(1));
+        press(&mut app, KeyCode::Esc);
+        assert!(!app.messages.is_open());
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(5)), "5s");
+        assert_eq!(format_age(Duration::from_secs(125)), "2m");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2h");
+    }
+}
@@ -0,0 +1,264 @@
+//! The print popup of the `p` commands and the decode menu.
+//!
+//! Content longer than the popup scrolls with j/k and the page keys, long
+//! lines either wrap or pan with h/l (`w` switches), and `y` copies the whole
+//! content. JSON, YAML and XML are syntax highlighted.
+
+use super::App;
+use crate::ui::highlight::{self, Syntax};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::Style,
+    text::Text,
+    widgets::{
+        Block, Borders, Clear, Paragraph, Wrap,
+        block::{Position, Title},
+    },
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Print popup width as fraction of screen width (4/5)
+const PRINT_POPUP_WIDTH_FRACTION: u16 = 4;
+const PRINT_POPUP_WIDTH_DIVISOR: u16 = 5;
+const PRINT_POPUP_MAX_WIDTH: u16 = 100;
+
+/// Print popup height as fraction of screen height (3/4)
+const PRINT_POPUP_HEIGHT_FRACTION: u16 = 3;
+const PRINT_POPUP_HEIGHT_DIVISOR: u16 = 4;
+const PRINT_POPUP_MAX_HEIGHT: u16 = 30;
+
+/// Columns moved by h/l
+const PAN_COLUMNS: usize = 4;
+
+/// Printed content and how it is scrolled
+#[derive(Debug)]
+pub(super) struct PrintPopup {
+    content: String,
+    /// The content as drawn, highlighted if its syntax is known
+    text: Text<'static>,
+    /// First row shown
+    scroll: usize,
+    /// First column shown while lines don't wrap
+    column: usize,
+    wrap: bool,
+    /// Rows and columns of content as last drawn, for clamping and paging
+    rows: usize,
+    columns: usize,
+    page: usize,
+    width: usize,
+}
+
+impl PrintPopup {
+    fn new(content: String, text: Text<'static>) -> Self {
+        Self {
+            content,
+            text,
+            scroll: 0,
+            column: 0,
+            wrap: true,
+            rows: 0,
+            columns: 0,
+            page: 1,
+            width: 0,
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.rows.saturating_sub(self.page)
+    }
+
+    fn scroll_by(&mut self, rows: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(rows)
+            .min(self.max_scroll());
+    }
+
+    fn pan_by(&mut self, columns: isize) {
+        if self.wrap {
+            return;
+        }
+        let max_column = self.columns.saturating_sub(self.width);
+        self.column = self.column.saturating_add_signed(columns).min(max_column);
+    }
+
+    /// Update the layout for an inner area of `width` by `height` cells
+    fn measure(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.page = height.max(1);
+        let widths = self.content.lines().map(UnicodeWidthStr::width);
+        self.columns = widths.clone().max().unwrap_or(0);
+        self.rows = if self.wrap {
+            // Approximates word wrapping, which may need an extra row at times
+            widths.map(|w| w.div_ceil(width.max(1)).max(1)).sum()
+        } else {
+            self.content.lines().count()
+        };
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+}
+
+#[cfg(test)]
+impl PrintPopup {
+    pub(super) fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+impl App {
+    /// Show `content` in the print popup highlighted as `syntax`, or warn
+    /// that there is nothing to show
+    pub(super) fn print(&mut self, content: Option<String>, syntax: Option<Syntax>) {
+        let Some(content) = content else {
+            // Some lookups already explained why they came up empty
+            if self.notification.is_none() {
+                self.notify_warning("Nothing to print for the selected node");
+            }
+            return;
+        };
+        let text = match syntax {
+            Some(syntax) => highlight::highlight(&content, syntax, &self.theme),
+            None => Text::raw(content.clone()),
+        };
+        self.print_popup = Some(PrintPopup::new(content, text));
+    }
+
+    /// Handle a key while the print popup is open
+    pub(super) fn handle_print_popup_key(&mut self, key: KeyEvent) {
+        let Some(popup) = &mut self.print_popup else {
+            return;
+        };
+        let page = popup.page as isize;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.print_popup = None,
+            KeyCode::Char('y') => {
+                let content = popup.content.clone();
+                self.copy_to_clipboard(&content);
+            }
+            KeyCode::Char('w') => {
+                popup.wrap = !popup.wrap;
+                popup.column = 0;
+            }
+            KeyCode::Char('d') if ctrl => popup.scroll_by(page / 2),
+            KeyCode::Char('u') if ctrl => popup.scroll_by(-page / 2),
+            KeyCode::Char('f') if ctrl => popup.scroll_by(page),
+            KeyCode::Char('b') if ctrl => popup.scroll_by(-page),
+            KeyCode::Down | KeyCode::Char('j') => popup.scroll_by(1),
+            KeyCode::Up | KeyCode::Char('k') => popup.scroll_by(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') => popup.scroll_by(page),
+            KeyCode::PageUp => popup.scroll_by(-page),
+            KeyCode::Home | KeyCode::Char('g') => popup.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => popup.scroll = popup.max_scroll(),
+            KeyCode::Left | KeyCode::Char('h') => popup.pan_by(-(PAN_COLUMNS as isize)),
+            KeyCode::Right | KeyCode::Char('l') => popup.pan_by(PAN_COLUMNS as isize),
+            _ => {}
+        }
+    }
+
+    pub(super) fn render_print_popup(&mut self, frame: &mut Frame) {
+        let Some(popup) = &mut self.print_popup else {
+            return;
+        };
+
+        // Create centered popup area
+        let area = frame.size();
+        let popup_width = (area.width * PRINT_POPUP_WIDTH_FRACTION / PRINT_POPUP_WIDTH_DIVISOR)
+            .min(PRINT_POPUP_MAX_WIDTH);
+        let popup_height = (area.height * PRINT_POPUP_HEIGHT_FRACTION / PRINT_POPUP_HEIGHT_DIVISOR)
+            .min(PRINT_POPUP_MAX_HEIGHT);
+        let popup_area = Rect {
+            x: (area.width - popup_width) / 2,
+            y: (area.height - popup_height) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+        popup.measure(
+            popup_width.saturating_sub(2) as usize,
+            popup_height.saturating_sub(2) as usize,
+        );
+
+        let position = format!(" {}/{} ", (popup.scroll + 1).min(popup.rows), popup.rows);
+        let hints = if popup.wrap {
+            " j/k: Scroll | w: No wrap | y: Copy | q: Close "
+        } else {
+            " j/k: Scroll | h/l: Pan | w: Wrap | y: Copy | q: Close "
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Printed Content ")
+            .title_alignment(Alignment::Center)
+            .title(Title::from(hints).position(Position::Bottom))
+            .title(
+                Title::from(position)
+                    .position(Position::Bottom)
+                    .alignment(Alignment::Right),
+            )
+            .style(
+                Style::default()
+                    .fg(self.theme.popup_fg)
+                    .bg(self.theme.popup_bg),
+            );
+        let scroll = (
+            popup.scroll.min(u16::MAX as usize) as u16,
+            popup.column.min(u16::MAX as usize) as u16,
+        );
+        let mut paragraph = Paragraph::new(popup.text.clone())
+            .block(block)
+            .scroll(scroll);
+        if popup.wrap {
+            paragraph = paragraph.wrap(Wrap { trim: false });
+        }
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(paragraph, popup_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popup(lines: usize) -> PrintPopup {
+        let content: Vec<String> = (0..lines).map(|i| format!("line {i}")).collect();
+        let content = content.join("\n");
+        let mut popup = PrintPopup::new(content.clone(), Text::raw(content));
+        popup.measure(20, 10);
+        popup
+    }
+
+    #[test]
+    fn test_scrolling_stops_at_the_last_page() {
+        let mut popup = popup(25);
+        popup.scroll_by(10);
+        assert_eq!(popup.scroll, 10);
+        popup.scroll_by(10);
+        assert_eq!(popup.scroll, 15);
+        popup.scroll_by(-100);
+        assert_eq!(popup.scroll, 0);
+
+        // Short content doesn't scroll
+        let mut popup = self::popup(3);
+        popup.scroll_by(1);
+        assert_eq!(popup.scroll, 0);
+    }
+
+    #[test]
+    fn test_wrapped_lines_count_as_rows() {
+        let content = format!("{}\nshort", "x".repeat(45));
+        let mut popup = PrintPopup::new(content.clone(), Text::raw(content));
+        popup.measure(20, 10);
+        assert_eq!(popup.rows, 4);
+
+        // Without wrapping lines pan instead
+        popup.pan_by(4);
+        assert_eq!(popup.column, 0);
+        popup.wrap = false;
+        popup.measure(20, 10);
+        assert_eq!(popup.rows, 2);
+        popup.pan_by(100);
+        assert_eq!(popup.column, 25);
+    }
+}
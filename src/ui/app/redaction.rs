@@ -0,0 +1,144 @@
+//! Redaction: `R` masks the values of the keys in `ui.redact_keys` (see
+//! [`crate::ui::redact`]) for screen sharing.
+//!
+//! The tree, the detail pane, the pinned nodes, the table view and the print
+//! popup show masked values. Copying a selection that holds any asks for the
+//! same keys again first, since the clipboard gets the real values.
+
+use super::App;
+use crate::tree::TreeNode;
+use crate::ui::redact::Redaction;
+
+impl App {
+    /// `R`: mask or show the values of sensitive keys
+    pub(super) fn toggle_redaction(&mut self) {
+        self.config.ui.redact = !self.config.ui.redact;
+        self.tree_view.set_redaction(self.active_redaction());
+        if self.config.ui.redact {
+            self.notify("Masking the values of sensitive keys (ui.redact_keys)");
+        } else {
+            self.notify("Showing all values");
+        }
+    }
+
+    /// The keys to mask while redaction is on
+    pub(super) fn active_redaction(&self) -> Option<Redaction> {
+        self.config.ui.redact.then(|| self.redaction.clone())
+    }
+
+    /// Whether redaction is on and masks the value of `node_id`
+    pub(super) fn redacts(&self, node_id: usize) -> bool {
+        self.config.ui.redact && self.redaction.redacts(&self.tree, node_id)
+    }
+
+    /// `node` with its values masked as they are in the tree
+    pub(super) fn redact_node(&self, node_id: usize, mut node: TreeNode) -> TreeNode {
+        if self.config.ui.redact {
+            self.redaction
+                .redact_node(&mut node, self.redaction.redacts(&self.tree, node_id));
+        }
+        node
+    }
+
+    /// Whether redaction is on and the selected subtree holds masked values
+    pub(super) fn selection_redacted(&mut self) -> bool {
+        if !self.config.ui.redact {
+            return false;
+        }
+        let Some(node_id) = self.tree_view.get_selected_node_id() else {
+            return false;
+        };
+        if self.redaction.redacts(&self.tree, node_id) {
+            return true;
+        }
+        crate::tree::export::to_json(&self.tree, node_id)
+            .is_some_and(|mut export| self.redaction.redact_json(&mut export.value, false) > 0)
+    }
+
+    /// Whether `y` followed by `command` may copy the selection: right away
+    /// if it holds no masked values, otherwise only when repeated
+    pub(super) fn confirm_redacted_yank(&mut self, command: char) -> bool {
+        let copies_values = matches!(command, 'y' | 'v' | 's' | 'o' | 'Y' | 'T' | 'C');
+        if !copies_values || self.confirm_redacted_yank == Some(command) {
+            self.confirm_redacted_yank = None;
+            return true;
+        }
+        if !self.selection_redacted() {
+            return true;
+        }
+        self.confirm_redacted_yank = Some(command);
+        self.notify_warning(format!(
+            "The selection holds redacted values; press y{} again to copy them",
+            command
+        ));
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_with_config, press};
+
+    fn app(redact: bool) -> App {
+        let json = r#"{"user": "ada", "db": {"Password": "hunter2", "port": 5432}}"#;
+        let mut config = Config::default();
+        config.ui.redact = redact;
+        let mut app = app_with_config(&JsonParser, json, config);
+        app.tree_view.expand_all(&app.tree, None);
+        app
+    }
+
+    fn printed(app: &mut App, keys: &str) -> String {
+        press(app, keys);
+        app.print_popup
+            .take()
+            .map(|popup| popup.content().to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_redaction_masks_values() {
+        let mut app = app(true);
+        let text = app.get_visible_tree_text().unwrap();
+        assert!(text.contains("Password: ••••••"), "{text}");
+        assert!(text.contains("port: 5432"), "{text}");
+        assert!(!text.contains("hunter2"));
+
+        // The db object's preview and the printed subtree are masked too
+        press(&mut app, "jh");
+        let text = app.get_visible_tree_text().unwrap();
+        assert!(text.contains("{Password: ••••••, port: 5432}"), "{text}");
+        let printed = printed(&mut app, "pp");
+        assert!(printed.contains("\"Password\": \"••••••\""), "{printed}");
+        assert!(!printed.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_disabled_redaction_leaves_values_alone() {
+        let mut app = app(false);
+        let before = app.get_visible_tree_text().unwrap();
+        assert!(before.contains("Password: hunter2"), "{before}");
+        press(&mut app, "j");
+        assert!(printed(&mut app, "pp").contains("hunter2"));
+
+        // Turning it on and off again restores the same output
+        press(&mut app, "RR");
+        assert_eq!(app.get_visible_tree_text().unwrap(), before);
+    }
+
+    #[test]
+    fn test_copying_redacted_values_asks_first() {
+        let mut app = app(true);
+        press(&mut app, "jj");
+        assert!(!app.confirm_redacted_yank('s'));
+        assert!(app.confirm_redacted_yank('s'));
+        assert!(app.confirm_redacted_yank('k'));
+
+        // Values that aren't masked copy right away
+        press(&mut app, "j");
+        assert!(app.confirm_redacted_yank('s'));
+    }
+}
@@ -0,0 +1,270 @@
+//! Detecting changes to the viewed file and reading it again.
+//!
+//! The app checks the file periodically and warns in the footer when it
+//! changed; `r` reads it again, or with `--watch` the app does so once the
+//! file has stopped changing. Files in memory are parsed anew, streamed files
+//! re-indexed: their trees hold byte offsets, which point at unrelated data
+//! once the file is regenerated.
+//!
+//! Node IDs of the new tree are unrelated to the old ones, so the selection,
+//! expanded nodes and marks are found again by their label paths. If the new
+//! contents fail to parse, the old tree stays with an error in the footer.
+
+use super::App;
+use crate::error::Result;
+use crate::parser::{ParserOptions, ParserRegistry};
+use crate::tree::TreeVariant;
+use crate::tree::streaming::FileStamp;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often the file is checked for changes
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The file a tab was read from
+#[derive(Debug)]
+pub(super) struct FileSource {
+    pub(super) path: PathBuf,
+    /// Format given with `--format`
+    format: Option<String>,
+    /// Stamp of the file when it was last read
+    stamp: Option<FileStamp>,
+    /// Stamp of the file at the last check
+    last_seen: Option<FileStamp>,
+}
+
+impl FileSource {
+    pub(super) fn new(path: PathBuf, format: Option<String>) -> Self {
+        let stamp = FileStamp::of(&path).ok();
+        Self {
+            path,
+            format,
+            stamp,
+            last_seen: stamp,
+        }
+    }
+
+    /// Take the file as read again after writing it
+    pub(super) fn saved(&mut self) {
+        self.stamp = FileStamp::of(&self.path).ok();
+        self.last_seen = self.stamp;
+    }
+
+    /// Parse the file into memory again
+    fn parse(&self, parsers: &ParserRegistry, options: &ParserOptions) -> Result<TreeVariant> {
+        let parser = parsers.for_file(&self.path, self.format.as_deref(), options)?;
+        let content = fs::read_to_string(&self.path)?;
+        Ok(TreeVariant::from(parser.parse(&content)?))
+    }
+}
+
+impl App {
+    /// Check the file for changes, at most once per interval.
+    ///
+    /// When watching, reads the file again once it was the same at two checks
+    /// in a row, so that a file written in several steps is read only once.
+    pub(super) fn poll_file_change(&mut self) {
+        if self.last_file_check.elapsed() < FILE_CHECK_INTERVAL {
+            return;
+        }
+        self.last_file_check = Instant::now();
+
+        let stamp = self
+            .source
+            .as_ref()
+            .and_then(|source| FileStamp::of(&source.path).ok());
+        match (&self.tree, &self.source) {
+            (TreeVariant::Streaming(tree), _) => self.file_changed = tree.is_stale(),
+            (_, Some(source)) => self.file_changed = stamp != source.stamp,
+            _ => {}
+        }
+
+        let settled = match &mut self.source {
+            Some(source) => {
+                let settled = source.last_seen == stamp;
+                source.last_seen = stamp;
+                settled
+            }
+            None => true,
+        };
+        // Unsaved changes are only thrown away with `r`
+        if self.file_changed && self.watch_file && settled && !self.edits.is_dirty() {
+            self.reload_tree();
+        }
+    }
+
+    /// Read the file again and restore the view by label paths.
+    ///
+    /// Entry labels are RDNs below their parent entry and keys below their
+    /// object, so the label path of a node identifies it across reads even
+    /// though node IDs change.
+    pub(super) fn reload_tree(&mut self) {
+        let stamp = self
+            .source
+            .as_ref()
+            .and_then(|source| FileStamp::of(&source.path).ok());
+        let result = match (&self.tree, &self.source) {
+            (TreeVariant::Streaming(tree), _) => tree
+                .reload()
+                .map(|tree| TreeVariant::Streaming(Box::new(tree))),
+            (_, Some(source)) => source.parse(&self.parsers, &self.config.parser),
+            _ => return,
+        };
+        let in_memory = !matches!(self.tree, TreeVariant::Streaming(_));
+        if let Some(source) = &mut self.source {
+            // A file that failed to parse is tried again once it changes
+            source.stamp = stamp;
+            source.last_seen = stamp;
+        }
+
+        match result {
+            Ok(tree) => {
+                let labels = self.selection_label_path();
+                let expanded: Vec<Vec<String>> = self
+                    .tree_view
+                    .expanded_nodes()
+                    .into_iter()
+                    .map(|node_id| self.label_path(node_id))
+                    .collect();
+                let hidden: Vec<Vec<String>> = self
+                    .tree_view
+                    .hidden_nodes()
+                    .into_iter()
+                    .map(|node_id| self.label_path(node_id))
+                    .collect();
+
+                self.tree = tree;
+                self.search_matches.clear();
+                self.current_match_index = None;
+                self.search_origin = None;
+                // The running search reports IDs of the old tree
+                self.search_task = None;
+                self.last_selected_id = None;
+                self.file_changed = false;
+                self.reload_error = None;
+                self.edits = Default::default();
+                self.reset_tree_view();
+                // Node IDs change, so old positions are meaningless
+                self.jump_history.clear();
+                self.restore_expansion(&expanded);
+                self.restore_selection(&labels);
+                self.notify("Reloaded the file");
+                self.remap_marks();
+                self.restore_declutter(&hidden);
+            }
+            Err(e) => {
+                // The old tree is still intact when it lives in memory
+                if in_memory {
+                    self.file_changed = false;
+                }
+                self.reload_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Expand the nodes whose whole label path still exists
+    pub(super) fn restore_expansion(&mut self, paths: &[Vec<String>]) {
+        for labels in paths {
+            let segments: Vec<&str> = labels.iter().map(String::as_str).collect();
+            if let Some(node_id) = self.tree.find_by_path(&segments) {
+                self.tree_view.expand_node(node_id);
+            }
+        }
+    }
+
+    /// Labels from below the root down to the selected node
+    pub(super) fn selection_label_path(&self) -> Vec<String> {
+        match self.tree_view.get_selected_node_id() {
+            Some(node_id) => self.label_path(node_id),
+            None => Vec::new(),
+        }
+    }
+
+    /// Labels from below the root down to `node_id`.
+    ///
+    /// Nodes that can no longer be loaded (attribute values of a changed file)
+    /// end the path at their closest loadable ancestor.
+    pub(super) fn label_path(&self, node_id: usize) -> Vec<String> {
+        let mut current = node_id;
+        let mut labels = Vec::new();
+        loop {
+            match self.tree.get_node(current) {
+                Some(node) => labels.push(node.label.into()),
+                None => labels.clear(),
+            }
+            match self.tree.get_parent(current) {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+
+        // Drop the root itself, then order from the top down
+        labels.pop();
+        labels.reverse();
+        labels
+    }
+
+    /// Select the deepest node matching a label path, expanding its ancestors.
+    pub(super) fn restore_selection(&mut self, labels: &[String]) {
+        let (node_id, _) = self.resolve_label_path(labels);
+        if node_id != self.tree.root_id() {
+            self.expand_to_node(node_id);
+            self.tree_view.navigate_to_node(&self.tree, node_id);
+        }
+    }
+
+    /// The deepest node matching a label path, and how many labels matched
+    pub(super) fn resolve_label_path(&self, labels: &[String]) -> (usize, usize) {
+        let mut current = self.tree.root_id();
+        for (depth, label) in labels.iter().enumerate() {
+            let next = self.tree.get_children(current).into_iter().find(|&child| {
+                self.tree
+                    .get_node(child)
+                    .is_some_and(|node| node.label == *label)
+            });
+            match next {
+                Some(next) => current = next,
+                None => return (current, depth),
+            }
+        }
+        (current, labels.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_with_config, press, selected_label};
+
+    #[test]
+    fn test_reload_keeps_view_and_survives_parse_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        let json = r#"{"a": {"x": 1}, "b": {"y": 2, "z": 3}}"#;
+        fs::write(&path, json).unwrap();
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 1;
+        let mut app = app_with_config(&JsonParser, json, config).with_file(&path, None);
+
+        // Expand `b` and select its `z`
+        press(&mut app, "jjljj");
+        assert_eq!(selected_label(&app), "z");
+
+        // A new key in front shifts every node ID
+        fs::write(&path, r#"{"0": 0, "a": {"x": 1}, "b": {"y": 2, "z": 4}}"#).unwrap();
+        app.reload_tree();
+        assert_eq!(selected_label(&app), "z");
+        assert!(app.notification.is_some());
+        let b = app.tree.get_children(app.tree.root_id())[2];
+        assert!(app.tree_view.expanded_nodes().contains(&b));
+
+        fs::write(&path, "{").unwrap();
+        app.reload_tree();
+        assert!(app.reload_error.is_some());
+        assert!(!app.file_changed);
+        assert_eq!(selected_label(&app), "z");
+    }
+}
@@ -0,0 +1,322 @@
+//! Repeating the last action and recording macros.
+//!
+//! `.` repeats the last structural action on the selected node: toggling it,
+//! expanding or collapsing its siblings (`e`, `E`, `c`, `C`), a sort command
+//! or a copy command. `3.` repeats it three times.
+//!
+//! `Q{letter}` records the motions and structural actions run until the next
+//! `Q` into a register, and `@{letter}` replays them, `@@` the last replayed
+//! register again. `q` already closes the tab, so recording starts with `Q`.
+//! Macros hold [`Action`]s rather than keys, so prompts and modes typed
+//! meanwhile don't end up in them, and actions work relative to the
+//! selection, so a macro does the same from whichever node it starts at,
+//! even after the tree changed. Macros hold at most [`MAX_MACRO_ACTIONS`]
+//! actions. Replaying a macro while recording records its actions, so
+//! macros never run other macros.
+
+use super::App;
+use crate::error::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+
+/// Actions a macro holds at most
+pub(super) const MAX_MACRO_ACTIONS: usize = 1000;
+
+/// A command that macros record, and `.` repeats if it is structural
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Action {
+    /// Move the selection by rows (`j`, `k` and the arrow keys)
+    MoveBy(isize),
+    /// `l`, `count` times
+    SmartRight(usize),
+    /// `h`, `count` times
+    SmartLeft(usize),
+    /// `H`, `count` times
+    Parent(usize),
+    /// `J`, `count` times
+    NextSibling(usize),
+    /// `K`, `count` times
+    PreviousSibling(usize),
+    /// `0`
+    FirstSibling,
+    /// `$`
+    LastSibling,
+    /// `G`
+    LastLine,
+    /// `{line}G`
+    GoToLine(usize),
+    /// Scroll by pages (`[`, `]` and the page keys) or half pages (Ctrl-u,
+    /// Ctrl-d), as rows per page divisor
+    ScrollPages(isize, usize),
+    /// `n`, `count` times
+    NextMatch(usize),
+    /// `N`, `count` times
+    PreviousMatch(usize),
+    /// Expand or collapse the selected node (Enter, Space)
+    Toggle,
+    /// `e`
+    ExpandSiblings,
+    /// `E`
+    ExpandSiblingsDeep,
+    /// `c`
+    CollapseSiblings,
+    /// `C`
+    CollapseSiblingsDeep,
+    /// A sort command, by the key after `s`
+    Sort(char),
+    /// A copy command, by the key after `y`
+    Yank(char),
+}
+
+impl Action {
+    /// Whether `.` repeats the action: everything but motions
+    fn is_structural(self) -> bool {
+        matches!(
+            self,
+            Action::Toggle
+                | Action::ExpandSiblings
+                | Action::ExpandSiblingsDeep
+                | Action::CollapseSiblings
+                | Action::CollapseSiblingsDeep
+                | Action::Sort(_)
+                | Action::Yank(_)
+        )
+    }
+}
+
+/// A macro being recorded
+#[derive(Debug)]
+pub(super) struct Recording {
+    pub(super) register: char,
+    actions: Vec<Action>,
+}
+
+impl App {
+    /// Run an action, add it to the macro being recorded and remember it for
+    /// `.` if it is structural
+    pub(super) fn run_action(&mut self, action: Action) -> Result<()> {
+        self.record_action(action);
+        if action.is_structural() {
+            self.last_action = Some(action);
+        }
+        match action {
+            Action::MoveBy(rows) => self.tree_view.navigate_by(rows, &self.tree),
+            Action::SmartRight(count) => self.tree_view.smart_right(&self.tree, count),
+            Action::SmartLeft(count) => self.tree_view.smart_left(&self.tree, count),
+            Action::Parent(count) => self.tree_view.navigate_to_parent(&self.tree, count),
+            Action::NextSibling(count) => {
+                self.tree_view.navigate_to_next_sibling(&self.tree, count)
+            }
+            Action::PreviousSibling(count) => self
+                .tree_view
+                .navigate_to_previous_sibling(&self.tree, count),
+            Action::FirstSibling => self.tree_view.navigate_to_first_sibling(&self.tree),
+            Action::LastSibling => self.tree_view.navigate_to_last_sibling(&self.tree),
+            Action::LastLine => self.tree_view.navigate_to_last_line(&self.tree),
+            Action::GoToLine(line) => self.go_to_line(line),
+            Action::ScrollPages(direction, divisor) => self.scroll_pages(direction, divisor),
+            Action::NextMatch(count) => {
+                for _ in 0..count {
+                    self.next_match();
+                }
+            }
+            Action::PreviousMatch(count) => {
+                for _ in 0..count {
+                    self.previous_match();
+                }
+            }
+            Action::Toggle => self.tree_view.toggle_expand(&self.tree),
+            Action::ExpandSiblings => self.tree_view.expand_all_siblings(&self.tree),
+            Action::ExpandSiblingsDeep => self.expand_siblings_deep(),
+            Action::CollapseSiblings => self.tree_view.collapse_all_siblings(&self.tree),
+            Action::CollapseSiblingsDeep => self.tree_view.collapse_all_siblings_deep(&self.tree),
+            Action::Sort(command) => self.sort(command),
+            Action::Yank(command) => return self.yank(command),
+        }
+        Ok(())
+    }
+
+    /// Run the last action again, `count` times
+    pub(super) fn repeat_action(&mut self, count: usize) -> Result<()> {
+        let Some(action) = self.last_action else {
+            self.notify_warning("Nothing to repeat yet");
+            return Ok(());
+        };
+        for _ in 0..count {
+            self.run_action(action)?;
+        }
+        Ok(())
+    }
+
+    /// `Q`: stop recording, or wait for the register to record into
+    pub(super) fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(recording) => self.store_macro(recording),
+            None => self.last_key_was_record = true,
+        }
+    }
+
+    /// Handle the key after `Q`: start recording into the register named by
+    /// a letter
+    pub(super) fn handle_record_command(&mut self, key: KeyEvent) {
+        self.last_key_was_record = false;
+        if let KeyCode::Char(register) = key.code
+            && register.is_ascii_alphabetic()
+        {
+            self.recording = Some(Recording {
+                register,
+                actions: Vec::new(),
+            });
+        }
+    }
+
+    /// Add an action to the macro being recorded, stopping at
+    /// [`MAX_MACRO_ACTIONS`]
+    fn record_action(&mut self, action: Action) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+        recording.actions.push(action);
+        if recording.actions.len() >= MAX_MACRO_ACTIONS
+            && let Some(recording) = self.recording.take()
+        {
+            self.notify_warning(format!(
+                "Stopped recording @{} at {} actions",
+                recording.register, MAX_MACRO_ACTIONS
+            ));
+            self.macros.insert(recording.register, recording.actions);
+        }
+    }
+
+    fn store_macro(&mut self, recording: Recording) {
+        self.notify(format!(
+            "Recorded {} actions into @{}",
+            recording.actions.len(),
+            recording.register
+        ));
+        self.macros.insert(recording.register, recording.actions);
+    }
+
+    /// Handle the key after `@`: replay the register named by a letter, or
+    /// with `@` the last replayed one
+    pub(super) fn handle_replay_command(&mut self, key: KeyEvent) -> Result<()> {
+        self.last_key_was_at = false;
+        let count = self.at_count.take().unwrap_or(1).max(1);
+        let register = match key.code {
+            KeyCode::Char('@') => self.last_macro,
+            KeyCode::Char(register) if register.is_ascii_alphabetic() => Some(register),
+            _ => None,
+        };
+        let Some(register) = register else {
+            return Ok(());
+        };
+        let Some(actions) = self.macros.get(&register).cloned() else {
+            self.notify_warning(format!("Nothing recorded in @{}", register));
+            return Ok(());
+        };
+
+        self.last_macro = Some(register);
+        for _ in 0..count {
+            for &action in &actions {
+                self.run_action(action)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_with_config, press};
+
+    fn app(json: &str) -> App {
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 1;
+        app_with_config(&JsonParser, json, config)
+    }
+
+    fn labels(app: &App, node_id: usize) -> Vec<String> {
+        app.tree
+            .get_children(node_id)
+            .into_iter()
+            .map(|id| app.tree.get_label(id).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_dot_repeats_the_last_action() {
+        let mut app = app(r#"{"a": {"z": 1, "y": 2}, "b": {"x": 1, "w": 2}}"#);
+        press(&mut app, ".");
+        assert!(app.notification.is_some());
+
+        // Reverse `a`'s children, then `b`'s with `.`
+        press(&mut app, "jsr");
+        let a = app.tree_view.get_selected_node_id().unwrap();
+        assert_eq!(labels(&app, a), ["z", "y"]);
+        press(&mut app, "J.");
+        let b = app.tree_view.get_selected_node_id().unwrap();
+        assert_eq!(labels(&app, b), ["x", "w"]);
+        assert_eq!(app.last_action, Some(Action::Sort('r')));
+
+        // Motions don't replace the action; toggling does
+        press(&mut app, "K ");
+        assert!(app.tree_view.expanded_nodes().contains(&a));
+        press(&mut app, "J.");
+        assert!(app.tree_view.expanded_nodes().contains(&b));
+        press(&mut app, "2.");
+        assert!(app.tree_view.expanded_nodes().contains(&b));
+    }
+
+    #[test]
+    fn test_record_and_replay_macros() {
+        let mut app = app(r#"{"a": {"x": 1}, "b": {"x": 2}, "c": {"x": 3}}"#);
+        // Expand a row and go to its next sibling
+        press(&mut app, "jQa");
+        assert!(app.position_indicator().contains("recording @a"));
+        press(&mut app, " JQ");
+        assert!(app.recording.is_none());
+        assert_eq!(app.macros[&'a'], [Action::Toggle, Action::NextSibling(1)]);
+        assert!(!app.position_indicator().contains("recording"));
+
+        press(&mut app, "@a");
+        press(&mut app, "@@");
+        let expanded = app.tree_view.expanded_nodes();
+        for id in app.tree.get_children(app.tree.root_id()) {
+            assert!(expanded.contains(&id));
+        }
+
+        press(&mut app, "@b");
+        assert!(app.notification.is_some());
+    }
+
+    #[test]
+    fn test_macros_are_bounded_and_not_recursive() {
+        let mut app = app(r#"{"a": 1, "b": 2}"#);
+        press(&mut app, "Qa");
+        press(&mut app, &"j".repeat(MAX_MACRO_ACTIONS));
+        assert!(app.recording.is_none());
+        assert_eq!(app.macros[&'a'].len(), MAX_MACRO_ACTIONS);
+
+        // Replaying while recording records the replayed actions
+        press(&mut app, "QbjQ");
+        press(&mut app, "Qc2@bKQ");
+        assert_eq!(
+            app.macros[&'c'],
+            [
+                Action::MoveBy(1),
+                Action::MoveBy(1),
+                Action::PreviousSibling(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_macros_skip_prompts_and_modes() {
+        let mut app = app(r#"{"a": {"z": 1, "y": 2}, "b": {"x": 1, "w": 2}}"#);
+        // A search typed while recording isn't part of the macro
+        press(&mut app, "Qa/y\njsrQ");
+        assert_eq!(app.macros[&'a'], [Action::MoveBy(1), Action::Sort('r')]);
+    }
+}
@@ -0,0 +1,609 @@
+//! Incremental search over labels, types and attributes.
+//!
+//! A `k:` prefix restricts the query to labels and attribute keys, a `v:`
+//! prefix to attribute values. Tab in the search prompt restricts the search to
+//! the subtree of the node that was selected when the search started.
+//!
+//! By default case is ignored unless the query contains an uppercase letter
+//! ("smart case"). Ctrl-S in the search prompt switches between smart,
+//! case-sensitive and case-insensitive matching for the current query.
+//!
+//! Ctrl-P in the search prompt matches in paths: the last word of the query
+//! must match a node and the words before it the labels above the node, so
+//! `database host` finds `host` under `database`. The walk builds the paths
+//! as it goes (see [`PathTracker`](crate::tree::search::PathTracker)), and
+//! in streamed trees from the index, so only nodes matching the last word
+//! pay for looking at their path.
+//!
+//! `F` switches between jumping to matches and filtering the tree down to the
+//! matches and their ancestors.
+//!
+//! Queries run on a worker thread (see [`crate::tree::search`]) once typing
+//! pauses, so that huge and streamed trees never freeze the UI.
+//!
+//! Up and Down in the search prompt recall earlier queries (see
+//! [`search_history`](super::search_history)).
+//!
+//! With many matches, `15n` skips ahead 15 of them, `15gn` jumps to match 15
+//! and Ctrl-L lists them all with their paths.
+
+use super::App;
+use crate::config::CaseSensitivity;
+use crate::error::Result;
+use crate::tree::SearchUpdate;
+use crate::ui::finder::Finder;
+use crate::ui::pattern::{SearchField, SearchPattern};
+use crate::ui::tree_view::format_thousands;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::time::{Duration, Instant};
+
+/// Maximum number of nodes to search through
+/// Prevents memory exhaustion when searching large streaming trees
+const MAX_SEARCH_NODES: usize = 100_000;
+
+/// Typing pause after which the query is searched
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Characters of a match's value shown in the match list
+const EXCERPT_LENGTH: usize = 60;
+
+/// Where a search looks for matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct SearchScope {
+    field: SearchField,
+    /// Node whose subtree is searched, the tree root unless searching under the cursor
+    root: usize,
+}
+
+impl App {
+    /// Handle a key while typing a search query
+    pub(super) fn handle_search_input_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                // Exit search mode
+                self.search_mode = false;
+                self.search_history.reset();
+                self.clear_search();
+            }
+            KeyCode::Enter => {
+                // Exit search mode but keep search active, without waiting for the debounce
+                self.search_mode = false;
+                self.record_query();
+                if self.search_due.is_some() {
+                    self.perform_search(self.search_scope());
+                }
+            }
+            KeyCode::Up => self.recall_older_query(),
+            KeyCode::Down => self.recall_newer_query(),
+            KeyCode::Tab => {
+                self.search_subtree = !self.search_subtree;
+                self.schedule_search();
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.search_case = self.search_case.next();
+                self.schedule_search();
+            }
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.search_paths = !self.search_paths;
+                self.schedule_search();
+            }
+            KeyCode::Backspace => {
+                self.search_query.pop();
+                self.schedule_search();
+            }
+            KeyCode::Char(c) => {
+                self.search_query.push(c);
+                self.schedule_search();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Start typing a new query, searching under the selected node if enabled
+    pub(super) fn start_search(&mut self) {
+        self.search_mode = true;
+        self.search_history.reset();
+        self.clear_search();
+        self.search_origin = self.tree_view.get_selected_node_id();
+        self.search_case = self.config.search.case_sensitive;
+    }
+
+    /// The current query as a pattern, matching case as the case mode says.
+    ///
+    /// Searching and highlighting both use this, so they agree on which
+    /// text matches.
+    pub(super) fn search_pattern(&self) -> SearchPattern {
+        let case_sensitive = self.search_case.is_sensitive(&self.search_query);
+        let pattern = SearchPattern::new(&self.search_query, case_sensitive);
+        if self.search_paths {
+            pattern.in_paths(self.path_separator())
+        } else {
+            pattern
+        }
+    }
+
+    /// The scope of the current query prefix and subtree toggle
+    pub(super) fn search_scope(&self) -> SearchScope {
+        if self.search_xpath {
+            return SearchScope {
+                field: SearchField::All,
+                root: self.tree.root_id(),
+            };
+        }
+        let field = self.search_pattern().field();
+        let root = match self.search_origin {
+            Some(origin) if self.search_subtree => origin,
+            _ => self.tree.root_id(),
+        };
+        SearchScope { field, root }
+    }
+
+    /// Describe a scope and the case mode for the footer, e.g.
+    /// `[keys, under users, smart case]`
+    pub(super) fn scope_tag(&self, scope: SearchScope) -> String {
+        let mut parts = Vec::new();
+        match scope.field {
+            SearchField::All => {}
+            SearchField::Keys => parts.push("keys".to_string()),
+            SearchField::Values => parts.push("values".to_string()),
+        }
+        if scope.root != self.tree.root_id() {
+            let label = self
+                .tree
+                .get_node(scope.root)
+                .map(|node| node.label)
+                .unwrap_or_default();
+            parts.push(format!("under {}", label));
+        }
+        if self.search_paths && !self.search_xpath {
+            parts.push("paths".to_string());
+        }
+        // XPath expressions are not matched as text
+        if !self.search_xpath {
+            parts.push(
+                match self.search_case {
+                    CaseSensitivity::Smart => "smart case",
+                    CaseSensitivity::Sensitive => "match case",
+                    CaseSensitivity::Insensitive => "ignore case",
+                }
+                .to_string(),
+            );
+        }
+
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", parts.join(", "))
+        }
+    }
+
+    /// Run the query once typing pauses, replacing any search in progress
+    pub(super) fn schedule_search(&mut self) {
+        self.search_task = None;
+        self.search_matches.clear();
+        self.current_match_index = None;
+        self.search_due = Some(Instant::now() + SEARCH_DEBOUNCE);
+    }
+
+    /// Stop searching and forget the query and its matches, which also
+    /// removes a filter
+    pub(super) fn clear_search(&mut self) {
+        self.search_task = None;
+        self.search_due = None;
+        self.search_query.clear();
+        self.search_matches.clear();
+        self.current_match_index = None;
+        self.search_xpath = false;
+        // A diff stays filtered to its differences
+        self.apply_filter();
+    }
+
+    /// Switch between jumping to matches and filtering the tree to them
+    pub(super) fn toggle_search_filter(&mut self) {
+        self.search_filter = !self.search_filter;
+        self.apply_filter();
+        self.jump_to_current_match();
+        self.notify(if !self.search_filter {
+            "Jump: the whole tree is shown"
+        } else if self.search_query.is_empty() && self.is_diff() {
+            "Filter: only differences and their ancestors are shown"
+        } else {
+            "Filter: only matches and their ancestors are shown"
+        });
+    }
+
+    /// Filter the tree to the current matches if filtering is on and there is
+    /// a query, or without one to the differences when comparing files
+    pub(super) fn apply_filter(&mut self) {
+        let matches = if !self.search_filter {
+            None
+        } else if !self.search_query.is_empty() {
+            Some(self.search_matches.as_slice())
+        } else {
+            self.differences.as_deref()
+        };
+        self.tree_view.set_filter(&self.tree, matches);
+    }
+
+    /// `Filter` while the query filters the tree, `Search` otherwise
+    pub(super) fn search_label(&self) -> &'static str {
+        match (self.search_xpath, self.search_filter) {
+            (true, true) => "XPath filter",
+            (true, false) => "XPath",
+            (false, true) => "Filter",
+            (false, false) => "Search",
+        }
+    }
+
+    /// Search and jump to the first match once it is found
+    pub(super) fn perform_search(&mut self, scope: SearchScope) {
+        self.find_matches(scope);
+        self.search_jump = true;
+    }
+
+    /// Start collecting the rows matching the search query into `search_matches`.
+    ///
+    /// The search runs on a worker thread; [`poll_search`](App::poll_search)
+    /// picks up its matches. Attributes shown inline are matched through their
+    /// element's row.
+    pub(super) fn find_matches(&mut self, scope: SearchScope) {
+        self.search_task = None;
+        self.search_due = None;
+        self.search_jump = false;
+        self.search_matches.clear();
+        self.current_match_index = None;
+
+        let pattern = self.search_pattern();
+        if pattern.is_empty() {
+            return;
+        }
+        match self.tree.search(scope.root, MAX_SEARCH_NODES, pattern) {
+            Ok(task) => self.search_task = Some(task),
+            Err(e) => self.notify_error(format!("Search failed: {}", e)),
+        }
+    }
+
+    /// Start a search whose debounce time is up and collect new matches
+    pub(super) fn poll_search(&mut self) {
+        if self.search_due.is_some_and(|due| Instant::now() >= due) {
+            self.perform_search(self.search_scope());
+        }
+        if let Some(task) = &self.search_task {
+            let update = task.poll();
+            self.apply_search_update(update);
+        }
+    }
+
+    /// Whether a search is waiting for typing to pause or still running
+    pub(super) fn is_searching(&self) -> bool {
+        self.search_due.is_some() || self.search_task.is_some()
+    }
+
+    /// Footer text for a search in progress, e.g. ` searching… 12,345 nodes`
+    pub(super) fn search_progress(&self) -> String {
+        match &self.search_task {
+            Some(task) => format!(" searching… {} nodes", format_thousands(task.searched())),
+            None if self.search_due.is_some() => " searching…".to_string(),
+            None => String::new(),
+        }
+    }
+
+    fn apply_search_update(&mut self, update: SearchUpdate) {
+        // A filter keeps showing the previous matches until new ones arrive
+        let refilter = self.search_filter && (!update.matches.is_empty() || update.finished);
+        for node_id in update.matches {
+            // Several inlined attributes can match on the same row
+            let row = self.tree_view.row_node(&self.tree, node_id);
+            if self.search_matches.last() != Some(&row) {
+                self.search_matches.push(row);
+            }
+        }
+
+        if refilter {
+            self.apply_filter();
+        }
+        if self.search_jump && self.current_match_index.is_none() && !self.search_matches.is_empty()
+        {
+            let before = self.selection();
+            self.current_match_index = Some(0);
+            self.jump_to_current_match();
+            self.record_jump(before);
+        }
+        if update.limit_reached {
+            self.notify_warning(format!(
+                "Search stopped after examining {} nodes (limit reached)",
+                format_thousands(MAX_SEARCH_NODES)
+            ));
+        }
+        if update.finished {
+            self.search_task = None;
+        }
+    }
+
+    /// Jump to match `number`, counting from 1, or the last one if there
+    /// are fewer
+    pub(super) fn jump_to_match(&mut self, number: usize) {
+        if self.search_matches.is_empty() {
+            self.notify_warning("No search matches");
+            return;
+        }
+        let index = number.clamp(1, self.search_matches.len()) - 1;
+        let before = self.selection();
+        self.current_match_index = Some(index);
+        self.jump_to_current_match();
+        self.record_jump(before);
+    }
+
+    /// List the matches found so far with their paths and values
+    pub(super) fn open_match_list(&mut self) {
+        if self.search_matches.is_empty() {
+            self.notify_warning("No search matches");
+            return;
+        }
+        let entries = self
+            .search_matches
+            .iter()
+            .map(|&node_id| {
+                let path = self.label_path(node_id).join(self.path_separator());
+                (node_id, path, self.match_excerpt(node_id))
+            })
+            .collect();
+        let selected = self.current_match_index.unwrap_or(0);
+        self.finder = Some(Finder::list("Matches", entries, selected));
+    }
+
+    /// The start of a node's value, or of its attributes if it has no value
+    fn match_excerpt(&self, node_id: usize) -> String {
+        let Some(node) = self.tree.get_node(node_id) else {
+            return String::new();
+        };
+        let text = match node.attributes.iter().find(|attr| attr.key == "value") {
+            Some(attr) => attr.value.clone(),
+            None => node
+                .attributes
+                .iter()
+                .map(|attr| format!("{}={}", attr.key, attr.value))
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        // Values can span lines, the list has one row per match
+        let mut excerpt: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if let Some((cut, _)) = excerpt.char_indices().nth(EXCERPT_LENGTH) {
+            excerpt.truncate(cut);
+            excerpt.push('…');
+        }
+        excerpt
+    }
+
+    /// Run a pending search to completion
+    #[cfg(test)]
+    pub(super) fn wait_for_search(&mut self) {
+        if self.search_due.is_some() {
+            self.perform_search(self.search_scope());
+        }
+        if let Some(task) = &self.search_task {
+            let update = task.wait();
+            self.apply_search_update(update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::tree::{Tree, TreeNode, TreeVariant};
+    use crate::ui::app::test_support::press;
+    use crossterm::event::KeyModifiers;
+
+    /// root > (users > (name = "alice", id = "name"), name = "bob")
+    fn app() -> App {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let users = tree.add_child_node(0, TreeNode::new("users", "object"));
+        for (key, value) in [("name", "alice"), ("id", "name")] {
+            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", value);
+            tree.add_child_node(users, node);
+        }
+        let mut node = TreeNode::new("name", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute("value", "bob");
+        tree.add_child_node(0, node);
+        App::new(TreeVariant::from(tree), Config::default())
+    }
+
+    /// Search for a query the way typing it does
+    fn search(app: &mut App, query: &str) {
+        app.search_query = query.to_string();
+        app.find_matches(app.search_scope());
+        app.wait_for_search();
+    }
+
+    #[test]
+    fn test_field_prefixes() {
+        let mut app = app();
+        press(&mut app, "/name");
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2, 3, 4]);
+
+        search(&mut app, "k:name");
+        assert_eq!(app.search_matches, [2, 4]);
+        assert_eq!(app.scope_tag(app.search_scope()), " [keys, smart case]");
+
+        search(&mut app, "v:name");
+        assert_eq!(app.search_matches, [3]);
+
+        // A bare prefix matches nothing rather than everything
+        search(&mut app, "v:");
+        assert!(app.search_matches.is_empty());
+        assert!(!app.is_searching());
+    }
+
+    #[test]
+    fn test_subtree_scope_starts_at_selection() {
+        let mut app = app();
+        press(&mut app, "j/\tk:name");
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2]);
+        assert_eq!(
+            app.scope_tag(app.search_scope()),
+            " [keys, under users, smart case]"
+        );
+
+        // The toggle stays on for the next search, under the new selection
+        press(&mut app, "\n");
+        app.tree_view.navigate_to_node(&app.tree, 0);
+        press(&mut app, "/k:name");
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2, 4]);
+        assert_eq!(app.scope_tag(app.search_scope()), " [keys, smart case]");
+    }
+
+    #[test]
+    fn test_ctrl_p_matches_in_paths() {
+        let mut app = app();
+        press(&mut app, "/users name");
+        app.wait_for_search();
+        assert!(app.search_matches.is_empty());
+
+        // `name` under `users`, by label or value, but not the `name` beside it
+        let ctrl_p = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+        app.handle_key(ctrl_p).unwrap();
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2, 3]);
+        assert_eq!(app.scope_tag(app.search_scope()), " [paths, smart case]");
+
+        press(&mut app, "\n/users bob");
+        app.wait_for_search();
+        assert!(app.search_matches.is_empty());
+    }
+
+    #[test]
+    fn test_smart_case_and_ctrl_s_toggle() {
+        let mut app = app();
+        // An uppercase letter makes a smart-case query match case
+        press(&mut app, "/Alice");
+        app.wait_for_search();
+        assert!(app.search_matches.is_empty());
+        assert!(!app.search_pattern().is_match("alice"));
+
+        // Ctrl-S switches to matching case, then to ignoring it
+        let ctrl_s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        app.handle_key(ctrl_s).unwrap();
+        assert_eq!(app.search_query, "Alice");
+        assert_eq!(app.scope_tag(app.search_scope()), " [match case]");
+        app.handle_key(ctrl_s).unwrap();
+        assert_eq!(app.scope_tag(app.search_scope()), " [ignore case]");
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2]);
+        // The highlighted text agrees with the matches
+        assert_eq!(app.search_pattern().find("name = alice"), [7..12]);
+
+        // The next search starts over in the configured mode
+        press(&mut app, "\n/alice");
+        assert_eq!(app.scope_tag(app.search_scope()), " [smart case]");
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2]);
+    }
+
+    #[test]
+    fn test_jump_to_match_number_and_match_list() {
+        let mut app = app();
+        press(&mut app, "/name\n");
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2, 3, 4]);
+
+        press(&mut app, "2n");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(4));
+        press(&mut app, "2gn");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(3));
+        // Past the last match goes to the last one
+        press(&mut app, "9gn");
+        assert_eq!(app.current_match_index, Some(2));
+
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        app.handle_key(ctrl_l).unwrap();
+        assert!(app.finder.is_some());
+        assert_eq!(app.match_excerpt(3), "name");
+        // The list opens at the current match; k and Enter jump to the one above
+        press(&mut app, "k\n");
+        assert!(app.finder.is_none());
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(3));
+        assert_eq!(app.current_match_index, Some(1));
+        press(&mut app, "n");
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(4));
+    }
+
+    #[test]
+    fn test_typing_is_debounced_and_enter_searches_at_once() {
+        let mut app = app();
+        press(&mut app, "/bob");
+        assert!(app.search_due.is_some());
+        assert!(app.search_task.is_none());
+        app.poll_search();
+        assert!(app.search_task.is_none(), "searched before typing paused");
+
+        press(&mut app, "\n");
+        assert!(app.search_due.is_none());
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [4]);
+        // The first match is selected once it arrives
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(4));
+    }
+
+    #[test]
+    fn test_filter_hides_other_branches_until_cleared() {
+        let mut app = app();
+        press(&mut app, "/alice\n");
+        app.wait_for_search();
+        // Expanded to reveal the match: root, users, name, id, name
+        assert_eq!(app.tree_view.position(), Some((3, 5)));
+
+        press(&mut app, "F");
+        assert_eq!(app.search_label(), "Filter");
+        assert_eq!(app.tree_view.position(), Some((3, 3)));
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(2));
+
+        // A new query filters as soon as its matches arrive
+        press(&mut app, "/bob\n");
+        app.wait_for_search();
+        assert_eq!(app.tree_view.position(), Some((2, 2)));
+
+        // Clearing restores the expansion from before filtering, with users open
+        press(&mut app, "\x1b");
+        assert!(!app.tree_view.is_filtered());
+        assert_eq!(app.tree_view.position(), Some((5, 5)));
+    }
+
+    #[test]
+    fn test_escape_cancels_running_search() {
+        let mut app = app();
+        press(&mut app, "/name\n");
+        press(&mut app, "\x1b");
+        assert!(!app.is_searching());
+        assert!(app.search_matches.is_empty());
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn test_up_recalls_committed_queries() {
+        let mut app = app();
+        press(
+            &mut app,
+            "/alice
+/bob
+",
+        );
+        press(&mut app, "/b↑");
+        assert_eq!(app.search_query, "bob");
+        press(&mut app, "↑↑");
+        assert_eq!(app.search_query, "alice");
+        press(&mut app, "↓↓");
+        assert_eq!(app.search_query, "b");
+
+        // Recalled queries are searched like typed ones
+        press(&mut app, "↑↑\n");
+        app.wait_for_search();
+        assert_eq!(app.search_matches, [2]);
+    }
+}
@@ -0,0 +1,189 @@
+//! Earlier search queries, recalled with Up/Down in the search prompt.
+//!
+//! Queries are remembered when a search is committed with Enter, most recent
+//! first and without duplicates. With `search.persist_history` they are kept in
+//! `~/.local/state/xtv/history`, one query per line.
+
+use super::App;
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Remembered queries and the position while recalling them
+#[derive(Debug, Default)]
+pub(super) struct SearchHistory {
+    /// Most recent first
+    entries: VecDeque<String>,
+    size: usize,
+    /// File the history is saved to, if persisted
+    path: Option<PathBuf>,
+    /// Entry shown in the prompt, `None` while editing a new query
+    cursor: Option<usize>,
+    /// Query typed before recalling, restored when moving past the newest entry
+    draft: String,
+}
+
+impl SearchHistory {
+    pub(super) fn new(size: usize) -> Self {
+        Self {
+            size,
+            ..Self::default()
+        }
+    }
+
+    /// Read the history from `path` and save it there from now on.
+    ///
+    /// A missing file is an empty history.
+    pub(super) fn load(&mut self, path: PathBuf) -> io::Result<()> {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        self.path = Some(path);
+        // The file lists the oldest query first, so recording keeps the order
+        for query in content.lines() {
+            self.insert(query);
+        }
+        Ok(())
+    }
+
+    /// Remember a committed query and save the history
+    pub(super) fn record(&mut self, query: &str) -> io::Result<()> {
+        self.reset();
+        if query.is_empty() || query.contains('\n') || self.size == 0 {
+            return Ok(());
+        }
+        self.insert(query);
+        match &self.path {
+            Some(path) => self.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Put a query first, dropping an older copy and the oldest entries
+    fn insert(&mut self, query: &str) {
+        if query.is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| entry != query);
+        self.entries.push_front(query.to_string());
+        self.entries.truncate(self.size);
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut content = String::new();
+        for entry in self.entries.iter().rev() {
+            content.push_str(entry);
+            content.push('\n');
+        }
+        std::fs::write(path, content)
+    }
+
+    /// Stop recalling, so the next Up starts from the newest entry again
+    pub(super) fn reset(&mut self) {
+        self.cursor = None;
+        self.draft.clear();
+    }
+
+    /// The entry before the one shown, remembering `current` when starting
+    fn older(&mut self, current: &str) -> Option<&str> {
+        let next = self.cursor.map_or(0, |cursor| cursor + 1);
+        if next >= self.entries.len() {
+            return None;
+        }
+        if self.cursor.is_none() {
+            self.draft = current.to_string();
+        }
+        self.cursor = Some(next);
+        Some(&self.entries[next])
+    }
+
+    /// The entry after the one shown, or the query typed before recalling
+    fn newer(&mut self) -> Option<String> {
+        match self.cursor? {
+            0 => {
+                self.cursor = None;
+                Some(std::mem::take(&mut self.draft))
+            }
+            cursor => {
+                self.cursor = Some(cursor - 1);
+                Some(self.entries[cursor - 1].clone())
+            }
+        }
+    }
+}
+
+impl App {
+    /// Show the previous query from the history in the search prompt
+    pub(super) fn recall_older_query(&mut self) {
+        if let Some(query) = self.search_history.older(&self.search_query) {
+            self.search_query = query.to_string();
+            self.schedule_search();
+        }
+    }
+
+    /// Show the next query from the history, or the one being typed
+    pub(super) fn recall_newer_query(&mut self) {
+        if let Some(query) = self.search_history.newer() {
+            self.search_query = query;
+            self.schedule_search();
+        }
+    }
+
+    /// Remember the query in the prompt, warning if the history can't be saved
+    pub(super) fn record_query(&mut self) {
+        if let Err(e) = self.search_history.record(&self.search_query) {
+            self.notify_error(format!("Failed to save search history: {}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(queries: &[&str]) -> SearchHistory {
+        let mut history = SearchHistory::new(3);
+        for query in queries {
+            history.record(query).unwrap();
+        }
+        history
+    }
+
+    #[test]
+    fn test_duplicates_move_to_front_and_size_is_kept() {
+        let history = history(&["a", "b", "c", "a", "d"]);
+        assert_eq!(history.entries, ["d", "a", "c"]);
+    }
+
+    #[test]
+    fn test_recall_restores_the_draft() {
+        let mut history = history(&["a", "b"]);
+        assert_eq!(history.older("dr"), Some("b"));
+        assert_eq!(history.older("b"), Some("a"));
+        assert_eq!(history.older("a"), None);
+        assert_eq!(history.newer().as_deref(), Some("b"));
+        assert_eq!(history.newer().as_deref(), Some("dr"));
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_saved_history_is_loaded_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("xtv").join("history");
+
+        let mut history = SearchHistory::new(3);
+        history.load(path.clone()).unwrap();
+        for query in ["a", "b", "a"] {
+            history.record(query).unwrap();
+        }
+
+        let mut loaded = SearchHistory::new(3);
+        loaded.load(path).unwrap();
+        assert_eq!(loaded.entries, ["a", "b"]);
+    }
+}
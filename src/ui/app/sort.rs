@@ -0,0 +1,197 @@
+//! Sorting children at runtime with the `s` prefix, without touching the file.
+//!
+//! `sk` and `sv` sort the selected node's children by key or value (`sK` and
+//! `sV` its whole subtree), `sf` sorts the objects of the enclosing array by
+//! the selected field, `sr` reverses the children and `ss` restores the
+//! order of the file below the selected node. See [`crate::tree::sort`].
+
+use super::App;
+use super::repeat::Action;
+use crate::error::Result;
+use crate::tree::sort::{self, SortKey};
+use crate::ui::tree_view::format_thousands;
+use crossterm::event::{KeyCode, KeyEvent};
+
+impl App {
+    /// Handle the key after `s`
+    pub(super) fn handle_sort_command(&mut self, key: KeyEvent) -> Result<()> {
+        self.last_key_was_s = false;
+        match key.code {
+            KeyCode::Char(command @ ('k' | 'v' | 'K' | 'V' | 'f' | 'r' | 's')) => {
+                self.run_action(Action::Sort(command))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Run the sort command named by the key after `s`
+    pub(super) fn sort(&mut self, command: char) {
+        let Some(node_id) = self.tree_view.get_selected_node_id() else {
+            return;
+        };
+        match command {
+            'k' => self.sort_children(node_id, &SortKey::Key, false),
+            'v' => self.sort_children(node_id, &SortKey::Value, false),
+            'K' => self.sort_children(node_id, &SortKey::Key, true),
+            'V' => self.sort_children(node_id, &SortKey::Value, true),
+            'f' => self.sort_by_field(node_id),
+            'r' => {
+                if self.tree.get_children(node_id).len() < 2 {
+                    self.notify_warning("Nothing to sort here");
+                    return;
+                }
+                let order = sort::reversed_children(&self.tree, node_id);
+                self.tree.reorder_children(node_id, order);
+                self.tree_view.children_reordered(&self.tree);
+                self.notify("Reversed the children");
+            }
+            's' => self.restore_order(node_id),
+            _ => {}
+        }
+    }
+
+    /// Sort the children of `node_id`, or with `recursive` of every node in
+    /// its subtree
+    fn sort_children(&mut self, node_id: usize, key: &SortKey, recursive: bool) {
+        let mut sorted = 0;
+        let mut stack = vec![node_id];
+        while let Some(id) = stack.pop() {
+            let children = self.tree.get_children(id);
+            if recursive {
+                stack.extend(&children);
+            }
+            if children.len() < 2 {
+                continue;
+            }
+            let order = sort::sorted_children(&self.tree, id, key, false);
+            self.tree.reorder_children(id, order);
+            sorted += 1;
+        }
+        if sorted == 0 {
+            self.notify_warning("Nothing to sort here");
+            return;
+        }
+        self.tree_view.children_reordered(&self.tree);
+
+        let by = if *key == SortKey::Key { "key" } else { "value" };
+        if recursive {
+            self.notify(format!(
+                "Sorted the children of {} nodes by {} (ss restores)",
+                format_thousands(sorted),
+                by
+            ));
+        } else {
+            self.notify(format!("Sorted by {} (ss restores)", by));
+        }
+    }
+
+    /// Sort the array (or element) holding the selected field's object by
+    /// that field, e.g. a list of users by `age` from any user's `age`
+    fn sort_by_field(&mut self, node_id: usize) {
+        let Some(field) = self.tree.get_label(node_id) else {
+            return;
+        };
+        let mut owner = self.tree.get_parent(node_id);
+        // Attributes belong to the element above their `@attributes` node
+        if let Some(id) = owner
+            && self
+                .tree
+                .get_node(id)
+                .is_some_and(|node| node.is_virtual_attributes())
+        {
+            owner = self.tree.get_parent(id);
+        }
+        let Some(container) = owner.and_then(|id| self.tree.get_parent(id)) else {
+            self.notify_warning("Select a field of an object inside an array to sort by it");
+            return;
+        };
+
+        let order =
+            sort::sorted_children(&self.tree, container, &SortKey::Field(field.clone()), false);
+        self.tree.reorder_children(container, order);
+        self.tree_view.children_reordered(&self.tree);
+        self.notify(format!("Sorted by {} (ss restores)", field));
+    }
+
+    /// Restore the file order of `node_id` and everything below it
+    fn restore_order(&mut self, node_id: usize) {
+        let mut restored = 0;
+        for id in self.tree.reordered_nodes() {
+            let below = id == node_id || self.tree.ancestors(id).any(|a| a == node_id);
+            if below && self.tree.restore_children(id) {
+                restored += 1;
+            }
+        }
+        if restored == 0 {
+            self.notify("Already in file order");
+            return;
+        }
+        self.tree_view.children_reordered(&self.tree);
+        self.notify("Restored the file order");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{json::JsonParser, yaml::YamlParser};
+    use crate::ui::app::test_support::{app_from, press};
+
+    fn labels(app: &App, node_id: usize) -> Vec<String> {
+        app.tree
+            .get_children(node_id)
+            .into_iter()
+            .filter_map(|id| app.tree.get_label(id))
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_and_restore() {
+        // YAML keeps the keys in file order
+        let yaml = "list:\n  b:\n    z: 1\n    y: 2\n  a: 3\n  c: 1\n";
+        let mut app = app_from(&YamlParser, yaml);
+        press(&mut app, "j");
+        let list = app.tree_view.get_selected_node_id().unwrap();
+        let b = app.tree.get_children(list)[0];
+
+        press(&mut app, "sk");
+        assert_eq!(labels(&app, list), ["a", "b", "c"]);
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(list));
+        press(&mut app, "sv");
+        assert_eq!(labels(&app, list), ["c", "a", "b"]);
+        press(&mut app, "sr");
+        assert_eq!(labels(&app, list), ["b", "a", "c"]);
+
+        press(&mut app, "sK");
+        assert_eq!(labels(&app, b), ["y", "z"]);
+        press(&mut app, "ss");
+        assert_eq!(labels(&app, list), ["b", "a", "c"]);
+        assert_eq!(labels(&app, b), ["z", "y"]);
+        assert!(app.tree.reordered_nodes().is_empty());
+    }
+
+    #[test]
+    fn test_sort_array_by_selected_field() {
+        let mut app = app_from(
+            &JsonParser,
+            r#"[{"id": 10, "n": "x"}, {"id": 2, "n": "y"}, {"id": 1}]"#,
+        );
+        let root = app.tree.root_id();
+        let first = app.tree.get_children(root)[0];
+        app.tree_view.expand_node(first);
+        // Select the first object's `id`
+        press(&mut app, "jjsf");
+
+        let ids: Vec<usize> = app.tree.get_children(root);
+        let values: Vec<String> = ids
+            .iter()
+            .map(|&id| {
+                let field = app.tree.get_children(id)[0];
+                app.tree.get_node(field).unwrap().attributes[0]
+                    .value
+                    .clone()
+            })
+            .collect();
+        assert_eq!(values, ["1", "2", "10"]);
+    }
+}
@@ -0,0 +1,251 @@
+//! The statistics popup of `S`: how big the selected node's subtree is
+//! before expanding it.
+//!
+//! The walk runs on a worker thread (see [`crate::tree::stats`]) while the
+//! popup counts the nodes visited; Esc cancels it. Streaming trees read
+//! values from disk only for subtrees of up to [`MAX_STATS_DISK_VALUES`]
+//! values, and otherwise ask before reading them.
+
+use super::App;
+use crate::tree::{MemoryUsage, NodeStats, StatsTask};
+use crate::ui::tree_view::{format_size, format_thousands};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, Paragraph,
+        block::{Position, Title},
+    },
+};
+
+/// Most values a streaming tree reads from disk without asking
+const MAX_STATS_DISK_VALUES: usize = 1_000_000;
+
+const STATS_POPUP_WIDTH: u16 = 56;
+
+/// Width of the name column
+const STATS_NAME_WIDTH: usize = 16;
+
+/// The walk or result shown in the popup
+#[derive(Debug)]
+pub(super) struct StatsPopup {
+    node_id: usize,
+    label: String,
+    /// The running walk, until it reports
+    task: Option<StatsTask>,
+    stats: Option<NodeStats>,
+    /// Memory of the whole tree when the popup opened
+    memory: MemoryUsage,
+}
+
+impl App {
+    /// Gather statistics about the selected node's subtree
+    pub(super) fn show_stats(&mut self) {
+        if let Some(node_id) = self.tree_view.get_selected_node_id() {
+            self.start_stats(node_id, MAX_STATS_DISK_VALUES);
+        }
+    }
+
+    fn start_stats(&mut self, node_id: usize, value_limit: usize) {
+        match self.tree.stats(node_id, value_limit) {
+            Ok(task) => {
+                self.stats_popup = Some(StatsPopup {
+                    node_id,
+                    label: self.tree.get_label(node_id).unwrap_or_default(),
+                    task: Some(task),
+                    stats: None,
+                    memory: self.tree.memory_usage(),
+                });
+            }
+            Err(e) => self.notify_error(format!("Statistics failed: {}", e)),
+        }
+    }
+
+    /// Pick up the result of a finished walk
+    pub(super) fn poll_stats(&mut self) {
+        if let Some(popup) = &mut self.stats_popup
+            && let Some(task) = &popup.task
+            && let Some(stats) = task.poll()
+        {
+            popup.stats = Some(stats);
+            popup.task = None;
+        }
+    }
+
+    /// Whether a walk is running
+    pub(super) fn is_computing_stats(&self) -> bool {
+        self.stats_popup
+            .as_ref()
+            .is_some_and(|popup| popup.task.is_some())
+    }
+
+    /// Handle a key while the statistics popup is open
+    pub(super) fn handle_stats_popup_key(&mut self, key: KeyEvent) {
+        let Some(popup) = &self.stats_popup else {
+            return;
+        };
+        match key.code {
+            // Dropping the task cancels a running walk
+            KeyCode::Esc | KeyCode::Char('q') => self.stats_popup = None,
+            KeyCode::Char('S') if popup.stats.as_ref().is_some_and(|s| s.unread_values > 0) => {
+                self.start_stats(popup.node_id, usize::MAX);
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn render_stats_popup(&self, frame: &mut Frame) {
+        let Some(popup) = &self.stats_popup else {
+            return;
+        };
+
+        let name = Style::default().fg(self.theme.key);
+        let value = Style::default().fg(self.theme.value);
+        let row = |label: &str, text: String| {
+            Line::from(vec![
+                Span::styled(format!(" {:<STATS_NAME_WIDTH$}", label), name),
+                Span::styled(text, value),
+            ])
+        };
+
+        let mut lines = Vec::new();
+        let hints = match (&popup.task, &popup.stats) {
+            (Some(task), _) => {
+                lines.push(row(
+                    "Counting…",
+                    format!("{} nodes", format_thousands(task.visited())),
+                ));
+                " Esc: Cancel "
+            }
+            (None, Some(stats)) => {
+                lines.push(row("Descendants", format_thousands(stats.descendants)));
+                lines.push(row("Max depth", stats.max_depth.to_string()));
+                lines.push(row("Distinct keys", format_thousands(stats.distinct_keys)));
+                lines.push(row(
+                    "Value bytes",
+                    match stats.value_bytes {
+                        Some(bytes) => format_thousands(bytes as usize),
+                        None => {
+                            format!("{} values not read", format_thousands(stats.unread_values))
+                        }
+                    },
+                ));
+                if let Some(bytes) = stats.json_bytes {
+                    lines.push(row("Size as JSON", format_size(bytes as usize)));
+                }
+                if let Some(numbers) = &stats.numbers {
+                    lines.push(row("Numbers", format_thousands(numbers.count)));
+                    lines.push(row(
+                        "  min/max",
+                        format!("{} / {}", numbers.min, numbers.max),
+                    ));
+                    lines.push(row("  mean", format_number(numbers.mean)));
+                }
+                if !stats.by_type.is_empty() {
+                    lines.push(Line::default());
+                    lines.push(Line::from(Span::styled(" By type", name)));
+                    for (node_type, count) in &stats.by_type {
+                        lines.push(row(&format!("  {}", node_type), format_thousands(*count)));
+                    }
+                }
+                lines.push(Line::default());
+                lines.push(Line::from(Span::styled(" Memory of the tree", name)));
+                let bytes = |n: usize| format!("{} bytes", format_thousands(n));
+                match popup.memory {
+                    MemoryUsage::InMemory { heap_bytes } => {
+                        lines.push(row("  nodes", bytes(heap_bytes)));
+                    }
+                    MemoryUsage::Streaming {
+                        index_bytes,
+                        cache_bytes,
+                        cached_nodes,
+                    } => {
+                        lines.push(row("  index", bytes(index_bytes)));
+                        lines.push(row(
+                            "  cache",
+                            format!(
+                                "{} ({} nodes)",
+                                bytes(cache_bytes),
+                                format_thousands(cached_nodes)
+                            ),
+                        ));
+                    }
+                }
+                if stats.unread_values > 0 {
+                    " S: Read values from disk | q: Close "
+                } else {
+                    " q: Close "
+                }
+            }
+            (None, None) => " q: Close ",
+        };
+
+        let area = frame.size();
+        let width = STATS_POPUP_WIDTH.min(area.width);
+        // One row per line plus the borders
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Statistics: {} ", popup.label))
+            .title_alignment(Alignment::Center)
+            .title(Title::from(hints).position(Position::Bottom))
+            .style(
+                Style::default()
+                    .fg(self.theme.popup_fg)
+                    .bg(self.theme.popup_bg),
+            );
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+    }
+}
+
+/// A number with at most four decimals and no trailing zeros
+fn format_number(number: f64) -> String {
+    let text = format!("{:.4}", number);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_from, press};
+
+    #[test]
+    fn test_stats_popup_shows_result_and_closes() {
+        let mut app = app_from(&JsonParser, r#"{"a": [1, 2, 4]}"#);
+        press(&mut app, "jS");
+        assert!(app.stats_popup.is_some());
+        assert!(matches!(
+            app.stats_popup.as_ref().unwrap().memory,
+            MemoryUsage::InMemory { heap_bytes } if heap_bytes > 0
+        ));
+
+        let task = app.stats_popup.as_mut().unwrap().task.take().unwrap();
+        let stats = task.wait().unwrap();
+        assert_eq!(stats.descendants, 3);
+        assert_eq!(format_number(stats.numbers.unwrap().mean), "2.3333");
+
+        // Keys go to the popup until it is closed
+        press(&mut app, "j");
+        assert!(app.stats_popup.is_some());
+        press(&mut app, "\x1b");
+        assert!(app.stats_popup.is_none());
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(2.0), "2");
+        assert_eq!(format_number(-0.25), "-0.25");
+    }
+}
@@ -0,0 +1,77 @@
+//! The table view of `T`: the objects of the array at or around the
+//! selection, one row each (see [`crate::ui::table_view`]).
+
+use super::App;
+use crate::tree::table::extract_table;
+use crate::ui::table_view::TableView;
+
+impl App {
+    /// Show the closest array of objects at or above the selection as a
+    /// table, with the row of the selected object selected
+    pub(super) fn open_table_view(&mut self) {
+        let Some(selected) = self.tree_view.get_selected_node_id() else {
+            return;
+        };
+        let mut path = vec![selected];
+        while let Some(parent) = self.tree.get_parent(*path.last().unwrap()) {
+            path.push(parent);
+        }
+        for (depth, &node_id) in path.iter().enumerate() {
+            let Some(mut table) = extract_table(&self.tree, node_id) else {
+                continue;
+            };
+            if self.config.ui.redact {
+                let redacted = self.redaction.redacts(&self.tree, node_id);
+                self.redaction.redact_table(&mut table, redacted);
+            }
+            let label = self.tree.get_label(node_id).unwrap_or_default();
+            let mut view = TableView::new(label, table);
+            // The object of the array the selection is in
+            if let Some(&row) = depth.checked_sub(1).and_then(|depth| path.get(depth)) {
+                view.select_node(row);
+            }
+            self.table_view = Some(view);
+            return;
+        }
+        self.notify_warning("The selection isn't in an array of objects");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_with_config, press};
+
+    fn selected(app: &App) -> Vec<String> {
+        let id = app.tree_view.get_selected_node_id().unwrap();
+        app.label_path(id)
+    }
+
+    #[test]
+    fn test_table_of_the_enclosing_array() {
+        let json = r#"{"users": [{"name": "a", "age": 1}, {"name": "b", "age": 2}], "n": 1}"#;
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 3;
+        let mut app = app_with_config(&JsonParser, json, config);
+
+        // From a field of the second user
+        let age = app.tree.find_by_path(&["users", "[1]", "age"]).unwrap();
+        app.reveal_node(age);
+        press(&mut app, "T");
+        assert!(app.table_view.is_some());
+
+        // Keys go to the table; Enter jumps to the selected row's object
+        press(&mut app, "k\n");
+        assert!(app.table_view.is_none());
+        assert_eq!(selected(&app), ["users", "[0]"]);
+
+        // Other nodes have no table
+        let n = app.tree.find_by_path(&["n"]).unwrap();
+        app.reveal_node(n);
+        press(&mut app, "T");
+        assert!(app.table_view.is_none());
+        assert!(app.notification.is_some());
+    }
+}
@@ -0,0 +1,291 @@
+//! Tabs for viewing several files at once, as in `xtv a.yaml b.yaml`.
+//!
+//! Each tab has its own tree, view, search, marks, pins and jump history. The
+//! active tab's state lives in the [`App`] fields the rest of the app works
+//! with; switching tabs swaps it with the state kept in the tab. With more
+//! than one tab `gt` and `gT` switch to the next and previous tab, and `q`
+//! closes the tab.
+
+use super::{App, declutter, edit, history, marks, reload};
+use crate::tree::{SearchTask, TreeVariant};
+use crate::ui::tree_view::TreeView;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+use std::collections::BTreeMap;
+use std::mem;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Separator between the names in the tab bar
+const TAB_SEPARATOR: &str = "│";
+
+/// A file open in a tab
+pub(super) struct Tab {
+    /// Shown in the tab bar
+    name: String,
+    /// The tab's state while another tab is active
+    state: Option<TabState>,
+}
+
+/// Everything that belongs to one file rather than to the app
+struct TabState {
+    tree: TreeVariant,
+    tree_view: TreeView,
+    search_query: String,
+    search_matches: Vec<usize>,
+    current_match_index: Option<usize>,
+    search_origin: Option<usize>,
+    search_task: Option<SearchTask>,
+    search_due: Option<Instant>,
+    search_jump: bool,
+    search_xpath: bool,
+    marks: BTreeMap<char, marks::Mark>,
+    pins: Vec<declutter::Pin>,
+    jump_history: history::JumpHistory,
+    file_changed: bool,
+    reload_error: Option<String>,
+    differences: Option<Vec<usize>>,
+    source: Option<reload::FileSource>,
+    edits: edit::Edits,
+}
+
+impl TabState {
+    fn new(tree: TreeVariant) -> Self {
+        Self {
+            tree_view: TreeView::new(tree.root_id()),
+            tree,
+            search_query: String::new(),
+            search_matches: Vec::new(),
+            current_match_index: None,
+            search_origin: None,
+            search_task: None,
+            search_due: None,
+            search_jump: false,
+            search_xpath: false,
+            marks: BTreeMap::new(),
+            pins: Vec::new(),
+            jump_history: history::JumpHistory::default(),
+            file_changed: false,
+            reload_error: None,
+            differences: None,
+            source: None,
+            edits: edit::Edits::default(),
+        }
+    }
+}
+
+impl Tab {
+    /// The tab of the app's initial tree, which starts out active
+    pub(super) fn active(name: String) -> Self {
+        Self { name, state: None }
+    }
+}
+
+impl App {
+    /// Name the last tab opened after the file at `path` its tree was read
+    /// from, with `format` given by `--format`, and read it from there on
+    /// reload
+    pub fn with_file(mut self, path: impl Into<PathBuf>, format: Option<String>) -> Self {
+        let path = path.into();
+        let last = self.tabs.len() - 1;
+        self.tabs[last].name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        let source = reload::FileSource::new(path, format);
+        match &mut self.tabs[last].state {
+            Some(state) => state.source = Some(source),
+            None => self.source = Some(source),
+        }
+        self
+    }
+
+    /// Open `tree` in another tab after the existing ones
+    pub fn with_tab(mut self, tree: TreeVariant) -> Self {
+        let active = self.active_tab;
+        self.tabs.push(Tab {
+            name: String::new(),
+            state: Some(TabState::new(tree)),
+        });
+        // Set the new tab's view up the way the first one was
+        self.switch_tab(self.tabs.len() - 1);
+        self.reset_tree_view();
+        self.switch_tab(active);
+        self
+    }
+
+    /// Whether more than one tab is open
+    pub(super) fn has_tabs(&self) -> bool {
+        self.tabs.len() > 1
+    }
+
+    /// Handle `t` or `T` after `g`
+    pub(super) fn handle_tab_command(&mut self, key: KeyEvent) {
+        let count = self.tabs.len();
+        match key.code {
+            KeyCode::Char('t') => self.switch_tab((self.active_tab + 1) % count),
+            KeyCode::Char('T') => self.switch_tab((self.active_tab + count - 1) % count),
+            _ => {}
+        }
+    }
+
+    /// Make tab `index` the active one
+    pub(super) fn switch_tab(&mut self, index: usize) {
+        if index == self.active_tab {
+            return;
+        }
+        let Some(incoming) = self.tabs[index].state.take() else {
+            return;
+        };
+        let outgoing = self.swap_tab_state(incoming);
+        self.tabs[self.active_tab].state = Some(outgoing);
+        self.active_tab = index;
+        // Redaction may have been switched while the tab was in the background
+        self.tree_view.set_redaction(self.active_redaction());
+        // The path bar belongs to the other tab's selection
+        self.last_selected_id = None;
+        self.stats_popup = None;
+    }
+
+    /// Put `state` into the app's fields and return what they held
+    fn swap_tab_state(&mut self, mut state: TabState) -> TabState {
+        mem::swap(&mut self.tree, &mut state.tree);
+        mem::swap(&mut self.tree_view, &mut state.tree_view);
+        mem::swap(&mut self.search_query, &mut state.search_query);
+        mem::swap(&mut self.search_matches, &mut state.search_matches);
+        mem::swap(
+            &mut self.current_match_index,
+            &mut state.current_match_index,
+        );
+        mem::swap(&mut self.search_origin, &mut state.search_origin);
+        mem::swap(&mut self.search_task, &mut state.search_task);
+        mem::swap(&mut self.search_due, &mut state.search_due);
+        mem::swap(&mut self.search_jump, &mut state.search_jump);
+        mem::swap(&mut self.search_xpath, &mut state.search_xpath);
+        mem::swap(&mut self.marks, &mut state.marks);
+        mem::swap(&mut self.pins, &mut state.pins);
+        self.pins_selected = None;
+        mem::swap(&mut self.jump_history, &mut state.jump_history);
+        mem::swap(&mut self.file_changed, &mut state.file_changed);
+        mem::swap(&mut self.reload_error, &mut state.reload_error);
+        mem::swap(&mut self.differences, &mut state.differences);
+        mem::swap(&mut self.source, &mut state.source);
+        mem::swap(&mut self.edits, &mut state.edits);
+        self.edit_prompt = None;
+        state
+    }
+
+    /// Number of tabs with unsaved changes
+    pub(super) fn unsaved_tabs(&self) -> usize {
+        let others = self
+            .tabs
+            .iter()
+            .filter_map(|tab| tab.state.as_ref())
+            .filter(|state| state.edits.is_dirty())
+            .count();
+        others + usize::from(self.edits.is_dirty())
+    }
+
+    /// Close the active tab, or quit if it is the last one, asking first if
+    /// it has unsaved changes
+    pub(super) fn close_tab(&mut self) {
+        if !self.confirm_discard('q') {
+            return;
+        }
+        if !self.has_tabs() {
+            self.should_quit = true;
+            return;
+        }
+        self.save_view_state();
+        let closing = self.active_tab;
+        let next = if closing + 1 < self.tabs.len() {
+            closing + 1
+        } else {
+            closing - 1
+        };
+        self.switch_tab(next);
+        // Dropping the state cancels the tab's running search
+        let tab = self.tabs.remove(closing);
+        if next > closing {
+            self.active_tab -= 1;
+        }
+        self.notify(format!("Closed {}", tab.name));
+    }
+
+    /// The tab bar: one line with the name of every tab
+    pub(super) fn render_tab_bar(&self, frame: &mut Frame, area: Rect) {
+        let inactive = Style::default().fg(self.theme.path_bar);
+        let active = self.theme.selection();
+
+        let mut spans = Vec::new();
+        for (index, tab) in self.tabs.iter().enumerate() {
+            if index > 0 {
+                spans.push(Span::styled(TAB_SEPARATOR, inactive));
+            }
+            let style = if index == self.active_tab {
+                active
+            } else {
+                inactive
+            };
+            spans.push(Span::styled(format!(" {} {} ", index + 1, tab.name), style));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser, yaml::YamlParser};
+    use crate::ui::app::test_support::{app_from, press, selected_label};
+
+    fn app() -> App {
+        let yaml = YamlParser.parse("x: 1\ny: 2\nz: 3\n").unwrap();
+        app_from(&JsonParser, r#"{"a": 1, "b": 2}"#)
+            .with_file("a.json", None)
+            .with_tab(TreeVariant::from(yaml))
+            .with_file("b.yaml", None)
+    }
+
+    #[test]
+    fn test_tabs_keep_their_own_state() {
+        let mut app = app();
+        press(&mut app, "jj");
+        assert_eq!(selected_label(&app), "b");
+
+        press(&mut app, "gt");
+        assert_eq!(app.active_tab, 1);
+        press(&mut app, "G");
+        assert_eq!(selected_label(&app), "z");
+
+        press(&mut app, "gT");
+        assert_eq!(selected_label(&app), "b");
+        // `gg` still goes to the first line
+        press(&mut app, "gg");
+        assert_eq!(selected_label(&app), "root");
+        // Going back from the first tab wraps around to the last
+        press(&mut app, "gT");
+        assert_eq!(selected_label(&app), "z");
+    }
+
+    #[test]
+    fn test_closing_the_last_tab_quits() {
+        let mut app = app();
+        press(&mut app, "jgtq");
+        assert!(!app.should_quit);
+        assert_eq!(app.tabs.len(), 1);
+        assert_eq!(app.active_tab, 0);
+        assert_eq!(selected_label(&app), "a");
+        assert_eq!(app.tree.get_children(app.tree.root_id()).len(), 2);
+
+        press(&mut app, "q");
+        assert!(app.should_quit);
+    }
+}
@@ -0,0 +1,152 @@
+//! Following LDIF URL references: attributes written as `key:< file:///…`
+//! keep their value in another file. `pf` shows that file's size and first
+//! bytes, `yf` copies its path.
+//!
+//! Only `file:` URLs are followed. Relative paths are resolved against the
+//! directory of the viewed file.
+
+use super::App;
+use crate::parser::ldif::file_url_path;
+use crate::ui::tree_view::{format_size, format_thousands};
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Bytes of the referenced file shown by `pf`
+const PREVIEW_BYTES: u64 = 512;
+
+impl App {
+    /// The path of the file the selected URL reference points to, as text
+    pub(super) fn get_referenced_path(&mut self) -> Option<String> {
+        self.referenced_file()
+            .map(|path| path.display().to_string())
+    }
+
+    /// Path, size and first bytes of the file the selected URL reference
+    /// points to: as text if they are UTF-8, otherwise as a hexdump
+    pub(super) fn get_referenced_file(&mut self) -> Option<String> {
+        let path = self.referenced_file()?;
+        let size = std::fs::metadata(&path).map(|meta| meta.len());
+        let mut bytes = Vec::new();
+        let read = File::open(&path)
+            .and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut bytes))
+            .and(size);
+        let size = match read {
+            Ok(size) => size,
+            Err(e) => {
+                self.notify_error(format!("Cannot read {}: {}", path.display(), e));
+                return None;
+            }
+        };
+
+        let mut text = format!(
+            "{}\nSize: {} ({} bytes)\n",
+            path.display(),
+            format_size(size as usize),
+            format_thousands(size as usize)
+        );
+        if bytes.is_empty() {
+            return Some(text);
+        }
+        let shown = if (bytes.len() as u64) < size {
+            format!("First {} bytes", bytes.len())
+        } else {
+            "Contents".to_string()
+        };
+        text.push_str(&format!("\n{}:\n", shown));
+        match std::str::from_utf8(&bytes) {
+            Ok(preview) => text.push_str(preview),
+            // The preview may end within a character
+            Err(e) if e.error_len().is_none() => {
+                text.push_str(&String::from_utf8_lossy(&bytes[..e.valid_up_to()]));
+            }
+            Err(_) => text.push_str(&self.format_hexdump(&bytes)),
+        }
+        Some(text)
+    }
+
+    /// The file the selected URL reference points to, if it exists, or
+    /// `None` after telling why not
+    fn referenced_file(&mut self) -> Option<PathBuf> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        let node = self.tree.get_node_blocking(node_id)?;
+        if !node.is_url_ref() {
+            self.notify_warning("The selected node isn't a URL reference");
+            return None;
+        }
+        let url = node
+            .attributes
+            .iter()
+            .find(|a| a.key == "value")
+            .map_or("", |a| a.value.as_str());
+        let Some(mut path) = file_url_path(url) else {
+            self.notify_warning(format!(
+                "Only local file: URLs can be followed, not {}",
+                url
+            ));
+            return None;
+        };
+        if path.is_relative()
+            && let Some(dir) = self.source.as_ref().and_then(|s| s.path.parent())
+        {
+            path = dir.join(path);
+        }
+        if !path.is_file() {
+            self.notify_error(format!("No such file: {}", path.display()));
+            return None;
+        }
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::ldif::LdifParser;
+    use crate::ui::app::test_support::app_with_config;
+
+    fn select(app: &mut App, label: &str) {
+        let id = (0..app.tree.node_count())
+            .find(|&id| app.tree.get_label(id).as_deref() == Some(label))
+            .unwrap();
+        app.tree_view.navigate_to_node(&app.tree, id);
+    }
+
+    #[test]
+    fn test_follows_file_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("note.txt");
+        let binary = dir.path().join("photo.jpg");
+        std::fs::write(&text, "hello").unwrap();
+        std::fs::write(&binary, [0xff, 0xd8, 0x00]).unwrap();
+        let ldif = format!(
+            "dn: cn=Test\nnote:< file://{}\njpegPhoto:< file://{}\n\
+             missing:< file://{}/gone\nsite:< http://example.com/\n",
+            text.display(),
+            binary.display(),
+            dir.path().display()
+        );
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 3;
+        let mut app = app_with_config(&LdifParser::default(), &ldif, config);
+
+        select(&mut app, "note");
+        assert_eq!(app.get_referenced_path(), Some(text.display().to_string()));
+        let shown = app.get_referenced_file().unwrap();
+        assert!(shown.contains("Size: 5 B (5 bytes)"), "{}", shown);
+        assert!(shown.ends_with("Contents:\nhello"), "{}", shown);
+
+        select(&mut app, "jpegPhoto");
+        let shown = app.get_referenced_file().unwrap();
+        assert!(shown.contains("ff d8 00"), "{}", shown);
+
+        // Missing files and other URLs are reported, not followed
+        for label in ["missing", "site", "dn"] {
+            app.notification = None;
+            select(&mut app, label);
+            assert_eq!(app.get_referenced_file(), None);
+            assert!(app.notification.is_some(), "{}", label);
+        }
+    }
+}
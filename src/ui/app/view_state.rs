@@ -0,0 +1,219 @@
+//! Expanded nodes, selection and search of each file, kept across sessions.
+//!
+//! With `ui.persist_state` the view of a tab read from a file is saved when
+//! the tab is closed or the viewer quits, to a JSON file in
+//! `~/.local/state/xtv/views` named after a hash of the file's path. Opening
+//! the file again restores it unless `--no-restore` is given.
+//!
+//! Node IDs differ between runs, so nodes are saved by their label paths as
+//! when reloading, and paths the file no longer has are skipped. A state file
+//! that can't be read or parsed is ignored.
+
+use super::App;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Most expanded nodes saved per file, so that a fully expanded large file
+/// doesn't write a huge state file
+const MAX_SAVED_EXPANDED: usize = 10_000;
+
+/// The view of one file as saved between sessions
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ViewState {
+    /// The file, to tell apart paths with the same hash
+    file: PathBuf,
+    /// Label paths of the expanded nodes
+    expanded: Vec<Vec<String>>,
+    /// Label path of the selected node
+    selected: Vec<String>,
+    search_query: String,
+}
+
+impl ViewState {
+    /// Read the state saved for `file`, or `None` if there is none usable
+    fn read(path: &Path, file: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let state: Self = serde_json::from_str(&content).ok()?;
+        (state.file == file).then_some(state)
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+}
+
+/// The file of the state saved for `file` in `dir`
+fn state_path(dir: &Path, file: &Path) -> PathBuf {
+    dir.join(format!(
+        "{:016x}.json",
+        fnv1a(file.as_os_str().as_encoded_bytes())
+    ))
+}
+
+/// 64-bit FNV-1a hash. Unlike `DefaultHasher` it is the same in every
+/// build, so files keep their state across upgrades.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl App {
+    /// Save the view of every tab read from a file in `dir` when it closes,
+    /// and with `restore` restore the views saved there earlier
+    pub fn with_view_states(mut self, dir: Option<PathBuf>, restore: bool) -> Self {
+        self.view_state_dir = dir;
+        if restore && self.view_state_dir.is_some() {
+            let active = self.active_tab;
+            for index in 0..self.tabs.len() {
+                self.switch_tab(index);
+                self.restore_view_state();
+            }
+            self.switch_tab(active);
+        }
+        self
+    }
+
+    /// The viewed file by its absolute path, and its state file
+    fn view_state_paths(&self) -> Option<(PathBuf, PathBuf)> {
+        let dir = self.view_state_dir.as_ref()?;
+        let path = &self.source.as_ref()?.path;
+        let file = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        let state = state_path(dir, &file);
+        Some((file, state))
+    }
+
+    /// Apply the state saved for the active tab's file, if any
+    fn restore_view_state(&mut self) {
+        let Some((file, path)) = self.view_state_paths() else {
+            return;
+        };
+        let Some(state) = ViewState::read(&path, &file) else {
+            return;
+        };
+        self.restore_expansion(&state.expanded);
+        self.restore_selection(&state.selected);
+        if !state.search_query.is_empty() {
+            self.search_query = state.search_query;
+            self.search_origin = self.tree_view.get_selected_node_id();
+            self.find_matches(self.search_scope());
+        }
+    }
+
+    /// Save the active tab's view, if it was read from a file
+    pub(super) fn save_view_state(&mut self) {
+        let Some((file, path)) = self.view_state_paths() else {
+            return;
+        };
+        let mut expanded = self.tree_view.expanded_nodes();
+        expanded.sort_unstable();
+        expanded.truncate(MAX_SAVED_EXPANDED);
+        let state = ViewState {
+            file,
+            expanded: expanded
+                .into_iter()
+                .filter(|&node_id| node_id != self.tree.root_id())
+                .map(|node_id| self.label_path(node_id))
+                .collect(),
+            selected: self.selection_label_path(),
+            // XPath expressions are not restored as text searches
+            search_query: if self.search_xpath {
+                String::new()
+            } else {
+                self.search_query.clone()
+            },
+        };
+        // The viewer is closing, so there is no footer to report this in
+        if let Err(e) = state.write(&path) {
+            log::warn!("Cannot save the view state to {:?}: {}", path, e);
+        }
+    }
+
+    /// Save the view of every tab
+    pub(super) fn save_view_states(&mut self) {
+        let active = self.active_tab;
+        for index in 0..self.tabs.len() {
+            self.switch_tab(index);
+            self.save_view_state();
+        }
+        self.switch_tab(active);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::app_with_config;
+
+    const DOCUMENT: &str = r#"{"a": {"b": {"c": 1}}, "d": {"e": 2}, "f": [3]}"#;
+
+    fn open(dir: &Path, file: &Path, restore: bool) -> App {
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 1;
+        app_with_config(&JsonParser, &fs::read_to_string(file).unwrap(), config)
+            .with_file(file, None)
+            .with_view_states(Some(dir.to_path_buf()), restore)
+    }
+
+    #[test]
+    fn test_view_is_restored_by_label_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data.json");
+        fs::write(&file, DOCUMENT).unwrap();
+
+        let mut app = open(dir.path(), &file, true);
+        let c = app.tree.find_by_path(&["a", "b", "c"]).unwrap();
+        app.reveal_node(c);
+        app.search_query = "e".to_string();
+        app.save_view_states();
+
+        // Node IDs shift when the file changes, labels don't
+        fs::write(&file, format!(r#"{{"new": 0, {}"#, &DOCUMENT[1..])).unwrap();
+        let mut app = open(dir.path(), &file, true);
+        let selected = app.tree_view.get_selected_node_id().unwrap();
+        assert_eq!(app.label_path(selected), ["a", "b", "c"]);
+        assert_eq!(app.search_query, "e");
+        app.wait_for_search();
+        assert!(!app.search_matches.is_empty());
+        let b = app.tree.find_by_path(&["a", "b"]).unwrap();
+        assert!(app.tree_view.expanded_nodes().contains(&b));
+
+        let app = open(dir.path(), &file, false);
+        assert!(app.selection_label_path().is_empty());
+        assert!(app.search_query.is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_and_foreign_state_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("data.json");
+        fs::write(&file, DOCUMENT).unwrap();
+        let path = state_path(dir.path(), &fs::canonicalize(&file).unwrap());
+
+        fs::write(&path, "{not json").unwrap();
+        let app = open(dir.path(), &file, true);
+        assert!(app.selection_label_path().is_empty());
+
+        let other = ViewState {
+            file: PathBuf::from("/elsewhere.json"),
+            selected: vec!["d".to_string()],
+            ..ViewState::default()
+        };
+        other.write(&path).unwrap();
+        let app = open(dir.path(), &file, true);
+        assert!(app.selection_label_path().is_empty());
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
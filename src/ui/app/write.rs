@@ -0,0 +1,331 @@
+//! Writing the selected node to a file: `w` asks for a file name in the
+//! footer, for values too large for the clipboard or sessions without one.
+//!
+//! Leaves (attribute values, text and comments) are written as their raw
+//! value, anything else as the pretty JSON that `yy` copies. Tab switches to
+//! writing the rows the tree shows as text instead, as `yt` copies them, and
+//! again to writing the view as an HTML page (see
+//! [`render_html`](crate::ui::render_html)) as `--export-html` does. An
+//! existing file is only replaced after pressing Enter a second time.
+
+use super::App;
+use crate::error::Result;
+use crate::tree::TreeNode;
+use crate::ui::render_html::{self, HtmlPage, MAX_HTML_DEPTH, MAX_HTML_ROWS};
+use crate::ui::tree_view::format_thousands;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::path::PathBuf;
+
+/// What the prompt writes, switched with Tab
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum WriteTarget {
+    #[default]
+    SelectedNode,
+    VisibleTree,
+    Html,
+}
+
+impl WriteTarget {
+    fn next(self) -> Self {
+        match self {
+            WriteTarget::SelectedNode => WriteTarget::VisibleTree,
+            WriteTarget::VisibleTree => WriteTarget::Html,
+            WriteTarget::Html => WriteTarget::SelectedNode,
+        }
+    }
+}
+
+/// The file name prompt and what it's waiting for
+#[derive(Debug, Default)]
+pub(super) struct WritePrompt {
+    path: String,
+    target: WriteTarget,
+    /// Whether the file exists and the next Enter overwrites it
+    confirm_overwrite: bool,
+    /// Why the last write failed
+    error: Option<String>,
+}
+
+impl WritePrompt {
+    /// Whether the prompt shows an error rather than a question
+    pub(super) fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+impl App {
+    /// Open the file name prompt for the selected node
+    pub(super) fn start_write(&mut self) {
+        self.write_prompt = Some(WritePrompt::default());
+    }
+
+    /// Handle a key while typing the file name
+    pub(super) fn handle_write_input_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(prompt) = &mut self.write_prompt else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.write_prompt = None,
+            KeyCode::Enter => self.write_selected(),
+            KeyCode::Tab => prompt.target = prompt.target.next(),
+            KeyCode::Backspace => {
+                prompt.path.pop();
+                prompt.confirm_overwrite = false;
+                prompt.error = None;
+            }
+            KeyCode::Char(c) => {
+                prompt.path.push(c);
+                prompt.confirm_overwrite = false;
+                prompt.error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Write the selected node to the file in the prompt, asking first if
+    /// the file exists and keeping the prompt open if writing fails
+    fn write_selected(&mut self) {
+        let Some(prompt) = &self.write_prompt else {
+            return;
+        };
+        if prompt.path.trim().is_empty() {
+            return;
+        }
+        let path = expand_tilde(prompt.path.trim());
+        if path.exists() && !prompt.confirm_overwrite {
+            if let Some(prompt) = &mut self.write_prompt {
+                prompt.confirm_overwrite = true;
+            }
+            return;
+        }
+
+        let content = match prompt.target {
+            WriteTarget::SelectedNode => self.selected_file_content(),
+            WriteTarget::VisibleTree => self.get_visible_tree_text(),
+            WriteTarget::Html => {
+                let (html, truncated) = self.view_as_html();
+                if truncated {
+                    self.notify_warning(render_html::truncation_warning());
+                }
+                Some(html)
+            }
+        };
+        let Some(content) = content else {
+            self.write_prompt = None;
+            self.notify_warning("Nothing to write for the selected node");
+            return;
+        };
+        match std::fs::write(&path, &content) {
+            Ok(()) => {
+                self.write_prompt = None;
+                // The truncation warning says more
+                if self.notification.is_some() {
+                    return;
+                }
+                self.notify(format!(
+                    "Wrote {} bytes to {}",
+                    format_thousands(content.len()),
+                    path.display()
+                ));
+            }
+            Err(e) => {
+                if let Some(prompt) = &mut self.write_prompt {
+                    prompt.confirm_overwrite = false;
+                    prompt.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// The raw value of a selected leaf, or the selected subtree as pretty JSON
+    fn selected_file_content(&mut self) -> Option<String> {
+        let node_id = self.tree_view.get_selected_node_id()?;
+        let node = self.tree.get_node_blocking(node_id)?;
+        if is_leaf(&node) {
+            return self.get_node_string_value();
+        }
+        let mut json = self.get_node_value_pretty()?;
+        json.push('\n');
+        Some(json)
+    }
+
+    /// The whole tree as an HTML page, open where the view is expanded and
+    /// with the path to the selection highlighted, and whether rows were left
+    /// out to keep to [`MAX_HTML_ROWS`] rows and [`MAX_HTML_DEPTH`] levels
+    pub fn view_as_html(&self) -> (String, bool) {
+        let (rows, truncated) = self.tree_view.html_rows(
+            &self.tree,
+            self.tree.root_id(),
+            &self.theme,
+            MAX_HTML_ROWS,
+            MAX_HTML_DEPTH,
+        );
+        let mut path = Vec::new();
+        let mut current = self.tree_view.get_selected_node_id();
+        while let Some(node_id) = current {
+            path.push(node_id);
+            current = self.tree.get_parent(node_id);
+        }
+        path.reverse();
+        let title = match &self.source {
+            Some(source) => source
+                .path
+                .file_name()
+                .unwrap_or(source.path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+            None => "stdin".to_string(),
+        };
+        let page = HtmlPage {
+            title: &title,
+            breadcrumb: &self.get_breadcrumb().unwrap_or_default(),
+            path: &path,
+            truncated,
+        };
+        (
+            render_html::rows_to_html(&rows, &page, &self.theme),
+            truncated,
+        )
+    }
+
+    /// Text of the file name prompt in the footer
+    pub(super) fn write_prompt_text(&self) -> String {
+        let Some(prompt) = &self.write_prompt else {
+            return String::new();
+        };
+        let what = match prompt.target {
+            WriteTarget::SelectedNode => "Write to",
+            WriteTarget::VisibleTree => "Write visible tree to",
+            WriteTarget::Html => "Write view as HTML to",
+        };
+        match &prompt.error {
+            Some(error) => format!("{}: {}  ({})", what, prompt.path, error),
+            None if prompt.confirm_overwrite => format!(
+                "{}: {}  (file exists, Enter: Overwrite, Esc: Cancel)",
+                what, prompt.path
+            ),
+            None => format!("{}: {}", what, prompt.path),
+        }
+    }
+}
+
+/// Whether a node is written as its raw value
+fn is_leaf(node: &TreeNode) -> bool {
+    node.is_attribute() || matches!(node.node_type.as_str(), "text" | "comment" | "doctype")
+}
+
+/// Replace a leading `~` with the home directory
+fn expand_tilde(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME");
+    match (path.strip_prefix('~'), home) {
+        (Some(""), Some(home)) => PathBuf::from(home),
+        (Some(rest), Some(home)) if rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser::json::JsonParser;
+    use crate::ui::app::test_support::{app_from, app_with_config, press};
+
+    #[test]
+    fn test_writes_subtrees_and_raw_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_from(&JsonParser, r#"{"a": {"b": [1, 2]}, "c": "line\nbreak"}"#);
+
+        let subtree = dir.path().join("a.json");
+        press(&mut app, &format!("jw{}\n", subtree.display()));
+        assert!(app.write_prompt.is_none());
+        let written = std::fs::read_to_string(&subtree).unwrap();
+        assert_eq!(written, "{\n  \"b\": [\n    1,\n    2\n  ]\n}\n");
+
+        press(&mut app, "G");
+        let value = dir.path().join("c.txt");
+        press(&mut app, &format!("w{}\n", value.display()));
+        assert_eq!(std::fs::read_to_string(&value).unwrap(), "line\nbreak");
+    }
+
+    #[test]
+    fn test_tab_writes_the_visible_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 2;
+        config.ui.copy_ascii_tree = true;
+        let mut app = app_with_config(&JsonParser, r#"{"a": {"b": [1, 2]}, "c": "x"}"#, config);
+
+        let path = dir.path().join("tree.txt");
+        press(&mut app, "w\t");
+        assert!(
+            app.write_prompt_text()
+                .starts_with("Write visible tree to: ")
+        );
+        press(&mut app, &format!("{}\n", path.display()));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "root [root]\n├── a [object]\n│   └── b (2) [array] [2 items: 1, 2]\n└── c: x\n"
+        );
+    }
+
+    #[test]
+    fn test_tab_twice_writes_the_view_as_html() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.ui.default_expanded_depth = 2;
+        let mut app = app_with_config(&JsonParser, r#"{"a": {"b": [1, 2]}, "c": "<x>"}"#, config);
+
+        let path = dir.path().join("view.html");
+        press(&mut app, "jjw\t\t");
+        assert!(
+            app.write_prompt_text()
+                .starts_with("Write view as HTML to: ")
+        );
+        press(&mut app, &format!("{}\n", path.display()));
+        let html = std::fs::read_to_string(&path).unwrap();
+        // `b` is collapsed in the view but its elements are on the page
+        assert!(html.contains("<details><summary class=\"selected\" id=\"selected\"><span"));
+        assert!(html.contains("<div class=\"leaf\"><span style=\"color: #00cdcd;\">[1]</span>"));
+        assert!(html.contains("&lt;x&gt;"));
+        assert!(html.contains("<p class=\"breadcrumb\">root &gt; a &gt; b</p>"));
+    }
+
+    #[test]
+    fn test_existing_files_are_replaced_after_confirming() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        std::fs::write(&path, "old").unwrap();
+        let mut app = app_from(&JsonParser, r#"{"a": "new"}"#);
+
+        press(&mut app, &format!("jw{}\n", path.display()));
+        assert!(app.write_prompt_text().contains("file exists"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        press(&mut app, "\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+
+        // Failures keep the prompt open
+        let missing = dir.path().join("missing").join("out.txt");
+        press(&mut app, &format!("w{}\n", missing.display()));
+        assert!(app.write_prompt.as_ref().unwrap().has_error());
+        press(&mut app, "\x1b");
+        assert!(app.write_prompt.is_none());
+    }
+
+    #[test]
+    fn test_tilde_expansion() {
+        let Some(home) = std::env::var_os("HOME") else {
+            return;
+        };
+        assert_eq!(expand_tilde("~"), PathBuf::from(&home));
+        assert_eq!(
+            expand_tilde("~/x.json"),
+            PathBuf::from(&home).join("x.json")
+        );
+        assert_eq!(expand_tilde("~x/y"), PathBuf::from("~x/y"));
+        assert_eq!(expand_tilde("/tmp/x"), PathBuf::from("/tmp/x"));
+    }
+}
@@ -0,0 +1,151 @@
+//! XPath queries: `x` opens a prompt whose expression selects nodes of an XML
+//! or HTML tree (see [`crate::query::xpath`]). In trees of other formats a
+//! JSON Pointer such as `/users/3/email` selects the node it points to.
+//!
+//! The selected nodes become the search matches, so `n`/`N`, highlighting and
+//! the `F` filter work on them just like on the results of `/`.
+
+use super::App;
+use crate::error::Result;
+use crate::query::path;
+use crate::query::xpath::XPath;
+use crate::tree::TreeVariant;
+use crossterm::event::{KeyCode, KeyEvent};
+
+impl App {
+    /// Open the XPath prompt, prefilled with the last query
+    pub(super) fn start_xpath(&mut self) {
+        if !matches!(self.tree, TreeVariant::InMemory(_)) {
+            self.notify_warning("XPath queries need a file loaded into memory (XML or HTML)");
+            return;
+        }
+        let query = if self.search_xpath {
+            std::mem::take(&mut self.search_query)
+        } else {
+            String::new()
+        };
+        self.clear_search();
+        self.search_query = query;
+        self.search_xpath = true;
+        self.xpath_mode = true;
+        self.xpath_error = None;
+    }
+
+    /// Handle a key while typing an XPath query
+    pub(super) fn handle_xpath_input_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.xpath_mode = false;
+                self.clear_search();
+            }
+            KeyCode::Enter => self.run_xpath(),
+            KeyCode::Backspace => {
+                self.search_query.pop();
+                self.xpath_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.search_query.push(c);
+                self.xpath_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Evaluate the query and jump to the first selected node, or keep the
+    /// prompt open to show why the query is invalid
+    fn run_xpath(&mut self) {
+        let TreeVariant::InMemory(tree) = &self.tree else {
+            return;
+        };
+        let pointer = (!path::uses_xpath(&self.tree))
+            .then(|| self.tree.find_by_json_pointer(&self.search_query))
+            .flatten();
+        let nodes = match XPath::parse(&self.search_query) {
+            _ if pointer.is_some() => pointer.into_iter().collect(),
+            Ok(xpath) => xpath.evaluate(tree),
+            Err(e) => {
+                self.xpath_error = Some(e.to_string());
+                return;
+            }
+        };
+
+        self.xpath_mode = false;
+        for node_id in nodes {
+            // Attributes shown inline select their element's row
+            let row = self.tree_view.row_node(&self.tree, node_id);
+            if !self.search_matches.contains(&row) {
+                self.search_matches.push(row);
+            }
+        }
+        if self.search_matches.is_empty() {
+            self.notify("No nodes match the XPath query");
+            return;
+        }
+        self.current_match_index = Some(0);
+        self.apply_filter();
+        self.jump_to_current_match();
+    }
+
+    /// Text of the XPath prompt in the footer
+    pub(super) fn xpath_prompt(&self) -> String {
+        match &self.xpath_error {
+            Some(error) => format!("XPath: {}  ({})", self.search_query, error),
+            None => format!("XPath: {}", self.search_query),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{json::JsonParser, xml::XmlParser};
+    use crate::ui::app::test_support::{app_from, press};
+
+    fn app() -> App {
+        let xml = r#"<shop><item id="a"><name>Dune</name></item><item id="b"><name>Emma</name></item></shop>"#;
+        app_from(&XmlParser, xml)
+    }
+
+    #[test]
+    fn test_results_become_search_matches() {
+        let mut app = app();
+        press(&mut app, "x//item/name\n");
+        assert!(!app.xpath_mode);
+        assert_eq!(app.search_matches.len(), 2);
+        assert_eq!(app.search_label(), "XPath");
+        let first = app.search_matches[0];
+        assert_eq!(app.tree_view.get_selected_node_id(), Some(first));
+
+        press(&mut app, "n");
+        assert_eq!(app.current_match_index, Some(1));
+
+        // Reopening edits the last query
+        press(&mut app, "x");
+        assert_eq!(app.search_query, "//item/name");
+        press(&mut app, "\x1b");
+        assert!(app.search_matches.is_empty());
+        assert!(!app.search_xpath);
+    }
+
+    #[test]
+    fn test_json_pointers_select_their_node() {
+        let mut app = app_from(&JsonParser, r#"{"users": [{"id": 1}, {"id": 2}]}"#);
+        press(&mut app, "x/users/1/id\n");
+        let selected = app.tree_view.get_selected_node_id().unwrap();
+        assert_eq!(app.search_matches, [selected]);
+        assert_eq!(path::json_pointer(&app.tree, selected), "/users/1/id");
+    }
+
+    #[test]
+    fn test_invalid_query_keeps_the_prompt_open() {
+        let mut app = app();
+        press(&mut app, "x//item[\n");
+        assert!(app.xpath_mode);
+        assert!(app.xpath_prompt().contains("expected"));
+
+        press(&mut app, "1]\n");
+        assert!(!app.xpath_mode);
+        assert_eq!(app.search_matches.len(), 1);
+    }
+}
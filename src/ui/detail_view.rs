@@ -1,8 +1,9 @@
 use crate::tree::TreeNode;
+use crate::ui::theme::Theme;
 use ratatui::{
     Frame,
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem},
 };
@@ -20,15 +21,15 @@ impl DetailView {
         Self
     }
 
-    pub fn render(&self, frame: &mut Frame, area: Rect, node: Option<&TreeNode>) {
+    pub fn render(&self, frame: &mut Frame, area: Rect, node: Option<&TreeNode>, theme: &Theme) {
         if let Some(node) = node {
-            self.render_node_details(frame, area, node);
+            self.render_node_details(frame, area, node, theme);
         } else {
-            self.render_empty(frame, area);
+            self.render_empty(frame, area, theme);
         }
     }
 
-    fn render_node_details(&self, frame: &mut Frame, area: Rect, node: &TreeNode) {
+    fn render_node_details(&self, frame: &mut Frame, area: Rect, node: &TreeNode, theme: &Theme) {
         let mut items = Vec::new();
 
         // Node label
@@ -36,10 +37,10 @@ impl DetailView {
             Span::styled(
                 "Label: ",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.heading)
                     .add_modifier(Modifier::BOLD),
             ),
-            Span::styled(&node.label, Style::default().fg(Color::Cyan)),
+            Span::styled(&node.label, Style::default().fg(theme.label)),
         ])));
 
         // Node type
@@ -47,10 +48,13 @@ impl DetailView {
             Span::styled(
                 "Type: ",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.heading)
                     .add_modifier(Modifier::BOLD),
             ),
-            Span::styled(&node.node_type, Style::default().fg(Color::Magenta)),
+            Span::styled(
+                node.node_type.as_str(),
+                Style::default().fg(theme.node_type),
+            ),
         ])));
 
         // Children count
@@ -58,27 +62,43 @@ impl DetailView {
             Span::styled(
                 "Children: ",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.heading)
                     .add_modifier(Modifier::BOLD),
             ),
             Span::styled(
                 format!("{}", node.children.len()),
-                Style::default().fg(Color::Green),
+                Style::default().fg(theme.value),
             ),
         ])));
 
+        // Position in the parsed file
+        if let Some((line, column)) = node.source_span {
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(
+                    "Source: ",
+                    Style::default()
+                        .fg(theme.heading)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("line {}, column {}", line, column),
+                    Style::default().fg(theme.value),
+                ),
+            ])));
+        }
+
         // Separator
         items.push(ListItem::new(Line::from("")));
 
         // Special handling for text nodes, comment nodes, and attribute nodes
-        if node.node_type == "text" || node.node_type == "comment" {
+        if matches!(node.node_type.as_str(), "text" | "comment" | "doctype") {
             // For text and comment nodes, display content directly without header or indentation
             if let Some(content_attr) = node.attributes.iter().find(|a| a.key == "content") {
                 let value_lines = self.wrap_text(&content_attr.value, area.width as usize);
                 for line in value_lines.iter() {
                     items.push(ListItem::new(Line::from(Span::styled(
                         line.clone(),
-                        Style::default().fg(Color::Green),
+                        Style::default().fg(theme.value),
                     ))));
                 }
             }
@@ -89,7 +109,20 @@ impl DetailView {
                 for line in value_lines.iter() {
                     items.push(ListItem::new(Line::from(Span::styled(
                         line.clone(),
-                        Style::default().fg(Color::Green),
+                        Style::default().fg(theme.value),
+                    ))));
+                }
+            }
+            if let Some(decoded) = node.attributes.iter().find(|a| a.key == "decoded") {
+                items.push(ListItem::new(Line::from("")));
+                items.push(ListItem::new(Line::from(Span::styled(
+                    "Decoded:",
+                    Style::default().fg(theme.key).add_modifier(Modifier::BOLD),
+                ))));
+                for line in self.wrap_text(&decoded.value, area.width as usize) {
+                    items.push(ListItem::new(Line::from(Span::styled(
+                        line,
+                        Style::default().fg(theme.value),
                     ))));
                 }
             }
@@ -98,7 +131,7 @@ impl DetailView {
             items.push(ListItem::new(Line::from(Span::styled(
                 "Attributes:",
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.heading)
                     .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
             ))));
 
@@ -109,9 +142,7 @@ impl DetailView {
                     Span::raw("    "),
                     Span::styled(
                         format!("{}:", attr.key),
-                        Style::default()
-                            .fg(Color::Blue)
-                            .add_modifier(Modifier::BOLD),
+                        Style::default().fg(theme.key).add_modifier(Modifier::BOLD),
                     ),
                 ])));
 
@@ -121,7 +152,7 @@ impl DetailView {
                 for line in value_lines.iter() {
                     items.push(ListItem::new(Line::from(vec![
                         Span::raw("        "),
-                        Span::styled(line.clone(), Style::default().fg(Color::Green)),
+                        Span::styled(line.clone(), Style::default().fg(theme.value)),
                     ])));
                 }
             }
@@ -129,7 +160,7 @@ impl DetailView {
             items.push(ListItem::new(Line::from(Span::styled(
                 "(No attributes)",
                 Style::default()
-                    .fg(Color::DarkGray)
+                    .fg(theme.node_type)
                     .add_modifier(Modifier::ITALIC),
             ))));
         }
@@ -140,13 +171,13 @@ impl DetailView {
         frame.render_widget(list, area);
     }
 
-    fn render_empty(&self, frame: &mut Frame, area: Rect) {
+    fn render_empty(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
         let items = vec![
             ListItem::new(Line::from("")),
             ListItem::new(Line::from(Span::styled(
                 "No node selected",
                 Style::default()
-                    .fg(Color::DarkGray)
+                    .fg(theme.node_type)
                     .add_modifier(Modifier::ITALIC),
             ))),
         ];
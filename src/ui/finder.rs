@@ -0,0 +1,581 @@
+//! Fuzzy finder over node paths, opened with Ctrl-P.
+//!
+//! Every node is listed by its full path, e.g. `root > users > [42] > email`,
+//! and ranked against the typed query with fzf's scoring: the query must occur
+//! as a subsequence, and matches at word boundaries and in runs score higher
+//! than scattered ones. Streaming trees are listed from their index labels, so
+//! opening the finder never reads the file.
+//!
+//! The same popup also lists the search matches (Ctrl-L), in document order
+//! and without a query: j/k move through them and Enter jumps to one.
+
+use crate::tree::TreeVariant;
+use crate::ui::theme::Theme;
+use crate::ui::tree_view::format_thousands;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+/// Maximum number of nodes listed, so huge trees open quickly
+const MAX_FINDER_NODES: usize = 100_000;
+
+/// Number of ranked candidates kept for display
+const MAX_FINDER_RESULTS: usize = 100;
+
+/// Finder popup width as a fraction of the screen, and its limit
+const FINDER_WIDTH_PERCENT: u16 = 80;
+const FINDER_MAX_WIDTH: u16 = 120;
+
+/// Finder popup height: the input, the results and the borders
+const FINDER_HEIGHT: u16 = 20;
+
+// Scores as in fzf
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+const BONUS_BOUNDARY_WHITE: i32 = 10;
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+const BONUS_NON_WORD: i32 = SCORE_MATCH / 2;
+const BONUS_CAMEL_123: i32 = BONUS_BOUNDARY - 1;
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+/// What the app should do after a key in the finder
+#[derive(Debug, PartialEq, Eq)]
+pub enum FinderAction {
+    None,
+    Close,
+    /// Reveal and select this node
+    Jump(usize),
+}
+
+/// A listed node and its path
+struct Candidate {
+    node_id: usize,
+    path: Vec<char>,
+    /// Shown after the path, e.g. an excerpt of the node's value
+    detail: String,
+}
+
+/// A candidate that matched the query
+struct Ranked {
+    candidate: usize,
+    score: i32,
+    /// Character positions of the matched query characters in the path
+    positions: Vec<usize>,
+}
+
+/// State of the finder popup
+pub struct Finder {
+    title: &'static str,
+    /// Whether typing filters the list, otherwise it is fixed and j/k move
+    filterable: bool,
+    query: String,
+    candidates: Vec<Candidate>,
+    results: Vec<Ranked>,
+    /// Number of candidates matching the query, of which the best are kept
+    matched: usize,
+    selected: usize,
+    /// Whether the tree had more nodes than were listed
+    truncated: bool,
+}
+
+impl Finder {
+    /// List the nodes of `tree` by their paths joined with `separator`
+    pub fn new(tree: &TreeVariant, separator: &str) -> Self {
+        let mut candidates = Vec::new();
+        let mut truncated = false;
+        let mut stack: Vec<(usize, String)> = vec![(tree.root_id(), String::new())];
+        while let Some((node_id, parent_path)) = stack.pop() {
+            if candidates.len() == MAX_FINDER_NODES {
+                truncated = true;
+                break;
+            }
+            let label = tree.get_label(node_id).unwrap_or_default();
+            let path = if parent_path.is_empty() {
+                label
+            } else {
+                format!("{}{}{}", parent_path, separator, label)
+            };
+            // Push in reverse order so children are listed in document order
+            for child in tree.get_children(node_id).into_iter().rev() {
+                stack.push((child, path.clone()));
+            }
+            candidates.push(Candidate {
+                node_id,
+                path: path.chars().collect(),
+                detail: String::new(),
+            });
+        }
+
+        let mut finder = Self {
+            title: "Find node",
+            filterable: true,
+            query: String::new(),
+            candidates,
+            results: Vec::new(),
+            matched: 0,
+            selected: 0,
+            truncated,
+        };
+        finder.rank();
+        finder
+    }
+
+    /// List `entries` of node ID, path and detail as they are, with the
+    /// entry at `selected` selected
+    pub fn list(
+        title: &'static str,
+        entries: Vec<(usize, String, String)>,
+        selected: usize,
+    ) -> Self {
+        let candidates: Vec<Candidate> = entries
+            .into_iter()
+            .map(|(node_id, path, detail)| Candidate {
+                node_id,
+                path: path.chars().collect(),
+                detail,
+            })
+            .collect();
+        let results = (0..candidates.len())
+            .map(|candidate| Ranked {
+                candidate,
+                score: 0,
+                positions: Vec::new(),
+            })
+            .collect();
+        Self {
+            title,
+            filterable: false,
+            query: String::new(),
+            matched: candidates.len(),
+            selected: selected.min(candidates.len().saturating_sub(1)),
+            candidates,
+            results,
+            truncated: false,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FinderAction {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        if !self.filterable {
+            match key.code {
+                KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Char('j') => self.select_next(),
+                KeyCode::Char('g') | KeyCode::Home => self.selected = 0,
+                KeyCode::Char('G') | KeyCode::End => {
+                    self.selected = self.results.len().saturating_sub(1);
+                }
+                KeyCode::Char('q') => return FinderAction::Close,
+                _ => {}
+            }
+        }
+        match key.code {
+            KeyCode::Esc => return FinderAction::Close,
+            KeyCode::Enter => {
+                return match self.results.get(self.selected) {
+                    Some(ranked) => FinderAction::Jump(self.candidates[ranked.candidate].node_id),
+                    None => FinderAction::Close,
+                };
+            }
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p' | 'k') if control => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.select_next(),
+            KeyCode::Char('n' | 'j') if control => self.select_next(),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.rank();
+            }
+            KeyCode::Char(c) if !control && self.filterable => {
+                self.query.push(c);
+                self.rank();
+            }
+            _ => {}
+        }
+        FinderAction::None
+    }
+
+    fn select_next(&mut self) {
+        if self.selected + 1 < self.results.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Score every candidate against the query and keep the best
+    fn rank(&mut self) {
+        self.selected = 0;
+        let query: Vec<char> = self.query.chars().collect();
+        let mut results: Vec<Ranked> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, candidate)| {
+                let (score, positions) = fuzzy_match(&query, &candidate.path)?;
+                Some(Ranked {
+                    candidate: index,
+                    score,
+                    positions,
+                })
+            })
+            .collect();
+        self.matched = results.len();
+
+        // Best score first, then shorter paths, then document order
+        let candidates = &self.candidates;
+        results.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| {
+                    let a_len = candidates[a.candidate].path.len();
+                    a_len.cmp(&candidates[b.candidate].path.len())
+                })
+                .then_with(|| a.candidate.cmp(&b.candidate))
+        });
+        results.truncate(MAX_FINDER_RESULTS);
+        self.results = results;
+    }
+
+    pub fn render(&self, frame: &mut Frame, theme: &Theme) {
+        let area = frame.size();
+        let width = (area.width * FINDER_WIDTH_PERCENT / 100)
+            .min(FINDER_MAX_WIDTH)
+            .min(area.width);
+        let height = FINDER_HEIGHT.min(area.height);
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+        frame.render_widget(Clear, popup_area);
+
+        let more = if self.truncated { "+" } else { "" };
+        let title = format!(
+            " {} ({}/{}{}) ",
+            self.title,
+            format_thousands(self.matched),
+            format_thousands(self.candidates.len()),
+            more
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .style(Style::default().fg(theme.popup_fg).bg(theme.popup_bg));
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+        if inner.height == 0 {
+            return;
+        }
+
+        let list_area = if self.filterable {
+            let input = Paragraph::new(Line::from(vec![
+                Span::styled("> ", Style::default().fg(theme.key)),
+                Span::raw(self.query.as_str()),
+            ]));
+            frame.render_widget(input, Rect { height: 1, ..inner });
+            Rect {
+                y: inner.y + 1,
+                height: inner.height - 1,
+                ..inner
+            }
+        } else {
+            inner
+        };
+        let highlight = theme.search_match(false);
+        // Only the page around the selection, as a list may hold every match
+        let rows = usize::from(list_area.height).max(1);
+        let first = self.selected.saturating_sub(rows - 1);
+        let items: Vec<ListItem> = self
+            .results
+            .iter()
+            .skip(first)
+            .take(rows)
+            .map(|ranked| {
+                let candidate = &self.candidates[ranked.candidate];
+                let width = (list_area.width as usize).saturating_sub(2);
+                // The detail gets what is left after at most two thirds for the path
+                let path_width = if candidate.detail.is_empty() {
+                    width
+                } else {
+                    candidate.path.len().min(width * 2 / 3)
+                };
+                let mut line = path_line(&candidate.path, &ranked.positions, path_width, highlight);
+                if !candidate.detail.is_empty() {
+                    line.spans.push(Span::styled(
+                        format!("  {}", candidate.detail),
+                        Style::default().fg(theme.value),
+                    ));
+                }
+                ListItem::new(line)
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(theme.selection())
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.selected - first));
+        frame.render_stateful_widget(list, list_area, &mut state);
+    }
+}
+
+/// A path cut to its last `width` characters, with matched characters highlighted
+fn path_line(path: &[char], positions: &[usize], width: usize, highlight: Style) -> Line<'static> {
+    let (skip, prefix) = if path.len() > width {
+        (path.len() - width + 1, "…")
+    } else {
+        (0, "")
+    };
+
+    let mut spans = vec![Span::raw(prefix)];
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, &c) in path.iter().enumerate().skip(skip) {
+        let matched = positions.binary_search(&i).is_ok();
+        if matched != run_matched && !run.is_empty() {
+            let style = if run_matched {
+                highlight
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let style = if run_matched {
+            highlight
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(run, style));
+    }
+    Line::from(spans)
+}
+
+/// Character classes used for boundary bonuses
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    White,
+    NonWord,
+    Lower,
+    Upper,
+    Number,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::White
+    } else if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Number
+    } else if c.is_alphabetic() {
+        // Scripts without case count as lowercase letters
+        CharClass::Lower
+    } else {
+        CharClass::NonWord
+    }
+}
+
+/// Bonus for matching a character of class `class` after one of class `prev`
+fn bonus_for(prev: CharClass, class: CharClass) -> i32 {
+    match (prev, class) {
+        (_, CharClass::White) => BONUS_BOUNDARY_WHITE,
+        (_, CharClass::NonWord) => BONUS_NON_WORD,
+        (CharClass::White, _) => BONUS_BOUNDARY_WHITE,
+        (CharClass::NonWord, _) => BONUS_BOUNDARY,
+        (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL_123,
+        (CharClass::Lower | CharClass::Upper, CharClass::Number) => BONUS_CAMEL_123,
+        _ => 0,
+    }
+}
+
+/// Score `text` against `query` with fzf's v1 algorithm.
+///
+/// Finds the first occurrence of the query as a subsequence, then shortens
+/// it from the back to the tightest window ending at the same character, and
+/// scores that window. Matching is case-insensitive unless the query contains
+/// an uppercase letter. An empty query matches everything with score 0.
+///
+/// Returns the score and the matched character positions, or `None` if the
+/// query is not a subsequence of the text.
+fn fuzzy_match(query: &[char], text: &[char]) -> Option<(i32, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let case_sensitive = query.iter().any(|c| c.is_uppercase());
+    let eq = |a: char, b: char| {
+        if case_sensitive {
+            a == b
+        } else {
+            a == b || a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+
+    // Forward: where the first complete occurrence ends
+    let mut query_index = 0;
+    let mut end = None;
+    for (i, &c) in text.iter().enumerate() {
+        if eq(c, query[query_index]) {
+            query_index += 1;
+            if query_index == query.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // Backward: the latest start that still contains the whole query
+    let mut query_index = query.len();
+    let mut start = end;
+    for i in (0..=end).rev() {
+        if eq(text[i], query[query_index - 1]) {
+            query_index -= 1;
+            if query_index == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    // Score the window
+    let mut score = 0;
+    let mut positions = Vec::with_capacity(query.len());
+    let mut query_index = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first_bonus = 0;
+    let mut prev_class = match start {
+        0 => CharClass::White,
+        _ => char_class(text[start - 1]),
+    };
+    for (i, &c) in text.iter().enumerate().take(end + 1).skip(start) {
+        let class = char_class(c);
+        if query_index < query.len() && eq(c, query[query_index]) {
+            score += SCORE_MATCH;
+            let mut bonus = bonus_for(prev_class, class);
+            if consecutive == 0 {
+                first_bonus = bonus;
+            } else {
+                // A run keeps the bonus of the boundary it started at
+                if bonus >= BONUS_BOUNDARY && bonus > first_bonus {
+                    first_bonus = bonus;
+                }
+                bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            score += if query_index == 0 {
+                bonus * BONUS_FIRST_CHAR_MULTIPLIER
+            } else {
+                bonus
+            };
+            positions.push(i);
+            query_index += 1;
+            consecutive += 1;
+            in_gap = false;
+        } else {
+            score += if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            in_gap = true;
+            consecutive = 0;
+            first_bonus = 0;
+        }
+        prev_class = class;
+    }
+    Some((score, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Tree, TreeNode};
+
+    fn score(query: &str, text: &str) -> Option<i32> {
+        let query: Vec<char> = query.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        fuzzy_match(&query, &text).map(|(score, _)| score)
+    }
+
+    fn press(finder: &mut Finder, keys: &str) -> FinderAction {
+        let mut action = FinderAction::None;
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '↓' => KeyCode::Down,
+                c => KeyCode::Char(c),
+            };
+            action = finder.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+        action
+    }
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(score("usr", "users").is_some());
+        assert!(score("rsu", "users").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_positions_use_the_tightest_window() {
+        let query: Vec<char> = "ab".chars().collect();
+        let text: Vec<char> = "a_xab".chars().collect();
+        let (_, positions) = fuzzy_match(&query, &text).unwrap();
+        assert_eq!(positions, [3, 4]);
+    }
+
+    #[test]
+    fn test_boundaries_and_runs_rank_higher() {
+        // Consecutive beats scattered
+        assert!(score("mail", "root > mailbox") > score("mail", "root > maxixl"));
+        // Word starts beat the middle of words
+        assert!(score("em", "root > email") > score("em", "root > item"));
+        // camelCase humps count as boundaries
+        assert!(score("fn", "firstName") > score("fn", "often"));
+    }
+
+    #[test]
+    fn test_smart_case() {
+        assert!(score("email", "EMAIL").is_some());
+        assert!(score("Email", "email").is_none());
+    }
+
+    #[test]
+    fn test_finder_ranks_paths_and_jumps() {
+        // root > users > ([0] > email, [1] > name), emails
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let users = tree.add_child_node(0, TreeNode::new("users", "array"));
+        for (i, key) in ["email", "name"].iter().enumerate() {
+            let item = tree.add_child_node(users, TreeNode::new(format!("[{i}]"), "object"));
+            tree.add_child_node(item, TreeNode::new(*key, "string"));
+        }
+        tree.add_child_node(0, TreeNode::new("emails", "array"));
+        let tree = TreeVariant::from(tree);
+
+        let mut finder = Finder::new(&tree, " > ");
+        assert_eq!(finder.matched, 7);
+
+        press(&mut finder, "usemail");
+        assert_eq!(finder.matched, 1);
+        assert_eq!(press(&mut finder, "\n"), FinderAction::Jump(3));
+
+        // "root > emails" is shorter than "root > users > [0] > email" with an
+        // equal score, so it comes first
+        let mut finder = Finder::new(&tree, " > ");
+        press(&mut finder, "email");
+        assert_eq!(finder.matched, 2);
+        assert_eq!(press(&mut finder, "\n"), FinderAction::Jump(6));
+        let mut finder = Finder::new(&tree, " > ");
+        assert_eq!(press(&mut finder, "email↓\n"), FinderAction::Jump(3));
+    }
+}
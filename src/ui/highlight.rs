@@ -0,0 +1,450 @@
+//! Syntax highlighting for the print popup.
+//!
+//! The printed text is JSON, YAML or XML as the serializers write it, so a
+//! small tokenizer per language is enough to color keys, strings, numbers and
+//! punctuation. Anything it doesn't recognize stays plain text.
+
+use super::theme::Theme;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+};
+
+/// Languages that can be highlighted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Json,
+    Yaml,
+    Xml,
+}
+
+impl Syntax {
+    /// The language a tree's source format is written in, if it's highlighted
+    pub fn for_format(format: &str) -> Option<Self> {
+        match format {
+            "json" | "jsonl" => Some(Self::Json),
+            "yaml" => Some(Self::Yaml),
+            "xml" | "html" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+}
+
+/// Kinds of fragments, each shown in its own theme color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    Plain,
+    /// Object and mapping keys, element names
+    Key,
+    /// XML attribute names
+    Attribute,
+    String,
+    /// Numbers, booleans and null
+    Literal,
+    Punctuation,
+    Comment,
+    /// YAML tags, anchors and aliases
+    Tag,
+}
+
+impl Token {
+    fn style(self, theme: &Theme) -> Style {
+        match self {
+            Token::Plain => Style::default(),
+            Token::Key => Style::default().fg(theme.label),
+            Token::Attribute => Style::default().fg(theme.key),
+            Token::String => Style::default().fg(theme.value),
+            Token::Literal => Style::default().fg(theme.icon),
+            Token::Punctuation => Style::default().fg(theme.node_type),
+            Token::Comment => Style::default()
+                .fg(theme.node_type)
+                .add_modifier(Modifier::ITALIC),
+            Token::Tag => Style::default().fg(theme.virtual_node),
+        }
+    }
+}
+
+/// `text` as styled lines, split the way `str::lines` splits it
+pub fn highlight(text: &str, syntax: Syntax, theme: &Theme) -> Text<'static> {
+    let mut lines = vec![Line::default()];
+    for (fragment, token) in tokenize(text, syntax) {
+        let style = token.style(theme);
+        for (i, part) in fragment.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Line::default());
+            }
+            if !part.is_empty()
+                && let Some(line) = lines.last_mut()
+            {
+                line.spans.push(Span::styled(part.to_string(), style));
+            }
+        }
+    }
+    if text.ends_with('\n') {
+        lines.pop();
+    }
+    Text::from(lines)
+}
+
+/// Split `text` into fragments that together make up all of it
+pub fn tokenize(text: &str, syntax: Syntax) -> Vec<(&str, Token)> {
+    let mut tokens = match syntax {
+        Syntax::Json => tokenize_json(text),
+        Syntax::Yaml => tokenize_yaml(text),
+        Syntax::Xml => tokenize_xml(text),
+    };
+    tokens.retain(|(fragment, _)| !fragment.is_empty());
+    tokens
+}
+
+fn tokenize_json(text: &str) -> Vec<(&str, Token)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let token = match bytes[i] {
+            b'"' => {
+                i = quoted_end(bytes, i);
+                let next = bytes[i..].iter().find(|b| !b.is_ascii_whitespace());
+                if next == Some(&b':') {
+                    Token::Key
+                } else {
+                    Token::String
+                }
+            }
+            b'{' | b'}' | b'[' | b']' | b',' | b':' => {
+                i += 1;
+                Token::Punctuation
+            }
+            b'-' | b'0'..=b'9' => {
+                i = run_end(bytes, i, |b| {
+                    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')
+                });
+                Token::Literal
+            }
+            b if b.is_ascii_alphabetic() => {
+                i = run_end(bytes, i, |b| b.is_ascii_alphanumeric());
+                Token::Literal
+            }
+            _ => {
+                i = run_end(bytes, i, |b| {
+                    !b.is_ascii_alphanumeric() && !b"\"{}[],:-".contains(&b)
+                });
+                Token::Plain
+            }
+        };
+        tokens.push((&text[start..i], token));
+    }
+    tokens
+}
+
+fn tokenize_yaml(text: &str) -> Vec<(&str, Token)> {
+    let mut tokens = Vec::new();
+    // Indentation of the line that started a `|` or `>` block scalar
+    let mut block_indent = None;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            tokens.push(("\n", Token::Plain));
+        }
+        let indent = line.len() - line.trim_start().len();
+        if let Some(block) = block_indent {
+            if line.trim().is_empty() || indent > block {
+                tokens.push((line, Token::String));
+                continue;
+            }
+            block_indent = None;
+        }
+        if yaml_line(line, &mut tokens) {
+            block_indent = Some(indent);
+        }
+    }
+    tokens
+}
+
+/// Tokenize one line of YAML, returning whether it starts a block scalar
+fn yaml_line<'a>(line: &'a str, tokens: &mut Vec<(&'a str, Token)>) -> bool {
+    let content = line.trim_start();
+    tokens.push((&line[..line.len() - content.len()], Token::Plain));
+    if matches!(content, "---" | "...") {
+        tokens.push((content, Token::Punctuation));
+        return false;
+    }
+
+    // Sequence items, possibly nested as in `- - a`
+    let mut rest = content;
+    while rest == "-" || rest.starts_with("- ") {
+        let marker = rest.len().min(2);
+        tokens.push((&rest[..marker], Token::Punctuation));
+        rest = &rest[marker..];
+    }
+
+    if !rest.starts_with('#')
+        && let Some(end) = yaml_key_end(rest)
+    {
+        tokens.push((&rest[..end], Token::Key));
+        tokens.push((&rest[end..end + 1], Token::Punctuation));
+        rest = &rest[end + 1..];
+    }
+    yaml_value(rest, tokens)
+}
+
+/// Length of the key in `rest` if it starts with `key:`
+fn yaml_key_end(rest: &str) -> Option<usize> {
+    let end = if rest.starts_with(['"', '\'']) {
+        quoted_end(rest.as_bytes(), 0)
+    } else if rest.starts_with(['[', '{']) {
+        return None;
+    } else {
+        rest.find(": ")
+            .or_else(|| rest.strip_suffix(':').map(str::len))?
+    };
+    let after = &rest[end..];
+    (after == ":" || after.starts_with(": ")).then_some(end)
+}
+
+/// Tokenize the value after any key, returning whether it starts a block
+/// scalar
+fn yaml_value<'a>(rest: &'a str, tokens: &mut Vec<(&'a str, Token)>) -> bool {
+    let value = rest.trim_start();
+    tokens.push((&rest[..rest.len() - value.len()], Token::Plain));
+
+    // Tags, anchors and aliases in front of the value
+    let mut value = value;
+    while value.starts_with(['!', '&', '*']) {
+        let end = value.find(' ').unwrap_or(value.len());
+        tokens.push((&value[..end], Token::Tag));
+        let after = value[end..].trim_start();
+        tokens.push((&value[end..value.len() - after.len()], Token::Plain));
+        value = after;
+    }
+
+    let block = value.starts_with(['|', '>'])
+        && value[1..]
+            .bytes()
+            .all(|b| matches!(b, b'-' | b'+' | b'0'..=b'9'));
+    let token = if value.starts_with('#') {
+        Token::Comment
+    } else if block || matches!(value, "[]" | "{}") {
+        Token::Punctuation
+    } else if is_yaml_literal(value) {
+        Token::Literal
+    } else {
+        Token::String
+    };
+    tokens.push((value, token));
+    block
+}
+
+/// Whether a plain scalar is a number, boolean or null
+fn is_yaml_literal(value: &str) -> bool {
+    matches!(
+        value,
+        "true" | "false" | "null" | "~" | ".inf" | "-.inf" | ".nan"
+    ) || (value.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+')
+        && value.parse::<f64>().is_ok())
+}
+
+fn tokenize_xml(text: &str) -> Vec<(&str, Token)> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &text[i..];
+        let start = i;
+        if rest.starts_with("<!--") {
+            i += find_end(rest, "-->");
+            tokens.push((&text[start..i], Token::Comment));
+        } else if rest.starts_with("<![CDATA[") {
+            i += find_end(rest, "]]>");
+            tokens.push((&text[start..i], Token::String));
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            i += find_end(rest, ">");
+            tokens.push((&text[start..i], Token::Punctuation));
+        } else if rest.starts_with('<') {
+            i = xml_tag(text, i, &mut tokens);
+        } else {
+            i = run_end(bytes, i, |b| b != b'<');
+            tokens.push((&text[start..i], Token::Plain));
+        }
+    }
+    tokens
+}
+
+/// Tokenize the tag starting at `start`, returning where it ends
+fn xml_tag<'a>(text: &'a str, start: usize, tokens: &mut Vec<(&'a str, Token)>) -> usize {
+    let bytes = text.as_bytes();
+    let is_name = |b: u8| !b.is_ascii_whitespace() && !b"<>/=\"'".contains(&b);
+
+    let mut i = start + 1;
+    if bytes.get(i) == Some(&b'/') {
+        i += 1;
+    }
+    tokens.push((&text[start..i], Token::Punctuation));
+    let name = i;
+    i = run_end(bytes, i, is_name);
+    tokens.push((&text[name..i], Token::Key));
+
+    while i < bytes.len() {
+        let from = i;
+        let token = match bytes[i] {
+            b'>' => {
+                tokens.push((&text[from..i + 1], Token::Punctuation));
+                return i + 1;
+            }
+            b'/' | b'=' => {
+                i += 1;
+                Token::Punctuation
+            }
+            b'"' | b'\'' => {
+                i = quoted_end(bytes, i);
+                Token::String
+            }
+            b'<' => return i,
+            b if b.is_ascii_whitespace() => {
+                i = run_end(bytes, i, |b| b.is_ascii_whitespace());
+                Token::Plain
+            }
+            _ => {
+                i = run_end(bytes, i, is_name);
+                Token::Attribute
+            }
+        };
+        tokens.push((&text[from..i], token));
+    }
+    i
+}
+
+/// Index after the quoted string starting at `start`, or the end of its line
+/// if it isn't closed
+fn quoted_end(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i] != b'\n' {
+        match bytes[i] {
+            // JSON escapes; YAML and XML quotes have no backslash escapes
+            // that end a string early, so the rare `\"` is acceptable there
+            b'\\' if quote == b'"' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    i.min(bytes.len())
+}
+
+/// Index of the first byte from `start` on not matching `f`
+fn run_end(bytes: &[u8], start: usize, f: impl Fn(u8) -> bool) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|&b| !f(b))
+        .map_or(bytes.len(), |n| start + n)
+}
+
+/// Length of `rest` up to and including `end`, or all of it
+fn find_end(rest: &str, end: &str) -> usize {
+    rest.find(end).map_or(rest.len(), |n| n + end.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str, syntax: Syntax) -> Vec<(&str, Token)> {
+        let tokens = tokenize(text, syntax);
+        let joined: String = tokens.iter().map(|(fragment, _)| *fragment).collect();
+        assert_eq!(joined, text);
+        tokens
+            .into_iter()
+            .filter(|(fragment, _)| !fragment.trim().is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_json_tokens() {
+        let json = "{\n  \"a \\\"b\\\"\": [1.5e3, true, null, \"x\"]\n}";
+        assert_eq!(
+            tokens(json, Syntax::Json),
+            [
+                ("{", Token::Punctuation),
+                ("\"a \\\"b\\\"\"", Token::Key),
+                (":", Token::Punctuation),
+                ("[", Token::Punctuation),
+                ("1.5e3", Token::Literal),
+                (",", Token::Punctuation),
+                ("true", Token::Literal),
+                (",", Token::Punctuation),
+                ("null", Token::Literal),
+                (",", Token::Punctuation),
+                ("\"x\"", Token::String),
+                ("]", Token::Punctuation),
+                ("}", Token::Punctuation),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_yaml_tokens() {
+        let yaml =
+            "name: app\nport: 8080\nitems:\n- x: !tag 'y: z'\ntext: |-\n  a: b\n  c\nnext: []";
+        assert_eq!(
+            tokens(yaml, Syntax::Yaml),
+            [
+                ("name", Token::Key),
+                (":", Token::Punctuation),
+                ("app", Token::String),
+                ("port", Token::Key),
+                (":", Token::Punctuation),
+                ("8080", Token::Literal),
+                ("items", Token::Key),
+                (":", Token::Punctuation),
+                ("- ", Token::Punctuation),
+                ("x", Token::Key),
+                (":", Token::Punctuation),
+                ("!tag", Token::Tag),
+                ("'y: z'", Token::String),
+                ("text", Token::Key),
+                (":", Token::Punctuation),
+                ("|-", Token::Punctuation),
+                ("  a: b", Token::String),
+                ("  c", Token::String),
+                ("next", Token::Key),
+                (":", Token::Punctuation),
+                ("[]", Token::Punctuation),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_xml_tokens() {
+        let xml = "<?xml version=\"1.0\"?>\n<a id=\"1\">t &amp; u<!-- c --><b/></a>";
+        assert_eq!(
+            tokens(xml, Syntax::Xml),
+            [
+                ("<?xml version=\"1.0\"?>", Token::Punctuation),
+                ("<", Token::Punctuation),
+                ("a", Token::Key),
+                ("id", Token::Attribute),
+                ("=", Token::Punctuation),
+                ("\"1\"", Token::String),
+                (">", Token::Punctuation),
+                ("t &amp; u", Token::Plain),
+                ("<!-- c -->", Token::Comment),
+                ("<", Token::Punctuation),
+                ("b", Token::Key),
+                ("/", Token::Punctuation),
+                (">", Token::Punctuation),
+                ("</", Token::Punctuation),
+                ("a", Token::Key),
+                (">", Token::Punctuation),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlighted_lines_match_the_text() {
+        let text = "{\n  \"a\": \"é\"\n}\n";
+        let highlighted = highlight(text, Syntax::Json, &Theme::default());
+        assert_eq!(highlighted.lines.len(), text.lines().count());
+        assert_eq!(highlighted.lines[1].spans.last().unwrap().content, "\"é\"");
+    }
+}
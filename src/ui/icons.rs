@@ -0,0 +1,141 @@
+//! Glyphs of the icon sets chosen with `ui.icons`.
+//!
+//! Every row starts with an expander showing whether the node is expanded,
+//! collapsed or a leaf. The Nerd Font set adds an icon for the node's type
+//! after it. Each glyph takes one column, and a set without an icon for some
+//! type pads with a space, so labels line up within a set.
+
+use crate::config::IconSet;
+use crate::tree::TreeNode;
+
+/// State shown by the icon in front of a label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expander {
+    Expanded,
+    Collapsed,
+    /// No children to show
+    Leaf,
+}
+
+impl IconSet {
+    /// The expander glyph. `@attributes` nodes get their own glyphs where the
+    /// set has them.
+    pub fn expander(self, expander: Expander, virtual_attributes: bool) -> &'static str {
+        match (self, expander, virtual_attributes) {
+            (IconSet::Ascii, Expander::Leaf, _) => "*",
+            (_, Expander::Leaf, _) => " ",
+            (IconSet::Unicode, Expander::Expanded, false) => "▼",
+            (IconSet::Unicode, Expander::Collapsed, false) => "▶",
+            (IconSet::Unicode, Expander::Expanded, true) => "▽",
+            (IconSet::Unicode, Expander::Collapsed, true) => "▷",
+            (IconSet::Ascii, Expander::Expanded, _) => "-",
+            (IconSet::Ascii, Expander::Collapsed, _) => "+",
+            // nf-cod-chevron_down and nf-cod-chevron_right
+            (IconSet::Nerd, Expander::Expanded, _) => "\u{eab4}",
+            (IconSet::Nerd, Expander::Collapsed, _) => "\u{eab6}",
+        }
+    }
+
+    /// The icon for a node's type, or `None` if the set has no type icons
+    pub fn node_type(self, node: &TreeNode) -> Option<&'static str> {
+        if self != IconSet::Nerd {
+            return None;
+        }
+        let icon = match node.node_type.as_str() {
+            // nf-cod-symbol_namespace, the braces of an object
+            t if TreeNode::OBJECT_TYPES.contains(&t) || t == "root" => "\u{ea8b}",
+            // nf-cod-symbol_array
+            t if TreeNode::ARRAY_TYPES.contains(&t) => "\u{ea8a}",
+            // nf-cod-code
+            "element" => "\u{eac4}",
+            // nf-cod-person
+            "entry" => "\u{ea67}",
+            // nf-cod-comment
+            "comment" => "\u{ea6b}",
+            // nf-cod-symbol_property
+            TreeNode::VIRTUAL_ATTRIBUTES_TYPE => "\u{eb65}",
+            // nf-cod-error
+            TreeNode::ERROR_TYPE => "\u{ea87}",
+            // nf-cod-link
+            TreeNode::URL_REF_TYPE => "\u{eb15}",
+            TreeNode::ATTRIBUTE_TYPE => {
+                let value = node.attributes.iter().find(|a| a.key == "value");
+                scalar_icon(value.map(|a| a.value.as_str()))
+            }
+            // nf-cod-symbol_string
+            "text" | "string" => "\u{eb8d}",
+            // nf-cod-symbol_numeric
+            "number" => "\u{ea90}",
+            _ => " ",
+        };
+        Some(icon)
+    }
+
+    /// The marker after the first line of a value with `lines` lines
+    pub fn line_count(self, lines: usize) -> String {
+        match self {
+            IconSet::Ascii => format!("({} lines)", lines),
+            IconSet::Unicode | IconSet::Nerd => format!("⏎×{}", lines),
+        }
+    }
+
+    /// Columns taken by the icons and their spacing in front of a label
+    pub fn width(self) -> usize {
+        match self {
+            IconSet::Nerd => 4,
+            IconSet::Unicode | IconSet::Ascii => 2,
+        }
+    }
+}
+
+/// The icon for a scalar by the look of its value
+fn scalar_icon(value: Option<&str>) -> &'static str {
+    match value {
+        // nf-cod-symbol_boolean
+        Some("true" | "false") => "\u{ea8f}",
+        // nf-cod-symbol_numeric
+        Some(value) if value.parse::<f64>().is_ok_and(f64::is_finite) => "\u{ea90}",
+        // nf-cod-symbol_string
+        _ => "\u{eb8d}",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn test_every_glyph_is_one_column() {
+        let mut nodes: Vec<TreeNode> =
+            ["object", "array", "element", "entry", "url_ref", "mystery"]
+                .iter()
+                .map(|t| TreeNode::new("n", *t))
+                .collect();
+        for value in ["42", "true", "text"] {
+            let mut node = TreeNode::new("n", TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", value);
+            nodes.push(node);
+        }
+        for set in [IconSet::Unicode, IconSet::Ascii, IconSet::Nerd] {
+            for expander in [Expander::Expanded, Expander::Collapsed, Expander::Leaf] {
+                for virtual_attributes in [false, true] {
+                    assert_eq!(set.expander(expander, virtual_attributes).width(), 1);
+                }
+            }
+            for node in &nodes {
+                if let Some(icon) = set.node_type(node) {
+                    assert_eq!(icon.width(), 1, "{:?} {}", set, node.node_type);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scalars_are_told_apart_by_value() {
+        assert_eq!(scalar_icon(Some("-1.5e3")), "\u{ea90}");
+        assert_eq!(scalar_icon(Some("false")), "\u{ea8f}");
+        assert_eq!(scalar_icon(Some("1.2.3")), "\u{eb8d}");
+        assert_eq!(scalar_icon(None), "\u{eb8d}");
+    }
+}
@@ -1,5 +1,16 @@
 pub mod app;
 pub mod detail_view;
+pub mod finder;
+pub mod highlight;
+pub mod icons;
+pub mod pattern;
+pub mod printer;
+pub mod redact;
+pub mod render_html;
+pub mod render_text;
+pub mod table_view;
+pub mod terminal;
+pub mod theme;
 pub mod tree_view;
 
 pub use app::App;
@@ -0,0 +1,285 @@
+//! Search queries and where they occur in a piece of text.
+//!
+//! The same pattern decides which nodes match and which fragments of a row
+//! are highlighted, so the two always agree.
+//!
+//! Matching [in paths](SearchPattern::in_paths), the last word of a query
+//! must match the node and the words before it the labels above it, so
+//! `database host` finds the `host` key of the `database` mapping.
+
+use crate::tree::{NodeMatcher, TreeNode};
+use std::ops::Range;
+
+/// Which parts of a node a query is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchField {
+    /// Labels, types, attribute keys and attribute values
+    All,
+    /// Labels and attribute keys (`k:`)
+    Keys,
+    /// Attribute values (`v:`), or values equal to the query (`v=`)
+    Values,
+}
+
+/// A parsed search query
+#[derive(Debug, Clone)]
+pub struct SearchPattern {
+    field: SearchField,
+    /// Query without its prefix, lowercased unless matching case-sensitively
+    needle: String,
+    case_sensitive: bool,
+    /// Whether whole values must equal the query (`v=`)
+    exact: bool,
+    /// Words that must occur in order in the path above a match, cased like
+    /// `needle`
+    context: Vec<String>,
+    /// Separator of the labels in paths, when matching in paths
+    separator: Option<String>,
+}
+
+impl SearchPattern {
+    /// Parse a query as typed, splitting off a `k:`, `v:` or `v=` prefix
+    pub fn new(query: &str, case_sensitive: bool) -> Self {
+        let (field, text, exact) = if let Some(rest) = query.strip_prefix("k:") {
+            (SearchField::Keys, rest, false)
+        } else if let Some(rest) = query.strip_prefix("v:") {
+            (SearchField::Values, rest, false)
+        } else if let Some(rest) = query.strip_prefix("v=") {
+            (SearchField::Values, rest, true)
+        } else {
+            (SearchField::All, query, false)
+        };
+        let needle = if case_sensitive {
+            text.to_string()
+        } else {
+            lowercase(text)
+        };
+        Self {
+            field,
+            needle,
+            case_sensitive,
+            exact,
+            context: Vec::new(),
+            separator: None,
+        }
+    }
+
+    /// Match in paths joined with `separator`: the last word of the query
+    /// must match the node, the words before it the labels above it
+    pub fn in_paths(mut self, separator: &str) -> Self {
+        let mut words: Vec<String> = self.needle.split_whitespace().map(String::from).collect();
+        if let Some(last) = words.pop()
+            && !words.is_empty()
+        {
+            self.needle = last;
+            self.context = words;
+        }
+        self.separator = Some(separator.to_string());
+        self
+    }
+
+    /// Whether the context words occur in order in `path`
+    fn context_in(&self, path: &str) -> bool {
+        if self.context.is_empty() {
+            return true;
+        }
+        let path = if self.case_sensitive {
+            path.to_string()
+        } else {
+            lowercase(path)
+        };
+        let mut rest = path.as_str();
+        self.context
+            .iter()
+            .all(|word| match rest.find(word.as_str()) {
+                Some(start) => {
+                    rest = &rest[start + word.len()..];
+                    true
+                }
+                None => false,
+            })
+    }
+
+    pub fn field(&self) -> SearchField {
+        self.field
+    }
+
+    /// Whether there is nothing to search for, e.g. only a prefix was typed.
+    ///
+    /// `v=` alone looks for empty values.
+    pub fn is_empty(&self) -> bool {
+        self.needle.is_empty() && !self.exact
+    }
+
+    /// Whether labels and keys are searched
+    pub fn searches_keys(&self) -> bool {
+        self.field != SearchField::Values
+    }
+
+    /// Whether attribute values are searched
+    pub fn searches_values(&self) -> bool {
+        self.field != SearchField::Keys
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        if self.is_empty() {
+            false
+        } else if self.exact && self.case_sensitive {
+            text == self.needle
+        } else if self.exact {
+            lowercase(text) == self.needle
+        } else if self.case_sensitive {
+            text.contains(&self.needle)
+        } else {
+            lowercase(text).contains(&self.needle)
+        }
+    }
+
+    /// Byte ranges of the non-overlapping occurrences in `text`.
+    ///
+    /// Ranges always fall on character boundaries of `text`, even when
+    /// lowercasing changed the length of some characters.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        if self.exact {
+            let whole = self.is_match(text) && !text.is_empty();
+            return whole.then_some(0..text.len()).into_iter().collect();
+        }
+        if self.case_sensitive {
+            return text
+                .match_indices(&self.needle)
+                .map(|(start, m)| start..start + m.len())
+                .collect();
+        }
+
+        // Lowercase while remembering which character each byte came from
+        let mut lowered = String::with_capacity(text.len());
+        let mut origin = Vec::with_capacity(text.len());
+        for (index, c) in text.char_indices() {
+            for lower in c.to_lowercase() {
+                lowered.push(lower);
+                origin.resize(lowered.len(), index);
+            }
+        }
+
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (start, m) in lowered.match_indices(&self.needle) {
+            let first = origin[start];
+            let last = origin[start + m.len() - 1];
+            let end = last + text[last..].chars().next().map_or(0, char::len_utf8);
+            // A character lowercasing to several could be claimed twice
+            if ranges.last().is_none_or(|prev| prev.end <= first) {
+                ranges.push(first..end);
+            }
+        }
+        ranges
+    }
+}
+
+impl NodeMatcher for SearchPattern {
+    fn matches(&self, node: &TreeNode) -> bool {
+        let label = self.searches_keys() && self.is_match(&node.label);
+        let node_type = self.field == SearchField::All && self.is_match(&node.node_type);
+        label
+            || node_type
+            || node.attributes.iter().any(|attr| {
+                (self.searches_keys() && self.is_match(&attr.key))
+                    || (self.searches_values() && self.is_match(&attr.value))
+            })
+    }
+
+    fn needs_values(&self) -> bool {
+        self.searches_values()
+    }
+
+    fn path_separator(&self) -> Option<&str> {
+        self.separator.as_deref()
+    }
+
+    fn matches_below(&self, node: &TreeNode, path: &str) -> bool {
+        // Paths are only lowercased for nodes that match themselves
+        self.matches(node) && self.context_in(path)
+    }
+}
+
+/// Lowercase character by character, so lengths line up with [`SearchPattern::find`]
+fn lowercase(text: &str) -> String {
+    text.chars().flat_map(char::to_lowercase).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefixes() {
+        let pattern = SearchPattern::new("k:name", false);
+        assert_eq!(pattern.field(), SearchField::Keys);
+        assert!(pattern.is_match("Name"));
+
+        let pattern = SearchPattern::new("v:", false);
+        assert_eq!(pattern.field(), SearchField::Values);
+        assert!(pattern.is_empty());
+        assert!(!pattern.is_match("anything"));
+    }
+
+    #[test]
+    fn test_exact_values() {
+        let pattern = SearchPattern::new("v=ab", false);
+        assert_eq!(pattern.field(), SearchField::Values);
+        assert!(pattern.is_match("AB"));
+        assert!(!pattern.is_match("abc"));
+        assert_eq!(pattern.find("Ab"), [0..2]);
+        assert!(pattern.find("xab").is_empty());
+        assert!(!SearchPattern::new("v=ab", true).is_match("AB"));
+
+        let pattern = SearchPattern::new("v=", true);
+        assert!(!pattern.is_empty());
+        assert!(pattern.is_match(""));
+        assert!(!pattern.is_match("a"));
+    }
+
+    #[test]
+    fn test_words_before_the_last_match_the_path() {
+        let pattern = SearchPattern::new("Database host", false).in_paths(".");
+        let host = TreeNode::new("host", "string");
+        assert!(pattern.matches_below(&host, "prod.database"));
+        assert!(pattern.matches_below(&host, "DATABASES.primary"));
+        assert!(!pattern.matches_below(&host, "cache"));
+        // Only the last word is highlighted
+        assert_eq!(pattern.find("hostname"), [0..4]);
+
+        let pattern = SearchPattern::new("b a", true).in_paths("/");
+        assert!(pattern.matches_below(&TreeNode::new("a", "x"), "b/c"));
+        assert!(!pattern.matches_below(&TreeNode::new("a", "x"), "B/c"));
+        let pattern = SearchPattern::new("a b c", false).in_paths("/");
+        assert!(!pattern.matches_below(&TreeNode::new("c", "x"), "b/a"));
+
+        // A single word matches anywhere, as without paths
+        let pattern = SearchPattern::new("host", false).in_paths(".");
+        assert!(pattern.matches_below(&host, ""));
+    }
+
+    #[test]
+    fn test_find_ranges() {
+        let pattern = SearchPattern::new("ab", false);
+        assert_eq!(pattern.find("xAByab"), [1..3, 4..6]);
+        assert!(SearchPattern::new("ab", true).find("xAB").is_empty());
+    }
+
+    #[test]
+    fn test_find_ranges_in_multibyte_text() {
+        let pattern = SearchPattern::new("über", false);
+        let text = "Grüße ÜBER alles";
+        let ranges = pattern.find(text);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(&text[ranges[0].clone()], "ÜBER");
+
+        // 'İ' lowercases to two characters, which must map back to one
+        let text = "xİy";
+        let ranges = SearchPattern::new("i̇y", false).find(text);
+        assert_eq!(&text[ranges[0].clone()], "İy");
+    }
+}
@@ -0,0 +1,161 @@
+//! Printing the tree as text without the TUI, for `xtv --print`.
+//!
+//! Rows come from [`TreeView::subtree_rows`], so they look like the rows of
+//! the viewer: same icons, labels, values, child counts and previews. They
+//! are laid out by [`render_text`](crate::ui::render_text), which can also
+//! leave out the icons and draw guides instead (`--ascii-tree`). With
+//! `--stats`, the numbers of [`TreeStats`] are printed instead.
+
+use crate::config::Config;
+use crate::tree::{MemoryUsage, TreeStats, TreeVariant};
+use crate::ui::render_text::{self, TextOptions};
+use crate::ui::theme::Theme;
+use crate::ui::tree_view::{TreeView, format_thousands};
+use std::io::{self, Write};
+
+/// Write the subtree below `root` to `out`, one row per line, expanded
+/// `depth` levels deep (all levels with `None`), laid out as `options` say
+/// with the colors of the configured theme
+pub fn print_tree(
+    out: &mut impl Write,
+    tree: &TreeVariant,
+    root: usize,
+    depth: Option<usize>,
+    config: &Config,
+    options: &TextOptions,
+) -> io::Result<()> {
+    let theme = Theme::from_config(&config.ui).unwrap_or_default();
+    let mut view = TreeView::new(root);
+    view.set_show_child_counts(config.ui.show_child_counts);
+    view.set_show_previews(config.ui.show_previews);
+    view.set_inline_attributes(config.ui.inline_attributes);
+
+    let rows = view.subtree_rows(tree, root, depth, &theme, options);
+    render_text::write_rows(out, &rows, options)?;
+    out.flush()
+}
+
+/// Write the statistics of a tree to `out`, one number per line
+pub fn print_stats(out: &mut impl Write, stats: &TreeStats) -> io::Result<()> {
+    let bytes = |n: usize| format!("{} bytes", format_thousands(n));
+    let mut rows = vec![
+        ("Nodes", format_thousands(stats.nodes)),
+        ("Max depth", stats.max_depth.to_string()),
+        ("Label bytes", format_thousands(stats.label_bytes)),
+        (
+            "Attribute bytes",
+            stats
+                .attribute_bytes
+                .map_or_else(|| "not read".to_string(), format_thousands),
+        ),
+    ];
+    match stats.memory {
+        MemoryUsage::InMemory { heap_bytes } => rows.push(("Memory", bytes(heap_bytes))),
+        MemoryUsage::Streaming {
+            index_bytes,
+            cache_bytes,
+            cached_nodes,
+        } => {
+            rows.push(("Index memory", bytes(index_bytes)));
+            rows.push((
+                "Cache memory",
+                format!(
+                    "{} ({} nodes)",
+                    bytes(cache_bytes),
+                    format_thousands(cached_nodes)
+                ),
+            ));
+        }
+    }
+    for (name, value) in rows {
+        writeln!(out, "{:<16} {}", name, value)?;
+    }
+    writeln!(out, "By type")?;
+    for (node_type, count) in &stats.by_type {
+        writeln!(out, "  {:<14} {}", node_type, format_thousands(*count))?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser};
+    use crate::ui::render_text::TextLayout;
+
+    fn print(json: &str, depth: Option<usize>, color: bool) -> String {
+        let options = TextOptions {
+            color,
+            ..TextOptions::default()
+        };
+        print_with(json, depth, &options)
+    }
+
+    fn print_with(json: &str, depth: Option<usize>, options: &TextOptions) -> String {
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        let mut out = Vec::new();
+        let root = tree.root_id();
+        print_tree(&mut out, &tree, root, depth, &Config::default(), options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_print_rows_like_the_viewer() {
+        let json = r#"{"a": {"b": [1, 2]}, "c": "x"}"#;
+        assert_eq!(
+            print(json, None, false),
+            "▼ root [root]\n  ▼ a [object]\n    ▼ b [array]\n        [0]: 1\n        [1]: 2\n    c: x\n"
+        );
+        // Collapsed nodes show their child count and a preview
+        assert_eq!(
+            print(json, Some(1), false),
+            "▼ root [root]\n  ▶ a (1) [object] {b: […]}\n    c: x\n"
+        );
+    }
+
+    #[test]
+    fn test_print_with_guides_and_full_values() {
+        let json = format!(r#"{{"a": {{"b": [1, 2]}}, "c": "{}"}}"#, "x".repeat(300));
+        let options = TextOptions {
+            layout: TextLayout::Guides,
+            full_values: true,
+            color: false,
+        };
+        assert_eq!(
+            print_with(&json, None, &options),
+            format!(
+                "root [root]\n├── a [object]\n│   └── b [array]\n│       ├── [0]: 1\n│       └── [1]: 2\n└── c: {}\n",
+                "x".repeat(300)
+            )
+        );
+
+        // Without full values long values are cut like in the viewer
+        let options = TextOptions {
+            layout: TextLayout::Plain,
+            ..TextOptions::default()
+        };
+        let printed = print_with(&json, Some(1), &options);
+        assert!(printed.starts_with("root [root]\n  a (1) [object] {b: […]}\n  c: xxx"));
+        assert!(printed.ends_with("...\n"));
+    }
+
+    #[test]
+    fn test_print_stats() {
+        let tree = JsonParser.parse(r#"{"a": [1, 2], "b": "x"}"#).unwrap();
+        let mut out = Vec::new();
+        print_stats(&mut out, &tree.stats()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("Nodes            5\nMax depth        2\n"));
+        assert!(out.contains("\nMemory           "));
+        assert!(
+            out.ends_with("By type\n  array          1\n  attribute      3\n  root           1\n")
+        );
+    }
+
+    #[test]
+    fn test_print_colors_only_when_asked() {
+        let colored = print(r#"{"a": 1}"#, None, true);
+        assert!(colored.contains("\x1b["));
+        assert!(!print(r#"{"a": 1}"#, None, false).contains('\x1b'));
+    }
+}
@@ -0,0 +1,190 @@
+//! Masking the values of sensitive keys, for showing files full of passwords
+//! and tokens on a shared screen.
+//!
+//! `ui.redact_keys` lists glob patterns, where `*` stands for any text and
+//! `?` for one character, matched against labels and attribute keys without
+//! regard to case. While redaction is on (`R`, or `ui.redact`), the values of
+//! matching nodes, and of everything below them, are drawn as [`MASK`] in the
+//! tree, the detail pane, the print popup and the table view. The structure
+//! stays visible.
+
+use crate::tree::table::Table;
+use crate::tree::{TreeLike, TreeNode};
+use serde_json::Value;
+
+/// What redacted values are drawn as
+pub const MASK: &str = "••••••";
+
+/// The keys whose values are masked
+#[derive(Debug, Clone, Default)]
+pub struct Redaction {
+    /// Lowercased glob patterns
+    patterns: Vec<String>,
+}
+
+impl Redaction {
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns.iter().map(|p| p.to_lowercase()).collect(),
+        }
+    }
+
+    /// Whether `key` matches one of the patterns
+    pub fn matches(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.patterns
+            .iter()
+            .any(|pattern| glob_matches(pattern, &key))
+    }
+
+    /// Whether the value of `node_id` is masked: its label, or that of one
+    /// of its ancestors, matches
+    pub fn redacts(&self, tree: &dyn TreeLike, node_id: usize) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        std::iter::once(node_id)
+            .chain(tree.ancestors(node_id))
+            .filter_map(|id| tree.get_label(id))
+            .any(|label| self.matches(&label))
+    }
+
+    /// Mask the attribute values of `node`: all of them if the node itself
+    /// is redacted, otherwise those whose key matches
+    pub fn redact_node(&self, node: &mut TreeNode, redacted: bool) {
+        for attr in &mut node.attributes {
+            if redacted || self.matches(&attr.key) {
+                attr.value = MASK.to_string();
+            }
+        }
+    }
+
+    /// Mask the cells of matching columns of `table`, or all cells if the
+    /// array is `redacted`
+    pub fn redact_table(&self, table: &mut Table, redacted: bool) {
+        for (column, key) in table.columns.iter().enumerate() {
+            if !redacted && !self.matches(key) {
+                continue;
+            }
+            for row in &mut table.rows {
+                if let Some(cell @ Some(_)) = row.cells.get_mut(column) {
+                    *cell = Some(MASK.to_string());
+                }
+            }
+        }
+    }
+
+    /// Mask the values of matching keys in `value`, or all of its values if
+    /// `redacted`, as exported for printing.
+    ///
+    /// # Returns
+    ///
+    /// How many values were masked
+    pub fn redact_json(&self, value: &mut Value, redacted: bool) -> usize {
+        match value {
+            Value::Object(map) => map
+                .iter_mut()
+                .map(|(key, value)| self.redact_json(value, redacted || self.matches(key)))
+                .sum(),
+            Value::Array(items) => items
+                .iter_mut()
+                .map(|item| self.redact_json(item, redacted))
+                .sum(),
+            _ if redacted => {
+                *value = Value::String(MASK.to_string());
+                1
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Whether `text` matches the glob `pattern`, where `*` matches any text and
+/// `?` one character
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            // Let the last `*` match one more character
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser};
+    use serde_json::json;
+
+    fn redaction() -> Redaction {
+        Redaction::new(&["password", "secret", "token", "*_key"].map(String::from))
+    }
+
+    #[test]
+    fn test_glob_matching() {
+        assert!(glob_matches("*_key", "api_key"));
+        assert!(glob_matches("*_key", "_key"));
+        assert!(!glob_matches("*_key", "api_keys"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+        assert!(glob_matches("to?en", "token"));
+        assert!(!glob_matches("to?en", "toen"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("token", "tokens"));
+    }
+
+    #[test]
+    fn test_keys_match_without_regard_to_case() {
+        let redaction = redaction();
+        assert!(redaction.matches("Password"));
+        assert!(redaction.matches("AWS_SECRET_KEY"));
+        assert!(!redaction.matches("userPassword"));
+        assert!(!Redaction::default().matches("password"));
+    }
+
+    #[test]
+    fn test_nodes_below_a_matching_key_are_redacted() {
+        let json = r#"{"user": "ada", "ssh_key": {"private": "x"}, "token": "t"}"#;
+        let tree = JsonParser.parse(json).unwrap();
+        let redaction = redaction();
+        let redacted: Vec<String> = tree
+            .walk(tree.root_id(), crate::tree::Order::DepthFirst)
+            .map(|(id, _)| id)
+            .filter(|&id| redaction.redacts(&tree, id))
+            .filter_map(|id| tree.get_label(id))
+            .collect();
+        assert_eq!(redacted, ["ssh_key", "private", "token"]);
+    }
+
+    #[test]
+    fn test_json_values_are_masked() {
+        let mut value = json!({"user": "ada", "db": {"password": 42, "hosts": ["a"]}});
+        assert_eq!(redaction().redact_json(&mut value, false), 1);
+        assert_eq!(
+            value,
+            json!({"user": "ada", "db": {"password": MASK, "hosts": ["a"]}})
+        );
+        assert_eq!(redaction().redact_json(&mut value, true), 3);
+    }
+}
@@ -0,0 +1,293 @@
+//! The tree as a standalone HTML page, for `xtv --export-html` and `w`.
+//!
+//! Nodes become `<details>` elements, open where the viewer has them
+//! expanded, with the labels, values and previews of the viewer's rows in the
+//! theme's colors. The path to the selected node is highlighted and scrolled
+//! into view. CSS and the few lines of script are inlined, so the page opens
+//! in any browser without xtv or other files.
+//!
+//! Collapsed nodes are written too, so that readers can open them, which
+//! makes pages of large trees large. Pages stop at [`MAX_HTML_ROWS`] rows and
+//! [`MAX_HTML_DEPTH`] levels, saying so at the top.
+
+use crate::ui::theme::Theme;
+use crate::ui::tree_view::format_thousands;
+use ratatui::style::{Color, Modifier};
+use ratatui::text::Span;
+use std::fmt::Write;
+
+/// Rows a page holds at most
+pub const MAX_HTML_ROWS: usize = 50_000;
+
+/// Levels below the root a page holds at most; browsers lay out deeper
+/// nesting slowly
+pub const MAX_HTML_DEPTH: usize = 100;
+
+/// A node's row and its place in the view
+#[derive(Debug, Clone)]
+pub struct HtmlRow {
+    pub node_id: usize,
+    pub depth: usize,
+    /// The row as the viewer draws it, without indentation and icons
+    pub spans: Vec<Span<'static>>,
+    /// Whether rows of children follow
+    pub expandable: bool,
+    /// Whether the view has the node expanded
+    pub expanded: bool,
+}
+
+/// What the page says besides the rows
+#[derive(Debug, Clone, Default)]
+pub struct HtmlPage<'a> {
+    /// The file name, or `stdin`
+    pub title: &'a str,
+    /// The selected node's path as in the path bar
+    pub breadcrumb: &'a str,
+    /// Nodes from the root down to the selected one
+    pub path: &'a [usize],
+    /// Whether rows were left out to stay within the limits
+    pub truncated: bool,
+}
+
+/// What the page and `--export-html` say when rows were left out
+pub fn truncation_warning() -> String {
+    format!(
+        "The tree is too large for one page: rows beyond {} or {} levels deep were left out",
+        format_thousands(MAX_HTML_ROWS),
+        MAX_HTML_DEPTH
+    )
+}
+
+/// `rows`, in depth-first order, as a page of nested `<details>` elements
+pub fn rows_to_html(rows: &[HtmlRow], page: &HtmlPage, theme: &Theme) -> String {
+    let background = css_color(theme.popup_bg).unwrap_or_else(|| "#000000".to_string());
+    let foreground = css_color(theme.popup_fg).unwrap_or_else(|| "#ffffff".to_string());
+    let highlight = css_color(theme.selection_bg).unwrap_or_else(|| "#444444".to_string());
+    let title = escape(page.title);
+
+    let mut html = String::new();
+    // Writing to a String doesn't fail
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title} - xtv</title>\n\
+         <style>\n\
+         body {{ background: {background}; color: {foreground}; font-family: monospace; }}\n\
+         summary, .leaf {{ white-space: pre; cursor: default; }}\n\
+         details details, details > .leaf {{ margin-left: 1.5em; }}\n\
+         .leaf {{ padding-left: 1.1em; }}\n\
+         .path {{ text-decoration: underline; }}\n\
+         .selected {{ background: {highlight}; }}\n\
+         .breadcrumb {{ font-weight: bold; }}\n\
+         .warning {{ color: {warning}; }}\n\
+         </style>\n</head>\n<body>\n<header>\n<h1>{title}</h1>\n\
+         <p class=\"breadcrumb\">{breadcrumb}</p>\n",
+        warning = css_color(theme.warning).unwrap_or_else(|| "orange".to_string()),
+        breadcrumb = escape(page.breadcrumb),
+    );
+    if page.truncated {
+        let _ = writeln!(
+            html,
+            "<p class=\"warning\">{}</p>",
+            escape(&truncation_warning())
+        );
+    }
+    html.push_str(
+        "<button onclick=\"setAll(true)\">Expand all</button>\n\
+         <button onclick=\"setAll(false)\">Collapse all</button>\n</header>\n<main>\n",
+    );
+
+    // Depths of the `<details>` still open
+    let mut open: Vec<usize> = Vec::new();
+    for row in rows {
+        while open.last().is_some_and(|&depth| depth >= row.depth) {
+            open.pop();
+            html.push_str("</details>\n");
+        }
+        let (marker, id) = if page.path.last() == Some(&row.node_id) {
+            (" selected", " id=\"selected\"")
+        } else if page.path.contains(&row.node_id) {
+            (" path", "")
+        } else {
+            ("", "")
+        };
+        if row.expandable {
+            let state = if row.expanded { " open" } else { "" };
+            let class = marker.trim_start();
+            if class.is_empty() {
+                let _ = write!(html, "<details{state}><summary>");
+            } else {
+                let _ = write!(html, "<details{state}><summary class=\"{class}\"{id}>");
+            }
+            push_spans(&mut html, &row.spans);
+            html.push_str("</summary>\n");
+            open.push(row.depth);
+        } else {
+            let _ = write!(html, "<div class=\"leaf{marker}\"{id}>");
+            push_spans(&mut html, &row.spans);
+            html.push_str("</div>\n");
+        }
+    }
+    for _ in open {
+        html.push_str("</details>\n");
+    }
+
+    html.push_str(
+        "</main>\n<script>\n\
+         function setAll(open) { document.querySelectorAll('details').forEach(d => d.open = open); }\n\
+         document.getElementById('selected')?.scrollIntoView({ block: 'center' });\n\
+         </script>\n</body>\n</html>\n",
+    );
+    html
+}
+
+/// Write spans in their colors, joining neighbours of the same style
+fn push_spans(html: &mut String, spans: &[Span]) {
+    for group in spans.chunk_by(|a, b| a.style == b.style) {
+        let style = group[0].style;
+        let mut css = String::new();
+        if let Some(color) = style.fg.and_then(css_color) {
+            let _ = write!(css, "color: {color};");
+        }
+        if let Some(color) = style.bg.and_then(css_color) {
+            let _ = write!(css, "background: {color};");
+        }
+        if style.add_modifier.contains(Modifier::BOLD) {
+            css.push_str("font-weight: bold;");
+        }
+        if style.add_modifier.contains(Modifier::DIM) {
+            css.push_str("opacity: 0.6;");
+        }
+        if style.add_modifier.contains(Modifier::UNDERLINED) {
+            css.push_str("text-decoration: underline;");
+        }
+        let text: String = group.iter().map(|span| escape(&span.content)).collect();
+        if css.is_empty() {
+            html.push_str(&text);
+        } else {
+            let _ = write!(html, "<span style=\"{css}\">{text}</span>");
+        }
+    }
+}
+
+/// A terminal color in CSS, with the usual xterm palette for named and
+/// indexed colors; `None` for the terminal's default
+fn css_color(color: Color) -> Option<String> {
+    const ANSI: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    let (r, g, b) = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => ANSI[0],
+        Color::Red => ANSI[1],
+        Color::Green => ANSI[2],
+        Color::Yellow => ANSI[3],
+        Color::Blue => ANSI[4],
+        Color::Magenta => ANSI[5],
+        Color::Cyan => ANSI[6],
+        Color::Gray => ANSI[7],
+        Color::DarkGray => ANSI[8],
+        Color::LightRed => ANSI[9],
+        Color::LightGreen => ANSI[10],
+        Color::LightYellow => ANSI[11],
+        Color::LightBlue => ANSI[12],
+        Color::LightMagenta => ANSI[13],
+        Color::LightCyan => ANSI[14],
+        Color::White => ANSI[15],
+        Color::Indexed(index @ 0..16) => ANSI[index as usize],
+        Color::Indexed(index @ 16..232) => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        Color::Indexed(index) => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    };
+    Some(format!("#{r:02x}{g:02x}{b:02x}"))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(node_id: usize, depth: usize, label: &str, expandable: bool, expanded: bool) -> HtmlRow {
+        HtmlRow {
+            node_id,
+            depth,
+            spans: vec![Span::raw(label.to_string())],
+            expandable,
+            expanded,
+        }
+    }
+
+    #[test]
+    fn test_rows_nest_as_details() {
+        let rows = [
+            row(0, 0, "root", true, true),
+            row(1, 1, "a", true, false),
+            row(2, 2, "<b>", false, false),
+            row(3, 1, "c", false, false),
+        ];
+        let page = HtmlPage {
+            title: "data.json",
+            breadcrumb: "a > <b>",
+            path: &[0, 1, 2],
+            truncated: false,
+        };
+        let html = rows_to_html(&rows, &page, &Theme::default());
+        let main = &html[html.find("<main>").unwrap()..html.find("</main>").unwrap()];
+        assert_eq!(
+            main,
+            "<main>\n\
+             <details open><summary class=\"path\">root</summary>\n\
+             <details><summary class=\"path\">a</summary>\n\
+             <div class=\"leaf selected\" id=\"selected\">&lt;b&gt;</div>\n\
+             </details>\n\
+             <div class=\"leaf\">c</div>\n\
+             </details>\n"
+        );
+        assert!(html.contains("<p class=\"breadcrumb\">a &gt; &lt;b&gt;</p>"));
+        assert!(!html.contains("class=\"warning\""));
+    }
+
+    #[test]
+    fn test_css_colors() {
+        assert_eq!(css_color(Color::Rgb(1, 2, 255)).unwrap(), "#0102ff");
+        assert_eq!(css_color(Color::Indexed(9)).unwrap(), "#ff0000");
+        assert_eq!(css_color(Color::Indexed(196)).unwrap(), "#ff0000");
+        assert_eq!(css_color(Color::Indexed(232)).unwrap(), "#080808");
+        assert_eq!(css_color(Color::Reset), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Rows of the tree as text, for `xtv --print` and for copying or writing
+//! the rows of the viewer with `yt`, `pt` and `w`.
+//!
+//! [`TreeView`](crate::ui::tree_view::TreeView) builds the rows with the same
+//! labels, values, child counts and previews as on screen; this module lays
+//! them out. Either like the viewer with its expander and type icons, or
+//! without decoration for pasting elsewhere: indented by two spaces a level,
+//! or drawn with `├──` and `└──` guides. Colors are written as ANSI escape
+//! codes only when asked for.
+
+use crossterm::queue;
+use crossterm::style::{
+    Attribute, Color as TermColor, Print, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use ratatui::style::Modifier;
+use ratatui::text::Span;
+use std::io::{self, Write};
+
+/// How rows show their depth
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextLayout {
+    /// Indented like in the viewer, with expander and type icons
+    #[default]
+    Viewer,
+    /// Indented by two spaces a level, without icons
+    Plain,
+    /// Drawn with `├──`, `│` and `└──` guides, without icons
+    Guides,
+}
+
+impl TextLayout {
+    /// Whether rows start with the viewer's indentation and icons
+    pub fn decorated(self) -> bool {
+        self == TextLayout::Viewer
+    }
+}
+
+/// How the rows are written
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextOptions {
+    pub layout: TextLayout,
+    /// Write long values whole instead of cutting them like the viewer does
+    pub full_values: bool,
+    /// Write the theme's colors as ANSI escape codes
+    pub color: bool,
+}
+
+/// A row of the tree and its depth
+#[derive(Debug, Clone)]
+pub struct TextRow {
+    pub depth: usize,
+    pub spans: Vec<Span<'static>>,
+}
+
+/// Write `rows` to `out`, one per line
+pub fn write_rows(out: &mut impl Write, rows: &[TextRow], options: &TextOptions) -> io::Result<()> {
+    for (row, prefix) in rows.iter().zip(prefixes(rows, options.layout)) {
+        write!(out, "{}", prefix)?;
+        for span in &row.spans {
+            write_span(out, span, options.color)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// `rows` as text without colors, one per line
+pub fn rows_to_string(rows: &[TextRow], layout: TextLayout) -> String {
+    let mut out = Vec::new();
+    let options = TextOptions {
+        layout,
+        ..TextOptions::default()
+    };
+    // Writing to memory doesn't fail
+    let _ = write_rows(&mut out, rows, &options);
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// What goes before each row: nothing for decorated rows, which bring their
+/// own indentation, otherwise spaces or guides
+fn prefixes(rows: &[TextRow], layout: TextLayout) -> Vec<String> {
+    match layout {
+        TextLayout::Viewer => vec![String::new(); rows.len()],
+        TextLayout::Plain => rows.iter().map(|row| "  ".repeat(row.depth)).collect(),
+        TextLayout::Guides => guides(rows),
+    }
+}
+
+/// Guides drawn from the depths of the rows alone, so that they work for any
+/// rows in depth-first order, such as the visible rows with collapsed nodes.
+///
+/// Going from the last row up, `later[d]` tells whether a row of depth `d`
+/// follows before the next shallower row, i.e. whether the current node or
+/// its ancestor at depth `d` has another sibling below.
+fn guides(rows: &[TextRow]) -> Vec<String> {
+    let mut later: Vec<bool> = Vec::new();
+    let mut prefixes: Vec<String> = rows
+        .iter()
+        .rev()
+        .map(|row| {
+            let depth = row.depth;
+            later.resize(depth + 1, false);
+            let mut prefix = String::new();
+            if depth > 0 {
+                for &more in &later[1..depth] {
+                    prefix.push_str(if more { "│   " } else { "    " });
+                }
+                prefix.push_str(if later[depth] {
+                    "├── "
+                } else {
+                    "└── "
+                });
+            }
+            later[depth] = true;
+            prefix
+        })
+        .collect();
+    prefixes.reverse();
+    prefixes
+}
+
+fn write_span(out: &mut impl Write, span: &Span, color: bool) -> io::Result<()> {
+    let style = span.style;
+    let styled =
+        color && (style.fg.is_some() || style.bg.is_some() || !style.add_modifier.is_empty());
+    if !styled {
+        return write!(out, "{}", span.content);
+    }
+    if let Some(fg) = style.fg {
+        queue!(out, SetForegroundColor(TermColor::from(fg)))?;
+    }
+    if let Some(bg) = style.bg {
+        queue!(out, SetBackgroundColor(TermColor::from(bg)))?;
+    }
+    if style.add_modifier.contains(Modifier::BOLD) {
+        queue!(out, SetAttribute(Attribute::Bold))?;
+    }
+    if style.add_modifier.contains(Modifier::DIM) {
+        queue!(out, SetAttribute(Attribute::Dim))?;
+    }
+    if style.add_modifier.contains(Modifier::UNDERLINED) {
+        queue!(out, SetAttribute(Attribute::Underlined))?;
+    }
+    queue!(out, Print(&span.content), SetAttribute(Attribute::Reset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[(usize, &str)]) -> Vec<TextRow> {
+        rows.iter()
+            .map(|&(depth, label)| TextRow {
+                depth,
+                spans: vec![Span::raw(label.to_string())],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_guides_follow_depths() {
+        let rows = rows(&[
+            (0, "root"),
+            (1, "a"),
+            (2, "b"),
+            (3, "c"),
+            (2, "d"),
+            (1, "e"),
+            (2, "f"),
+        ]);
+        assert_eq!(
+            rows_to_string(&rows, TextLayout::Guides),
+            "root\n├── a\n│   ├── b\n│   │   └── c\n│   └── d\n└── e\n    └── f\n"
+        );
+        assert_eq!(
+            rows_to_string(&rows, TextLayout::Plain),
+            "root\n  a\n    b\n      c\n    d\n  e\n    f\n"
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Table of the objects of an array, opened with `T`.
+//!
+//! Each object is a row and each key most of them share is a column (see
+//! [`crate::tree::table`]). j/k move between rows and h/l between columns,
+//! which scroll sideways when they don't all fit next to the row labels. `s`
+//! sorts by the selected column, and again in reverse; Enter jumps to the
+//! row's object in the tree.
+
+use crate::tree::table::Table;
+use crate::ui::theme::Theme;
+use crate::ui::tree_view::{format_thousands, truncate_to_width};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, Paragraph,
+        block::{Position, Title},
+    },
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Widest a column gets, however long its values
+const MAX_COLUMN_WIDTH: usize = 30;
+
+/// Columns between cells
+const COLUMN_GAP: usize = 2;
+
+/// Table popup size as a percentage of the screen
+const TABLE_POPUP_PERCENT: u16 = 90;
+
+/// What the app should do after a key in the table
+#[derive(Debug, PartialEq, Eq)]
+pub enum TableAction {
+    None,
+    Close,
+    /// Reveal and select this node
+    Jump(usize),
+}
+
+/// State of the table popup
+pub struct TableView {
+    /// Label of the array
+    title: String,
+    table: Table,
+    /// Width of each column from its header and cells
+    widths: Vec<usize>,
+    /// Width of the row labels
+    label_width: usize,
+    /// Rows in the order shown
+    order: Vec<usize>,
+    /// Column sorted by and whether descending
+    sort: Option<(usize, bool)>,
+    /// Selected position in `order`
+    selected: usize,
+    column: usize,
+    /// First column and row shown
+    first_column: usize,
+    first_row: usize,
+    /// Rows shown as last drawn
+    page: usize,
+}
+
+impl TableView {
+    pub fn new(title: impl Into<String>, table: Table) -> Self {
+        // Headers leave room for the sort arrow
+        let widths = table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let cells = table
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.cells[index].as_deref())
+                    .map(UnicodeWidthStr::width);
+                cells
+                    .chain([column.width() + 2])
+                    .max()
+                    .unwrap_or(0)
+                    .min(MAX_COLUMN_WIDTH)
+            })
+            .collect();
+        let label_width = table
+            .rows
+            .iter()
+            .map(|row| row.label.width())
+            .max()
+            .unwrap_or(0)
+            .min(MAX_COLUMN_WIDTH);
+        Self {
+            title: title.into(),
+            order: (0..table.rows.len()).collect(),
+            table,
+            widths,
+            label_width,
+            sort: None,
+            selected: 0,
+            column: 0,
+            first_column: 0,
+            first_row: 0,
+            page: 1,
+        }
+    }
+
+    /// Select the row of `node_id`, if it has one
+    pub fn select_node(&mut self, node_id: usize) {
+        if let Some(position) = self
+            .order
+            .iter()
+            .position(|&row| self.table.rows[row].node_id == node_id)
+        {
+            self.selected = position;
+        }
+    }
+
+    /// The node of the selected row
+    pub fn selected_node(&self) -> Option<usize> {
+        let row = *self.order.get(self.selected)?;
+        Some(self.table.rows[row].node_id)
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> TableAction {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let last = self.order.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'T') => return TableAction::Close,
+            KeyCode::Enter => {
+                return match self.selected_node() {
+                    Some(node_id) => TableAction::Jump(node_id),
+                    None => TableAction::Close,
+                };
+            }
+            KeyCode::Char('f') if control => self.move_by(self.page as isize),
+            KeyCode::Char('b') if control => self.move_by(-(self.page as isize)),
+            KeyCode::Char('d') if control => self.move_by(self.page as isize / 2),
+            KeyCode::Char('u') if control => self.move_by(-(self.page as isize) / 2),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(self.page as isize),
+            KeyCode::PageUp => self.move_by(-(self.page as isize)),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = last,
+            KeyCode::Left | KeyCode::Char('h') => self.column = self.column.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') if self.column + 1 < self.widths.len() => {
+                self.column += 1;
+            }
+            KeyCode::Char('s') => self.sort_by_column(),
+            _ => {}
+        }
+        TableAction::None
+    }
+
+    fn move_by(&mut self, rows: isize) {
+        let last = self.order.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(rows).min(last);
+    }
+
+    /// Sort by the selected column, or reverse the order if it is sorted by
+    /// it already, keeping the selected row selected
+    fn sort_by_column(&mut self) {
+        if self.widths.is_empty() {
+            return;
+        }
+        let descending = self.sort == Some((self.column, false));
+        let row = self.order.get(self.selected).copied();
+        self.order = self.table.sorted_rows(self.column, descending);
+        self.sort = Some((self.column, descending));
+        self.selected = row
+            .and_then(|row| self.order.iter().position(|&r| r == row))
+            .unwrap_or(0);
+    }
+
+    /// Scroll so that the selected row and column are shown in an area of
+    /// `width` by `rows` cells for the columns
+    fn scroll_to_selection(&mut self, width: usize, rows: usize) {
+        self.page = rows.max(1);
+        if self.selected < self.first_row {
+            self.first_row = self.selected;
+        } else if self.selected >= self.first_row + self.page {
+            self.first_row = self.selected + 1 - self.page;
+        }
+
+        let mut first = self.first_column.min(self.column);
+        let span = |first: usize| -> usize {
+            self.widths[first..=self.column].iter().sum::<usize>()
+                + COLUMN_GAP * (self.column - first)
+        };
+        while first < self.column && span(first) > width {
+            first += 1;
+        }
+        self.first_column = first;
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, theme: &Theme) {
+        let area = frame.size();
+        let width = area.width * TABLE_POPUP_PERCENT / 100;
+        let height = area.height * TABLE_POPUP_PERCENT / 100;
+        let popup_area = Rect {
+            x: (area.width - width) / 2,
+            y: (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let more = if self.table.truncated { "+" } else { "" };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                " {} ({}{} rows) ",
+                self.title,
+                format_thousands(self.table.rows.len()),
+                more
+            ))
+            .title_alignment(Alignment::Center)
+            .title(
+                Title::from(" j/k: Rows | h/l: Columns | s: Sort | Enter: Jump | q: Close ")
+                    .position(Position::Bottom),
+            )
+            .style(Style::default().fg(theme.popup_fg).bg(theme.popup_bg));
+        let inner = block.inner(popup_area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(block, popup_area);
+        if inner.height == 0 {
+            return;
+        }
+
+        let columns_width = (inner.width as usize).saturating_sub(self.label_width + COLUMN_GAP);
+        self.scroll_to_selection(columns_width, inner.height as usize - 1);
+
+        // The columns that fit from the first one shown, the last maybe cut
+        let mut shown = Vec::new();
+        let mut used = 0;
+        for (index, &column_width) in self.widths.iter().enumerate().skip(self.first_column) {
+            let left = columns_width.saturating_sub(used);
+            if left == 0 {
+                break;
+            }
+            shown.push((index, column_width.min(left)));
+            used += column_width + COLUMN_GAP;
+        }
+
+        let gap = " ".repeat(COLUMN_GAP);
+        let heading = Style::default().fg(theme.key).add_modifier(Modifier::BOLD);
+        let mut header = vec![
+            Span::raw(pad("", self.label_width, false)),
+            Span::raw(gap.clone()),
+        ];
+        for &(index, column_width) in &shown {
+            let arrow = match self.sort {
+                Some((sorted, false)) if sorted == index => " ▲",
+                Some((sorted, true)) if sorted == index => " ▼",
+                _ => "",
+            };
+            let name = format!("{}{}", self.table.columns[index], arrow);
+            let style = if index == self.column {
+                heading.add_modifier(Modifier::REVERSED)
+            } else {
+                heading
+            };
+            header.push(Span::styled(pad(&name, column_width, false), style));
+            header.push(Span::raw(gap.clone()));
+        }
+        let mut lines = vec![Line::from(header)];
+
+        let label_style = Style::default().fg(theme.key);
+        let value_style = Style::default().fg(theme.value);
+        for (position, &row) in self
+            .order
+            .iter()
+            .enumerate()
+            .skip(self.first_row)
+            .take(self.page)
+        {
+            let row = &self.table.rows[row];
+            let mut spans = vec![
+                Span::styled(pad(&row.label, self.label_width, false), label_style),
+                Span::raw(gap.clone()),
+            ];
+            for &(index, column_width) in &shown {
+                let cell = row.cells[index].as_deref().unwrap_or("");
+                let number = cell.trim().parse::<f64>().is_ok();
+                spans.push(Span::styled(pad(cell, column_width, number), value_style));
+                spans.push(Span::raw(gap.clone()));
+            }
+            let mut line = Line::from(spans);
+            if position == self.selected {
+                line = line.style(theme.selection());
+            }
+            lines.push(line);
+        }
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+}
+
+/// `text` on one line, cut or padded to `width` columns, aligned right if
+/// `right`
+fn pad(text: &str, width: usize, right: bool) -> String {
+    let text = truncate_to_width(&text.replace(['\n', '\r', '\t'], " "), width);
+    let fill = " ".repeat(width.saturating_sub(text.width()));
+    if right {
+        format!("{}{}", fill, text)
+    } else {
+        format!("{}{}", text, fill)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, json::JsonParser};
+    use crate::tree::TreeVariant;
+    use crate::tree::table::extract_table;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    fn view(json: &str) -> TableView {
+        let tree = TreeVariant::from(JsonParser.parse(json).unwrap());
+        TableView::new("root", extract_table(&tree, tree.root_id()).unwrap())
+    }
+
+    fn press(view: &mut TableView, keys: &str) -> TableAction {
+        let mut action = TableAction::None;
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                c => KeyCode::Char(c),
+            };
+            action = view.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+        action
+    }
+
+    fn screen(view: &mut TableView, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal
+            .draw(|frame| view.render(frame, &Theme::default()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sorts_and_jumps_to_rows() {
+        let mut table = view(r#"[{"a": 10, "b": "x"}, {"a": 9, "b": "z"}, {"b": "y"}]"#);
+        assert_eq!(table.widths, [3, 3]);
+        // Sorting keeps the selected row, the second
+        press(&mut table, "js");
+        assert_eq!(table.order, [1, 0, 2]);
+        assert_eq!(table.selected, 0);
+        press(&mut table, "s");
+        assert_eq!(table.order, [0, 1, 2]);
+        assert_eq!(table.sort, Some((0, true)));
+
+        press(&mut table, "lsG");
+        assert_eq!(table.order, [0, 2, 1]);
+        let last = table.table.rows[1].node_id;
+        assert_eq!(press(&mut table, "\n"), TableAction::Jump(last));
+        assert_eq!(press(&mut table, "q"), TableAction::Close);
+    }
+
+    #[test]
+    fn test_scrolls_to_the_selected_column() {
+        let mut table = view(r#"[{"first": "aaaaaaaa", "second": "bbbbbbbb", "third": 3}]"#);
+        let lines = screen(&mut table, 30, 8);
+        assert!(lines[1].contains("first"), "{:?}", lines);
+        assert!(!lines[1].contains("third"), "{:?}", lines);
+
+        press(&mut table, "ll");
+        let lines = screen(&mut table, 30, 8);
+        assert!(lines[1].contains("third"), "{:?}", lines);
+        assert!(!lines[1].contains("first"), "{:?}", lines);
+        // Row labels stay, numbers are right-aligned
+        assert!(lines[2].contains("[0]"), "{:?}", lines);
+        assert!(lines[2].contains("    3"), "{:?}", lines);
+    }
+}
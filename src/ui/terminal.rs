@@ -0,0 +1,110 @@
+//! Entering and leaving the terminal's raw mode and alternate screen.
+//!
+//! The terminal must be restored on every exit path: normal return, early
+//! errors during setup, and panics. Otherwise the user's shell is left in raw
+//! mode with the alternate screen still active. The same goes for Ctrl-Z,
+//! which hands the terminal back to the shell until the user runs `fg`.
+
+use crate::error::{Result, XtvError};
+use crate::interrupt;
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use std::io;
+use std::sync::Once;
+
+/// Restores the terminal when dropped.
+///
+/// Created by [`TerminalGuard::enter`], which also installs a panic hook so
+/// that panic messages are printed to the normal screen.
+pub struct TerminalGuard {
+    _private: (),
+}
+
+impl TerminalGuard {
+    /// Switch to raw mode and the alternate screen, capturing the mouse if asked.
+    ///
+    /// If entering the alternate screen fails, raw mode is disabled again
+    /// before the error is returned.
+    pub fn enter(mouse: bool) -> Result<Self> {
+        install_panic_hook();
+        // Signals would end the process without restoring the terminal
+        interrupt::install();
+
+        enable_raw_mode().map_err(|e| XtvError::Tui(e.to_string()))?;
+        // From here on, dropping the guard undoes whatever was set up
+        let guard = Self { _private: () };
+        enter_screen(mouse)?;
+        Ok(guard)
+    }
+
+    /// Stop the process group like Ctrl-Z in a shell, with the terminal
+    /// restored while stopped.
+    ///
+    /// Returns once the shell continues the process, after taking over the
+    /// terminal again. The caller should redraw everything, since the shell
+    /// drew over the screen and the terminal may have been resized. Does
+    /// nothing on platforms without job control.
+    pub fn suspend(&self, mouse: bool) -> Result<()> {
+        #[cfg(unix)]
+        {
+            restore();
+            // Stops here until SIGCONT
+            // SAFETY: kill has no memory safety requirements
+            if unsafe { libc::kill(0, libc::SIGTSTP) } != 0 {
+                log::warn!("Cannot suspend: {}", io::Error::last_os_error());
+            }
+            enable_raw_mode().map_err(|e| XtvError::Tui(e.to_string()))?;
+            enter_screen(mouse)?;
+        }
+        #[cfg(not(unix))]
+        let _ = mouse;
+        Ok(())
+    }
+}
+
+/// Enter the alternate screen and capture the mouse if asked
+fn enter_screen(mouse: bool) -> Result<()> {
+    execute!(io::stdout(), EnterAlternateScreen).map_err(|e| XtvError::Tui(e.to_string()))?;
+    if mouse {
+        execute!(io::stdout(), EnableMouseCapture).map_err(|e| XtvError::Tui(e.to_string()))?;
+    }
+    Ok(())
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Release the mouse, leave the alternate screen and raw mode, ignoring errors.
+///
+/// Safe to call more than once.
+fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        LeaveAlternateScreen,
+        Show
+    );
+}
+
+/// Restore the terminal before the default panic message is printed.
+///
+/// The previous hook is kept and called afterwards, so panic output and
+/// backtraces still appear. Installed only once per process.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            previous(info);
+        }));
+    });
+}
@@ -0,0 +1,367 @@
+//! Colors used by all widgets.
+//!
+//! A [`Theme`] starts from a built-in palette selected by `ui.theme` and then
+//! applies per-slot overrides from `[ui.colors]`:
+//!
+//! ```toml
+//! [ui]
+//! theme = "light"
+//!
+//! [ui.colors]
+//! label = "blue"
+//! selection_bg = "#d0d0d0"
+//! ```
+//!
+//! Colors are standard names (`"cyan"`, `"dark gray"`, `"light red"`), 256-color
+//! indices (`"136"`) or hex values (`"#rrggbb"`).
+//!
+//! With `ui.color = "never"`, `--color never` or `NO_COLOR`, every slot
+//! resolves to the terminal's default colors instead, and the selection and
+//! search matches are shown in reverse video and underlined.
+
+use crate::config::{ColorMode, UiConfig};
+use crate::error::{Result, XtvError};
+use ratatui::style::{Color, Modifier, Style};
+use std::str::FromStr;
+
+/// Named color slots used by the widgets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Node labels
+    pub label: Color,
+    /// Inline and detail values
+    pub value: Color,
+    /// Node type suffix such as `[object]`
+    pub node_type: Color,
+    /// `@attributes` virtual nodes and their icons
+    pub virtual_node: Color,
+    /// Expand/collapse icons
+    pub icon: Color,
+    /// Background of the selected row
+    pub selection_bg: Color,
+    /// Background of search matches
+    pub search_highlight: Color,
+    /// Background of the current search match
+    pub search_current: Color,
+    /// Foreground of highlighted search matches
+    pub search_fg: Color,
+    /// Path bar text
+    pub path_bar: Color,
+    /// Footer help text
+    pub footer: Color,
+    /// Section headings in popups and the detail view
+    pub heading: Color,
+    /// Attribute keys in the detail view
+    pub key: Color,
+    /// Errors and failed loads
+    pub error: Color,
+    /// Warnings such as a changed file
+    pub warning: Color,
+    /// Popup background
+    pub popup_bg: Color,
+    /// Popup text
+    pub popup_fg: Color,
+    /// Nodes only in the new file of a diff
+    pub diff_added: Color,
+    /// Nodes only in the old file of a diff
+    pub diff_removed: Color,
+    /// Nodes whose value or type differs in a diff
+    pub diff_changed: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// Palette for terminals with a dark background
+    pub fn dark() -> Self {
+        Self {
+            label: Color::Cyan,
+            value: Color::Green,
+            node_type: Color::DarkGray,
+            virtual_node: Color::Magenta,
+            icon: Color::Yellow,
+            selection_bg: Color::DarkGray,
+            search_highlight: Color::LightYellow,
+            search_current: Color::Yellow,
+            search_fg: Color::Black,
+            path_bar: Color::Gray,
+            footer: Color::Reset,
+            heading: Color::Yellow,
+            key: Color::Blue,
+            error: Color::Red,
+            warning: Color::Yellow,
+            popup_bg: Color::Black,
+            popup_fg: Color::White,
+            diff_added: Color::LightGreen,
+            diff_removed: Color::LightRed,
+            diff_changed: Color::LightYellow,
+        }
+    }
+
+    /// Palette for terminals with a light background
+    pub fn light() -> Self {
+        Self {
+            label: Color::Blue,
+            value: Color::Green,
+            node_type: Color::DarkGray,
+            virtual_node: Color::Magenta,
+            icon: Color::Indexed(136),
+            selection_bg: Color::Indexed(252),
+            search_highlight: Color::Indexed(229),
+            search_current: Color::Yellow,
+            search_fg: Color::Black,
+            path_bar: Color::DarkGray,
+            footer: Color::Reset,
+            heading: Color::Indexed(130),
+            key: Color::Blue,
+            error: Color::Red,
+            warning: Color::Indexed(130),
+            popup_bg: Color::White,
+            popup_fg: Color::Black,
+            diff_added: Color::Indexed(28),
+            diff_removed: Color::Red,
+            diff_changed: Color::Indexed(130),
+        }
+    }
+
+    /// The terminal's default colors in every slot
+    pub fn monochrome() -> Self {
+        Self {
+            label: Color::Reset,
+            value: Color::Reset,
+            node_type: Color::Reset,
+            virtual_node: Color::Reset,
+            icon: Color::Reset,
+            selection_bg: Color::Reset,
+            search_highlight: Color::Reset,
+            search_current: Color::Reset,
+            search_fg: Color::Reset,
+            path_bar: Color::Reset,
+            footer: Color::Reset,
+            heading: Color::Reset,
+            key: Color::Reset,
+            error: Color::Reset,
+            warning: Color::Reset,
+            popup_bg: Color::Reset,
+            popup_fg: Color::Reset,
+            diff_added: Color::Reset,
+            diff_removed: Color::Reset,
+            diff_changed: Color::Reset,
+        }
+    }
+
+    /// The selected row, in reverse video if it has no background color
+    pub fn selection(&self) -> Style {
+        let style = Style::default()
+            .bg(self.selection_bg)
+            .add_modifier(Modifier::BOLD);
+        if self.selection_bg == Color::Reset {
+            style.add_modifier(Modifier::REVERSED)
+        } else {
+            style
+        }
+    }
+
+    /// A search match, or the current one, underlined if it has no
+    /// background color
+    pub fn search_match(&self, current: bool) -> Style {
+        let (bg, style) = if current {
+            (
+                self.search_current,
+                Style::default().add_modifier(Modifier::BOLD),
+            )
+        } else {
+            (self.search_highlight, Style::default())
+        };
+        let style = style.fg(self.search_fg).bg(bg);
+        if bg == Color::Reset {
+            style.add_modifier(Modifier::UNDERLINED)
+        } else {
+            style
+        }
+    }
+
+    /// Resolve the theme from `ui.theme` and the `[ui.colors]` overrides.
+    ///
+    /// # Errors
+    ///
+    /// Returns a config error for an unknown theme, slot, or color.
+    pub fn from_config(ui: &UiConfig) -> Result<Self> {
+        let mut theme = match ui.theme.as_str() {
+            "dark" => Self::dark(),
+            "light" => Self::light(),
+            other => {
+                return Err(XtvError::Config(format!(
+                    "Invalid theme '{}'. Must be 'dark' or 'light'",
+                    other
+                )));
+            }
+        };
+
+        for (slot, value) in &ui.colors {
+            let color = Color::from_str(value).map_err(|_| {
+                XtvError::Config(format!(
+                    "Invalid color '{}' for ui.colors.{}: use a name like \"cyan\", \
+                     a 256-color index like \"136\", or hex like \"#00ffff\"",
+                    value, slot
+                ))
+            })?;
+            let target = theme.slot_mut(slot).ok_or_else(|| {
+                XtvError::Config(format!(
+                    "Unknown color slot '{}' in [ui.colors]. Valid slots: {}",
+                    slot,
+                    SLOTS.join(", ")
+                ))
+            })?;
+            *target = color;
+        }
+
+        // The theme and colors are checked even when they go unused
+        if ui.color == ColorMode::Never {
+            return Ok(Self::monochrome());
+        }
+        Ok(theme)
+    }
+
+    fn slot_mut(&mut self, slot: &str) -> Option<&mut Color> {
+        Some(match slot {
+            "label" => &mut self.label,
+            "value" => &mut self.value,
+            "type" => &mut self.node_type,
+            "virtual_node" => &mut self.virtual_node,
+            "icon" => &mut self.icon,
+            "selection_bg" => &mut self.selection_bg,
+            "search_highlight" => &mut self.search_highlight,
+            "search_current" => &mut self.search_current,
+            "search_fg" => &mut self.search_fg,
+            "path_bar" => &mut self.path_bar,
+            "footer" => &mut self.footer,
+            "heading" => &mut self.heading,
+            "key" => &mut self.key,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "popup_bg" => &mut self.popup_bg,
+            "popup_fg" => &mut self.popup_fg,
+            "diff_added" => &mut self.diff_added,
+            "diff_removed" => &mut self.diff_removed,
+            "diff_changed" => &mut self.diff_changed,
+            _ => return None,
+        })
+    }
+}
+
+/// Slot names accepted in `[ui.colors]`
+const SLOTS: &[&str] = &[
+    "label",
+    "value",
+    "type",
+    "virtual_node",
+    "icon",
+    "selection_bg",
+    "search_highlight",
+    "search_current",
+    "search_fg",
+    "path_bar",
+    "footer",
+    "heading",
+    "key",
+    "error",
+    "warning",
+    "popup_bg",
+    "popup_fg",
+    "diff_added",
+    "diff_removed",
+    "diff_changed",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ui(theme: &str, colors: &[(&str, &str)]) -> UiConfig {
+        UiConfig {
+            theme: theme.to_string(),
+            colors: colors
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..UiConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_builtin_palettes() {
+        assert_eq!(Theme::from_config(&ui("dark", &[])).unwrap(), Theme::dark());
+        assert_eq!(
+            Theme::from_config(&ui("light", &[])).unwrap(),
+            Theme::light()
+        );
+        assert!(Theme::from_config(&ui("solarized", &[])).is_err());
+    }
+
+    #[test]
+    fn test_overrides_by_name_index_and_hex() {
+        let theme = Theme::from_config(&ui(
+            "light",
+            &[
+                ("label", "light red"),
+                ("type", "240"),
+                ("path_bar", "#102030"),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(theme.label, Color::LightRed);
+        assert_eq!(theme.node_type, Color::Indexed(240));
+        assert_eq!(theme.path_bar, Color::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(theme.value, Theme::light().value);
+    }
+
+    #[test]
+    fn test_invalid_color_and_slot_are_rejected() {
+        let err = Theme::from_config(&ui("dark", &[("label", "blurple")])).unwrap_err();
+        assert!(err.to_string().contains("ui.colors.label"));
+
+        let err = Theme::from_config(&ui("dark", &[("labels", "red")])).unwrap_err();
+        assert!(err.to_string().contains("Unknown color slot 'labels'"));
+    }
+
+    #[test]
+    fn test_colors_can_be_turned_off() {
+        let mut config = ui("light", &[("label", "red")]);
+        config.color = ColorMode::Never;
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme, Theme::monochrome());
+        assert!(theme.selection().add_modifier.contains(Modifier::REVERSED));
+        assert!(
+            theme
+                .search_match(false)
+                .add_modifier
+                .contains(Modifier::UNDERLINED)
+        );
+
+        // Colors stay on otherwise, even if not on a terminal
+        config.color = ColorMode::Auto;
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.label, Color::Red);
+        assert!(!theme.selection().add_modifier.contains(Modifier::REVERSED));
+
+        config
+            .colors
+            .insert("label".to_string(), "blurple".to_string());
+        config.color = ColorMode::Never;
+        assert!(Theme::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_every_slot_is_settable() {
+        let mut theme = Theme::dark();
+        for slot in SLOTS {
+            assert!(theme.slot_mut(slot).is_some(), "{slot}");
+        }
+    }
+}
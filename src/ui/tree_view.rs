@@ -1,522 +1,1767 @@
-use crate::tree::TreeVariant;
+use crate::config::{IconSet, LineNumbers};
+use crate::error::XtvError;
+use crate::parser::ldif::schema::AttributeLabels;
+use crate::tree::diff::Change;
+use crate::tree::{Order, TreeLike, TreeNode};
+use crate::ui::icons::Expander;
+use crate::ui::pattern::{SearchField, SearchPattern};
+use crate::ui::redact::{MASK, Redaction};
+use crate::ui::render_html::HtmlRow;
+use crate::ui::render_text::{TextLayout, TextOptions, TextRow};
+use crate::ui::theme::Theme;
 use ratatui::{
     Frame,
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{List, ListItem, ListState},
+    widgets::{List, ListItem, ListState, Paragraph},
 };
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use unicode_width::UnicodeWidthChar;
+
+mod paging;
+mod viewport;
+
+use paging::Page;
+use viewport::{aligned_offset, viewport_offset};
+
+/// The set of expanded node IDs, versioned so the visible node list is only
+/// rebuilt after the set actually changes.
+///
+/// Leaves with a multi-line value are expanded to show its lines.
+#[derive(Debug, Default)]
+struct ExpandedSet {
+    ids: HashSet<usize>,
+    /// Expanded pages of large arrays, as array ID and page number
+    pages: HashSet<(usize, usize)>,
+    version: u64,
+}
+
+impl ExpandedSet {
+    fn contains(&self, id: &usize) -> bool {
+        self.ids.contains(id)
+    }
+
+    fn insert(&mut self, id: usize) {
+        if self.ids.insert(id) {
+            self.version += 1;
+        }
+    }
+
+    fn remove(&mut self, id: &usize) {
+        if self.ids.remove(id) {
+            self.version += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        if !self.ids.is_empty() || !self.pages.is_empty() {
+            self.ids.clear();
+            self.pages.clear();
+            self.version += 1;
+        }
+    }
+
+    /// Replace the set with one saved earlier
+    fn restore(&mut self, ids: HashSet<usize>) {
+        self.ids = ids;
+        self.version += 1;
+    }
+}
+
+/// Symbol drawn in front of the selected row; every row is indented by its width
+const HIGHLIGHT_SYMBOL: &str = ">> ";
+
+/// Children peeked at for an inline preview, so streaming trees only load a few
+const PREVIEW_MAX_CHILDREN: usize = 5;
+
+/// Columns of an attribute value or text content shown after the label in
+/// rows without a width limit, such as those copied with `yt`
+const VALUE_MAX_COLUMNS: usize = 40;
+
+/// Columns a value keeps at least when the row is too narrow for it, taken
+/// from a long label
+const VALUE_MIN_COLUMNS: usize = 10;
+
+/// Columns a label keeps at least, however narrow the row
+const LABEL_MIN_COLUMNS: usize = 8;
+
+/// Lines of a multi-line value shown below its row at most; a last row
+/// counts the rest
+const MAX_VALUE_LINES: usize = 1000;
+
+/// The row under a screen position, as found by [`TreeView::hit_test`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowHit {
+    /// Index into the visible rows
+    pub index: usize,
+    pub node_id: usize,
+    /// Whether the position is on the expand/collapse icon
+    pub on_icon: bool,
+}
+
+/// Where to place the selected row in the viewport on the next render
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// Search state the tree is drawn with
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchHighlight<'a> {
+    /// Rows matching the search
+    pub matches: &'a [usize],
+    /// Index of the focused match in `matches`
+    pub current: Option<usize>,
+    /// Query whose occurrences are highlighted within matching rows
+    pub pattern: Option<&'a SearchPattern>,
+}
+
+/// Per-row state used to draw one list item
+struct RowInfo {
+    node_id: usize,
+    depth: usize,
+    is_match: bool,
+    is_current_match: bool,
+    /// Child count shown after the label of a collapsed node
+    hidden_children: Option<usize>,
+    /// Inline preview of a collapsed object or array
+    preview: Option<String>,
+    /// `key="value"` summary of attributes whose `@attributes` row is hidden
+    inline_attributes: Option<String>,
+    /// Whether the node has no rows to show below it, e.g. when its only
+    /// child is its hidden `@attributes` node
+    leaf: bool,
+    /// Columns available for the row, excluding the highlight symbol
+    width: usize,
+    /// Whether the whole value is shown, to be wrapped over several lines
+    wrap: bool,
+    /// Whether the row starts with its indentation and icons, which rows
+    /// written as text may leave out
+    decorated: bool,
+    /// Whether the node's value is masked
+    redacted: bool,
+}
 
 pub struct TreeView {
-    expanded: HashSet<usize>,
+    expanded: ExpandedSet,
+    show_child_counts: bool,
+    show_previews: bool,
+    inline_attributes: bool,
+    line_numbers: LineNumbers,
+    icons: IconSet,
+    /// Values at least this many bytes long show their size, 0 for none
+    size_hint_threshold: usize,
+    /// Friendly names shown after the keys of LDIF attributes
+    attribute_labels: Option<AttributeLabels>,
+    /// Keys whose values are masked, while redaction is on
+    redaction: Option<Redaction>,
+    scrolloff: usize,
+    /// Requested placement of the selection, applied on the next render
+    align: Option<Align>,
+    /// Nodes left visible by a search filter: the matches and their ancestors
+    filter: Option<HashSet<usize>>,
+    /// Expanded nodes from before the filter was applied
+    unfiltered: Option<HashSet<usize>>,
+    /// Nodes left out of the rows along with their subtrees
+    hidden: HashSet<usize>,
     visible_nodes: Vec<(usize, usize)>, // (node_id, depth)
+    /// Page rows by row index; their entry in `visible_nodes` holds the
+    /// array's ID
+    pages: HashMap<usize, Page>,
+    /// Rows showing a line of an expanded multi-line value, by row index,
+    /// with the number of the line; their entry in `visible_nodes` holds the
+    /// leaf's ID. Line [`MAX_VALUE_LINES`] counts the lines not shown.
+    value_lines: HashMap<usize, usize>,
+    /// Arrays with more elements than this are shown in pages, 0 for none
+    array_page_size: usize,
+    /// Arrays whose paging was turned off for them
+    unpaged: HashSet<usize>,
+    /// Expansion version and node count `visible_nodes` was built for
+    built_for: Option<(u64, usize)>,
+    rebuild_count: usize,
     list_state: ListState,
+    /// Columns scrolled off the left edge of every row
+    horizontal_scroll: usize,
+    /// Whether the selected row wraps instead of being cut at the right edge
+    wrap_selected: bool,
+    /// Columns available for rows as of the last render
+    row_width: usize,
+    /// How nodes differ when showing a diff
+    changes: HashMap<usize, Change>,
 }
 
 impl TreeView {
     pub fn new(root_id: usize) -> Self {
-        let mut expanded = HashSet::new();
+        let mut expanded = ExpandedSet::default();
         expanded.insert(root_id); // Root is expanded by default
 
         let mut view = Self {
             expanded,
+            show_child_counts: true,
+            show_previews: true,
+            inline_attributes: false,
+            line_numbers: LineNumbers::Off,
+            icons: IconSet::Unicode,
+            size_hint_threshold: 0,
+            attribute_labels: None,
+            redaction: None,
+            scrolloff: 0,
+            align: None,
+            filter: None,
+            unfiltered: None,
+            hidden: HashSet::new(),
             visible_nodes: Vec::new(),
+            pages: HashMap::new(),
+            value_lines: HashMap::new(),
+            array_page_size: 0,
+            unpaged: HashSet::new(),
+            built_for: None,
+            rebuild_count: 0,
             list_state: ListState::default(),
+            horizontal_scroll: 0,
+            wrap_selected: false,
+            row_width: 0,
+            changes: HashMap::new(),
         };
 
         view.list_state.select(Some(0));
         view
     }
 
-    pub fn render(
+    /// Expand every node less than `depth` levels below the root.
+    ///
+    /// `None` expands the whole tree. The walk is breadth-first, so if it stops
+    /// after `max_nodes` expansions the top levels are complete and only the
+    /// deepest expanded level is partial.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the walk stopped early because of `max_nodes`
+    pub fn expand_to_depth(
         &mut self,
-        frame: &mut Frame,
-        area: Rect,
-        tree: &TreeVariant,
-        search_matches: &[usize],
-        current_match_index: Option<usize>,
-    ) {
-        // Rebuild visible nodes list
-        self.rebuild_visible_nodes(tree);
-
-        // Get current match node ID if any
-        let current_match_id = current_match_index.and_then(|idx| search_matches.get(idx).copied());
-
-        // Create list items - collect the visible nodes data first to avoid borrow issues
-        let visible_nodes_copy = self.visible_nodes.clone();
-        let items: Vec<ListItem> = visible_nodes_copy
-            .iter()
-            .map(|(node_id, depth)| {
-                let node = tree.get_node(*node_id).unwrap();
-                let is_match = search_matches.contains(node_id);
-                let is_current_match = Some(*node_id) == current_match_id;
-                self.create_list_item(node, *depth, *node_id, is_match, is_current_match)
-            })
-            .collect();
-
-        let list = List::new(items)
-            .highlight_style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">> ");
-
-        frame.render_stateful_widget(list, area, &mut self.list_state);
-    }
+        tree: &dyn TreeLike,
+        depth: Option<usize>,
+        max_nodes: Option<usize>,
+    ) -> bool {
+        let mut expanded = 0;
 
-    fn create_list_item(
-        &self,
-        node: crate::tree::TreeNode,
-        depth: usize,
-        node_id: usize,
-        is_match: bool,
-        is_current_match: bool,
-    ) -> ListItem<'static> {
-        let indent = "  ".repeat(depth);
-        let icon = if node.is_virtual_attributes() {
-            // Virtual attribute nodes get hollow/solid triangle
-            if self.expanded.contains(&node_id) {
-                "▽" // Hollow triangle down when expanded
-            } else {
-                "▷" // Hollow triangle right when collapsed
+        for (node_id, level) in tree.walk(tree.root_id(), Order::BreadthFirst) {
+            // Breadth-first, so every node after this one is at least as deep
+            if depth.is_some_and(|depth| level >= depth) {
+                break;
             }
-        } else if node.has_children() {
-            if self.expanded.contains(&node_id) {
-                "▼"
-            } else {
-                "▶"
-            }
-        } else {
-            " "
-        };
-
-        // Create display text
-        let mut spans = vec![Span::raw(indent)];
-
-        // Icon with special color for virtual nodes
-        let icon_color = if node.is_virtual_attributes() {
-            Color::Magenta
-        } else {
-            Color::Yellow
-        };
-        spans.push(Span::styled(icon, Style::default().fg(icon_color)));
-        spans.push(Span::raw(" "));
-
-        // Label with highlighting for search matches
-        let label_style = if is_current_match {
-            // Current match: bright yellow background
-            Style::default()
-                .fg(Color::Black)
-                .bg(Color::Yellow)
-                .add_modifier(Modifier::BOLD)
-        } else if is_match {
-            // Other matches: dimmer highlight
-            Style::default().fg(Color::Black).bg(Color::LightYellow)
-        } else if node.is_virtual_attributes() {
-            Style::default().fg(Color::Magenta)
-        } else {
-            Style::default().fg(Color::Cyan)
-        };
-        spans.push(Span::styled(node.label.clone(), label_style));
-
-        // For attribute nodes, show key: value (no type bracket)
-        // For text/comment nodes, show label: content
-        // For regular nodes, show type
-        if node.is_attribute() {
-            if let Some(attr) = node.attributes.first() {
-                let value = if attr.value.len() > 40 {
-                    format!(": {}...", &attr.value[..40])
-                } else {
-                    format!(": {}", attr.value)
-                };
-                spans.push(Span::styled(value, Style::default().fg(Color::Green)));
+            if !has_children(tree, node_id) {
+                continue;
             }
-        } else if node.node_type == "text" || node.node_type == "comment" {
-            // Show content inline for text and comment nodes
-            if let Some(content_attr) = node.attributes.iter().find(|a| a.key == "content") {
-                let value = if content_attr.value.len() > 40 {
-                    format!(": {}...", &content_attr.value[..40])
-                } else {
-                    format!(": {}", content_attr.value)
-                };
-                spans.push(Span::styled(value, Style::default().fg(Color::Green)));
+            if max_nodes.is_some_and(|max| expanded >= max) {
+                return false;
             }
-        } else {
-            // Only show node type for regular nodes
-            spans.push(Span::raw(" "));
-            spans.push(Span::styled(
-                format!("[{}]", node.node_type),
-                Style::default().fg(Color::DarkGray),
-            ));
+
+            self.expanded.insert(node_id);
+            expanded += 1;
         }
+        true
+    }
 
-        ListItem::new(Line::from(spans))
+    /// Mark nodes as added, removed or changed, as when showing a diff
+    pub fn set_changes(&mut self, changes: HashMap<usize, Change>) {
+        self.changes = changes;
     }
 
-    fn rebuild_visible_nodes(&mut self, tree: &TreeVariant) {
-        self.visible_nodes.clear();
-        self.collect_visible_nodes(tree, tree.root_id(), 0);
+    /// Show `(n)` after collapsed nodes to hint at how many children they hide
+    pub fn set_show_child_counts(&mut self, show: bool) {
+        self.show_child_counts = show;
     }
 
-    fn collect_visible_nodes(&mut self, tree: &TreeVariant, node_id: usize, depth: usize) {
-        self.visible_nodes.push((node_id, depth));
+    /// Show a one-line preview of the first few children after collapsed
+    /// objects and arrays, e.g. `{name: "Alice", age: 30, …}`
+    pub fn set_show_previews(&mut self, show: bool) {
+        self.show_previews = show;
+    }
 
-        // If expanded, add children
-        if self.expanded.contains(&node_id) {
-            let children = tree.get_children(node_id);
-            for child_id in children {
-                self.collect_visible_nodes(tree, child_id, depth + 1);
-            }
+    /// Hide `@attributes` rows and summarize their attributes on the parent row
+    pub fn set_inline_attributes(&mut self, inline: bool) {
+        if self.inline_attributes != inline {
+            self.inline_attributes = inline;
+            self.built_for = None;
         }
     }
 
-    pub fn navigate_up(&mut self) {
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i > 0 {
-                    i - 1
-                } else {
-                    0
-                }
-            }
-            None => 0,
-        };
-        self.list_state.select(Some(i));
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.line_numbers = line_numbers;
     }
 
-    pub fn navigate_down(&mut self, tree: &TreeVariant) {
-        self.rebuild_visible_nodes(tree);
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i >= self.visible_nodes.len() - 1 {
-                    self.visible_nodes.len() - 1
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
-        };
-        self.list_state.select(Some(i));
+    /// Choose the glyphs drawn in front of labels
+    pub fn set_icons(&mut self, icons: IconSet) {
+        self.icons = icons;
     }
 
-    pub fn toggle_expand(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                let node = tree.get_node(*node_id).unwrap();
-                if node.has_children() {
-                    if self.expanded.contains(node_id) {
-                        self.expanded.remove(node_id);
-                    } else {
-                        self.expanded.insert(*node_id);
-                    }
-                }
-            }
-        }
+    /// Show the size of values of at least `bytes` after their label, e.g.
+    /// `(2.1 MB)`; 0 shows none
+    pub fn set_size_hint_threshold(&mut self, bytes: usize) {
+        self.size_hint_threshold = bytes;
     }
 
-    pub fn collapse(&mut self, _tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if self.expanded.contains(node_id) {
-                    // Collapse current node
-                    self.expanded.remove(node_id);
-                }
-            }
-        }
+    /// Show the friendly names of `labels` after the keys of attributes, for
+    /// LDIF trees; `None` shows none
+    pub fn set_attribute_labels(&mut self, labels: Option<AttributeLabels>) {
+        self.attribute_labels = labels;
     }
 
-    pub fn get_selected_node_id(&self) -> Option<usize> {
-        self.list_state
-            .selected()
-            .and_then(|index| self.visible_nodes.get(index))
-            .map(|(node_id, _)| *node_id)
+    /// Mask the values of keys matching `redaction`, or with `None` show
+    /// all values
+    pub fn set_redaction(&mut self, redaction: Option<Redaction>) {
+        self.redaction = redaction;
+        // Masked values don't expand to their lines
+        self.built_for = None;
     }
 
-    pub fn collapse_parent(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                // Find the parent of the current node
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    // Collapse the parent node
-                    self.expanded.remove(&parent_id);
-
-                    // Navigate to the parent node
-                    // Rebuild visible nodes to reflect the collapsed state
-                    self.rebuild_visible_nodes(tree);
-
-                    // Find the index of the parent in the visible nodes
-                    if let Some(parent_index) = self
-                        .visible_nodes
-                        .iter()
-                        .position(|(id, _)| *id == parent_id)
-                    {
-                        self.list_state.select(Some(parent_index));
-                    }
-                }
-            }
-        }
+    /// Whether the value of `node_id` is masked
+    fn redacts(&self, tree: &dyn TreeLike, node_id: usize) -> bool {
+        self.redaction
+            .as_ref()
+            .is_some_and(|redaction| redaction.redacts(tree, node_id))
     }
 
-    pub fn expand(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                let node = tree.get_node(*node_id).unwrap();
-                if node.has_children() {
-                    self.expanded.insert(*node_id);
-                }
-            }
-        }
+    /// Whether a value under `key` is masked, below a node that is masked
+    /// entirely if `redacted`
+    fn masks(&self, redacted: bool, key: &str) -> bool {
+        redacted
+            || self
+                .redaction
+                .as_ref()
+                .is_some_and(|redaction| redaction.matches(key))
     }
 
-    // Smart left: collapse if expanded, otherwise move to parent
-    pub fn smart_left(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                let node = tree.get_node(*node_id).unwrap();
-                if node.has_children() && self.expanded.contains(node_id) {
-                    // Collapse if expanded
-                    self.expanded.remove(node_id);
-                } else {
-                    // Move to parent
-                    self.navigate_to_parent(tree);
-                }
-            }
+    /// Collapse every node except the root.
+    ///
+    /// The selection moves to its closest ancestor that is still visible.
+    pub fn collapse_all(&mut self, tree: &dyn TreeLike) {
+        let selected = self.get_selected_node_id();
+        self.expanded.clear();
+        self.expanded.insert(tree.root_id());
+        self.select_visible_ancestor(tree, selected);
+    }
+
+    /// Expand the whole tree, or with a `depth` show exactly that many levels
+    /// below the root and collapse everything deeper.
+    ///
+    /// The selection stays on the same node, or its closest visible ancestor.
+    pub fn expand_all(&mut self, tree: &dyn TreeLike, depth: Option<usize>) {
+        let selected = self.get_selected_node_id();
+        if depth.is_some() {
+            self.expanded.clear();
+            self.expanded.insert(tree.root_id());
         }
+        self.expand_to_depth(tree, depth, None);
+        self.select_visible_ancestor(tree, selected);
     }
 
-    // Smart right: expand if collapsed, move to first child if expanded
-    pub fn smart_right(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                let node = tree.get_node(*node_id).unwrap();
-                if node.has_children() {
-                    if !self.expanded.contains(node_id) {
-                        // Expand if collapsed
-                        self.expanded.insert(*node_id);
-                    } else {
-                        // Move to first child if expanded
-                        self.rebuild_visible_nodes(tree);
-                        if index + 1 < self.visible_nodes.len() {
-                            self.list_state.select(Some(index + 1));
+    /// Show only `matches` and their ancestors, expanding the ancestors, or
+    /// with `None` show the whole tree again as it was before filtering.
+    ///
+    /// The selection stays on the same node if it is still visible.
+    pub fn set_filter(&mut self, tree: &dyn TreeLike, matches: Option<&[usize]>) {
+        let selected = self.get_selected_node_id();
+        match matches {
+            Some(matches) => {
+                if self.unfiltered.is_none() {
+                    self.unfiltered = Some(self.expanded.ids.clone());
+                }
+                let mut allowed = HashSet::new();
+                for &node_id in matches {
+                    allowed.insert(node_id);
+                    for ancestor in tree.ancestors(node_id) {
+                        self.expanded.insert(ancestor);
+                        // The rest of the chain was added with an earlier match
+                        if !allowed.insert(ancestor) {
+                            break;
                         }
                     }
                 }
+                let selected = selected.filter(|id| allowed.contains(id));
+                self.filter = Some(allowed);
+                self.built_for = None;
+                self.select_visible_ancestor(tree, selected.or(Some(tree.root_id())));
             }
-        }
-    }
-
-    // Navigate to parent without collapsing
-    pub fn navigate_to_parent(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    // Find the index of the parent in the visible nodes
-                    if let Some(parent_index) = self
-                        .visible_nodes
-                        .iter()
-                        .position(|(id, _)| *id == parent_id)
-                    {
-                        self.list_state.select(Some(parent_index));
-                    }
+            None => {
+                if self.filter.take().is_none() {
+                    return;
+                }
+                if let Some(ids) = self.unfiltered.take() {
+                    self.expanded.restore(ids);
                 }
+                self.built_for = None;
+                self.select_visible_ancestor(tree, selected);
             }
         }
     }
 
-    // Navigate to next sibling
-    pub fn navigate_to_next_sibling(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    if let Some(current_pos) = siblings.iter().position(|&id| id == *node_id) {
-                        if current_pos + 1 < siblings.len() {
-                            let next_sibling = siblings[current_pos + 1];
-                            // Find this sibling in visible nodes
-                            if let Some(sibling_index) = self
-                                .visible_nodes
-                                .iter()
-                                .position(|(id, _)| *id == next_sibling)
-                            {
-                                self.list_state.select(Some(sibling_index));
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    pub fn is_filtered(&self) -> bool {
+        self.filter.is_some()
     }
 
-    // Navigate to previous sibling
-    pub fn navigate_to_previous_sibling(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    if let Some(current_pos) = siblings.iter().position(|&id| id == *node_id) {
-                        if current_pos > 0 {
-                            let prev_sibling = siblings[current_pos - 1];
-                            // Find this sibling in visible nodes
-                            if let Some(sibling_index) = self
-                                .visible_nodes
-                                .iter()
-                                .position(|(id, _)| *id == prev_sibling)
-                            {
-                                self.list_state.select(Some(sibling_index));
-                            }
-                        }
-                    }
-                }
-            }
+    /// Leave the selected node and its subtree out of the rows, selecting
+    /// the row that takes its place. The root and pages can't be hidden.
+    ///
+    /// # Returns
+    ///
+    /// The hidden node
+    pub fn hide_selected(&mut self, tree: &dyn TreeLike) -> Option<usize> {
+        let index = self.list_state.selected()?;
+        if self.page_at(index).is_some() {
+            return None;
+        }
+        let node_id = self.get_selected_node_id()?;
+        if node_id == tree.root_id() {
+            return None;
         }
+        self.hide(node_id);
+        self.rebuild_visible_nodes(tree);
+        let last = self.visible_nodes.len().saturating_sub(1);
+        self.list_state.select(Some(index.min(last)));
+        Some(node_id)
     }
 
-    // Navigate to first sibling
-    pub fn navigate_to_first_sibling(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    if let Some(first_sibling) = siblings.first() {
-                        // Find this sibling in visible nodes
-                        if let Some(sibling_index) = self
-                            .visible_nodes
-                            .iter()
-                            .position(|(id, _)| *id == *first_sibling)
-                        {
-                            self.list_state.select(Some(sibling_index));
-                        }
-                    }
-                }
-            }
+    /// Leave `node_id` and its subtree out of the rows
+    pub fn hide(&mut self, node_id: usize) {
+        if self.hidden.insert(node_id) {
+            self.built_for = None;
         }
     }
 
-    // Navigate to last sibling
-    pub fn navigate_to_last_sibling(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    if let Some(last_sibling) = siblings.last() {
-                        // Find this sibling in visible nodes
-                        if let Some(sibling_index) = self
-                            .visible_nodes
-                            .iter()
-                            .position(|(id, _)| *id == *last_sibling)
-                        {
-                            self.list_state.select(Some(sibling_index));
-                        }
-                    }
-                }
+    /// Show all hidden nodes again, keeping the selection
+    ///
+    /// # Returns
+    ///
+    /// How many nodes were hidden
+    pub fn unhide_all(&mut self, tree: &dyn TreeLike) -> usize {
+        let count = self.hidden.len();
+        if count > 0 {
+            let selected = self.get_selected_node_id();
+            self.hidden.clear();
+            self.built_for = None;
+            if let Some(node_id) = selected {
+                self.navigate_to_node(tree, node_id);
             }
         }
+        count
     }
 
-    // Navigate to first line
-    pub fn navigate_to_first_line(&mut self) {
-        self.list_state.select(Some(0));
+    /// Nodes hidden with `X`, whose subtrees are hidden too
+    pub fn hidden_nodes(&self) -> Vec<usize> {
+        self.hidden.iter().copied().collect()
     }
 
-    // Navigate to last line
-    pub fn navigate_to_last_line(&mut self, tree: &TreeVariant) {
-        self.rebuild_visible_nodes(tree);
-        if !self.visible_nodes.is_empty() {
-            self.list_state.select(Some(self.visible_nodes.len() - 1));
+    /// Show `node_id` again if it or one of its ancestors is hidden
+    fn unhide_path(&mut self, tree: &dyn TreeLike, node_id: usize) {
+        for id in std::iter::once(node_id).chain(tree.ancestors(node_id)) {
+            if self.hidden.remove(&id) {
+                self.built_for = None;
+            }
         }
     }
 
-    // Shallow expand focused node and all its siblings
-    pub fn expand_all_siblings(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    for sibling_id in siblings {
-                        let sibling = tree.get_node(sibling_id).unwrap();
-                        if sibling.has_children() {
-                            self.expanded.insert(sibling_id);
-                        }
-                    }
-                }
+    /// Select `node_id`, or if it is hidden its closest visible ancestor
+    fn select_visible_ancestor(&mut self, tree: &dyn TreeLike, node_id: Option<usize>) {
+        let Some(node_id) = node_id else {
+            return;
+        };
+        // Ancestors from the node up to the root
+        let mut chain: Vec<usize> = std::iter::once(node_id)
+            .chain(tree.ancestors(node_id))
+            .collect();
+
+        // Walk down from the root while each node is expanded
+        let Some(mut visible) = chain.pop() else {
+            return;
+        };
+        while let Some(next) = chain.pop() {
+            if !self.expanded.contains(&visible) {
+                break;
             }
+            visible = next;
         }
+        self.navigate_to_node(tree, visible);
     }
 
-    // Deep expand focused node and all its siblings (recursively expand all descendants)
-    pub fn expand_all_siblings_deep(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    for sibling_id in siblings {
-                        self.expand_recursive(tree, sibling_id);
-                    }
-                }
-            }
-        }
+    /// Keep this many rows visible above and below the selection when scrolling
+    pub fn set_scrolloff(&mut self, scrolloff: usize) {
+        self.scrolloff = scrolloff;
     }
 
-    // Shallow collapse focused node and all its siblings
-    pub fn collapse_all_siblings(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    for sibling_id in siblings {
-                        self.expanded.remove(&sibling_id);
-                    }
-                }
-            }
-        }
+    /// Scroll so the selected row is at the top, center or bottom of the view
+    pub fn align_selection(&mut self, align: Align) {
+        self.align = Some(align);
     }
 
-    // Deep collapse focused node and all its siblings (recursively collapse all descendants)
-    pub fn collapse_all_siblings_deep(&mut self, tree: &TreeVariant) {
-        if let Some(index) = self.list_state.selected() {
-            if let Some((node_id, _)) = self.visible_nodes.get(index) {
-                if let Some(parent_id) = tree.get_parent(*node_id) {
-                    let siblings = tree.get_children(parent_id);
-                    for sibling_id in siblings {
-                        self.collapse_recursive(tree, sibling_id);
-                    }
-                }
-            }
-        }
+    /// Scroll all rows `columns` to the right (left if negative).
+    ///
+    /// The next render stops the scroll at the widest row in view.
+    pub fn scroll_horizontally(&mut self, columns: isize) {
+        self.horizontal_scroll = self.horizontal_scroll.saturating_add_signed(columns);
     }
 
-    // Helper: recursively expand a node and all its descendants
-    fn expand_recursive(&mut self, tree: &TreeVariant, node_id: usize) {
-        let node = tree.get_node(node_id).unwrap();
-        if node.has_children() {
-            self.expanded.insert(node_id);
-            let children = tree.get_children(node_id);
-            for child_id in children {
-                self.expand_recursive(tree, child_id);
-            }
-        }
+    /// Columns available for rows as of the last render
+    pub fn row_width(&self) -> usize {
+        self.row_width
     }
 
-    // Helper: recursively collapse a node and all its descendants
-    fn collapse_recursive(&mut self, tree: &TreeVariant, node_id: usize) {
-        let node = tree.get_node(node_id).unwrap();
-        if node.has_children() {
-            self.expanded.remove(&node_id);
-            let children = tree.get_children(node_id);
-            for child_id in children {
-                self.collapse_recursive(tree, child_id);
-            }
-        }
+    /// Switch between cutting the selected row at the right edge and wrapping
+    /// it, with its value in full, over as many lines as it needs
+    pub fn toggle_wrap_selected(&mut self) -> bool {
+        self.wrap_selected = !self.wrap_selected;
+        self.wrap_selected
     }
 
-    // Navigate to a specific node by ID
-    pub fn navigate_to_node(&mut self, tree: &TreeVariant, node_id: usize) {
-        self.rebuild_visible_nodes(tree);
-        if let Some(index) = self.visible_nodes.iter().position(|(id, _)| *id == node_id) {
-            self.list_state.select(Some(index));
-        }
+    /// The selected row (starting at 1) and the number of visible rows, as of
+    /// the last render
+    pub fn position(&self) -> Option<(usize, usize)> {
+        let selected = self.list_state.selected()?;
+        let total = self.visible_nodes.len();
+        (selected < total).then_some((selected + 1, total))
+    }
+
+    /// The node whose row shows `node_id`.
+    ///
+    /// With inline attributes, an `@attributes` node and the attributes below
+    /// it are shown on their element's row; every other node is its own row.
+    pub fn row_node(&self, tree: &dyn TreeLike, node_id: usize) -> usize {
+        if !self.inline_attributes {
+            return node_id;
+        }
+        let owner = |id| {
+            is_virtual_attributes(tree, id)
+                .then(|| tree.get_parent(id))
+                .flatten()
+        };
+        owner(node_id)
+            .or_else(|| tree.get_parent(node_id).and_then(owner))
+            .unwrap_or(node_id)
+    }
+
+    /// Number of times the visible node list has been rebuilt.
+    ///
+    /// Useful for checking that navigation doesn't trigger rebuilds.
+    pub fn rebuild_count(&self) -> usize {
+        self.rebuild_count
+    }
+
+    pub fn render(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        tree: &dyn TreeLike,
+        theme: &Theme,
+        search: &SearchHighlight,
+    ) {
+        self.rebuild_visible_nodes(tree);
+        let (gutter, area) = self.split_gutter(area);
+
+        // Get current match node ID if any
+        let current_match_id = search
+            .current
+            .and_then(|idx| search.matches.get(idx).copied());
+
+        // Only build list items for the rows that fit in the viewport
+        let last = self.visible_nodes.len().saturating_sub(1);
+        let selected = self.list_state.selected().map(|i| i.min(last));
+        let height = area.height as usize;
+        let offset = match (self.align.take(), selected) {
+            (Some(align), Some(selected)) => {
+                aligned_offset(align, selected, height, self.scrolloff)
+                    .min(self.visible_nodes.len().saturating_sub(1))
+            }
+            _ => viewport_offset(
+                self.list_state.offset(),
+                selected,
+                height,
+                self.visible_nodes.len(),
+                self.scrolloff,
+            ),
+        };
+        *self.list_state.offset_mut() = offset;
+        let end = (offset + area.height as usize).min(self.visible_nodes.len());
+        let width = (area.width as usize).saturating_sub(HIGHLIGHT_SYMBOL.len());
+        self.row_width = width;
+
+        let rows: Vec<(Vec<Span<'static>>, bool)> = self.visible_nodes[offset..end]
+            .iter()
+            .enumerate()
+            .map(|(index, &(node_id, depth))| {
+                if let Some(&page) = self.pages.get(&(offset + index)) {
+                    return (self.page_spans(node_id, page, depth, theme, true), false);
+                }
+                if self.value_lines.contains_key(&(offset + index)) {
+                    let highlight = search
+                        .pattern
+                        .filter(|pattern| pattern.searches_values())
+                        .filter(|_| search.matches.contains(&node_id))
+                        .map(|pattern| {
+                            (
+                                theme.search_match(Some(node_id) == current_match_id),
+                                pattern,
+                            )
+                        });
+                    let spans = self.value_line_spans(tree, offset + index, theme, true, highlight);
+                    return (spans, false);
+                }
+                let wrap = self.wrap_selected && selected == Some(offset + index);
+                let row = RowInfo {
+                    node_id,
+                    depth,
+                    is_match: search.matches.contains(&node_id),
+                    is_current_match: Some(node_id) == current_match_id,
+                    hidden_children: None,
+                    preview: None,
+                    inline_attributes: None,
+                    leaf: false,
+                    // Scrolled off columns make room for more of the preview
+                    width: if wrap {
+                        usize::MAX
+                    } else {
+                        width + self.horizontal_scroll
+                    },
+                    wrap,
+                    decorated: true,
+                    redacted: false,
+                };
+                (self.build_row(tree, row, theme, search.pattern), wrap)
+            })
+            .collect();
+
+        // Stop scrolling once the widest row in view ends at the right edge
+        let widest = rows
+            .iter()
+            .filter(|(_, wrap)| !wrap)
+            .map(|(spans, _)| spans.iter().map(Span::width).sum::<usize>())
+            .max()
+            .unwrap_or(0);
+        self.horizontal_scroll = self.horizontal_scroll.min(widest.saturating_sub(width));
+
+        let items: Vec<ListItem> = rows
+            .into_iter()
+            .map(|(spans, wrap)| {
+                if wrap {
+                    let indent = spans.first().map_or(0, Span::width) + self.icons.width();
+                    ListItem::new(wrap_spans(spans, width, indent))
+                } else {
+                    ListItem::new(Line::from(skip_columns(spans, self.horizontal_scroll)))
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .highlight_style(theme.selection())
+            .highlight_symbol(HIGHLIGHT_SYMBOL);
+
+        // The items start at the offset, so render them with a window-relative state
+        let mut window_state = ListState::default().with_selected(selected.map(|i| i - offset));
+        frame.render_stateful_widget(list, area, &mut window_state);
+
+        if gutter.width > 0 {
+            self.render_line_numbers(frame, gutter, offset..end, selected, theme);
+        }
+    }
+
+    /// Split off the line number gutter on the left, sized to the largest number
+    fn split_gutter(&self, area: Rect) -> (Rect, Rect) {
+        let width = match self.line_numbers {
+            LineNumbers::Off => 0,
+            // One column of padding between the numbers and the tree
+            _ => (self.visible_nodes.len().max(1).ilog10() + 2) as u16,
+        }
+        .min(area.width);
+        let gutter = Rect { width, ..area };
+        let rest = Rect {
+            x: area.x + width,
+            width: area.width - width,
+            ..area
+        };
+        (gutter, rest)
+    }
+
+    fn render_line_numbers(
+        &self,
+        frame: &mut Frame,
+        gutter: Rect,
+        rows: std::ops::Range<usize>,
+        selected: Option<usize>,
+        theme: &Theme,
+    ) {
+        let width = gutter.width as usize - 1;
+        let lines: Vec<Line> = rows
+            .map(|index| {
+                let number = match (self.line_numbers, selected) {
+                    (LineNumbers::Relative, Some(selected)) if index != selected => {
+                        index.abs_diff(selected)
+                    }
+                    _ => index + 1,
+                };
+                let style = if Some(index) == selected {
+                    Style::default().fg(theme.icon).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(theme.node_type)
+                };
+                Line::styled(format!("{number:>width$}"), style)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), gutter);
+    }
+
+    /// Children hidden by a collapsed node, used for its count and preview.
+    ///
+    /// Child IDs come from the tree structure, which is cheap even in streaming
+    /// mode. `@attributes` nodes are skipped since their summary is rarely useful.
+    fn hidden_children(&self, tree: &dyn TreeLike, node: &TreeNode, node_id: usize) -> Vec<usize> {
+        if (!self.show_child_counts && !self.show_previews)
+            || node.is_virtual_attributes()
+            || self.expanded.contains(&node_id)
+        {
+            return Vec::new();
+        }
+        self.visible_children(tree, node_id)
+    }
+
+    /// Children shown as rows below `node_id`, leaving out an inlined `@attributes` node
+    fn visible_children(&self, tree: &dyn TreeLike, node_id: usize) -> Vec<usize> {
+        let mut children = tree.get_children(node_id);
+        if self.inline_attributes
+            && children
+                .first()
+                .is_some_and(|&id| is_virtual_attributes(tree, id))
+        {
+            children.remove(0);
+        }
+        if let Some(allowed) = &self.filter {
+            children.retain(|id| allowed.contains(id));
+        }
+        if !self.hidden.is_empty() {
+            children.retain(|id| !self.hidden.contains(id));
+        }
+        children
+    }
+
+    /// Whether `node_id` has rows to show below it. Only these nodes expand
+    /// and collapse, whatever their type, so `@attributes` nodes behave like
+    /// any other container.
+    fn is_expandable(&self, tree: &dyn TreeLike, node_id: usize) -> bool {
+        !self.visible_children(tree, node_id).is_empty()
+    }
+
+    /// The hidden `@attributes` child of `node_id`, if attributes are inlined.
+    ///
+    /// Parsers always add the virtual node as the first child, so only that
+    /// one child is loaded to check.
+    fn inlined_attributes(&self, tree: &dyn TreeLike, node_id: usize) -> Option<usize> {
+        if !self.inline_attributes {
+            return None;
+        }
+        tree.get_children(node_id)
+            .first()
+            .copied()
+            .filter(|&id| is_virtual_attributes(tree, id))
+    }
+
+    /// Rows of the subtree below `root`, expanded `depth` levels deep (all
+    /// levels with `None`), drawn as in the tree but without a width limit,
+    /// for printing it without the TUI
+    pub fn subtree_rows(
+        &mut self,
+        tree: &dyn TreeLike,
+        root: usize,
+        depth: Option<usize>,
+        theme: &Theme,
+        options: &TextOptions,
+    ) -> Vec<TextRow> {
+        let mut rows = Vec::new();
+        let mut stack = vec![(root, 0)];
+        while let Some((node_id, level)) = stack.pop() {
+            let children = self.visible_children(tree, node_id);
+            let leaf = children.is_empty();
+            if depth.is_none_or(|depth| level < depth) && !leaf {
+                self.expanded.insert(node_id);
+                stack.extend(children.into_iter().rev().map(|id| (id, level + 1)));
+            } else {
+                self.expanded.remove(&node_id);
+            }
+            rows.push(self.text_row(tree, node_id, level, theme, options));
+        }
+        rows
+    }
+
+    /// Rows of every node below `root`, shown or inside collapsed nodes, with
+    /// whether the view has them expanded, for exporting the view as HTML.
+    ///
+    /// Stops after `max_rows` rows and leaves out nodes more than `max_depth`
+    /// levels deep; the flag returned tells whether it left out any.
+    pub fn html_rows(
+        &self,
+        tree: &dyn TreeLike,
+        root: usize,
+        theme: &Theme,
+        max_rows: usize,
+        max_depth: usize,
+    ) -> (Vec<HtmlRow>, bool) {
+        let options = TextOptions {
+            layout: TextLayout::Plain,
+            ..TextOptions::default()
+        };
+        let mut rows = Vec::new();
+        let mut truncated = false;
+        let mut stack = vec![(root, 0)];
+        while let Some((node_id, depth)) = stack.pop() {
+            if rows.len() == max_rows {
+                truncated = true;
+                break;
+            }
+            let children = self.visible_children(tree, node_id);
+            let expandable = !children.is_empty() && depth < max_depth;
+            truncated |= !children.is_empty() && !expandable;
+            if expandable {
+                stack.extend(children.into_iter().rev().map(|id| (id, depth + 1)));
+            }
+            rows.push(HtmlRow {
+                node_id,
+                depth,
+                spans: self.text_row(tree, node_id, depth, theme, &options).spans,
+                expandable,
+                expanded: self.expanded.contains(&node_id),
+            });
+        }
+        (rows, truncated)
+    }
+
+    /// All rows the view shows, not only those that fit on screen, for
+    /// copying them as text
+    pub fn visible_rows(
+        &mut self,
+        tree: &dyn TreeLike,
+        theme: &Theme,
+        options: &TextOptions,
+    ) -> Vec<TextRow> {
+        self.rebuild_visible_nodes(tree);
+        self.visible_nodes
+            .iter()
+            .enumerate()
+            .map(|(index, &(node_id, depth))| {
+                let decorated = options.layout.decorated();
+                if let Some(&page) = self.pages.get(&index) {
+                    let spans = self.page_spans(node_id, page, depth, theme, decorated);
+                    return TextRow { depth, spans };
+                }
+                if self.value_lines.contains_key(&index) {
+                    let spans = self.value_line_spans(tree, index, theme, decorated, None);
+                    return TextRow { depth, spans };
+                }
+                self.text_row(tree, node_id, depth, theme, options)
+            })
+            .collect()
+    }
+
+    /// The spans of the row at `index`, which shows a line of a leaf's
+    /// value, indented like a child of the leaf
+    fn value_line_spans(
+        &self,
+        tree: &dyn TreeLike,
+        index: usize,
+        theme: &Theme,
+        decorated: bool,
+        highlight: Option<(Style, &SearchPattern)>,
+    ) -> Vec<Span<'static>> {
+        let (node_id, depth) = self.visible_nodes[index];
+        let line = self.value_lines[&index];
+        let node = tree.try_get_node(node_id).ok();
+        let value = node.as_deref().and_then(node_value).unwrap_or_default();
+        let mut spans = Vec::new();
+        if decorated {
+            spans.push(Span::raw("  ".repeat(depth)));
+            spans.push(Span::raw(" ".repeat(self.icons.width())));
+        }
+        if line == MAX_VALUE_LINES {
+            let rest = value.lines().count().saturating_sub(MAX_VALUE_LINES);
+            spans.push(Span::styled(
+                format!("… {} more lines", format_thousands(rest)),
+                Style::default()
+                    .fg(theme.node_type)
+                    .add_modifier(Modifier::DIM),
+            ));
+        } else {
+            let text = value.lines().nth(line).unwrap_or_default();
+            push_highlighted(
+                &mut spans,
+                text,
+                Style::default().fg(theme.value),
+                highlight,
+            );
+        }
+        spans
+    }
+
+    fn text_row(
+        &self,
+        tree: &dyn TreeLike,
+        node_id: usize,
+        depth: usize,
+        theme: &Theme,
+        options: &TextOptions,
+    ) -> TextRow {
+        let row = RowInfo {
+            node_id,
+            depth,
+            is_match: false,
+            is_current_match: false,
+            hidden_children: None,
+            preview: None,
+            inline_attributes: None,
+            leaf: false,
+            width: usize::MAX,
+            wrap: options.full_values,
+            decorated: options.layout.decorated(),
+            redacted: false,
+        };
+        TextRow {
+            depth,
+            spans: self.build_row(tree, row, theme, None),
+        }
+    }
+
+    /// The spans of a row, filling in its child count, preview and inlined
+    /// attributes
+    fn build_row(
+        &self,
+        tree: &dyn TreeLike,
+        mut row: RowInfo,
+        theme: &Theme,
+        pattern: Option<&SearchPattern>,
+    ) -> Vec<Span<'static>> {
+        // A node that fails to load still gets a row instead of aborting the frame
+        let node = tree
+            .try_get_node(row.node_id)
+            .map(Cow::into_owned)
+            .unwrap_or_else(|error| unavailable_node(tree, row.node_id, &error));
+        let hidden = self.hidden_children(tree, &node, row.node_id);
+        let attributes_id = self.inlined_attributes(tree, row.node_id);
+        row.redacted = self.redacts(tree, row.node_id);
+        let masked = |key: &str| self.masks(row.redacted, key);
+        row.hidden_children =
+            (self.show_child_counts && !hidden.is_empty()).then_some(hidden.len());
+        if self.show_previews {
+            row.preview = preview(tree, &node, &hidden, masked);
+        }
+        row.inline_attributes = attributes_id.map(|id| attribute_summary(tree, id, masked));
+        row.leaf = !self.is_expandable(tree, row.node_id);
+        self.row_spans(node, &row, theme, pattern)
+    }
+
+    /// The spans of one row, before scrolling or wrapping
+    fn row_spans(
+        &self,
+        node: TreeNode,
+        row: &RowInfo,
+        theme: &Theme,
+        pattern: Option<&SearchPattern>,
+    ) -> Vec<Span<'static>> {
+        let RowInfo {
+            node_id,
+            depth,
+            is_match,
+            is_current_match,
+            hidden_children,
+            ..
+        } = *row;
+        let mut spans = Vec::new();
+        if row.decorated {
+            let expander = if row.leaf {
+                Expander::Leaf
+            } else if self.expanded.contains(&node_id) {
+                Expander::Expanded
+            } else {
+                Expander::Collapsed
+            };
+            let icon = self.icons.expander(expander, node.is_virtual_attributes());
+            spans.push(Span::raw("  ".repeat(depth)));
+
+            // Icon with special color for virtual nodes
+            let icon_color = if node.is_virtual_attributes() {
+                theme.virtual_node
+            } else {
+                theme.icon
+            };
+            spans.push(Span::styled(icon, Style::default().fg(icon_color)));
+            spans.push(Span::raw(" "));
+            if let Some(type_icon) = self.icons.node_type(&node) {
+                spans.push(Span::styled(type_icon, Style::default().fg(icon_color)));
+                spans.push(Span::raw(" "));
+            }
+        }
+
+        // Differences are marked like in a unified diff and colored
+        let change = self.changes.get(&node_id);
+        let change_style = change.map(|change| {
+            Style::default().fg(match change {
+                Change::Added => theme.diff_added,
+                Change::Removed => theme.diff_removed,
+                Change::Value(_) | Change::Type(_) => theme.diff_changed,
+            })
+        });
+        if let Some((change, style)) = change.zip(change_style) {
+            let sign = match change {
+                Change::Added => "+ ",
+                Change::Removed => "- ",
+                Change::Value(_) | Change::Type(_) => "~ ",
+            };
+            spans.push(Span::styled(sign, style.add_modifier(Modifier::BOLD)));
+        }
+
+        // Matched fragments of the label, value and type are highlighted
+        // The current match is brighter than the others
+        let match_style =
+            (is_match || is_current_match).then(|| theme.search_match(is_current_match));
+        let highlight = match_style.zip(pattern);
+        let keys = highlight.filter(|(_, pattern)| pattern.searches_keys());
+        let values = highlight.filter(|(_, pattern)| pattern.searches_values());
+        let types = highlight.filter(|(_, pattern)| pattern.field() == SearchField::All);
+
+        let label_style = if let Some(style) = change_style {
+            style
+        } else if node.is_virtual_attributes() {
+            Style::default().fg(theme.virtual_node)
+        } else {
+            Style::default().fg(theme.label)
+        };
+        let old_value = match change {
+            Some(Change::Value(old)) => Some((old.as_str(), change_style.unwrap_or_default())),
+            _ => None,
+        };
+        // Long labels give up their middle so that some of the value fits
+        let has_value = node.is_attribute()
+            || matches!(node.node_type.as_str(), "text" | "comment" | "doctype");
+        let label = if row.wrap || row.width == usize::MAX {
+            Cow::Borrowed(node.label.as_str())
+        } else {
+            let reserved = if has_value { 2 + VALUE_MIN_COLUMNS } else { 0 };
+            let available = row.width.saturating_sub(spans_width(&spans) + reserved);
+            truncate_middle(&node.label, available.max(LABEL_MIN_COLUMNS))
+        };
+        let label_index = spans.len();
+        let mut highlighted = push_highlighted(&mut spans, &label, label_style, keys);
+
+        if let Some(info) = self
+            .attribute_labels
+            .as_ref()
+            .filter(|_| node.is_attribute())
+            .and_then(|labels| labels.get(&node.label))
+        {
+            spans.push(Span::styled(
+                format!(" ({})", info.describe()),
+                Style::default()
+                    .fg(theme.node_type)
+                    .add_modifier(Modifier::DIM),
+            ));
+        }
+        if let Some(count) = hidden_children {
+            spans.push(Span::styled(
+                format!(" ({})", format_thousands(count)),
+                Style::default()
+                    .fg(theme.node_type)
+                    .add_modifier(Modifier::DIM),
+            ));
+        }
+        // Huge values are cut at the edge, so their size goes before them
+        if let Some(bytes) = self.value_size_hint(&node) {
+            spans.push(Span::styled(
+                format!(" ({})", format_size(bytes)),
+                Style::default()
+                    .fg(theme.node_type)
+                    .add_modifier(Modifier::DIM),
+            ));
+        }
+
+        // For attribute nodes, show key: value (no type bracket)
+        // For text/comment nodes, show label: content
+        // For regular nodes, show type
+        if node.is_attribute() {
+            if let Some(attr) = node.attributes.first() {
+                // URL references are underlined like links
+                let style = if node.is_url_ref() {
+                    Style::default()
+                        .fg(theme.value)
+                        .add_modifier(Modifier::UNDERLINED)
+                } else {
+                    Style::default().fg(theme.value)
+                };
+                highlighted |=
+                    self.push_node_value(&mut spans, row, old_value, &attr.value, style, values);
+            }
+            // The readable form of a timestamp, SID or flags
+            if let Some(decoded) = node
+                .attributes
+                .iter()
+                .find(|a| a.key == "decoded")
+                .filter(|_| !row.redacted)
+            {
+                spans.push(Span::styled(
+                    format!(" ({})", decoded.value),
+                    Style::default().fg(theme.value).add_modifier(Modifier::DIM),
+                ));
+            }
+        } else if node.node_type == TreeNode::ERROR_TYPE {
+            // Show load failures inline instead of on stderr
+            if let Some(error_attr) = node.attributes.iter().find(|a| a.key == "error") {
+                spans.push(Span::styled(
+                    format!(": {}", error_attr.value),
+                    Style::default().fg(theme.error),
+                ));
+            }
+        } else if matches!(node.node_type.as_str(), "text" | "comment" | "doctype") {
+            // Show content inline for text, comment and doctype nodes
+            if let Some(content_attr) = node.attributes.iter().find(|a| a.key == "content") {
+                let style = Style::default().fg(theme.value);
+                highlighted |= self.push_node_value(
+                    &mut spans,
+                    row,
+                    old_value,
+                    &content_attr.value,
+                    style,
+                    values,
+                );
+            }
+        } else {
+            // Only show node type for regular nodes
+            let style = Style::default().fg(theme.node_type);
+            spans.push(Span::raw(" "));
+            spans.push(Span::styled("[", style));
+            if let Some((Change::Type(old), changed)) = change.zip(change_style) {
+                spans.push(Span::styled(format!("{} → ", old), changed));
+            }
+            highlighted |= push_highlighted(&mut spans, &node.node_type, style, types);
+            spans.push(Span::styled("]", style));
+        }
+
+        // The query matched something not drawn, such as an attribute key, so
+        // mark the whole label instead
+        if !highlighted && let Some(style) = match_style {
+            spans[label_index].style = style;
+        }
+
+        if let Some(attributes) = &row.inline_attributes {
+            let style = Style::default().fg(theme.key);
+            push_truncated(&mut spans, attributes, style, row.width);
+        }
+        if let Some(preview) = &row.preview {
+            let style = Style::default().fg(theme.value).add_modifier(Modifier::DIM);
+            push_truncated(&mut spans, preview, style, row.width);
+        }
+
+        spans
+    }
+
+    /// Append a node's value like [`push_changed_value`]. Multi-line values
+    /// show their first line and how many lines they have, unless the row is
+    /// wrapped, or only how many if they are expanded to show their lines.
+    ///
+    /// Returns whether anything was highlighted.
+    fn push_node_value(
+        &self,
+        spans: &mut Vec<Span<'static>>,
+        row: &RowInfo,
+        old: Option<(&str, Style)>,
+        value: &str,
+        style: Style,
+        highlight: Option<(Style, &SearchPattern)>,
+    ) -> bool {
+        let width = value_width(row);
+        if row.redacted {
+            push_value(spans, MASK, style, None, width);
+            return false;
+        }
+        let lines = value.lines().count();
+        if width == ValueWidth::Full || lines < 2 {
+            return push_changed_value(spans, old, value, style, highlight, width);
+        }
+        let marker = Span::styled(
+            format!(" {}", self.icons.line_count(lines)),
+            style.add_modifier(Modifier::DIM),
+        );
+        if self.expanded.contains(&row.node_id) {
+            spans.push(Span::styled(":", style));
+            spans.push(marker);
+            return false;
+        }
+        let old = old.map(|(old, old_style)| (first_line(old), old_style));
+        let width = width.narrower(marker.width());
+        let highlighted =
+            push_changed_value(spans, old, first_line(value), style, highlight, width);
+        spans.push(marker);
+        highlighted
+    }
+
+    /// The size of a node's value or text content if it reaches the threshold
+    fn value_size_hint(&self, node: &TreeNode) -> Option<usize> {
+        if self.size_hint_threshold == 0 {
+            return None;
+        }
+        let key = if node.is_attribute() {
+            "value"
+        } else if matches!(node.node_type.as_str(), "text" | "comment" | "doctype") {
+            "content"
+        } else {
+            return None;
+        };
+        let value = node.attributes.iter().find(|a| a.key == key)?;
+        (value.value.len() >= self.size_hint_threshold).then_some(value.value.len())
+    }
+
+    /// Rebuild the visible node list if the expansion set or the tree changed.
+    ///
+    /// The selection follows its node to the node's new row. If a collapse
+    /// hid the node, its closest visible ancestor is selected instead.
+    fn rebuild_visible_nodes(&mut self, tree: &dyn TreeLike) {
+        let key = (self.expanded.version, tree.node_count());
+        if self.built_for == Some(key) {
+            return;
+        }
+
+        let selected = self.get_selected_node_id();
+        let selected_page = self
+            .list_state
+            .selected()
+            .and_then(|index| self.page_at(index));
+        let selected_line = self
+            .list_state
+            .selected()
+            .and_then(|index| self.value_line_at(index));
+        self.collect_visible_nodes(tree);
+        self.built_for = Some(key);
+        self.rebuild_count += 1;
+
+        // So does a line of a value while the value is expanded
+        if let Some(index) = selected_line.and_then(|line| {
+            (0..self.visible_nodes.len()).find(|&i| self.value_line_at(i) == Some(line))
+        }) {
+            self.list_state.select(Some(index));
+            return;
+        }
+
+        // A selected page stays selected while its array is paged
+        if let Some(index) = selected_page
+            .and_then(|page| (0..self.visible_nodes.len()).find(|&i| self.page_at(i) == Some(page)))
+        {
+            self.list_state.select(Some(index));
+            return;
+        }
+        match selected {
+            Some(node_id) => {
+                let index = self.closest_visible_row(tree, node_id);
+                self.list_state.select(Some(index));
+            }
+            None if !self.visible_nodes.is_empty() => {
+                let last = self.visible_nodes.len() - 1;
+                if let Some(index) = self.list_state.selected()
+                    && index > last
+                {
+                    self.list_state.select(Some(last));
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Row of `node_id`, or of its closest ancestor or page that has one
+    fn closest_visible_row(&self, tree: &dyn TreeLike, node_id: usize) -> usize {
+        // Distance of the node and each ancestor from the node, doubled so
+        // that a page sits between an array and its elements
+        let distance: HashMap<usize, usize> = std::iter::once(node_id)
+            .chain(tree.ancestors(node_id))
+            .enumerate()
+            .map(|(distance, id)| (id, 2 * distance))
+            .collect();
+        let pages: HashMap<(usize, usize), usize> = if self.pages.is_empty() {
+            HashMap::new()
+        } else {
+            self.pages_on_path(tree, node_id)
+                .into_iter()
+                .map(|(array, page)| ((array, page), distance[&array] - 1))
+                .collect()
+        };
+        self.visible_nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (id, _))| match self.page_at(index) {
+                Some(page) => pages.get(&page).map(|&d| (d, index)),
+                None => distance.get(id).map(|&d| (d, index)),
+            })
+            .min()
+            .map_or(0, |(_, index)| index)
+    }
+
+    /// Depth-first walk over expanded nodes, using an explicit stack so deeply
+    /// nested trees cannot overflow the call stack
+    fn collect_visible_nodes(&mut self, tree: &dyn TreeLike) {
+        self.visible_nodes.clear();
+        self.pages.clear();
+        self.value_lines.clear();
+        // Pages are pushed as their array's ID with the page
+        let mut stack = vec![(tree.root_id(), 0, None)];
+
+        while let Some((node_id, depth, page)) = stack.pop() {
+            if let Some(page) = page {
+                self.pages.insert(self.visible_nodes.len(), page);
+            }
+            self.visible_nodes.push((node_id, depth));
+            if page.is_some() || !self.expanded.contains(&node_id) {
+                continue;
+            }
+
+            // Add children reversed so the first child is visited next
+            let children = self.visible_children(tree, node_id);
+            if children.is_empty() {
+                self.push_value_lines(tree, node_id, depth + 1);
+                continue;
+            }
+            match self.page_size(tree, node_id, &children) {
+                Some(size) => {
+                    // Each page is followed by its elements if it is expanded
+                    for (number, chunk) in children.chunks(size).enumerate().rev() {
+                        if self.expanded.contains_page(node_id, number) {
+                            stack.extend(chunk.iter().rev().map(|&id| (id, depth + 2, None)));
+                        }
+                        let page = Page {
+                            number,
+                            len: chunk.len(),
+                        };
+                        stack.push((node_id, depth + 1, Some(page)));
+                    }
+                }
+                None => stack.extend(children.into_iter().rev().map(|id| (id, depth + 1, None))),
+            }
+        }
+    }
+
+    /// Number of lines in the value of `node_id`, 0 for nodes without one
+    /// and for masked values
+    fn value_line_count(&self, tree: &dyn TreeLike, node_id: usize) -> usize {
+        if self.redacts(tree, node_id) {
+            return 0;
+        }
+        tree.get_node(node_id)
+            .and_then(|node| node_value(&node).map(|value| value.lines().count()))
+            .unwrap_or(0)
+    }
+
+    /// Add a row for each line of the value of `node_id`, if it has several
+    fn push_value_lines(&mut self, tree: &dyn TreeLike, node_id: usize, depth: usize) {
+        let lines = self.value_line_count(tree, node_id);
+        if lines < 2 {
+            return;
+        }
+        // Past the limit a last row counts the lines left out
+        let rows = if lines > MAX_VALUE_LINES {
+            MAX_VALUE_LINES + 1
+        } else {
+            lines
+        };
+        for line in 0..rows {
+            self.value_lines.insert(self.visible_nodes.len(), line);
+            self.visible_nodes.push((node_id, depth));
+        }
+    }
+
+    /// The leaf ID and line number of the row at `index`, if it shows a
+    /// line of a value
+    fn value_line_at(&self, index: usize) -> Option<(usize, usize)> {
+        let line = self.value_lines.get(&index)?;
+        Some((self.visible_nodes[index].0, *line))
+    }
+
+    /// The row that the row at `index` is drawn below: its parent's, or
+    /// that of the page it is on. Nodes without a known parent, such as the
+    /// root, have none.
+    fn parent_row(&self, tree: &dyn TreeLike, index: usize) -> Option<usize> {
+        let &(node_id, depth) = self.visible_nodes.get(index)?;
+        if !self.pages.contains_key(&index) {
+            tree.get_parent(node_id)?;
+        }
+        self.visible_nodes[..index]
+            .iter()
+            .rposition(|&(_, d)| d < depth)
+    }
+
+    /// The rows below the same parent row as the row at `index`, itself
+    /// included
+    fn sibling_rows(&self, tree: &dyn TreeLike, index: usize) -> Vec<usize> {
+        let Some(&(_, depth)) = self.visible_nodes.get(index) else {
+            return Vec::new();
+        };
+        let Some(parent) = self.parent_row(tree, index) else {
+            return vec![index];
+        };
+        let start = parent + 1;
+        self.visible_nodes[start..]
+            .iter()
+            .enumerate()
+            .take_while(|(_, (_, d))| *d >= depth)
+            .filter(|(_, (_, d))| *d == depth)
+            .map(|(i, _)| start + i)
+            .collect()
+    }
+
+    pub fn navigate_up(&mut self) {
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i > 0 {
+                    i - 1
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    pub fn navigate_down(&mut self, tree: &dyn TreeLike) {
+        self.rebuild_visible_nodes(tree);
+        let i = match self.list_state.selected() {
+            Some(i) => (i + 1).min(self.visible_nodes.len().saturating_sub(1)),
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    /// Find the row drawn at a screen position, given the area of the last render
+    pub fn hit_test(&self, area: Rect, column: u16, row: u16) -> Option<RowHit> {
+        let inside = column >= area.x
+            && column < area.x + area.width
+            && row >= area.y
+            && row < area.y + area.height;
+        if !inside {
+            return None;
+        }
+
+        let (_, area) = self.split_gutter(area);
+        let index = self.list_state.offset() + (row - area.y) as usize;
+        let (node_id, depth) = *self.visible_nodes.get(index)?;
+        // The icon scrolls with the row, and may be scrolled out of view
+        let icon_column = (2 * depth)
+            .checked_sub(self.horizontal_scroll)
+            .map(|indent| area.x as usize + HIGHLIGHT_SYMBOL.len() + indent);
+        Some(RowHit {
+            index,
+            node_id,
+            on_icon: icon_column == Some(column as usize) && !self.value_lines.contains_key(&index),
+        })
+    }
+
+    /// Select a visible row by index, ignoring out-of-range indices
+    pub fn select_index(&mut self, index: usize) {
+        if index < self.visible_nodes.len() {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    /// Move the selection by `delta` rows (negative moves up), stopping at
+    /// the first and last row
+    pub fn navigate_by(&mut self, delta: isize, tree: &dyn TreeLike) {
+        self.rebuild_visible_nodes(tree);
+        let last = self.visible_nodes.len().saturating_sub(1);
+        let current = self.list_state.selected().unwrap_or(0);
+        let target = current.saturating_add_signed(delta).min(last);
+        self.list_state.select(Some(target));
+    }
+
+    /// Expand a collapsed container or collapse an expanded one; does nothing
+    /// on leaves
+    pub fn toggle_expand(&mut self, tree: &dyn TreeLike) {
+        if let Some(index) = self.list_state.selected()
+            && self.row_expandable(tree, index)
+        {
+            self.set_row_expanded(index, !self.row_expanded(index));
+        }
+    }
+
+    /// Collapse the selected node. Unlike `h` it never moves the selection.
+    pub fn collapse(&mut self, _tree: &dyn TreeLike) {
+        if let Some(index) = self.list_state.selected() {
+            self.set_row_expanded(index, false);
+        }
+    }
+
+    pub fn get_selected_node_id(&self) -> Option<usize> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.visible_nodes.get(index))
+            .map(|(node_id, _)| *node_id)
+    }
+
+    pub fn collapse_parent(&mut self, tree: &dyn TreeLike) {
+        let Some(parent) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.parent_row(tree, index))
+        else {
+            return;
+        };
+        self.set_row_expanded(parent, false);
+
+        // Rows above the parent's stay where they are
+        self.rebuild_visible_nodes(tree);
+        self.list_state.select(Some(parent));
+    }
+
+    pub fn expand(&mut self, tree: &dyn TreeLike) {
+        if let Some(index) = self.list_state.selected()
+            && self.row_expandable(tree, index)
+        {
+            self.set_row_expanded(index, true);
+        }
+    }
+
+    // Smart left: collapse if expanded, otherwise move to parent; repeated `count` times
+    pub fn smart_left(&mut self, tree: &dyn TreeLike, count: usize) {
+        for step in 0..count {
+            // A collapse in the previous step changes the visible rows
+            if step > 0 {
+                self.rebuild_visible_nodes(tree);
+            }
+            if let Some(index) = self.list_state.selected() {
+                if self.row_expanded(index) && self.row_expandable(tree, index) {
+                    // Collapse if expanded
+                    self.set_row_expanded(index, false);
+                } else {
+                    // Move to parent
+                    self.navigate_to_parent(tree, 1);
+                }
+            }
+        }
+    }
+
+    // Smart right: expand if collapsed, move to first child if expanded.
+    // With a count, each step but the last expands and descends.
+    pub fn smart_right(&mut self, tree: &dyn TreeLike, count: usize) {
+        for step in 0..count {
+            let Some(index) = self.list_state.selected() else {
+                return;
+            };
+            if !self.row_expandable(tree, index) {
+                return;
+            }
+
+            if !self.row_expanded(index) {
+                // Expand if collapsed
+                self.set_row_expanded(index, true);
+                if step + 1 == count {
+                    return;
+                }
+            }
+            // Move to first child
+            self.rebuild_visible_nodes(tree);
+            if index + 1 < self.visible_nodes.len() {
+                self.list_state.select(Some(index + 1));
+            }
+        }
+    }
+
+    // Navigate `count` levels up without collapsing, stopping at the root
+    pub fn navigate_to_parent(&mut self, tree: &dyn TreeLike, count: usize) {
+        let Some(mut index) = self.list_state.selected() else {
+            return;
+        };
+        for _ in 0..count {
+            match self.parent_row(tree, index) {
+                Some(parent) => index = parent,
+                None => break,
+            }
+        }
+        if index < self.visible_nodes.len() {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    // Navigate `count` siblings down, stopping at the last one
+    pub fn navigate_to_next_sibling(&mut self, tree: &dyn TreeLike, count: usize) {
+        let Some(index) = self.list_state.selected() else {
+            return;
+        };
+        let siblings = self.sibling_rows(tree, index);
+        if let Some(position) = siblings.iter().position(|&row| row == index)
+            && position + 1 < siblings.len()
+        {
+            let next = siblings[(position + count).min(siblings.len() - 1)];
+            self.list_state.select(Some(next));
+        }
+    }
+
+    // Navigate `count` siblings up, stopping at the first one
+    pub fn navigate_to_previous_sibling(&mut self, tree: &dyn TreeLike, count: usize) {
+        let Some(index) = self.list_state.selected() else {
+            return;
+        };
+        let siblings = self.sibling_rows(tree, index);
+        if let Some(position) = siblings.iter().position(|&row| row == index)
+            && position > 0
+        {
+            self.list_state
+                .select(Some(siblings[position.saturating_sub(count)]));
+        }
+    }
+
+    // Navigate to first sibling
+    pub fn navigate_to_first_sibling(&mut self, tree: &dyn TreeLike) {
+        if let Some(first) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.sibling_rows(tree, index).first().copied())
+        {
+            self.list_state.select(Some(first));
+        }
+    }
+
+    // Navigate to last sibling
+    pub fn navigate_to_last_sibling(&mut self, tree: &dyn TreeLike) {
+        if let Some(last) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.sibling_rows(tree, index).last().copied())
+        {
+            self.list_state.select(Some(last));
+        }
+    }
+
+    // Navigate to first line
+    pub fn navigate_to_first_line(&mut self) {
+        self.list_state.select(Some(0));
+    }
+
+    // Navigate to last line
+    pub fn navigate_to_last_line(&mut self, tree: &dyn TreeLike) {
+        self.rebuild_visible_nodes(tree);
+        if !self.visible_nodes.is_empty() {
+            self.list_state.select(Some(self.visible_nodes.len() - 1));
+        }
+    }
+
+    // Shallow expand focused node and all its siblings
+    pub fn expand_all_siblings(&mut self, tree: &dyn TreeLike) {
+        if let Some(index) = self.list_state.selected() {
+            if let Some((node_id, _)) = self.visible_nodes.get(index) {
+                if let Some(parent_id) = tree.get_parent(*node_id) {
+                    let siblings = tree.get_children(parent_id);
+                    for sibling_id in siblings {
+                        if has_children(tree, sibling_id) {
+                            self.expanded.insert(sibling_id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Deep expand focused node and all its siblings (recursively expand all descendants)
+    pub fn expand_all_siblings_deep(&mut self, tree: &dyn TreeLike) {
+        for sibling_id in self.selected_siblings(tree) {
+            self.expand_recursive(tree, sibling_id);
+        }
+    }
+
+    /// Number of nodes that [`expand_all_siblings_deep`](Self::expand_all_siblings_deep)
+    /// would show: the focused node, its siblings and all their descendants.
+    /// Counting stops after `limit + 1`, which is enough to tell a subtree is
+    /// larger than `limit` without walking all of it.
+    ///
+    /// Only children lists are looked at, so streaming trees count from their
+    /// index without reading the file.
+    pub fn siblings_deep_count(&self, tree: &dyn TreeLike, limit: usize) -> usize {
+        self.selected_siblings(tree)
+            .into_iter()
+            .flat_map(|id| tree.walk(id, Order::DepthFirst))
+            .take(limit.saturating_add(1))
+            .count()
+    }
+
+    // The focused node and its siblings, or nothing for the root
+    fn selected_siblings(&self, tree: &dyn TreeLike) -> Vec<usize> {
+        self.get_selected_node_id()
+            .and_then(|node_id| tree.get_parent(node_id))
+            .map(|parent_id| tree.get_children(parent_id))
+            .unwrap_or_default()
+    }
+
+    // Shallow collapse focused node and all its siblings
+    pub fn collapse_all_siblings(&mut self, tree: &dyn TreeLike) {
+        if let Some(index) = self.list_state.selected() {
+            if let Some((node_id, _)) = self.visible_nodes.get(index) {
+                if let Some(parent_id) = tree.get_parent(*node_id) {
+                    let siblings = tree.get_children(parent_id);
+                    for sibling_id in siblings {
+                        self.expanded.remove(&sibling_id);
+                    }
+                }
+            }
+        }
+    }
+
+    // Deep collapse focused node and all its siblings (recursively collapse all descendants)
+    pub fn collapse_all_siblings_deep(&mut self, tree: &dyn TreeLike) {
+        if let Some(index) = self.list_state.selected() {
+            if let Some((node_id, _)) = self.visible_nodes.get(index) {
+                if let Some(parent_id) = tree.get_parent(*node_id) {
+                    let siblings = tree.get_children(parent_id);
+                    for sibling_id in siblings {
+                        self.collapse_recursive(tree, sibling_id);
+                    }
+                }
+            }
+        }
+    }
+
+    // Helper: expand a node and all its descendants
+    fn expand_recursive(&mut self, tree: &dyn TreeLike, node_id: usize) {
+        for id in descendants_with_children(tree, node_id) {
+            self.expanded.insert(id);
+        }
+    }
+
+    // Helper: collapse a node and all its descendants
+    fn collapse_recursive(&mut self, tree: &dyn TreeLike, node_id: usize) {
+        for id in descendants_with_children(tree, node_id) {
+            self.expanded.remove(&id);
+        }
+    }
+
+    // Navigate to a specific node by ID, expanding the pages it is on and
+    // showing it again if it is hidden
+    pub fn navigate_to_node(&mut self, tree: &dyn TreeLike, node_id: usize) {
+        if !self.hidden.is_empty() {
+            self.unhide_path(tree, node_id);
+        }
+        self.rebuild_visible_nodes(tree);
+        let mut index = self.visible_nodes.iter().position(|(id, _)| *id == node_id);
+        if index.is_none() && self.array_page_size > 0 {
+            for (array, page) in self.pages_on_path(tree, node_id) {
+                self.expanded.insert_page(array, page);
+            }
+            self.rebuild_visible_nodes(tree);
+            index = self.visible_nodes.iter().position(|(id, _)| *id == node_id);
+        }
+        if let Some(index) = index {
+            self.list_state.select(Some(index));
+        }
+    }
+
+    /// Rebuild the rows after children were reordered, keeping the same node
+    /// selected
+    pub fn children_reordered(&mut self, tree: &dyn TreeLike) {
+        let selected = self.get_selected_node_id();
+        self.built_for = None;
+        if let Some(node_id) = selected {
+            self.navigate_to_node(tree, node_id);
+        }
     }
 
     // Expand a specific node by ID
@@ -524,8 +1769,1632 @@ impl TreeView {
         self.expanded.insert(node_id);
     }
 
-    // Get the set of search match node IDs for highlighting
-    pub fn get_highlighted_nodes(&self) -> &HashSet<usize> {
-        &self.expanded // Temporary - will be replaced with actual highlight set
+    /// IDs of the expanded nodes, as they were before any filter
+    pub fn expanded_nodes(&self) -> Vec<usize> {
+        self.unfiltered
+            .as_ref()
+            .unwrap_or(&self.expanded.ids)
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    // Get the set of search match node IDs for highlighting
+    pub fn get_highlighted_nodes(&self) -> &HashSet<usize> {
+        &self.expanded.ids // Temporary - will be replaced with actual highlight set
+    }
+}
+
+/// The value a row shows after its label: that of an attribute, or the
+/// content of a text, comment or doctype node
+fn node_value(node: &TreeNode) -> Option<&str> {
+    let attr = if node.is_attribute() {
+        node.attributes.first()
+    } else if matches!(node.node_type.as_str(), "text" | "comment" | "doctype") {
+        node.attributes.iter().find(|a| a.key == "content")
+    } else {
+        None
+    };
+    attr.map(|attr| attr.value.as_str())
+}
+
+/// The first line of a value
+fn first_line(value: &str) -> &str {
+    value.lines().next().unwrap_or_default()
+}
+
+/// Whether `node_id` is an `@attributes` virtual node
+fn is_virtual_attributes(tree: &dyn TreeLike, node_id: usize) -> bool {
+    tree.get_node(node_id)
+        .is_some_and(|node| node.is_virtual_attributes())
+}
+
+/// Whether a node has children, answered from the tree structure alone so it
+/// works even when the node itself fails to load
+fn has_children(tree: &dyn TreeLike, node_id: usize) -> bool {
+    !tree.get_children(node_id).is_empty()
+}
+
+/// One-line summary of a collapsed object or array from its first few children.
+///
+/// Objects render as `{name: "Alice", age: 30, …}` and arrays as
+/// `[3 items: "a", "b", "c"]`. Other node types have no preview.
+fn preview(
+    tree: &dyn TreeLike,
+    node: &TreeNode,
+    children: &[usize],
+    masked: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let is_object = node.is_object();
+    let is_array = node.is_array();
+    if children.is_empty() || !(is_object || is_array) {
+        return None;
+    }
+
+    let mut items: Vec<String> = children
+        .iter()
+        .take(PREVIEW_MAX_CHILDREN)
+        .map(|&child_id| match tree.get_node(child_id) {
+            Some(child) => {
+                let value = if masked(&child.label) {
+                    MASK.to_string()
+                } else {
+                    preview_value(&child)
+                };
+                if is_object {
+                    format!("{}: {}", child.label, value)
+                } else {
+                    value
+                }
+            }
+            None => "…".to_string(),
+        })
+        .collect();
+    if children.len() > PREVIEW_MAX_CHILDREN {
+        items.push("…".to_string());
+    }
+
+    Some(if is_object {
+        format!("{{{}}}", items.join(", "))
+    } else {
+        let noun = if children.len() == 1 { "item" } else { "items" };
+        format!("[{} {}: {}]", children.len(), noun, items.join(", "))
+    })
+}
+
+/// `key="value"` pairs for the first few attributes below an `@attributes` node
+fn attribute_summary(
+    tree: &dyn TreeLike,
+    attributes_id: usize,
+    masked: impl Fn(&str) -> bool,
+) -> String {
+    let children = tree.get_children(attributes_id);
+    let mut pairs: Vec<String> = children
+        .iter()
+        .take(PREVIEW_MAX_CHILDREN)
+        .filter_map(|&id| tree.get_node(id))
+        .map(
+            |attr| match attr.attributes.iter().find(|a| a.key == "value") {
+                Some(_) if masked(&attr.label) => format!("{}={}", attr.label, MASK),
+                Some(value) => format!("{}={:?}", attr.label, value.value),
+                None => attr.label.to_string(),
+            },
+        )
+        .collect();
+    if children.len() > PREVIEW_MAX_CHILDREN {
+        pairs.push("…".to_string());
+    }
+    pairs.join(" ")
+}
+
+/// Columns taken by `spans`
+fn spans_width(spans: &[Span]) -> usize {
+    spans.iter().map(Span::width).sum()
+}
+
+/// The width values of a row are cut to: the row's width, none for a
+/// wrapped row or full values, or [`VALUE_MAX_COLUMNS`] after the label for
+/// rows without a width limit
+fn value_width(row: &RowInfo) -> ValueWidth {
+    if row.wrap {
+        ValueWidth::Full
+    } else if row.width == usize::MAX {
+        ValueWidth::Columns(VALUE_MAX_COLUMNS)
+    } else {
+        ValueWidth::Row(row.width)
+    }
+}
+
+/// How much of a value a row shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueWidth {
+    /// All of it
+    Full,
+    /// At most this many columns
+    Columns(usize),
+    /// What fits in a row of this many columns
+    Row(usize),
+}
+
+impl ValueWidth {
+    /// Columns left for a value after `used` columns and the `: ` before it
+    fn columns(self, used: usize) -> usize {
+        match self {
+            Self::Full => usize::MAX,
+            Self::Columns(columns) => columns,
+            Self::Row(width) => width.saturating_sub(used + 2),
+        }
+    }
+
+    /// Leave `columns` columns at the end for something else
+    fn narrower(self, columns: usize) -> Self {
+        match self {
+            Self::Full => Self::Full,
+            Self::Columns(width) => Self::Columns(width.saturating_sub(columns)),
+            Self::Row(width) => Self::Row(width.saturating_sub(columns)),
+        }
+    }
+}
+
+/// Append ` text` to a row, cut to the columns left of `width`.
+///
+/// Nothing is added if fewer than a few columns remain.
+fn push_truncated(spans: &mut Vec<Span<'static>>, text: &str, style: Style, width: usize) {
+    let used = spans_width(spans);
+    // Leave room for the separating space
+    let available = width.saturating_sub(used + 1);
+    if available >= 4 {
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(truncate_to_width(text, available), style));
+    }
+}
+
+/// Append `text`, styling the pattern's occurrences with the highlight style.
+///
+/// Returns whether anything was highlighted.
+fn push_highlighted(
+    spans: &mut Vec<Span<'static>>,
+    text: &str,
+    style: Style,
+    highlight: Option<(Style, &SearchPattern)>,
+) -> bool {
+    let ranges = match highlight {
+        Some((_, pattern)) => pattern.find(text),
+        None => Vec::new(),
+    };
+    let Some((highlight_style, _)) = highlight.filter(|_| !ranges.is_empty()) else {
+        spans.push(Span::styled(text.to_string(), style));
+        return false;
+    };
+
+    let mut last = 0;
+    for range in ranges {
+        if range.start > last {
+            spans.push(Span::styled(text[last..range.start].to_string(), style));
+        }
+        spans.push(Span::styled(
+            text[range.clone()].to_string(),
+            highlight_style,
+        ));
+        last = range.end;
+    }
+    if last < text.len() {
+        spans.push(Span::styled(text[last..].to_string(), style));
+    }
+    true
+}
+
+/// Append `: value` after a label, cut to the columns `width` leaves for it,
+/// ending with `...` when cut.
+///
+/// Returns whether anything was highlighted.
+fn push_value(
+    spans: &mut Vec<Span<'static>>,
+    value: &str,
+    style: Style,
+    highlight: Option<(Style, &SearchPattern)>,
+    width: ValueWidth,
+) -> bool {
+    let columns = width.columns(spans_width(spans));
+    spans.push(Span::styled(": ", style));
+    let shown = if width == ValueWidth::Full {
+        value
+    } else {
+        cut_to_width(value, columns, 3)
+    };
+    let highlighted = push_highlighted(spans, shown, style, highlight);
+    if shown.len() < value.len() {
+        spans.push(Span::styled("...", style));
+    }
+    highlighted
+}
+
+/// Append `: value` like [`push_value`], or `: old → value` for a value
+/// that changed in a diff, with the old value in its own style.
+///
+/// Returns whether anything was highlighted.
+fn push_changed_value(
+    spans: &mut Vec<Span<'static>>,
+    old: Option<(&str, Style)>,
+    value: &str,
+    style: Style,
+    highlight: Option<(Style, &SearchPattern)>,
+    width: ValueWidth,
+) -> bool {
+    let Some((old, old_style)) = old else {
+        return push_value(spans, value, style, highlight, width);
+    };
+    // The old value gets at most half of the room, the new one the rest
+    let old_width = match width {
+        ValueWidth::Row(row_width) => {
+            let used = spans_width(spans);
+            ValueWidth::Row(used + row_width.saturating_sub(used) / 2)
+        }
+        other => other,
+    };
+    push_value(spans, old, old_style, None, old_width);
+    // The new value's `: ` becomes the arrow
+    let arrow = spans.len();
+    let highlighted = push_value(spans, value, style, highlight, width);
+    spans[arrow] = Span::styled(" → ", old_style);
+    highlighted
+}
+
+/// A child's value as shown inside a preview: strings are quoted, nested
+/// containers are elided
+fn preview_value(node: &TreeNode) -> String {
+    if node.is_object() {
+        return "{…}".to_string();
+    }
+    if node.is_array() {
+        return "[…]".to_string();
+    }
+    if node.node_type == TreeNode::LOADING_TYPE {
+        return "…".to_string();
+    }
+    match node.attributes.iter().find(|a| a.key == "value") {
+        Some(attr) if is_bare_scalar(&attr.value) => attr.value.clone(),
+        Some(attr) => format!("{:?}", attr.value),
+        None => node.label.to_string(),
+    }
+}
+
+/// Whether a value reads as a number, boolean or null rather than a string
+fn is_bare_scalar(value: &str) -> bool {
+    matches!(value, "true" | "false" | "null") || value.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+/// Drop the first `columns` columns of a row.
+///
+/// Half of a wide character at the edge becomes a space.
+fn skip_columns(spans: Vec<Span<'static>>, columns: usize) -> Vec<Span<'static>> {
+    if columns == 0 {
+        return spans;
+    }
+    let mut skipped = 0;
+    let mut kept = Vec::new();
+    for span in spans {
+        if skipped >= columns {
+            kept.push(span);
+            continue;
+        }
+        let mut text = String::new();
+        for c in span.content.chars() {
+            if skipped < columns {
+                skipped += c.width().unwrap_or(0);
+                if skipped > columns {
+                    text.push_str(&" ".repeat(skipped - columns));
+                }
+            } else {
+                text.push(c);
+            }
+        }
+        if !text.is_empty() {
+            kept.push(Span::styled(text, span.style));
+        }
+    }
+    kept
+}
+
+/// Break a row into lines of at most `width` columns, indenting every line
+/// after the first by `indent` columns
+fn wrap_spans(spans: Vec<Span<'static>>, width: usize, indent: usize) -> Vec<Line<'static>> {
+    let mut lines = vec![Vec::new()];
+    let mut used = 0;
+    for span in spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            // Lines always take at least one character past the indent
+            if used + w > width && used > indent {
+                if !text.is_empty()
+                    && let Some(line) = lines.last_mut()
+                {
+                    line.push(Span::styled(std::mem::take(&mut text), span.style));
+                }
+                lines.push(vec![Span::raw(" ".repeat(indent))]);
+                used = indent;
+            }
+            text.push(c);
+            used += w;
+        }
+        if !text.is_empty()
+            && let Some(line) = lines.last_mut()
+        {
+            line.push(Span::styled(text, span.style));
+        }
+    }
+    lines.into_iter().map(Line::from).collect()
+}
+
+/// The longest start of `text` that fits in `width` columns, or if it has
+/// to be cut, in `width - marker` columns to leave room for a marker
+fn cut_to_width(text: &str, width: usize, marker: usize) -> &str {
+    let mut used = 0;
+    let mut fits = None;
+    for (index, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width.saturating_sub(marker) && fits.is_none() {
+            fits = Some(index);
+        }
+        if used > width {
+            return &text[..fits.unwrap_or(index)];
+        }
+    }
+    text
+}
+
+/// Cut `text` to at most `width` terminal columns by replacing its middle
+/// with `…`, keeping its start and end
+pub(crate) fn truncate_middle(text: &str, width: usize) -> Cow<'_, str> {
+    let total: usize = text.chars().map(|c| c.width().unwrap_or(0)).sum();
+    if total <= width {
+        return Cow::Borrowed(text);
+    }
+    // The start gets the odd column
+    let room = width.saturating_sub(1);
+    let head = cut_to_width(text, room - room / 2, 0);
+    let mut tail_width = 0;
+    let tail_start = text
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| {
+            tail_width += c.width().unwrap_or(0);
+            tail_width <= room / 2
+        })
+        .last()
+        .map_or(text.len(), |(index, _)| index);
+    Cow::Owned(format!("{}…{}", head, &text[tail_start..]))
+}
+
+/// Cut `text` to at most `width` terminal columns, ending with `…` when shortened
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> String {
+    let total: usize = text.chars().map(|c| c.width().unwrap_or(0)).sum();
+    if total <= width {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        // Keep one column for the ellipsis
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+/// A byte count such as `512 B`, `1.2 KB` or `3.4 MB`
+pub fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Format a number with comma thousands separators, e.g. `1,234,567`
+pub fn format_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// Error node shown in place of a node that could not be loaded, with its
+/// label if the tree knows it without loading the node
+pub(crate) fn unavailable_node(tree: &dyn TreeLike, node_id: usize, error: &XtvError) -> TreeNode {
+    let label = tree
+        .get_label(node_id)
+        .unwrap_or_else(|| format!("#{}", node_id));
+    let message = match error {
+        XtvError::NodeLoad { source, .. } => source.to_string(),
+        _ => error.to_string(),
+    };
+    let mut node = TreeNode::new(label, TreeNode::ERROR_TYPE);
+    node.add_attribute("error", message);
+    node
+}
+
+/// A node and all its descendants that have children
+fn descendants_with_children(tree: &dyn TreeLike, node_id: usize) -> Vec<usize> {
+    tree.walk(node_id, Order::DepthFirst)
+        .map(|(id, _)| id)
+        .filter(|&id| has_children(tree, id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Tree, TreeVariant};
+    use ratatui::{Terminal, backend::TestBackend, style::Color};
+    use std::cell::RefCell;
+    use std::collections::BTreeSet;
+
+    /// A root with `count` leaf children
+    fn flat_tree(count: usize) -> TreeVariant {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        for i in 0..count {
+            tree.add_child_node(0, TreeNode::new(format!("item{i}"), "string"));
+        }
+        TreeVariant::from(tree)
+    }
+
+    fn draw(terminal: &mut Terminal<TestBackend>, view: &mut TreeView, tree: &dyn TreeLike) {
+        terminal
+            .draw(|f| {
+                view.render(
+                    f,
+                    f.size(),
+                    tree,
+                    &Theme::dark(),
+                    &SearchHighlight::default(),
+                )
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_navigation_does_not_rebuild_visible_nodes() {
+        let tree = flat_tree(1000);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+
+        for _ in 0..100 {
+            view.navigate_down(&tree);
+            draw(&mut terminal, &mut view, &tree);
+        }
+        view.navigate_to_last_line(&tree);
+        draw(&mut terminal, &mut view, &tree);
+
+        assert_eq!(view.rebuild_count(), 1);
+        assert_eq!(view.get_selected_node_id(), Some(1000));
+    }
+
+    #[test]
+    fn test_expansion_change_rebuilds_once() {
+        let tree = flat_tree(10);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        view.toggle_expand(&tree); // collapse the root
+        draw(&mut terminal, &mut view, &tree);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.rebuild_count(), 2);
+        assert_eq!(view.visible_nodes.len(), 1);
+
+        view.expand_node(tree.root_id()); // no-op when already expanded
+        view.expand_node(tree.root_id());
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.rebuild_count(), 3);
+    }
+
+    /// `root { a { b { c } }, d, e }`, fully expanded
+    fn expanded_nested_tree() -> (TreeVariant, TreeView) {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let a = tree.add_child_node(0, TreeNode::new("a", "object")); // 1
+        let b = tree.add_child_node(a, TreeNode::new("b", "object")); // 2
+        tree.add_child_node(b, TreeNode::new("c", "string")); // 3
+        tree.add_child_node(0, TreeNode::new("d", "string")); // 4
+        tree.add_child_node(0, TreeNode::new("e", "string")); // 5
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        view.expand_all(&tree, None);
+        (tree, view)
+    }
+
+    #[test]
+    fn test_collapse_above_selection_selects_visible_ancestor() {
+        let (tree, mut view) = expanded_nested_tree();
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+
+        // Collapsing `a` from elsewhere, e.g. with the mouse, hides `c`
+        view.navigate_to_node(&tree, 3);
+        view.expanded.remove(&1);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.get_selected_node_id(), Some(1));
+        assert_eq!(view.list_state.selected(), Some(1));
+
+        // A selection below the collapsed node moves up with its row
+        view.expand_node(1);
+        view.navigate_to_node(&tree, 5);
+        assert_eq!(view.list_state.selected(), Some(5));
+        view.expanded.remove(&1);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.get_selected_node_id(), Some(5));
+        assert_eq!(view.list_state.selected(), Some(3));
+    }
+
+    #[test]
+    fn test_collapse_all_keeps_selection_in_range() {
+        let (tree, mut view) = expanded_nested_tree();
+        view.navigate_to_node(&tree, 3);
+        view.collapse_recursive(&tree, tree.root_id());
+        view.navigate_by(0, &tree);
+        assert_eq!(view.visible_nodes.len(), 1);
+        assert_eq!(view.list_state.selected(), Some(0));
+
+        let (tree, mut view) = expanded_nested_tree();
+        view.navigate_to_node(&tree, 3);
+        view.collapse_all(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(1));
+        view.navigate_to_node(&tree, 5);
+        view.collapse_all_siblings_deep(&tree);
+        view.navigate_by(0, &tree);
+        assert_eq!(view.get_selected_node_id(), Some(5));
+    }
+
+    #[test]
+    fn test_viewport_follows_selection() {
+        let tree = flat_tree(100);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+
+        view.navigate_to_node(&tree, 50);
+        draw(&mut terminal, &mut view, &tree);
+
+        let buffer = terminal.backend().buffer();
+        let last_row: String = (0..40).map(|x| buffer.get(x, 4).symbol()).collect();
+        assert!(last_row.starts_with(">> "));
+        assert!(last_row.contains("item49"));
+        assert_eq!(view.list_state.offset(), 46);
+    }
+
+    /// A tree whose root lists children that don't exist, like a streaming
+    /// tree whose loads fail
+    fn tree_with_missing_nodes() -> TreeVariant {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let present = tree.add_child_node(0, TreeNode::new("present", "object"));
+        tree.add_child_node(present, TreeNode::new("leaf", "string"));
+        tree.get_node_mut(0).unwrap().children.extend([98, 99]);
+        TreeVariant::from(tree)
+    }
+
+    #[test]
+    fn test_navigation_on_empty_view_is_noop() {
+        let tree = flat_tree(0);
+        let mut view = TreeView::new(tree.root_id());
+        // Nothing has been rendered, so there are no visible rows yet
+        view.navigate_up();
+        view.toggle_expand(&tree);
+        view.expand(&tree);
+        view.collapse(&tree);
+        view.smart_left(&tree, 1);
+        view.smart_right(&tree, 1);
+        view.collapse_parent(&tree);
+        view.navigate_to_next_sibling(&tree, 1);
+        view.expand_all_siblings(&tree);
+        assert_eq!(view.get_selected_node_id(), None);
+
+        view.navigate_down(&tree);
+        view.navigate_down(&tree);
+        view.navigate_to_last_line(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(tree.root_id()));
+    }
+
+    #[test]
+    fn test_missing_nodes_render_as_placeholders() {
+        let tree = tree_with_missing_nodes();
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(60, 10)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        let buffer = terminal.backend().buffer();
+        let row = |y| -> String { (0..60).map(|x| buffer.get(x, y).symbol()).collect() };
+        assert!(row(2).contains("#98"));
+        assert!(row(3).contains("#99: Invalid node ID: 99"));
+    }
+
+    #[test]
+    fn test_actions_on_missing_nodes_do_not_panic() {
+        let tree = tree_with_missing_nodes();
+        let mut view = TreeView::new(tree.root_id());
+        view.navigate_down(&tree);
+        view.expand_all_siblings(&tree);
+        view.expand_all_siblings_deep(&tree);
+        view.navigate_to_last_line(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(99));
+
+        view.toggle_expand(&tree);
+        view.expand(&tree);
+        view.smart_right(&tree, 1);
+        view.smart_left(&tree, 1);
+        // The missing node has no known parent, so the selection stays put
+        assert_eq!(view.get_selected_node_id(), Some(99));
+    }
+
+    /// A root with `count` children, each with `count` leaf children
+    fn nested_tree(count: usize) -> TreeVariant {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        for i in 0..count {
+            let child = tree.add_child_node(0, TreeNode::new(format!("child{i}"), "object"));
+            for j in 0..count {
+                tree.add_child_node(child, TreeNode::new(format!("leaf{j}"), "string"));
+            }
+        }
+        TreeVariant::from(tree)
+    }
+
+    pub(super) fn visible_count(view: &mut TreeView, tree: &dyn TreeLike) -> usize {
+        view.rebuild_visible_nodes(tree);
+        view.visible_nodes.len()
+    }
+
+    #[test]
+    fn test_expand_to_depth() {
+        let tree = nested_tree(3);
+
+        let mut view = TreeView::new(tree.root_id());
+        assert!(view.expand_to_depth(&tree, Some(0), None));
+        assert_eq!(visible_count(&mut view, &tree), 4);
+
+        let mut view = TreeView::new(tree.root_id());
+        assert!(view.expand_to_depth(&tree, Some(2), None));
+        assert_eq!(visible_count(&mut view, &tree), 13);
+
+        let mut view = TreeView::new(tree.root_id());
+        assert!(view.expand_to_depth(&tree, None, None));
+        assert_eq!(visible_count(&mut view, &tree), 13);
+    }
+
+    #[test]
+    fn test_expand_to_depth_stops_at_max_nodes() {
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+
+        // Root plus the first child
+        assert!(!view.expand_to_depth(&tree, None, Some(2)));
+        assert_eq!(visible_count(&mut view, &tree), 7);
+    }
+
+    #[test]
+    fn test_navigate_by_clamps_to_rows() {
+        let tree = flat_tree(20);
+        let mut view = TreeView::new(tree.root_id());
+
+        view.navigate_by(10, &tree);
+        assert_eq!(view.get_selected_node_id(), Some(10));
+        view.navigate_by(100, &tree);
+        assert_eq!(view.get_selected_node_id(), Some(20));
+        view.navigate_by(-5, &tree);
+        assert_eq!(view.get_selected_node_id(), Some(15));
+        view.navigate_by(-100, &tree);
+        assert_eq!(view.get_selected_node_id(), Some(0));
+        assert_eq!(view.rebuild_count(), 1);
+    }
+
+    #[test]
+    fn test_hit_test_accounts_for_offset_and_icon() {
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 4)).unwrap();
+        let area = Rect::new(0, 0, 40, 4);
+        view.expand_to_depth(&tree, None, None);
+        view.navigate_to_last_line(&tree);
+        draw(&mut terminal, &mut view, &tree);
+        let offset = view.list_state.offset();
+        assert!(offset > 0);
+
+        // Top row shows the node at the offset
+        let hit = view.hit_test(area, 10, 0).unwrap();
+        assert_eq!(hit.index, offset);
+        assert!(!hit.on_icon);
+
+        // Child rows (depth 1) have their icon after the highlight symbol and indent
+        let child_row = view.visible_nodes[offset..]
+            .iter()
+            .position(|&(_, depth)| depth == 1)
+            .unwrap();
+        let hit = view.hit_test(area, 5, child_row as u16).unwrap();
+        assert!(hit.on_icon);
+
+        assert_eq!(view.hit_test(area, 10, 4), None);
+    }
+
+    #[test]
+    fn test_format_thousands() {
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1234), "1,234");
+        assert_eq!(format_thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1229), "1.2 KB");
+        assert_eq!(format_size(3 * 1024 * 1024 + 400 * 1024), "3.4 MB");
+    }
+
+    #[test]
+    fn test_size_hint_on_large_values() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        for (key, size) in [("small", 10), ("blob", 3 * 1024)] {
+            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", "x".repeat(size));
+            tree.add_child_node(0, node);
+        }
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        view.set_size_hint_threshold(1024);
+        let mut terminal = Terminal::new(TestBackend::new(30, 3)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+        let row = |terminal: &Terminal<TestBackend>, y| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..30).map(|x| buffer.get(x, y).symbol()).collect()
+        };
+        assert_eq!(row(&terminal, 1), "       small: xxxxxxxxxx      ");
+        assert_eq!(row(&terminal, 2), "       blob (3.0 KB): xxxxx...");
+
+        view.set_size_hint_threshold(0);
+        draw(&mut terminal, &mut view, &tree);
+        assert!(!row(&terminal, 2).contains("KB"));
+    }
+
+    #[test]
+    fn test_friendly_names_after_attribute_keys() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        for key in ["memberOf [0]", "mystery"] {
+            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", "x");
+            tree.add_child_node(0, node);
+        }
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        view.set_attribute_labels(Some(AttributeLabels::default()));
+        let mut terminal = Terminal::new(TestBackend::new(50, 3)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+        let row = |terminal: &Terminal<TestBackend>, y| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..50).map(|x| buffer.get(x, y).symbol()).collect()
+        };
+        assert_eq!(
+            row(&terminal, 1).trim_end(),
+            "       memberOf [0] (Member of · membership): x"
+        );
+        assert_eq!(row(&terminal, 2).trim_end(), "       mystery: x");
+    }
+
+    #[test]
+    fn test_decoded_values_after_raw_ones() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let mut node = TreeNode::new("userAccountControl", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute("value", "512");
+        node.add_attribute("decoded", "NORMAL_ACCOUNT");
+        tree.add_child_node(0, node);
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(50, 2)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+        let buffer = terminal.backend().buffer();
+        let row: String = (0..50).map(|x| buffer.get(x, 1).symbol()).collect();
+        assert_eq!(
+            row.trim_end(),
+            "       userAccountControl: 512 (NORMAL_ACCOUNT)"
+        );
+    }
+
+    pub(super) fn labels(view: &mut TreeView, tree: &dyn TreeLike) -> Vec<String> {
+        let rows = view.visible_rows(tree, &Theme::default(), &TextOptions::default());
+        rows.iter()
+            .map(|row| {
+                let text: String = row.spans.iter().map(|span| span.content.as_ref()).collect();
+                text.trim().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_line_values_expand_to_their_lines() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let mut node = TreeNode::new("script", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute("value", "set -e\nmake\nmake install\n");
+        tree.add_child_node(0, node);
+        let mut node = TreeNode::new("name", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute("value", "xtv");
+        tree.add_child_node(0, node);
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        assert_eq!(
+            labels(&mut view, &tree)[1..],
+            ["script: set -e ⏎×3", "name: xtv"]
+        );
+
+        // Single-line values don't expand
+        view.navigate_to_node(&tree, 2);
+        view.toggle_expand(&tree);
+        assert_eq!(visible_count(&mut view, &tree), 3);
+
+        view.navigate_to_node(&tree, 1);
+        view.smart_right(&tree, 1);
+        assert_eq!(
+            labels(&mut view, &tree)[1..],
+            ["script: ⏎×3", "set -e", "make", "make install", "name: xtv"]
+        );
+
+        // The lines are rows of their own, below the leaf
+        view.smart_right(&tree, 1);
+        view.navigate_to_next_sibling(&tree, 5);
+        assert_eq!(view.position(), Some((5, 6)));
+        assert_eq!(view.get_selected_node_id(), Some(1));
+        view.navigate_down(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(2));
+        view.navigate_up();
+        view.navigate_to_parent(&tree, 1);
+        assert_eq!(view.position(), Some((2, 6)));
+
+        // The same key collapses them again, also from one of the lines
+        view.toggle_expand(&tree);
+        assert_eq!(visible_count(&mut view, &tree), 3);
+        view.toggle_expand(&tree);
+        view.navigate_by(2, &tree);
+        view.smart_left(&tree, 1);
+        assert_eq!(visible_count(&mut view, &tree), 3);
+        assert_eq!(view.position(), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_long_values_show_their_first_lines() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let mut node = TreeNode::new("log", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute("value", vec!["line"; MAX_VALUE_LINES + 5].join("\n"));
+        tree.add_child_node(0, node);
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        view.expand_node(1);
+        let labels = labels(&mut view, &tree);
+        assert_eq!(labels.len(), MAX_VALUE_LINES + 3);
+        assert_eq!(labels.last().unwrap(), "… 5 more lines");
+    }
+
+    #[test]
+    fn test_child_counts_on_collapsed_nodes() {
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        let row = |terminal: &Terminal<TestBackend>, y| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..40).map(|x| buffer.get(x, y).symbol()).collect()
+        };
+        // The expanded root has no count, its collapsed children do
+        assert!(!row(&terminal, 0).contains('('));
+        assert!(row(&terminal, 1).contains("child0 (3)"));
+
+        view.set_show_child_counts(false);
+        draw(&mut terminal, &mut view, &tree);
+        assert!(!row(&terminal, 1).contains("(3)"));
+    }
+
+    /// A collapsed `user` object with name, age and a `tags` array
+    fn object_tree() -> TreeVariant {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let user = tree.add_child_node(0, TreeNode::new("user", "object"));
+        for (key, value) in [("name", "Alice"), ("age", "30")] {
+            let mut node = TreeNode::new(key, TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", value);
+            tree.add_child_node(user, node);
+        }
+        let tags = tree.add_child_node(user, TreeNode::new("tags", "array"));
+        for (i, value) in ["a", "b", "c"].iter().enumerate() {
+            let mut node = TreeNode::new(format!("[{i}]"), TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", *value);
+            tree.add_child_node(tags, node);
+        }
+        TreeVariant::from(tree)
+    }
+
+    #[test]
+    fn test_preview_of_objects_and_arrays() {
+        let tree = object_tree();
+        let node = |id| tree.get_node(id).unwrap();
+
+        assert_eq!(
+            preview(&tree, &node(1), &tree.get_children(1), |_| false).unwrap(),
+            r#"{name: "Alice", age: 30, tags: […]}"#
+        );
+        assert_eq!(
+            preview(&tree, &node(4), &tree.get_children(4), |_| false).unwrap(),
+            r#"[3 items: "a", "b", "c"]"#
+        );
+        // Leaves and expanded nodes (no hidden children) have no preview
+        assert_eq!(preview(&tree, &node(2), &[], |_| false), None);
+        assert_eq!(preview(&tree, &node(1), &[], |_| false), None);
+    }
+
+    #[test]
+    fn test_preview_peeks_at_a_bounded_number_of_children() {
+        let mut tree = Tree::new(TreeNode::new("root", "array"));
+        for i in 0..100 {
+            let mut node = TreeNode::new(format!("[{i}]"), TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", i.to_string());
+            tree.add_child_node(0, node);
+        }
+        let tree = TreeVariant::from(tree);
+        let root = tree.get_node(0).unwrap();
+
+        assert_eq!(
+            preview(&tree, &root, &tree.get_children(0), |_| false).unwrap(),
+            "[100 items: 0, 1, 2, 3, 4, …]"
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("abcdef", 6), "abcdef");
+        assert_eq!(truncate_to_width("abcdef", 4), "abc…");
+        // Wide characters take two columns each
+        assert_eq!(truncate_to_width("日本語テキスト", 6), "日本…");
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("abcdef", 6), "abcdef");
+        assert_eq!(truncate_middle("abcdefgh", 6), "abc…gh");
+        assert_eq!(truncate_middle("日本語テキスト", 8), "日本…ト");
+        // Half of a wide character doesn't fit
+        assert_eq!(truncate_middle("日本語テキスト", 7), "日…ト");
+        assert_eq!(truncate_middle("abc", 1), "…");
+    }
+
+    #[test]
+    fn test_values_fill_the_width() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let mut node = TreeNode::new("a_rather_long_key_name", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute("value", "v".repeat(500));
+        tree.add_child_node(0, node);
+        let tree = TreeVariant::from(tree);
+
+        let row = |width: u16| -> String {
+            let mut view = TreeView::new(tree.root_id());
+            let mut terminal = Terminal::new(TestBackend::new(width, 3)).unwrap();
+            draw(&mut terminal, &mut view, &tree);
+            let buffer = terminal.backend().buffer();
+            (0..width).map(|x| buffer.get(x, 1).symbol()).collect()
+        };
+
+        // The value ends exactly at the right edge, however wide
+        for width in [60, 120, 300] {
+            let text = row(width);
+            assert!(text.ends_with("v..."), "{text}");
+            let shown = text.matches('v').count();
+            assert_eq!(
+                shown,
+                width as usize - 3 - 4 - "a_rather_long_key_name: ".len() - 3
+            );
+        }
+
+        // Narrow rows cut the label in the middle to leave room for the value
+        let text = row(30);
+        assert_eq!(text, "       a_rat…_name: vvvvvvv...");
+        // Labels keep a few columns even if the value is left with none
+        let text = row(20);
+        assert_eq!(text, "       a_ra…ame: ...");
+    }
+
+    #[test]
+    fn test_preview_is_drawn_and_can_be_hidden() {
+        let tree = object_tree();
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 3)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        let row = |terminal: &Terminal<TestBackend>| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..40).map(|x| buffer.get(x, 1).symbol()).collect()
+        };
+        // Truncated to the 40 columns of the terminal
+        assert_eq!(
+            row(&terminal).trim_end(),
+            r#"     ▶ user (3) [object] {name: "Alice"…"#
+        );
+
+        view.set_show_previews(false);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(row(&terminal).trim_end(), "     ▶ user (3) [object]");
+    }
+
+    #[test]
+    fn test_matched_fragment_is_highlighted() {
+        let tree = object_tree();
+        let mut view = TreeView::new(tree.root_id());
+        view.expand_node(1);
+        let mut terminal = Terminal::new(TestBackend::new(40, 4)).unwrap();
+        let pattern = SearchPattern::new("LIC", false);
+        let search = SearchHighlight {
+            matches: &[2],
+            current: Some(0),
+            pattern: Some(&pattern),
+        };
+        terminal
+            .draw(|f| view.render(f, f.size(), &tree, &Theme::dark(), &search))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let row: String = (0..40).map(|x| buffer.get(x, 2).symbol()).collect();
+        let start = row.chars().position(|c| c == 'A').unwrap() as u16;
+        let bg = |x: u16| buffer.get(x, 2).bg;
+        let current = Theme::dark().search_current;
+        // Only "lic" of "Alice" is marked
+        assert_ne!(bg(start), current);
+        assert_eq!(bg(start + 1), current);
+        assert_eq!(bg(start + 3), current);
+        assert_ne!(bg(start + 4), current);
+    }
+
+    #[test]
+    fn test_long_multibyte_values_are_cut_on_characters() {
+        let mut spans = Vec::new();
+        let value = "ü".repeat(VALUE_MAX_COLUMNS + 5);
+        let pattern = SearchPattern::new("Ü", false);
+        let highlight = Some((Style::default().bg(Color::Yellow), &pattern));
+        let width = ValueWidth::Columns(VALUE_MAX_COLUMNS);
+        assert!(push_value(
+            &mut spans,
+            &value,
+            Style::default(),
+            highlight,
+            width
+        ));
+
+        let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(text, format!(": {}...", "ü".repeat(VALUE_MAX_COLUMNS - 3)));
+
+        // Wide characters count twice
+        let mut spans = vec![Span::raw("key")];
+        push_value(
+            &mut spans,
+            &"日".repeat(20),
+            Style::default(),
+            None,
+            ValueWidth::Row(20),
+        );
+        let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(text, format!("key: {}...", "日".repeat(6)));
+        assert_eq!(spans_width(&spans), 20);
+    }
+
+    /// `<div id="main" class="x"><p/><span lang="en"/></div>`, with `@attributes`
+    /// as the first child of each element like the XML parser builds it
+    fn element_tree() -> TreeVariant {
+        fn add_attributes(tree: &mut Tree, element: usize, attributes: &[(&str, &str)]) {
+            let virtual_id = tree.add_child_node(
+                element,
+                TreeNode::new("@attributes", TreeNode::VIRTUAL_ATTRIBUTES_TYPE),
+            );
+            for (key, value) in attributes {
+                let mut node = TreeNode::new(*key, TreeNode::ATTRIBUTE_TYPE);
+                node.add_attribute("value", *value);
+                tree.add_child_node(virtual_id, node);
+            }
+        }
+
+        let mut tree = Tree::new(TreeNode::new("div", "element"));
+        add_attributes(&mut tree, 0, &[("class", "x"), ("id", "main")]); // 1, 2, 3
+        tree.add_child_node(0, TreeNode::new("p", "element")); // 4
+        let span = tree.add_child_node(0, TreeNode::new("span", "element")); // 5
+        add_attributes(&mut tree, span, &[("lang", "en")]); // 6, 7
+        TreeVariant::from(tree)
+    }
+
+    #[test]
+    fn test_inline_attributes_hide_virtual_rows() {
+        let tree = element_tree();
+        let mut view = TreeView::new(tree.root_id());
+        assert_eq!(visible_count(&mut view, &tree), 4);
+
+        view.set_inline_attributes(true);
+        view.rebuild_visible_nodes(&tree);
+        let ids: Vec<usize> = view.visible_nodes.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [0, 4, 5]);
+
+        // Sibling navigation skips the hidden @attributes node
+        view.navigate_to_node(&tree, 5);
+        view.navigate_to_first_sibling(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(4));
+    }
+
+    #[test]
+    fn test_expand_and_collapse_treat_attributes_like_containers() {
+        let tree = element_tree();
+        let mut view = TreeView::new(tree.root_id());
+        view.rebuild_visible_nodes(&tree);
+
+        // `l` on the collapsed @attributes node expands it, then descends
+        view.navigate_to_node(&tree, 1);
+        view.smart_right(&tree, 1);
+        assert!(view.expanded.contains(&1));
+        assert_eq!(view.get_selected_node_id(), Some(1));
+        view.smart_right(&tree, 1);
+        assert_eq!(view.get_selected_node_id(), Some(2));
+
+        // Toggling a leaf does nothing, `l` on it stays put
+        let version = view.expanded.version;
+        view.toggle_expand(&tree);
+        view.expand(&tree);
+        view.smart_right(&tree, 1);
+        assert_eq!(view.expanded.version, version);
+        assert_eq!(view.get_selected_node_id(), Some(2));
+
+        // `h` on a leaf goes to its parent, then collapses it; `c` stays put
+        view.smart_left(&tree, 2);
+        assert_eq!(view.get_selected_node_id(), Some(1));
+        assert!(!view.expanded.contains(&1));
+        view.toggle_expand(&tree);
+        view.collapse(&tree);
+        assert!(!view.expanded.contains(&1));
+        assert_eq!(view.get_selected_node_id(), Some(1));
+
+        // An @attributes node with a single child behaves the same
+        view.navigate_to_node(&tree, 5);
+        view.smart_right(&tree, 3);
+        assert_eq!(view.get_selected_node_id(), Some(7));
+        assert!(view.expanded.contains(&5) && view.expanded.contains(&6));
+    }
+
+    #[test]
+    fn test_nodes_without_visible_children_are_leaves() {
+        let tree = element_tree();
+        let mut view = TreeView::new(tree.root_id());
+        view.set_inline_attributes(true);
+        view.rebuild_visible_nodes(&tree);
+
+        // `p` has no children, `span` only its inlined @attributes
+        for node_id in [4, 5] {
+            view.navigate_to_node(&tree, node_id);
+            view.toggle_expand(&tree);
+            view.smart_right(&tree, 1);
+            assert!(!view.expanded.contains(&node_id));
+            assert_eq!(view.get_selected_node_id(), Some(node_id));
+        }
+        let mut terminal = Terminal::new(TestBackend::new(40, 4)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+        let buffer = terminal.backend().buffer();
+        let span_row: String = (0..40).map(|x| buffer.get(x, 2).symbol()).collect();
+        assert!(span_row.starts_with(">>     span"), "{span_row}");
+
+        // The same goes for nodes whose children are all filtered out
+        let mock = MockTree::default();
+        let mut view = TreeView::new(mock.root_id());
+        view.set_filter(&mock, Some(&[1]));
+        view.navigate_to_node(&mock, 1);
+        view.toggle_expand(&mock);
+        view.smart_right(&mock, 1);
+        assert!(!view.expanded.contains(&1));
+        assert_eq!(view.get_selected_node_id(), Some(1));
+    }
+
+    #[test]
+    fn test_inline_attributes_are_summarized_on_the_element_row() {
+        let tree = element_tree();
+        let mut view = TreeView::new(tree.root_id());
+        view.set_inline_attributes(true);
+        let mut terminal = Terminal::new(TestBackend::new(50, 4)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        let row = |terminal: &Terminal<TestBackend>, y| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..50).map(|x| buffer.get(x, y).symbol()).collect()
+        };
+        assert_eq!(
+            row(&terminal, 0).trim_end(),
+            r#">> ▼ div [element] class="x" id="main""#
+        );
+        // An element whose only child is @attributes is drawn as a leaf
+        assert_eq!(
+            row(&terminal, 2).trim_end(),
+            r#"       span [element] lang="en""#
+        );
+    }
+
+    #[test]
+    fn test_row_node_maps_attributes_to_their_element() {
+        let tree = element_tree();
+        let mut view = TreeView::new(tree.root_id());
+        assert_eq!(view.row_node(&tree, 3), 3);
+
+        view.set_inline_attributes(true);
+        assert_eq!(view.row_node(&tree, 1), 0);
+        assert_eq!(view.row_node(&tree, 3), 0);
+        assert_eq!(view.row_node(&tree, 7), 5);
+        assert_eq!(view.row_node(&tree, 4), 4);
+    }
+
+    #[test]
+    fn test_line_number_gutter() {
+        let tree = flat_tree(150);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(30, 4)).unwrap();
+        let row = |terminal: &Terminal<TestBackend>, y| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..12).map(|x| buffer.get(x, y).symbol()).collect()
+        };
+
+        // 151 rows need three digits plus one column of padding
+        view.set_line_numbers(LineNumbers::Absolute);
+        view.navigate_by(2, &tree);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(row(&terminal, 0), "  1    ▼ roo");
+        assert_eq!(row(&terminal, 2), "  3 >>     i");
+
+        view.set_line_numbers(LineNumbers::Relative);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(row(&terminal, 0), "  2    ▼ roo");
+        assert_eq!(row(&terminal, 2), "  3 >>     i");
+        assert_eq!(row(&terminal, 3), "  1        i");
+    }
+
+    #[test]
+    fn test_icon_sets_keep_labels_aligned() {
+        let tree = flat_tree(1);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(20, 2)).unwrap();
+        let row = |terminal: &Terminal<TestBackend>, y| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..12).map(|x| buffer.get(x, y).symbol()).collect()
+        };
+
+        view.set_icons(IconSet::Ascii);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(row(&terminal, 0), ">> - root [o");
+        assert_eq!(row(&terminal, 1), "     * item0");
+
+        // The type icon comes after the expander, on every row
+        view.set_icons(IconSet::Nerd);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(row(&terminal, 0), ">> \u{eab4} \u{ea8b} root ");
+        assert_eq!(row(&terminal, 1), "       \u{eb8d} ite");
+    }
+
+    #[test]
+    fn test_hit_test_skips_the_gutter() {
+        let tree = nested_tree(2);
+        let mut view = TreeView::new(tree.root_id());
+        view.set_line_numbers(LineNumbers::Absolute);
+        let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        let area = Rect::new(0, 0, 40, 5);
+        // Two columns of gutter, then ">> " and one level of indent
+        assert!(view.hit_test(area, 7, 1).unwrap().on_icon);
+        assert!(!view.hit_test(area, 5, 1).unwrap().on_icon);
+    }
+
+    #[test]
+    fn test_position() {
+        let tree = flat_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        view.navigate_to_last_line(&tree);
+        assert_eq!(view.position(), Some((4, 4)));
+    }
+
+    #[test]
+    fn test_counted_tree_motions() {
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        visible_count(&mut view, &tree);
+
+        // From the expanded root: descend into child0, then expand it
+        view.smart_right(&tree, 2);
+        assert_eq!(view.get_selected_node_id(), Some(1));
+        assert!(view.expanded.contains(&1));
+        view.smart_right(&tree, 5);
+        assert_eq!(view.get_selected_node_id(), Some(2));
+
+        view.navigate_to_parent(&tree, 5);
+        assert_eq!(view.get_selected_node_id(), Some(0));
+
+        view.navigate_to_node(&tree, 1);
+        view.navigate_to_next_sibling(&tree, 5);
+        assert_eq!(view.get_selected_node_id(), Some(9));
+        view.navigate_to_previous_sibling(&tree, 1);
+        assert_eq!(view.get_selected_node_id(), Some(5));
+
+        // Up from leaf to child0, collapse child0, then up to the root
+        view.navigate_to_node(&tree, 2);
+        view.smart_left(&tree, 3);
+        assert_eq!(view.get_selected_node_id(), Some(0));
+        assert!(!view.expanded.contains(&1));
+    }
+
+    /// A complete tree of `FANOUT` children per node and `LEVELS` levels below
+    /// the root, made up on demand, that remembers which nodes were read
+    #[derive(Default)]
+    struct MockTree {
+        loaded: RefCell<BTreeSet<usize>>,
+    }
+
+    impl MockTree {
+        const FANOUT: usize = 3;
+        const LEVELS: u32 = 3;
+        /// IDs are numbered level by level
+        const COUNT: usize = (Self::FANOUT.pow(Self::LEVELS + 1) - 1) / (Self::FANOUT - 1);
+    }
+
+    impl TreeLike for MockTree {
+        fn root_id(&self) -> usize {
+            0
+        }
+
+        fn get_node(&self, id: usize) -> Option<Cow<'_, TreeNode>> {
+            if id >= Self::COUNT {
+                return None;
+            }
+            self.loaded.borrow_mut().insert(id);
+            let mut node = TreeNode::new(format!("node{id}"), "object");
+            node.children = self.get_children(id);
+            node.parent_id = self.get_parent(id);
+            Some(Cow::Owned(node))
+        }
+
+        fn get_children(&self, id: usize) -> Vec<usize> {
+            let first = Self::FANOUT * id + 1;
+            if first < Self::COUNT {
+                (first..first + Self::FANOUT).collect()
+            } else {
+                Vec::new()
+            }
+        }
+
+        fn get_parent(&self, id: usize) -> Option<usize> {
+            (id > 0 && id < Self::COUNT).then(|| (id - 1) / Self::FANOUT)
+        }
+
+        fn node_count(&self) -> usize {
+            Self::COUNT
+        }
+    }
+
+    #[test]
+    fn test_navigation_over_a_mock_tree() {
+        let tree = MockTree::default();
+        let mut view = TreeView::new(tree.root_id());
+        assert_eq!(visible_count(&mut view, &tree), 4);
+
+        view.smart_right(&tree, 2);
+        assert_eq!(view.get_selected_node_id(), Some(1));
+        assert_eq!(visible_count(&mut view, &tree), 7);
+        view.navigate_down(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(4));
+        view.navigate_to_last_sibling(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(6));
+        view.navigate_to_parent(&tree, 1);
+        view.navigate_to_next_sibling(&tree, 1);
+        assert_eq!(view.get_selected_node_id(), Some(2));
+
+        let like: &dyn TreeLike = &tree;
+        for ancestor in like.ancestors(39) {
+            view.expand_node(ancestor);
+        }
+        view.navigate_to_node(&tree, 39);
+        assert_eq!(view.get_selected_node_id(), Some(39));
+        assert_eq!(visible_count(&mut view, &tree), 13);
+        // The root stays expanded
+        view.collapse_all(&tree);
+        assert_eq!(view.get_selected_node_id(), Some(3));
+        assert_eq!(visible_count(&mut view, &tree), 4);
+    }
+
+    #[test]
+    fn test_drawing_reads_only_shown_nodes_and_their_children() {
+        let tree = MockTree::default();
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        // The root and its children are shown; their children are read for
+        // previews, but nothing deeper
+        let loaded = tree.loaded.borrow();
+        assert!(loaded.contains(&12));
+        assert!(loaded.iter().all(|&id| id <= 12), "{:?}", loaded);
+    }
+
+    #[test]
+    fn test_align_selection_on_render() {
+        let tree = flat_tree(100);
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(40, 11)).unwrap();
+        view.navigate_by(50, &tree);
+        draw(&mut terminal, &mut view, &tree);
+
+        view.align_selection(Align::Center);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.list_state.offset(), 45);
+
+        // The request applies once; later renders keep the offset
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.list_state.offset(), 45);
+    }
+
+    #[test]
+    fn test_collapse_all_selects_visible_ancestor() {
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        view.expand_all(&tree, None);
+        assert_eq!(visible_count(&mut view, &tree), 13);
+
+        view.navigate_to_node(&tree, 7);
+        view.collapse_all(&tree);
+        assert_eq!(visible_count(&mut view, &tree), 4);
+        assert_eq!(view.get_selected_node_id(), Some(5));
+    }
+
+    #[test]
+    fn test_filter_shows_matches_with_their_ancestors() {
+        // root > (child0 > leaf 2-4, child1 > leaf 6-8, child2 > leaf 10-12)
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        view.expand_node(1);
+        let before = visible_count(&mut view, &tree);
+
+        view.set_filter(&tree, Some(&[7]));
+        assert!(view.is_filtered());
+        visible_count(&mut view, &tree);
+        let rows: Vec<usize> = view.visible_nodes.iter().map(|(id, _)| *id).collect();
+        assert_eq!(rows, [0, 5, 7]);
+
+        view.set_filter(&tree, None);
+        assert!(!view.is_filtered());
+        assert_eq!(visible_count(&mut view, &tree), before);
+        assert!(!view.expanded.contains(&5));
+    }
+
+    #[test]
+    fn test_expand_all_to_depth_collapses_deeper_nodes() {
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        view.expand_all(&tree, None);
+        view.navigate_to_node(&tree, 12);
+
+        view.expand_all(&tree, Some(1));
+        assert_eq!(visible_count(&mut view, &tree), 4);
+        assert_eq!(view.get_selected_node_id(), Some(9));
+
+        view.expand_all(&tree, Some(2));
+        assert_eq!(visible_count(&mut view, &tree), 13);
+        // The selection doesn't move back down on its own
+        assert_eq!(view.get_selected_node_id(), Some(9));
+    }
+
+    #[test]
+    fn test_siblings_deep_count_stops_past_the_limit() {
+        let tree = nested_tree(3);
+        let mut view = TreeView::new(tree.root_id());
+        view.navigate_to_node(&tree, 1);
+        assert_eq!(view.siblings_deep_count(&tree, usize::MAX), 12);
+        assert_eq!(view.siblings_deep_count(&tree, 12), 12);
+        assert_eq!(view.siblings_deep_count(&tree, 5), 6);
+    }
+
+    /// A root with one attribute whose value is longer than the view
+    fn long_value_tree() -> TreeVariant {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let mut node = TreeNode::new("key", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute(
+            "value",
+            "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRST",
+        );
+        tree.add_child_node(0, node);
+        TreeVariant::from(tree)
+    }
+
+    #[test]
+    fn test_horizontal_scroll_stops_at_the_widest_row() {
+        let tree = long_value_tree();
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(30, 5)).unwrap();
+        let row = |terminal: &Terminal<TestBackend>, y| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..30).map(|x| buffer.get(x, y).symbol()).collect()
+        };
+
+        view.scroll_horizontally(4);
+        draw(&mut terminal, &mut view, &tree);
+        // The value is cut where the scrolled view ends
+        assert_eq!(row(&terminal, 1), "   key: 0123456789abcdefghi...");
+
+        // "    key: " and the whole value end at column 65
+        view.scroll_horizontally(1000);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.horizontal_scroll, 65 - 27);
+        assert!(row(&terminal, 1).ends_with("QRST"));
+
+        view.scroll_horizontally(-1000);
+        draw(&mut terminal, &mut view, &tree);
+        assert_eq!(view.horizontal_scroll, 0);
+    }
+
+    #[test]
+    fn test_wrapped_selection_shows_the_whole_value() {
+        let tree = long_value_tree();
+        let mut view = TreeView::new(tree.root_id());
+        let mut terminal = Terminal::new(TestBackend::new(30, 5)).unwrap();
+        view.navigate_down(&tree);
+        assert!(view.toggle_wrap_selected());
+        draw(&mut terminal, &mut view, &tree);
+
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (1..4)
+            .map(|y| (0..30).map(|x| buffer.get(x, y).symbol()).collect())
+            .collect();
+        assert_eq!(rows[0], ">>     key: 0123456789abcdefgh");
+        assert_eq!(rows[1], "       ijklmnopqrstuvwxyzABCDE");
+        assert_eq!(rows[2].trim_end(), "       FGHIJKLMNOPQRST");
+    }
+
+    #[test]
+    fn test_changes_are_marked() {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let mut node = TreeNode::new("a", TreeNode::ATTRIBUTE_TYPE);
+        node.add_attribute("value", "2");
+        let changed = tree.add_child_node(0, node);
+        let added = tree.add_child_node(0, TreeNode::new("b", "array"));
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        view.set_changes(HashMap::from([
+            (changed, Change::Value("1".to_string())),
+            (added, Change::Type("object".to_string())),
+        ]));
+        let mut terminal = Terminal::new(TestBackend::new(30, 3)).unwrap();
+        draw(&mut terminal, &mut view, &tree);
+
+        let buffer = terminal.backend().buffer();
+        let row = |y| -> String { (0..30).map(|x| buffer.get(x, y).symbol()).collect() };
+        assert_eq!(row(1).trim_end(), "       ~ a: 1 → 2");
+        assert_eq!(row(2).trim_end(), "       ~ b [object → array]");
+        assert_eq!(buffer.get(9, 1).fg, Theme::dark().diff_changed);
+    }
+
+    #[test]
+    fn test_skip_columns_splits_wide_characters() {
+        let spans = vec![Span::raw("ab"), Span::raw("日本")];
+        let text = |spans: Vec<Span>| {
+            spans
+                .iter()
+                .map(|s| s.content.to_string())
+                .collect::<String>()
+        };
+        assert_eq!(text(skip_columns(spans.clone(), 1)), "b日本");
+        assert_eq!(text(skip_columns(spans.clone(), 3)), " 本");
+        assert_eq!(text(skip_columns(spans, 10)), "");
     }
 }
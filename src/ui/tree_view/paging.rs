@@ -0,0 +1,290 @@
+//! Pages of large arrays.
+//!
+//! Arrays with more elements than the page size show them below page rows
+//! such as `[0..999]`, which exist only in the view: their row in
+//! `visible_nodes` holds the array's ID, and `pages` tells them apart from
+//! the array's own row.
+
+use super::{ExpandedSet, TreeView, format_thousands};
+use crate::tree::TreeLike;
+use crate::ui::icons::Expander;
+use crate::ui::theme::Theme;
+use ratatui::{
+    style::{Modifier, Style},
+    text::Span,
+};
+
+/// A row grouping some elements of a large array, such as `[1000..1999]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Page {
+    pub(super) number: usize,
+    /// Elements on the page, fewer than the page size only on the last one
+    pub(super) len: usize,
+}
+
+impl ExpandedSet {
+    pub(super) fn contains_page(&self, array: usize, page: usize) -> bool {
+        self.pages.contains(&(array, page))
+    }
+
+    pub(super) fn insert_page(&mut self, array: usize, page: usize) {
+        if self.pages.insert((array, page)) {
+            self.version += 1;
+        }
+    }
+
+    pub(super) fn remove_page(&mut self, array: usize, page: usize) {
+        if self.pages.remove(&(array, page)) {
+            self.version += 1;
+        }
+    }
+}
+
+impl TreeView {
+    /// Show the elements of arrays longer than `size` in collapsible pages of
+    /// `size` elements, such as `[0..999]`; 0 shows all elements at once
+    pub fn set_array_page_size(&mut self, size: usize) {
+        if self.array_page_size != size {
+            self.array_page_size = size;
+            self.built_for = None;
+        }
+    }
+
+    /// Switch between pages and all elements at once for the selected array,
+    /// or the array of the selected page or element.
+    ///
+    /// # Returns
+    ///
+    /// Whether the array is now shown in pages, or `None` if there is no
+    /// array with more elements than fit on a page
+    pub fn toggle_paging(&mut self, tree: &dyn TreeLike) -> Option<bool> {
+        let index = self.list_state.selected()?;
+        let &(node_id, _) = self.visible_nodes.get(index)?;
+        let parent = tree
+            .get_parent(node_id)
+            .filter(|_| !self.pages.contains_key(&index));
+        let array = std::iter::once(node_id).chain(parent).find(|&id| {
+            let children = self.visible_children(tree, id);
+            self.pageable(tree, id, &children).is_some()
+        })?;
+
+        let paged = self.unpaged.remove(&array);
+        if !paged {
+            self.unpaged.insert(array);
+        }
+        self.built_for = None;
+        self.navigate_to_node(tree, node_id);
+        Some(paged)
+    }
+
+    /// The page size if the elements of `node_id` can be shown in pages: it
+    /// is an array, which includes the root of a top-level array, with more
+    /// elements than fit on one page
+    fn pageable(&self, tree: &dyn TreeLike, node_id: usize, children: &[usize]) -> Option<usize> {
+        let size = self.array_page_size;
+        if size == 0 || children.len() <= size {
+            return None;
+        }
+        let is_array = tree.get_node(node_id).is_some_and(|node| node.is_array());
+        is_array.then_some(size)
+    }
+
+    /// The page size if the elements of `node_id` are shown in pages
+    pub(super) fn page_size(
+        &self,
+        tree: &dyn TreeLike,
+        node_id: usize,
+        children: &[usize],
+    ) -> Option<usize> {
+        if self.unpaged.contains(&node_id) {
+            return None;
+        }
+        self.pageable(tree, node_id, children)
+    }
+
+    /// The pages that `node_id` and its ancestors are on, as array ID and
+    /// page number
+    pub(super) fn pages_on_path(&self, tree: &dyn TreeLike, node_id: usize) -> Vec<(usize, usize)> {
+        if self.array_page_size == 0 {
+            return Vec::new();
+        }
+        let chain: Vec<usize> = std::iter::once(node_id)
+            .chain(tree.ancestors(node_id))
+            .collect();
+        chain
+            .windows(2)
+            .filter_map(|pair| {
+                let (child, parent) = (pair[0], pair[1]);
+                let children = self.visible_children(tree, parent);
+                let size = self.page_size(tree, parent, &children)?;
+                let position = children.iter().position(|&id| id == child)?;
+                Some((parent, position / size))
+            })
+            .collect()
+    }
+
+    /// The spans of a page row: its range of indices, and how many elements
+    /// it holds while collapsed
+    pub(super) fn page_spans(
+        &self,
+        array_id: usize,
+        page: Page,
+        depth: usize,
+        theme: &Theme,
+        decorated: bool,
+    ) -> Vec<Span<'static>> {
+        let style = Style::default().fg(theme.virtual_node);
+        let expanded = self.expanded.contains_page(array_id, page.number);
+        let mut spans = Vec::new();
+        if decorated {
+            let expander = if expanded {
+                Expander::Expanded
+            } else {
+                Expander::Collapsed
+            };
+            spans.push(Span::raw("  ".repeat(depth)));
+            spans.push(Span::styled(self.icons.expander(expander, true), style));
+            spans.push(Span::raw(" "));
+        }
+        let start = page.number * self.array_page_size;
+        spans.push(Span::styled(
+            format!("[{}..{}]", start, start + page.len - 1),
+            style,
+        ));
+        if self.show_child_counts && !expanded {
+            spans.push(Span::styled(
+                format!(" ({})", format_thousands(page.len)),
+                Style::default()
+                    .fg(theme.node_type)
+                    .add_modifier(Modifier::DIM),
+            ));
+        }
+        spans
+    }
+
+    /// The array ID and page number of the row at `index`, if it is a page
+    pub(super) fn page_at(&self, index: usize) -> Option<(usize, usize)> {
+        let page = self.pages.get(&index)?;
+        Some((self.visible_nodes[index].0, page.number))
+    }
+
+    /// Whether the row at `index` has rows to show below it. A leaf with a
+    /// multi-line value shows its lines, and collapses from any of them.
+    pub(super) fn row_expandable(&self, tree: &dyn TreeLike, index: usize) -> bool {
+        self.pages.contains_key(&index)
+            || self.value_lines.contains_key(&index)
+            || self.visible_nodes.get(index).is_some_and(|&(node_id, _)| {
+                self.is_expandable(tree, node_id) || self.value_line_count(tree, node_id) > 1
+            })
+    }
+
+    pub(super) fn row_expanded(&self, index: usize) -> bool {
+        match self.page_at(index) {
+            Some((array, page)) => self.expanded.contains_page(array, page),
+            None => self
+                .visible_nodes
+                .get(index)
+                .is_some_and(|(node_id, _)| self.expanded.contains(node_id)),
+        }
+    }
+
+    pub(super) fn set_row_expanded(&mut self, index: usize, expanded: bool) {
+        match (self.page_at(index), self.visible_nodes.get(index)) {
+            (Some((array, page)), _) if expanded => self.expanded.insert_page(array, page),
+            (Some((array, page)), _) => self.expanded.remove_page(array, page),
+            (None, Some(&(node_id, _))) if expanded => self.expanded.insert(node_id),
+            (None, Some(&(node_id, _))) => self.expanded.remove(&node_id),
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Tree, TreeNode, TreeVariant};
+    use crate::ui::tree_view::tests::{labels, visible_count};
+
+    /// An object with an array `items` of `count` numbers
+    fn array_tree(count: usize) -> TreeVariant {
+        let mut tree = Tree::new(TreeNode::new("root", "object"));
+        let items = tree.add_child_node(0, TreeNode::new("items", "array"));
+        for i in 0..count {
+            let mut node = TreeNode::new(format!("[{i}]"), TreeNode::ATTRIBUTE_TYPE);
+            node.add_attribute("value", i.to_string());
+            tree.add_child_node(items, node);
+        }
+        TreeVariant::from(tree)
+    }
+
+    #[test]
+    fn test_large_arrays_are_paged() {
+        let tree = array_tree(25);
+        let mut view = TreeView::new(tree.root_id());
+        view.set_array_page_size(10);
+        view.expand_node(1);
+        assert_eq!(
+            labels(&mut view, &tree)[1..],
+            [
+                "▼ items [array]",
+                "▷ [0..9] (10)",
+                "▷ [10..19] (10)",
+                "▷ [20..24] (5)"
+            ]
+        );
+
+        // Pages expand like nodes, and count as the parents of their elements
+        view.navigate_to_last_line(&tree);
+        view.smart_right(&tree, 2);
+        assert_eq!(view.get_selected_node_id(), Some(22));
+        view.navigate_to_next_sibling(&tree, 10);
+        assert_eq!(view.get_selected_node_id(), Some(26));
+        view.navigate_to_parent(&tree, 1);
+        assert_eq!(
+            labels(&mut view, &tree)[view.position().unwrap().0 - 1],
+            "▽ [20..24]"
+        );
+        view.navigate_to_previous_sibling(&tree, 1);
+        assert_eq!(
+            labels(&mut view, &tree)[view.position().unwrap().0 - 1],
+            "▷ [10..19] (10)"
+        );
+
+        // Going to an element opens its page, and collapsing keeps it selected
+        view.navigate_to_node(&tree, 2);
+        assert_eq!(view.get_selected_node_id(), Some(2));
+        view.collapse_parent(&tree);
+        assert_eq!(
+            labels(&mut view, &tree)[view.position().unwrap().0 - 1],
+            "▷ [0..9] (10)"
+        );
+
+        // Paging can be turned off for one array
+        view.navigate_to_node(&tree, 5);
+        assert_eq!(view.toggle_paging(&tree), Some(false));
+        assert_eq!(view.get_selected_node_id(), Some(5));
+        assert_eq!(visible_count(&mut view, &tree), 27);
+        assert_eq!(view.toggle_paging(&tree), Some(true));
+        assert_eq!(view.get_selected_node_id(), Some(5));
+
+        view.navigate_to_first_line();
+        assert_eq!(view.toggle_paging(&tree), None);
+        view.set_array_page_size(0);
+        assert_eq!(visible_count(&mut view, &tree), 27);
+    }
+
+    #[test]
+    fn test_top_level_arrays_are_paged() {
+        let mut tree = Tree::new(TreeNode::new("root", "array"));
+        for i in 0..5 {
+            tree.add_child_node(0, TreeNode::new(format!("[{i}]"), "object"));
+        }
+        let tree = TreeVariant::from(tree);
+        let mut view = TreeView::new(tree.root_id());
+        view.set_array_page_size(2);
+        assert_eq!(
+            labels(&mut view, &tree)[1..],
+            ["▷ [0..1] (2)", "▷ [2..3] (2)", "▷ [4..4] (1)"]
+        );
+    }
+}
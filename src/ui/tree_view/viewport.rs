@@ -0,0 +1,82 @@
+//! Which rows of the tree fit in the viewport.
+
+use super::Align;
+
+/// First row to display so that the selected row stays inside a viewport of
+/// `height` rows with `scrolloff` rows of context around it, scrolling as
+/// little as possible from `offset`
+pub(super) fn viewport_offset(
+    offset: usize,
+    selected: Option<usize>,
+    height: usize,
+    len: usize,
+    scrolloff: usize,
+) -> usize {
+    let height = height.max(1);
+    let margin = scroll_margin(scrolloff, height);
+    let offset = match selected {
+        Some(selected) if selected < offset + margin => selected.saturating_sub(margin),
+        Some(selected) if selected + margin >= offset + height => {
+            // Don't scroll past the last row just to keep the margin
+            (selected + margin + 1 - height).min(len.saturating_sub(height).max(offset))
+        }
+        _ => offset,
+    };
+    offset.min(len.saturating_sub(1))
+}
+
+/// First row to display to put the selected row at the top, center or bottom
+pub(super) fn aligned_offset(
+    align: Align,
+    selected: usize,
+    height: usize,
+    scrolloff: usize,
+) -> usize {
+    let height = height.max(1);
+    let margin = scroll_margin(scrolloff, height);
+    match align {
+        Align::Top => selected.saturating_sub(margin),
+        Align::Center => selected.saturating_sub((height - 1) / 2),
+        Align::Bottom => (selected + margin + 1).saturating_sub(height),
+    }
+}
+
+/// The scroll margin, limited so it still leaves room for the selection
+pub(super) fn scroll_margin(scrolloff: usize, height: usize) -> usize {
+    scrolloff.min(height.saturating_sub(1) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_offset() {
+        assert_eq!(viewport_offset(0, Some(3), 10, 100, 0), 0);
+        assert_eq!(viewport_offset(0, Some(10), 10, 100, 0), 1);
+        assert_eq!(viewport_offset(20, Some(5), 10, 100, 0), 5);
+        assert_eq!(viewport_offset(50, None, 10, 20, 0), 19);
+        assert_eq!(viewport_offset(0, None, 10, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_viewport_offset_keeps_scrolloff_margin() {
+        // Moving down to row 7 of 10 starts scrolling with a margin of 3
+        assert_eq!(viewport_offset(0, Some(6), 10, 100, 3), 0);
+        assert_eq!(viewport_offset(0, Some(7), 10, 100, 3), 1);
+        // Moving up keeps three rows above the selection
+        assert_eq!(viewport_offset(20, Some(22), 10, 100, 3), 19);
+        // Near the end the margin gives way instead of showing empty rows
+        assert_eq!(viewport_offset(90, Some(99), 10, 100, 3), 90);
+        // A small viewport shrinks the margin
+        assert_eq!(viewport_offset(0, Some(2), 3, 100, 3), 1);
+    }
+
+    #[test]
+    fn test_aligned_offset() {
+        assert_eq!(aligned_offset(Align::Top, 50, 10, 3), 47);
+        assert_eq!(aligned_offset(Align::Center, 50, 10, 3), 46);
+        assert_eq!(aligned_offset(Align::Bottom, 50, 10, 3), 44);
+        assert_eq!(aligned_offset(Align::Center, 2, 10, 3), 0);
+    }
+}
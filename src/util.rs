@@ -0,0 +1,143 @@
+//! Natural ordering of labels and keys.
+//!
+//! Plain string order puts `item10` before `item2` and every uppercase letter
+//! before every lowercase one. Natural order compares runs of digits by their
+//! number, so `[2]` comes before `[10]`, and by default ignores case until
+//! the strings differ only in case. It doesn't depend on the locale, so the
+//! order is the same on every system.
+//!
+//! Sorting children, the attributes of XML and HTML elements and the
+//! attributes of LDIF entries all use it.
+
+use std::cmp::Ordering;
+
+/// Compare two strings with runs of digits compared by their number, and
+/// letters compared case-insensitively before falling back to case
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    compare(a, b, true)
+}
+
+/// Compare two strings like [`natural_cmp`], but with letters compared by
+/// case like plain string order, so `B` comes before `a`
+pub fn natural_cmp_case_sensitive(a: &str, b: &str) -> Ordering {
+    compare(a, b, false)
+}
+
+fn compare(a: &str, b: &str, ignore_case: bool) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    loop {
+        match (a_rest.chars().next(), b_rest.chars().next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (a_digits, a_after) = split_digits(a_rest);
+                let (b_digits, b_after) = split_digits(b_rest);
+                let a_number = a_digits.trim_start_matches('0');
+                let b_number = b_digits.trim_start_matches('0');
+                let ordering = a_number
+                    .len()
+                    .cmp(&b_number.len())
+                    .then_with(|| a_number.cmp(b_number));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                (a_rest, b_rest) = (a_after, b_after);
+            }
+            (Some(x), Some(y)) => {
+                let ordering = if ignore_case {
+                    x.to_lowercase().cmp(y.to_lowercase())
+                } else {
+                    x.cmp(&y)
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_rest = &a_rest[x.len_utf8()..];
+                b_rest = &b_rest[y.len_utf8()..];
+            }
+        }
+    }
+}
+
+/// The leading digits of `s` and the rest
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys mixing letters of both cases, digits with and without leading
+    /// zeros, punctuation and non-ASCII letters
+    fn keys() -> Vec<String> {
+        let mut keys = Vec::new();
+        for prefix in ["", "a", "A", "b", "item", "Item", "é", "x-", "[", "ß"] {
+            for number in ["", "0", "1", "01", "2", "9", "10", "010", "100"] {
+                for suffix in ["", "a", "B", "]", "1"] {
+                    keys.push(format!("{}{}{}", prefix, number, suffix));
+                }
+            }
+        }
+        keys
+    }
+
+    fn assert_total_order(cmp: fn(&str, &str) -> Ordering) {
+        let keys = keys();
+        let mut sorted = keys.clone();
+        sorted.sort_by(|a, b| cmp(a, b));
+        sorted.dedup();
+        for (i, a) in sorted.iter().enumerate() {
+            assert_eq!(cmp(a, a), Ordering::Equal);
+            for b in &sorted[i + 1..] {
+                // Every pair of the sorted keys compares in sorted order
+                // both ways, so the order is antisymmetric and transitive
+                assert_eq!(cmp(a, b), Ordering::Less, "{:?} < {:?}", a, b);
+                assert_eq!(cmp(b, a), Ordering::Greater, "{:?} > {:?}", b, a);
+            }
+        }
+    }
+
+    #[test]
+    fn test_natural_order() {
+        let mut labels = vec!["[10]", "[2]", "item10", "Item9", "[1]", "b", "a"];
+        labels.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(labels, ["[1]", "[2]", "[10]", "a", "b", "Item9", "item10"]);
+        assert_eq!(natural_cmp("a01", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("a1", "a1"), Ordering::Equal);
+        assert_eq!(natural_cmp("A", "a"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_case_sensitive_natural_order() {
+        let mut labels = vec!["b", "item10", "B", "Item9", "item2", "a"];
+        labels.sort_by(|a, b| natural_cmp_case_sensitive(a, b));
+        assert_eq!(labels, ["B", "Item9", "a", "b", "item2", "item10"]);
+    }
+
+    #[test]
+    fn test_natural_orders_are_total() {
+        assert_total_order(natural_cmp);
+        assert_total_order(natural_cmp_case_sensitive);
+    }
+
+    #[test]
+    fn test_numbers_compare_by_value() {
+        for (a, b) in [(1u64, 2), (9, 10), (99, 100), (7, 70), (123, 1230)] {
+            for key in ["x{}", "{}y", "a{}b"] {
+                let (a, b) = (
+                    key.replace("{}", &a.to_string()),
+                    key.replace("{}", &b.to_string()),
+                );
+                assert_eq!(natural_cmp(&a, &b), Ordering::Less, "{} < {}", a, b);
+            }
+        }
+        // Longer than any integer type
+        assert_eq!(
+            natural_cmp("n99999999999999999999999", "n100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Exit codes and `--error-format json` of the binary on bad input.
+
+use std::io::Write;
+use std::process::{Command, Output};
+use tempfile::NamedTempFile;
+
+fn xtv(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_xtv"))
+        .args(args)
+        .env_remove("XTV_CONFIG")
+        .env("XDG_CONFIG_HOME", "/nonexistent")
+        .output()
+        .expect("Failed to run xtv")
+}
+
+fn file_with(suffix: &str, content: &str) -> NamedTempFile {
+    let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file
+}
+
+fn path(file: &NamedTempFile) -> &str {
+    file.path().to_str().unwrap()
+}
+
+#[test]
+fn test_success_exits_with_zero() {
+    let file = file_with(".json", r#"{"a": 1}"#);
+    assert_eq!(xtv(&["--print", path(&file)]).status.code(), Some(0));
+}
+
+#[test]
+fn test_bench_prints_timings_and_exits() {
+    let file = file_with(".json", r#"{"a": [1, 2]}"#);
+    let output = xtv(&["--bench", path(&file)]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let bench: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(bench["file"], path(&file));
+    assert_eq!(bench["format"], "json");
+    assert_eq!(bench["nodes"], 4);
+    assert!(bench["timings_ms"]["parse"].is_number());
+}
+
+#[test]
+fn test_bench_of_stdin_streams_large_input() {
+    let ldif: String = (0..100)
+        .map(|i| format!("dn: cn=user{i},dc=example\ncn: user{i}\n\n"))
+        .collect();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_xtv"))
+        .arg("--bench")
+        .env_remove("XTV_CONFIG")
+        .env("XDG_CONFIG_HOME", "/nonexistent")
+        .env("XTV_STREAMING_THRESHOLD", "1KB")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(ldif.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+
+    let bench: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(bench["file"], "-");
+    assert_eq!(bench["format"], "ldif");
+    assert_eq!(bench["streaming"], true);
+    assert_eq!(bench["bytes"], ldif.len());
+}
+
+#[test]
+fn test_output_writes_the_selection() {
+    let file = file_with(".json", r#"{"a": [], "b": {"c": "1"}}"#);
+    let output = xtv(&["--output", "-", "--select", ".b", path(&file)]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\n  \"c\": \"1\"\n}\n"
+    );
+
+    let file = file_with(".json", "[]");
+    let output = xtv(&["--output", "-", "--output-format", "yaml", path(&file)]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "[]\n");
+
+    let output = xtv(&["--output", "-", "--select", ".nope", path(&file)]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_missing_file_is_an_io_error() {
+    let output = xtv(&["--print", "/nonexistent/data.json"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("Error: /nonexistent/data.json: IO error"));
+}
+
+#[test]
+fn test_malformed_file_is_a_parse_error() {
+    let file = file_with(".json", "{\"a\": 1,\n  \"b\": }\n");
+    let output = xtv(&["--print", "--error-format", "json", path(&file)]);
+    assert_eq!(output.status.code(), Some(4));
+
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["kind"], "parse");
+    assert_eq!(error["file"], path(&file));
+    assert_eq!(error["line"], 2);
+    assert_eq!(error["column"], 8);
+    assert!(
+        error["message"]
+            .as_str()
+            .unwrap()
+            .contains("expected value")
+    );
+}
+
+#[test]
+fn test_unknown_format_is_unsupported() {
+    let file = file_with(".data", "plain text");
+    assert_eq!(xtv(&["--print", path(&file)]).status.code(), Some(5));
+    assert_eq!(
+        xtv(&["--print", "--format", "csv", path(&file)])
+            .status
+            .code(),
+        Some(5)
+    );
+}
+
+#[test]
+fn test_invalid_config_is_a_config_error() {
+    let config = file_with(".toml", "[navigation]\npage_scroll_lines = 0\n");
+    let file = file_with(".json", "{}");
+    let output = xtv(&[
+        "--config",
+        path(&config),
+        "--error-format",
+        "json",
+        "--print",
+        path(&file),
+    ]);
+    assert_eq!(output.status.code(), Some(6));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["kind"], "config");
+    assert!(error.get("file").is_none());
+}
+
+#[test]
+fn test_usage_errors_exit_with_two() {
+    assert_eq!(xtv(&["--color", "sometimes"]).status.code(), Some(2));
+}
+
+#[test]
+fn test_export_html_writes_a_page() {
+    let file = file_with(".json", r#"{"a": {"b": 1}}"#);
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("view.html");
+    let output = xtv(&[
+        "--export-html",
+        out.to_str().unwrap(),
+        "--select",
+        ".a.b",
+        path(&file),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let html = std::fs::read_to_string(&out).unwrap();
+    assert!(html.contains("id=\"selected\""));
+
+    let output = xtv(&[
+        "--export-html",
+        out.to_str().unwrap(),
+        "--select",
+        ".x",
+        path(&file),
+    ]);
+    assert_ne!(output.status.code(), Some(0));
+}
@@ -1,14 +1,15 @@
 use std::fs;
 use std::path::PathBuf;
 use xtv::parser;
-use xtv::parser::Parser;
+use xtv::parser::{Parser, ParserOptions};
 
 #[test]
 fn test_parse_sample_json() {
     let path = PathBuf::from("examples/sample.json");
     let content = fs::read_to_string(&path).expect("Failed to read sample.json");
 
-    let parser = parser::detect_parser(&path).expect("Failed to detect parser");
+    let parser =
+        parser::detect_parser(&path, &ParserOptions::default()).expect("Failed to detect parser");
     let tree = parser.parse(&content).expect("Failed to parse JSON");
 
     // Verify tree structure
@@ -23,7 +24,8 @@ fn test_parse_sample_xml() {
     let path = PathBuf::from("examples/sample.xml");
     let content = fs::read_to_string(&path).expect("Failed to read sample.xml");
 
-    let parser = parser::detect_parser(&path).expect("Failed to detect parser");
+    let parser =
+        parser::detect_parser(&path, &ParserOptions::default()).expect("Failed to detect parser");
     let tree = parser.parse(&content).expect("Failed to parse XML");
 
     // Verify tree structure
@@ -38,7 +40,8 @@ fn test_parse_sample_ldif() {
     let path = PathBuf::from("examples/sample.ldif");
     let content = fs::read_to_string(&path).expect("Failed to read sample.ldif");
 
-    let parser = parser::detect_parser(&path).expect("Failed to detect parser");
+    let parser =
+        parser::detect_parser(&path, &ParserOptions::default()).expect("Failed to detect parser");
     let tree = parser.parse(&content).expect("Failed to parse LDIF");
 
     // Verify tree structure
@@ -56,7 +59,8 @@ fn test_parse_sample_complex_ldif() {
     let path = PathBuf::from("examples/sample-complex.ldif");
     let content = fs::read_to_string(&path).expect("Failed to read sample-complex.ldif");
 
-    let parser = parser::detect_parser(&path).expect("Failed to detect parser");
+    let parser =
+        parser::detect_parser(&path, &ParserOptions::default()).expect("Failed to detect parser");
     let tree = parser.parse(&content).expect("Failed to parse LDIF");
 
     assert!(tree.node_count() > 0);
@@ -65,7 +69,7 @@ fn test_parse_sample_complex_ldif() {
 #[test]
 fn test_ldif_entry_structure() {
     let ldif = "version: 1\n\ndn: cn=Test,dc=example,dc=com\ncn: Test\nsn: User\n";
-    let parser = xtv::parser::ldif::LdifParser;
+    let parser = xtv::parser::ldif::LdifParser::default();
     let tree = parser.parse(ldif).unwrap();
 
     let root = tree.get_node(0).unwrap();
@@ -92,7 +96,7 @@ objectClass: person
 mail: first@example.com
 mail: second@example.com
 "#;
-    let parser = xtv::parser::ldif::LdifParser;
+    let parser = xtv::parser::ldif::LdifParser::default();
     let tree = parser.parse(ldif).unwrap();
 
     let root = tree.get_node(0).unwrap();
@@ -119,7 +123,8 @@ fn test_parse_sample_yaml() {
     let path = PathBuf::from("examples/sample.yaml");
     let content = fs::read_to_string(&path).expect("Failed to read sample.yaml");
 
-    let parser = parser::detect_parser(&path).expect("Failed to detect parser");
+    let parser =
+        parser::detect_parser(&path, &ParserOptions::default()).expect("Failed to detect parser");
     let tree = parser.parse(&content).expect("Failed to parse YAML");
 
     // Verify tree structure
@@ -170,7 +175,8 @@ fn test_parse_sample_jsonlines() {
     let path = PathBuf::from("examples/sample.jsonl");
     let content = fs::read_to_string(&path).expect("Failed to read sample.jsonl");
 
-    let parser = parser::detect_parser(&path).expect("Failed to detect parser");
+    let parser =
+        parser::detect_parser(&path, &ParserOptions::default()).expect("Failed to detect parser");
     let tree = parser.parse(&content).expect("Failed to parse JSON Lines");
 
     // Verify tree structure
@@ -188,7 +194,7 @@ fn test_jsonlines_line_numbering() {
     let jsonl = r#"{"id": 1}
 {"id": 2}
 {"id": 3}"#;
-    let parser = xtv::parser::jsonlines::JsonLinesParser;
+    let parser = xtv::parser::jsonlines::JsonLinesParser::default();
     let tree = parser.parse(jsonl).unwrap();
 
     let root = tree.get_node(0).unwrap();
@@ -208,7 +214,93 @@ fn test_jsonlines_line_numbering() {
 #[test]
 fn test_unsupported_format() {
     let path = PathBuf::from("test.unsupported");
-    let result = parser::detect_parser(&path);
+    let result = parser::detect_parser(&path, &ParserOptions::default());
 
     assert!(result.is_err());
 }
+
+#[test]
+fn test_ldif_with_other_extension_streams() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bigdump.txt");
+    fs::write(&path, "dn: cn=Test,dc=example,dc=com\ncn: Test\n").unwrap();
+
+    // Detected from content, and from an explicit format
+    for format in [None, Some("ldif")] {
+        let parser =
+            parser::detect_parser_for_file(&path, format, &ParserOptions::default()).unwrap();
+        assert_eq!(parser.format_name(), "ldif");
+        assert!(parser.supports_streaming());
+
+        let tree = parser.build_index(&path).unwrap();
+        let entry = tree.get_children(tree.root_id())[0];
+        assert_eq!(
+            tree.get_node(entry).unwrap().label,
+            "cn=Test,dc=example,dc=com"
+        );
+    }
+}
+
+#[test]
+fn test_non_streaming_format_refuses_index() {
+    let parser = parser::get_parser_from_format("json", &ParserOptions::default()).unwrap();
+    assert!(!parser.supports_streaming());
+    assert!(parser.build_index(&PathBuf::from("data.json")).is_err());
+}
+
+#[test]
+fn test_deeply_nested_json_parses_and_expands() {
+    const DEPTH: usize = 200_000;
+    let json = format!("{}{}", "[".repeat(DEPTH), "]".repeat(DEPTH));
+
+    let parser =
+        parser::detect_parser(&PathBuf::from("deep.json"), &ParserOptions::default()).unwrap();
+    let tree = parser
+        .parse(&json)
+        .expect("Failed to parse deeply nested JSON");
+    // The outer array's items go directly below the root
+    assert_eq!(tree.node_count(), DEPTH);
+
+    let tree = xtv::tree::TreeVariant::from(tree);
+    let mut view = xtv::ui::tree_view::TreeView::new(tree.root_id());
+    view.navigate_down(&tree);
+    view.expand_all_siblings_deep(&tree);
+    view.navigate_to_last_line(&tree);
+
+    let deepest = view.get_selected_node_id().unwrap();
+    assert!(tree.get_children(deepest).is_empty());
+    assert_eq!(tree.get_node(deepest).unwrap().label, "[0]");
+
+    let top = tree.get_children(tree.root_id())[0];
+    view.navigate_to_node(&tree, top);
+    view.collapse_all_siblings_deep(&tree);
+    view.navigate_to_last_line(&tree);
+    assert_eq!(view.get_selected_node_id(), Some(top));
+}
+
+#[test]
+fn test_export_sample_yaml_as_html() {
+    let path = PathBuf::from("examples/sample.yaml");
+    let content = fs::read_to_string(&path).expect("Failed to read sample.yaml");
+    let tree = xtv::parser::yaml::YamlParser.parse(&content).unwrap();
+    let mut config = xtv::config::Config::default();
+    config.ui.default_expanded_depth = 1;
+    let app = xtv::ui::app::App::new(xtv::tree::TreeVariant::from(tree), config)
+        .with_selection(".database.host");
+    let (html, truncated) = app.view_as_html();
+
+    assert!(!truncated);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(!html.contains("<script src") && !html.contains("<link"));
+    assert_eq!(
+        html.matches("<details").count(),
+        html.matches("</details>").count()
+    );
+    // The path to the selection is open and highlighted, other mappings closed
+    assert!(html.contains("<p class=\"breadcrumb\">root &gt; database &gt; host</p>"));
+    assert!(html.contains("<details open><summary class=\"path\">"));
+    assert!(html.contains("<div class=\"leaf selected\" id=\"selected\">"));
+    assert!(html.contains("<details><summary><span style=\"color: #00cdcd;\">application</span>"));
+    // Collapsed nodes are still on the page
+    assert!(html.contains("192.168.1.10"));
+}